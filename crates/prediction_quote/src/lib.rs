@@ -0,0 +1,336 @@
+//! CPMM quote and fee math of the prediction program, free of Anchor and
+//! Solana dependencies. The program calls these same functions, so Rust
+//! clients, keepers and wasm builds get byte-identical quotes and rounding.
+//!
+//! `no_std` by default; the `std` feature adds `std::error::Error` for
+//! `QuoteError`.
+#![no_std]
+
+#[cfg(feature = "std")]
+extern crate std;
+
+pub const BPS_DENOM: u64 = 10_000;
+/// Hard ceiling on the total fee a trade can pay.
+pub const MAX_FEE_BPS: u64 = 300;
+pub const PRICE_SCALE: u64 = 1_000_000; // prices / probabilities are 1e6 fixed-point
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QuoteError {
+  InvalidLiquidity,
+  InvalidOutcome,
+  FeeCapExceeded,
+  MathOverflow,
+}
+
+impl core::fmt::Display for QuoteError {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    f.write_str(match self {
+      QuoteError::InvalidLiquidity => "invalid liquidity",
+      QuoteError::InvalidOutcome => "invalid outcome",
+      QuoteError::FeeCapExceeded => "fee exceeds the protocol cap",
+      QuoteError::MathOverflow => "math overflow",
+    })
+  }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for QuoteError {}
+
+pub type QuoteResult<T> = Result<T, QuoteError>;
+
+// ----------------------------
+// CPMM Math (swap-style)
+// ----------------------------
+// Each returns (new yes_pool, new no_pool, amount out).
+
+/// Buying YES: add `net_in` to the NO reserve, take YES out.
+pub fn cpmm_buy_yes(yes_pool: u64, no_pool: u64, net_in: u64) -> QuoteResult<(u64, u64, u64)> {
+  let (yes, no, out) = swap_in(yes_pool, no_pool, net_in)?;
+  Ok((yes, no, out))
+}
+
+/// Buying NO: add `net_in` to the YES reserve, take NO out.
+pub fn cpmm_buy_no(yes_pool: u64, no_pool: u64, net_in: u64) -> QuoteResult<(u64, u64, u64)> {
+  let (no, yes, out) = swap_in(no_pool, yes_pool, net_in)?;
+  Ok((yes, no, out))
+}
+
+/// Selling YES: add `shares_in` to the YES reserve, take NO out.
+pub fn cpmm_sell_yes(yes_pool: u64, no_pool: u64, shares_in: u64) -> QuoteResult<(u64, u64, u64)> {
+  let (no, yes, out) = swap_in(no_pool, yes_pool, shares_in)?;
+  Ok((yes, no, out))
+}
+
+/// Selling NO: add `shares_in` to the NO reserve, take YES out.
+pub fn cpmm_sell_no(yes_pool: u64, no_pool: u64, shares_in: u64) -> QuoteResult<(u64, u64, u64)> {
+  let (yes, no, out) = swap_in(yes_pool, no_pool, shares_in)?;
+  Ok((yes, no, out))
+}
+
+/// Add `amount_in` to reserve `y` and take `x` out at constant `x * y`,
+/// rounding the new `x` up so the amount out rounds down, in the pool's
+/// favour. Returns (new x, new y, x out).
+fn swap_in(x: u64, y: u64, amount_in: u64) -> QuoteResult<(u64, u64, u64)> {
+  if x == 0 || y == 0 {
+    return Err(QuoteError::InvalidLiquidity);
+  }
+  let (x, y) = (x as u128, y as u128);
+  let k = x.checked_mul(y).ok_or(QuoteError::MathOverflow)?;
+  let y_new = y
+    .checked_add(amount_in as u128)
+    .ok_or(QuoteError::MathOverflow)?;
+  let x_new = k.div_ceil(y_new);
+  let out = x.checked_sub(x_new).ok_or(QuoteError::MathOverflow)?;
+  Ok((to_u64(x_new)?, to_u64(y_new)?, to_u64(out)?))
+}
+
+// ----------------------------
+// Fees
+// ----------------------------
+
+/// `amount * fee_bps`, refusing any rate above `MAX_FEE_BPS`.
+pub fn fee_for(amount: u64, fee_bps: u64) -> QuoteResult<u64> {
+  if fee_bps > MAX_FEE_BPS {
+    return Err(QuoteError::FeeCapExceeded);
+  }
+  amount
+    .checked_mul(fee_bps)
+    .ok_or(QuoteError::MathOverflow)?
+    .checked_div(BPS_DENOM)
+    .ok_or(QuoteError::MathOverflow)
+}
+
+/// Fee taken from trade input: (net, fee).
+pub fn apply_fee_in(gross_in: u64, fee_bps: u64) -> QuoteResult<(u64, u64)> {
+  let fee = fee_for(gross_in, fee_bps)?;
+  let net = gross_in.checked_sub(fee).ok_or(QuoteError::MathOverflow)?;
+  Ok((net, fee))
+}
+
+/// Fee taken from trade output: (net, fee).
+pub fn apply_fee_out(gross_out: u64, fee_bps: u64) -> QuoteResult<(u64, u64)> {
+  apply_fee_in(gross_out, fee_bps)
+}
+
+// ----------------------------
+// Pricing (1e6 fixed-point)
+// ----------------------------
+
+/// `a * b / c` in u128, floored.
+pub fn mul_div(a: u64, b: u64, c: u64) -> QuoteResult<u64> {
+  let out = (a as u128)
+    .checked_mul(b as u128)
+    .ok_or(QuoteError::MathOverflow)?
+    .checked_div(c as u128)
+    .ok_or(QuoteError::MathOverflow)?;
+  to_u64(out)
+}
+
+/// YES is priced by the opposite reserve: p_yes = no_pool / (yes_pool + no_pool).
+pub fn implied_probability(yes_pool: u64, no_pool: u64, outcome_index: u8) -> QuoteResult<u64> {
+  let total = (yes_pool as u128)
+    .checked_add(no_pool as u128)
+    .ok_or(QuoteError::MathOverflow)?;
+  if total == 0 {
+    return Err(QuoteError::InvalidLiquidity);
+  }
+  let numerator = match outcome_index {
+    0 => no_pool,
+    1 => yes_pool,
+    _ => return Err(QuoteError::InvalidOutcome),
+  };
+  let p = (numerator as u128)
+    .checked_mul(PRICE_SCALE as u128)
+    .ok_or(QuoteError::MathOverflow)?
+    .checked_div(total)
+    .ok_or(QuoteError::MathOverflow)?;
+  to_u64(p)
+}
+
+// ----------------------------
+// Trade quotes
+// ----------------------------
+
+/// Result of buying outcome shares with collateral.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BuyQuote {
+  pub shares_out: u64,
+  pub fee: u64,
+  pub yes_pool: u64,
+  pub no_pool: u64,
+}
+
+/// Buy `outcome_index` (0 = YES, 1 = NO) with `collateral_in`, paying
+/// `fee_bps` on the input, exactly as `buy_shares` does.
+pub fn quote_buy(
+  yes_pool: u64,
+  no_pool: u64,
+  outcome_index: u8,
+  collateral_in: u64,
+  fee_bps: u64,
+) -> QuoteResult<BuyQuote> {
+  let (net_in, fee) = apply_fee_in(collateral_in, fee_bps)?;
+  let (yes_pool, no_pool, shares_out) = match outcome_index {
+    0 => cpmm_buy_yes(yes_pool, no_pool, net_in)?,
+    1 => cpmm_buy_no(yes_pool, no_pool, net_in)?,
+    _ => return Err(QuoteError::InvalidOutcome),
+  };
+  Ok(BuyQuote {
+    shares_out,
+    fee,
+    yes_pool,
+    no_pool,
+  })
+}
+
+/// Result of selling outcome shares for collateral.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SellQuote {
+  pub collateral_out: u64,
+  pub fee: u64,
+  pub yes_pool: u64,
+  pub no_pool: u64,
+}
+
+/// Sell `shares_in` of `outcome_index`, paying `fee_bps` on the output,
+/// exactly as `sell_shares` does.
+pub fn quote_sell(
+  yes_pool: u64,
+  no_pool: u64,
+  outcome_index: u8,
+  shares_in: u64,
+  fee_bps: u64,
+) -> QuoteResult<SellQuote> {
+  let (yes_pool, no_pool, gross_out) = match outcome_index {
+    0 => cpmm_sell_yes(yes_pool, no_pool, shares_in)?,
+    1 => cpmm_sell_no(yes_pool, no_pool, shares_in)?,
+    _ => return Err(QuoteError::InvalidOutcome),
+  };
+  let (collateral_out, fee) = apply_fee_out(gross_out, fee_bps)?;
+  Ok(SellQuote {
+    collateral_out,
+    fee,
+    yes_pool,
+    no_pool,
+  })
+}
+
+fn to_u64(v: u128) -> QuoteResult<u64> {
+  v.try_into().map_err(|_| QuoteError::MathOverflow)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const POOLS: [(u64, u64); 4] = [
+    (1_000_000_000, 1_000_000_000),
+    (250_000_000, 4_000_000_000),
+    (7, 13),
+    (u64::MAX / 4, u64::MAX / 4),
+  ];
+  const AMOUNTS: [u64; 5] = [1, 3, 199, 100_000_000, 1_000_000_000_000];
+
+  #[test]
+  fn buy_then_sell_never_returns_more_than_went_in() {
+    for (yes, no) in POOLS {
+      for amount in AMOUNTS {
+        let (y, n, shares) = cpmm_buy_yes(yes, no, amount).unwrap();
+        let (y, n, out) = cpmm_sell_yes(y, n, shares).unwrap();
+        assert!(out <= amount, "YES {yes}/{no} in {amount} out {out}");
+        assert!((y as u128) * (n as u128) >= (yes as u128) * (no as u128));
+
+        let (y, n, shares) = cpmm_buy_no(yes, no, amount).unwrap();
+        let (y, n, out) = cpmm_sell_no(y, n, shares).unwrap();
+        assert!(out <= amount, "NO {yes}/{no} in {amount} out {out}");
+        assert!((y as u128) * (n as u128) >= (yes as u128) * (no as u128));
+      }
+    }
+  }
+
+  #[test]
+  fn swaps_round_the_amount_out_down() {
+    // 1000 * 1000 / 1100 = 909.09..: the pool keeps 910, 90 YES go out
+    assert_eq!(cpmm_buy_yes(1_000, 1_000, 100).unwrap(), (910, 1_100, 90));
+    assert_eq!(cpmm_buy_no(1_000, 1_000, 100).unwrap(), (1_100, 910, 90));
+    assert_eq!(cpmm_sell_yes(910, 1_100, 90).unwrap(), (1_000, 1_001, 99));
+    assert_eq!(cpmm_sell_no(1_100, 910, 90).unwrap(), (1_001, 1_000, 99));
+  }
+
+  #[test]
+  fn swaps_reject_empty_pools() {
+    assert_eq!(cpmm_buy_yes(0, 1_000, 1), Err(QuoteError::InvalidLiquidity));
+    assert_eq!(cpmm_sell_no(1_000, 0, 1), Err(QuoteError::InvalidLiquidity));
+  }
+
+  #[test]
+  fn fees_round_down_and_respect_the_cap() {
+    assert_eq!(fee_for(199, 50), Ok(0));
+    assert_eq!(fee_for(200, 50), Ok(1));
+    assert_eq!(fee_for(1_000_000, MAX_FEE_BPS), Ok(30_000));
+    assert_eq!(fee_for(1_000_000, MAX_FEE_BPS + 1), Err(QuoteError::FeeCapExceeded));
+    assert_eq!(fee_for(u64::MAX, 50), Err(QuoteError::MathOverflow));
+
+    // The trader keeps the rounding remainder, on input and output alike
+    assert_eq!(apply_fee_in(10_199, 50), Ok((10_149, 50)));
+    assert_eq!(apply_fee_out(10_199, 50), Ok((10_149, 50)));
+    for gross in AMOUNTS {
+      let (net, fee) = apply_fee_in(gross, MAX_FEE_BPS).unwrap();
+      assert_eq!(net + fee, gross);
+      assert!(fee * BPS_DENOM <= gross * MAX_FEE_BPS);
+    }
+  }
+
+  #[test]
+  fn mul_div_holds_at_u64_bounds() {
+    assert_eq!(mul_div(10, 1, 3), Ok(3));
+    assert_eq!(mul_div(u64::MAX, u64::MAX, u64::MAX), Ok(u64::MAX));
+    assert_eq!(mul_div(u64::MAX, u64::MAX - 1, u64::MAX), Ok(u64::MAX - 1));
+    assert_eq!(mul_div(u64::MAX, PRICE_SCALE, PRICE_SCALE), Ok(u64::MAX));
+    assert_eq!(mul_div(u64::MAX, 2, 1), Err(QuoteError::MathOverflow));
+    assert_eq!(mul_div(1, 1, 0), Err(QuoteError::MathOverflow));
+    assert_eq!(mul_div(0, 0, 0), Err(QuoteError::MathOverflow));
+  }
+
+  // `buy_shares` takes the fee off what the vault received, then swaps the
+  // rest; `sell_shares` swaps, then takes the fee off the proceeds
+  #[test]
+  fn quotes_match_the_instruction_steps() {
+    for (yes, no) in POOLS {
+      for amount in AMOUNTS {
+        let (net_in, fee) = apply_fee_in(amount, 50).unwrap();
+        let (y, n, shares_out) = cpmm_buy_yes(yes, no, net_in).unwrap();
+        let buy = quote_buy(yes, no, 0, amount, 50).unwrap();
+        assert_eq!(buy, BuyQuote { shares_out, fee, yes_pool: y, no_pool: n });
+
+        let (y, n, gross_out) = cpmm_sell_no(yes, no, amount).unwrap();
+        let (collateral_out, fee) = apply_fee_out(gross_out, 50).unwrap();
+        let sell = quote_sell(yes, no, 1, amount, 50).unwrap();
+        assert_eq!(sell, SellQuote { collateral_out, fee, yes_pool: y, no_pool: n });
+      }
+    }
+
+    // 100 collateral into a 1000/1000 pool at the default 50 bps
+    let buy = quote_buy(1_000_000_000, 1_000_000_000, 0, 100_000_000, 50).unwrap();
+    assert_eq!(
+      buy,
+      BuyQuote {
+        shares_out: 90_495_679,
+        fee: 500_000,
+        yes_pool: 909_504_321,
+        no_pool: 1_099_500_000,
+      }
+    );
+    let sell = quote_sell(buy.yes_pool, buy.no_pool, 0, buy.shares_out, 50).unwrap();
+    assert_eq!(
+      sell,
+      SellQuote {
+        collateral_out: 99_002_500,
+        fee: 497_499,
+        yes_pool: 1_000_000_000,
+        no_pool: 1_000_000_001,
+      }
+    );
+    assert_eq!(quote_buy(1_000, 1_000, 2, 100, 50), Err(QuoteError::InvalidOutcome));
+  }
+}
@@ -1,5 +1,22 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Mint, Token, TokenAccount, Transfer};
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::program::invoke_signed;
+use anchor_lang::system_program;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token::spl_token::native_mint;
+use anchor_spl::token_2022::spl_token_2022::extension::{
+  BaseStateWithExtensions, ExtensionType, StateWithExtensions,
+};
+use anchor_spl::token_2022::spl_token_2022::state::Mint as MintState;
+use anchor_spl::token_interface::{
+  self, Burn, CloseAccount, Mint, MintTo, SyncNative, TokenAccount, TokenInterface,
+  TransferChecked,
+};
+use bytemuck::Zeroable;
+pub use prediction_quote as quote;
+use prediction_quote::QuoteError;
+use solana_instructions_sysvar as ix_sysvar;
+use solana_sha256_hasher::{hash, hashv};
 
 declare_id!("7zGEj8SHZ6bDzfFJwfJxSZWvyMEoXtX5nTf6Wk4vFzj5"); // TODO: replace after deploy
 
@@ -7,772 +24,17085 @@ declare_id!("7zGEj8SHZ6bDzfFJwfJxSZWvyMEoXtX5nTf6Wk4vFzj5"); // TODO: replace af
 // Config
 // ----------------------------
 pub const FEE_BPS: u64 = 50; // 0.50% fee
+/// Hard ceiling on the total fee a trade can pay, creator, protocol and any
+/// other share included. Not configurable.
+pub const MAX_FEE_BPS: u64 = 300;
+const _: () = assert!(FEE_BPS <= MAX_FEE_BPS);
 pub const BPS_DENOM: u64 = 10_000;
+pub const MAX_APPROVED_MINTS: usize = 16;
+pub const MAX_METADATA_URI_LEN: usize = 96; // e.g. ipfs://<cid> or ar://<tx id>
+// `MarketV3::layout_version` written by this build
+pub const MARKET_LAYOUT_VERSION: u8 = 1;
+pub const PRICE_SCALE: u64 = 1_000_000; // prices / probabilities are 1e6 fixed-point
+const _: () = assert!(
+  quote::BPS_DENOM == BPS_DENOM && quote::MAX_FEE_BPS == MAX_FEE_BPS && quote::PRICE_SCALE == PRICE_SCALE
+);
+pub const REWARD_ACC_SCALE: u128 = 1_000_000_000_000; // LP reward-per-share precision
+pub const REGISTRY_PAGE_SIZE: u64 = 32; // markets per registry page
+pub const MAX_PORTFOLIO_MARKETS: usize = 64;
+pub const CLAIM_ALL_GROUP_LEN: usize = 6; // accounts per market in `claim_all`
+pub const PAYOUT_BATCH_GROUP_LEN: usize = 3; // accounts per position in `distribute_payouts_batch`
+pub const CLAIM_GROUP_LEN: usize = 4; // accounts per member in `claim_group`
+pub const PYTH_RECEIVER_PROGRAM_ID: Pubkey = pubkey!("rec5EKMGg6MxZYaMdyBfgwp4d5rB9T1VQH5pJv5LtFJ");
+pub const CHAINLINK_STORE_PROGRAM_ID: Pubkey = pubkey!("HEvSKofvBgfaexv23kMabbYqxasxU3mQ4ibBMEmJWHny");
+pub const MAX_TWAP_SLICES: u16 = 100;
+pub const MAX_BOOK_ORDERS: usize = 32; // per side
+pub const MAX_GROUP_MEMBERS: usize = 16;
+pub const MAX_OUTCOME_LABEL_LEN: usize = 32; // bytes, e.g. "Candidate A" or "Other"
+pub const UNLISTED_OUTCOME: u8 = u8::MAX; // `resolve_group` winner matching no listed member
+pub const GROUP_PAYOUT_WINNER_TAKE_ALL: u8 = 0; // one member resolves YES
+pub const GROUP_PAYOUT_PROPORTIONAL: u8 = 1; // members split a payout distribution
+pub const GROUP_PAYOUT_RANKED: u8 = 2; // finishing places pay `rank_weights`
+pub const MAX_PARLAY_LEGS: usize = 8;
+pub const PARLAY_LEG_GROUP_LEN: usize = 2; // accounts per leg in `place_parlay`
+pub const PARLAY_HOUSE_EDGE_BPS: u64 = 500; // taken off every parlay multiplier
+pub const MAX_PARLAY_LEG_DEVIATION_BPS: u64 = 500; // leg spot price vs its candle reference
+pub const MAX_MARKET_MAKERS: usize = 8;
+pub const MAX_TEMPLATE_PARAMS_LEN: usize = 128;
+pub const POINTS_EPOCH_SECS: i64 = 7 * 24 * 60 * 60;
+pub const MAX_POINTS_CHECKPOINTS: usize = 12; // closed epochs kept per user
+pub const MAX_STATS_LOTS: usize = 16; // open markets tracked per user
+pub const STATS_VOLUME_HOURS: usize = 24; // rolling window of hourly volume
+pub const MAX_PRICE_CANDLES: usize = 64; // ring buffer per market
+pub const MAX_TAPE_TRADES: usize = 32; // ring buffer per market
+pub const TAPE_BUY: u8 = 0;
+pub const TAPE_SELL: u8 = 1;
+pub const PRICE_FEED_VERSION: u8 = 1;
+pub const MAX_PERMIT_SECS: i64 = 5 * 60; // longest a trade permit may stay valid
+pub const MAX_SESSION_SECS: i64 = 7 * 24 * 60 * 60;
+pub const MAX_END_TIME_EXTENSION_SECS: i64 = 90 * 24 * 60 * 60; // total, via `update_end_time`
+pub const MIN_CLAIM_WINDOW_SECS: i64 = 7 * 24 * 60 * 60; // shortest nonzero `claim_window`
+pub const MIN_CANDLE_INTERVAL_SECS: i64 = 60;
+pub const MAX_CANDLE_INTERVAL_SECS: i64 = 7 * 24 * 60 * 60;
+pub const MAX_COMPETITION_MARKETS: usize = 8;
+pub const MAX_COMPETITION_WINNERS: usize = 10;
+pub const COMPETITION_SUBMIT_SECS: i64 = 24 * 60 * 60; // after `end`
+pub const ORACLE_MAX_STALENESS_SECS: i64 = 60;
+pub const ORACLE_MAX_CONF_BPS: u64 = 200; // confidence interval / price
+pub const ORACLE_VOID_TIMEOUT_SECS: i64 = 3 * 24 * 60 * 60; // after end_time
+pub const OUTCOME_SPLIT: i8 = 2; // resolved to a YES/NO payout split, see `payout_fraction`
+pub const OUTCOME_INVALID: i8 = 3; // resolved Invalid by earlier builds: shares refunded at the reference price in `payout_fraction`
+pub const OUTCOME_REFUND_AT_COST: i8 = 4; // cancelled or resolved Invalid: positions refunded their `cost_basis`
+pub const ED25519_PROGRAM_ID: Pubkey = pubkey!("Ed25519SigVerify111111111111111111111111111");
+pub const SQUADS_PROGRAM_ID: Pubkey = pubkey!("SQDS4ep65T869zMMBKyuUq6aD6EgTu8psMjkvj52pCf");
+pub const SPL_STAKE_POOL_PROGRAM_ID: Pubkey = pubkey!("SPoo1Ku8WFXoNDMHPsrGSTSG1Y47rzgn41SLUNakuHy");
+pub const ACCOUNT_COMPRESSION_PROGRAM_ID: Pubkey = pubkey!("cmtDvXumGCrqC1Age74AVPhSRVXJMd8PJS91L8KbNCK");
+pub const NOOP_PROGRAM_ID: Pubkey = pubkey!("noopb9bkMVfRPU8AsbpTUg8AQkHtKwMYZiFUjNRtMmV");
+// Lending programs idle collateral can be deployed to (see `invoke_lending_program`)
+pub const KAMINO_LEND_PROGRAM_ID: Pubkey = pubkey!("KLend2g3cP87fffoy8q1mQqGKjrxjC8boSyAYavgmjD");
+pub const SOLEND_PROGRAM_ID: Pubkey = pubkey!("So1endDq2YkqhipRh3WViPa8hdiSpxWy6z3Z6tMCpAo");
 
 #[program]
 pub mod prediction_program_v2 {
   use super::*;
 
-  /// Create a new YES/NO CPMM market.
-  ///
-  /// - Market account is a PDA derived from (authority, market_id)
-  /// - Vault token account is a PDA owned by vault_authority PDA
-  /// - Authority funds the vault with initial liquidity backing
-  pub fn create_market_cpmm(
-    ctx: Context<CreateMarketCpmm>,
-    args: CreateMarketCpmmArgs,
+  /// Create the protocol config PDA. Only the program's upgrade authority may
+  /// call this; it becomes the config admin.
+  pub fn initialize_config(
+    ctx: Context<InitializeConfig>,
+    collateral_policy: CollateralPolicy,
+    permissionless_collateral: bool,
   ) -> Result<()> {
-    require!(args.initial_liquidity > 0, PredictionError::InvalidLiquidity);
+    let config = &mut ctx.accounts.config;
 
-    let market = &mut ctx.accounts.market;
+    config.admin = ctx.accounts.admin.key();
+    config.collateral_policy = collateral_policy;
+    config.permissionless_collateral = permissionless_collateral;
+    config.approved_mints = Vec::new();
+    config.market_count = 0;
+    config.creation_bond = 0;
+    config.treasury = ctx.accounts.admin.key();
+    config.dispute_window = 0;
+    config.dispute_bond = 0;
+    config.slash_disputer_bps = 0;
+    config.curator = ctx.accounts.admin.key();
+    config.report_bond = 0;
+    config.creator_fee_share_bps = 0;
+    config.protocol_fee_share_bps = 0;
+    config.insurance_fee_share_bps = 0;
+    config.yield_program = Pubkey::default();
+    config.correction_window = 0;
+    config.pending_admin = Pubkey::default();
+    config.param_timelock = 0;
+    config.bump = ctx.bumps.config;
 
-    market.market_id = args.market_id;
-    market.authority = ctx.accounts.authority.key();
-    market.question = args.question;
-    market.collateral_mint = ctx.accounts.collateral_mint.key();
-    market.vault = ctx.accounts.vault.key();
-    market.end_time = args.end_time;
-    market.status = MarketStatus::Open as u8;
-    market.winning_outcome = -1;
+    Ok(())
+  }
 
-    // Symmetric initial reserves. These reserves are in "collateral units".
-    // We back them by depositing 2*L collateral into the vault.
-    market.yes_pool = args.initial_liquidity;
-    market.no_pool = args.initial_liquidity;
+  /// Update which Token-2022 extensions (and mint authorities) are acceptable
+  /// on collateral mints for newly created markets.
+  pub fn set_collateral_policy(
+    ctx: Context<UpdateConfig>,
+    collateral_policy: CollateralPolicy,
+  ) -> Result<()> {
+    ctx.accounts.config.collateral_policy = collateral_policy;
+    Ok(())
+  }
 
-    market.total_yes_shares = 0;
-    market.total_no_shares = 0;
+  /// Add a mint to the collateral whitelist checked at market creation.
+  pub fn add_approved_mint(ctx: Context<UpdateConfig>, mint: Pubkey) -> Result<()> {
+    let config = &mut ctx.accounts.config;
 
-    // CLASSIC PRO-RATA: init snapshots to 0
-    market.resolved_vault_balance = 0;
-    market.resolved_total_winning_shares = 0;
+    require!(
+      !config.approved_mints.contains(&mint),
+      PredictionError::MintAlreadyApproved
+    );
+    require!(
+      config.approved_mints.len() < MAX_APPROVED_MINTS,
+      PredictionError::ApprovedMintsFull
+    );
 
-    // Deposit 2*L collateral into vault as backing.
-    let backing = args
-      .initial_liquidity
-      .checked_mul(2)
-      .ok_or(PredictionError::MathOverflow)?;
+    config.approved_mints.push(mint);
+    Ok(())
+  }
 
-    let cpi_accounts = Transfer {
-      from: ctx.accounts.authority_collateral_ata.to_account_info(),
-      to: ctx.accounts.vault.to_account_info(),
-      authority: ctx.accounts.authority.to_account_info(),
-    };
+  /// Remove a mint from the collateral whitelist. Existing markets are unaffected.
+  pub fn remove_approved_mint(ctx: Context<UpdateConfig>, mint: Pubkey) -> Result<()> {
+    let config = &mut ctx.accounts.config;
 
-    token::transfer(
-      CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts),
-      backing,
-    )?;
+    let index = config
+      .approved_mints
+      .iter()
+      .position(|m| *m == mint)
+      .ok_or(PredictionError::MintNotApproved)?;
 
+    config.approved_mints.swap_remove(index);
     Ok(())
   }
 
-  /// Buy YES (0) or NO (1) shares by paying collateral.
-  ///
-  /// Fee is taken from the input collateral (gross_in).
-  /// Swap is computed on net_in to protect the pool.
-  pub fn buy_shares(
-    ctx: Context<BuyShares>,
-    outcome_index: u8,
-    max_collateral_in: u64,
-    min_shares_out: u64, // slippage guard (recommended)
+  /// Toggle permissionless mode, where any mint passing the extension policy
+  /// can be used as collateral without being whitelisted.
+  pub fn set_permissionless_collateral(
+    ctx: Context<UpdateConfig>,
+    permissionless_collateral: bool,
+  ) -> Result<()> {
+    ctx.accounts.config.permissionless_collateral = permissionless_collateral;
+    Ok(())
+  }
+
+  /// Set the SOL bond required to create a market and where forfeited bonds go.
+  pub fn set_creation_bond(
+    ctx: Context<UpdateConfig>,
+    creation_bond: u64,
+    treasury: Pubkey,
   ) -> Result<()> {
-    let market = &mut ctx.accounts.market;
+    let config = &mut ctx.accounts.config;
+    require!(config.param_timelock == 0, PredictionError::TimelockRequired);
+    config.creation_bond = creation_bond;
+    config.treasury = treasury;
+    Ok(())
+  }
 
+  /// Set how trading fees of newly created markets are split between the
+  /// creator and the protocol; LPs receive the remainder.
+  pub fn set_fee_shares(
+    ctx: Context<UpdateConfig>,
+    creator_fee_share_bps: u16,
+    protocol_fee_share_bps: u16,
+  ) -> Result<()> {
+    let config = &mut ctx.accounts.config;
+    require!(config.param_timelock == 0, PredictionError::TimelockRequired);
     require!(
-      market.status == MarketStatus::Open as u8,
-      PredictionError::InvalidMarketStatus
+      creator_fee_share_bps as u64
+        + protocol_fee_share_bps as u64
+        + config.insurance_fee_share_bps as u64
+        <= BPS_DENOM,
+      PredictionError::InvalidFeeShares
     );
 
-    let clock = Clock::get()?;
+    config.creator_fee_share_bps = creator_fee_share_bps;
+    config.protocol_fee_share_bps = protocol_fee_share_bps;
+    Ok(())
+  }
+
+  /// Set the slice of trading fees of newly created markets that is routed
+  /// to the insurance fund.
+  pub fn set_insurance_fee_share(ctx: Context<UpdateConfig>, insurance_fee_share_bps: u16) -> Result<()> {
+    let config = &mut ctx.accounts.config;
+    require!(config.param_timelock == 0, PredictionError::TimelockRequired);
     require!(
-      clock.unix_timestamp < market.end_time,
-      PredictionError::MarketExpired
+      config.creator_fee_share_bps as u64
+        + config.protocol_fee_share_bps as u64
+        + insurance_fee_share_bps as u64
+        <= BPS_DENOM,
+      PredictionError::InvalidFeeShares
     );
 
-    require!(outcome_index <= 1, PredictionError::InvalidOutcome);
-    require!(max_collateral_in > 0, PredictionError::ZeroAmount);
+    config.insurance_fee_share_bps = insurance_fee_share_bps;
+    Ok(())
+  }
 
-    // Fee on input
-    let (net_in, _fee) = apply_fee_in(max_collateral_in)?;
+  /// Set the lending program (Kamino Lend or Solend) that opted-in markets
+  /// may deposit idle collateral into. `Pubkey::default()` disables it.
+  pub fn set_yield_program(ctx: Context<UpdateConfig>, yield_program: Pubkey) -> Result<()> {
+    let config = &mut ctx.accounts.config;
+    require!(config.param_timelock == 0, PredictionError::TimelockRequired);
+    check_yield_program(yield_program)?;
+    config.yield_program = yield_program;
+    Ok(())
+  }
 
-    // CPMM buy using net_in
-    let (new_yes, new_no, shares_out) = match outcome_index {
-      0 => cpmm_buy_yes(market.yes_pool, market.no_pool, net_in)?,
-      1 => cpmm_buy_no(market.yes_pool, market.no_pool, net_in)?,
-      _ => return err!(PredictionError::InvalidOutcome),
-    };
+  /// Set the lamport bond required to report a market.
+  pub fn set_report_bond(ctx: Context<UpdateConfig>, report_bond: u64) -> Result<()> {
+    ctx.accounts.config.report_bond = report_bond;
+    Ok(())
+  }
 
-    require!(shares_out >= min_shares_out, PredictionError::SlippageExceeded);
-    require!(shares_out > 0, PredictionError::ZeroSharesOut);
+  /// Set the key allowed to mark markets as verified.
+  pub fn set_curator(ctx: Context<UpdateConfig>, curator: Pubkey) -> Result<()> {
+    let config = &mut ctx.accounts.config;
+    require!(config.param_timelock == 0, PredictionError::TimelockRequired);
+    config.curator = curator;
+    Ok(())
+  }
 
-    // Transfer gross collateral to vault; fee stays inside vault.
-    let cpi_accounts = Transfer {
-      from: ctx.accounts.user_collateral_ata.to_account_info(),
+  /// Configure the post-resolution dispute window and bonds.
+  pub fn set_dispute_params(
+    ctx: Context<UpdateConfig>,
+    dispute_window: i64,
+    dispute_bond: u64,
+    slash_disputer_bps: u16,
+  ) -> Result<()> {
+    require!(dispute_window >= 0, PredictionError::InvalidDisputeParams);
+    require!(
+      slash_disputer_bps as u64 <= BPS_DENOM,
+      PredictionError::InvalidDisputeParams
+    );
+
+    let config = &mut ctx.accounts.config;
+    require!(config.param_timelock == 0, PredictionError::TimelockRequired);
+    config.dispute_window = dispute_window;
+    config.dispute_bond = dispute_bond;
+    config.slash_disputer_bps = slash_disputer_bps;
+    Ok(())
+  }
+
+  /// Set the post-resolution correction window (0 = no corrections).
+  pub fn set_correction_window(ctx: Context<UpdateConfig>, correction_window: i64) -> Result<()> {
+    require!(correction_window >= 0, PredictionError::InvalidDisputeParams);
+    let config = &mut ctx.accounts.config;
+    require!(config.param_timelock == 0, PredictionError::TimelockRequired);
+    config.correction_window = correction_window;
+    Ok(())
+  }
+
+  /// Propose a new admin, e.g. a Realms governance PDA so parameter changes
+  /// go through DAO proposals. Takes effect once it calls `accept_admin`.
+  pub fn transfer_admin(ctx: Context<UpdateConfig>, new_admin: Pubkey) -> Result<()> {
+    ctx.accounts.config.pending_admin = new_admin;
+    Ok(())
+  }
+
+  /// The pending admin takes over the config. A governance PDA signs this
+  /// from an executed proposal.
+  pub fn accept_admin(ctx: Context<AcceptAdmin>) -> Result<()> {
+    let config = &mut ctx.accounts.config;
+    let previous = config.admin;
+    config.admin = config.pending_admin;
+    config.pending_admin = Pubkey::default();
+    emit!(AdminTransferred {
+      previous_admin: previous,
+      new_admin: config.admin,
+    });
+    Ok(())
+  }
+
+  /// Apply every `Some` field of `params` in one instruction, so a
+  /// governance proposal can change several parameters atomically. Checks
+  /// match the individual setters.
+  pub fn set_params(ctx: Context<UpdateConfig>, params: ConfigParams) -> Result<()> {
+    let config = &mut ctx.accounts.config;
+    require!(
+      config.param_timelock == 0 || !params.is_timelocked(),
+      PredictionError::TimelockRequired
+    );
+    config.apply_params(params)?;
+
+    emit!(ConfigParamsUpdated {
+      admin: ctx.accounts.admin.key(),
+    });
+    Ok(())
+  }
+
+  /// Queue a parameter change that `execute_params` applies once
+  /// `config.param_timelock` seconds have passed, giving traders time to exit.
+  /// One change can be queued at a time.
+  pub fn queue_params(ctx: Context<QueueParams>, params: ConfigParams) -> Result<()> {
+    let eta = Clock::get()?
+      .unix_timestamp
+      .checked_add(ctx.accounts.config.param_timelock)
+      .ok_or(PredictionError::MathOverflow)?;
+
+    let pending = &mut ctx.accounts.pending_params;
+    pending.params = params.clone();
+    pending.eta = eta;
+    pending.proposer = ctx.accounts.admin.key();
+    pending.bump = ctx.bumps.pending_params;
+
+    emit!(ParamsQueued { params, eta });
+    Ok(())
+  }
+
+  /// Apply the queued change once its delay has passed. Permissionless.
+  pub fn execute_params(ctx: Context<ExecuteParams>) -> Result<()> {
+    let pending = &ctx.accounts.pending_params;
+    require!(
+      Clock::get()?.unix_timestamp >= pending.eta,
+      PredictionError::TimelockPending
+    );
+    ctx.accounts.config.apply_params(pending.params.clone())?;
+
+    emit!(ParamsExecuted {
+      params: pending.params.clone(),
+    });
+    Ok(())
+  }
+
+  /// Drop the queued change.
+  pub fn cancel_params(ctx: Context<CancelParams>) -> Result<()> {
+    emit!(ParamsCancelled {
+      params: ctx.accounts.pending_params.params.clone(),
+    });
+    Ok(())
+  }
+
+  /// Create a new YES/NO CPMM market.
+  ///
+  /// - The question/rules document lives off-chain (IPFS/Arweave) at `metadata_uri`;
+  ///   the market stores its sha256 `question_hash` so clients can verify it
+  /// - Market account is a PDA derived from (authority, market_id)
+  /// - Vault token account is a PDA owned by vault_authority PDA
+  /// - Authority funds the vault with initial liquidity backing
+  /// - Collateral may be an SPL Token or Token-2022 mint (all transfers use `transfer_checked`)
+  ///   as long as it is whitelisted (or the config is permissionless) and its
+  ///   extensions pass the config's collateral policy
+  /// - Anyone may create a market; the creator escrows `config.creation_bond`
+  ///   lamports in a bond PDA until resolution
+  pub fn create_market_cpmm(
+    ctx: Context<CreateMarketCpmm>,
+    args: CreateMarketCpmmArgs,
+  ) -> Result<()> {
+    check_collateral_allowed(&ctx.accounts.config, &ctx.accounts.collateral_mint)?;
+    validate_market_args(&args)?;
+
+    // Deposit yes + no reserves of collateral into vault as backing.
+    let backing = args
+      .initial_yes_reserve
+      .checked_add(args.initial_no_reserve)
+      .ok_or(PredictionError::MathOverflow)?;
+
+    let vault_before = ctx.accounts.vault.amount;
+
+    let cpi_accounts = TransferChecked {
+      from: ctx.accounts.authority_collateral_ata.to_account_info(),
+      mint: ctx.accounts.collateral_mint.to_account_info(),
       to: ctx.accounts.vault.to_account_info(),
-      authority: ctx.accounts.user.to_account_info(),
+      authority: ctx.accounts.authority.to_account_info(),
     };
 
-    token::transfer(
+    token_interface::transfer_checked(
       CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts),
-      max_collateral_in,
+      backing,
+      ctx.accounts.collateral_mint.decimals,
     )?;
 
-    // Update reserves
-    market.yes_pool = new_yes;
-    market.no_pool = new_no;
+    ctx.accounts.vault.reload()?;
+    let received = ctx
+      .accounts
+      .vault
+      .amount
+      .checked_sub(vault_before)
+      .ok_or(PredictionError::MathOverflow)?;
 
-    // Init/update position
-    let position = &mut ctx.accounts.position;
+    let mut market = ctx.accounts.market.load_init()?;
 
-    // Fresh position
-    if position.owner == Pubkey::default() {
-      position.market = market.key();
-      position.owner = ctx.accounts.user.key();
-      position.yes_shares = 0;
-      position.no_shares = 0;
-      position.claimed = false;
-    } else {
-      require!(
-        position.market == market.key(),
-        PredictionError::PositionMarketMismatch
-      );
-      require!(
-        position.owner == ctx.accounts.user.key(),
-        PredictionError::PositionOwnerMismatch
-      );
-    }
+    market.layout_version = MARKET_LAYOUT_VERSION;
+    market.bump = ctx.bumps.market;
+    market.vault_bump = ctx.bumps.vault;
+    market.vault_auth_bump = ctx.bumps.vault_authority;
+    market.authority = ctx.accounts.authority.key();
+    market.collateral_mint = ctx.accounts.collateral_mint.key();
+    market.collateral_decimals = ctx.accounts.collateral_mint.decimals;
+    market.vault = ctx.accounts.vault.key();
+    let lp_shares = init_market_state(
+      &mut market,
+      &mut ctx.accounts.config,
+      &args,
+      received,
+      Clock::get()?.unix_timestamp,
+    )?;
 
-    match outcome_index {
-      0 => {
-        position.yes_shares = position
-          .yes_shares
-          .checked_add(shares_out)
-          .ok_or(PredictionError::MathOverflow)?;
-        market.total_yes_shares = market
-          .total_yes_shares
-          .checked_add(shares_out)
-          .ok_or(PredictionError::MathOverflow)?;
-      }
-      1 => {
-        position.no_shares = position
-          .no_shares
-          .checked_add(shares_out)
-          .ok_or(PredictionError::MathOverflow)?;
-        market.total_no_shares = market
-          .total_no_shares
-          .checked_add(shares_out)
-          .ok_or(PredictionError::MathOverflow)?;
-      }
-      _ => unreachable!(),
+    let lp_position = &mut ctx.accounts.creator_lp_position;
+    lp_position.market = ctx.accounts.market.key();
+    lp_position.owner = ctx.accounts.authority.key();
+    lp_position.lp_shares = lp_shares;
+    lp_position.locked = true;
+    lp_position.bump = ctx.bumps.creator_lp_position;
+
+    // Shares and CLASSIC PRO-RATA snapshots start zeroed (load_init).
+
+    // Creation bond: held in the bond PDA on top of its rent
+    let bond_amount = ctx.accounts.config.creation_bond;
+    if bond_amount > 0 {
+      system_program::transfer(
+        CpiContext::new(
+          ctx.accounts.system_program.to_account_info(),
+          system_program::Transfer {
+            from: ctx.accounts.authority.to_account_info(),
+            to: ctx.accounts.creator_bond.to_account_info(),
+          },
+        ),
+        bond_amount,
+      )?;
     }
+    let bond = &mut ctx.accounts.creator_bond;
+    bond.market = ctx.accounts.market.key();
+    bond.creator = ctx.accounts.authority.key();
+    bond.amount = bond_amount;
+    bond.bump = ctx.bumps.creator_bond;
+
+    ctx.accounts.registry.bump = ctx.bumps.registry;
+    register_market_entry(
+      &mut ctx.accounts.registry,
+      &mut ctx.accounts.registry_page,
+      ctx.bumps.registry_page,
+      ctx.accounts.market.key(),
+      &mut market,
+    )?;
+    count_created_market(ctx.accounts.protocol_stats.as_deref_mut(), &mut market)?;
+
+    emit!(MarketCreated {
+      market: ctx.accounts.market.key(),
+      authority: market.authority,
+      market_id: market.market_id,
+      market_seq: market.market_seq,
+      question_hash: args.question_hash,
+      metadata_uri: args.metadata_uri,
+      collateral_mint: market.collateral_mint,
+      start_time: market.start_time,
+      end_time: market.end_time,
+    });
 
     Ok(())
   }
 
-  /// Sell YES (0) or NO (1) shares back to the AMM for collateral.
-  ///
-  /// Fee is taken from the output collateral.
-  /// The fee stays in the vault, effectively increasing solvency over time.
-  pub fn sell_shares(
-    ctx: Context<SellShares>,
-    outcome_index: u8,
-    shares_in: u64,
-    min_collateral_out: u64, // slippage guard
-  ) -> Result<()> {
-    let market = &mut ctx.accounts.market;
-    let position = &mut ctx.accounts.position;
+  /// Curator marks (or unmarks) a market as verified, so frontends can
+  /// separate vetted markets from permissionless long-tail ones.
+  pub fn set_market_verified(ctx: Context<CuratorMarket>, verified: bool) -> Result<()> {
+    let mut market = ctx.accounts.market.load_mut()?;
+    market.verified = verified as u8;
 
-    require!(
-      market.status == MarketStatus::Open as u8,
-      PredictionError::InvalidMarketStatus
-    );
+    emit!(MarketVerificationChanged {
+      market: ctx.accounts.market.key(),
+      curator: ctx.accounts.curator.key(),
+      verified,
+    });
 
-    let clock = Clock::get()?;
-    require!(
-      clock.unix_timestamp < market.end_time,
-      PredictionError::MarketExpired
-    );
+    Ok(())
+  }
 
-    require!(outcome_index <= 1, PredictionError::InvalidOutcome);
-    require!(shares_in > 0, PredictionError::ZeroAmount);
+  /// Curator pauses or unpauses trading on a market.
+  pub fn set_market_paused(ctx: Context<CuratorMarket>, paused: bool) -> Result<()> {
+    ctx.accounts.market.load_mut()?.paused = paused as u8;
+    Ok(())
+  }
 
-    require!(
-      position.market == market.key(),
-      PredictionError::PositionMarketMismatch
-    );
-    require!(
-      position.owner == ctx.accounts.user.key(),
-      PredictionError::PositionOwnerMismatch
-    );
-    require!(!position.claimed, PredictionError::AlreadyClaimed);
+  /// Flag a market as violating the rules (duplicate, ambiguous, illegal).
+  /// Permissionless; the reporter escrows `config.report_bond` lamports
+  /// until a curator adjudicates with `resolve_report`.
+  pub fn report_market(ctx: Context<ReportMarket>, reason: u8) -> Result<()> {
+    ReportReason::try_from(reason)?;
+    {
+      let market = ctx.accounts.market.load()?;
+      require!(
+        market.is_live(),
+        PredictionError::InvalidMarketStatus
+      );
+    }
 
-    // Ensure user has shares
-    match outcome_index {
-      0 => require!(
-        position.yes_shares >= shares_in,
-        PredictionError::InsufficientShares
-      ),
-      1 => require!(
-        position.no_shares >= shares_in,
-        PredictionError::InsufficientShares
-      ),
-      _ => return err!(PredictionError::InvalidOutcome),
+    let bond_amount = ctx.accounts.config.report_bond;
+    if bond_amount > 0 {
+      system_program::transfer(
+        CpiContext::new(
+          ctx.accounts.system_program.to_account_info(),
+          system_program::Transfer {
+            from: ctx.accounts.reporter.to_account_info(),
+            to: ctx.accounts.report.to_account_info(),
+          },
+        ),
+        bond_amount,
+      )?;
     }
 
-    // Compute gross collateral out by CPMM
-    let (new_yes, new_no, gross_out) = match outcome_index {
-      0 => cpmm_sell_yes(market.yes_pool, market.no_pool, shares_in)?,
-      1 => cpmm_sell_no(market.yes_pool, market.no_pool, shares_in)?,
-      _ => return err!(PredictionError::InvalidOutcome),
-    };
+    let report = &mut ctx.accounts.report;
+    report.market = ctx.accounts.market.key();
+    report.reporter = ctx.accounts.reporter.key();
+    report.reason = reason;
+    report.bond = bond_amount;
+    report.bump = ctx.bumps.report;
 
-    require!(gross_out > 0, PredictionError::ZeroAmount);
+    emit!(MarketReported {
+      market: report.market,
+      reporter: report.reporter,
+      reason,
+      bond: bond_amount,
+    });
 
-    // Fee on output; user receives net_out
-    let (net_out, _fee) = apply_fee_out(gross_out)?;
-    require!(net_out >= min_collateral_out, PredictionError::SlippageExceeded);
+    Ok(())
+  }
 
-    // Update reserves (see comment in your original code)
-    market.yes_pool = new_yes;
-    market.no_pool = new_no;
+  /// Curator adjudicates a report.
+  ///
+  /// - Reject: the report bond is forfeited to the treasury
+  /// - Pause: trading is paused; the reporter gets their bond back
+  /// - Void: the market is cancelled; the reporter gets their bond back
+  ///   plus the creator's bond as a reward
+  pub fn resolve_report(ctx: Context<ResolveReport>, action: u8) -> Result<()> {
+    let action = ReportAction::try_from(action)?;
+    let market_key = ctx.accounts.market.key();
+    let mut market = ctx.accounts.market.load_mut()?;
 
-    // Adjust reserve to account for fee retention
-    let fee_kept = gross_out
-      .checked_sub(net_out)
-      .ok_or(PredictionError::MathOverflow)?;
-    if fee_kept > 0 {
-      match outcome_index {
-        0 => {
-          market.no_pool = market
-            .no_pool
-            .checked_add(fee_kept)
-            .ok_or(PredictionError::MathOverflow)?;
-        }
-        1 => {
-          market.yes_pool = market
-            .yes_pool
-            .checked_add(fee_kept)
-            .ok_or(PredictionError::MathOverflow)?;
-        }
-        _ => {}
+    let destination = match action {
+      ReportAction::Reject => ctx.accounts.treasury.to_account_info(),
+      ReportAction::Pause => {
+        market.paused = 1;
+        ctx.accounts.reporter.to_account_info()
       }
-    }
+      ReportAction::Void => {
+        require!(
+          market.is_live(),
+          PredictionError::InvalidMarketStatus
+        );
+        market.cancel_at_cost(Clock::get()?.unix_timestamp)?;
+        sync_registry_entry(ctx.accounts.registry_page.as_deref_mut(), market_key, &market)?;
 
-    // Burn shares from position and totals
-    match outcome_index {
-      0 => {
-        position.yes_shares = position
-          .yes_shares
-          .checked_sub(shares_in)
-          .ok_or(PredictionError::MathOverflow)?;
-        market.total_yes_shares = market
-          .total_yes_shares
-          .checked_sub(shares_in)
-          .ok_or(PredictionError::MathOverflow)?;
-      }
-      1 => {
-        position.no_shares = position
-          .no_shares
-          .checked_sub(shares_in)
-          .ok_or(PredictionError::MathOverflow)?;
-        market.total_no_shares = market
-          .total_no_shares
-          .checked_sub(shares_in)
-          .ok_or(PredictionError::MathOverflow)?;
+        if let Some(bond) = ctx.accounts.creator_bond.as_ref() {
+          emit!(BondForfeited {
+            market: market_key,
+            creator: bond.creator,
+            recipient: ctx.accounts.reporter.key(),
+            amount: bond.amount,
+          });
+          bond.close(ctx.accounts.reporter.to_account_info())?;
+        }
+        ctx.accounts.reporter.to_account_info()
       }
-      _ => unreachable!(),
-    }
-
-    // Transfer net_out from vault to user using PDA vault authority signer
-    let binding = market.key();
-    let seeds: &[&[u8]] = &[
-      b"vault_auth_v2",
-      binding.as_ref(),
-      &[ctx.bumps.vault_authority],
-    ];
-
-    let cpi_accounts = Transfer {
-      from: ctx.accounts.vault.to_account_info(),
-      to: ctx.accounts.user_collateral_ata.to_account_info(),
-      authority: ctx.accounts.vault_authority.to_account_info(),
     };
 
-    token::transfer(
-      CpiContext::new_with_signer(
-        ctx.accounts.token_program.to_account_info(),
-        cpi_accounts,
-        &[seeds],
-      ),
-      net_out,
-    )?;
+    emit!(ReportResolved {
+      market: market_key,
+      reporter: ctx.accounts.reporter.key(),
+      action: action as u8,
+    });
+
+    ctx.accounts.report.close(destination)?;
 
     Ok(())
   }
 
-  /// Resolve market with winning outcome (0 = YES, 1 = NO).
-  ///
-  /// CLASSIC PRO-RATA:
-  /// - snapshot vault amount and total winning shares at resolution
-  /// - claims compute against snapshot (order independent)
-  pub fn resolve_market(ctx: Context<ResolveMarketV2>, winning_outcome: u8) -> Result<()> {
-    let market = &mut ctx.accounts.market;
+  /// Update discovery metadata (category, tags, image/rules/metadata URIs).
+  /// Only the market authority may call this, and only before the first trade,
+  /// so traders never see the rules change under them.
+  pub fn update_metadata(
+    ctx: Context<UpdateMarketMetadata>,
+    metadata_uri: String,
+    metadata: MarketMetadataArgs,
+  ) -> Result<()> {
+    let mut market = ctx.accounts.market.load_mut()?;
 
-    require!(
-      ctx.accounts.authority.key() == market.authority,
+    require_keys_eq!(
+      market.authority,
+      ctx.accounts.authority.key(),
       PredictionError::Unauthorized
     );
     require!(
-      market.status == MarketStatus::Open as u8,
+      market.is_live(),
       PredictionError::InvalidMarketStatus
     );
-    require!(winning_outcome <= 1, PredictionError::InvalidOutcome);
-
-    let total_winning_shares = match winning_outcome {
-      0 => market.total_yes_shares,
-      1 => market.total_no_shares,
-      _ => return err!(PredictionError::InvalidOutcome),
-    };
-    require!(total_winning_shares > 0, PredictionError::NoWinnings);
+    require!(market.trade_count == 0, PredictionError::MarketAlreadyTraded);
 
-    // Snapshot at resolution time
-    market.resolved_vault_balance = ctx.accounts.vault.amount;
-    market.resolved_total_winning_shares = total_winning_shares;
+    let (metadata_uri_bytes, metadata_uri_len) = encode_metadata_uri(&metadata_uri)?;
+    market.metadata_uri = metadata_uri_bytes;
+    market.metadata_uri_len = metadata_uri_len;
+    market.set_metadata(&metadata)?;
 
-    market.status = MarketStatus::Resolved as u8;
-    market.winning_outcome = winning_outcome as i8;
+    emit!(MetadataUpdated {
+      market: ctx.accounts.market.key(),
+      metadata_uri,
+      category: metadata.category,
+      tags: metadata.tags,
+      image_uri: metadata.image_uri,
+      rules_uri: metadata.rules_uri,
+    });
 
     Ok(())
   }
 
-  /// Claim winnings after resolution using classic pro-rata payout from snapshot.
-  ///
-  /// payout = resolved_vault_balance * user_winning_shares / resolved_total_winning_shares
-  pub fn claim_winnings_v2(ctx: Context<ClaimWinningsV2>) -> Result<()> {
-    let market = &ctx.accounts.market;
-    let position = &mut ctx.accounts.position;
+  /// Push `end_time` back to fix a scheduling mistake. Only until the first
+  /// trade (liquidity changes don't count), and at most
+  /// `MAX_END_TIME_EXTENSION_SECS` in total.
+  pub fn update_end_time(ctx: Context<UpdateEndTime>, new_end_time: i64) -> Result<()> {
+    let market_key = ctx.accounts.market.key();
+    let mut market = ctx.accounts.market.load_mut()?;
+    require_keys_eq!(
+      market.authority,
+      ctx.accounts.authority.key(),
+      PredictionError::Unauthorized
+    );
+    require!(market.is_live(), PredictionError::InvalidMarketStatus);
+    require!(market.trade_count == 0, PredictionError::MarketAlreadyTraded);
+    let now = Clock::get()?.unix_timestamp;
+    require!(now < market.end_time, PredictionError::MarketExpired);
+    require!(new_end_time > market.end_time, PredictionError::InvalidEndTime);
 
+    let extension = (new_end_time - market.end_time)
+      .checked_add(market.end_time_extension as i64)
+      .ok_or(PredictionError::MathOverflow)?;
     require!(
-      market.status == MarketStatus::Resolved as u8,
-      PredictionError::MarketNotResolved
+      extension <= MAX_END_TIME_EXTENSION_SECS,
+      PredictionError::EndTimeExtensionCapExceeded
     );
+    let old_end_time = market.end_time;
+    market.end_time = new_end_time;
+    market.end_time_extension = extension as u32;
 
-    let winning = market.winning_outcome;
-    require!(winning >= 0, PredictionError::InvalidWinningOutcome);
+    sync_registry_entry(ctx.accounts.registry_page.as_deref_mut(), market_key, &market)?;
+
+    emit!(EndTimeUpdated {
+      market: market_key,
+      old_end_time,
+      new_end_time,
+      total_extension: extension,
+    });
+    Ok(())
+  }
 
-    require!(!position.claimed, PredictionError::AlreadyClaimed);
+  /// Halt trading before `end_time` once the real-world outcome is known
+  /// (e.g. a match ended early), so nobody can trade against a settled
+  /// result while resolution is pending. Moves `end_time` to now.
+  ///
+  /// Oracle and scalar markets read their settlement price at `end_time`,
+  /// so moving it would let the caller pick the price; they can't close early.
+  /// A pre-market market hasn't opened yet, so there is nothing to close.
+  pub fn close_trading_early(ctx: Context<CloseTradingEarly>) -> Result<()> {
+    let market_key = ctx.accounts.market.key();
+    let mut market = ctx.accounts.market.load_mut()?;
+    require!(market.scalar == 0, PredictionError::ScalarMarket);
+    require!(market.oracle_market == 0, PredictionError::OracleMarket);
+    let signer = ctx.accounts.authority.key();
     require!(
-      position.market == market.key(),
-      PredictionError::PositionMarketMismatch
+      signer == market.authority || signer == market.resolver(),
+      PredictionError::Unauthorized
     );
+    require!(market.is_live(), PredictionError::InvalidMarketStatus);
+    let now = Clock::get()?.unix_timestamp;
+    market.refresh_phase(now);
     require!(
-      position.owner == ctx.accounts.user.key(),
-      PredictionError::PositionOwnerMismatch
+      market.status == MarketStatus::Open as u8 && now >= market.start_time,
+      PredictionError::MarketNotStarted
     );
+    require!(now < market.end_time, PredictionError::MarketExpired);
 
-    // CLASSIC PRO-RATA: use snapshots
-    let total_winning_shares = market.resolved_total_winning_shares;
-    let vault_balance = market.resolved_vault_balance;
+    let scheduled_end_time = market.end_time;
+    market.end_time = now;
 
-    require!(total_winning_shares > 0, PredictionError::NoWinnings);
-    require!(vault_balance > 0, PredictionError::NoWinnings);
+    sync_registry_entry(ctx.accounts.registry_page.as_deref_mut(), market_key, &market)?;
 
-    let user_winning_shares = match winning as u8 {
-      0 => position.yes_shares,
-      1 => position.no_shares,
-      _ => return err!(PredictionError::InvalidWinningOutcome),
-    };
+    emit!(TradingClosedEarly {
+      market: market_key,
+      scheduled_end_time,
+      closed_at: now,
+    });
+    Ok(())
+  }
 
-    require!(user_winning_shares > 0, PredictionError::NoWinnings);
+  /// Migrate a legacy Borsh `MarketV2` account (inline question string) in place
+  /// to the zero-copy `MarketV3` layout.
+  ///
+  /// The address is unchanged, so the vault, vault authority and position PDAs
+  /// keep working. The legacy question text is hashed into `question_hash` and
+  /// emitted once in `MarketMigrated`; the authority supplies the metadata URI.
+  pub fn migrate_market_v2(ctx: Context<MigrateMarketV2>, metadata_uri: String) -> Result<()> {
+    let info = ctx.accounts.market.to_account_info();
 
-    let payout_u128 = (vault_balance as u128)
-      .checked_mul(user_winning_shares as u128)
-      .ok_or(PredictionError::MathOverflow)?
-      .checked_div(total_winning_shares as u128)
-      .ok_or(PredictionError::MathOverflow)?;
+    let legacy = {
+      let data = info.try_borrow_data()?;
+      MarketV2::try_deserialize(&mut &data[..])?
+    };
 
-    let payout: u64 = payout_u128
-      .try_into()
-      .map_err(|_| PredictionError::MathOverflow)?;
-    require!(payout > 0, PredictionError::NoWinnings);
+    require_keys_eq!(
+      legacy.authority,
+      ctx.accounts.authority.key(),
+      PredictionError::Unauthorized
+    );
+    require_keys_eq!(
+      legacy.vault,
+      ctx.accounts.vault.key(),
+      PredictionError::InvalidMigrationAccount
+    );
+    require_keys_eq!(
+      legacy.collateral_mint,
+      ctx.accounts.collateral_mint.key(),
+      PredictionError::InvalidMigrationAccount
+    );
 
-    // Transfer payout from vault to user using PDA vault authority signer
-    let binding = market.key();
-    let seeds: &[&[u8]] = &[
-      b"vault_auth_v2",
-      binding.as_ref(),
-      &[ctx.bumps.vault_authority],
-    ];
+    let (expected_market, market_bump) = Pubkey::find_program_address(
+      &[
+        b"market_v2",
+        legacy.authority.as_ref(),
+        &legacy.market_id.to_le_bytes(),
+      ],
+      ctx.program_id,
+    );
+    require_keys_eq!(
+      expected_market,
+      info.key(),
+      PredictionError::InvalidMigrationAccount
+    );
 
-    let cpi_accounts = Transfer {
-      from: ctx.accounts.vault.to_account_info(),
-      to: ctx.accounts.user_collateral_ata.to_account_info(),
-      authority: ctx.accounts.vault_authority.to_account_info(),
-    };
+    let (metadata_uri_bytes, metadata_uri_len) = encode_metadata_uri(&metadata_uri)?;
+    let question_hash = hash(legacy.question.as_bytes()).to_bytes();
 
-    token::transfer(
-      CpiContext::new_with_signer(
+    let mut market = MarketV3::zeroed();
+    market.layout_version = MARKET_LAYOUT_VERSION;
+    market.bump = market_bump;
+    market.vault_bump = ctx.bumps.vault;
+    market.vault_auth_bump = ctx.bumps.vault_authority;
+    market.market_id = legacy.market_id;
+    market.authority = legacy.authority;
+    market.question_hash = question_hash;
+    market.metadata_uri = metadata_uri_bytes;
+    market.metadata_uri_len = metadata_uri_len;
+    market.collateral_mint = legacy.collateral_mint;
+    market.collateral_decimals = ctx.accounts.collateral_mint.decimals;
+    market.vault = legacy.vault;
+    market.end_time = legacy.end_time;
+    market.status = legacy.status;
+    market.winning_outcome = legacy.winning_outcome;
+    market.yes_pool = legacy.yes_pool;
+    market.no_pool = legacy.no_pool;
+    market.total_yes_shares = legacy.total_yes_shares;
+    market.total_no_shares = legacy.total_no_shares;
+    market.resolved_vault_balance = legacy.resolved_vault_balance;
+    market.resolved_total_winning_shares = legacy.resolved_total_winning_shares;
+    // Legacy resolutions paid the whole vault snapshot to winners
+    market.resolved_payable = legacy.resolved_vault_balance;
+    market.payout_rate = payout_rate(
+      legacy.resolved_vault_balance,
+      legacy.resolved_total_winning_shares,
+    )?;
+    // Legacy markets had no internal accounting; adopt the vault as it stands
+    market.vault_balance = ctx.accounts.vault.amount;
+    market.market_seq = ctx.accounts.config.next_market_seq()?;
+    // Legacy markets didn't count trades; treat any outstanding shares as traded
+    // so metadata stays frozen.
+    if legacy.total_yes_shares > 0 || legacy.total_no_shares > 0 {
+      market.trade_count = 1;
+    }
+
+    // Resize to the new layout, topping up rent from the authority.
+    let new_len = 8 + std::mem::size_of::<MarketV3>();
+    let rent_due = Rent::get()?
+      .minimum_balance(new_len)
+      .saturating_sub(info.lamports());
+    if rent_due > 0 {
+      system_program::transfer(
+        CpiContext::new(
+          ctx.accounts.system_program.to_account_info(),
+          system_program::Transfer {
+            from: ctx.accounts.authority.to_account_info(),
+            to: info.clone(),
+          },
+        ),
+        rent_due,
+      )?;
+    }
+    info.resize(new_len)?;
+
+    {
+      let mut data = info.try_borrow_mut_data()?;
+      data.fill(0);
+      data[..8].copy_from_slice(MarketV3::DISCRIMINATOR);
+      data[8..new_len].copy_from_slice(bytemuck::bytes_of(&market));
+    }
+
+    emit!(MarketMigrated {
+      market: info.key(),
+      market_seq: market.market_seq,
+      question_hash,
+      question: legacy.question,
+      metadata_uri,
+    });
+
+    Ok(())
+  }
+
+  /// Migrate a legacy position (written before positions stored their bump
+  /// or cost basis) in place. Permissionless; `payer` covers the extra rent.
+  /// The cost basis of a migrated position starts at zero.
+  pub fn migrate_position_v2(ctx: Context<MigratePositionV2>) -> Result<()> {
+    let info = ctx.accounts.position.to_account_info();
+
+    let legacy = {
+      let data = info.try_borrow_data()?;
+      // Pre-bump, or with the bump but no cost basis
+      require!(
+        (data.len() == 8 + LegacyPositionV2::SIZE || data.len() == 8 + LegacyPositionV2::SIZE + 1)
+          && data[..8] == *PositionV2::DISCRIMINATOR,
+        PredictionError::InvalidMigrationAccount
+      );
+      LegacyPositionV2::deserialize(&mut &data[8..])?
+    };
+
+    let (expected_position, bump) = Pubkey::find_program_address(
+      &[
+        b"position_v2",
+        legacy.market.as_ref(),
+        legacy.owner.as_ref(),
+      ],
+      ctx.program_id,
+    );
+    require_keys_eq!(
+      expected_position,
+      info.key(),
+      PredictionError::InvalidMigrationAccount
+    );
+
+    let position = PositionV2 {
+      market: legacy.market,
+      owner: legacy.owner,
+      yes_shares: legacy.yes_shares,
+      no_shares: legacy.no_shares,
+      claimed: legacy.claimed,
+      bump,
+      cost_basis: 0,
+    };
+
+    let new_len = 8 + PositionV2::INIT_SPACE;
+    let rent_due = Rent::get()?
+      .minimum_balance(new_len)
+      .saturating_sub(info.lamports());
+    if rent_due > 0 {
+      system_program::transfer(
+        CpiContext::new(
+          ctx.accounts.system_program.to_account_info(),
+          system_program::Transfer {
+            from: ctx.accounts.payer.to_account_info(),
+            to: info.clone(),
+          },
+        ),
+        rent_due,
+      )?;
+    }
+    info.resize(new_len)?;
+
+    let mut data = info.try_borrow_mut_data()?;
+    position.try_serialize(&mut &mut data[..])?;
+
+    Ok(())
+  }
+
+  /// Add a market that predates the registry (e.g. a migrated one) to it.
+  /// Permissionless; `payer` covers rent for a new registry page.
+  pub fn register_market(ctx: Context<RegisterMarket>) -> Result<()> {
+    let mut market = ctx.accounts.market.load_mut()?;
+    require!(market.registered == 0, PredictionError::MarketAlreadyRegistered);
+
+    ctx.accounts.registry.bump = ctx.bumps.registry;
+    register_market_entry(
+      &mut ctx.accounts.registry,
+      &mut ctx.accounts.registry_page,
+      ctx.bumps.registry_page,
+      ctx.accounts.market.key(),
+      &mut market,
+    )
+  }
+
+  /// Deposit collateral as pool liquidity (pre-market or open).
+  ///
+  /// The deposit mints complete sets; the pool takes them in proportion to
+  /// its current reserves so the price doesn't move, and any leftover YES or
+  /// NO shares are credited to the LP's position.
+  pub fn add_liquidity(ctx: Context<AddLiquidity>, amount: u64, min_lp_out: u64) -> Result<()> {
+    let market_key = ctx.accounts.market.key();
+    let mut market = ctx.accounts.market.load_mut()?;
+
+    let now = Clock::get()?.unix_timestamp;
+    market.refresh_phase(now);
+    require!(market.is_live(), PredictionError::InvalidMarketStatus);
+    require!(market.paused == 0, PredictionError::MarketPaused);
+    require!(now < market.end_time, PredictionError::MarketExpired);
+    require!(amount > 0, PredictionError::ZeroAmount);
+    // Migrated markets have no LP accounting for their existing pool
+    require!(market.total_lp_shares > 0, PredictionError::LiquidityNotEnabled);
+
+    let received = deposit_collateral(
+      ctx.accounts.user.to_account_info(),
+      ctx.accounts.user_collateral_ata.as_ref().map(|a| a.to_account_info()),
+      &mut ctx.accounts.vault,
+      &ctx.accounts.collateral_mint,
+      ctx.accounts.token_program.to_account_info(),
+      ctx.accounts.system_program.to_account_info(),
+      amount,
+    )?;
+    market.credit_vault(received)?;
+
+    let max_pool = market.yes_pool.max(market.no_pool);
+    let yes_add = mul_div(received, market.yes_pool, max_pool)?;
+    let no_add = mul_div(received, market.no_pool, max_pool)?;
+    let lp_out = mul_div(received, market.total_lp_shares, max_pool)?;
+    require!(lp_out > 0, PredictionError::ZeroAmount);
+    require!(lp_out >= min_lp_out, PredictionError::SlippageExceeded);
+
+    market.yes_pool = market
+      .yes_pool
+      .checked_add(yes_add)
+      .ok_or(PredictionError::MathOverflow)?;
+    market.no_pool = market
+      .no_pool
+      .checked_add(no_add)
+      .ok_or(PredictionError::MathOverflow)?;
+    let lp_position = &mut ctx.accounts.lp_position;
+    let rewards = checkpoint_lp_rewards(
+      &market,
+      ctx.accounts.lp_rewards.as_deref_mut(),
+      lp_position,
+      now,
+    )?;
+
+    market.total_lp_shares = market
+      .total_lp_shares
+      .checked_add(lp_out)
+      .ok_or(PredictionError::MathOverflow)?;
+
+    if lp_position.owner == Pubkey::default() {
+      lp_position.market = market_key;
+      lp_position.owner = ctx.accounts.user.key();
+      lp_position.bump = ctx.bumps.lp_position;
+    }
+    lp_position.lp_shares = lp_position
+      .lp_shares
+      .checked_add(lp_out)
+      .ok_or(PredictionError::MathOverflow)?;
+    if let Some(rewards) = rewards {
+      rewards.reset_debt(lp_position)?;
+    }
+
+    // Leftover outcome shares from the complete sets the pool didn't take
+    let position = &mut ctx.accounts.position;
+    init_or_check_position(
+      position,
+      market_key,
+      ctx.accounts.user.key(),
+      ctx.bumps.position,
+    )?;
+    let yes_left = received
+      .checked_sub(yes_add)
+      .ok_or(PredictionError::MathOverflow)?;
+    let no_left = received
+      .checked_sub(no_add)
+      .ok_or(PredictionError::MathOverflow)?;
+    credit_shares(&mut market, position, yes_left, no_left)?;
+
+    emit!(LiquidityAdded {
+      market: market_key,
+      provider: ctx.accounts.user.key(),
+      amount: received,
+      lp_shares: lp_out,
+    });
+
+    Ok(())
+  }
+
+  /// Burn LP shares for a proportional slice of both pool reserves, credited
+  /// as YES/NO shares to the LP's position (merge them to get collateral back).
+  /// The creator's locked position can't be removed before resolution.
+  pub fn remove_liquidity(
+    ctx: Context<RemoveLiquidity>,
+    lp_shares: u64,
+    min_yes_out: u64,
+    min_no_out: u64,
+  ) -> Result<()> {
+    let market_key = ctx.accounts.market.key();
+    let mut market = ctx.accounts.market.load_mut()?;
+    let lp_position = &mut ctx.accounts.lp_position;
+
+    let now = Clock::get()?.unix_timestamp;
+    market.refresh_phase(now);
+    require!(market.is_live(), PredictionError::InvalidMarketStatus);
+    require!(!lp_position.locked, PredictionError::LiquidityLocked);
+    require!(lp_shares > 0, PredictionError::ZeroAmount);
+    require!(
+      lp_position.lp_shares >= lp_shares,
+      PredictionError::InsufficientShares
+    );
+
+    let yes_out = mul_div(market.yes_pool, lp_shares, market.total_lp_shares)?;
+    let no_out = mul_div(market.no_pool, lp_shares, market.total_lp_shares)?;
+    require!(
+      yes_out >= min_yes_out && no_out >= min_no_out,
+      PredictionError::SlippageExceeded
+    );
+
+    market.yes_pool = market
+      .yes_pool
+      .checked_sub(yes_out)
+      .ok_or(PredictionError::MathOverflow)?;
+    market.no_pool = market
+      .no_pool
+      .checked_sub(no_out)
+      .ok_or(PredictionError::MathOverflow)?;
+    require!(
+      market.yes_pool > 0 && market.no_pool > 0,
+      PredictionError::InvalidLiquidity
+    );
+    let rewards = checkpoint_lp_rewards(
+      &market,
+      ctx.accounts.lp_rewards.as_deref_mut(),
+      lp_position,
+      now,
+    )?;
+    market.total_lp_shares -= lp_shares;
+    lp_position.lp_shares -= lp_shares;
+    if let Some(rewards) = rewards {
+      rewards.reset_debt(lp_position)?;
+    }
+
+    let position = &mut ctx.accounts.position;
+    init_or_check_position(
+      position,
+      market_key,
+      ctx.accounts.user.key(),
+      ctx.bumps.position,
+    )?;
+    credit_shares(&mut market, position, yes_out, no_out)?;
+
+    emit!(LiquidityRemoved {
+      market: market_key,
+      provider: ctx.accounts.user.key(),
+      lp_shares,
+      yes_shares: yes_out,
+      no_shares: no_out,
+    });
+
+    Ok(())
+  }
+
+  /// Deposit collateral for an equal number of YES and NO shares (complete
+  /// sets). Allowed in the pre-market phase. Fee-free and not counted as a
+  /// trade: the conversion is riskless, and charging for it would break the
+  /// arbitrage that keeps YES + NO = 1.
+  pub fn split_shares(ctx: Context<SplitShares>, amount: u64) -> Result<()> {
+    let market_key = ctx.accounts.market.key();
+    let mut market = ctx.accounts.market.load_mut()?;
+
+    let now = Clock::get()?.unix_timestamp;
+    market.refresh_phase(now);
+    require!(market.is_live(), PredictionError::InvalidMarketStatus);
+    require!(market.paused == 0, PredictionError::MarketPaused);
+    require!(now < market.end_time, PredictionError::MarketExpired);
+    require!(amount > 0, PredictionError::ZeroAmount);
+
+    let received = deposit_collateral(
+      ctx.accounts.user.to_account_info(),
+      ctx.accounts.user_collateral_ata.as_ref().map(|a| a.to_account_info()),
+      &mut ctx.accounts.vault,
+      &ctx.accounts.collateral_mint,
+      ctx.accounts.token_program.to_account_info(),
+      ctx.accounts.system_program.to_account_info(),
+      amount,
+    )?;
+    market.credit_vault(received)?;
+    require!(received > 0, PredictionError::ZeroAmount);
+
+    let position = &mut ctx.accounts.position;
+    init_or_check_position(
+      position,
+      market_key,
+      ctx.accounts.user.key(),
+      ctx.bumps.position,
+    )?;
+    credit_shares(&mut market, position, received, received)?;
+    add_cost_basis(&mut market, position, received)?;
+
+    Ok(())
+  }
+
+  /// Burn an equal number of YES and NO shares for collateral, 1:1 and fee-free
+  /// like `split_shares`.
+  pub fn merge_shares(ctx: Context<MergeShares>, amount: u64) -> Result<()> {
+    let market_key = ctx.accounts.market.key();
+    let mut market = ctx.accounts.market.load_mut()?;
+    let position = &mut ctx.accounts.position;
+
+    let now = Clock::get()?.unix_timestamp;
+    market.refresh_phase(now);
+    require!(market.is_live(), PredictionError::InvalidMarketStatus);
+    require!(market.paused == 0, PredictionError::MarketPaused);
+    require!(amount > 0, PredictionError::ZeroAmount);
+    require!(
+      position.yes_shares >= amount && position.no_shares >= amount,
+      PredictionError::InsufficientShares
+    );
+
+    position.yes_shares -= amount;
+    position.no_shares -= amount;
+    market.total_yes_shares = market
+      .total_yes_shares
+      .checked_sub(amount)
+      .ok_or(PredictionError::MathOverflow)?;
+    market.total_no_shares = market
+      .total_no_shares
+      .checked_sub(amount)
+      .ok_or(PredictionError::MathOverflow)?;
+    reduce_cost_basis(&mut market, position, amount)?;
+
+    let seeds: &[&[u8]] = &[
+      b"vault_auth_v2",
+      market_key.as_ref(),
+      &[market.vault_auth_bump],
+    ];
+
+    pay_out_collateral(
+      &ctx.accounts.vault,
+      ctx.accounts.vault_authority.to_account_info(),
+      seeds,
+      &ctx.accounts.collateral_mint,
+      ctx.accounts.user_collateral_ata.as_ref().map(|a| a.to_account_info()),
+      ctx.accounts.wsol_unwrap.as_ref().map(|a| a.to_account_info()),
+      ctx.accounts.user.to_account_info(),
+      ctx.accounts.payer.to_account_info(),
+      ctx.accounts.token_program.to_account_info(),
+      ctx.accounts.system_program.to_account_info(),
+      amount,
+    )?;
+    market.debit_vault(amount)
+  }
+
+  /// Net a hedged position: merge min(YES, NO) complete sets back into
+  /// collateral (fee-free, as `merge_shares`), freeing the locked capital.
+  pub fn net_position(ctx: Context<MergeShares>) -> Result<()> {
+    let position = &ctx.accounts.position;
+    let amount = position.yes_shares.min(position.no_shares);
+    require!(amount > 0, PredictionError::NothingToNet);
+
+    merge_shares(ctx, amount)
+  }
+
+  /// Buy YES (0) or NO (1) shares by paying collateral.
+  ///
+  /// Fee is taken from the input collateral (gross_in).
+  /// Swap is computed on net_in to protect the pool.
+  /// gross_in is the amount the vault actually received, which is less than
+  /// `max_collateral_in` for Token-2022 mints with a transfer fee.
+  ///
+  /// For native SOL markets (collateral mint = wSOL) the user pays in lamports:
+  /// they are moved straight into the vault and wrapped with `sync_native`,
+  /// so `user_collateral_ata` can be omitted.
+  pub fn buy_shares(
+    ctx: Context<BuyShares>,
+    outcome_index: u8,
+    max_collateral_in: u64,
+    min_shares_out: u64, // slippage guard (recommended)
+  ) -> Result<()> {
+    let market_key = ctx.accounts.market.key();
+    let mut market = ctx.accounts.market.load_mut()?;
+
+    let now = Clock::get()?.unix_timestamp;
+    check_tradable(&mut market, now)?;
+    require!(outcome_index <= 1, PredictionError::InvalidOutcome);
+    require!(max_collateral_in > 0, PredictionError::ZeroAmount);
+
+    // Transfer gross collateral to vault; fee stays inside vault.
+    // Transfer-fee (Token-2022) mints deliver less than requested: trade on the vault delta.
+    let received = deposit_collateral(
+      ctx.accounts.user.to_account_info(),
+      ctx.accounts.user_collateral_ata.as_ref().map(|a| a.to_account_info()),
+      &mut ctx.accounts.vault,
+      &ctx.accounts.collateral_mint,
+      ctx.accounts.token_program.to_account_info(),
+      ctx.accounts.system_program.to_account_info(),
+      max_collateral_in,
+    )?;
+    market.credit_vault(received)?;
+
+    // Init/update position
+    let position = &mut ctx.accounts.position;
+    let new_trader = position.owner == Pubkey::default();
+    init_or_check_position(
+      position,
+      market_key,
+      ctx.accounts.user.key(),
+      ctx.bumps.position,
+    )?;
+
+    let mut trade = TradeAccounts {
+      config: &ctx.accounts.config,
+      trader: ctx.accounts.user.key(),
+      new_trader,
+      transient: false,
+      allowlist_entry: ctx.accounts.allowlist_entry.as_deref(),
+      permit_gate: ctx.accounts.permit_gate.as_deref(),
+      instructions: ctx.accounts.instructions.as_ref(),
+      token_gate: ctx.accounts.token_gate.as_deref(),
+      gate_token_account: ctx.accounts.gate_token_account.as_deref(),
+      market_makers: ctx.accounts.market_makers.as_deref_mut(),
+      user_stats: ctx.accounts.user_stats.as_deref_mut(),
+      competition_entry: ctx.accounts.competition_entry.as_deref_mut(),
+      market_stats: ctx.accounts.market_stats.as_deref_mut(),
+      protocol_stats: ctx.accounts.protocol_stats.as_deref_mut(),
+      price_candles: ctx.accounts.price_candles.as_ref(),
+      trade_tape: ctx.accounts.trade_tape.as_ref(),
+      price_feed: ctx.accounts.price_feed.as_ref(),
+    };
+    execute_buy(
+      &mut market,
+      market_key,
+      position,
+      outcome_index,
+      received,
+      min_shares_out,
+      now,
+      &mut trade,
+    )?;
+
+    Ok(())
+  }
+
+  /// `buy_shares` for `user`, paid from their collateral ATA by its SPL
+  /// delegate. Lets a router or smart wallet approved on the ATA route a buy
+  /// in via CPI without the ATA owner signing; the shares land in `user`'s
+  /// position.
+  pub fn buy_shares_delegated(
+    ctx: Context<DelegatedBuy>,
+    outcome_index: u8,
+    max_collateral_in: u64,
+    min_shares_out: u64,
+  ) -> Result<()> {
+    let market_key = ctx.accounts.market.key();
+    let mut market = ctx.accounts.market.load_mut()?;
+    let user = ctx.accounts.user.key();
+
+    let now = Clock::get()?.unix_timestamp;
+    check_tradable(&mut market, now)?;
+    require!(outcome_index <= 1, PredictionError::InvalidOutcome);
+    require!(max_collateral_in > 0, PredictionError::ZeroAmount);
+
+    // The token program enforces the delegate's remaining allowance
+    let vault_before = ctx.accounts.vault.amount;
+    token_interface::transfer_checked(
+      CpiContext::new(
         ctx.accounts.token_program.to_account_info(),
-        cpi_accounts,
-        &[seeds],
+        TransferChecked {
+          from: ctx.accounts.user_collateral_ata.to_account_info(),
+          mint: ctx.accounts.collateral_mint.to_account_info(),
+          to: ctx.accounts.vault.to_account_info(),
+          authority: ctx.accounts.delegate.to_account_info(),
+        },
       ),
-      payout,
+      max_collateral_in,
+      ctx.accounts.collateral_mint.decimals,
+    )?;
+    ctx.accounts.vault.reload()?;
+    let received = ctx
+      .accounts
+      .vault
+      .amount
+      .checked_sub(vault_before)
+      .ok_or(PredictionError::MathOverflow)?;
+    require!(received > 0, PredictionError::ZeroAmount);
+    market.credit_vault(received)?;
+
+    let position = &mut ctx.accounts.position;
+    let new_trader = position.owner == Pubkey::default();
+    init_or_check_position(position, market_key, user, ctx.bumps.position)?;
+
+    let mut trade = TradeAccounts {
+      config: &ctx.accounts.config,
+      trader: user,
+      new_trader,
+      transient: false,
+      allowlist_entry: ctx.accounts.allowlist_entry.as_deref(),
+      permit_gate: ctx.accounts.permit_gate.as_deref(),
+      instructions: ctx.accounts.instructions.as_ref(),
+      token_gate: ctx.accounts.token_gate.as_deref(),
+      gate_token_account: ctx.accounts.gate_token_account.as_deref(),
+      market_makers: None,
+      user_stats: None,
+      competition_entry: None,
+      market_stats: ctx.accounts.market_stats.as_deref_mut(),
+      protocol_stats: ctx.accounts.protocol_stats.as_deref_mut(),
+      price_candles: ctx.accounts.price_candles.as_ref(),
+      trade_tape: ctx.accounts.trade_tape.as_ref(),
+      price_feed: ctx.accounts.price_feed.as_ref(),
+    };
+    let shares_out = execute_buy(
+      &mut market,
+      market_key,
+      position,
+      outcome_index,
+      received,
+      min_shares_out,
+      now,
+      &mut trade,
+    )?;
+    emit!(DelegatedBuyExecuted {
+      market: market_key,
+      user,
+      delegate: ctx.accounts.delegate.key(),
+      collateral_in: received,
+      shares_out,
+    });
+    Ok(())
+  }
+
+  /// Sell YES (0) or NO (1) shares back to the AMM for collateral.
+  ///
+  /// Fee is taken from the output collateral.
+  /// The fee stays in the vault, effectively increasing solvency over time.
+  ///
+  /// For native SOL markets the payout is unwrapped through the temporary
+  /// `wsol_unwrap` account and lands in the user's wallet as lamports.
+  pub fn sell_shares(
+    ctx: Context<SellShares>,
+    outcome_index: u8,
+    shares_in: u64,
+    min_collateral_out: u64, // slippage guard
+  ) -> Result<()> {
+    let market_key = ctx.accounts.market.key();
+    let mut market = ctx.accounts.market.load_mut()?;
+    let position = &mut ctx.accounts.position;
+
+    let now = Clock::get()?.unix_timestamp;
+    check_tradable(&mut market, now)?;
+    require!(
+      position.owner == ctx.accounts.user.key(),
+      PredictionError::PositionOwnerMismatch
+    );
+
+    // The token gate only applies to buys
+    let mut trade = TradeAccounts {
+      config: &ctx.accounts.config,
+      trader: ctx.accounts.user.key(),
+      new_trader: false,
+      transient: false,
+      allowlist_entry: ctx.accounts.allowlist_entry.as_deref(),
+      permit_gate: ctx.accounts.permit_gate.as_deref(),
+      instructions: ctx.accounts.instructions.as_ref(),
+      token_gate: None,
+      gate_token_account: None,
+      market_makers: ctx.accounts.market_makers.as_deref_mut(),
+      user_stats: ctx.accounts.user_stats.as_deref_mut(),
+      competition_entry: ctx.accounts.competition_entry.as_deref_mut(),
+      market_stats: ctx.accounts.market_stats.as_deref_mut(),
+      protocol_stats: ctx.accounts.protocol_stats.as_deref_mut(),
+      price_candles: ctx.accounts.price_candles.as_ref(),
+      trade_tape: ctx.accounts.trade_tape.as_ref(),
+      price_feed: ctx.accounts.price_feed.as_ref(),
+    };
+    let net_out = execute_sell(
+      &mut market,
+      market_key,
+      position,
+      outcome_index,
+      shares_in,
+      min_collateral_out,
+      now,
+      &mut trade,
+    )?;
+    // Transfer net_out from vault to user using PDA vault authority signer
+    let seeds: &[&[u8]] = &[
+      b"vault_auth_v2",
+      market_key.as_ref(),
+      &[market.vault_auth_bump],
+    ];
+
+    pay_out_collateral(
+      &ctx.accounts.vault,
+      ctx.accounts.vault_authority.to_account_info(),
+      seeds,
+      &ctx.accounts.collateral_mint,
+      ctx.accounts.user_collateral_ata.as_ref().map(|a| a.to_account_info()),
+      ctx.accounts.wsol_unwrap.as_ref().map(|a| a.to_account_info()),
+      ctx.accounts.user.to_account_info(),
+      ctx.accounts.payer.to_account_info(),
+      ctx.accounts.token_program.to_account_info(),
+      ctx.accounts.system_program.to_account_info(),
+      net_out,
     )?;
+    market.debit_vault(net_out)
+  }
+
+  /// Resolve market with winning outcome (0 = YES, 1 = NO).
+  ///
+  /// CLASSIC PRO-RATA:
+  /// - snapshot the internally tracked vault balance and total winning shares
+  ///   at resolution (tokens sent straight to the vault are ignored)
+  /// - claims compute against snapshot (order independent)
+  pub fn resolve_market(ctx: Context<ResolveMarketV2>, winning_outcome: u8) -> Result<()> {
+    require!(winning_outcome <= 1, PredictionError::InvalidOutcome);
+    resolve_market_as(ctx, winning_outcome as i8, 0)
+  }
+
+  /// Resolve to a payout vector `[yes, no]` (PRICE_SCALE, summing to
+  /// PRICE_SCALE) for ambiguous or partially-true questions; each side's
+  /// shares are paid their fraction of the snapshot. `[PRICE_SCALE, 0]` and
+  /// `[0, PRICE_SCALE]` are plain YES / NO resolutions.
+  pub fn resolve_market_payouts(ctx: Context<ResolveMarketV2>, payouts: Vec<u64>) -> Result<()> {
+    require!(
+      payouts.len() == 2
+        && payouts[0].checked_add(payouts[1]) == Some(PRICE_SCALE),
+      PredictionError::InvalidPayoutVector
+    );
+    match payouts[0] {
+      PRICE_SCALE => resolve_market_as(ctx, 0, 0),
+      0 => resolve_market_as(ctx, 1, 0),
+      yes => resolve_market_as(ctx, OUTCOME_SPLIT, yes),
+    }
+  }
+
+  /// Resolve as Invalid, once trading has ended, when the question has no
+  /// fair YES or NO answer. Instead of paying either side, positions are
+  /// refunded their cost basis (OUTCOME_REFUND_AT_COST), so the closing
+  /// pool price can't be pushed to decide the refund. Claims and challenge
+  /// windows work as for any resolution.
+  pub fn resolve_invalid(ctx: Context<ResolveMarketV2>) -> Result<()> {
+    require!(
+      Clock::get()?.unix_timestamp >= ctx.accounts.market.load()?.end_time,
+      PredictionError::MarketNotEnded
+    );
+    resolve_market_as(ctx, OUTCOME_REFUND_AT_COST, 0)
+  }
+
+  /// Resolver or admin corrects the outcome during the correction window.
+  /// Nothing has been paid out yet, so the resolution snapshot is recomputed
+  /// against the same vault balance.
+  pub fn correct_resolution(ctx: Context<CorrectResolution>, winning_outcome: u8) -> Result<()> {
+    require!(winning_outcome <= 1, PredictionError::InvalidOutcome);
+    let mut market = ctx.accounts.market.load_mut()?;
+    let signer = ctx.accounts.authority.key();
+    require!(
+      signer == market.resolver() || signer == ctx.accounts.config.admin,
+      PredictionError::Unauthorized
+    );
+    require!(
+      market.status == MarketStatus::Resolved as u8,
+      PredictionError::MarketNotResolved
+    );
+    require!(
+      Clock::get()?.unix_timestamp < market.correction_deadline,
+      PredictionError::CorrectionWindowClosed
+    );
+
+    let previous = market.winning_outcome;
+    market.winning_outcome = winning_outcome as i8;
+    market.payout_fraction = 0;
+    let total_winning_shares =
+      market.winning_shares(market.total_yes_shares, market.total_no_shares)?;
+    require!(total_winning_shares > 0, PredictionError::NoWinnings);
+    let vault_balance = market.resolved_vault_balance;
+    market.snapshot_resolution(vault_balance, total_winning_shares)?;
+
+    emit!(ResolutionCorrected {
+      market: ctx.accounts.market.key(),
+      corrected_by: signer,
+      previous_outcome: previous,
+      winning_outcome: market.winning_outcome,
+    });
+    Ok(())
+  }
+
+  /// Claim while payouts are still held back: the position's shares move
+  /// into a per-user escrow, paid out by `claim_winnings_v2` (callable by
+  /// anyone) against the final outcome once the windows close.
+  pub fn escrow_claim(ctx: Context<EscrowClaim>) -> Result<()> {
+    let market = ctx.accounts.market.load()?;
+    require!(
+      market.status == MarketStatus::Resolved as u8,
+      PredictionError::MarketNotResolved
+    );
+    require!(
+      Clock::get()?.unix_timestamp < market.dispute_deadline,
+      PredictionError::DisputeWindowClosed
+    );
+
+    let position = &mut ctx.accounts.position;
+    let (yes, no) = (position.yes_shares, position.no_shares);
+    require!(yes > 0 || no > 0, PredictionError::NoWinnings);
+    take_position_shares(position, 0, yes)?;
+    take_position_shares(position, 1, no)?;
+
+    let escrow = &mut ctx.accounts.claim_escrow;
+    escrow.market = ctx.accounts.market.key();
+    escrow.owner = ctx.accounts.user.key();
+    escrow.yes_shares = yes;
+    escrow.no_shares = no;
+    escrow.bump = ctx.bumps.claim_escrow;
+    Ok(())
+  }
+
+  /// Return the creation bond (and its rent) to the creator once the market
+  /// has resolved.
+  pub fn release_bond(ctx: Context<ReleaseBond>) -> Result<()> {
+    let market = ctx.accounts.market.load()?;
+    require!(
+      market.status == MarketStatus::Resolved as u8,
+      PredictionError::MarketNotResolved
+    );
+    require!(
+      Clock::get()?.unix_timestamp >= market.dispute_deadline,
+      PredictionError::DisputeWindowOpen
+    );
+    require!(!ctx.accounts.creator_bond.slashed, PredictionError::BondSlashed);
+
+    emit!(BondReleased {
+      market: ctx.accounts.market.key(),
+      creator: ctx.accounts.creator.key(),
+      amount: ctx.accounts.creator_bond.amount,
+    });
+
+    // Account is closed to the creator by the `close` constraint.
+    Ok(())
+  }
+
+  /// Challenge a resolution during the dispute window by escrowing
+  /// `config.dispute_bond` lamports. Claims are paused until settled.
+  pub fn dispute_resolution(ctx: Context<DisputeResolution>, proposed_outcome: u8) -> Result<()> {
+    let mut market = ctx.accounts.market.load_mut()?;
+    require!(
+      market.status == MarketStatus::Resolved as u8,
+      PredictionError::MarketNotResolved
+    );
+    require!(
+      Clock::get()?.unix_timestamp < market.dispute_deadline,
+      PredictionError::DisputeWindowClosed
+    );
+    require!(proposed_outcome <= 1, PredictionError::InvalidOutcome);
+    require!(
+      proposed_outcome as i8 != market.winning_outcome,
+      PredictionError::InvalidOutcome
+    );
+
+    let bond_amount = ctx.accounts.config.dispute_bond;
+    if bond_amount > 0 {
+      system_program::transfer(
+        CpiContext::new(
+          ctx.accounts.system_program.to_account_info(),
+          system_program::Transfer {
+            from: ctx.accounts.disputer.to_account_info(),
+            to: ctx.accounts.dispute.to_account_info(),
+          },
+        ),
+        bond_amount,
+      )?;
+    }
+
+    let dispute = &mut ctx.accounts.dispute;
+    dispute.market = ctx.accounts.market.key();
+    dispute.disputer = ctx.accounts.disputer.key();
+    dispute.proposed_outcome = proposed_outcome;
+    dispute.bond = bond_amount;
+    dispute.bump = ctx.bumps.dispute;
+
+    market.status = MarketStatus::Disputed as u8;
+
+    emit!(ResolutionDisputed {
+      market: dispute.market,
+      disputer: dispute.disputer,
+      proposed_outcome,
+      bond: bond_amount,
+    });
+
+    Ok(())
+  }
 
-    position.claimed = true;
+  /// Admin settles a dispute.
+  ///
+  /// Upheld: the outcome flips to the proposed one, the disputer gets their
+  /// bond back plus `slash_disputer_bps` of the creator bond, and the rest of
+  /// the creator bond is paid out pro-rata to winning positions on claim.
+  /// Rejected: the original outcome stands and the dispute bond goes to the treasury.
+  /// Either way the dispute and correction windows close.
+  pub fn settle_dispute(ctx: Context<SettleDispute>, uphold: bool) -> Result<()> {
+    let mut market = ctx.accounts.market.load_mut()?;
+    require!(
+      market.status == MarketStatus::Disputed as u8,
+      PredictionError::InvalidMarketStatus
+    );
+
+    let dispute = &ctx.accounts.dispute;
+    let dispute_destination = if uphold {
+      let proposed = dispute.proposed_outcome;
+      require!(proposed <= 1, PredictionError::InvalidOutcome);
+
+      // No claims ran during the window, so the vault snapshot still holds.
+      market.winning_outcome = proposed as i8;
+      market.payout_fraction = 0;
+      let total_winning_shares =
+        market.winning_shares(market.total_yes_shares, market.total_no_shares)?;
+      require!(total_winning_shares > 0, PredictionError::NoWinnings);
+      let vault_balance = market.resolved_vault_balance;
+      market.snapshot_resolution(vault_balance, total_winning_shares)?;
+
+      let bond = &mut ctx.accounts.creator_bond;
+      let slashed = bond.amount;
+      let disputer_cut = (slashed as u128)
+        .checked_mul(ctx.accounts.config.slash_disputer_bps as u128)
+        .ok_or(PredictionError::MathOverflow)?
+        .checked_div(BPS_DENOM as u128)
+        .ok_or(PredictionError::MathOverflow)? as u64;
+
+      let trader_cut = slashed
+        .checked_sub(disputer_cut)
+        .ok_or(PredictionError::MathOverflow)?;
+      bond.amount = 0;
+      bond.slashed = true;
+      bond.trader_pool = bond
+        .trader_pool
+        .checked_add(trader_cut)
+        .ok_or(PredictionError::MathOverflow)?;
+
+      if disputer_cut > 0 {
+        let bond_info = bond.to_account_info();
+        **bond_info.try_borrow_mut_lamports()? -= disputer_cut;
+        **ctx.accounts.disputer.try_borrow_mut_lamports()? += disputer_cut;
+      }
+
+      emit!(CreatorBondSlashed {
+        market: ctx.accounts.market.key(),
+        creator: bond.creator,
+        disputer: dispute.disputer,
+        disputer_amount: disputer_cut,
+        trader_amount: trader_cut,
+      });
+
+      ctx.accounts.disputer.to_account_info()
+    } else {
+      ctx.accounts.treasury.to_account_info()
+    };
+
+    // The admin's ruling is final: no further disputes or corrections, and
+    // claims open now
+    let now = Clock::get()?.unix_timestamp;
+    market.status = MarketStatus::Resolved as u8;
+    market.dispute_deadline = now;
+    market.correction_deadline = market.correction_deadline.min(now);
+
+    emit!(DisputeSettled {
+      market: ctx.accounts.market.key(),
+      disputer: dispute.disputer,
+      upheld: uphold,
+      winning_outcome: market.winning_outcome,
+      payout_rate: market.payout_rate,
+    });
+
+    ctx.accounts.dispute.close(dispute_destination)?;
+
+    Ok(())
+  }
+
+  /// Admin voids an open market as spam/ambiguous. The market is cancelled
+  /// and the creator's bond is forfeited to the treasury. Positions are
+  /// refunded at cost via `claim_winnings_v2`.
+  pub fn void_market(ctx: Context<VoidMarket>) -> Result<()> {
+    let mut market = ctx.accounts.market.load_mut()?;
+    require!(market.group == Pubkey::default(), PredictionError::GroupedMarket);
+    require!(
+      market.is_live(),
+      PredictionError::InvalidMarketStatus
+    );
+
+    market.cancel_at_cost(Clock::get()?.unix_timestamp)?;
+
+    sync_registry_entry(
+      ctx.accounts.registry_page.as_deref_mut(),
+      ctx.accounts.market.key(),
+      &market,
+    )?;
+
+    emit!(BondForfeited {
+      market: ctx.accounts.market.key(),
+      creator: ctx.accounts.creator_bond.creator,
+      recipient: ctx.accounts.treasury.key(),
+      amount: ctx.accounts.creator_bond.amount,
+    });
+
+    // Bond account is closed to the treasury by the `close` constraint.
+    Ok(())
+  }
+
+  /// Market creator withdraws their accrued share of trading fees.
+  pub fn withdraw_creator_fees(ctx: Context<WithdrawFees>) -> Result<()> {
+    let market_key = ctx.accounts.market.key();
+    let mut market = ctx.accounts.market.load_mut()?;
+    require_keys_eq!(
+      market.authority,
+      ctx.accounts.recipient.key(),
+      PredictionError::Unauthorized
+    );
+
+    let amount = market.creator_fees;
+    require!(amount > 0, PredictionError::ZeroAmount);
+    market.creator_fees = 0;
+    market.debit_vault(amount)?;
+
+    pay_fees(&ctx, market_key, market.vault_auth_bump, amount)?;
+
+    emit!(FeesWithdrawn {
+      market: market_key,
+      recipient: ctx.accounts.recipient.key(),
+      amount,
+      protocol: false,
+    });
+    Ok(())
+  }
+
+  /// Sweep the protocol's accrued share of trading fees to the treasury.
+  /// Permissionless: the destination is fixed by the config.
+  pub fn withdraw_protocol_fees(ctx: Context<WithdrawFees>) -> Result<()> {
+    let market_key = ctx.accounts.market.key();
+    let mut market = ctx.accounts.market.load_mut()?;
+    require_keys_eq!(
+      ctx.accounts.config.treasury,
+      ctx.accounts.recipient.key(),
+      PredictionError::Unauthorized
+    );
+
+    let amount = market.protocol_fees;
+    require!(amount > 0, PredictionError::ZeroAmount);
+    market.protocol_fees = 0;
+    market.debit_vault(amount)?;
+
+    pay_fees(&ctx, market_key, market.vault_auth_bump, amount)?;
+
+    emit!(FeesWithdrawn {
+      market: market_key,
+      recipient: ctx.accounts.recipient.key(),
+      amount,
+      protocol: true,
+    });
+    Ok(())
+  }
+
+  /// Sweep tokens sent straight to the vault (outside the program's own
+  /// transfers) to the treasury. Permissionless; they never back any payout.
+  pub fn skim_surplus(ctx: Context<WithdrawFees>) -> Result<()> {
+    let market_key = ctx.accounts.market.key();
+    let market = ctx.accounts.market.load()?;
+    require_keys_eq!(
+      ctx.accounts.config.treasury,
+      ctx.accounts.recipient.key(),
+      PredictionError::Unauthorized
+    );
+
+    let amount = ctx
+      .accounts
+      .vault
+      .amount
+      .checked_sub(market.vault_balance)
+      .ok_or(PredictionError::VaultShortfall)?;
+    require!(amount > 0, PredictionError::ZeroAmount);
+
+    pay_fees(&ctx, market_key, market.vault_auth_bump, amount)?;
+
+    emit!(SurplusSkimmed {
+      market: market_key,
+      recipient: ctx.accounts.recipient.key(),
+      amount,
+    });
+    Ok(())
+  }
+
+  /// Move the market's accrued insurance fees into the insurance vault for
+  /// its collateral mint. Permissionless; the vault is created on first use.
+  pub fn sweep_insurance_fees(ctx: Context<SweepInsuranceFees>) -> Result<()> {
+    let market_key = ctx.accounts.market.key();
+    let mut market = ctx.accounts.market.load_mut()?;
+
+    let amount = market.insurance_fees;
+    require!(amount > 0, PredictionError::ZeroAmount);
+    market.insurance_fees = 0;
+    market.debit_vault(amount)?;
+
+    let seeds: &[&[u8]] = &[
+      b"vault_auth_v2",
+      market_key.as_ref(),
+      &[market.vault_auth_bump],
+    ];
+
+    token_interface::transfer_checked(
+      CpiContext::new_with_signer(
+        ctx.accounts.token_program.to_account_info(),
+        TransferChecked {
+          from: ctx.accounts.vault.to_account_info(),
+          mint: ctx.accounts.collateral_mint.to_account_info(),
+          to: ctx.accounts.insurance_vault.to_account_info(),
+          authority: ctx.accounts.vault_authority.to_account_info(),
+        },
+        &[seeds],
+      ),
+      amount,
+      ctx.accounts.collateral_mint.decimals,
+    )?;
+
+    emit!(InsuranceFeesSwept {
+      market: market_key,
+      amount,
+    });
+    Ok(())
+  }
+
+  /// Designate the market's makers. Their `buy_shares` / `sell_shares` accrue
+  /// `rebate_bps` of the traded collateral, paid from the market's LP fees,
+  /// so a maker's effective fee can go negative. Makers dropped from the list
+  /// must have claimed their rebate first.
+  pub fn set_market_makers(
+    ctx: Context<SetMarketMakers>,
+    makers: Vec<Pubkey>,
+    rebate_bps: u16,
+  ) -> Result<()> {
+    require!(makers.len() <= MAX_MARKET_MAKERS, PredictionError::TooManyMarketMakers);
+    require!(rebate_bps as u64 <= MAX_FEE_BPS, PredictionError::FeeCapExceeded);
+    for (i, maker) in makers.iter().enumerate() {
+      require!(!makers[..i].contains(maker), PredictionError::TooManyMarketMakers);
+    }
+
+    let market_key = ctx.accounts.market.key();
+    let list = &mut ctx.accounts.market_makers;
+    for entry in &list.makers {
+      require!(
+        entry.accrued == 0 || makers.contains(&entry.maker),
+        PredictionError::MakerRebateOutstanding
+      );
+    }
+    let entries = makers
+      .iter()
+      .map(|&maker| MakerRebate {
+        maker,
+        accrued: list
+          .makers
+          .iter()
+          .find(|e| e.maker == maker)
+          .map_or(0, |e| e.accrued),
+      })
+      .collect();
+
+    list.market = market_key;
+    list.makers = entries;
+    list.rebate_bps = rebate_bps;
+    list.bump = ctx.bumps.market_makers;
+
+    emit!(MarketMakersSet {
+      market: market_key,
+      makers,
+      rebate_bps,
+    });
+    Ok(())
+  }
+
+  /// Pay a designated maker the rebate accrued so far.
+  pub fn claim_mm_rebate(ctx: Context<ClaimMmRebate>) -> Result<()> {
+    let market_key = ctx.accounts.market.key();
+    let mut market = ctx.accounts.market.load_mut()?;
+    let maker = ctx.accounts.maker.key();
+
+    let entry = ctx
+      .accounts
+      .market_makers
+      .makers
+      .iter_mut()
+      .find(|e| e.maker == maker)
+      .ok_or(PredictionError::NotMarketMaker)?;
+    let amount = entry.accrued;
+    require!(amount > 0, PredictionError::ZeroAmount);
+    entry.accrued = 0;
+    market.mm_rebates = market
+      .mm_rebates
+      .checked_sub(amount)
+      .ok_or(PredictionError::MathOverflow)?;
+    market.debit_vault(amount)?;
+
+    let seeds: &[&[u8]] = &[
+      b"vault_auth_v2",
+      market_key.as_ref(),
+      &[market.vault_auth_bump],
+    ];
+    pay_out_collateral(
+      &ctx.accounts.vault,
+      ctx.accounts.vault_authority.to_account_info(),
+      seeds,
+      &ctx.accounts.collateral_mint,
+      ctx.accounts.maker_collateral_ata.as_ref().map(|a| a.to_account_info()),
+      ctx.accounts.wsol_unwrap.as_ref().map(|a| a.to_account_info()),
+      ctx.accounts.maker.to_account_info(),
+      ctx.accounts.maker.to_account_info(),
+      ctx.accounts.token_program.to_account_info(),
+      ctx.accounts.system_program.to_account_info(),
+      amount,
+    )?;
+
+    emit!(MmRebateClaimed {
+      market: market_key,
+      maker,
+      amount,
+    });
+    Ok(())
+  }
+
+  /// Start on-chain statistics for a market. From then on every AMM trade
+  /// must pass the account; traders are counted from their first buy after
+  /// this. Permissionless (the payer funds the rent).
+  pub fn init_market_stats(ctx: Context<InitMarketStats>) -> Result<()> {
+    let mut market = ctx.accounts.market.load_mut()?;
+    require!(market.market_stats == 0, PredictionError::InvalidMarketStatus);
+    market.market_stats = 1;
+
+    let stats = &mut ctx.accounts.market_stats;
+    stats.market = ctx.accounts.market.key();
+    stats.hour = Clock::get()?.unix_timestamp / 3600;
+    stats.bump = ctx.bumps.market_stats;
+    Ok(())
+  }
+
+  /// Start keeping `interval`-second OHLC candles of the market's YES
+  /// probability. From then on every AMM trade must pass the account.
+  /// Market authority only.
+  pub fn init_price_candles(ctx: Context<InitPriceCandles>, interval: i64) -> Result<()> {
+    let mut market = ctx.accounts.market.load_mut()?;
+    require!(
+      ctx.accounts.authority.key() == market.authority,
+      PredictionError::Unauthorized
+    );
+    require!(market.price_candles == 0, PredictionError::InvalidMarketStatus);
+    require!(
+      (MIN_CANDLE_INTERVAL_SECS..=MAX_CANDLE_INTERVAL_SECS).contains(&interval),
+      PredictionError::InvalidCandleInterval
+    );
+    market.price_candles = 1;
+
+    let mut candles = ctx.accounts.price_candles.load_init()?;
+    candles.market = ctx.accounts.market.key();
+    candles.interval = interval;
+    candles.bump = ctx.bumps.price_candles;
+    Ok(())
+  }
+
+  /// Record the market's current YES probability in its candles without a
+  /// trade, so quiet intervals still get a candle. Permissionless.
+  pub fn snapshot_price(ctx: Context<SnapshotPrice>) -> Result<()> {
+    let market = ctx.accounts.market.load()?;
+    require!(market.is_live(), PredictionError::InvalidMarketStatus);
+    ctx
+      .accounts
+      .price_candles
+      .load_mut()?
+      .record(market.implied_probability(0)?, Clock::get()?.unix_timestamp);
+    Ok(())
+  }
+
+  /// Publish the market's probability in a small fixed-layout `PriceFeed`
+  /// account for other programs to read. From then on every AMM trade must
+  /// pass the account. Permissionless (the payer funds the rent).
+  pub fn init_price_feed(ctx: Context<InitPriceFeed>) -> Result<()> {
+    let mut market = ctx.accounts.market.load_mut()?;
+    require!(market.price_feed == 0, PredictionError::InvalidMarketStatus);
+    market.price_feed = 1;
+
+    let mut feed = ctx.accounts.price_feed.load_init()?;
+    feed.version = PRICE_FEED_VERSION;
+    feed.bump = ctx.bumps.price_feed;
+    feed.market = ctx.accounts.market.key();
+    feed.refresh(&market, Clock::get()?.unix_timestamp)
+  }
+
+  /// Bring a price feed up to date, e.g. after the market stops trading or
+  /// resolves. Permissionless.
+  pub fn update_price_feed(ctx: Context<UpdatePriceFeed>) -> Result<()> {
+    let market = ctx.accounts.market.load()?;
+    ctx
+      .accounts
+      .price_feed
+      .load_mut()?
+      .refresh(&market, Clock::get()?.unix_timestamp)
+  }
+
+  /// Start recording the market's last MAX_TAPE_TRADES AMM trades. From
+  /// then on every AMM trade must pass the account. Permissionless (the
+  /// payer funds the rent).
+  pub fn init_trade_tape(ctx: Context<InitTradeTape>) -> Result<()> {
+    let mut market = ctx.accounts.market.load_mut()?;
+    require!(market.trade_tape == 0, PredictionError::InvalidMarketStatus);
+    market.trade_tape = 1;
+
+    let mut tape = ctx.accounts.trade_tape.load_init()?;
+    tape.market = ctx.accounts.market.key();
+    tape.bump = ctx.bumps.trade_tape;
+    Ok(())
+  }
+
+  /// Create the protocol-wide stats account. Admin only.
+  pub fn init_protocol_stats(ctx: Context<InitProtocolStats>) -> Result<()> {
+    ctx.accounts.protocol_stats.bump = ctx.bumps.protocol_stats;
+    Ok(())
+  }
+
+  /// Bring the protocol TVL up to date with a market's vault balance, for
+  /// changes made by instructions that don't take the stats account.
+  /// Permissionless.
+  pub fn sync_protocol_stats(ctx: Context<SyncProtocolStats>) -> Result<()> {
+    let mut market = ctx.accounts.market.load_mut()?;
+    ctx.accounts.protocol_stats.sync_tvl(&mut market)
+  }
+
+  /// Create the caller's stats account. Trades made with it passed to
+  /// `buy_shares` / `sell_shares` earn points.
+  pub fn init_user_stats(ctx: Context<InitUserStats>) -> Result<()> {
+    let stats = &mut ctx.accounts.user_stats;
+    stats.owner = ctx.accounts.user.key();
+    stats.epoch = points_epoch(Clock::get()?.unix_timestamp);
+    stats.bump = ctx.bumps.user_stats;
+    Ok(())
+  }
+
+  /// Close out a finished points epoch for a user who hasn't traded since.
+  /// Permissionless.
+  pub fn checkpoint_points(ctx: Context<CheckpointPoints>) -> Result<()> {
+    ctx.accounts.user_stats.roll_epoch(Clock::get()?.unix_timestamp);
+    Ok(())
+  }
+
+  /// Realize a user's open cost basis in a settled market at its settlement
+  /// value, e.g. for losing sides that never claim. Permissionless.
+  pub fn realize_settled_pnl(ctx: Context<RealizeSettledPnl>) -> Result<()> {
+    let market = ctx.accounts.market.load()?;
+    require!(market.is_settled(), PredictionError::MarketNotResolved);
+    ctx
+      .accounts
+      .user_stats
+      .record_settlement(ctx.accounts.market.key(), &market)
+  }
+
+  /// Open a trading competition over `markets` between `start` and `end`,
+  /// escrowing `prizes` (one per rank, best first) from the admin.
+  pub fn create_competition(ctx: Context<CreateCompetition>, args: CreateCompetitionArgs) -> Result<()> {
+    require!(
+      args.start < args.end
+        && !args.markets.is_empty()
+        && args.markets.len() <= MAX_COMPETITION_MARKETS
+        && !args.prizes.is_empty()
+        && args.prizes.len() <= MAX_COMPETITION_WINNERS,
+      PredictionError::InvalidCompetition
+    );
+    let total = args
+      .prizes
+      .iter()
+      .try_fold(0u64, |acc, &p| acc.checked_add(p))
+      .ok_or(PredictionError::MathOverflow)?;
+
+    token_interface::transfer_checked(
+      CpiContext::new(
+        ctx.accounts.token_program.to_account_info(),
+        TransferChecked {
+          from: ctx.accounts.admin_prize_ata.to_account_info(),
+          mint: ctx.accounts.prize_mint.to_account_info(),
+          to: ctx.accounts.prize_vault.to_account_info(),
+          authority: ctx.accounts.admin.to_account_info(),
+        },
+      ),
+      total,
+      ctx.accounts.prize_mint.decimals,
+    )?;
+
+    let competition = &mut ctx.accounts.competition;
+    competition.id = args.id;
+    competition.prize_mint = ctx.accounts.prize_mint.key();
+    competition.start = args.start;
+    competition.end = args.end;
+    competition.markets = args.markets;
+    competition.prizes = args.prizes;
+    competition.leaders = Vec::new();
+    competition.finalized = false;
+    competition.bump = ctx.bumps.competition;
+
+    emit!(CompetitionCreated {
+      competition: competition.key(),
+      start: args.start,
+      end: args.end,
+      prize_total: total,
+    });
+    Ok(())
+  }
+
+  /// Enter a competition before it ends. Pass the entry to `buy_shares` /
+  /// `sell_shares` in its markets: PnL is realized on sells, against the
+  /// cost of shares bought with the entry during the competition.
+  pub fn join_competition(ctx: Context<JoinCompetition>) -> Result<()> {
+    let competition = &ctx.accounts.competition;
+    require!(
+      Clock::get()?.unix_timestamp < competition.end,
+      PredictionError::InvalidCompetition
+    );
+
+    let entry = &mut ctx.accounts.competition_entry;
+    entry.competition = competition.key();
+    entry.user = ctx.accounts.user.key();
+    entry.start = competition.start;
+    entry.end = competition.end;
+    entry.realized_pnl = 0;
+    entry.lots = competition
+      .markets
+      .iter()
+      .map(|&market| CostLot {
+        market,
+        ..Default::default()
+      })
+      .collect();
+    entry.submitted = false;
+    entry.bump = ctx.bumps.competition_entry;
+    Ok(())
+  }
+
+  /// After the competition ends, rank an entry on its realized PnL; it
+  /// makes the leaderboard if it beats the last ranked entry. Permissionless,
+  /// for COMPETITION_SUBMIT_SECS after the end.
+  pub fn submit_competition_entry(ctx: Context<SubmitCompetitionEntry>) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    let competition = &mut ctx.accounts.competition;
+    let entry = &mut ctx.accounts.competition_entry;
+    require!(now >= competition.end, PredictionError::CompetitionNotEnded);
+    require!(
+      now < competition.end.saturating_add(COMPETITION_SUBMIT_SECS) && !competition.finalized,
+      PredictionError::CompetitionSubmissionsClosed
+    );
+    require!(!entry.submitted, PredictionError::EntryAlreadySubmitted);
+    entry.submitted = true;
+
+    let rank = competition
+      .leaders
+      .iter()
+      .position(|l| entry.realized_pnl > l.pnl)
+      .unwrap_or(competition.leaders.len());
+    let winners = competition.prizes.len();
+    if rank < winners {
+      competition.leaders.insert(
+        rank,
+        CompetitionLeader {
+          user: entry.user,
+          pnl: entry.realized_pnl,
+          claimed: false,
+        },
+      );
+      competition.leaders.truncate(winners);
+    }
+
+    emit!(CompetitionEntrySubmitted {
+      competition: competition.key(),
+      user: entry.user,
+      realized_pnl: entry.realized_pnl,
+    });
+    Ok(())
+  }
+
+  /// Lock the leaderboard once submissions close and return prizes for
+  /// unfilled ranks to the treasury. Permissionless.
+  pub fn finalize_competition(ctx: Context<FinalizeCompetition>) -> Result<()> {
+    let competition_key = ctx.accounts.competition.key();
+    let competition = &mut ctx.accounts.competition;
+    require!(
+      Clock::get()?.unix_timestamp >= competition.end.saturating_add(COMPETITION_SUBMIT_SECS),
+      PredictionError::CompetitionNotEnded
+    );
+    require!(!competition.finalized, PredictionError::InvalidCompetition);
+    competition.finalized = true;
+
+    let unallocated: u64 = competition.prizes[competition.leaders.len()..].iter().sum();
+    if unallocated > 0 {
+      let id = competition.id.to_le_bytes();
+      let seeds: &[&[u8]] = &[b"competition", id.as_ref(), &[competition.bump]];
+      token_interface::transfer_checked(
+        CpiContext::new_with_signer(
+          ctx.accounts.token_program.to_account_info(),
+          TransferChecked {
+            from: ctx.accounts.prize_vault.to_account_info(),
+            mint: ctx.accounts.prize_mint.to_account_info(),
+            to: ctx.accounts.treasury_prize_ata.to_account_info(),
+            authority: competition.to_account_info(),
+          },
+          &[seeds],
+        ),
+        unallocated,
+        ctx.accounts.prize_mint.decimals,
+      )?;
+    }
+
+    emit!(CompetitionFinalized {
+      competition: competition_key,
+      winners: competition.leaders.len() as u8,
+    });
+    Ok(())
+  }
+
+  /// Pay a ranked user their prize after finalization.
+  pub fn claim_competition_prize(ctx: Context<ClaimCompetitionPrize>) -> Result<()> {
+    let competition_key = ctx.accounts.competition.key();
+    let competition = &mut ctx.accounts.competition;
+    require!(competition.finalized, PredictionError::CompetitionNotEnded);
+    let user = ctx.accounts.user.key();
+    let rank = competition
+      .leaders
+      .iter()
+      .position(|l| l.user == user)
+      .ok_or(PredictionError::NoWinnings)?;
+    require!(!competition.leaders[rank].claimed, PredictionError::AlreadyClaimed);
+    competition.leaders[rank].claimed = true;
+    let amount = competition.prizes[rank];
+
+    let id = competition.id.to_le_bytes();
+    let seeds: &[&[u8]] = &[b"competition", id.as_ref(), &[competition.bump]];
+    token_interface::transfer_checked(
+      CpiContext::new_with_signer(
+        ctx.accounts.token_program.to_account_info(),
+        TransferChecked {
+          from: ctx.accounts.prize_vault.to_account_info(),
+          mint: ctx.accounts.prize_mint.to_account_info(),
+          to: ctx.accounts.user_prize_ata.to_account_info(),
+          authority: competition.to_account_info(),
+        },
+        &[seeds],
+      ),
+      amount,
+      ctx.accounts.prize_mint.decimals,
+    )?;
+
+    emit!(CompetitionPrizeClaimed {
+      competition: competition_key,
+      user,
+      rank: rank as u8,
+      amount,
+    });
+    Ok(())
+  }
+
+  /// Fund liquidity mining on a market: `rate` reward tokens per second
+  /// between `start` and `end` (no later than the market's end), streamed to
+  /// LPs pro-rata to their shares over time. The full budget is deposited
+  /// upfront by the market authority.
+  pub fn init_lp_rewards(ctx: Context<InitLpRewards>, rate: u64, start: i64, end: i64) -> Result<()> {
+    let market_key = ctx.accounts.market.key();
+    let mut market = ctx.accounts.market.load_mut()?;
+    let now = Clock::get()?.unix_timestamp;
+    require!(market.is_live(), PredictionError::InvalidMarketStatus);
+    require!(market.total_lp_shares > 0, PredictionError::LiquidityNotEnabled);
+    require!(
+      rate > 0 && start >= now && start < end && end <= market.end_time,
+      PredictionError::InvalidEmissions
+    );
+
+    let budget = ((end - start) as u64)
+      .checked_mul(rate)
+      .ok_or(PredictionError::MathOverflow)?;
+    token_interface::transfer_checked(
+      CpiContext::new(
+        ctx.accounts.token_program.to_account_info(),
+        TransferChecked {
+          from: ctx.accounts.funder_reward_ata.to_account_info(),
+          mint: ctx.accounts.reward_mint.to_account_info(),
+          to: ctx.accounts.reward_vault.to_account_info(),
+          authority: ctx.accounts.authority.to_account_info(),
+        },
+      ),
+      budget,
+      ctx.accounts.reward_mint.decimals,
+    )?;
+    // Fee-on-transfer reward mints would leave the stream underfunded
+    ctx.accounts.reward_vault.reload()?;
+    require!(
+      ctx.accounts.reward_vault.amount >= budget,
+      PredictionError::InvalidEmissions
+    );
+
+    let rewards = &mut ctx.accounts.lp_rewards;
+    rewards.market = market_key;
+    rewards.reward_mint = ctx.accounts.reward_mint.key();
+    rewards.rate = rate;
+    rewards.start = start;
+    rewards.end = end;
+    rewards.acc_per_share = 0;
+    rewards.last_update = now;
+    rewards.bump = ctx.bumps.lp_rewards;
+    market.lp_rewards = 1;
+
+    emit!(LpRewardsInitialized {
+      market: market_key,
+      reward_mint: rewards.reward_mint,
+      rate,
+      start,
+      end,
+    });
+    Ok(())
+  }
+
+  /// Pay an LP the emissions their position has earned so far.
+  pub fn claim_lp_rewards(ctx: Context<ClaimLpRewards>) -> Result<()> {
+    let market_key = ctx.accounts.market.key();
+    let market = ctx.accounts.market.load()?;
+    let rewards = &mut ctx.accounts.lp_rewards;
+    let lp_position = &mut ctx.accounts.lp_position;
+
+    rewards.update(market.total_lp_shares, Clock::get()?.unix_timestamp)?;
+    rewards.settle(lp_position)?;
+    let amount = lp_position.rewards_owed;
+    require!(amount > 0, PredictionError::ZeroAmount);
+    lp_position.rewards_owed = 0;
+
+    let seeds: &[&[u8]] = &[
+      b"vault_auth_v2",
+      market_key.as_ref(),
+      &[market.vault_auth_bump],
+    ];
+    token_interface::transfer_checked(
+      CpiContext::new_with_signer(
+        ctx.accounts.token_program.to_account_info(),
+        TransferChecked {
+          from: ctx.accounts.reward_vault.to_account_info(),
+          mint: ctx.accounts.reward_mint.to_account_info(),
+          to: ctx.accounts.user_reward_ata.to_account_info(),
+          authority: ctx.accounts.vault_authority.to_account_info(),
+        },
+        &[seeds],
+      ),
+      amount,
+      ctx.accounts.reward_mint.decimals,
+    )?;
+
+    emit!(LpRewardsClaimed {
+      market: market_key,
+      provider: ctx.accounts.user.key(),
+      amount,
+    });
+    Ok(())
+  }
+
+  /// Admin tops up a live market's vault from the insurance fund, up to its
+  /// current shortfall against worst-case liabilities.
+  pub fn insurance_top_up(ctx: Context<InsuranceTopUp>, amount: u64) -> Result<()> {
+    let market_key = ctx.accounts.market.key();
+    let mut market = ctx.accounts.market.load_mut()?;
+    require!(market.is_live(), PredictionError::InvalidMarketStatus);
+    require!(amount > 0, PredictionError::ZeroAmount);
+
+    let shortfall = market
+      .worst_case_liabilities()?
+      .saturating_sub(market.total_assets()?);
+    require!(shortfall > 0, PredictionError::MarketSolvent);
+    let amount = amount.min(shortfall);
+
+    let seeds: &[&[u8]] = &[b"insurance_auth", &[ctx.bumps.insurance_authority]];
+
+    token_interface::transfer_checked(
+      CpiContext::new_with_signer(
+        ctx.accounts.token_program.to_account_info(),
+        TransferChecked {
+          from: ctx.accounts.insurance_vault.to_account_info(),
+          mint: ctx.accounts.collateral_mint.to_account_info(),
+          to: ctx.accounts.vault.to_account_info(),
+          authority: ctx.accounts.insurance_authority.to_account_info(),
+        },
+        &[seeds],
+      ),
+      amount,
+      ctx.accounts.collateral_mint.decimals,
+    )?;
+    market.credit_vault(amount)?;
+
+    emit!(InsuranceToppedUp {
+      market: market_key,
+      amount,
+    });
+    Ok(())
+  }
+
+  /// Market authority opts the vault in or out of idle-collateral yield.
+  /// Opting out requires everything to be withdrawn first.
+  pub fn set_vault_yield(ctx: Context<UpdateMarketMetadata>, enabled: bool) -> Result<()> {
+    let mut market = ctx.accounts.market.load_mut()?;
+    require_keys_eq!(
+      market.authority,
+      ctx.accounts.authority.key(),
+      PredictionError::Unauthorized
+    );
+    require!(
+      enabled || market.yield_deployed == 0,
+      PredictionError::YieldDeployed
+    );
+    market.yield_enabled = enabled as u8;
+    Ok(())
+  }
+
+  /// Admin deposits `amount` of idle vault collateral into a reserve of the
+  /// configured lending program. The reserve's collateral tokens are minted to
+  /// the market's `yield_receipt` account; the first deposit records the
+  /// program, reserve and receipt in `YieldPosition`, and later deposits and
+  /// the withdrawal must use the same ones.
+  pub fn deploy_idle_collateral(ctx: Context<DeployIdleCollateral>, amount: u64) -> Result<()> {
+    let market_key = ctx.accounts.market.key();
+    let vault_auth_bump = {
+      let mut market = ctx.accounts.market.load_mut()?;
+      require!(market.yield_enabled == 1, PredictionError::YieldNotEnabled);
+      require!(market.is_live(), PredictionError::InvalidMarketStatus);
+      require!(amount > 0, PredictionError::ZeroAmount);
+
+      market.debit_vault(amount)?;
+      market.yield_deployed = market
+        .yield_deployed
+        .checked_add(amount)
+        .ok_or(PredictionError::MathOverflow)?;
+      market.vault_auth_bump
+    };
+
+    let position = &mut ctx.accounts.yield_position;
+    if position.market == Pubkey::default() {
+      position.market = market_key;
+      position.lending_program = ctx.accounts.lending_program.key();
+      position.reserve = ctx.accounts.reserve.key();
+      position.receipt = ctx.accounts.yield_receipt.key();
+      position.bump = ctx.bumps.yield_position;
+    }
+    require!(
+      position.lending_program == ctx.accounts.lending_program.key()
+        && position.reserve == ctx.accounts.reserve.key(),
+      PredictionError::YieldReserveMismatch
+    );
+
+    let vault_before = ctx.accounts.vault.amount;
+    let receipt_before = ctx.accounts.yield_receipt.amount;
+    let seeds: &[&[u8]] = &[b"vault_auth_v2", market_key.as_ref(), &[vault_auth_bump]];
+    invoke_lending_program(
+      LendingOp::Deposit,
+      amount,
+      LendingAccounts {
+        program: ctx.accounts.lending_program.to_account_info(),
+        reserve: ctx.accounts.reserve.to_account_info(),
+        lending_market: ctx.accounts.lending_market.to_account_info(),
+        lending_market_authority: ctx.accounts.lending_market_authority.to_account_info(),
+        liquidity_mint: ctx.accounts.collateral_mint.to_account_info(),
+        liquidity_supply: ctx.accounts.reserve_liquidity_supply.to_account_info(),
+        collateral_mint: ctx.accounts.reserve_collateral_mint.to_account_info(),
+        vault: ctx.accounts.vault.to_account_info(),
+        receipt: ctx.accounts.yield_receipt.to_account_info(),
+        vault_authority: ctx.accounts.vault_authority.to_account_info(),
+        token_program: ctx.accounts.token_program.to_account_info(),
+        receipt_token_program: ctx.accounts.receipt_token_program.to_account_info(),
+        instructions: ctx.accounts.instructions.to_account_info(),
+      },
+      seeds,
+    )?;
+    ctx.accounts.vault.reload()?;
+    ctx.accounts.yield_receipt.reload()?;
+
+    let sent = vault_before
+      .checked_sub(ctx.accounts.vault.amount)
+      .ok_or(PredictionError::YieldTransferMismatch)?;
+    require!(sent == amount, PredictionError::YieldTransferMismatch);
+    let minted = ctx
+      .accounts
+      .yield_receipt
+      .amount
+      .checked_sub(receipt_before)
+      .ok_or(PredictionError::YieldTransferMismatch)?;
+    require!(minted > 0, PredictionError::YieldTransferMismatch);
+
+    emit!(IdleCollateralDeployed {
+      market: market_key,
+      reserve: ctx.accounts.reserve.key(),
+      amount,
+      receipt_minted: minted,
+    });
+    Ok(())
+  }
+
+  /// Admin redeems the whole `yield_receipt` balance from the recorded
+  /// reserve back into the vault. Anything above the deployed principal is
+  /// accrued as trading fees (LP / creator / protocol / insurance split). A
+  /// loss reduces backing and is recorded in `YieldPosition::total_shortfall`
+  /// and the event.
+  pub fn withdraw_idle_collateral(ctx: Context<WithdrawIdleCollateral>) -> Result<()> {
+    let market_key = ctx.accounts.market.key();
+    let (principal, vault_auth_bump) = {
+      let market = ctx.accounts.market.load()?;
+      (market.yield_deployed, market.vault_auth_bump)
+    };
+    require!(principal > 0, PredictionError::ZeroAmount);
+
+    let redeemed = ctx.accounts.yield_receipt.amount;
+    require!(redeemed > 0, PredictionError::YieldTransferMismatch);
+
+    let vault_before = ctx.accounts.vault.amount;
+    let seeds: &[&[u8]] = &[b"vault_auth_v2", market_key.as_ref(), &[vault_auth_bump]];
+    invoke_lending_program(
+      LendingOp::Redeem,
+      redeemed,
+      LendingAccounts {
+        program: ctx.accounts.lending_program.to_account_info(),
+        reserve: ctx.accounts.reserve.to_account_info(),
+        lending_market: ctx.accounts.lending_market.to_account_info(),
+        lending_market_authority: ctx.accounts.lending_market_authority.to_account_info(),
+        liquidity_mint: ctx.accounts.collateral_mint.to_account_info(),
+        liquidity_supply: ctx.accounts.reserve_liquidity_supply.to_account_info(),
+        collateral_mint: ctx.accounts.reserve_collateral_mint.to_account_info(),
+        vault: ctx.accounts.vault.to_account_info(),
+        receipt: ctx.accounts.yield_receipt.to_account_info(),
+        vault_authority: ctx.accounts.vault_authority.to_account_info(),
+        token_program: ctx.accounts.token_program.to_account_info(),
+        receipt_token_program: ctx.accounts.receipt_token_program.to_account_info(),
+        instructions: ctx.accounts.instructions.to_account_info(),
+      },
+      seeds,
+    )?;
+    ctx.accounts.vault.reload()?;
+    ctx.accounts.yield_receipt.reload()?;
+
+    // Every receipt token must be burned, so the principal can't be written
+    // off while the reserve still owes it
+    require!(
+      ctx.accounts.yield_receipt.amount == 0,
+      PredictionError::YieldTransferMismatch
+    );
+    let returned = ctx
+      .accounts
+      .vault
+      .amount
+      .checked_sub(vault_before)
+      .ok_or(PredictionError::YieldTransferMismatch)?;
+
+    let shortfall = principal.saturating_sub(returned);
+    let position = &mut ctx.accounts.yield_position;
+    position.total_shortfall = position
+      .total_shortfall
+      .checked_add(shortfall)
+      .ok_or(PredictionError::MathOverflow)?;
+
+    let mut market = ctx.accounts.market.load_mut()?;
+    market.yield_deployed = 0;
+    market.credit_vault(returned)?;
+    let yield_earned = returned.saturating_sub(principal);
+    market.accrue_fee(yield_earned)?;
+
+    emit!(IdleCollateralWithdrawn {
+      market: market_key,
+      principal,
+      returned,
+      shortfall,
+    });
+    Ok(())
+  }
+
+  /// Mark the market's collateral as a liquid-staking token (jitoSOL, bSOL, …)
+  /// backed by an SPL stake pool. Only before the first trade. Share
+  /// accounting stays in token units; the pool's SOL exchange rate is
+  /// reported in events so token payouts aren't mistaken for SOL amounts.
+  pub fn set_lst_stake_pool(ctx: Context<SetLstStakePool>) -> Result<()> {
+    let market_key = ctx.accounts.market.key();
+    let mut market = ctx.accounts.market.load_mut()?;
+    require_keys_eq!(
+      market.authority,
+      ctx.accounts.authority.key(),
+      PredictionError::Unauthorized
+    );
+    require!(market.trade_count == 0, PredictionError::MarketAlreadyTraded);
+
+    let rate = read_stake_pool_rate(&ctx.accounts.stake_pool, &market.collateral_mint)?;
+    market.lst_stake_pool = ctx.accounts.stake_pool.key();
+
+    emit!(LstRateObserved {
+      market: market_key,
+      stake_pool: market.lst_stake_pool,
+      rate,
+    });
+    Ok(())
+  }
+
+  /// Permissionless: emit the current LST exchange rate for an LST market.
+  pub fn record_lst_rate(ctx: Context<RecordLstRate>) -> Result<()> {
+    let market = ctx.accounts.market.load()?;
+    let rate = read_stake_pool_rate(&ctx.accounts.stake_pool, &market.collateral_mint)?;
+
+    emit!(LstRateObserved {
+      market: ctx.accounts.market.key(),
+      stake_pool: market.lst_stake_pool,
+      rate,
+    });
+    Ok(())
+  }
+
+  /// Deposit collateral into the caller's trading account for this mint.
+  /// The tokens sit in a per-mint custody account and can then be traded
+  /// across markets without touching the user's wallet.
+  pub fn deposit_to_trading_account(ctx: Context<TradingAccountDeposit>, amount: u64) -> Result<()> {
+    require!(amount > 0, PredictionError::ZeroAmount);
+
+    let received = deposit_collateral(
+      ctx.accounts.user.to_account_info(),
+      ctx.accounts.user_collateral_ata.as_ref().map(|a| a.to_account_info()),
+      &mut ctx.accounts.custody,
+      &ctx.accounts.collateral_mint,
+      ctx.accounts.token_program.to_account_info(),
+      ctx.accounts.system_program.to_account_info(),
+      amount,
+    )?;
+
+    let account = &mut ctx.accounts.trading_account;
+    if account.owner == Pubkey::default() {
+      account.owner = ctx.accounts.user.key();
+      account.collateral_mint = ctx.accounts.collateral_mint.key();
+      account.bump = ctx.bumps.trading_account;
+    }
+    account.balance = account
+      .balance
+      .checked_add(received)
+      .ok_or(PredictionError::MathOverflow)?;
+
+    Ok(())
+  }
+
+  /// Withdraw collateral from the caller's trading account to their wallet.
+  pub fn withdraw_from_trading_account(ctx: Context<TradingAccountWithdraw>, amount: u64) -> Result<()> {
+    require!(amount > 0, PredictionError::ZeroAmount);
+
+    let account = &mut ctx.accounts.trading_account;
+    account.balance = account
+      .balance
+      .checked_sub(amount)
+      .ok_or(PredictionError::InsufficientBalance)?;
+
+    let seeds: &[&[u8]] = &[b"trading_custody_auth", &[ctx.bumps.custody_authority]];
+
+    pay_out_collateral(
+      &ctx.accounts.custody,
+      ctx.accounts.custody_authority.to_account_info(),
+      seeds,
+      &ctx.accounts.collateral_mint,
+      ctx.accounts.user_collateral_ata.as_ref().map(|a| a.to_account_info()),
+      ctx.accounts.wsol_unwrap.as_ref().map(|a| a.to_account_info()),
+      ctx.accounts.user.to_account_info(),
+      ctx.accounts.payer.to_account_info(),
+      ctx.accounts.token_program.to_account_info(),
+      ctx.accounts.system_program.to_account_info(),
+      amount,
+    )
+  }
+
+  /// `buy_shares` paid from the caller's trading account balance.
+  pub fn buy_from_trading_account(
+    ctx: Context<TradeFromAccount>,
+    outcome_index: u8,
+    collateral_in: u64,
+    min_shares_out: u64,
+  ) -> Result<()> {
+    let market_key = ctx.accounts.market.key();
+    let mut market = ctx.accounts.market.load_mut()?;
+
+    let now = Clock::get()?.unix_timestamp;
+    check_tradable(&mut market, now)?;
+    require!(outcome_index <= 1, PredictionError::InvalidOutcome);
+    require!(collateral_in > 0, PredictionError::ZeroAmount);
+
+    let received = fund_from_trading_account(
+      &mut market,
+      &mut ctx.accounts.trading_account,
+      &ctx.accounts.custody,
+      ctx.accounts.custody_authority.to_account_info(),
+      ctx.bumps.custody_authority,
+      &mut ctx.accounts.vault,
+      &ctx.accounts.collateral_mint,
+      ctx.accounts.token_program.to_account_info(),
+      collateral_in,
+    )?;
+
+    let position = &mut ctx.accounts.position;
+    let new_trader = position.owner == Pubkey::default();
+    init_or_check_position(
+      position,
+      market_key,
+      ctx.accounts.user.key(),
+      ctx.bumps.position,
+    )?;
+
+    let mut trade = TradeAccounts {
+      config: &ctx.accounts.config,
+      trader: ctx.accounts.user.key(),
+      new_trader,
+      transient: false,
+      allowlist_entry: ctx.accounts.allowlist_entry.as_deref(),
+      permit_gate: ctx.accounts.permit_gate.as_deref(),
+      instructions: ctx.accounts.instructions.as_ref(),
+      token_gate: ctx.accounts.token_gate.as_deref(),
+      gate_token_account: ctx.accounts.gate_token_account.as_deref(),
+      market_makers: ctx.accounts.market_makers.as_deref_mut(),
+      user_stats: ctx.accounts.user_stats.as_deref_mut(),
+      competition_entry: ctx.accounts.competition_entry.as_deref_mut(),
+      market_stats: ctx.accounts.market_stats.as_deref_mut(),
+      protocol_stats: ctx.accounts.protocol_stats.as_deref_mut(),
+      price_candles: ctx.accounts.price_candles.as_ref(),
+      trade_tape: ctx.accounts.trade_tape.as_ref(),
+      price_feed: ctx.accounts.price_feed.as_ref(),
+    };
+    execute_buy(
+      &mut market,
+      market_key,
+      position,
+      outcome_index,
+      received,
+      min_shares_out,
+      now,
+      &mut trade,
+    )?;
+
+    Ok(())
+  }
+
+  /// `sell_shares` with the proceeds credited to the caller's trading account.
+  pub fn sell_to_trading_account(
+    ctx: Context<TradeFromAccount>,
+    outcome_index: u8,
+    shares_in: u64,
+    min_collateral_out: u64,
+  ) -> Result<()> {
+    let market_key = ctx.accounts.market.key();
+    let mut market = ctx.accounts.market.load_mut()?;
+    let position = &mut ctx.accounts.position;
+
+    let now = Clock::get()?.unix_timestamp;
+    check_tradable(&mut market, now)?;
+    require!(
+      position.owner == ctx.accounts.user.key(),
+      PredictionError::PositionOwnerMismatch
+    );
+
+    let mut trade = TradeAccounts {
+      config: &ctx.accounts.config,
+      trader: ctx.accounts.user.key(),
+      new_trader: false,
+      transient: false,
+      allowlist_entry: ctx.accounts.allowlist_entry.as_deref(),
+      permit_gate: ctx.accounts.permit_gate.as_deref(),
+      instructions: ctx.accounts.instructions.as_ref(),
+      token_gate: ctx.accounts.token_gate.as_deref(),
+      gate_token_account: ctx.accounts.gate_token_account.as_deref(),
+      market_makers: ctx.accounts.market_makers.as_deref_mut(),
+      user_stats: ctx.accounts.user_stats.as_deref_mut(),
+      competition_entry: ctx.accounts.competition_entry.as_deref_mut(),
+      market_stats: ctx.accounts.market_stats.as_deref_mut(),
+      protocol_stats: ctx.accounts.protocol_stats.as_deref_mut(),
+      price_candles: ctx.accounts.price_candles.as_ref(),
+      trade_tape: ctx.accounts.trade_tape.as_ref(),
+      price_feed: ctx.accounts.price_feed.as_ref(),
+    };
+    let net_out = execute_sell(
+      &mut market,
+      market_key,
+      position,
+      outcome_index,
+      shares_in,
+      min_collateral_out,
+      now,
+      &mut trade,
+    )?;
+    credit_trading_account(
+      &mut market,
+      market_key,
+      &mut ctx.accounts.trading_account,
+      &ctx.accounts.vault,
+      ctx.accounts.vault_authority.to_account_info(),
+      &mut ctx.accounts.custody,
+      &ctx.accounts.collateral_mint,
+      ctx.accounts.token_program.to_account_info(),
+      net_out,
+    )
+  }
+
+  /// Let `session_key` trade from the owner's trading accounts until
+  /// `expiry`, spending at most `spend_cap` on buys. Sessions can't
+  /// withdraw, so a leaked key can only trade.
+  pub fn create_session(
+    ctx: Context<CreateSession>,
+    session_key: Pubkey,
+    expiry: i64,
+    spend_cap: u64,
+  ) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    require!(
+      expiry > now && expiry - now <= MAX_SESSION_SECS,
+      PredictionError::InvalidSessionExpiry
+    );
+
+    let session = &mut ctx.accounts.session;
+    session.owner = ctx.accounts.owner.key();
+    session.session_key = session_key;
+    session.expiry = expiry;
+    session.spend_cap = spend_cap;
+    session.spent = 0;
+    session.bump = ctx.bumps.session;
+
+    emit!(SessionCreated {
+      owner: session.owner,
+      session_key,
+      expiry,
+      spend_cap,
+    });
+    Ok(())
+  }
+
+  /// End a session early, refunding its rent to the owner.
+  pub fn revoke_session(ctx: Context<RevokeSession>) -> Result<()> {
+    emit!(SessionRevoked {
+      owner: ctx.accounts.owner.key(),
+      session_key: ctx.accounts.session.session_key,
+    });
+    Ok(())
+  }
+
+  /// `buy_from_trading_account` signed by a session key of the owner.
+  pub fn session_buy(
+    ctx: Context<SessionTrade>,
+    outcome_index: u8,
+    collateral_in: u64,
+    min_shares_out: u64,
+  ) -> Result<()> {
+    let market_key = ctx.accounts.market.key();
+    let mut market = ctx.accounts.market.load_mut()?;
+
+    let now = Clock::get()?.unix_timestamp;
+    ctx.accounts.session.spend(collateral_in, now)?;
+    check_tradable(&mut market, now)?;
+    require!(outcome_index <= 1, PredictionError::InvalidOutcome);
+    require!(collateral_in > 0, PredictionError::ZeroAmount);
+
+    let received = fund_from_trading_account(
+      &mut market,
+      &mut ctx.accounts.trading_account,
+      &ctx.accounts.custody,
+      ctx.accounts.custody_authority.to_account_info(),
+      ctx.bumps.custody_authority,
+      &mut ctx.accounts.vault,
+      &ctx.accounts.collateral_mint,
+      ctx.accounts.token_program.to_account_info(),
+      collateral_in,
+    )?;
+
+    let position = &mut ctx.accounts.position;
+    let new_trader = position.owner == Pubkey::default();
+    init_or_check_position(
+      position,
+      market_key,
+      ctx.accounts.owner.key(),
+      ctx.bumps.position,
+    )?;
+
+    let mut trade = TradeAccounts {
+      config: &ctx.accounts.config,
+      trader: ctx.accounts.owner.key(),
+      new_trader,
+      transient: false,
+      allowlist_entry: ctx.accounts.allowlist_entry.as_deref(),
+      permit_gate: ctx.accounts.permit_gate.as_deref(),
+      instructions: ctx.accounts.instructions.as_ref(),
+      token_gate: ctx.accounts.token_gate.as_deref(),
+      gate_token_account: ctx.accounts.gate_token_account.as_deref(),
+      market_makers: ctx.accounts.market_makers.as_deref_mut(),
+      user_stats: ctx.accounts.user_stats.as_deref_mut(),
+      competition_entry: ctx.accounts.competition_entry.as_deref_mut(),
+      market_stats: ctx.accounts.market_stats.as_deref_mut(),
+      protocol_stats: ctx.accounts.protocol_stats.as_deref_mut(),
+      price_candles: ctx.accounts.price_candles.as_ref(),
+      trade_tape: ctx.accounts.trade_tape.as_ref(),
+      price_feed: ctx.accounts.price_feed.as_ref(),
+    };
+    execute_buy(
+      &mut market,
+      market_key,
+      position,
+      outcome_index,
+      received,
+      min_shares_out,
+      now,
+      &mut trade,
+    )?;
+
+    Ok(())
+  }
+
+  /// `sell_to_trading_account` signed by a session key of the owner.
+  pub fn session_sell(
+    ctx: Context<SessionTrade>,
+    outcome_index: u8,
+    shares_in: u64,
+    min_collateral_out: u64,
+  ) -> Result<()> {
+    let market_key = ctx.accounts.market.key();
+    let mut market = ctx.accounts.market.load_mut()?;
+    let position = &mut ctx.accounts.position;
+
+    let now = Clock::get()?.unix_timestamp;
+    ctx.accounts.session.spend(0, now)?;
+    check_tradable(&mut market, now)?;
+    require!(
+      position.owner == ctx.accounts.owner.key(),
+      PredictionError::PositionOwnerMismatch
+    );
+
+    let mut trade = TradeAccounts {
+      config: &ctx.accounts.config,
+      trader: ctx.accounts.owner.key(),
+      new_trader: false,
+      transient: false,
+      allowlist_entry: ctx.accounts.allowlist_entry.as_deref(),
+      permit_gate: ctx.accounts.permit_gate.as_deref(),
+      instructions: ctx.accounts.instructions.as_ref(),
+      token_gate: ctx.accounts.token_gate.as_deref(),
+      gate_token_account: ctx.accounts.gate_token_account.as_deref(),
+      market_makers: ctx.accounts.market_makers.as_deref_mut(),
+      user_stats: ctx.accounts.user_stats.as_deref_mut(),
+      competition_entry: ctx.accounts.competition_entry.as_deref_mut(),
+      market_stats: ctx.accounts.market_stats.as_deref_mut(),
+      protocol_stats: ctx.accounts.protocol_stats.as_deref_mut(),
+      price_candles: ctx.accounts.price_candles.as_ref(),
+      trade_tape: ctx.accounts.trade_tape.as_ref(),
+      price_feed: ctx.accounts.price_feed.as_ref(),
+    };
+    let net_out = execute_sell(
+      &mut market,
+      market_key,
+      position,
+      outcome_index,
+      shares_in,
+      min_collateral_out,
+      now,
+      &mut trade,
+    )?;
+    credit_trading_account(
+      &mut market,
+      market_key,
+      &mut ctx.accounts.trading_account,
+      &ctx.accounts.vault,
+      ctx.accounts.vault_authority.to_account_info(),
+      &mut ctx.accounts.custody,
+      &ctx.accounts.collateral_mint,
+      ctx.accounts.token_program.to_account_info(),
+      net_out,
+    )
+  }
+
+  /// After resolution, pay an LP their pro-rata share of the vault left over
+  /// once winners are covered (initial backing + fees - payouts).
+  /// Locked creator liquidity is released here too.
+  pub fn withdraw_residual_liquidity(ctx: Context<WithdrawResidualLiquidity>) -> Result<()> {
+    let market_key = ctx.accounts.market.key();
+    let mut market = ctx.accounts.market.load_mut()?;
+    let lp_position = &mut ctx.accounts.lp_position;
+
+    require!(
+      market.is_settled(),
+      PredictionError::MarketNotResolved
+    );
+    let now = Clock::get()?.unix_timestamp;
+    require!(now >= market.dispute_deadline, PredictionError::DisputeWindowOpen);
+    require!(lp_position.lp_shares > 0, PredictionError::NoWinnings);
+    require!(market.total_lp_shares > 0, PredictionError::NoWinnings);
+    require!(market.unclaimed_swept == 0, PredictionError::ClaimWindowClosed);
+
+    // total_lp_shares is frozen at resolution; burning only the position keeps
+    // every LP's share of the snapshot stable regardless of withdrawal order.
+    let amount = mul_div(
+      market.residual_liquidity,
+      lp_position.lp_shares,
+      market.total_lp_shares,
+    )?;
+    require!(amount > 0, PredictionError::NoWinnings);
+
+    // Emissions earned so far stay claimable
+    let rewards = checkpoint_lp_rewards(
+      &market,
+      ctx.accounts.lp_rewards.as_deref_mut(),
+      lp_position,
+      now,
+    )?;
+    let lp_shares = lp_position.lp_shares;
+    lp_position.lp_shares = 0;
+    lp_position.locked = false;
+    if let Some(rewards) = rewards {
+      rewards.reset_debt(lp_position)?;
+    }
+    market.debit_vault(amount)?;
+
+    let seeds: &[&[u8]] = &[
+      b"vault_auth_v2",
+      market_key.as_ref(),
+      &[market.vault_auth_bump],
+    ];
+
+    pay_out_collateral(
+      &ctx.accounts.vault,
+      ctx.accounts.vault_authority.to_account_info(),
+      seeds,
+      &ctx.accounts.collateral_mint,
+      ctx.accounts.user_collateral_ata.as_ref().map(|a| a.to_account_info()),
+      ctx.accounts.wsol_unwrap.as_ref().map(|a| a.to_account_info()),
+      ctx.accounts.user.to_account_info(),
+      ctx.accounts.payer.to_account_info(),
+      ctx.accounts.token_program.to_account_info(),
+      ctx.accounts.system_program.to_account_info(),
+      amount,
+    )?;
+
+    emit!(ResidualLiquidityWithdrawn {
+      market: market_key,
+      provider: ctx.accounts.user.key(),
+      lp_shares,
+      amount,
+    });
+
+    Ok(())
+  }
+
+  /// Claim winnings after resolution using classic pro-rata payout from snapshot.
+  ///
+  /// payout = resolved_payable * user_winning_shares / resolved_total_winning_shares
+  ///
+  /// Winning shares redeem at most 1:1; the rest of the vault is LP residual.
+  /// Payouts step through the running total (`pro_rata_step`), so they sum
+  /// to exactly `resolved_payable` and no rounding dust is left behind.
+  ///
+  /// The position owner's collateral ATA is created if missing (rent paid by `payer`),
+  /// so anyone (the owner or a cranker) can settle a position into the owner's wallet.
+  /// Native SOL markets pay out through the temporary `wsol_unwrap` account instead.
+  pub fn claim_winnings_v2(ctx: Context<ClaimWinningsV2>) -> Result<()> {
+    let market_key = ctx.accounts.market.key();
+    let mut market = ctx.accounts.market.load_mut()?;
+    let position = &mut ctx.accounts.position;
+
+    // Shares escrowed during the correction window come back for the claim;
+    // the escrow account is closed to its owner.
+    if let Some(escrow) = ctx.accounts.claim_escrow.as_ref() {
+      give_position_shares(position, 0, escrow.yes_shares)?;
+      give_position_shares(position, 1, escrow.no_shares)?;
+    }
+
+    let (payout, user_winning_shares) = settle_claim(
+      &mut market,
+      market_key,
+      position,
+      ctx.accounts.user.key(),
+      Clock::get()?.unix_timestamp,
+    )?;
+
+    // Transfer payout from vault to user using PDA vault authority signer
+    let seeds: &[&[u8]] = &[
+      b"vault_auth_v2",
+      market_key.as_ref(),
+      &[market.vault_auth_bump],
+    ];
+
+    pay_out_collateral(
+      &ctx.accounts.vault,
+      ctx.accounts.vault_authority.to_account_info(),
+      seeds,
+      &ctx.accounts.collateral_mint,
+      ctx.accounts.user_collateral_ata.as_ref().map(|a| a.to_account_info()),
+      ctx.accounts.wsol_unwrap.as_ref().map(|a| a.to_account_info()),
+      ctx.accounts.user.to_account_info(),
+      ctx.accounts.payer.to_account_info(),
+      ctx.accounts.token_program.to_account_info(),
+      ctx.accounts.system_program.to_account_info(),
+      payout,
+    )?;
+
+    pay_slashed_bond_share(
+      ctx.accounts.creator_bond.as_ref(),
+      &market,
+      user_winning_shares,
+      &ctx.accounts.user.to_account_info(),
+    )?;
+
+    if let Some(stats) = ctx.accounts.user_stats.as_mut() {
+      stats.record_claim(market_key, &market, payout, user_winning_shares)?;
+    }
+    if let Some(stats) = ctx.accounts.protocol_stats.as_mut() {
+      stats.sync_tvl(&mut market)?;
+    }
+
+    Ok(())
+  }
+
+  /// Claim winnings from a resolved market and spend the whole payout on
+  /// `outcome_index` shares of an open market with the same collateral. The
+  /// payout moves vault to vault; slashed-bond payouts still need
+  /// `claim_winnings_v2`.
+  pub fn claim_and_buy(
+    ctx: Context<ClaimAndBuy>,
+    outcome_index: u8,
+    min_shares_out: u64,
+  ) -> Result<()> {
+    let source_key = ctx.accounts.source_market.key();
+    let target_key = ctx.accounts.target_market.key();
+    require_keys_neq!(source_key, target_key, PredictionError::InvalidTargetMarket);
+
+    let mut source = ctx.accounts.source_market.load_mut()?;
+    let mut target = ctx.accounts.target_market.load_mut()?;
+    let now = Clock::get()?.unix_timestamp;
+
+    let (payout, _) = settle_claim(
+      &mut source,
+      source_key,
+      &mut ctx.accounts.source_position,
+      ctx.accounts.user.key(),
+      now,
+    )?;
+
+    check_tradable(&mut target, now)?;
+    require!(outcome_index <= 1, PredictionError::InvalidOutcome);
+
+    let seeds: &[&[u8]] = &[
+      b"vault_auth_v2",
+      source_key.as_ref(),
+      &[source.vault_auth_bump],
+    ];
+    let received = transfer_signed(
+      &ctx.accounts.source_vault,
+      &mut ctx.accounts.target_vault,
+      &ctx.accounts.collateral_mint,
+      ctx.accounts.source_vault_authority.to_account_info(),
+      seeds,
+      ctx.accounts.token_program.to_account_info(),
+      payout,
+    )?;
+    target.credit_vault(received)?;
+
+    let position = &mut ctx.accounts.target_position;
+    let new_trader = position.owner == Pubkey::default();
+    init_or_check_position(
+      position,
+      target_key,
+      ctx.accounts.user.key(),
+      ctx.bumps.target_position,
+    )?;
+    let mut trade = TradeAccounts {
+      config: &ctx.accounts.config,
+      trader: ctx.accounts.user.key(),
+      new_trader,
+      transient: false,
+      allowlist_entry: ctx.accounts.allowlist_entry.as_deref(),
+      permit_gate: ctx.accounts.permit_gate.as_deref(),
+      instructions: ctx.accounts.instructions.as_ref(),
+      token_gate: ctx.accounts.token_gate.as_deref(),
+      gate_token_account: ctx.accounts.gate_token_account.as_deref(),
+      market_makers: ctx.accounts.market_makers.as_deref_mut(),
+      user_stats: ctx.accounts.user_stats.as_deref_mut(),
+      competition_entry: ctx.accounts.competition_entry.as_deref_mut(),
+      market_stats: ctx.accounts.market_stats.as_deref_mut(),
+      protocol_stats: ctx.accounts.protocol_stats.as_deref_mut(),
+      price_candles: ctx.accounts.price_candles.as_ref(),
+      trade_tape: ctx.accounts.trade_tape.as_ref(),
+      price_feed: ctx.accounts.price_feed.as_ref(),
+    };
+    let shares_out = execute_buy(
+      &mut target,
+      target_key,
+      position,
+      outcome_index,
+      received,
+      min_shares_out,
+      now,
+      &mut trade,
+    )?;
+
+    emit!(WinningsRolled {
+      source_market: source_key,
+      target_market: target_key,
+      owner: ctx.accounts.user.key(),
+      payout,
+      outcome_index,
+      shares_out,
+    });
+
+    Ok(())
+  }
+
+  /// Escrow `shares` of `outcome_index` from the maker's position in an OTC
+  /// offer. The ask is either `ask_amount` collateral of the same market
+  /// (`ask_market` = default) or `ask_amount` shares of `ask_outcome` in
+  /// `ask_market`. Block trades settle without touching the AMM price.
+  pub fn create_offer(ctx: Context<CreateOffer>, args: CreateOfferArgs) -> Result<()> {
+    let market_key = ctx.accounts.market.key();
+    let mut market = ctx.accounts.market.load_mut()?;
+    market.refresh_phase(Clock::get()?.unix_timestamp);
+    require!(market.is_live(), PredictionError::InvalidMarketStatus);
+    require!(args.shares > 0 && args.ask_amount > 0, PredictionError::ZeroAmount);
+    require!(
+      args.outcome_index <= 1 && args.ask_outcome <= 1,
+      PredictionError::InvalidOutcome
+    );
+    require_keys_neq!(args.ask_market, market_key, PredictionError::InvalidTargetMarket);
+
+    take_position_shares(&mut ctx.accounts.maker_position, args.outcome_index, args.shares)?;
+
+    let offer = &mut ctx.accounts.offer;
+    offer.maker = ctx.accounts.maker.key();
+    offer.offer_id = args.offer_id;
+    offer.market = market_key;
+    offer.outcome_index = args.outcome_index;
+    offer.shares = args.shares;
+    offer.ask_market = args.ask_market;
+    offer.ask_outcome = args.ask_outcome;
+    offer.ask_amount = args.ask_amount;
+    offer.bump = ctx.bumps.offer;
+
+    emit!(OfferCreated {
+      offer: offer.key(),
+      maker: offer.maker,
+      market: market_key,
+      outcome_index: args.outcome_index,
+      shares: args.shares,
+      ask_market: args.ask_market,
+      ask_outcome: args.ask_outcome,
+      ask_amount: args.ask_amount,
+    });
+    Ok(())
+  }
+
+  /// Fill a collateral-priced offer: the taker pays `ask_amount` collateral
+  /// straight to the maker and receives the escrowed shares, passing the
+  /// market's gates as a buyer.
+  pub fn accept_offer(ctx: Context<AcceptOffer>) -> Result<()> {
+    let offer = &ctx.accounts.offer;
+    require!(
+      offer.ask_market == Pubkey::default(),
+      PredictionError::OfferAskMismatch
+    );
+    {
+      let now = Clock::get()?.unix_timestamp;
+      let mut market = ctx.accounts.market.load_mut()?;
+      market.refresh_phase(now);
+      require!(market.is_live(), PredictionError::InvalidMarketStatus);
+      let gates = PartyGates {
+        allowlist_entry: ctx.accounts.allowlist_entry.as_deref(),
+        permit_gate: ctx.accounts.permit_gate.as_deref(),
+        instructions: ctx.accounts.instructions.as_ref(),
+        token_gate: ctx.accounts.token_gate.as_deref(),
+        gate_token_account: ctx.accounts.gate_token_account.as_deref(),
+      };
+      check_party_gates(&market, offer.market, ctx.accounts.taker.key(), &gates, true, now)?;
+    }
+
+    if ctx.accounts.collateral_mint.key() == native_mint::ID {
+      system_program::transfer(
+        CpiContext::new(
+          ctx.accounts.system_program.to_account_info(),
+          system_program::Transfer {
+            from: ctx.accounts.taker.to_account_info(),
+            to: ctx.accounts.maker.to_account_info(),
+          },
+        ),
+        offer.ask_amount,
+      )?;
+    } else {
+      token_interface::transfer_checked(
+        CpiContext::new(
+          ctx.accounts.token_program.to_account_info(),
+          TransferChecked {
+            from: ctx
+              .accounts
+              .taker_collateral_ata
+              .as_ref()
+              .ok_or(PredictionError::MissingCollateralAccount)?
+              .to_account_info(),
+            mint: ctx.accounts.collateral_mint.to_account_info(),
+            to: ctx
+              .accounts
+              .maker_collateral_ata
+              .as_ref()
+              .ok_or(PredictionError::MissingCollateralAccount)?
+              .to_account_info(),
+            authority: ctx.accounts.taker.to_account_info(),
+          },
+        ),
+        offer.ask_amount,
+        ctx.accounts.collateral_mint.decimals,
+      )?;
+    }
+
+    let position = &mut ctx.accounts.taker_position;
+    init_or_check_position(
+      position,
+      offer.market,
+      ctx.accounts.taker.key(),
+      ctx.bumps.taker_position,
+    )?;
+    give_position_shares(position, offer.outcome_index, offer.shares)?;
+
+    emit!(OfferFilled {
+      offer: offer.key(),
+      maker: offer.maker,
+      taker: ctx.accounts.taker.key(),
+    });
+    Ok(())
+  }
+
+  /// Fill a share-for-share offer: the taker hands over `ask_amount`
+  /// `ask_outcome` shares in `ask_market` and receives the escrowed shares.
+  /// Both sides receive shares, so the taker passes `market`'s gates and the
+  /// maker `ask_market`'s.
+  pub fn accept_share_offer(ctx: Context<AcceptShareOffer>) -> Result<()> {
+    let offer = &ctx.accounts.offer;
+    let now = Clock::get()?.unix_timestamp;
+    for loader in [&ctx.accounts.market, &ctx.accounts.ask_market] {
+      let mut market = loader.load_mut()?;
+      market.refresh_phase(now);
+      require!(market.is_live(), PredictionError::InvalidMarketStatus);
+    }
+    let taker_gates = PartyGates {
+      allowlist_entry: ctx.accounts.allowlist_entry.as_deref(),
+      permit_gate: ctx.accounts.permit_gate.as_deref(),
+      instructions: ctx.accounts.instructions.as_ref(),
+      token_gate: ctx.accounts.token_gate.as_deref(),
+      gate_token_account: ctx.accounts.gate_token_account.as_deref(),
+    };
+    check_party_gates(
+      &*ctx.accounts.market.load()?,
+      offer.market,
+      ctx.accounts.taker.key(),
+      &taker_gates,
+      true,
+      now,
+    )?;
+    let maker_gates = PartyGates {
+      allowlist_entry: ctx.accounts.maker_allowlist_entry.as_deref(),
+      permit_gate: ctx.accounts.ask_permit_gate.as_deref(),
+      instructions: ctx.accounts.instructions.as_ref(),
+      token_gate: ctx.accounts.ask_token_gate.as_deref(),
+      gate_token_account: ctx.accounts.maker_gate_token_account.as_deref(),
+    };
+    check_party_gates(
+      &*ctx.accounts.ask_market.load()?,
+      offer.ask_market,
+      offer.maker,
+      &maker_gates,
+      true,
+      now,
+    )?;
+
+    take_position_shares(
+      &mut ctx.accounts.taker_ask_position,
+      offer.ask_outcome,
+      offer.ask_amount,
+    )?;
+
+    let maker_ask = &mut ctx.accounts.maker_ask_position;
+    init_or_check_position(
+      maker_ask,
+      offer.ask_market,
+      offer.maker,
+      ctx.bumps.maker_ask_position,
+    )?;
+    give_position_shares(maker_ask, offer.ask_outcome, offer.ask_amount)?;
+
+    let position = &mut ctx.accounts.taker_position;
+    init_or_check_position(
+      position,
+      offer.market,
+      ctx.accounts.taker.key(),
+      ctx.bumps.taker_position,
+    )?;
+    give_position_shares(position, offer.outcome_index, offer.shares)?;
+
+    emit!(OfferFilled {
+      offer: offer.key(),
+      maker: offer.maker,
+      taker: ctx.accounts.taker.key(),
+    });
+    Ok(())
+  }
+
+  /// Maker cancels an open offer; the escrowed shares return to their position.
+  pub fn cancel_offer(ctx: Context<CancelOffer>) -> Result<()> {
+    let offer = &ctx.accounts.offer;
+    give_position_shares(
+      &mut ctx.accounts.maker_position,
+      offer.outcome_index,
+      offer.shares,
+    )?;
+
+    emit!(OfferCancelled {
+      offer: offer.key(),
+      maker: offer.maker,
+    });
+    Ok(())
+  }
+
+  /// Rest a limit order against the AMM. Buy orders escrow `amount`
+  /// collateral, sell orders escrow `amount` shares from the owner's position.
+  /// `limit_price` (PRICE_SCALE) caps the average buy price / floors the
+  /// average sell price. `keeper_tip` lamports are held in the order and paid
+  /// to whoever fills it. Orders fill in full or not at all.
+  ///
+  /// A sell order with a `trigger_price` is a stop-loss: it can only be filled
+  /// once the outcome's implied probability is at or below the trigger, and
+  /// `limit_price` is the worst average price the owner accepts.
+  ///
+  /// With `oracle_trigger` the order is also gated on an external Pyth price
+  /// (e.g. buy YES on "ETF approved" only if BTC trades below 50k).
+  pub fn place_limit_order(ctx: Context<PlaceLimitOrder>, args: PlaceLimitOrderArgs) -> Result<()> {
+    let market_key = ctx.accounts.market.key();
+    {
+      let mut market = ctx.accounts.market.load_mut()?;
+      market.refresh_phase(Clock::get()?.unix_timestamp);
+      require!(market.is_live(), PredictionError::InvalidMarketStatus);
+    }
+    require!(args.side <= 1, PredictionError::InvalidOrderSide);
+    require!(args.outcome_index <= 1, PredictionError::InvalidOutcome);
+    require!(args.amount > 0, PredictionError::ZeroAmount);
+    require!(
+      args.limit_price > 0 && args.limit_price < PRICE_SCALE,
+      PredictionError::InvalidLimitPrice
+    );
+    require!(
+      args.trigger_price == 0
+        || (args.side == OrderSide::Sell as u8 && args.trigger_price < PRICE_SCALE),
+      PredictionError::InvalidTriggerPrice
+    );
+
+    let position = &mut ctx.accounts.position;
+    init_or_check_position(
+      position,
+      market_key,
+      ctx.accounts.owner.key(),
+      ctx.bumps.position,
+    )?;
+
+    let amount = if args.side == OrderSide::Buy as u8 {
+      deposit_collateral(
+        ctx.accounts.owner.to_account_info(),
+        ctx.accounts.owner_collateral_ata.as_ref().map(|a| a.to_account_info()),
+        &mut ctx.accounts.escrow,
+        &ctx.accounts.collateral_mint,
+        ctx.accounts.token_program.to_account_info(),
+        ctx.accounts.system_program.to_account_info(),
+        args.amount,
+      )?
+    } else {
+      take_position_shares(position, args.outcome_index, args.amount)?;
+      args.amount
+    };
+
+    if args.keeper_tip > 0 {
+      system_program::transfer(
+        CpiContext::new(
+          ctx.accounts.system_program.to_account_info(),
+          system_program::Transfer {
+            from: ctx.accounts.owner.to_account_info(),
+            to: ctx.accounts.order.to_account_info(),
+          },
+        ),
+        args.keeper_tip,
+      )?;
+    }
+
+    let order = &mut ctx.accounts.order;
+    order.owner = ctx.accounts.owner.key();
+    order.market = market_key;
+    order.order_id = args.order_id;
+    order.side = args.side;
+    order.outcome_index = args.outcome_index;
+    order.amount = amount;
+    order.limit_price = args.limit_price;
+    order.trigger_price = args.trigger_price;
+    if let Some(trigger) = &args.oracle_trigger {
+      require!(
+        trigger.price_account != Pubkey::default(),
+        PredictionError::InvalidOracle
+      );
+      order.oracle_account = trigger.price_account;
+      order.oracle_price = trigger.price;
+      order.oracle_above = trigger.above;
+    }
+    order.keeper_tip = args.keeper_tip;
+    order.filled = false;
+    order.bump = ctx.bumps.order;
+
+    emit!(LimitOrderPlaced {
+      order: order.key(),
+      owner: order.owner,
+      market: market_key,
+      side: args.side,
+      outcome_index: args.outcome_index,
+      amount,
+      limit_price: args.limit_price,
+      trigger_price: args.trigger_price,
+    });
+    Ok(())
+  }
+
+  /// Permissionless crank: execute a resting order against the AMM once its
+  /// limit is reachable (`SlippageExceeded` until then), paying the keeper
+  /// the order's tip. Buy fills credit
+  /// shares to the owner's position; sell proceeds land in the order escrow
+  /// until the owner closes the order.
+  pub fn fill_limit_order(ctx: Context<FillLimitOrder>) -> Result<()> {
+    let market_key = ctx.accounts.market.key();
+    let mut market = ctx.accounts.market.load_mut()?;
+    let order = &mut ctx.accounts.order;
+    require!(!order.filled, PredictionError::OrderFilled);
+
+    let now = Clock::get()?.unix_timestamp;
+    check_tradable(&mut market, now)?;
+
+    // Stop-loss: armed only once the probability has fallen to the trigger
+    if order.trigger_price > 0 {
+      require!(
+        market.implied_probability(order.outcome_index)? <= order.trigger_price,
+        PredictionError::StopNotTriggered
+      );
+    }
+
+    // Oracle condition: the external price must be fresh and past the trigger
+    if order.oracle_account != Pubkey::default() {
+      let oracle = ctx
+        .accounts
+        .oracle
+        .as_ref()
+        .ok_or(PredictionError::InvalidOracle)?;
+      let price = read_pyth_price(oracle, now)?;
+      let crossed = if order.oracle_above {
+        price.price >= order.oracle_price
+      } else {
+        price.price <= order.oracle_price
+      };
+      require!(crossed, PredictionError::OracleTriggerNotMet);
+    }
+
+    let seeds: &[&[u8]] = &[
+      b"vault_auth_v2",
+      market_key.as_ref(),
+      &[market.vault_auth_bump],
+    ];
+    let mut trade = TradeAccounts {
+      config: &ctx.accounts.config,
+      trader: order.owner,
+      new_trader: false,
+      transient: false,
+      allowlist_entry: ctx.accounts.allowlist_entry.as_deref(),
+      permit_gate: ctx.accounts.permit_gate.as_deref(),
+      instructions: ctx.accounts.instructions.as_ref(),
+      token_gate: ctx.accounts.token_gate.as_deref(),
+      gate_token_account: ctx.accounts.gate_token_account.as_deref(),
+      market_makers: ctx.accounts.market_makers.as_deref_mut(),
+      user_stats: ctx.accounts.user_stats.as_deref_mut(),
+      competition_entry: ctx.accounts.competition_entry.as_deref_mut(),
+      market_stats: ctx.accounts.market_stats.as_deref_mut(),
+      protocol_stats: ctx.accounts.protocol_stats.as_deref_mut(),
+      price_candles: ctx.accounts.price_candles.as_ref(),
+      trade_tape: ctx.accounts.trade_tape.as_ref(),
+      price_feed: ctx.accounts.price_feed.as_ref(),
+    };
+    let position = &mut ctx.accounts.position;
+
+    if order.side == OrderSide::Buy as u8 {
+      let received = transfer_signed(
+        &ctx.accounts.escrow,
+        &mut ctx.accounts.vault,
+        &ctx.accounts.collateral_mint,
+        ctx.accounts.vault_authority.to_account_info(),
+        seeds,
+        ctx.accounts.token_program.to_account_info(),
+        order.amount,
+      )?;
+      market.credit_vault(received)?;
+
+      // avg price <= limit  <=>  shares_out >= amount / limit
+      let min_shares_out = mul_div(order.amount, PRICE_SCALE, order.limit_price)?;
+      execute_buy(
+        &mut market,
+        market_key,
+        position,
+        order.outcome_index,
+        received,
+        min_shares_out,
+        now,
+        &mut trade,
+      )?;
+    } else {
+      give_position_shares(position, order.outcome_index, order.amount)?;
+      let min_collateral_out = mul_div(order.amount, order.limit_price, PRICE_SCALE)?;
+      let net_out = execute_sell(
+        &mut market,
+        market_key,
+        position,
+        order.outcome_index,
+        order.amount,
+        min_collateral_out,
+        now,
+        &mut trade,
+      )?;
+      market.debit_vault(net_out)?;
+
+      transfer_signed(
+        &ctx.accounts.vault,
+        &mut ctx.accounts.escrow,
+        &ctx.accounts.collateral_mint,
+        ctx.accounts.vault_authority.to_account_info(),
+        seeds,
+        ctx.accounts.token_program.to_account_info(),
+        net_out,
+      )?;
+    }
+
+    order.filled = true;
+    let tip = order.keeper_tip;
+    order.keeper_tip = 0;
+    if tip > 0 {
+      **order.to_account_info().try_borrow_mut_lamports()? -= tip;
+      **ctx.accounts.keeper.try_borrow_mut_lamports()? += tip;
+    }
+
+    emit!(LimitOrderFilled {
+      order: order.key(),
+      keeper: ctx.accounts.keeper.key(),
+      tip,
+    });
+    Ok(())
+  }
+
+  /// Owner closes an order: unfilled sell shares go back to the position and
+  /// whatever the escrow holds (unfilled buy collateral or sell proceeds) is
+  /// paid out. The escrow and order accounts are closed to the owner.
+  pub fn close_limit_order(ctx: Context<CloseLimitOrder>) -> Result<()> {
+    let market_key = ctx.accounts.market.key();
+    let market = ctx.accounts.market.load()?;
+    let order = &ctx.accounts.order;
+
+    // Escrowed shares are worthless once the position has been claimed
+    if !order.filled && order.side == OrderSide::Sell as u8 && !ctx.accounts.position.claimed {
+      give_position_shares(&mut ctx.accounts.position, order.outcome_index, order.amount)?;
+    }
+
+    let seeds: &[&[u8]] = &[
+      b"vault_auth_v2",
+      market_key.as_ref(),
+      &[market.vault_auth_bump],
+    ];
+
+    let balance = ctx.accounts.escrow.amount;
+    if balance > 0 {
+      pay_out_collateral(
+        &ctx.accounts.escrow,
+        ctx.accounts.vault_authority.to_account_info(),
+        seeds,
+        &ctx.accounts.collateral_mint,
+        ctx.accounts.owner_collateral_ata.as_ref().map(|a| a.to_account_info()),
+        ctx.accounts.wsol_unwrap.as_ref().map(|a| a.to_account_info()),
+        ctx.accounts.owner.to_account_info(),
+        ctx.accounts.payer.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        ctx.accounts.system_program.to_account_info(),
+        balance,
+      )?;
+    }
+
+    token_interface::close_account(CpiContext::new_with_signer(
+      ctx.accounts.token_program.to_account_info(),
+      CloseAccount {
+        account: ctx.accounts.escrow.to_account_info(),
+        destination: ctx.accounts.owner.to_account_info(),
+        authority: ctx.accounts.vault_authority.to_account_info(),
+      },
+      &[seeds],
+    ))?;
+
+    emit!(LimitOrderClosed {
+      order: order.key(),
+      owner: order.owner,
+      filled: order.filled,
+    });
+    Ok(())
+  }
+
+  /// Schedule a large buy (collateral) or sell (shares) to be executed in
+  /// `slices` equal parts, at least `interval` seconds apart, by keepers.
+  /// Each slice must average no worse than `limit_price` (PRICE_SCALE), and
+  /// pays `keeper_tip` lamports to whoever executes it.
+  pub fn place_twap_order(ctx: Context<PlaceTwapOrder>, args: PlaceTwapOrderArgs) -> Result<()> {
+    let market_key = ctx.accounts.market.key();
+    let now = Clock::get()?.unix_timestamp;
+    {
+      let mut market = ctx.accounts.market.load_mut()?;
+      market.refresh_phase(now);
+      require!(market.is_live(), PredictionError::InvalidMarketStatus);
+    }
+    require!(args.side <= 1, PredictionError::InvalidOrderSide);
+    require!(args.outcome_index <= 1, PredictionError::InvalidOutcome);
+    require!(
+      args.slices >= 2 && args.slices <= MAX_TWAP_SLICES,
+      PredictionError::InvalidSliceCount
+    );
+    require!(args.interval > 0, PredictionError::InvalidSliceCount);
+    require!(
+      args.amount >= args.slices as u64,
+      PredictionError::ZeroAmount
+    );
+    require!(
+      args.limit_price > 0 && args.limit_price < PRICE_SCALE,
+      PredictionError::InvalidLimitPrice
+    );
+
+    let position = &mut ctx.accounts.position;
+    init_or_check_position(
+      position,
+      market_key,
+      ctx.accounts.owner.key(),
+      ctx.bumps.position,
+    )?;
+
+    let amount = if args.side == OrderSide::Buy as u8 {
+      deposit_collateral(
+        ctx.accounts.owner.to_account_info(),
+        ctx.accounts.owner_collateral_ata.as_ref().map(|a| a.to_account_info()),
+        &mut ctx.accounts.escrow,
+        &ctx.accounts.collateral_mint,
+        ctx.accounts.token_program.to_account_info(),
+        ctx.accounts.system_program.to_account_info(),
+        args.amount,
+      )?
+    } else {
+      take_position_shares(position, args.outcome_index, args.amount)?;
+      args.amount
+    };
+
+    // Tips for every slice are prepaid into the order account
+    let tips = args
+      .keeper_tip
+      .checked_mul(args.slices as u64)
+      .ok_or(PredictionError::MathOverflow)?;
+    if tips > 0 {
+      system_program::transfer(
+        CpiContext::new(
+          ctx.accounts.system_program.to_account_info(),
+          system_program::Transfer {
+            from: ctx.accounts.owner.to_account_info(),
+            to: ctx.accounts.order.to_account_info(),
+          },
+        ),
+        tips,
+      )?;
+    }
+
+    let order = &mut ctx.accounts.order;
+    order.owner = ctx.accounts.owner.key();
+    order.market = market_key;
+    order.order_id = args.order_id;
+    order.side = args.side;
+    order.outcome_index = args.outcome_index;
+    order.remaining = amount;
+    order.slices = args.slices;
+    order.slices_done = 0;
+    order.interval = args.interval;
+    order.next_slice_at = now;
+    order.limit_price = args.limit_price;
+    order.keeper_tip = args.keeper_tip;
+    order.bump = ctx.bumps.order;
+
+    emit!(TwapOrderPlaced {
+      order: order.key(),
+      owner: order.owner,
+      market: market_key,
+      side: args.side,
+      outcome_index: args.outcome_index,
+      amount,
+      slices: args.slices,
+      interval: args.interval,
+      limit_price: args.limit_price,
+    });
+    Ok(())
+  }
+
+  /// Permissionless crank: execute the next due slice of a TWAP order. The
+  /// last slice takes whatever remains. Buy slices credit shares to the
+  /// owner's position; sell proceeds accumulate in the order escrow.
+  pub fn execute_twap_slice(ctx: Context<ExecuteTwapSlice>) -> Result<()> {
+    let market_key = ctx.accounts.market.key();
+    let mut market = ctx.accounts.market.load_mut()?;
+    let order = &mut ctx.accounts.order;
+    require!(order.slices_done < order.slices, PredictionError::OrderFilled);
+
+    let now = Clock::get()?.unix_timestamp;
+    require!(now >= order.next_slice_at, PredictionError::SliceNotDue);
+    check_tradable(&mut market, now)?;
+
+    let slices_left = (order.slices - order.slices_done) as u64;
+    let slice = order.remaining / slices_left;
+    require!(slice > 0, PredictionError::ZeroAmount);
+
+    let seeds: &[&[u8]] = &[
+      b"vault_auth_v2",
+      market_key.as_ref(),
+      &[market.vault_auth_bump],
+    ];
+    let mut trade = TradeAccounts {
+      config: &ctx.accounts.config,
+      trader: order.owner,
+      new_trader: false,
+      transient: false,
+      allowlist_entry: ctx.accounts.allowlist_entry.as_deref(),
+      permit_gate: ctx.accounts.permit_gate.as_deref(),
+      instructions: ctx.accounts.instructions.as_ref(),
+      token_gate: ctx.accounts.token_gate.as_deref(),
+      gate_token_account: ctx.accounts.gate_token_account.as_deref(),
+      market_makers: ctx.accounts.market_makers.as_deref_mut(),
+      user_stats: ctx.accounts.user_stats.as_deref_mut(),
+      competition_entry: ctx.accounts.competition_entry.as_deref_mut(),
+      market_stats: ctx.accounts.market_stats.as_deref_mut(),
+      protocol_stats: ctx.accounts.protocol_stats.as_deref_mut(),
+      price_candles: ctx.accounts.price_candles.as_ref(),
+      trade_tape: ctx.accounts.trade_tape.as_ref(),
+      price_feed: ctx.accounts.price_feed.as_ref(),
+    };
+    let position = &mut ctx.accounts.position;
+
+    let filled = if order.side == OrderSide::Buy as u8 {
+      let received = transfer_signed(
+        &ctx.accounts.escrow,
+        &mut ctx.accounts.vault,
+        &ctx.accounts.collateral_mint,
+        ctx.accounts.vault_authority.to_account_info(),
+        seeds,
+        ctx.accounts.token_program.to_account_info(),
+        slice,
+      )?;
+      market.credit_vault(received)?;
+
+      let min_shares_out = mul_div(slice, PRICE_SCALE, order.limit_price)?;
+      execute_buy(
+        &mut market,
+        market_key,
+        position,
+        order.outcome_index,
+        received,
+        min_shares_out,
+        now,
+        &mut trade,
+      )?
+    } else {
+      give_position_shares(position, order.outcome_index, slice)?;
+      let min_collateral_out = mul_div(slice, order.limit_price, PRICE_SCALE)?;
+      let net_out = execute_sell(
+        &mut market,
+        market_key,
+        position,
+        order.outcome_index,
+        slice,
+        min_collateral_out,
+        now,
+        &mut trade,
+      )?;
+      market.debit_vault(net_out)?;
+
+      transfer_signed(
+        &ctx.accounts.vault,
+        &mut ctx.accounts.escrow,
+        &ctx.accounts.collateral_mint,
+        ctx.accounts.vault_authority.to_account_info(),
+        seeds,
+        ctx.accounts.token_program.to_account_info(),
+        net_out,
+      )?;
+      net_out
+    };
+
+    order.remaining -= slice;
+    order.slices_done += 1;
+    order.next_slice_at = now
+      .checked_add(order.interval)
+      .ok_or(PredictionError::MathOverflow)?;
+
+    let tip = order.keeper_tip;
+    if tip > 0 {
+      **order.to_account_info().try_borrow_mut_lamports()? -= tip;
+      **ctx.accounts.keeper.try_borrow_mut_lamports()? += tip;
+    }
+
+    emit!(TwapSliceExecuted {
+      order: order.key(),
+      keeper: ctx.accounts.keeper.key(),
+      slice_index: order.slices_done,
+      amount_in: slice,
+      amount_out: filled,
+      tip,
+    });
+    Ok(())
+  }
+
+  /// Owner cancels or finalizes a TWAP order: unexecuted sell shares go back
+  /// to the position, the escrow balance is paid out, and the escrow and
+  /// order accounts (with any unused tips) are closed to the owner.
+  pub fn close_twap_order(ctx: Context<CloseTwapOrder>) -> Result<()> {
+    let market_key = ctx.accounts.market.key();
+    let market = ctx.accounts.market.load()?;
+    let order = &ctx.accounts.order;
+
+    if order.remaining > 0 && order.side == OrderSide::Sell as u8 && !ctx.accounts.position.claimed {
+      give_position_shares(&mut ctx.accounts.position, order.outcome_index, order.remaining)?;
+    }
+
+    let seeds: &[&[u8]] = &[
+      b"vault_auth_v2",
+      market_key.as_ref(),
+      &[market.vault_auth_bump],
+    ];
+
+    let balance = ctx.accounts.escrow.amount;
+    if balance > 0 {
+      pay_out_collateral(
+        &ctx.accounts.escrow,
+        ctx.accounts.vault_authority.to_account_info(),
+        seeds,
+        &ctx.accounts.collateral_mint,
+        ctx.accounts.owner_collateral_ata.as_ref().map(|a| a.to_account_info()),
+        ctx.accounts.wsol_unwrap.as_ref().map(|a| a.to_account_info()),
+        ctx.accounts.owner.to_account_info(),
+        ctx.accounts.payer.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        ctx.accounts.system_program.to_account_info(),
+        balance,
+      )?;
+    }
+
+    token_interface::close_account(CpiContext::new_with_signer(
+      ctx.accounts.token_program.to_account_info(),
+      CloseAccount {
+        account: ctx.accounts.escrow.to_account_info(),
+        destination: ctx.accounts.owner.to_account_info(),
+        authority: ctx.accounts.vault_authority.to_account_info(),
+      },
+      &[seeds],
+    ))?;
+
+    emit!(TwapOrderClosed {
+      order: order.key(),
+      owner: order.owner,
+      slices_done: order.slices_done,
+    });
+    Ok(())
+  }
+
+  /// Settle against a market maker's signed quote at the quoted price,
+  /// outside the AMM curve. The maker's ed25519 signature over the borsh
+  /// encoded `quote` must be verified by the instruction immediately before
+  /// this one. Collateral moves between the two parties' trading accounts and
+  /// shares between their positions; each quote nonce fills once. Both
+  /// parties pass the market's gates like an AMM trader, the buyer
+  /// including the token gate.
+  pub fn settle_rfq(ctx: Context<SettleRfq>, quote: RfqQuote) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    let maker_sells = quote.maker_side == OrderSide::Sell as u8;
+    {
+      let mut market = ctx.accounts.market.load_mut()?;
+      market.refresh_phase(now);
+      require!(market.is_live(), PredictionError::InvalidMarketStatus);
+      require!(market.paused == 0, PredictionError::MarketPaused);
+
+      let market_key = ctx.accounts.market.key();
+      let maker_gates = PartyGates {
+        allowlist_entry: ctx.accounts.maker_allowlist_entry.as_deref(),
+        permit_gate: ctx.accounts.permit_gate.as_deref(),
+        instructions: Some(&ctx.accounts.instructions),
+        token_gate: ctx.accounts.token_gate.as_deref(),
+        gate_token_account: ctx.accounts.maker_gate_token_account.as_deref(),
+      };
+      check_party_gates(&market, market_key, quote.maker, &maker_gates, !maker_sells, now)?;
+      let taker_gates = PartyGates {
+        allowlist_entry: ctx.accounts.taker_allowlist_entry.as_deref(),
+        permit_gate: ctx.accounts.permit_gate.as_deref(),
+        instructions: Some(&ctx.accounts.instructions),
+        token_gate: ctx.accounts.token_gate.as_deref(),
+        gate_token_account: ctx.accounts.taker_gate_token_account.as_deref(),
+      };
+      let taker_key = ctx.accounts.taker.key();
+      check_party_gates(&market, market_key, taker_key, &taker_gates, maker_sells, now)?;
+    }
+    require!(now <= quote.expiry, PredictionError::QuoteExpired);
+    require!(
+      quote.taker == Pubkey::default() || quote.taker == ctx.accounts.taker.key(),
+      PredictionError::QuoteTakerMismatch
+    );
+    require!(quote.maker_side <= 1, PredictionError::InvalidOrderSide);
+    require!(quote.outcome_index <= 1, PredictionError::InvalidOutcome);
+    require!(quote.shares > 0, PredictionError::ZeroAmount);
+    require!(
+      quote.price > 0 && quote.price < PRICE_SCALE,
+      PredictionError::InvalidLimitPrice
+    );
+
+    verify_ed25519_signature(
+      &ctx.accounts.instructions,
+      &quote.maker,
+      &quote.try_to_vec()?,
+    )?;
+
+    let market_key = ctx.accounts.market.key();
+    let maker_key = ctx.accounts.maker.key();
+    let taker_key = ctx.accounts.taker.key();
+    let collateral = mul_div(quote.shares, quote.price, PRICE_SCALE)?;
+    require!(collateral > 0, PredictionError::ZeroAmount);
+
+    init_or_check_position(
+      &mut ctx.accounts.maker_position,
+      market_key,
+      maker_key,
+      ctx.bumps.maker_position,
+    )?;
+    init_or_check_position(
+      &mut ctx.accounts.taker_position,
+      market_key,
+      taker_key,
+      ctx.bumps.taker_position,
+    )?;
+
+    let (seller_position, buyer_position, buyer_account, seller_account) = if maker_sells {
+      (
+        &mut ctx.accounts.maker_position,
+        &mut ctx.accounts.taker_position,
+        &mut ctx.accounts.taker_trading_account,
+        &mut ctx.accounts.maker_trading_account,
+      )
+    } else {
+      (
+        &mut ctx.accounts.taker_position,
+        &mut ctx.accounts.maker_position,
+        &mut ctx.accounts.maker_trading_account,
+        &mut ctx.accounts.taker_trading_account,
+      )
+    };
+
+    take_position_shares(seller_position, quote.outcome_index, quote.shares)?;
+    give_position_shares(buyer_position, quote.outcome_index, quote.shares)?;
+
+    buyer_account.balance = buyer_account
+      .balance
+      .checked_sub(collateral)
+      .ok_or(PredictionError::InsufficientBalance)?;
+    seller_account.balance = seller_account
+      .balance
+      .checked_add(collateral)
+      .ok_or(PredictionError::MathOverflow)?;
+
+    let fill = &mut ctx.accounts.quote_fill;
+    fill.maker = maker_key;
+    fill.nonce = quote.nonce;
+    fill.bump = ctx.bumps.quote_fill;
+
+    emit!(RfqSettled {
+      market: market_key,
+      maker: maker_key,
+      taker: taker_key,
+      maker_side: quote.maker_side,
+      outcome_index: quote.outcome_index,
+      shares: quote.shares,
+      price: quote.price,
+      nonce: quote.nonce,
+    });
+    Ok(())
+  }
+
+  /// Create the YES-share order book for a market. Permissionless.
+  pub fn init_order_book(ctx: Context<InitOrderBook>) -> Result<()> {
+    let book = &mut ctx.accounts.order_book;
+    book.market = ctx.accounts.market.key();
+    book.collateral_mint = ctx.accounts.market.load()?.collateral_mint;
+    book.next_order_id = 0;
+    book.bids = Vec::new();
+    book.asks = Vec::new();
+    book.bump = ctx.bumps.order_book;
+    Ok(())
+  }
+
+  /// Rest a post-only bid or ask for YES shares at `price` (PRICE_SCALE).
+  /// Bids lock `shares * price` from the owner's trading account; asks
+  /// escrow YES shares from their position. Orders that would cross the
+  /// book are rejected; takers reach them through `route_buy_yes` /
+  /// `route_sell_yes`. The owner isn't present when a bid fills, so they
+  /// pass the market's gates here, bids including the token gate.
+  pub fn place_book_order(
+    ctx: Context<PlaceBookOrder>,
+    side: u8,
+    price: u64,
+    shares: u64,
+  ) -> Result<()> {
+    let market_key = ctx.accounts.market.key();
+    require!(side <= 1, PredictionError::InvalidOrderSide);
+    {
+      let now = Clock::get()?.unix_timestamp;
+      let mut market = ctx.accounts.market.load_mut()?;
+      market.refresh_phase(now);
+      require!(market.is_live(), PredictionError::InvalidMarketStatus);
+      let gates = PartyGates {
+        allowlist_entry: ctx.accounts.allowlist_entry.as_deref(),
+        permit_gate: ctx.accounts.permit_gate.as_deref(),
+        instructions: ctx.accounts.instructions.as_ref(),
+        token_gate: ctx.accounts.token_gate.as_deref(),
+        gate_token_account: ctx.accounts.gate_token_account.as_deref(),
+      };
+      let buying = side == OrderSide::Buy as u8;
+      check_party_gates(&market, market_key, ctx.accounts.owner.key(), &gates, buying, now)?;
+    }
+    require!(shares > 0, PredictionError::ZeroAmount);
+    require!(price > 0 && price < PRICE_SCALE, PredictionError::InvalidLimitPrice);
+
+    let owner = ctx.accounts.owner.key();
+    init_or_check_position(
+      &mut ctx.accounts.position,
+      market_key,
+      owner,
+      ctx.bumps.position,
+    )?;
+
+    let book = &mut ctx.accounts.order_book;
+    let order_id = book.next_order_id;
+    book.next_order_id = order_id
+      .checked_add(1)
+      .ok_or(PredictionError::MathOverflow)?;
+
+    if side == OrderSide::Buy as u8 {
+      require!(
+        book.asks.first().is_none_or(|ask| price < ask.price),
+        PredictionError::OrderWouldCross
+      );
+      require!(book.bids.len() < MAX_BOOK_ORDERS, PredictionError::OrderBookFull);
+
+      let collateral = mul_div(shares, price, PRICE_SCALE)?;
+      require!(collateral > 0, PredictionError::ZeroAmount);
+      let account = &mut ctx.accounts.trading_account;
+      account.balance = account
+        .balance
+        .checked_sub(collateral)
+        .ok_or(PredictionError::InsufficientBalance)?;
+
+      // Best (highest) bid first, FIFO within a price
+      let at = book.bids.partition_point(|bid| bid.price >= price);
+      book.bids.insert(at, BookOrder { order_id, owner, price, shares, collateral });
+    } else {
+      require!(
+        book.bids.first().is_none_or(|bid| price > bid.price),
+        PredictionError::OrderWouldCross
+      );
+      require!(book.asks.len() < MAX_BOOK_ORDERS, PredictionError::OrderBookFull);
+
+      take_position_shares(&mut ctx.accounts.position, 0, shares)?;
+
+      // Best (lowest) ask first, FIFO within a price
+      let at = book.asks.partition_point(|ask| ask.price <= price);
+      book.asks.insert(at, BookOrder { order_id, owner, price, shares, collateral: 0 });
+    }
+
+    emit!(BookOrderPlaced {
+      market: market_key,
+      owner,
+      order_id,
+      side,
+      price,
+      shares,
+    });
+    Ok(())
+  }
+
+  /// Cancel a resting book order: locked bid collateral returns to the
+  /// owner's trading account, unsold ask shares to their position.
+  pub fn cancel_book_order(ctx: Context<CancelBookOrder>, side: u8, order_id: u64) -> Result<()> {
+    let owner = ctx.accounts.owner.key();
+    let book = &mut ctx.accounts.order_book;
+    let orders = match side {
+      0 => &mut book.bids,
+      1 => &mut book.asks,
+      _ => return err!(PredictionError::InvalidOrderSide),
+    };
+    let at = orders
+      .iter()
+      .position(|o| o.order_id == order_id && o.owner == owner)
+      .ok_or(PredictionError::OrderNotFound)?;
+    let order = orders.remove(at);
+
+    if side == OrderSide::Buy as u8 {
+      let account = &mut ctx.accounts.trading_account;
+      account.balance = account
+        .balance
+        .checked_add(order.collateral)
+        .ok_or(PredictionError::MathOverflow)?;
+    } else if !ctx.accounts.position.claimed {
+      // Escrowed shares are worthless once the position has been claimed
+      give_position_shares(&mut ctx.accounts.position, 0, order.shares)?;
+    }
+
+    emit!(BookOrderCancelled {
+      market: book.market,
+      owner,
+      order_id,
+      side,
+    });
+    Ok(())
+  }
+
+  /// Buy YES with `collateral_in` from the caller's trading account, taking
+  /// book asks priced at or below the AMM's marginal YES price first and
+  /// routing the rest through the AMM. Remaining accounts: the trading
+  /// account of each ask maker that gets filled, in book order.
+  pub fn route_buy_yes<'info>(
+    ctx: Context<'_, '_, 'info, 'info, RouteOrder<'info>>,
+    collateral_in: u64,
+    min_shares_out: u64,
+  ) -> Result<()> {
+    let market_key = ctx.accounts.market.key();
+    let mut market = ctx.accounts.market.load_mut()?;
+    let now = Clock::get()?.unix_timestamp;
+    check_tradable(&mut market, now)?;
+    require!(collateral_in > 0, PredictionError::ZeroAmount);
+
+    let taker = ctx.accounts.user.key();
+    let new_trader = ctx.accounts.position.owner == Pubkey::default();
+    let mut trade = TradeAccounts {
+      config: &ctx.accounts.config,
+      trader: taker,
+      new_trader,
+      transient: false,
+      allowlist_entry: ctx.accounts.allowlist_entry.as_deref(),
+      permit_gate: ctx.accounts.permit_gate.as_deref(),
+      instructions: ctx.accounts.instructions.as_ref(),
+      token_gate: ctx.accounts.token_gate.as_deref(),
+      gate_token_account: ctx.accounts.gate_token_account.as_deref(),
+      market_makers: ctx.accounts.market_makers.as_deref_mut(),
+      user_stats: ctx.accounts.user_stats.as_deref_mut(),
+      competition_entry: ctx.accounts.competition_entry.as_deref_mut(),
+      market_stats: ctx.accounts.market_stats.as_deref_mut(),
+      protocol_stats: ctx.accounts.protocol_stats.as_deref_mut(),
+      price_candles: ctx.accounts.price_candles.as_ref(),
+      trade_tape: ctx.accounts.trade_tape.as_ref(),
+      price_feed: ctx.accounts.price_feed.as_ref(),
+    };
+    // Book fills don't go through `execute_buy`
+    check_trade_gates(&market, market_key, &trade, true, now)?;
+
+    let account = &mut ctx.accounts.trading_account;
+    account.balance = account
+      .balance
+      .checked_sub(collateral_in)
+      .ok_or(PredictionError::InsufficientBalance)?;
+
+    let position = &mut ctx.accounts.position;
+    init_or_check_position(position, market_key, taker, ctx.bumps.position)?;
+
+    let amm_price = market.implied_probability(0)?;
+    let book = &mut ctx.accounts.order_book;
+    let collateral_mint = book.collateral_mint;
+    let mut makers = ctx.remaining_accounts.iter();
+    let mut remaining = collateral_in;
+    let mut book_shares = 0u64;
+
+    while let Some(ask) = book.asks.first_mut() {
+      if ask.price > amm_price {
+        break;
+      }
+      let fill = mul_div(remaining, PRICE_SCALE, ask.price)?.min(ask.shares);
+      let cost = mul_div(fill, ask.price, PRICE_SCALE)?;
+      if cost == 0 {
+        break;
+      }
+      require_keys_neq!(ask.owner, taker, PredictionError::SelfTrade);
+
+      let maker_info = makers.next().ok_or(PredictionError::InvalidRemainingAccounts)?;
+      let mut maker = Account::<TradingAccount>::try_from(maker_info)?;
+      require!(
+        maker.owner == ask.owner && maker.collateral_mint == collateral_mint,
+        PredictionError::InvalidRemainingAccounts
+      );
+      maker.balance = maker
+        .balance
+        .checked_add(cost)
+        .ok_or(PredictionError::MathOverflow)?;
+      maker.exit(ctx.program_id)?;
+
+      ask.shares -= fill;
+      remaining -= cost;
+      book_shares += fill;
+      if ask.shares == 0 {
+        book.asks.remove(0);
+      }
+    }
+    give_position_shares(position, 0, book_shares)?;
+
+    let mut amm_shares = 0;
+    if remaining > 0 {
+      let seeds: &[&[u8]] = &[b"trading_custody_auth", &[ctx.bumps.custody_authority]];
+      let received = transfer_signed(
+        &ctx.accounts.custody,
+        &mut ctx.accounts.vault,
+        &ctx.accounts.collateral_mint,
+        ctx.accounts.custody_authority.to_account_info(),
+        seeds,
+        ctx.accounts.token_program.to_account_info(),
+        remaining,
+      )?;
+      market.credit_vault(received)?;
+      amm_shares = execute_buy(&mut market, market_key, position, 0, received, 0, now, &mut trade)?;
+    }
+
+    let shares_out = book_shares
+      .checked_add(amm_shares)
+      .ok_or(PredictionError::MathOverflow)?;
+    require!(shares_out >= min_shares_out, PredictionError::SlippageExceeded);
+
+    emit!(OrderRouted {
+      market: market_key,
+      taker,
+      side: OrderSide::Buy as u8,
+      book_amount: book_shares,
+      amm_amount: amm_shares,
+    });
+    Ok(())
+  }
+
+  /// Sell `shares_in` YES into book bids priced at or above the AMM's
+  /// marginal YES price first, then the AMM; proceeds go to the caller's
+  /// trading account. Remaining accounts: the position of each bid maker
+  /// that gets filled, in book order.
+  pub fn route_sell_yes<'info>(
+    ctx: Context<'_, '_, 'info, 'info, RouteOrder<'info>>,
+    shares_in: u64,
+    min_collateral_out: u64,
+  ) -> Result<()> {
+    let market_key = ctx.accounts.market.key();
+    let mut market = ctx.accounts.market.load_mut()?;
+    let now = Clock::get()?.unix_timestamp;
+    check_tradable(&mut market, now)?;
+    require!(shares_in > 0, PredictionError::ZeroAmount);
+
+    let taker = ctx.accounts.user.key();
+    let mut trade = TradeAccounts {
+      config: &ctx.accounts.config,
+      trader: taker,
+      new_trader: false,
+      transient: false,
+      allowlist_entry: ctx.accounts.allowlist_entry.as_deref(),
+      permit_gate: ctx.accounts.permit_gate.as_deref(),
+      instructions: ctx.accounts.instructions.as_ref(),
+      token_gate: ctx.accounts.token_gate.as_deref(),
+      gate_token_account: ctx.accounts.gate_token_account.as_deref(),
+      market_makers: ctx.accounts.market_makers.as_deref_mut(),
+      user_stats: ctx.accounts.user_stats.as_deref_mut(),
+      competition_entry: ctx.accounts.competition_entry.as_deref_mut(),
+      market_stats: ctx.accounts.market_stats.as_deref_mut(),
+      protocol_stats: ctx.accounts.protocol_stats.as_deref_mut(),
+      price_candles: ctx.accounts.price_candles.as_ref(),
+      trade_tape: ctx.accounts.trade_tape.as_ref(),
+      price_feed: ctx.accounts.price_feed.as_ref(),
+    };
+    // Book fills don't go through `execute_sell`
+    check_trade_gates(&market, market_key, &trade, false, now)?;
+
+    let position = &mut ctx.accounts.position;
+    require!(position.owner == taker, PredictionError::PositionOwnerMismatch);
+
+    let amm_price = market.implied_probability(0)?;
+    let book = &mut ctx.accounts.order_book;
+    let mut makers = ctx.remaining_accounts.iter();
+    let mut remaining = shares_in;
+    let mut book_proceeds = 0u64;
+
+    while let Some(bid) = book.bids.first_mut() {
+      if remaining == 0 || bid.price < amm_price {
+        break;
+      }
+      require_keys_neq!(bid.owner, taker, PredictionError::SelfTrade);
+      let fill = remaining.min(bid.shares);
+      let proceeds = if fill == bid.shares {
+        bid.collateral
+      } else {
+        mul_div(fill, bid.price, PRICE_SCALE)?
+      };
+
+      let maker_info = makers.next().ok_or(PredictionError::InvalidRemainingAccounts)?;
+      let mut maker = Account::<PositionV2>::try_from(maker_info)?;
+      require!(
+        maker.owner == bid.owner && maker.market == market_key,
+        PredictionError::InvalidRemainingAccounts
+      );
+      take_position_shares(position, 0, fill)?;
+      give_position_shares(&mut maker, 0, fill)?;
+      maker.exit(ctx.program_id)?;
+
+      bid.shares -= fill;
+      bid.collateral -= proceeds;
+      remaining -= fill;
+      book_proceeds += proceeds;
+      if bid.shares == 0 {
+        book.bids.remove(0);
+      }
+    }
+
+    let mut amm_proceeds = 0;
+    if remaining > 0 {
+      let net_out = execute_sell(&mut market, market_key, position, 0, remaining, 0, now, &mut trade)?;
+      market.debit_vault(net_out)?;
+
+      let seeds: &[&[u8]] = &[
+        b"vault_auth_v2",
+        market_key.as_ref(),
+        &[market.vault_auth_bump],
+      ];
+      amm_proceeds = transfer_signed(
+        &ctx.accounts.vault,
+        &mut ctx.accounts.custody,
+        &ctx.accounts.collateral_mint,
+        ctx.accounts.vault_authority.to_account_info(),
+        seeds,
+        ctx.accounts.token_program.to_account_info(),
+        net_out,
+      )?;
+    }
+
+    let collateral_out = book_proceeds
+      .checked_add(amm_proceeds)
+      .ok_or(PredictionError::MathOverflow)?;
+    require!(
+      collateral_out >= min_collateral_out,
+      PredictionError::SlippageExceeded
+    );
+    let account = &mut ctx.accounts.trading_account;
+    account.balance = account
+      .balance
+      .checked_add(collateral_out)
+      .ok_or(PredictionError::MathOverflow)?;
+
+    emit!(OrderRouted {
+      market: market_key,
+      taker,
+      side: OrderSide::Sell as u8,
+      book_amount: book_proceeds,
+      amm_amount: amm_proceeds,
+    });
+    Ok(())
+  }
+
+  /// Create an empty position for the caller, e.g. to receive converted or
+  /// transferred shares.
+  pub fn open_position(ctx: Context<OpenPosition>) -> Result<()> {
+    init_or_check_position(
+      &mut ctx.accounts.position,
+      ctx.accounts.market.key(),
+      ctx.accounts.owner.key(),
+      ctx.bumps.position,
+    )
+  }
+
+  /// Create a negative-risk group: mutually exclusive, exhaustive binary
+  /// markets (one per candidate). Winner-take-all groups resolve exactly one
+  /// member YES (`resolve_group`); proportional groups pay each member its
+  /// share of a distribution (`resolve_group_distribution`); ranked groups
+  /// pay by finishing place (`resolve_group_ranking`).
+  pub fn create_market_group(
+    ctx: Context<CreateMarketGroup>,
+    group_id: u64,
+    payout_mode: u8,
+  ) -> Result<()> {
+    require!(
+      payout_mode <= GROUP_PAYOUT_RANKED,
+      PredictionError::InvalidPayoutMode
+    );
+    let group = &mut ctx.accounts.group;
+    group.authority = ctx.accounts.authority.key();
+    group.group_id = group_id;
+    group.collateral_mint = ctx.accounts.collateral_mint.key();
+    group.members = Vec::new();
+    group.converted = 0;
+    group.resolved = false;
+    group.bump = ctx.bumps.group;
+    group.labels = Vec::new();
+    group.catch_all = None;
+    group.payout_mode = payout_mode;
+    group.rank_weights = Vec::new();
+    Ok(())
+  }
+
+  /// Add one of the authority's markets to a group, labelled with the
+  /// outcome it stands for (at most `MAX_OUTCOME_LABEL_LEN` bytes) so
+  /// wallets can render the group without fetching metadata. Membership is
+  /// frozen once the first conversion happens.
+  pub fn add_group_member(ctx: Context<AddGroupMember>, label: String) -> Result<()> {
+    require!(
+      label.len() <= MAX_OUTCOME_LABEL_LEN,
+      PredictionError::OutcomeLabelTooLong
+    );
+    let group_key = ctx.accounts.group.key();
+    let group = &mut ctx.accounts.group;
+    let mut market = ctx.accounts.market.load_mut()?;
+    require!(
+      market.authority == group.authority,
+      PredictionError::Unauthorized
+    );
+    require!(market.is_live(), PredictionError::InvalidMarketStatus);
+    require!(group.converted == 0, PredictionError::GroupFrozen);
+    require!(group.members.len() < MAX_GROUP_MEMBERS, PredictionError::GroupFull);
+    require!(market.group == Pubkey::default(), PredictionError::GroupedMarket);
+    require_keys_eq!(
+      market.collateral_mint,
+      group.collateral_mint,
+      PredictionError::InvalidTargetMarket
+    );
+    require!(
+      market.lst_stake_pool == Pubkey::default(),
+      PredictionError::InvalidStakePool
+    );
+    require!(market.scalar == 0, PredictionError::ScalarMarket);
+    require!(market.oracle_market == 0, PredictionError::OracleMarket);
+
+    market.group = group_key;
+    group.members.push(ctx.accounts.market.key());
+    group.labels.push(label);
+    Ok(())
+  }
+
+  /// Designate the "other / none of the above" member, which wins when the
+  /// group resolves to `UNLISTED_OUTCOME`. Fixed once conversions start.
+  pub fn set_group_catch_all(ctx: Context<SetGroupCatchAll>, member_index: u8) -> Result<()> {
+    let group = &mut ctx.accounts.group;
+    require!(group.converted == 0, PredictionError::GroupFrozen);
+    require!(!group.resolved, PredictionError::InvalidMarketStatus);
+    require!(
+      (member_index as usize) < group.members.len(),
+      PredictionError::InvalidOutcome
+    );
+    group.catch_all = Some(member_index);
+    Ok(())
+  }
+
+  /// Turn `amount` NO shares in one member into `amount` YES shares in every
+  /// other member. Remaining accounts: (market, caller's position) for each
+  /// other member, in group order; positions must already exist.
+  pub fn convert<'info>(
+    ctx: Context<'_, '_, 'info, 'info, ConvertPosition<'info>>,
+    amount: u64,
+  ) -> Result<()> {
+    require!(amount > 0, PredictionError::ZeroAmount);
+    let group = &mut ctx.accounts.group;
+    require!(!group.resolved, PredictionError::InvalidMarketStatus);
+    require!(
+      group.payout_mode == GROUP_PAYOUT_WINNER_TAKE_ALL,
+      PredictionError::WinnerTakeAllOnly
+    );
+    require!(group.members.len() >= 2, PredictionError::GroupTooSmall);
+
+    let source_key = ctx.accounts.market.key();
+    let now = Clock::get()?.unix_timestamp;
+    {
+      let mut market = ctx.accounts.market.load_mut()?;
+      market.refresh_phase(now);
+      require!(market.is_live(), PredictionError::InvalidMarketStatus);
+      require!(market.paused == 0, PredictionError::MarketPaused);
+
+      take_position_shares(&mut ctx.accounts.position, 1, amount)?;
+      market.total_no_shares = market
+        .total_no_shares
+        .checked_sub(amount)
+        .ok_or(PredictionError::MathOverflow)?;
+      market.group_no_claim = market
+        .group_no_claim
+        .checked_add(amount)
+        .ok_or(PredictionError::MathOverflow)?;
+    }
+
+    let others: Vec<Pubkey> = group
+      .members
+      .iter()
+      .copied()
+      .filter(|m| *m != source_key)
+      .collect();
+    require!(
+      ctx.remaining_accounts.len() == others.len() * 2,
+      PredictionError::InvalidRemainingAccounts
+    );
+
+    let owner = ctx.accounts.user.key();
+    for (member, pair) in others.iter().zip(ctx.remaining_accounts.chunks_exact(2)) {
+      let [market_info, position_info] = pair else {
+        unreachable!()
+      };
+      require_keys_eq!(market_info.key(), *member, PredictionError::InvalidRemainingAccounts);
+      let loader = AccountLoader::<MarketV3>::try_from(market_info)?;
+      let mut market = loader.load_mut()?;
+      market.refresh_phase(now);
+      require!(market.is_live(), PredictionError::InvalidMarketStatus);
+
+      let mut position = Account::<PositionV2>::try_from(position_info)?;
+      require!(
+        position.owner == owner && position.market == *member,
+        PredictionError::InvalidRemainingAccounts
+      );
+      give_position_shares(&mut position, 0, amount)?;
+      position.exit(ctx.program_id)?;
+
+      market.total_yes_shares = market
+        .total_yes_shares
+        .checked_add(amount)
+        .ok_or(PredictionError::MathOverflow)?;
+      market.group_backed_yes = market
+        .group_backed_yes
+        .checked_add(amount)
+        .ok_or(PredictionError::MathOverflow)?;
+    }
+
+    group.converted = group
+      .converted
+      .checked_add(amount)
+      .ok_or(PredictionError::MathOverflow)?;
+
+    emit!(PositionConverted {
+      group: group.key(),
+      owner,
+      market: source_key,
+      amount,
+    });
+    Ok(())
+  }
+
+  /// Resolve every member at once: `winner_index` resolves YES, the rest NO.
+  /// `UNLISTED_OUTCOME` (a result matching no listed member) resolves the
+  /// catch-all member YES instead of voiding the group. Each loser's `group_no_claim` moves to the winner's vault to back the
+  /// YES minted there by conversions. Remaining accounts: (market, vault,
+  /// vault authority) per member, in group order. Group members skip the
+  /// dispute window and registry sync.
+  pub fn resolve_group<'info>(
+    ctx: Context<'_, '_, 'info, 'info, ResolveGroup<'info>>,
+    winner_index: u8,
+  ) -> Result<()> {
+    let group = &mut ctx.accounts.group;
+    require!(!group.resolved, PredictionError::InvalidMarketStatus);
+    require!(
+      group.payout_mode == GROUP_PAYOUT_WINNER_TAKE_ALL,
+      PredictionError::WinnerTakeAllOnly
+    );
+    let winner_index = if winner_index == UNLISTED_OUTCOME {
+      group.catch_all.ok_or(PredictionError::NoCatchAllOutcome)?
+    } else {
+      winner_index
+    } as usize;
+    require!(winner_index < group.members.len(), PredictionError::InvalidOutcome);
+    require!(
+      ctx.remaining_accounts.len() == group.members.len() * 3,
+      PredictionError::InvalidRemainingAccounts
+    );
+
+    let accounts: Vec<&[AccountInfo<'info>]> = ctx.remaining_accounts.chunks_exact(3).collect();
+    for (member, triple) in group.members.iter().zip(&accounts) {
+      require_keys_eq!(triple[0].key(), *member, PredictionError::InvalidRemainingAccounts);
+    }
+
+    let winner_loader = AccountLoader::<MarketV3>::try_from(&accounts[winner_index][0])?;
+    let mut winner_vault = InterfaceAccount::<TokenAccount>::try_from(&accounts[winner_index][1])?;
+    let now = Clock::get()?.unix_timestamp;
+
+    for (i, triple) in accounts.iter().enumerate() {
+      if i == winner_index {
+        continue;
+      }
+      let [market_info, vault_info, vault_auth_info] = triple else {
+        unreachable!()
+      };
+      let loader = AccountLoader::<MarketV3>::try_from(market_info)?;
+      let mut market = loader.load_mut()?;
+      require!(market.is_live(), PredictionError::InvalidMarketStatus);
+      require!(market.yield_deployed == 0, PredictionError::YieldDeployed);
+      require_keys_eq!(market.vault, vault_info.key(), PredictionError::InvalidRemainingAccounts);
+
+      let mut vault = InterfaceAccount::<TokenAccount>::try_from(vault_info)?;
+      let owed = market.group_no_claim;
+      if owed > 0 {
+        let market_key = market_info.key();
+        let seeds: &[&[u8]] = &[
+          b"vault_auth_v2",
+          market_key.as_ref(),
+          &[market.vault_auth_bump],
+        ];
+        market.debit_vault(owed)?;
+        let received = transfer_signed(
+          &vault,
+          &mut winner_vault,
+          &ctx.accounts.collateral_mint,
+          vault_auth_info.clone(),
+          seeds,
+          ctx.accounts.token_program.to_account_info(),
+          owed,
+        )?;
+        winner_loader.load_mut()?.credit_vault(received)?;
+        market.group_no_claim = 0;
+        vault.reload()?;
+      }
+
+      finalize_group_member(&mut market, market_info.key(), 1, 0, vault.amount, now)?;
+    }
+
+    {
+      let mut winner = winner_loader.load_mut()?;
+      require!(winner.is_live(), PredictionError::InvalidMarketStatus);
+      require!(winner.yield_deployed == 0, PredictionError::YieldDeployed);
+      require_keys_eq!(winner.vault, winner_vault.key(), PredictionError::InvalidRemainingAccounts);
+      finalize_group_member(
+        &mut winner,
+        winner_loader.key(),
+        0,
+        0,
+        winner_vault.amount,
+        now,
+      )?;
+    }
+
+    group.resolved = true;
+    emit!(GroupResolved {
+      group: group.key(),
+      winner: group.members[winner_index],
+    });
+    Ok(())
+  }
+
+  /// Resolve a proportional group to a payout distribution: `payouts[i]`
+  /// (PRICE_SCALE, summing to PRICE_SCALE) is what one YES share of member
+  /// `i` pays, its NO gets the complement. Remaining accounts: (market,
+  /// vault) per member, in group order.
+  pub fn resolve_group_distribution<'info>(
+    ctx: Context<'_, '_, 'info, 'info, ResolveGroup<'info>>,
+    payouts: Vec<u64>,
+  ) -> Result<()> {
+    let group = &mut ctx.accounts.group;
+    require!(!group.resolved, PredictionError::InvalidMarketStatus);
+    require!(
+      group.payout_mode == GROUP_PAYOUT_PROPORTIONAL,
+      PredictionError::InvalidPayoutMode
+    );
+    require!(
+      payouts.len() == group.members.len()
+        && payouts
+          .iter()
+          .try_fold(0u64, |sum, p| sum.checked_add(*p))
+          == Some(PRICE_SCALE),
+      PredictionError::InvalidPayoutVector
+    );
+    require!(
+      ctx.remaining_accounts.len() == group.members.len() * 2,
+      PredictionError::InvalidRemainingAccounts
+    );
+
+    settle_group_payouts(
+      &group.members,
+      ctx.remaining_accounts,
+      &payouts,
+      Clock::get()?.unix_timestamp,
+    )?;
+
+    group.resolved = true;
+    emit!(GroupDistributionResolved {
+      group: group.key(),
+      payouts,
+    });
+    Ok(())
+  }
+
+  /// Set the payout per finishing place of a ranked group, e.g. podium
+  /// weights 60/30/10 as `[600_000, 300_000, 100_000]`. Fixed once set, so
+  /// traders know the weights before they trade.
+  pub fn set_rank_weights(ctx: Context<SetGroupRankWeights>, weights: Vec<u64>) -> Result<()> {
+    let group = &mut ctx.accounts.group;
+    require!(
+      group.payout_mode == GROUP_PAYOUT_RANKED,
+      PredictionError::InvalidPayoutMode
+    );
+    require!(group.rank_weights.is_empty(), PredictionError::RankWeightsAlreadySet);
+    require!(
+      !weights.is_empty()
+        && weights.len() <= MAX_GROUP_MEMBERS
+        && weights.iter().all(|w| *w > 0)
+        && weights.iter().try_fold(0u64, |sum, w| sum.checked_add(*w)) == Some(PRICE_SCALE),
+      PredictionError::InvalidPayoutVector
+    );
+    group.rank_weights = weights;
+    Ok(())
+  }
+
+  /// Resolve a ranked group from its finishing order: `ranking[k]` is the
+  /// member index placed k-th and its YES pays `rank_weights[k]`; unplaced
+  /// members resolve NO. Remaining accounts: (market, vault) per member, in
+  /// group order.
+  pub fn resolve_group_ranking<'info>(
+    ctx: Context<'_, '_, 'info, 'info, ResolveGroup<'info>>,
+    ranking: Vec<u8>,
+  ) -> Result<()> {
+    let group = &mut ctx.accounts.group;
+    require!(!group.resolved, PredictionError::InvalidMarketStatus);
+    require!(
+      group.payout_mode == GROUP_PAYOUT_RANKED,
+      PredictionError::InvalidPayoutMode
+    );
+    require!(!group.rank_weights.is_empty(), PredictionError::InvalidPayoutVector);
+    require!(
+      ranking.len() == group.rank_weights.len(),
+      PredictionError::InvalidRanking
+    );
+    require!(
+      ctx.remaining_accounts.len() == group.members.len() * 2,
+      PredictionError::InvalidRemainingAccounts
+    );
+
+    let mut payouts = vec![0u64; group.members.len()];
+    for (&member_index, &weight) in ranking.iter().zip(&group.rank_weights) {
+      let payout = payouts
+        .get_mut(member_index as usize)
+        .ok_or(PredictionError::InvalidRanking)?;
+      require!(*payout == 0, PredictionError::InvalidRanking);
+      *payout = weight;
+    }
+    settle_group_payouts(
+      &group.members,
+      ctx.remaining_accounts,
+      &payouts,
+      Clock::get()?.unix_timestamp,
+    )?;
+
+    group.resolved = true;
+    emit!(GroupRankingResolved {
+      group: group.key(),
+      ranking,
+    });
+    Ok(())
+  }
+
+  /// Claim everything the caller holds across a resolved group in one
+  /// instruction: YES in the winning member and NO in the losers (or both
+  /// sides of a member settled as a split). Remaining accounts: (market,
+  /// vault, vault authority, position) per member the caller holds, in any
+  /// order; positions that are already claimed or losing are skipped. SPL
+  /// collateral only.
+  pub fn claim_group<'info>(ctx: Context<'_, '_, 'info, 'info, ClaimGroup<'info>>) -> Result<()> {
+    let group_key = ctx.accounts.group.key();
+    require!(ctx.accounts.group.resolved, PredictionError::MarketNotResolved);
+    require!(
+      ctx.accounts.collateral_mint.key() != native_mint::ID,
+      PredictionError::InvalidRemainingAccounts
+    );
+    let members = ctx.remaining_accounts.chunks_exact(CLAIM_GROUP_LEN);
+    require!(
+      members.remainder().is_empty(),
+      PredictionError::InvalidRemainingAccounts
+    );
+
+    let owner = ctx.accounts.owner.key();
+    let now = Clock::get()?.unix_timestamp;
+    let mut markets = 0u32;
+    let mut total_payout = 0u64;
+
+    for member in members {
+      let [market_info, vault_info, vault_auth_info, position_info] = member else {
+        unreachable!()
+      };
+
+      let market_loader = AccountLoader::<MarketV3>::try_from(market_info)?;
+      let market_key = market_info.key();
+      let mut market = market_loader.load_mut()?;
+      require_keys_eq!(market.group, group_key, PredictionError::InvalidRemainingAccounts);
+
+      let (position_pda, _) = Pubkey::find_program_address(
+        &[b"position_v2", market_key.as_ref(), owner.as_ref()],
+        ctx.program_id,
+      );
+      require_keys_eq!(position_pda, position_info.key(), PredictionError::InvalidRemainingAccounts);
+      let mut position = Account::<PositionV2>::try_from(position_info)?;
+
+      let winning_shares = market
+        .position_winnings(&position)
+        .unwrap_or(0);
+      if position.claimed || winning_shares == 0 {
+        continue;
+      }
+
+      require_keys_eq!(market.vault, vault_info.key(), PredictionError::InvalidRemainingAccounts);
+      let vault = InterfaceAccount::<TokenAccount>::try_from(vault_info)?;
+      let (payout, _) = settle_claim(&mut market, market_key, &mut position, owner, now)?;
+
+      let seeds: &[&[u8]] = &[
+        b"vault_auth_v2",
+        market_key.as_ref(),
+        &[market.vault_auth_bump],
+      ];
+      pay_out_collateral(
+        &vault,
+        vault_auth_info.clone(),
+        seeds,
+        &ctx.accounts.collateral_mint,
+        Some(ctx.accounts.owner_collateral_ata.to_account_info()),
+        None,
+        ctx.accounts.owner.to_account_info(),
+        ctx.accounts.owner.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        ctx.accounts.system_program.to_account_info(),
+        payout,
+      )?;
+
+      position.exit(ctx.program_id)?;
+      markets += 1;
+      total_payout = total_payout
+        .checked_add(payout)
+        .ok_or(PredictionError::MathOverflow)?;
+    }
+    require!(total_payout > 0, PredictionError::NoWinnings);
+
+    emit!(GroupClaimed {
+      group: group_key,
+      owner,
+      markets,
+      payout: total_payout,
+    });
+    Ok(())
+  }
+
+  /// Admin deposits collateral into the per-mint parlay pool, the house
+  /// that pays out winning parlays and keeps losing stakes.
+  pub fn fund_parlay_pool(ctx: Context<FundParlayPool>, amount: u64) -> Result<()> {
+    require!(amount > 0, PredictionError::ZeroAmount);
+    let pool = &mut ctx.accounts.pool;
+    if pool.collateral_mint == Pubkey::default() {
+      pool.collateral_mint = ctx.accounts.collateral_mint.key();
+      pool.reserved = 0;
+      pool.bump = ctx.bumps.pool;
+    }
+
+    token_interface::transfer_checked(
+      CpiContext::new(
+        ctx.accounts.token_program.to_account_info(),
+        TransferChecked {
+          from: ctx.accounts.admin_collateral_ata.to_account_info(),
+          mint: ctx.accounts.collateral_mint.to_account_info(),
+          to: ctx.accounts.pool_vault.to_account_info(),
+          authority: ctx.accounts.admin.to_account_info(),
+        },
+      ),
+      amount,
+      ctx.accounts.collateral_mint.decimals,
+    )
+  }
+
+  /// Admin withdraws pool collateral not reserved for open parlays.
+  pub fn withdraw_parlay_pool(ctx: Context<WithdrawParlayPool>, amount: u64) -> Result<()> {
+    let free = ctx
+      .accounts
+      .pool_vault
+      .amount
+      .saturating_sub(ctx.accounts.pool.reserved);
+    require!(amount > 0 && amount <= free, PredictionError::InsufficientBalance);
+
+    let seeds: &[&[u8]] = &[b"parlay_auth", &[ctx.bumps.pool_authority]];
+    token_interface::transfer_checked(
+      CpiContext::new_with_signer(
+        ctx.accounts.token_program.to_account_info(),
+        TransferChecked {
+          from: ctx.accounts.pool_vault.to_account_info(),
+          mint: ctx.accounts.collateral_mint.to_account_info(),
+          to: ctx.accounts.admin_collateral_ata.to_account_info(),
+          authority: ctx.accounts.pool_authority.to_account_info(),
+        },
+        &[seeds],
+      ),
+      amount,
+      ctx.accounts.collateral_mint.decimals,
+    )
+  }
+
+  /// Stake collateral on every (market, outcome) leg resolving favourably.
+  /// Each leg is priced at the higher of its spot implied probability and
+  /// its candles' reference price, and rejected if the two are more than
+  /// MAX_PARLAY_LEG_DEVIATION_BPS apart, so a same-transaction trade can't
+  /// inflate the odds. The payout multiplies the legs' odds, less
+  /// PARLAY_HOUSE_EDGE_BPS, and is reserved from the parlay pool. Remaining
+  /// accounts, per leg in the order of `args.outcomes`: [market,
+  /// price_candles].
+  pub fn place_parlay<'info>(
+    ctx: Context<'_, '_, 'info, 'info, PlaceParlay<'info>>,
+    args: PlaceParlayArgs,
+  ) -> Result<()> {
+    let legs_len = args.outcomes.len();
+    require!(
+      (2..=MAX_PARLAY_LEGS).contains(&legs_len),
+      PredictionError::InvalidParlayLegs
+    );
+    require!(
+      ctx.remaining_accounts.len() == legs_len * PARLAY_LEG_GROUP_LEN,
+      PredictionError::InvalidRemainingAccounts
+    );
+    require!(args.stake > 0, PredictionError::ZeroAmount);
+
+    let now = Clock::get()?.unix_timestamp;
+    let mint = ctx.accounts.collateral_mint.key();
+    let mut legs: Vec<ParlayLeg> = Vec::with_capacity(legs_len);
+    for (group, &outcome) in ctx
+      .remaining_accounts
+      .chunks(PARLAY_LEG_GROUP_LEN)
+      .zip(&args.outcomes)
+    {
+      let (info, candles_info) = (&group[0], &group[1]);
+      require!(outcome <= 1, PredictionError::InvalidOutcome);
+      require!(
+        legs.iter().all(|leg| leg.market != info.key()),
+        PredictionError::InvalidParlayLegs
+      );
+      let loader = AccountLoader::<MarketV3>::try_from(info)?;
+      let mut market = loader.load_mut()?;
+      check_tradable(&mut market, now)?;
+      require_keys_eq!(market.collateral_mint, mint, PredictionError::InvalidParlayLegs);
+
+      let candles_loader = AccountLoader::<PriceCandles>::try_from(candles_info)?;
+      let candles = candles_loader.load()?;
+      require_keys_eq!(candles.market, info.key(), PredictionError::InvalidRemainingAccounts);
+      let yes_reference = candles
+        .reference_price(now)
+        .ok_or(PredictionError::PriceCandlesRequired)?;
+      let reference = match outcome {
+        0 => yes_reference,
+        _ => PRICE_SCALE.saturating_sub(yes_reference),
+      };
+
+      let spot = market.implied_probability(outcome)?;
+      require!(
+        (spot.abs_diff(reference) as u128) * (BPS_DENOM as u128)
+          <= (reference as u128) * (MAX_PARLAY_LEG_DEVIATION_BPS as u128),
+        PredictionError::ParlayLegPriceDeviation
+      );
+      let entry_price = spot.max(reference);
+      require!(entry_price > 0, PredictionError::InvalidParlayLegs);
+      legs.push(ParlayLeg {
+        market: info.key(),
+        outcome,
+        entry_price,
+      });
+    }
+
+    let stake = deposit_collateral(
+      ctx.accounts.owner.to_account_info(),
+      ctx.accounts.owner_collateral_ata.as_ref().map(|a| a.to_account_info()),
+      &mut ctx.accounts.pool_vault,
+      &ctx.accounts.collateral_mint,
+      ctx.accounts.token_program.to_account_info(),
+      ctx.accounts.system_program.to_account_info(),
+      args.stake,
+    )?;
+
+    let mut payout = stake;
+    for leg in &legs {
+      payout = mul_div(payout, PRICE_SCALE, leg.entry_price)?;
+    }
+    payout = mul_div(payout, BPS_DENOM - PARLAY_HOUSE_EDGE_BPS, BPS_DENOM)?;
+    require!(payout >= args.min_payout, PredictionError::SlippageExceeded);
+
+    let pool = &mut ctx.accounts.pool;
+    pool.reserved = pool
+      .reserved
+      .checked_add(payout)
+      .ok_or(PredictionError::MathOverflow)?;
+    ctx.accounts.pool_vault.reload()?;
+    require!(
+      ctx.accounts.pool_vault.amount >= pool.reserved,
+      PredictionError::InsufficientPoolLiquidity
+    );
+
+    let parlay = &mut ctx.accounts.parlay;
+    parlay.owner = ctx.accounts.owner.key();
+    parlay.parlay_id = args.parlay_id;
+    parlay.collateral_mint = mint;
+    parlay.stake = stake;
+    parlay.payout = payout;
+    parlay.legs = legs;
+    parlay.bump = ctx.bumps.parlay;
+
+    emit!(ParlayPlaced {
+      parlay: parlay.key(),
+      owner: parlay.owner,
+      legs: legs_len as u8,
+      stake,
+      payout,
+    });
+    Ok(())
+  }
+
+  /// Permissionless: settle a parlay once any leg has lost or every leg is
+  /// final. A cancelled leg, or one resolved Invalid, is a push and drops
+  /// out of the odds. Winnings go
+  /// to the owner's token account; the parlay account closes to the owner.
+  /// Remaining accounts: the leg markets, in parlay order.
+  pub fn settle_parlay<'info>(ctx: Context<'_, '_, 'info, 'info, SettleParlay<'info>>) -> Result<()> {
+    let parlay = &ctx.accounts.parlay;
+    require!(
+      ctx.remaining_accounts.len() == parlay.legs.len(),
+      PredictionError::InvalidRemainingAccounts
+    );
+
+    let now = Clock::get()?.unix_timestamp;
+    let mut payout = parlay.payout;
+    let mut lost = false;
+    let mut pending = false;
+    for (info, leg) in ctx.remaining_accounts.iter().zip(&parlay.legs) {
+      require_keys_eq!(info.key(), leg.market, PredictionError::InvalidRemainingAccounts);
+      let loader = AccountLoader::<MarketV3>::try_from(info)?;
+      let market = loader.load()?;
+
+      let refunded = market.winning_outcome == OUTCOME_REFUND_AT_COST;
+      let finalized = market.status == MarketStatus::Resolved as u8 && now >= market.dispute_deadline;
+      if market.status == MarketStatus::Cancelled as u8 || (finalized && refunded) {
+        payout = mul_div(payout, leg.entry_price, PRICE_SCALE)?;
+      } else if finalized {
+        // A split resolution pays the leg its fraction of a full win
+        let won = match leg.outcome {
+          0 => market.winning_shares(PRICE_SCALE, 0)?,
+          _ => market.winning_shares(0, PRICE_SCALE)?,
+        };
+        payout = mul_div(payout, won, PRICE_SCALE)?;
+        lost |= won == 0;
+      } else {
+        pending = true;
+      }
+    }
+    require!(lost || !pending, PredictionError::ParlayNotSettled);
+
+    let pool = &mut ctx.accounts.pool;
+    pool.reserved = pool
+      .reserved
+      .checked_sub(parlay.payout)
+      .ok_or(PredictionError::MathOverflow)?;
+
+    let paid = if lost { 0 } else { payout };
+    if paid > 0 {
+      let seeds: &[&[u8]] = &[b"parlay_auth", &[ctx.bumps.pool_authority]];
+      token_interface::transfer_checked(
+        CpiContext::new_with_signer(
+          ctx.accounts.token_program.to_account_info(),
+          TransferChecked {
+            from: ctx.accounts.pool_vault.to_account_info(),
+            mint: ctx.accounts.collateral_mint.to_account_info(),
+            to: ctx.accounts.owner_collateral_ata.to_account_info(),
+            authority: ctx.accounts.pool_authority.to_account_info(),
+          },
+          &[seeds],
+        ),
+        paid,
+        ctx.accounts.collateral_mint.decimals,
+      )?;
+    }
+
+    emit!(ParlaySettled {
+      parlay: parlay.key(),
+      owner: parlay.owner,
+      won: !lost,
+      payout: paid,
+    });
+    Ok(())
+  }
+
+  /// Create the SPL mint for one outcome of a market. Permissionless; the
+  /// market's vault authority is the mint authority.
+  pub fn init_outcome_mint(_ctx: Context<InitOutcomeMint>, outcome: u8) -> Result<()> {
+    require!(outcome <= 1, PredictionError::InvalidOutcome);
+    Ok(())
+  }
+
+  /// Move `amount` shares out of the caller's position into outcome tokens.
+  /// Tokens stay counted in the market's share totals.
+  pub fn tokenize_shares(ctx: Context<TokenizeShares>, outcome: u8, amount: u64) -> Result<()> {
+    require!(amount > 0, PredictionError::ZeroAmount);
+    take_position_shares(&mut ctx.accounts.position, outcome, amount)?;
+
+    let market_key = ctx.accounts.market.key();
+    let seeds: &[&[u8]] = &[
+      b"vault_auth_v2",
+      market_key.as_ref(),
+      &[ctx.accounts.market.load()?.vault_auth_bump],
+    ];
+    token_interface::mint_to(
+      CpiContext::new_with_signer(
+        ctx.accounts.token_program.to_account_info(),
+        MintTo {
+          mint: ctx.accounts.outcome_mint.to_account_info(),
+          to: ctx.accounts.user_outcome_ata.to_account_info(),
+          authority: ctx.accounts.vault_authority.to_account_info(),
+        },
+        &[seeds],
+      ),
+      amount,
+    )
+  }
+
+  /// Burn outcome tokens back into shares in the caller's position.
+  pub fn redeem_outcome_tokens(
+    ctx: Context<RedeemOutcomeTokens>,
+    outcome: u8,
+    amount: u64,
+  ) -> Result<()> {
+    require!(amount > 0, PredictionError::ZeroAmount);
+    burn_outcome_tokens(
+      &ctx.accounts.outcome_mint,
+      &ctx.accounts.user_outcome_ata,
+      ctx.accounts.user.to_account_info(),
+      ctx.accounts.token_program.to_account_info(),
+      amount,
+    )?;
+
+    let position = &mut ctx.accounts.position;
+    init_or_check_position(
+      position,
+      ctx.accounts.market.key(),
+      ctx.accounts.user.key(),
+      ctx.bumps.position,
+    )?;
+    give_position_shares(position, outcome, amount)
+  }
+
+  /// After resolution, burn winning outcome tokens for their pro-rata share
+  /// of the payable collateral, as `claim_winnings_v2` does for positions.
+  pub fn claim_outcome_tokens(ctx: Context<ClaimOutcomeTokens>, outcome: u8, amount: u64) -> Result<()> {
+    require!(amount > 0, PredictionError::ZeroAmount);
+    let market_key = ctx.accounts.market.key();
+    let mut market = ctx.accounts.market.load_mut()?;
+    require!(
+      market.is_settled(),
+      PredictionError::MarketNotResolved
+    );
+    require!(
+      Clock::get()?.unix_timestamp >= market.dispute_deadline,
+      PredictionError::DisputeWindowOpen
+    );
+    require!(
+      Clock::get()?.unix_timestamp >= market.claims_open_at()?,
+      PredictionError::ClaimDelayActive
+    );
+    require!(market.unclaimed_swept == 0, PredictionError::ClaimWindowClosed);
+    let winning_amount = match outcome {
+      0 => market.winning_shares(amount, 0)?,
+      1 => market.winning_shares(0, amount)?,
+      _ => return err!(PredictionError::InvalidOutcome),
+    };
+    require!(winning_amount > 0, PredictionError::NoWinnings);
+
+    burn_outcome_tokens(
+      &ctx.accounts.outcome_mint,
+      &ctx.accounts.user_outcome_ata,
+      ctx.accounts.user.to_account_info(),
+      ctx.accounts.token_program.to_account_info(),
+      amount,
+    )?;
+
+    require!(market.resolved_total_winning_shares > 0, PredictionError::NoWinnings);
+    let payout = market.pay_winnings(winning_amount)?;
+    require!(payout > 0, PredictionError::NoWinnings);
+
+    let seeds: &[&[u8]] = &[
+      b"vault_auth_v2",
+      market_key.as_ref(),
+      &[market.vault_auth_bump],
+    ];
+    pay_out_collateral(
+      &ctx.accounts.vault,
+      ctx.accounts.vault_authority.to_account_info(),
+      seeds,
+      &ctx.accounts.collateral_mint,
+      ctx.accounts.user_collateral_ata.as_ref().map(|a| a.to_account_info()),
+      ctx.accounts.wsol_unwrap.as_ref().map(|a| a.to_account_info()),
+      ctx.accounts.user.to_account_info(),
+      ctx.accounts.payer.to_account_info(),
+      ctx.accounts.token_program.to_account_info(),
+      ctx.accounts.system_program.to_account_info(),
+      payout,
+    )
+  }
+
+  /// Exact-in swap between the collateral mint and one of the market's
+  /// outcome mints against the CPMM curve, in the account shape aggregators
+  /// such as Jupiter route through. Collateral in mints outcome tokens;
+  /// outcome tokens in are burned and paid out in collateral. Gated markets
+  /// don't route.
+  pub fn swap(ctx: Context<Swap>, amount_in: u64, minimum_amount_out: u64) -> Result<()> {
+    require!(amount_in > 0, PredictionError::ZeroAmount);
+    let market_key = ctx.accounts.market.key();
+    let mut market = ctx.accounts.market.load_mut()?;
+
+    let now = Clock::get()?.unix_timestamp;
+    check_tradable(&mut market, now)?;
+
+    let source_mint = ctx.accounts.source_mint.key();
+    let destination_mint = ctx.accounts.destination_mint.key();
+    let (outcome_mint, buying) = if source_mint == market.collateral_mint {
+      (destination_mint, true)
+    } else if destination_mint == market.collateral_mint {
+      (source_mint, false)
+    } else {
+      return err!(PredictionError::InvalidSwapMints);
+    };
+    let outcome_index = (0..=1u8)
+      .find(|outcome| {
+        Pubkey::find_program_address(
+          &[b"outcome_mint", market_key.as_ref(), &[*outcome]],
+          ctx.program_id,
+        )
+        .0 == outcome_mint
+      })
+      .ok_or(PredictionError::InvalidSwapMints)?;
+
+    // Shares only pass through: tokens in become shares to sell, shares
+    // bought leave as tokens
+    let mut position = PositionV2 {
+      market: market_key,
+      owner: ctx.accounts.user_transfer_authority.key(),
+      yes_shares: 0,
+      no_shares: 0,
+      claimed: false,
+      bump: 0,
+      cost_basis: 0,
+    };
+    let seeds: &[&[u8]] = &[
+      b"vault_auth_v2",
+      market_key.as_ref(),
+      &[market.vault_auth_bump],
+    ];
+
+    // No gate accounts in this shape, so gated markets are refused
+    let mut trade = TradeAccounts {
+      config: &ctx.accounts.config,
+      trader: ctx.accounts.user_transfer_authority.key(),
+      new_trader: false,
+      transient: true,
+      allowlist_entry: None,
+      permit_gate: None,
+      instructions: None,
+      token_gate: None,
+      gate_token_account: None,
+      market_makers: None,
+      user_stats: None,
+      competition_entry: None,
+      market_stats: ctx.accounts.market_stats.as_deref_mut(),
+      protocol_stats: ctx.accounts.protocol_stats.as_deref_mut(),
+      price_candles: ctx.accounts.price_candles.as_ref(),
+      trade_tape: ctx.accounts.trade_tape.as_ref(),
+      price_feed: ctx.accounts.price_feed.as_ref(),
+    };
+    let amount_out = if buying {
+      let vault_before = ctx.accounts.vault.amount;
+      token_interface::transfer_checked(
+        CpiContext::new(
+          ctx.accounts.token_program.to_account_info(),
+          TransferChecked {
+            from: ctx.accounts.user_source_token_account.to_account_info(),
+            mint: ctx.accounts.source_mint.to_account_info(),
+            to: ctx.accounts.vault.to_account_info(),
+            authority: ctx.accounts.user_transfer_authority.to_account_info(),
+          },
+        ),
+        amount_in,
+        ctx.accounts.source_mint.decimals,
+      )?;
+      ctx.accounts.vault.reload()?;
+      let received = ctx
+        .accounts
+        .vault
+        .amount
+        .checked_sub(vault_before)
+        .ok_or(PredictionError::MathOverflow)?;
+      require!(received > 0, PredictionError::ZeroAmount);
+      market.credit_vault(received)?;
+
+      let shares_out = execute_buy(
+        &mut market,
+        market_key,
+        &mut position,
+        outcome_index,
+        received,
+        minimum_amount_out,
+        now,
+        &mut trade,
+      )?;
+
+      token_interface::mint_to(
+        CpiContext::new_with_signer(
+          ctx.accounts.token_program.to_account_info(),
+          MintTo {
+            mint: ctx.accounts.destination_mint.to_account_info(),
+            to: ctx.accounts.user_destination_token_account.to_account_info(),
+            authority: ctx.accounts.vault_authority.to_account_info(),
+          },
+          &[seeds],
+        ),
+        shares_out,
+      )?;
+      shares_out
+    } else {
+      burn_outcome_tokens(
+        &ctx.accounts.source_mint,
+        &ctx.accounts.user_source_token_account,
+        ctx.accounts.user_transfer_authority.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        amount_in,
+      )?;
+      give_position_shares(&mut position, outcome_index, amount_in)?;
+
+      let net_out = execute_sell(
+        &mut market,
+        market_key,
+        &mut position,
+        outcome_index,
+        amount_in,
+        minimum_amount_out,
+        now,
+        &mut trade,
+      )?;
+      market.debit_vault(net_out)?;
+
+      transfer_signed(
+        &ctx.accounts.vault,
+        &mut ctx.accounts.user_destination_token_account,
+        &ctx.accounts.destination_mint,
+        ctx.accounts.vault_authority.to_account_info(),
+        seeds,
+        ctx.accounts.token_program.to_account_info(),
+        net_out,
+      )?;
+      net_out
+    };
+    emit!(SwapExecuted {
+      market: market_key,
+      source_mint,
+      destination_mint,
+      amount_in,
+      amount_out,
+    });
+    Ok(())
+  }
+
+  /// Turn on compressed positions for a market. `merkle_tree` must already be
+  /// allocated (owned by spl-account-compression, sized for `max_depth` and
+  /// `max_buffer_size`); it is initialized here with the market's
+  /// `compressed_positions` PDA as tree authority.
+  pub fn init_compressed_positions(
+    ctx: Context<InitCompressedPositions>,
+    max_depth: u32,
+    max_buffer_size: u32,
+  ) -> Result<()> {
+    let market_key = ctx.accounts.market.key();
+    {
+      let market = ctx.accounts.market.load()?;
+      let signer = ctx.accounts.authority.key();
+      require!(
+        signer == market.authority || signer == ctx.accounts.config.admin,
+        PredictionError::Unauthorized
+      );
+      require!(market.is_live(), PredictionError::InvalidMarketStatus);
+    }
+
+    let tree = &mut ctx.accounts.compressed_positions;
+    tree.market = market_key;
+    tree.merkle_tree = ctx.accounts.merkle_tree.key();
+    tree.num_leaves = 0;
+    tree.bump = ctx.bumps.compressed_positions;
+
+    let mut args = max_depth.to_le_bytes().to_vec();
+    args.extend_from_slice(&max_buffer_size.to_le_bytes());
+    invoke_compression(
+      "init_empty_merkle_tree",
+      &args,
+      &ctx.accounts.compressed_positions,
+      ctx.accounts.merkle_tree.to_account_info(),
+      ctx.accounts.noop_program.to_account_info(),
+      ctx.accounts.compression_program.to_account_info(),
+      &[],
+    )?;
+
+    emit!(CompressedPositionsInitialized {
+      market: market_key,
+      merkle_tree: ctx.accounts.merkle_tree.key(),
+      max_depth,
+      max_buffer_size,
+    });
+    Ok(())
+  }
+
+  /// `buy_shares` that records the shares as a leaf of the market's
+  /// position tree instead of a `PositionV2` account, so a tiny bet costs no
+  /// rent. Every buy appends its own leaf; indexers rebuild the tree from
+  /// `CompressedBuy` events. Gated markets don't support compressed buys.
+  pub fn buy_compressed(
+    ctx: Context<BuyCompressed>,
+    outcome_index: u8,
+    max_collateral_in: u64,
+    min_shares_out: u64,
+  ) -> Result<()> {
+    let market_key = ctx.accounts.market.key();
+    let mut market = ctx.accounts.market.load_mut()?;
+    let user = ctx.accounts.user.key();
+
+    let now = Clock::get()?.unix_timestamp;
+    check_tradable(&mut market, now)?;
+    require!(outcome_index <= 1, PredictionError::InvalidOutcome);
+    require!(max_collateral_in > 0, PredictionError::ZeroAmount);
+
+    let received = deposit_collateral(
+      ctx.accounts.user.to_account_info(),
+      ctx.accounts.user_collateral_ata.as_ref().map(|a| a.to_account_info()),
+      &mut ctx.accounts.vault,
+      &ctx.accounts.collateral_mint,
+      ctx.accounts.token_program.to_account_info(),
+      ctx.accounts.system_program.to_account_info(),
+      max_collateral_in,
+    )?;
+    market.credit_vault(received)?;
+
+    let mut position = PositionV2 {
+      market: market_key,
+      owner: user,
+      yes_shares: 0,
+      no_shares: 0,
+      claimed: false,
+      bump: 0,
+      cost_basis: 0,
+    };
+    // No gate accounts here, so gated markets are refused
+    let mut trade = TradeAccounts {
+      config: &ctx.accounts.config,
+      trader: user,
+      new_trader: true,
+      transient: true,
+      allowlist_entry: None,
+      permit_gate: None,
+      instructions: None,
+      token_gate: None,
+      gate_token_account: None,
+      market_makers: None,
+      user_stats: None,
+      competition_entry: None,
+      market_stats: ctx.accounts.market_stats.as_deref_mut(),
+      protocol_stats: ctx.accounts.protocol_stats.as_deref_mut(),
+      price_candles: ctx.accounts.price_candles.as_ref(),
+      trade_tape: ctx.accounts.trade_tape.as_ref(),
+      price_feed: ctx.accounts.price_feed.as_ref(),
+    };
+    let shares_out = execute_buy(
+      &mut market,
+      market_key,
+      &mut position,
+      outcome_index,
+      received,
+      min_shares_out,
+      now,
+      &mut trade,
+    )?;
+
+    let leaf_index = ctx.accounts.compressed_positions.num_leaves;
+    let leaf = compressed_leaf(&market_key, &user, outcome_index, shares_out, leaf_index);
+    invoke_compression(
+      "append",
+      &leaf,
+      &ctx.accounts.compressed_positions,
+      ctx.accounts.merkle_tree.to_account_info(),
+      ctx.accounts.noop_program.to_account_info(),
+      ctx.accounts.compression_program.to_account_info(),
+      &[],
+    )?;
+    let tree = &mut ctx.accounts.compressed_positions;
+    tree.num_leaves = leaf_index
+      .checked_add(1)
+      .ok_or(PredictionError::MathOverflow)?;
+
+    emit!(CompressedBuy {
+      market: market_key,
+      owner: user,
+      leaf_index,
+      outcome: outcome_index,
+      shares: shares_out,
+      leaf,
+    });
+    Ok(())
+  }
+
+  /// Claim the winnings of one compressed position. The leaf is proven
+  /// against `root` (proof nodes as remaining accounts) and replaced with an
+  /// empty leaf, so it can't be claimed twice.
+  pub fn claim_compressed<'info>(
+    ctx: Context<'_, '_, 'info, 'info, ClaimCompressed<'info>>,
+    root: [u8; 32],
+    leaf_index: u64,
+    outcome: u8,
+    shares: u64,
+  ) -> Result<()> {
+    let market_key = ctx.accounts.market.key();
+    let mut market = ctx.accounts.market.load_mut()?;
+    let now = Clock::get()?.unix_timestamp;
+    require!(market.is_settled(), PredictionError::MarketNotResolved);
+    require!(now >= market.dispute_deadline, PredictionError::DisputeWindowOpen);
+    require!(now >= market.claims_open_at()?, PredictionError::ClaimDelayActive);
+    require!(market.unclaimed_swept == 0, PredictionError::ClaimWindowClosed);
+    let winning_amount = match outcome {
+      0 => market.winning_shares(shares, 0)?,
+      1 => market.winning_shares(0, shares)?,
+      _ => return err!(PredictionError::InvalidOutcome),
+    };
+    require!(winning_amount > 0, PredictionError::NoWinnings);
+
+    let user = ctx.accounts.user.key();
+    let leaf = compressed_leaf(&market_key, &user, outcome, shares, leaf_index);
+    let index = u32::try_from(leaf_index).map_err(|_| PredictionError::MathOverflow)?;
+    let mut args = root.to_vec();
+    args.extend_from_slice(&leaf);
+    args.extend_from_slice(&[0u8; 32]);
+    args.extend_from_slice(&index.to_le_bytes());
+    invoke_compression(
+      "replace_leaf",
+      &args,
+      &ctx.accounts.compressed_positions,
+      ctx.accounts.merkle_tree.to_account_info(),
+      ctx.accounts.noop_program.to_account_info(),
+      ctx.accounts.compression_program.to_account_info(),
+      ctx.remaining_accounts,
+    )?;
+
+    require!(market.resolved_total_winning_shares > 0, PredictionError::NoWinnings);
+    let payout = market.pay_winnings(winning_amount)?;
+    require!(payout > 0, PredictionError::NoWinnings);
+
+    let seeds: &[&[u8]] = &[
+      b"vault_auth_v2",
+      market_key.as_ref(),
+      &[market.vault_auth_bump],
+    ];
+    pay_out_collateral(
+      &ctx.accounts.vault,
+      ctx.accounts.vault_authority.to_account_info(),
+      seeds,
+      &ctx.accounts.collateral_mint,
+      ctx.accounts.user_collateral_ata.as_ref().map(|a| a.to_account_info()),
+      ctx.accounts.wsol_unwrap.as_ref().map(|a| a.to_account_info()),
+      ctx.accounts.user.to_account_info(),
+      ctx.accounts.payer.to_account_info(),
+      ctx.accounts.token_program.to_account_info(),
+      ctx.accounts.system_program.to_account_info(),
+      payout,
+    )?;
+
+    emit!(CompressedClaimed {
+      market: market_key,
+      owner: user,
+      leaf_index,
+      payout,
+    });
+    Ok(())
+  }
+
+  /// Switch a settled market to Merkle-distributor payouts: the resolver
+  /// (or the config admin as crank) commits the root of the off-chain list
+  /// of per-user payouts, computed from the positions at resolution. Only
+  /// before any position has been claimed; position claims are closed after.
+  pub fn commit_payout_root(
+    ctx: Context<CommitPayoutRoot>,
+    root: [u8; 32],
+    total_payout: u64,
+  ) -> Result<()> {
+    let market_key = ctx.accounts.market.key();
+    let mut market = ctx.accounts.market.load_mut()?;
+    let signer = ctx.accounts.authority.key();
+    require!(
+      signer == market.resolver() || signer == ctx.accounts.config.admin,
+      PredictionError::Unauthorized
+    );
+    require!(market.is_settled(), PredictionError::MarketNotResolved);
+    let now = Clock::get()?.unix_timestamp;
+    require!(now >= market.dispute_deadline, PredictionError::DisputeWindowOpen);
+    require!(market.unclaimed_swept == 0, PredictionError::ClaimWindowClosed);
+    require!(market.claimed_winning_shares == 0, PredictionError::AlreadyClaimed);
+    require!(total_payout > 0, PredictionError::ZeroAmount);
+    require!(
+      total_payout <= market.resolved_payable,
+      PredictionError::PayoutExceedsPayable
+    );
+    market.payout_distributor = 1;
+
+    let distributor = &mut ctx.accounts.payout_distributor;
+    distributor.market = market_key;
+    distributor.root = root;
+    distributor.total_payout = total_payout;
+    distributor.claimed = 0;
+    distributor.num_claims = 0;
+    distributor.bump = ctx.bumps.payout_distributor;
+
+    emit!(PayoutRootCommitted {
+      market: market_key,
+      root,
+      total_payout,
+    });
+    Ok(())
+  }
+
+  /// Claim the payout at `index` of a committed distributor by proving the
+  /// `distributor_leaf` against its root. The claim receipt PDA makes each
+  /// index claimable once.
+  pub fn claim_from_distributor(
+    ctx: Context<ClaimFromDistributor>,
+    index: u64,
+    amount: u64,
+    proof: Vec<[u8; 32]>,
+  ) -> Result<()> {
+    let market_key = ctx.accounts.market.key();
+    let mut market = ctx.accounts.market.load_mut()?;
+    let now = Clock::get()?.unix_timestamp;
+    require!(now >= market.claims_open_at()?, PredictionError::ClaimDelayActive);
+    require!(market.unclaimed_swept == 0, PredictionError::ClaimWindowClosed);
+    require!(amount > 0, PredictionError::NoWinnings);
+
+    let user = ctx.accounts.user.key();
+    let distributor = &mut ctx.accounts.payout_distributor;
+    let leaf = distributor_leaf(index, &user, amount);
+    require!(
+      verify_merkle_proof(&proof, distributor.root, leaf),
+      PredictionError::InvalidMerkleProof
+    );
+    distributor.claimed = distributor
+      .claimed
+      .checked_add(amount)
+      .ok_or(PredictionError::MathOverflow)?;
+    require!(
+      distributor.claimed <= distributor.total_payout,
+      PredictionError::PayoutExceedsPayable
+    );
+    distributor.num_claims += 1;
+    market.debit_vault(amount)?;
+
+    let receipt = &mut ctx.accounts.claim_receipt;
+    receipt.claimant = user;
+    receipt.amount = amount;
+    receipt.claimed_at = now;
+
+    let seeds: &[&[u8]] = &[
+      b"vault_auth_v2",
+      market_key.as_ref(),
+      &[market.vault_auth_bump],
+    ];
+    pay_out_collateral(
+      &ctx.accounts.vault,
+      ctx.accounts.vault_authority.to_account_info(),
+      seeds,
+      &ctx.accounts.collateral_mint,
+      ctx.accounts.user_collateral_ata.as_ref().map(|a| a.to_account_info()),
+      ctx.accounts.wsol_unwrap.as_ref().map(|a| a.to_account_info()),
+      ctx.accounts.user.to_account_info(),
+      ctx.accounts.payer.to_account_info(),
+      ctx.accounts.token_program.to_account_info(),
+      ctx.accounts.system_program.to_account_info(),
+      amount,
+    )?;
+
+    emit!(DistributorClaimed {
+      market: market_key,
+      owner: user,
+      index,
+      payout: amount,
+    });
+    Ok(())
+  }
+
+  /// Make this market conditional on `parent_outcome` of its parent: its
+  /// collateral must be that outcome's token. Set before the first trade.
+  pub fn set_parent_market(ctx: Context<SetParentMarket>, parent_outcome: u8) -> Result<()> {
+    require!(parent_outcome <= 1, PredictionError::InvalidOutcome);
+    let parent_key = ctx.accounts.parent_market.key();
+    let mut market = ctx.accounts.market.load_mut()?;
+    require!(market.trade_count == 0, PredictionError::InvalidMarketStatus);
+    require!(
+      market.parent_market == Pubkey::default(),
+      PredictionError::InvalidTargetMarket
+    );
+
+    let (outcome_mint, _) = Pubkey::find_program_address(
+      &[b"outcome_mint", parent_key.as_ref(), &[parent_outcome]],
+      ctx.program_id,
+    );
+    require_keys_eq!(
+      market.collateral_mint,
+      outcome_mint,
+      PredictionError::InvalidTargetMarket
+    );
+    {
+      let parent = ctx.accounts.parent_market.load()?;
+      require!(parent.end_time <= market.end_time, PredictionError::InvalidTargetMarket);
+    }
+
+    market.parent_market = parent_key;
+    market.parent_outcome = parent_outcome;
+    Ok(())
+  }
+
+  /// Create a recurring market series. Instances are created one period at a
+  /// time with `create_next_in_series`.
+  pub fn create_market_series(ctx: Context<CreateMarketSeries>, args: CreateSeriesArgs) -> Result<()> {
+    check_collateral_allowed(&ctx.accounts.config, &ctx.accounts.collateral_mint)?;
+    require!(args.duration > 0, PredictionError::InvalidStartTime);
+    require!(args.initial_liquidity > 0, PredictionError::InvalidLiquidity);
+    require!(
+      args.question_hash != [0u8; 32],
+      PredictionError::InvalidQuestionHash
+    );
+    require!(
+      args.creator_fee_share_bps <= ctx.accounts.config.creator_fee_share_bps,
+      PredictionError::InvalidFeeShares
+    );
+    MarketCategory::try_from(args.metadata.category)?;
+    encode_metadata_uri(&args.metadata_uri)?;
+    encode_metadata_uri(&args.metadata.image_uri)?;
+    encode_metadata_uri(&args.metadata.rules_uri)?;
+
+    let series = &mut ctx.accounts.series;
+    series.authority = ctx.accounts.authority.key();
+    series.series_id = args.series_id;
+    series.collateral_mint = ctx.accounts.collateral_mint.key();
+    series.question_hash = args.question_hash;
+    series.metadata_uri = args.metadata_uri;
+    series.category = args.metadata.category;
+    series.tags = args.metadata.tags;
+    series.image_uri = args.metadata.image_uri;
+    series.rules_uri = args.metadata.rules_uri;
+    series.oracle = args.oracle;
+    series.duration = args.duration;
+    series.initial_liquidity = args.initial_liquidity;
+    series.creator_fee_share_bps = args.creator_fee_share_bps;
+    series.next_index = 0;
+    series.next_start_time = args.first_start_time;
+    series.last_market = Pubkey::default();
+    series.bump = ctx.bumps.series;
+    Ok(())
+  }
+
+  /// Create the series' next market, funded by the series authority. Periods
+  /// that passed without an instance are skipped; the next one may be created
+  /// at most one period ahead of its start.
+  pub fn create_next_in_series(ctx: Context<CreateNextInSeries>, market_id: u64) -> Result<()> {
+    check_collateral_allowed(&ctx.accounts.config, &ctx.accounts.collateral_mint)?;
+    let now = Clock::get()?.unix_timestamp;
+    let series = &ctx.accounts.series;
+    let (index, start_time) = series.next_period(now)?;
+    require!(
+      now >= start_time.saturating_sub(series.duration),
+      PredictionError::SeriesNotDue
+    );
+    let args = series.instance_args(market_id, index, start_time)?;
+    validate_market_args(&args)?;
+
+    let backing = args
+      .initial_yes_reserve
+      .checked_add(args.initial_no_reserve)
+      .ok_or(PredictionError::MathOverflow)?;
+    let vault_before = ctx.accounts.vault.amount;
+    token_interface::transfer_checked(
+      CpiContext::new(
+        ctx.accounts.token_program.to_account_info(),
+        TransferChecked {
+          from: ctx.accounts.authority_collateral_ata.to_account_info(),
+          mint: ctx.accounts.collateral_mint.to_account_info(),
+          to: ctx.accounts.vault.to_account_info(),
+          authority: ctx.accounts.authority.to_account_info(),
+        },
+      ),
+      backing,
+      ctx.accounts.collateral_mint.decimals,
+    )?;
+    ctx.accounts.vault.reload()?;
+    let received = ctx
+      .accounts
+      .vault
+      .amount
+      .checked_sub(vault_before)
+      .ok_or(PredictionError::MathOverflow)?;
+
+    let market_key = ctx.accounts.market.key();
+    let mut market = ctx.accounts.market.load_init()?;
+    market.layout_version = MARKET_LAYOUT_VERSION;
+    market.bump = ctx.bumps.market;
+    market.vault_bump = ctx.bumps.vault;
+    market.vault_auth_bump = ctx.bumps.vault_authority;
+    market.authority = ctx.accounts.authority.key();
+    market.collateral_mint = ctx.accounts.collateral_mint.key();
+    market.collateral_decimals = ctx.accounts.collateral_mint.decimals;
+    market.vault = ctx.accounts.vault.key();
+    let lp_shares = init_market_state(&mut market, &mut ctx.accounts.config, &args, received, now)?;
+    market.creator_fee_share_bps = market
+      .creator_fee_share_bps
+      .min(ctx.accounts.series.creator_fee_share_bps);
+    market.series = ctx.accounts.series.key();
+    market.series_index = index;
+
+    let lp_position = &mut ctx.accounts.creator_lp_position;
+    lp_position.market = market_key;
+    lp_position.owner = ctx.accounts.authority.key();
+    lp_position.lp_shares = lp_shares;
+    lp_position.locked = true;
+    lp_position.bump = ctx.bumps.creator_lp_position;
+
+    let bond_amount = ctx.accounts.config.creation_bond;
+    if bond_amount > 0 {
+      system_program::transfer(
+        CpiContext::new(
+          ctx.accounts.system_program.to_account_info(),
+          system_program::Transfer {
+            from: ctx.accounts.authority.to_account_info(),
+            to: ctx.accounts.creator_bond.to_account_info(),
+          },
+        ),
+        bond_amount,
+      )?;
+    }
+    let bond = &mut ctx.accounts.creator_bond;
+    bond.market = market_key;
+    bond.creator = ctx.accounts.authority.key();
+    bond.amount = bond_amount;
+    bond.bump = ctx.bumps.creator_bond;
+
+    ctx.accounts.registry.bump = ctx.bumps.registry;
+    register_market_entry(
+      &mut ctx.accounts.registry,
+      &mut ctx.accounts.registry_page,
+      ctx.bumps.registry_page,
+      market_key,
+      &mut market,
+    )?;
+    count_created_market(ctx.accounts.protocol_stats.as_deref_mut(), &mut market)?;
+
+    let series = &mut ctx.accounts.series;
+    series.next_index = index.checked_add(1).ok_or(PredictionError::MathOverflow)?;
+    series.next_start_time = args.end_time;
+    series.last_market = market_key;
+
+    emit!(MarketCreated {
+      market: market_key,
+      authority: market.authority,
+      market_id: market.market_id,
+      market_seq: market.market_seq,
+      question_hash: args.question_hash,
+      metadata_uri: args.metadata_uri,
+      collateral_mint: market.collateral_mint,
+      start_time: market.start_time,
+      end_time: market.end_time,
+    });
+    emit!(SeriesMarketCreated {
+      series: series.key(),
+      market: market_key,
+      index,
+    });
+    Ok(())
+  }
+
+  /// Admin or curator registers a parameterized market template.
+  pub fn register_market_template(
+    ctx: Context<RegisterMarketTemplate>,
+    args: RegisterTemplateArgs,
+  ) -> Result<()> {
+    require!(args.duration > 0, PredictionError::InvalidStartTime);
+    require!(
+      args.question_hash != [0u8; 32],
+      PredictionError::InvalidQuestionHash
+    );
+    require!(
+      args.creator_fee_share_bps <= ctx.accounts.config.creator_fee_share_bps,
+      PredictionError::InvalidFeeShares
+    );
+    MarketCategory::try_from(args.category)?;
+    encode_metadata_uri(&args.rules_uri)?;
+
+    let template = &mut ctx.accounts.template;
+    template.template_id = args.template_id;
+    template.registrar = ctx.accounts.registrar.key();
+    template.question_hash = args.question_hash;
+    template.rules_uri = args.rules_uri;
+    template.category = args.category;
+    template.oracle = args.oracle;
+    template.duration = args.duration;
+    template.creator_fee_share_bps = args.creator_fee_share_bps;
+    template.min_liquidity = args.min_liquidity;
+    template.active = true;
+    template.bump = ctx.bumps.template;
+    Ok(())
+  }
+
+  /// Admin or curator retires (or re-enables) a template.
+  pub fn set_template_active(ctx: Context<SetTemplateActive>, active: bool) -> Result<()> {
+    ctx.accounts.template.active = active;
+    Ok(())
+  }
+
+  /// Permissionlessly create a market from a template. The question hash is
+  /// sha256(template.question_hash || params), so anyone holding the params
+  /// can check the market asks the templated question.
+  pub fn create_from_template(
+    ctx: Context<CreateFromTemplate>,
+    _template_id: u64,
+    params: TemplateMarketParams,
+  ) -> Result<()> {
+    check_collateral_allowed(&ctx.accounts.config, &ctx.accounts.collateral_mint)?;
+    let template = &ctx.accounts.template;
+    require!(template.active, PredictionError::TemplateInactive);
+    require!(
+      !params.params.is_empty() && params.params.len() <= MAX_TEMPLATE_PARAMS_LEN,
+      PredictionError::InvalidTemplateParams
+    );
+    require!(
+      params.initial_yes_reserve >= template.min_liquidity
+        && params.initial_no_reserve >= template.min_liquidity,
+      PredictionError::InvalidLiquidity
+    );
+
+    let now = Clock::get()?.unix_timestamp;
+    let start_time = params.start_time.max(now);
+    let args = CreateMarketCpmmArgs {
+      market_id: params.market_id,
+      question_hash: hashv(&[&template.question_hash, &params.params]).to_bytes(),
+      metadata_uri: params.metadata_uri,
+      start_time: params.start_time,
+      end_time: start_time
+        .checked_add(template.duration)
+        .ok_or(PredictionError::MathOverflow)?,
+      initial_yes_reserve: params.initial_yes_reserve,
+      initial_no_reserve: params.initial_no_reserve,
+      metadata: MarketMetadataArgs {
+        category: template.category,
+        tags: params.tags,
+        image_uri: params.image_uri,
+        rules_uri: template.rules_uri.clone(),
+      },
+      opening_auction: None,
+    };
+    validate_market_args(&args)?;
+
+    let backing = args
+      .initial_yes_reserve
+      .checked_add(args.initial_no_reserve)
+      .ok_or(PredictionError::MathOverflow)?;
+    let vault_before = ctx.accounts.vault.amount;
+    token_interface::transfer_checked(
+      CpiContext::new(
+        ctx.accounts.token_program.to_account_info(),
+        TransferChecked {
+          from: ctx.accounts.authority_collateral_ata.to_account_info(),
+          mint: ctx.accounts.collateral_mint.to_account_info(),
+          to: ctx.accounts.vault.to_account_info(),
+          authority: ctx.accounts.authority.to_account_info(),
+        },
+      ),
+      backing,
+      ctx.accounts.collateral_mint.decimals,
+    )?;
+    ctx.accounts.vault.reload()?;
+    let received = ctx
+      .accounts
+      .vault
+      .amount
+      .checked_sub(vault_before)
+      .ok_or(PredictionError::MathOverflow)?;
+
+    let market_key = ctx.accounts.market.key();
+    let mut market = ctx.accounts.market.load_init()?;
+    market.layout_version = MARKET_LAYOUT_VERSION;
+    market.bump = ctx.bumps.market;
+    market.vault_bump = ctx.bumps.vault;
+    market.vault_auth_bump = ctx.bumps.vault_authority;
+    market.authority = ctx.accounts.authority.key();
+    market.collateral_mint = ctx.accounts.collateral_mint.key();
+    market.collateral_decimals = ctx.accounts.collateral_mint.decimals;
+    market.vault = ctx.accounts.vault.key();
+    let lp_shares = init_market_state(&mut market, &mut ctx.accounts.config, &args, received, now)?;
+    market.creator_fee_share_bps = market
+      .creator_fee_share_bps
+      .min(ctx.accounts.template.creator_fee_share_bps);
+    market.template = ctx.accounts.template.key();
+
+    let lp_position = &mut ctx.accounts.creator_lp_position;
+    lp_position.market = market_key;
+    lp_position.owner = ctx.accounts.authority.key();
+    lp_position.lp_shares = lp_shares;
+    lp_position.locked = true;
+    lp_position.bump = ctx.bumps.creator_lp_position;
+
+    let bond_amount = ctx.accounts.config.creation_bond;
+    if bond_amount > 0 {
+      system_program::transfer(
+        CpiContext::new(
+          ctx.accounts.system_program.to_account_info(),
+          system_program::Transfer {
+            from: ctx.accounts.authority.to_account_info(),
+            to: ctx.accounts.creator_bond.to_account_info(),
+          },
+        ),
+        bond_amount,
+      )?;
+    }
+    let bond = &mut ctx.accounts.creator_bond;
+    bond.market = market_key;
+    bond.creator = ctx.accounts.authority.key();
+    bond.amount = bond_amount;
+    bond.bump = ctx.bumps.creator_bond;
+
+    ctx.accounts.registry.bump = ctx.bumps.registry;
+    register_market_entry(
+      &mut ctx.accounts.registry,
+      &mut ctx.accounts.registry_page,
+      ctx.bumps.registry_page,
+      market_key,
+      &mut market,
+    )?;
+    count_created_market(ctx.accounts.protocol_stats.as_deref_mut(), &mut market)?;
+
+    emit!(MarketCreated {
+      market: market_key,
+      authority: market.authority,
+      market_id: market.market_id,
+      market_seq: market.market_seq,
+      question_hash: args.question_hash,
+      metadata_uri: args.metadata_uri,
+      collateral_mint: market.collateral_mint,
+      start_time: market.start_time,
+      end_time: market.end_time,
+    });
+    Ok(())
+  }
+
+  /// Market authority sets how long claims stay open after the dispute
+  /// deadline (0 = no window, otherwise at least MIN_CLAIM_WINDOW_SECS), and
+  /// whether unclaimed funds then roll into the series' next market. Before
+  /// resolution; once settled, holders are already counting on the window,
+  /// so it can only be extended.
+  pub fn set_claim_window(ctx: Context<SetClaimWindow>, claim_window: i64, rollover: bool) -> Result<()> {
+    let mut market = ctx.accounts.market.load_mut()?;
+    require!(
+      claim_window == 0 || claim_window >= MIN_CLAIM_WINDOW_SECS,
+      PredictionError::InvalidClaimWindow
+    );
+    require!(claim_window > 0 || !rollover, PredictionError::InvalidClaimWindow);
+    require!(
+      !rollover || market.series != Pubkey::default(),
+      PredictionError::NotInSeries
+    );
+    if market.is_settled() {
+      require!(market.unclaimed_swept == 0, PredictionError::ClaimWindowClosed);
+      require!(
+        market.claim_window > 0
+          && claim_window >= market.claim_window
+          && rollover == (market.rollover_unclaimed != 0),
+        PredictionError::InvalidClaimWindow
+      );
+    } else {
+      require!(
+        market.status == MarketStatus::Open as u8 || market.status == MarketStatus::PreMarket as u8,
+        PredictionError::InvalidMarketStatus
+      );
+    }
+
+    market.claim_window = claim_window;
+    market.rollover_unclaimed = rollover as u8;
+    Ok(())
+  }
+
+  /// Hold claims back for `claim_delay` seconds after resolution so disputers
+  /// can act before funds leave the vault. Only before resolution.
+  pub fn set_claim_delay(ctx: Context<SetClaimWindow>, claim_delay: i64) -> Result<()> {
+    let mut market = ctx.accounts.market.load_mut()?;
+    require!(
+      market.status == MarketStatus::Open as u8 || market.status == MarketStatus::PreMarket as u8,
+      PredictionError::InvalidMarketStatus
+    );
+    require!(claim_delay >= 0, PredictionError::InvalidClaimWindow);
+    market.claim_delay = claim_delay;
+    Ok(())
+  }
+
+  /// Pay whoever executes the permissionless resolution `bounty` plus
+  /// `bounty_bps` of the vault. Only collateral the winners can't claim is
+  /// used, so the bounty is capped at the residual liquidity.
+  pub fn set_resolver_bounty(ctx: Context<SetClaimWindow>, bounty: u64, bounty_bps: u16) -> Result<()> {
+    let mut market = ctx.accounts.market.load_mut()?;
+    require!(
+      market.status == MarketStatus::Open as u8 || market.status == MarketStatus::PreMarket as u8,
+      PredictionError::InvalidMarketStatus
+    );
+    require!(bounty_bps as u64 <= BPS_DENOM, PredictionError::InvalidResolverBounty);
+    market.resolver_bounty = bounty;
+    market.resolver_bounty_bps = bounty_bps;
+    Ok(())
+  }
+
+  /// Waive the trading fee between `start` and `end` (unix seconds), e.g. for
+  /// a launch promotion. Market authority or config admin; `0, 0` clears it.
+  pub fn set_fee_holiday(ctx: Context<SetFeeHoliday>, start: i64, end: i64) -> Result<()> {
+    let market_key = ctx.accounts.market.key();
+    let mut market = ctx.accounts.market.load_mut()?;
+    let signer = ctx.accounts.authority.key();
+    require!(
+      signer == market.authority || signer == ctx.accounts.config.admin,
+      PredictionError::Unauthorized
+    );
+    require!(market.is_live(), PredictionError::InvalidMarketStatus);
+    require!(
+      (start == 0 && end == 0) || (start >= 0 && start < end),
+      PredictionError::InvalidFeeHoliday
+    );
+
+    market.fee_holiday_start = start;
+    market.fee_holiday_end = end;
+
+    emit!(FeeHolidaySet {
+      market: market_key,
+      start,
+      end,
+    });
+    Ok(())
+  }
+
+  /// Cap the YES or NO shares a single position can hold through AMM buys,
+  /// e.g. for capped-exposure promotional markets. Market authority or
+  /// config admin; 0 removes the cap.
+  pub fn set_max_position(ctx: Context<SetMaxPosition>, max_shares: u64) -> Result<()> {
+    let market_key = ctx.accounts.market.key();
+    let mut market = ctx.accounts.market.load_mut()?;
+    let signer = ctx.accounts.authority.key();
+    require!(
+      signer == market.authority || signer == ctx.accounts.config.admin,
+      PredictionError::Unauthorized
+    );
+    require!(market.is_live(), PredictionError::InvalidMarketStatus);
+    market.max_position_shares = max_shares;
+
+    emit!(MaxPositionSet {
+      market: market_key,
+      max_shares,
+    });
+    Ok(())
+  }
+
+  /// Restrict buys and sells to wallets the authority has allowlisted, e.g.
+  /// after KYC. Entries can be added before the gate is switched on.
+  pub fn set_allowlist_gate(ctx: Context<SetAllowlistGate>, enabled: bool) -> Result<()> {
+    let mut market = ctx.accounts.market.load_mut()?;
+    require!(market.is_live(), PredictionError::InvalidMarketStatus);
+    market.allowlist = enabled as u8;
+
+    emit!(AllowlistGateSet {
+      market: ctx.accounts.market.key(),
+      enabled,
+    });
+    Ok(())
+  }
+
+  /// Allow `wallet` to trade on an allowlist-gated market. The entry holds
+  /// no personal data, only the wallet.
+  pub fn allow_wallet(ctx: Context<AllowWallet>, wallet: Pubkey) -> Result<()> {
+    let entry = &mut ctx.accounts.allowlist_entry;
+    entry.market = ctx.accounts.market.key();
+    entry.wallet = wallet;
+    entry.bump = ctx.bumps.allowlist_entry;
+
+    emit!(AllowlistUpdated {
+      market: entry.market,
+      wallet,
+      allowed: true,
+    });
+    Ok(())
+  }
+
+  /// Only let wallets holding at least `min_balance` of `gate_mint` (a
+  /// community token or NFT) buy; 0 lifts the gate. Anyone can still sell.
+  pub fn set_token_gate(ctx: Context<SetTokenGate>, min_balance: u64) -> Result<()> {
+    let mut market = ctx.accounts.market.load_mut()?;
+    require!(market.is_live(), PredictionError::InvalidMarketStatus);
+    market.token_gate = (min_balance > 0) as u8;
+
+    let gate = &mut ctx.accounts.token_gate;
+    gate.market = ctx.accounts.market.key();
+    gate.mint = ctx.accounts.gate_mint.key();
+    gate.min_balance = min_balance;
+    gate.bump = ctx.bumps.token_gate;
+
+    emit!(TokenGateSet {
+      market: gate.market,
+      mint: gate.mint,
+      min_balance,
+    });
+    Ok(())
+  }
+
+  /// Require every buy and sell to carry a short-lived `TradePermit` signed
+  /// by `operator`, so compliance checks (geo, KYC) stay off-chain and no
+  /// personal data lands on-chain. The default key lifts the requirement.
+  pub fn set_permit_gate(ctx: Context<SetPermitGate>, operator: Pubkey) -> Result<()> {
+    let mut market = ctx.accounts.market.load_mut()?;
+    require!(market.is_live(), PredictionError::InvalidMarketStatus);
+    market.trade_permit = (operator != Pubkey::default()) as u8;
+
+    let gate = &mut ctx.accounts.permit_gate;
+    gate.market = ctx.accounts.market.key();
+    gate.operator = operator;
+    gate.bump = ctx.bumps.permit_gate;
+
+    emit!(PermitGateSet {
+      market: gate.market,
+      operator,
+    });
+    Ok(())
+  }
+
+  /// Remove a wallet from the allowlist, refunding the entry's rent. It
+  /// keeps its position and can still claim once the market settles.
+  pub fn revoke_wallet(ctx: Context<RevokeWallet>) -> Result<()> {
+    emit!(AllowlistUpdated {
+      market: ctx.accounts.market.key(),
+      wallet: ctx.accounts.allowlist_entry.wallet,
+      allowed: false,
+    });
+    Ok(())
+  }
+
+  /// Require resolutions of this market to be signed by vault `vault_index`
+  /// of a Squads multisig rather than the authority's key. Permanent once set.
+  pub fn set_resolution_multisig(ctx: Context<SetResolutionMultisig>, vault_index: u8) -> Result<()> {
+    let mut market = ctx.accounts.market.load_mut()?;
+    require!(market.is_live(), PredictionError::InvalidMarketStatus);
+    require!(
+      market.resolution_vault == Pubkey::default(),
+      PredictionError::ResolutionMultisigSet
+    );
+
+    let multisig = ctx.accounts.multisig.key();
+    let (vault, _) = Pubkey::find_program_address(
+      &[b"multisig", multisig.as_ref(), b"vault", &[vault_index]],
+      &SQUADS_PROGRAM_ID,
+    );
+    market.resolution_vault = vault;
+
+    emit!(ResolutionMultisigSet {
+      market: ctx.accounts.market.key(),
+      multisig,
+      vault,
+    });
+    Ok(())
+  }
+
+  /// After the claim window, move everything but unwithdrawn fees out of the
+  /// vault: to the treasury, or for rollover markets into a later live
+  /// market of the same series as liquidity owned by its creator. Claims and
+  /// LP withdrawals on this market are closed afterwards. Permissionless.
+  pub fn sweep_unclaimed(ctx: Context<SweepUnclaimed>) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    let market_key = ctx.accounts.market.key();
+    let mut market = ctx.accounts.market.load_mut()?;
+    require!(
+      market.is_settled(),
+      PredictionError::MarketNotResolved
+    );
+    require!(market.claim_window > 0, PredictionError::InvalidClaimWindow);
+    require!(market.unclaimed_swept == 0, PredictionError::ClaimWindowClosed);
+    let deadline = market
+      .claims_open_at()?
+      .checked_add(market.claim_window)
+      .ok_or(PredictionError::MathOverflow)?;
+    require!(now >= deadline, PredictionError::ClaimWindowOpen);
+
+    let amount = market
+      .vault_balance
+      .checked_sub(market.creator_fees)
+      .and_then(|a| a.checked_sub(market.protocol_fees))
+      .and_then(|a| a.checked_sub(market.insurance_fees))
+      .and_then(|a| a.checked_sub(market.mm_rebates))
+      .ok_or(PredictionError::MathOverflow)?;
+    require!(amount > 0, PredictionError::ZeroAmount);
+    market.debit_vault(amount)?;
+    market.unclaimed_swept = 1;
+    market.residual_liquidity = 0;
+
+    let seeds: &[&[u8]] = &[
+      b"vault_auth_v2",
+      market_key.as_ref(),
+      &[market.vault_auth_bump],
+    ];
+
+    if market.rollover_unclaimed == 1 {
+      let next_loader = ctx
+        .accounts
+        .next_market
+        .as_ref()
+        .ok_or(PredictionError::NotInSeries)?;
+      let next_key = next_loader.key();
+      let mut next = next_loader.load_mut()?;
+      require!(
+        next.series == market.series && next.series_index > market.series_index,
+        PredictionError::NotInSeries
+      );
+      next.refresh_phase(now);
+      require!(
+        next.is_live() && now < next.end_time && next.total_lp_shares > 0,
+        PredictionError::InvalidMarketStatus
+      );
+
+      let next_vault = ctx
+        .accounts
+        .next_vault
+        .as_mut()
+        .ok_or(PredictionError::NotInSeries)?;
+      require_keys_eq!(next_vault.key(), next.vault, PredictionError::NotInSeries);
+      let received = transfer_signed(
+        &ctx.accounts.vault,
+        next_vault,
+        &ctx.accounts.collateral_mint,
+        ctx.accounts.vault_authority.to_account_info(),
+        seeds,
+        ctx.accounts.token_program.to_account_info(),
+        amount,
+      )?;
+      next.credit_vault(received)?;
+
+      // Same pricing as `add_liquidity`; the unpaired remainder stays in the
+      // vault as extra backing for the pool.
+      let max_pool = next.yes_pool.max(next.no_pool);
+      let yes_add = mul_div(received, next.yes_pool, max_pool)?;
+      let no_add = mul_div(received, next.no_pool, max_pool)?;
+      let lp_out = mul_div(received, next.total_lp_shares, max_pool)?;
+      next.yes_pool = next
+        .yes_pool
+        .checked_add(yes_add)
+        .ok_or(PredictionError::MathOverflow)?;
+      next.no_pool = next
+        .no_pool
+        .checked_add(no_add)
+        .ok_or(PredictionError::MathOverflow)?;
+      let lp_position = ctx
+        .accounts
+        .next_lp_position
+        .as_mut()
+        .ok_or(PredictionError::NotInSeries)?;
+      require!(
+        lp_position.market == next_key && lp_position.owner == next.authority,
+        PredictionError::NotInSeries
+      );
+      let next_rewards = ctx.accounts.next_lp_rewards.as_deref_mut();
+      if let Some(rewards) = &next_rewards {
+        require_keys_eq!(rewards.market, next_key, PredictionError::NotInSeries);
+      }
+      let rewards = checkpoint_lp_rewards(&next, next_rewards, lp_position, now)?;
+
+      next.total_lp_shares = next
+        .total_lp_shares
+        .checked_add(lp_out)
+        .ok_or(PredictionError::MathOverflow)?;
+      lp_position.lp_shares = lp_position
+        .lp_shares
+        .checked_add(lp_out)
+        .ok_or(PredictionError::MathOverflow)?;
+      if let Some(rewards) = rewards {
+        rewards.reset_debt(lp_position)?;
+      }
+
+      emit!(UnclaimedSwept {
+        market: market_key,
+        destination: next_key,
+        amount: received,
+        rolled_over: true,
+      });
+    } else {
+      let treasury_ata = ctx
+        .accounts
+        .treasury_collateral_ata
+        .as_mut()
+        .ok_or(PredictionError::Unauthorized)?;
+      transfer_signed(
+        &ctx.accounts.vault,
+        treasury_ata,
+        &ctx.accounts.collateral_mint,
+        ctx.accounts.vault_authority.to_account_info(),
+        seeds,
+        ctx.accounts.token_program.to_account_info(),
+        amount,
+      )?;
+
+      emit!(UnclaimedSwept {
+        market: market_key,
+        destination: treasury_ata.key(),
+        amount,
+        rolled_over: false,
+      });
+    }
+    Ok(())
+  }
+
+  /// Turn a fresh market into a scalar market on a Pyth feed: YES is the long
+  /// side, NO the short side, and the expiry price within [lower, upper]
+  /// sets how the vault splits between them.
+  pub fn init_scalar_market(ctx: Context<InitScalarMarket>, args: InitScalarArgs) -> Result<()> {
+    require!(args.lower < args.upper, PredictionError::InvalidScalarRange);
+    let mut market = ctx.accounts.market.load_mut()?;
+    require!(market.trade_count == 0, PredictionError::MarketAlreadyTraded);
+    require!(market.oracle_market == 0, PredictionError::OracleMarket);
+    require!(market.is_live(), PredictionError::InvalidMarketStatus);
+    require!(market.group == Pubkey::default(), PredictionError::GroupedMarket);
+    require!(
+      market.lst_stake_pool == Pubkey::default(),
+      PredictionError::InvalidStakePool
+    );
+    require!(
+      market.parent_market == Pubkey::default(),
+      PredictionError::InvalidTargetMarket
+    );
+
+    let scalar = &mut ctx.accounts.scalar;
+    scalar.market = ctx.accounts.market.key();
+    scalar.feed_id = args.feed_id;
+    scalar.lower = args.lower;
+    scalar.upper = args.upper;
+    scalar.exponent = args.exponent;
+    scalar.settlement_price = 0;
+    scalar.bump = ctx.bumps.scalar;
+    market.scalar = 1;
+    Ok(())
+  }
+
+  /// Permissionlessly settle a scalar market from a fully verified Pyth update
+  /// published within `ORACLE_MAX_STALENESS_SECS` of `end_time`. The price is
+  /// clamped to the range and YES is paid (price - lower) / (upper - lower)
+  /// per share, NO the rest.
+  pub fn settle_scalar_with_pyth(ctx: Context<SettleScalarWithPyth>) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    let mut market = ctx.accounts.market.load_mut()?;
+    market.refresh_phase(now);
+    require!(market.is_live(), PredictionError::InvalidMarketStatus);
+    require!(now >= market.end_time, PredictionError::MarketNotEnded);
+
+    let scalar = &mut ctx.accounts.scalar;
+    let price = read_settlement_price(
+      &ctx.accounts.price_update,
+      &scalar.feed_id,
+      scalar.exponent,
+      market.end_time,
+    )?;
+
+    let clamped = price.price.clamp(scalar.lower, scalar.upper);
+    let fraction = ((clamped - scalar.lower) as u128)
+      .checked_mul(PRICE_SCALE as u128)
+      .ok_or(PredictionError::MathOverflow)?
+      .checked_div((scalar.upper - scalar.lower) as u128)
+      .ok_or(PredictionError::MathOverflow)? as u64;
+    scalar.settlement_price = price.price;
+
+    market.winning_outcome = OUTCOME_SPLIT;
+    market.payout_fraction = fraction;
+    let (total_winning_shares, bounty) = finish_oracle_resolution(
+      &mut market,
+      &ctx.accounts.config,
+      ctx.accounts.vault.amount,
+      ctx.accounts.resolver_collateral_ata.is_some(),
+      now,
+    )?;
+
+    sync_registry_entry(
+      ctx.accounts.registry_page.as_deref_mut(),
+      ctx.accounts.market.key(),
+      &market,
+    )?;
+
+    if let Some(resolver_ata) = ctx.accounts.resolver_collateral_ata.as_mut() {
+      let market_key = ctx.accounts.market.key();
+      let seeds: &[&[u8]] = &[
+        b"vault_auth_v2",
+        market_key.as_ref(),
+        &[market.vault_auth_bump],
+      ];
+      pay_resolver_bounty(
+        &ctx.accounts.vault,
+        resolver_ata,
+        &ctx.accounts.collateral_mint,
+        ctx.accounts.vault_authority.to_account_info(),
+        seeds,
+        ctx.accounts.token_program.to_account_info(),
+        market_key,
+        ctx.accounts.resolver.key(),
+        bounty,
+      )?;
+    }
+
+    emit!(ScalarSettled {
+      market: ctx.accounts.market.key(),
+      price: price.price,
+      exponent: price.exponent,
+      publish_time: price.publish_time,
+      payout_fraction: fraction,
+    });
+    emit!(MarketResolved {
+      market: ctx.accounts.market.key(),
+      winning_outcome: market.winning_outcome,
+      total_winning_shares,
+      payable: market.resolved_payable,
+      payout_rate: market.payout_rate,
+      lst_rate: 0,
+    });
+    Ok(())
+  }
+
+  /// Configure how a binary market resolves, through one of the
+  /// `OracleAdapter`s:
+  ///
+  /// - Pyth / Chainlink: YES if the expiry price is at or above (or, with
+  ///   `yes_if_above` false, at or below) `threshold`, in the feed's exponent
+  /// - SignedReport: an outcome signed by the `feed` reporter key
+  /// - Manual: an outcome reported by the market authority
+  ///
+  /// Every adapter resolves through `resolve_with_oracle`. Authority only,
+  /// before any trade.
+  pub fn init_oracle_config(ctx: Context<InitOracleConfig>, args: InitOracleConfigArgs) -> Result<()> {
+    let adapter = OracleAdapter::try_from(args.adapter)?;
+    match adapter {
+      OracleAdapter::Pyth | OracleAdapter::Manual => {}
+      OracleAdapter::Chainlink | OracleAdapter::SignedReport => {
+        require!(args.feed != Pubkey::default(), PredictionError::InvalidOracle)
+      }
+      OracleAdapter::Switchboard => return err!(PredictionError::UnsupportedOracleAdapter),
+    }
+
+    let mut market = ctx.accounts.market.load_mut()?;
+    require!(market.trade_count == 0, PredictionError::MarketAlreadyTraded);
+    require!(market.is_live(), PredictionError::InvalidMarketStatus);
+    require!(market.scalar == 0, PredictionError::ScalarMarket);
+    require!(market.group == Pubkey::default(), PredictionError::GroupedMarket);
+    require!(
+      market.lst_stake_pool == Pubkey::default(),
+      PredictionError::InvalidStakePool
+    );
+    require!(
+      market.parent_market == Pubkey::default(),
+      PredictionError::InvalidTargetMarket
+    );
+
+    let oracle = &mut ctx.accounts.oracle_config;
+    oracle.market = ctx.accounts.market.key();
+    oracle.adapter = adapter as u8;
+    oracle.feed = args.feed;
+    oracle.feed_id = args.feed_id;
+    oracle.threshold = args.threshold;
+    oracle.exponent = args.exponent;
+    oracle.yes_if_above = args.yes_if_above;
+    oracle.settlement_price = 0;
+    oracle.bump = ctx.bumps.oracle_config;
+    market.oracle_market = 1;
+    Ok(())
+  }
+
+  /// Resolve a market through its `OracleConfig` once `end_time` has passed:
+  ///
+  /// - Pyth: an `oracle_account` update held to the same checks as scalar
+  ///   settlement
+  /// - Chainlink: the round of the configured feed closest to `end_time`
+  /// - SignedReport: `report`, signed by the reporter key; the ed25519
+  ///   signature must be verified by the instruction immediately before
+  /// - Manual: `report`, submitted by the market's resolver
+  ///
+  /// Permissionless except for Manual; the caller collects the resolver bounty.
+  pub fn resolve_with_oracle(
+    ctx: Context<ResolveWithOracle>,
+    report: Option<ResolutionReport>,
+  ) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    let market_key = ctx.accounts.market.key();
+    let mut market = ctx.accounts.market.load_mut()?;
+    market.refresh_phase(now);
+    require!(market.is_live(), PredictionError::InvalidMarketStatus);
+    require!(now >= market.end_time, PredictionError::MarketNotEnded);
+
+    let oracle = &mut ctx.accounts.oracle_config;
+    let adapter = OracleAdapter::try_from(oracle.adapter)?;
+    let (winning_outcome, price, published_at) = match adapter {
+      OracleAdapter::Pyth | OracleAdapter::Chainlink => {
+        let info = ctx
+          .accounts
+          .oracle_account
+          .as_ref()
+          .ok_or(PredictionError::InvalidOracle)?;
+        let price = if adapter == OracleAdapter::Pyth {
+          read_settlement_price(info, &oracle.feed_id, oracle.exponent, market.end_time)?
+        } else {
+          require_keys_eq!(info.key(), oracle.feed, PredictionError::InvalidOracle);
+          let price = read_chainlink_price(info, market.end_time)?;
+          require!(price.exponent == oracle.exponent, PredictionError::InvalidOracle);
+          price
+        };
+        oracle.settlement_price = price.price;
+        let yes = if oracle.yes_if_above {
+          price.price >= oracle.threshold
+        } else {
+          price.price <= oracle.threshold
+        };
+        (if yes { 0 } else { 1 }, price.price, price.publish_time)
+      }
+      OracleAdapter::SignedReport | OracleAdapter::Manual => {
+        let report = report.ok_or(PredictionError::InvalidOracle)?;
+        require_keys_eq!(report.market, market_key, PredictionError::InvalidTargetMarket);
+        require!(report.winning_outcome <= 1, PredictionError::InvalidOutcome);
+        require!(report.reported_at >= market.end_time, PredictionError::MarketNotEnded);
+        if adapter == OracleAdapter::SignedReport {
+          let instructions = ctx
+            .accounts
+            .instructions
+            .as_ref()
+            .ok_or(PredictionError::InvalidSignature)?;
+          verify_ed25519_signature(instructions, &oracle.feed, &report.try_to_vec()?)?;
+        } else {
+          require_keys_eq!(
+            ctx.accounts.resolver.key(),
+            market.resolver(),
+            PredictionError::Unauthorized
+          );
+        }
+        (report.winning_outcome, 0, report.reported_at)
+      }
+      OracleAdapter::Switchboard => return err!(PredictionError::UnsupportedOracleAdapter),
+    };
+
+    market.winning_outcome = winning_outcome as i8;
+    market.payout_fraction = 0;
+    // No bounty for the authority resolving its own market
+    let (total_winning_shares, bounty) = finish_oracle_resolution(
+      &mut market,
+      &ctx.accounts.config,
+      ctx.accounts.vault.amount,
+      adapter != OracleAdapter::Manual && ctx.accounts.resolver_collateral_ata.is_some(),
+      now,
+    )?;
+
+    sync_registry_entry(
+      ctx.accounts.registry_page.as_deref_mut(),
+      market_key,
+      &market,
+    )?;
+
+    if let Some(resolver_ata) = ctx.accounts.resolver_collateral_ata.as_mut() {
+      let seeds: &[&[u8]] = &[
+        b"vault_auth_v2",
+        market_key.as_ref(),
+        &[market.vault_auth_bump],
+      ];
+      pay_resolver_bounty(
+        &ctx.accounts.vault,
+        resolver_ata,
+        &ctx.accounts.collateral_mint,
+        ctx.accounts.vault_authority.to_account_info(),
+        seeds,
+        ctx.accounts.token_program.to_account_info(),
+        market_key,
+        ctx.accounts.resolver.key(),
+        bounty,
+      )?;
+    }
+
+    emit!(OracleResolved {
+      market: market_key,
+      adapter: adapter as u8,
+      price,
+      exponent: oracle.exponent,
+      publish_time: published_at,
+      winning_outcome: market.winning_outcome,
+    });
+    emit!(MarketResolved {
+      market: market_key,
+      winning_outcome: market.winning_outcome,
+      total_winning_shares,
+      payable: market.resolved_payable,
+      payout_rate: market.payout_rate,
+      lst_rate: 0,
+    });
+    Ok(())
+  }
+
+  /// Permissionlessly void an oracle-settled market whose feed has not
+  /// produced a usable price within `ORACLE_VOID_TIMEOUT_SECS` of `end_time`.
+  /// Positions are refunded at cost via `claim_winnings_v2`, as for
+  /// `void_market`; LPs keep the residual as after a resolution.
+  pub fn void_unresolvable_market(ctx: Context<VoidUnresolvableMarket>) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    let market_key = ctx.accounts.market.key();
+    let mut market = ctx.accounts.market.load_mut()?;
+    require!(
+      market.scalar == 1 || market.oracle_market == 1,
+      PredictionError::NotOracleMarket
+    );
+    market.refresh_phase(now);
+    require!(market.is_live(), PredictionError::InvalidMarketStatus);
+    let deadline = market
+      .end_time
+      .checked_add(ORACLE_VOID_TIMEOUT_SECS)
+      .ok_or(PredictionError::MathOverflow)?;
+    require!(now >= deadline, PredictionError::OracleTimeoutPending);
+
+    require!(
+      ctx.accounts.vault.amount >= market.vault_balance,
+      PredictionError::VaultShortfall
+    );
+    market.cancel_at_cost(now)?;
+
+    sync_registry_entry(ctx.accounts.registry_page.as_deref_mut(), market_key, &market)?;
+
+    emit!(MarketVoidedOnOracleFailure {
+      market: market_key,
+      refundable: market.resolved_payable,
+    });
+    Ok(())
+  }
+
+  /// Create the caller's portfolio, an index of markets they hold positions in.
+  pub fn init_portfolio(ctx: Context<InitPortfolio>) -> Result<()> {
+    let portfolio = &mut ctx.accounts.portfolio;
+    portfolio.owner = ctx.accounts.owner.key();
+    portfolio.markets = Vec::new();
+    portfolio.bump = ctx.bumps.portfolio;
+    Ok(())
+  }
+
+  /// Add a market the caller holds a position in to their portfolio.
+  pub fn track_market(ctx: Context<TrackMarket>) -> Result<()> {
+    let market = ctx.accounts.position.market;
+    let portfolio = &mut ctx.accounts.portfolio;
+    require!(
+      !portfolio.markets.contains(&market),
+      PredictionError::MarketAlreadyTracked
+    );
+    require!(
+      portfolio.markets.len() < MAX_PORTFOLIO_MARKETS,
+      PredictionError::PortfolioFull
+    );
+    portfolio.markets.push(market);
+    Ok(())
+  }
+
+  /// Drop a market from the caller's portfolio.
+  pub fn untrack_market(ctx: Context<UpdatePortfolio>, market: Pubkey) -> Result<()> {
+    ctx.accounts.portfolio.markets.retain(|m| *m != market);
+    Ok(())
+  }
+
+  /// Claim every claimable position in one transaction. `remaining_accounts`
+  /// holds groups of [market, vault, vault_authority, position, collateral_mint,
+  /// owner_collateral_ata]; groups that aren't claimable yet (unresolved, in
+  /// the dispute window, already claimed or losing) are skipped. Claimed
+  /// markets are dropped from the portfolio. SPL collateral only: native SOL
+  /// markets and slashed-bond payouts go through `claim_winnings_v2`.
+  pub fn claim_all<'info>(ctx: Context<'_, '_, 'info, 'info, ClaimAll<'info>>) -> Result<()> {
+    let groups = ctx.remaining_accounts.chunks_exact(CLAIM_ALL_GROUP_LEN);
+    require!(
+      groups.remainder().is_empty(),
+      PredictionError::InvalidRemainingAccounts
+    );
+
+    let owner = ctx.accounts.owner.key();
+    let now = Clock::get()?.unix_timestamp;
+    let mut claimed_markets = 0u32;
+
+    for group in groups {
+      let [market_info, vault_info, vault_auth_info, position_info, mint_info, ata_info] = group else {
+        unreachable!()
+      };
+
+      let market_loader = AccountLoader::<MarketV3>::try_from(market_info)?;
+      let market_key = market_info.key();
+      let mut market = market_loader.load_mut()?;
+
+      let (position_pda, _) = Pubkey::find_program_address(
+        &[b"position_v2", market_key.as_ref(), owner.as_ref()],
+        ctx.program_id,
+      );
+      require_keys_eq!(position_pda, position_info.key(), PredictionError::InvalidRemainingAccounts);
+      let mut position = Account::<PositionV2>::try_from(position_info)?;
+
+      let winning_shares = market
+        .position_winnings(&position)
+        .unwrap_or(0);
+      if !market.is_settled()
+        || now < market.dispute_deadline
+        || now < market.claims_open_at()?
+        || position.claimed
+        || winning_shares == 0
+      {
+        continue;
+      }
+
+      require_keys_eq!(market.vault, vault_info.key(), PredictionError::InvalidRemainingAccounts);
+      require!(
+        market.collateral_mint != native_mint::ID,
+        PredictionError::InvalidRemainingAccounts
+      );
+      let vault = InterfaceAccount::<TokenAccount>::try_from(vault_info)?;
+      let mint = InterfaceAccount::<Mint>::try_from(mint_info)?;
+      require_keys_eq!(mint.key(), market.collateral_mint, PredictionError::InvalidRemainingAccounts);
+      let ata = InterfaceAccount::<TokenAccount>::try_from(ata_info)?;
+      require!(
+        ata.owner == owner && ata.mint == market.collateral_mint,
+        PredictionError::InvalidRemainingAccounts
+      );
+
+      let (payout, _) = settle_claim(&mut market, market_key, &mut position, owner, now)?;
+
+      let seeds: &[&[u8]] = &[
+        b"vault_auth_v2",
+        market_key.as_ref(),
+        &[market.vault_auth_bump],
+      ];
+      pay_out_collateral(
+        &vault,
+        vault_auth_info.clone(),
+        seeds,
+        &mint,
+        Some(ata_info.clone()),
+        None,
+        ctx.accounts.owner.to_account_info(),
+        ctx.accounts.owner.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        ctx.accounts.system_program.to_account_info(),
+        payout,
+      )?;
+
+      position.exit(ctx.program_id)?;
+      ctx.accounts.portfolio.markets.retain(|m| *m != market_key);
+      claimed_markets += 1;
+    }
+
+    emit!(PortfolioClaimed {
+      owner,
+      markets: claimed_markets,
+    });
+
+    Ok(())
+  }
+
+  /// Pay out up to `max_positions` winning positions of a settled market so
+  /// the operator can settle it without waiting for owners to claim.
+  /// `remaining_accounts` holds groups of [position, owner_collateral_ata,
+  /// owner]; positions that are already claimed or losing are skipped.
+  /// Permissionless, since every payout goes to the position owner. SPL
+  /// collateral only.
+  pub fn distribute_payouts_batch<'info>(
+    ctx: Context<'_, '_, 'info, 'info, DistributePayoutsBatch<'info>>,
+    max_positions: u8,
+  ) -> Result<()> {
+    let groups = ctx.remaining_accounts.chunks_exact(PAYOUT_BATCH_GROUP_LEN);
+    require!(
+      groups.remainder().is_empty(),
+      PredictionError::InvalidRemainingAccounts
+    );
+
+    let market_key = ctx.accounts.market.key();
+    let mut market = ctx.accounts.market.load_mut()?;
+    require!(market.is_settled(), PredictionError::MarketNotResolved);
+    require!(
+      market.collateral_mint != native_mint::ID,
+      PredictionError::InvalidRemainingAccounts
+    );
+    let now = Clock::get()?.unix_timestamp;
+    let seeds: &[&[u8]] = &[
+      b"vault_auth_v2",
+      market_key.as_ref(),
+      &[market.vault_auth_bump],
+    ];
+
+    let mut positions = 0u32;
+    let mut total_payout = 0u64;
+    for group in groups.take(max_positions as usize) {
+      let [position_info, ata_info, owner_info] = group else {
+        unreachable!()
+      };
+
+      let owner = owner_info.key();
+      let (position_pda, _) = Pubkey::find_program_address(
+        &[b"position_v2", market_key.as_ref(), owner.as_ref()],
+        ctx.program_id,
+      );
+      require_keys_eq!(position_pda, position_info.key(), PredictionError::InvalidRemainingAccounts);
+      let mut position = Account::<PositionV2>::try_from(position_info)?;
+
+      let winning_shares = market
+        .position_winnings(&position)
+        .unwrap_or(0);
+      if position.claimed || winning_shares == 0 {
+        continue;
+      }
+
+      let ata = InterfaceAccount::<TokenAccount>::try_from(ata_info)?;
+      require!(
+        ata.owner == owner && ata.mint == market.collateral_mint,
+        PredictionError::InvalidRemainingAccounts
+      );
+
+      let (payout, user_winning_shares) =
+        settle_claim(&mut market, market_key, &mut position, owner, now)?;
+      pay_out_collateral(
+        &ctx.accounts.vault,
+        ctx.accounts.vault_authority.to_account_info(),
+        seeds,
+        &ctx.accounts.collateral_mint,
+        Some(ata_info.clone()),
+        None,
+        owner_info.clone(),
+        ctx.accounts.keeper.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        ctx.accounts.system_program.to_account_info(),
+        payout,
+      )?;
+
+      pay_slashed_bond_share(
+        ctx.accounts.creator_bond.as_ref(),
+        &market,
+        user_winning_shares,
+        owner_info,
+      )?;
+
+      position.exit(ctx.program_id)?;
+      positions += 1;
+      total_payout = total_payout
+        .checked_add(payout)
+        .ok_or(PredictionError::MathOverflow)?;
+    }
+
+    if let Some(stats) = ctx.accounts.protocol_stats.as_mut() {
+      stats.sync_tvl(&mut market)?;
+    }
+
+    emit!(PayoutsDistributed {
+      market: market_key,
+      positions,
+      total_payout,
+    });
+    Ok(())
+  }
+
+  /// Read-only price view (simulate and read the return data).
+  ///
+  /// Probabilities and price-per-share are in PRICE_SCALE (1e6) fixed-point,
+  /// independent of the collateral mint's decimals.
+  pub fn get_market_price(ctx: Context<ViewMarket>) -> Result<MarketPrice> {
+    ctx.accounts.market.load()?.price()
+  }
+
+  /// Read-only quote of `buy_shares` spending `collateral_in` (as received
+  /// by the vault) on `outcome_index`. Simulate and read the return data.
+  pub fn quote_buy(ctx: Context<ViewMarket>, outcome_index: u8, collateral_in: u64) -> Result<TradeQuote> {
+    let now = Clock::get()?.unix_timestamp;
+    let mut market = *ctx.accounts.market.load()?;
+    check_tradable(&mut market, now)?;
+    market.settle_opening_auction(now)?;
+
+    let price_before = market.implied_probability(outcome_index)?;
+    let q = quote::quote_buy(
+      market.yes_pool,
+      market.no_pool,
+      outcome_index,
+      collateral_in,
+      market.fee_bps(now),
+    )
+    .map_err(quote_error)?;
+    TradeQuote::new(outcome_index, price_before, q.shares_out, q.fee, q.yes_pool, q.no_pool, collateral_in, q.shares_out)
+  }
+
+  /// Read-only quote of `sell_shares` selling `shares_in` of
+  /// `outcome_index`. Simulate and read the return data.
+  pub fn quote_sell(ctx: Context<ViewMarket>, outcome_index: u8, shares_in: u64) -> Result<TradeQuote> {
+    let now = Clock::get()?.unix_timestamp;
+    let mut market = *ctx.accounts.market.load()?;
+    check_tradable(&mut market, now)?;
+
+    let price_before = market.implied_probability(outcome_index)?;
+    let q = quote::quote_sell(
+      market.yes_pool,
+      market.no_pool,
+      outcome_index,
+      shares_in,
+      market.fee_bps(now),
+    )
+    .map_err(quote_error)?;
+    TradeQuote::new(
+      outcome_index,
+      price_before,
+      q.collateral_out,
+      q.fee,
+      q.yes_pool,
+      q.no_pool,
+      q.collateral_out,
+      shares_in,
+    )
+  }
+
+  /// Read-only view of the collateral paid per winning share (PRICE_SCALE)
+  /// of a resolved market; anything below PRICE_SCALE is a haircut.
+  pub fn get_payout_rate(ctx: Context<ViewMarket>) -> Result<u64> {
+    let market = ctx.accounts.market.load()?;
+    require!(
+      market.status == MarketStatus::Resolved as u8,
+      PredictionError::MarketNotResolved
+    );
+    Ok(market.payout_rate)
+  }
+
+  /// Permissionless solvency check for a live market: the worst-case payout
+  /// (the larger outstanding share side) plus accrued fees must be covered by
+  /// the recorded vault balance, and the vault must actually hold it.
+  /// Emits the result; with `pause_if_insolvent` a failing market is paused.
+  pub fn assert_solvency(ctx: Context<AssertSolvency>, pause_if_insolvent: bool) -> Result<()> {
+    let market_key = ctx.accounts.market.key();
+    let mut market = ctx.accounts.market.load_mut()?;
+    require!(market.is_live(), PredictionError::InvalidMarketStatus);
+
+    let liabilities = market.worst_case_liabilities()?;
+    let assets = market
+      .vault_balance
+      .min(ctx.accounts.vault.amount)
+      .checked_add(market.yield_deployed)
+      .ok_or(PredictionError::MathOverflow)?;
+    let solvent = assets >= liabilities;
+
+    let paused = !solvent && pause_if_insolvent && market.paused == 0;
+    if paused {
+      market.paused = 1;
+    }
+
+    emit!(SolvencyChecked {
+      market: market_key,
+      liabilities,
+      vault_balance: market.vault_balance,
+      vault_amount: ctx.accounts.vault.amount,
+      solvent,
+      paused,
+    });
+
+    Ok(())
+  }
+}
+
+// ----------------------------
+// Args / Enums
+// ----------------------------
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct PlaceLimitOrderArgs {
+  pub order_id: u64,
+  pub side: u8, // OrderSide
+  pub outcome_index: u8,
+  pub amount: u64,      // collateral (buy) or shares (sell)
+  pub limit_price: u64, // PRICE_SCALE
+  pub keeper_tip: u64,  // lamports
+  pub trigger_price: u64, // PRICE_SCALE stop-loss trigger (sell only); 0 = none
+  pub oracle_trigger: Option<OracleTriggerArgs>,
+}
+
+/// External price condition, in the feed's own units and exponent.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct OracleTriggerArgs {
+  pub price_account: Pubkey, // Pyth `PriceUpdateV2` account
+  pub price: i64,
+  pub above: bool, // fire when price >= `price` (else <=)
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct PlaceTwapOrderArgs {
+  pub order_id: u64,
+  pub side: u8, // OrderSide
+  pub outcome_index: u8,
+  pub amount: u64,      // total collateral (buy) or shares (sell)
+  pub slices: u16,
+  pub interval: i64,    // min seconds between slices
+  pub limit_price: u64, // PRICE_SCALE, per slice
+  pub keeper_tip: u64,  // lamports per slice
+}
+
+/// Attestation a permit-gated market's operator signs (borsh encoding)
+/// after its off-chain checks, letting `wallet` trade until `expiry`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct TradePermit {
+  pub market: Pubkey,
+  pub wallet: Pubkey,
+  pub expiry: i64, // at most MAX_PERMIT_SECS ahead when used
+}
+
+/// Off-chain quote signed by a market maker. The maker signs the borsh
+/// encoding of this struct.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct RfqQuote {
+  pub market: Pubkey,
+  pub maker: Pubkey,
+  pub taker: Pubkey,  // default = any taker
+  pub maker_side: u8, // OrderSide, from the maker's point of view
+  pub outcome_index: u8,
+  pub shares: u64,
+  pub price: u64, // PRICE_SCALE per share
+  pub expiry: i64,
+  pub nonce: u64,
+}
+
+/// Per-user trading record. Points are non-transferable: one per trade plus
+/// one per unit of fee paid. `epoch_points` accrue in `epoch`; closed epochs
+/// move to `checkpoints` (oldest dropped past MAX_POINTS_CHECKPOINTS).
+///
+/// `lots` hold the cost basis of shares bought with the account; PnL is
+/// realized on sells, claims and `realize_settled_pnl`. Buys in a new market
+/// are left untracked while MAX_STATS_LOTS markets are open.
+#[account]
+#[derive(InitSpace)]
+pub struct UserStats {
+  pub owner: Pubkey,
+  pub points: u64, // lifetime
+  pub epoch: u64,
+  pub epoch_points: u64,
+  #[max_len(MAX_POINTS_CHECKPOINTS)]
+  pub checkpoints: Vec<PointsCheckpoint>,
+  pub volume: u64, // collateral traded, lifetime
+  pub realized_pnl: i64,
+  #[max_len(MAX_STATS_LOTS)]
+  pub lots: Vec<CostLot>,
+  pub bump: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct PointsCheckpoint {
+  pub epoch: u64,
+  pub points: u64,
+}
+
+impl UserStats {
+  /// Move into `now`'s epoch, checkpointing the one that closed.
+  pub fn roll_epoch(&mut self, now: i64) {
+    let epoch = points_epoch(now);
+    if epoch == self.epoch {
+      return;
+    }
+    if self.epoch_points > 0 {
+      if self.checkpoints.len() == MAX_POINTS_CHECKPOINTS {
+        self.checkpoints.remove(0);
+      }
+      self.checkpoints.push(PointsCheckpoint {
+        epoch: self.epoch,
+        points: self.epoch_points,
+      });
+    }
+    self.epoch = epoch;
+    self.epoch_points = 0;
+  }
+
+  /// Credit a trade that paid `fee`.
+  pub fn accrue_points(&mut self, fee: u64, now: i64) -> Result<()> {
+    self.roll_epoch(now);
+    let points = fee.checked_add(1).ok_or(PredictionError::MathOverflow)?;
+    self.points = self
+      .points
+      .checked_add(points)
+      .ok_or(PredictionError::MathOverflow)?;
+    self.epoch_points = self
+      .epoch_points
+      .checked_add(points)
+      .ok_or(PredictionError::MathOverflow)?;
+    Ok(())
+  }
+
+  fn add_volume(&mut self, amount: u64) -> Result<()> {
+    self.volume = self
+      .volume
+      .checked_add(amount)
+      .ok_or(PredictionError::MathOverflow)?;
+    Ok(())
+  }
+
+  fn realize(&mut self, pnl: i64) -> Result<()> {
+    self.realized_pnl = self
+      .realized_pnl
+      .checked_add(pnl)
+      .ok_or(PredictionError::MathOverflow)?;
+    Ok(())
+  }
+
+  fn take_lot(&mut self, market: Pubkey) -> Option<CostLot> {
+    let i = self.lots.iter().position(|l| l.market == market)?;
+    Some(self.lots.remove(i))
+  }
+
+  /// Track `shares` bought for `cost` collateral.
+  pub fn record_buy(&mut self, market: Pubkey, outcome: u8, shares: u64, cost: u64) -> Result<()> {
+    self.add_volume(cost)?;
+    let i = match self.lots.iter().position(|l| l.market == market) {
+      Some(i) => i,
+      None if self.lots.len() < MAX_STATS_LOTS => {
+        self.lots.push(CostLot {
+          market,
+          ..Default::default()
+        });
+        self.lots.len() - 1
+      }
+      None => return Ok(()),
+    };
+    self.lots[i].add(outcome, shares, cost)
+  }
+
+  /// Realize PnL on the tracked part of `shares` sold for `proceeds`.
+  pub fn record_sell(&mut self, market: Pubkey, outcome: u8, shares: u64, proceeds: u64) -> Result<()> {
+    self.add_volume(proceeds)?;
+    let Some(i) = self.lots.iter().position(|l| l.market == market) else {
+      return Ok(());
+    };
+    let pnl = self.lots[i].remove(outcome, shares, proceeds)?;
+    if self.lots[i].is_empty() {
+      self.lots.remove(i);
+    }
+    self.realize(pnl)
+  }
+
+  /// Close the market's lot against the tracked share of a claim `payout`
+  /// made on `winning_shares`.
+  pub fn record_claim(&mut self, market_key: Pubkey, market: &MarketV3, payout: u64, winning_shares: u64) -> Result<()> {
+    let Some(lot) = self.take_lot(market_key) else {
+      return Ok(());
+    };
+    let tracked = lot_winnings(market, &lot)?.min(winning_shares);
+    let value = mul_div(payout, tracked, winning_shares)?;
+    self.realize(value as i64 - lot.cost() as i64)
+  }
+
+  /// Close the market's lot at its settlement value.
+  pub fn record_settlement(&mut self, market_key: Pubkey, market: &MarketV3) -> Result<()> {
+    let Some(lot) = self.take_lot(market_key) else {
+      return Ok(());
+    };
+    let value = if market.winning_outcome >= 0 && market.resolved_total_winning_shares > 0 {
+      let tracked = lot_winnings(market, &lot)?;
+      mul_div(market.resolved_payable, tracked, market.resolved_total_winning_shares)?
+    } else {
+      0
+    };
+    self.realize(value as i64 - lot.cost() as i64)
+  }
+}
+
+/// Claim units a stats lot holds in a settled market, as
+/// `MarketV3::position_winnings` counts them for the position.
+fn lot_winnings(market: &MarketV3, lot: &CostLot) -> Result<u64> {
+  if market.winning_outcome == OUTCOME_REFUND_AT_COST {
+    Ok(lot.cost())
+  } else {
+    market.winning_shares(lot.yes_shares, lot.no_shares)
+  }
+}
+
+fn points_epoch(now: i64) -> u64 {
+  (now / POINTS_EPOCH_SECS) as u64
+}
+
+/// Trading competition over a fixed set of markets. `leaders` is the ranked
+/// leaderboard (best first), one per prize.
+#[account]
+#[derive(InitSpace)]
+pub struct Competition {
+  pub id: u64,
+  pub prize_mint: Pubkey,
+  pub start: i64,
+  pub end: i64,
+  #[max_len(MAX_COMPETITION_MARKETS)]
+  pub markets: Vec<Pubkey>,
+  #[max_len(MAX_COMPETITION_WINNERS)]
+  pub prizes: Vec<u64>, // by rank
+  #[max_len(MAX_COMPETITION_WINNERS)]
+  pub leaders: Vec<CompetitionLeader>,
+  pub finalized: bool,
+  pub bump: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct CompetitionLeader {
+  pub user: Pubkey,
+  pub pnl: i64,
+  pub claimed: bool,
+}
+
+/// A user's competition record. Only shares bought with the entry during
+/// the competition are tracked, so PnL can't be minted from shares acquired
+/// elsewhere (e.g. split from collateral).
+#[account]
+#[derive(InitSpace)]
+pub struct CompetitionEntry {
+  pub competition: Pubkey,
+  pub user: Pubkey,
+  pub start: i64,
+  pub end: i64,
+  pub realized_pnl: i64,
+  #[max_len(MAX_COMPETITION_MARKETS)]
+  pub lots: Vec<CostLot>,
+  pub submitted: bool,
+  pub bump: u8,
+}
+
+/// Tracked shares per side of one market and the collateral paid for them.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default, InitSpace)]
+pub struct CostLot {
+  pub market: Pubkey,
+  pub yes_shares: u64,
+  pub yes_cost: u64,
+  pub no_shares: u64,
+  pub no_cost: u64,
+}
+
+impl CostLot {
+  fn side(&mut self, outcome: u8) -> (&mut u64, &mut u64) {
+    match outcome {
+      0 => (&mut self.yes_shares, &mut self.yes_cost),
+      _ => (&mut self.no_shares, &mut self.no_cost),
+    }
+  }
+
+  pub fn cost(&self) -> u64 {
+    self.yes_cost.saturating_add(self.no_cost)
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.yes_shares == 0 && self.no_shares == 0
+  }
+
+  /// Add `shares` bought for `cost`.
+  pub fn add(&mut self, outcome: u8, shares: u64, cost: u64) -> Result<()> {
+    let (held, paid) = self.side(outcome);
+    *held = held.checked_add(shares).ok_or(PredictionError::MathOverflow)?;
+    *paid = paid.checked_add(cost).ok_or(PredictionError::MathOverflow)?;
+    Ok(())
+  }
+
+  /// Remove the tracked part of `shares` sold for `proceeds` at average
+  /// cost; returns the realized PnL.
+  pub fn remove(&mut self, outcome: u8, shares: u64, proceeds: u64) -> Result<i64> {
+    let (held, paid) = self.side(outcome);
+    let tracked = shares.min(*held);
+    if tracked == 0 {
+      return Ok(0);
+    }
+    let basis = mul_div(*paid, tracked, *held)?;
+    let tracked_proceeds = mul_div(proceeds, tracked, shares)?;
+    *held -= tracked;
+    *paid -= basis;
+    Ok(tracked_proceeds as i64 - basis as i64)
+  }
+}
+
+impl CompetitionEntry {
+  fn lot(&mut self, market: Pubkey, now: i64) -> Option<&mut CostLot> {
+    if now < self.start || now >= self.end {
+      return None;
+    }
+    self.lots.iter_mut().find(|l| l.market == market)
+  }
+
+  /// Track `shares` bought for `cost` collateral.
+  pub fn record_buy(&mut self, market: Pubkey, outcome: u8, shares: u64, cost: u64, now: i64) -> Result<()> {
+    match self.lot(market, now) {
+      Some(lot) => lot.add(outcome, shares, cost),
+      None => Ok(()),
+    }
+  }
+
+  /// Realize PnL on the tracked part of `shares` sold for `proceeds`.
+  pub fn record_sell(&mut self, market: Pubkey, outcome: u8, shares: u64, proceeds: u64, now: i64) -> Result<()> {
+    let Some(lot) = self.lot(market, now) else {
+      return Ok(());
+    };
+    let pnl = lot.remove(outcome, shares, proceeds)?;
+    self.realized_pnl = self
+      .realized_pnl
+      .checked_add(pnl)
+      .ok_or(PredictionError::MathOverflow)?;
+    Ok(())
+  }
+}
+
+/// Cumulative trading statistics of one market. `hourly_volume[h % 24]`
+/// holds the volume of hour `h` for the 24 hours up to `hour`.
+#[account]
+#[derive(InitSpace)]
+pub struct MarketStats {
+  pub market: Pubkey,
+  pub volume: u64,
+  pub fees: u64,
+  pub trades: u64,
+  pub traders: u64,
+  pub hour: i64, // unix hour of the latest bucket
+  pub hourly_volume: [u64; STATS_VOLUME_HOURS],
+  pub bump: u8,
+}
+
+impl MarketStats {
+  /// Count a trade of `notional` collateral that paid `fee`.
+  pub fn record_trade(&mut self, notional: u64, fee: u64, new_trader: bool, now: i64) -> Result<()> {
+    let hour = now / 3600;
+    if hour > self.hour {
+      let stale = (hour - self.hour).min(STATS_VOLUME_HOURS as i64);
+      for h in (hour - stale + 1)..=hour {
+        self.hourly_volume[h as usize % STATS_VOLUME_HOURS] = 0;
+      }
+      self.hour = hour;
+    }
+    let bucket = &mut self.hourly_volume[self.hour as usize % STATS_VOLUME_HOURS];
+    *bucket = bucket.checked_add(notional).ok_or(PredictionError::MathOverflow)?;
+
+    self.volume = self.volume.checked_add(notional).ok_or(PredictionError::MathOverflow)?;
+    self.fees = self.fees.checked_add(fee).ok_or(PredictionError::MathOverflow)?;
+    self.trades = self.trades.checked_add(1).ok_or(PredictionError::MathOverflow)?;
+    if new_trader {
+      self.traders = self.traders.checked_add(1).ok_or(PredictionError::MathOverflow)?;
+    }
+    Ok(())
+  }
+
+  /// Volume of the trailing 24 hours as of `now`.
+  pub fn volume_24h(&self, now: i64) -> u64 {
+    let hour = now / 3600;
+    let first = hour - STATS_VOLUME_HOURS as i64 + 1;
+    (first.max(self.hour - STATS_VOLUME_HOURS as i64 + 1)..=self.hour)
+      .map(|h| self.hourly_volume[h as usize % STATS_VOLUME_HOURS])
+      .fold(0u64, |acc, v| acc.saturating_add(v))
+  }
+}
+
+/// Ring buffer of OHLC candles of a market's YES probability (PRICE_SCALE).
+/// `candles[head]` is the latest of `count`; intervals with neither a trade
+/// nor a `snapshot_price` have no candle.
+#[account(zero_copy)]
+pub struct PriceCandles {
+  pub market: Pubkey,
+  pub interval: i64,
+  pub head: u32,
+  pub count: u32,
+  pub bump: u8,
+  pub _padding: [u8; 7],
+  pub candles: [Candle; MAX_PRICE_CANDLES],
+}
+
+#[zero_copy]
+pub struct Candle {
+  pub start: i64,
+  pub open: u64,
+  pub high: u64,
+  pub low: u64,
+  pub close: u64,
+}
+
+impl PriceCandles {
+  /// YES probability as of the start of `now`'s interval: the current
+  /// candle's open, else the latest candle's close. Trades inside the
+  /// current interval can't move it.
+  pub fn reference_price(&self, now: i64) -> Option<u64> {
+    if self.count == 0 {
+      return None;
+    }
+    let latest = &self.candles[self.head as usize];
+    let start = now - now.rem_euclid(self.interval);
+    Some(if latest.start == start { latest.open } else { latest.close })
+  }
+
+  /// Fold `price` at `now` into the current candle, opening a new one (at
+  /// the previous close) when `now` is past its interval.
+  pub fn record(&mut self, price: u64, now: i64) {
+    let start = now - now.rem_euclid(self.interval);
+    if self.count > 0 {
+      let latest = &mut self.candles[self.head as usize];
+      if latest.start == start {
+        latest.high = latest.high.max(price);
+        latest.low = latest.low.min(price);
+        latest.close = price;
+        return;
+      }
+    }
+
+    let open = if self.count > 0 {
+      self.candles[self.head as usize].close
+    } else {
+      price
+    };
+    if self.count > 0 {
+      self.head = (self.head + 1) % MAX_PRICE_CANDLES as u32;
+    }
+    self.count = (self.count + 1).min(MAX_PRICE_CANDLES as u32);
+    self.candles[self.head as usize] = Candle {
+      start,
+      open,
+      high: open.max(price),
+      low: open.min(price),
+      close: price,
+    };
+  }
+}
+
+/// Stable-layout price of one market for CPI consumers, versioned by
+/// `version`; fields are only ever appended out of `_reserved`. `depth`
+/// (the shallower AMM pool side) is a confidence proxy: the deeper the
+/// pools, the costlier it is to move `probability`. Read `status` /
+/// `winning_outcome` before trusting `probability` on a closed market.
+#[account(zero_copy)]
+pub struct PriceFeed {
+  pub version: u8,
+  pub status: u8, // MarketStatus
+  pub winning_outcome: i8,
+  pub bump: u8,
+  pub _padding: [u8; 4],
+  pub market: Pubkey,
+  pub probability: u64, // YES, PRICE_SCALE
+  pub depth: u64,
+  pub last_update: i64,
+  pub _reserved: [u8; 64],
+}
+
+const _: () = assert!(std::mem::size_of::<PriceFeed>() == 128);
+
+impl PriceFeed {
+  /// Copy the market's state; the price only moves while it's live.
+  pub fn refresh(&mut self, market: &MarketV3, now: i64) -> Result<()> {
+    if market.is_live() {
+      self.probability = market.implied_probability(0)?;
+      self.depth = market.yes_pool.min(market.no_pool);
+    }
+    self.status = market.status;
+    self.winning_outcome = market.winning_outcome;
+    self.last_update = now;
+    Ok(())
+  }
+}
+
+/// Ring buffer of a market's latest AMM trades; `trades[head]` is the most
+/// recent of `count`.
+#[account(zero_copy)]
+pub struct TradeTape {
+  pub market: Pubkey,
+  pub head: u32,
+  pub count: u32,
+  pub bump: u8,
+  pub _padding: [u8; 7],
+  pub trades: [TapeTrade; MAX_TAPE_TRADES],
+}
+
+#[zero_copy]
+pub struct TapeTrade {
+  pub timestamp: i64,
+  pub collateral: u64, // paid (buy) or received (sell)
+  pub shares: u64,
+  pub price: u64, // PRICE_SCALE per share
+  pub side: u8,   // TAPE_BUY / TAPE_SELL
+  pub outcome: u8,
+  pub _padding: [u8; 6],
+}
+
+impl TradeTape {
+  pub fn push(&mut self, trade: TapeTrade) {
+    if self.count > 0 {
+      self.head = (self.head + 1) % MAX_TAPE_TRADES as u32;
+    }
+    self.count = (self.count + 1).min(MAX_TAPE_TRADES as u32);
+    self.trades[self.head as usize] = trade;
+  }
+}
+
+/// Protocol-wide totals, updated by the instructions it's passed to.
+/// Amounts are raw collateral units summed across mints. `tvl` counts each
+/// market's vault balance as of its last sync.
+#[account]
+#[derive(InitSpace)]
+pub struct ProtocolStats {
+  pub markets_created: u64,
+  pub volume: u64,
+  pub fees: u64,
+  pub tvl: u64,
+  pub bump: u8,
+}
+
+impl ProtocolStats {
+  pub fn record_trade(&mut self, notional: u64, fee: u64) -> Result<()> {
+    self.volume = self.volume.checked_add(notional).ok_or(PredictionError::MathOverflow)?;
+    self.fees = self.fees.checked_add(fee).ok_or(PredictionError::MathOverflow)?;
+    Ok(())
+  }
+
+  /// Replace the market's last counted vault balance with the current one.
+  pub fn sync_tvl(&mut self, market: &mut MarketV3) -> Result<()> {
+    self.tvl = self
+      .tvl
+      .saturating_sub(market.stats_tvl)
+      .checked_add(market.vault_balance)
+      .ok_or(PredictionError::MathOverflow)?;
+    market.stats_tvl = market.vault_balance;
+    Ok(())
+  }
+}
+
+/// Liquidity-mining emissions of one market: `rate` reward tokens per second
+/// between `start` and `end`, shared pro-rata over the LP shares outstanding.
+#[account]
+#[derive(InitSpace)]
+pub struct LpRewards {
+  pub market: Pubkey,
+  pub reward_mint: Pubkey,
+  pub rate: u64,
+  pub start: i64,
+  pub end: i64,
+  pub acc_per_share: u128, // REWARD_ACC_SCALE
+  pub last_update: i64,
+  pub bump: u8,
+}
+
+impl LpRewards {
+  /// Accrue emissions up to `now` over `total_lp_shares`.
+  pub fn update(&mut self, total_lp_shares: u64, now: i64) -> Result<()> {
+    let from = self.last_update.max(self.start);
+    let to = now.min(self.end);
+    if to > from && total_lp_shares > 0 {
+      let emitted = (to - from) as u128 * self.rate as u128;
+      self.acc_per_share = emitted
+        .checked_mul(REWARD_ACC_SCALE)
+        .map(|e| e / total_lp_shares as u128)
+        .and_then(|d| self.acc_per_share.checked_add(d))
+        .ok_or(PredictionError::MathOverflow)?;
+    }
+    self.last_update = self.last_update.max(now);
+    Ok(())
+  }
+
+  fn accrued(&self, lp_shares: u64) -> Result<u128> {
+    (lp_shares as u128)
+      .checked_mul(self.acc_per_share)
+      .map(|a| a / REWARD_ACC_SCALE)
+      .ok_or_else(|| PredictionError::MathOverflow.into())
+  }
+
+  /// Move the position's pending rewards into `rewards_owed`. Call after
+  /// `update` and before its `lp_shares` change; then `reset_debt`.
+  pub fn settle(&self, lp_position: &mut LpPosition) -> Result<()> {
+    let pending = self
+      .accrued(lp_position.lp_shares)?
+      .saturating_sub(lp_position.reward_debt);
+    lp_position.rewards_owed = u64::try_from(pending)
+      .ok()
+      .and_then(|p| lp_position.rewards_owed.checked_add(p))
+      .ok_or(PredictionError::MathOverflow)?;
+    lp_position.reward_debt = self.accrued(lp_position.lp_shares)?;
+    Ok(())
+  }
+
+  /// Re-base the position's debt on its new `lp_shares`.
+  pub fn reset_debt(&self, lp_position: &mut LpPosition) -> Result<()> {
+    lp_position.reward_debt = self.accrued(lp_position.lp_shares)?;
+    Ok(())
+  }
+}
+
+/// Designated market makers of a market and their unclaimed rebates.
+#[account]
+#[derive(InitSpace)]
+pub struct MarketMakers {
+  pub market: Pubkey,
+  pub rebate_bps: u16, // of traded collateral
+  #[max_len(MAX_MARKET_MAKERS)]
+  pub makers: Vec<MakerRebate>,
+  pub bump: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct MakerRebate {
+  pub maker: Pubkey,
+  pub accrued: u64,
+}
+
+/// Operator whose signed `TradePermit`s a permit-gated market requires.
+#[account]
+#[derive(InitSpace)]
+pub struct PermitGate {
+  pub market: Pubkey,
+  pub operator: Pubkey,
+  pub bump: u8,
+}
+
+/// Holding a buyer of a token-gated market must show.
+#[account]
+#[derive(InitSpace)]
+pub struct TokenGate {
+  pub market: Pubkey,
+  pub mint: Pubkey,
+  pub min_balance: u64, // raw units; 1 for an NFT
+  pub bump: u8,
+}
+
+/// Wallet allowed to trade on an allowlist-gated market.
+#[account]
+#[derive(InitSpace)]
+pub struct AllowlistEntry {
+  pub market: Pubkey,
+  pub wallet: Pubkey,
+  pub bump: u8,
+}
+
+/// Parameter change waiting out the config's timelock.
+#[account]
+#[derive(InitSpace)]
+pub struct PendingParams {
+  pub params: ConfigParams,
+  pub eta: i64,
+  pub proposer: Pubkey, // receives the rent back
+  pub bump: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct CreateCompetitionArgs {
+  pub id: u64,
+  pub start: i64,
+  pub end: i64,
+  pub markets: Vec<Pubkey>,
+  pub prizes: Vec<u64>, // one per rank, best first
+}
+
+/// Protocol parameters for `set_params`; `None` leaves a field unchanged.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default, InitSpace)]
+pub struct ConfigParams {
+  pub creator_fee_share_bps: Option<u16>,
+  pub protocol_fee_share_bps: Option<u16>,
+  pub insurance_fee_share_bps: Option<u16>,
+  pub dispute_window: Option<i64>,
+  pub correction_window: Option<i64>,
+  pub dispute_bond: Option<u64>,
+  pub slash_disputer_bps: Option<u16>,
+  pub creation_bond: Option<u64>,
+  pub report_bond: Option<u64>,
+  pub treasury: Option<Pubkey>,
+  pub permissionless_collateral: Option<bool>,
+  #[max_len(MAX_APPROVED_MINTS)]
+  pub approved_mints: Option<Vec<Pubkey>>, // replaces the whole whitelist
+  pub param_timelock: Option<i64>,
+  pub min_trade_collateral: Option<u64>,
+  pub min_trade_shares: Option<u64>,
+  pub curator: Option<Pubkey>,
+  pub yield_program: Option<Pubkey>,
+}
+
+impl ConfigParams {
+  /// Fee and fee-recipient changes, dispute and correction terms, the
+  /// curator, the yield program (and the timelock itself) must be queued
+  /// while a timelock is configured.
+  pub fn is_timelocked(&self) -> bool {
+    self.creator_fee_share_bps.is_some()
+      || self.protocol_fee_share_bps.is_some()
+      || self.insurance_fee_share_bps.is_some()
+      || self.treasury.is_some()
+      || self.param_timelock.is_some()
+      || self.dispute_window.is_some()
+      || self.correction_window.is_some()
+      || self.dispute_bond.is_some()
+      || self.slash_disputer_bps.is_some()
+      || self.curator.is_some()
+      || self.yield_program.is_some()
+  }
+}
+
+/// Outcome for the SignedReport (signed over its borsh bytes by the reporter
+/// key) and Manual oracle adapters.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct ResolutionReport {
+  pub market: Pubkey,
+  pub winning_outcome: u8,
+  pub reported_at: i64, // must not precede the market's end_time
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct CreateSeriesArgs {
+  pub series_id: u64,
+  pub question_hash: [u8; 32], // sha256 of the question pattern
+  pub metadata_uri: String,    // base; instance n lives at `{metadata_uri}/{n}`
+  pub metadata: MarketMetadataArgs,
+  pub oracle: Pubkey, // resolution source; default = resolved by the authority
+  pub first_start_time: i64,
+  pub duration: i64,
+  pub initial_liquidity: u64, // per side, 50/50 open
+  pub creator_fee_share_bps: u16, // at most the config's
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct RegisterTemplateArgs {
+  pub template_id: u64,
+  pub question_hash: [u8; 32], // sha256 of the question format, with placeholders
+  pub rules_uri: String,       // resolution rules shared by every instance
+  pub category: u8,            // MarketCategory
+  pub oracle: Pubkey,          // resolution source; default = resolved by the creator
+  pub duration: i64,
+  pub creator_fee_share_bps: u16, // at most the config's
+  pub min_liquidity: u64,         // per side
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct TemplateMarketParams {
+  pub market_id: u64,
+  pub params: Vec<u8>, // the template's placeholder values, as the template specifies
+  pub metadata_uri: String,
+  pub image_uri: String,
+  pub tags: u64,
+  pub start_time: i64, // 0 = immediately; end_time = start + template duration
+  pub initial_yes_reserve: u64,
+  pub initial_no_reserve: u64,
+}
+
+/// Scalar range in the feed's own units and exponent.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct InitScalarArgs {
+  pub feed_id: [u8; 32], // Pyth price feed id
+  pub lower: i64,
+  pub upper: i64,
+  pub exponent: i32,
+}
+
+/// Price adapters compare against `threshold` in the feed's own units and
+/// exponent; the other fields are ignored by adapters that don't use them.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct InitOracleConfigArgs {
+  pub adapter: u8,       // OracleAdapter
+  pub feed: Pubkey,      // Chainlink feed account or SignedReport reporter key
+  pub feed_id: [u8; 32], // Pyth price feed id
+  pub threshold: i64,
+  pub exponent: i32,
+  pub yes_if_above: bool,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct PlaceParlayArgs {
+  pub parlay_id: u64,
+  pub outcomes: Vec<u8>, // one per leg market (remaining accounts)
+  pub stake: u64,
+  pub min_payout: u64,
+}
+
+#[repr(u8)]
+pub enum OrderSide {
+  Buy = 0,
+  Sell = 1,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct CreateOfferArgs {
+  pub offer_id: u64,
+  pub outcome_index: u8,
+  pub shares: u64,
+  // Pubkey::default() = priced in collateral of the offered market
+  pub ask_market: Pubkey,
+  pub ask_outcome: u8,
+  pub ask_amount: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct CreateMarketCpmmArgs {
+  pub market_id: u64,
+  pub question_hash: [u8; 32],
+  pub metadata_uri: String,
+  pub start_time: i64, // trading opens at this timestamp (0 = immediately)
+  pub end_time: i64,
+  // Opening reserves; equal values open at 50/50, no = 4 * yes opens YES at 80%
+  pub initial_yes_reserve: u64,
+  pub initial_no_reserve: u64,
+  pub metadata: MarketMetadataArgs,
+  pub opening_auction: Option<OpeningAuctionArgs>,
+}
+
+/// Dutch-auction opening: the YES price walks linearly from `start_yes_price`
+/// to `end_yes_price` (PRICE_SCALE) over `duration` seconds from trading start.
+/// The first trade re-centres the pool at the current auction price.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct OpeningAuctionArgs {
+  pub start_yes_price: u64,
+  pub end_yes_price: u64,
+  pub duration: i64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct MarketMetadataArgs {
+  pub category: u8, // MarketCategory
+  pub tags: u64,
+  pub image_uri: String,
+  pub rules_uri: String,
+}
+
+#[repr(u8)]
+pub enum MarketCategory {
+  Other = 0,
+  Politics = 1,
+  Sports = 2,
+  Crypto = 3,
+  Economics = 4,
+  Science = 5,
+  Entertainment = 6,
+  Weather = 7,
+}
+
+#[repr(u8)]
+pub enum ReportReason {
+  Duplicate = 0,
+  Ambiguous = 1,
+  Illegal = 2,
+  Other = 3,
+}
+
+impl TryFrom<u8> for ReportReason {
+  type Error = Error;
+
+  fn try_from(value: u8) -> Result<Self> {
+    Ok(match value {
+      0 => ReportReason::Duplicate,
+      1 => ReportReason::Ambiguous,
+      2 => ReportReason::Illegal,
+      3 => ReportReason::Other,
+      _ => return err!(PredictionError::InvalidReportReason),
+    })
+  }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum OracleAdapter {
+  Pyth = 0,
+  Switchboard = 1,
+  Chainlink = 2,
+  SignedReport = 3,
+  Manual = 4,
+}
+
+impl TryFrom<u8> for OracleAdapter {
+  type Error = Error;
+
+  fn try_from(value: u8) -> Result<Self> {
+    Ok(match value {
+      0 => OracleAdapter::Pyth,
+      1 => OracleAdapter::Switchboard,
+      2 => OracleAdapter::Chainlink,
+      3 => OracleAdapter::SignedReport,
+      4 => OracleAdapter::Manual,
+      _ => return err!(PredictionError::InvalidOracle),
+    })
+  }
+}
+
+#[repr(u8)]
+pub enum ReportAction {
+  Reject = 0,
+  Pause = 1,
+  Void = 2,
+}
+
+impl TryFrom<u8> for ReportAction {
+  type Error = Error;
+
+  fn try_from(value: u8) -> Result<Self> {
+    Ok(match value {
+      0 => ReportAction::Reject,
+      1 => ReportAction::Pause,
+      2 => ReportAction::Void,
+      _ => return err!(PredictionError::InvalidReportAction),
+    })
+  }
+}
+
+impl TryFrom<u8> for MarketCategory {
+  type Error = Error;
+
+  fn try_from(value: u8) -> Result<Self> {
+    Ok(match value {
+      0 => MarketCategory::Other,
+      1 => MarketCategory::Politics,
+      2 => MarketCategory::Sports,
+      3 => MarketCategory::Crypto,
+      4 => MarketCategory::Economics,
+      5 => MarketCategory::Science,
+      6 => MarketCategory::Entertainment,
+      7 => MarketCategory::Weather,
+      _ => return err!(PredictionError::InvalidCategory),
+    })
+  }
+}
+
+/// Which risky collateral-mint features the protocol tolerates.
+///
+/// Extensions that let a third party move, freeze, or tax vault funds are
+/// rejected unless explicitly allowed. Confidential-transfer and
+/// non-transferable mints are never accepted.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace)]
+pub struct CollateralPolicy {
+  pub allow_freeze_authority: bool,
+  pub allow_transfer_fee: bool,
+  pub allow_transfer_hook: bool,
+  pub allow_permanent_delegate: bool,
+  pub allow_default_account_state: bool,
+  pub allow_pausable: bool,
+}
+
+/// Spot price of a market, 1e6 fixed-point.
+///
+/// In a binary CPMM the marginal price of a share (collateral per share, with
+/// shares carrying the collateral's decimals) equals its implied probability.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct MarketPrice {
+  pub yes_price: u64,
+  pub no_price: u64,
+  pub collateral_decimals: u8,
+}
+
+/// Return data of `quote_buy` / `quote_sell`. Prices are PRICE_SCALE per
+/// share of the traded outcome; pools are as left by the trade.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct TradeQuote {
+  pub amount_out: u64, // shares (buy) or collateral (sell), net of fee
+  pub fee: u64,
+  pub yes_pool: u64,
+  pub no_pool: u64,
+  pub price_before: u64,
+  pub price_after: u64,
+  pub avg_price: u64, // collateral per share, fee included
+  pub price_impact_bps: u64, // |avg_price - price_before| / price_before
+}
+
+impl TradeQuote {
+  #[allow(clippy::too_many_arguments)]
+  fn new(
+    outcome_index: u8,
+    price_before: u64,
+    amount_out: u64,
+    fee: u64,
+    yes_pool: u64,
+    no_pool: u64,
+    collateral: u64,
+    shares: u64,
+  ) -> Result<Self> {
+    require!(shares > 0, PredictionError::ZeroSharesOut);
+    let avg_price = mul_div(collateral, PRICE_SCALE, shares)?;
+    Ok(Self {
+      amount_out,
+      fee,
+      yes_pool,
+      no_pool,
+      price_before,
+      price_after: implied_probability(yes_pool, no_pool, outcome_index)?,
+      avg_price,
+      price_impact_bps: mul_div(avg_price.abs_diff(price_before), BPS_DENOM, price_before)?,
+    })
+  }
+}
+
+#[repr(u8)]
+pub enum MarketStatus {
+  Open = 0,
+  Resolved = 1,
+  Cancelled = 2,
+  Disputed = 3,
+  // Created with a future start_time: liquidity and splits only, no trading
+  PreMarket = 4,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct ProtocolConfig {
+  pub admin: Pubkey,
+  pub collateral_policy: CollateralPolicy,
+  // When false, only `approved_mints` can back new markets
+  pub permissionless_collateral: bool,
+  #[max_len(MAX_APPROVED_MINTS)]
+  pub approved_mints: Vec<Pubkey>,
+  // Monotonic counter; each market gets the next value as its `market_seq`
+  pub market_count: u64,
+  // Lamports every creator escrows per market; forfeited to `treasury` on void
+  pub creation_bond: u64,
+  pub treasury: Pubkey,
+  // Seconds after resolution during which the outcome can be disputed (0 = no disputes)
+  pub dispute_window: i64,
+  // Lamports a disputer escrows; returned if upheld, forfeited to treasury if rejected
+  pub dispute_bond: u64,
+  // Share of a slashed creator bond paid to the disputer; the rest goes to winning traders
+  pub slash_disputer_bps: u16,
+  // May mark markets as verified for frontends
+  pub curator: Pubkey,
+  // Lamports a reporter escrows with `report_market`
+  pub report_bond: u64,
+  // Split of trading fees for new markets; LPs get the remainder
+  pub creator_fee_share_bps: u16,
+  pub protocol_fee_share_bps: u16,
+  pub insurance_fee_share_bps: u16,
+  // Lending program idle vault collateral may be deposited into; default = off
+  pub yield_program: Pubkey,
+  // Seconds after resolution the resolver or admin may correct the outcome;
+  // claims made meanwhile are escrowed until it closes
+  pub correction_window: i64,
+  // Proposed by `transfer_admin`, takes over on `accept_admin`
+  pub pending_admin: Pubkey,
+  // Delay between `queue_params` and `execute_params` for fee and recipient
+  // changes; while non-zero those can't be set directly (0 = no timelock)
+  pub param_timelock: i64,
+  // Smallest AMM trade accepted, in raw collateral units and shares, so
+  // positions worth less than their rent aren't opened (0 = no minimum)
+  pub min_trade_collateral: u64,
+  pub min_trade_shares: u64,
+  pub bump: u8,
+}
+
+impl ProtocolConfig {
+  /// Apply every `Some` field of `params`, with the individual setters' checks.
+  pub fn apply_params(&mut self, params: ConfigParams) -> Result<()> {
+    if let Some(bps) = params.creator_fee_share_bps {
+      self.creator_fee_share_bps = bps;
+    }
+    if let Some(bps) = params.protocol_fee_share_bps {
+      self.protocol_fee_share_bps = bps;
+    }
+    if let Some(bps) = params.insurance_fee_share_bps {
+      self.insurance_fee_share_bps = bps;
+    }
+    require!(
+      self.creator_fee_share_bps as u64
+        + self.protocol_fee_share_bps as u64
+        + self.insurance_fee_share_bps as u64
+        <= BPS_DENOM,
+      PredictionError::InvalidFeeShares
+    );
+
+    if let Some(window) = params.dispute_window {
+      require!(window >= 0, PredictionError::InvalidDisputeParams);
+      self.dispute_window = window;
+    }
+    if let Some(window) = params.correction_window {
+      require!(window >= 0, PredictionError::InvalidDisputeParams);
+      self.correction_window = window;
+    }
+    if let Some(bond) = params.dispute_bond {
+      self.dispute_bond = bond;
+    }
+    if let Some(bps) = params.slash_disputer_bps {
+      require!(bps as u64 <= BPS_DENOM, PredictionError::InvalidDisputeParams);
+      self.slash_disputer_bps = bps;
+    }
+    if let Some(bond) = params.creation_bond {
+      self.creation_bond = bond;
+    }
+    if let Some(bond) = params.report_bond {
+      self.report_bond = bond;
+    }
+    if let Some(treasury) = params.treasury {
+      self.treasury = treasury;
+    }
+    if let Some(permissionless) = params.permissionless_collateral {
+      self.permissionless_collateral = permissionless;
+    }
+    if let Some(mints) = params.approved_mints {
+      require!(mints.len() <= MAX_APPROVED_MINTS, PredictionError::ApprovedMintsFull);
+      for (i, mint) in mints.iter().enumerate() {
+        require!(!mints[..i].contains(mint), PredictionError::MintAlreadyApproved);
+      }
+      self.approved_mints = mints;
+    }
+    if let Some(delay) = params.param_timelock {
+      require!(delay >= 0, PredictionError::InvalidDisputeParams);
+      self.param_timelock = delay;
+    }
+    if let Some(min) = params.min_trade_collateral {
+      self.min_trade_collateral = min;
+    }
+    if let Some(min) = params.min_trade_shares {
+      self.min_trade_shares = min;
+    }
+    if let Some(curator) = params.curator {
+      self.curator = curator;
+    }
+    if let Some(program) = params.yield_program {
+      check_yield_program(program)?;
+      self.yield_program = program;
+    }
+    Ok(())
+  }
+
+  /// A buy must clear both minimums. Sells below them are only allowed
+  /// when they close out that side of the position.
+  pub fn check_trade_size(&self, collateral: u64, shares: u64, closing: bool) -> Result<()> {
+    require!(
+      closing || (collateral >= self.min_trade_collateral && shares >= self.min_trade_shares),
+      PredictionError::TradeTooSmall
+    );
+    Ok(())
+  }
+
+  /// Hand out the next global market sequence number.
+  pub fn next_market_seq(&mut self) -> Result<u64> {
+    self.market_count = self
+      .market_count
+      .checked_add(1)
+      .ok_or(PredictionError::MathOverflow)?;
+    Ok(self.market_count)
+  }
+}
+
+/// SOL escrowed by a market's creator. `amount` lamports sit on top of rent.
+#[account]
+#[derive(InitSpace)]
+pub struct CreatorBond {
+  pub market: Pubkey,
+  pub creator: Pubkey,
+  pub amount: u64,
+  // Set when a dispute overturns the creator's resolution
+  pub slashed: bool,
+  // Lamports owed pro-rata to winning positions after a slash
+  pub trader_pool: u64,
+  pub bump: u8,
+}
+
+/// Open challenge to a market's resolution; one at a time per market.
+#[account]
+#[derive(InitSpace)]
+pub struct Dispute {
+  pub market: Pubkey,
+  pub disputer: Pubkey,
+  pub proposed_outcome: u8,
+  pub bond: u64,
+  pub bump: u8,
+}
+
+/// A user's collateral balance for one mint, tradable across markets.
+/// The tokens are held in the mint's shared custody account.
+#[account]
+#[derive(InitSpace)]
+pub struct TradingAccount {
+  pub owner: Pubkey,
+  pub collateral_mint: Pubkey,
+  pub balance: u64,
+  pub bump: u8,
+}
+
+/// Ephemeral key allowed to trade from `owner`'s trading accounts.
+#[account]
+#[derive(InitSpace)]
+pub struct SessionKey {
+  pub owner: Pubkey,
+  pub session_key: Pubkey,
+  pub expiry: i64,
+  pub spend_cap: u64, // collateral, across buys
+  pub spent: u64,
+  pub bump: u8,
+}
+
+impl SessionKey {
+  /// Count `amount` of buying against the cap of a live session.
+  pub fn spend(&mut self, amount: u64, now: i64) -> Result<()> {
+    require!(now < self.expiry, PredictionError::SessionExpired);
+    self.spent = self
+      .spent
+      .checked_add(amount)
+      .filter(|s| *s <= self.spend_cap)
+      .ok_or(PredictionError::SessionSpendCapExceeded)?;
+    Ok(())
+  }
+}
+
+/// Resting limit order executed against the AMM by a keeper.
+#[account]
+#[derive(InitSpace)]
+pub struct LimitOrder {
+  pub owner: Pubkey,
+  pub market: Pubkey,
+  pub order_id: u64,
+  pub side: u8,
+  pub outcome_index: u8,
+  pub amount: u64,
+  pub limit_price: u64,
+  pub trigger_price: u64,
+  pub oracle_account: Pubkey, // default = no oracle condition
+  pub oracle_price: i64,
+  pub oracle_above: bool,
+  pub keeper_tip: u64,
+  pub filled: bool,
+  pub bump: u8,
+}
+
+/// Large order executed against the AMM in equal slices over time.
+#[account]
+#[derive(InitSpace)]
+pub struct TwapOrder {
+  pub owner: Pubkey,
+  pub market: Pubkey,
+  pub order_id: u64,
+  pub side: u8,
+  pub outcome_index: u8,
+  pub remaining: u64,
+  pub slices: u16,
+  pub slices_done: u16,
+  pub interval: i64,
+  pub next_slice_at: i64,
+  pub limit_price: u64,
+  pub keeper_tip: u64,
+  pub bump: u8,
+}
+
+/// Post-only central limit order book for a market's YES shares, priced in
+/// collateral. Bids are sorted best (highest) first, asks best (lowest) first.
+#[account]
+#[derive(InitSpace)]
+pub struct OrderBook {
+  pub market: Pubkey,
+  pub collateral_mint: Pubkey,
+  pub next_order_id: u64,
+  #[max_len(MAX_BOOK_ORDERS)]
+  pub bids: Vec<BookOrder>,
+  #[max_len(MAX_BOOK_ORDERS)]
+  pub asks: Vec<BookOrder>,
+  pub bump: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct BookOrder {
+  pub order_id: u64,
+  pub owner: Pubkey,
+  pub price: u64,      // PRICE_SCALE per YES share
+  pub shares: u64,     // unfilled
+  pub collateral: u64, // bids: locked collateral still unspent
+}
+
+/// Per-mint house pool backing parlay payouts.
+#[account]
+#[derive(InitSpace)]
+pub struct ParlayPool {
+  pub collateral_mint: Pubkey,
+  pub reserved: u64, // max payouts owed to open parlays
+  pub bump: u8,
+}
+
+/// Multi-leg bet paid only if every leg resolves favourably.
+#[account]
+#[derive(InitSpace)]
+pub struct Parlay {
+  pub owner: Pubkey,
+  pub parlay_id: u64,
+  pub collateral_mint: Pubkey,
+  pub stake: u64,
+  pub payout: u64,
+  #[max_len(MAX_PARLAY_LEGS)]
+  pub legs: Vec<ParlayLeg>,
+  pub bump: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct ParlayLeg {
+  pub market: Pubkey,
+  pub outcome: u8,
+  pub entry_price: u64, // probability the leg was priced at (PRICE_SCALE)
+}
+
+/// Recurring market (e.g. "BTC up this week?"). Instance `n` runs for
+/// `duration` seconds and hashes its question as sha256(question_hash || n).
+#[account]
+#[derive(InitSpace)]
+pub struct MarketSeries {
+  pub authority: Pubkey,
+  pub series_id: u64,
+  pub collateral_mint: Pubkey,
+  pub question_hash: [u8; 32],
+  #[max_len(MAX_METADATA_URI_LEN)]
+  pub metadata_uri: String,
+  pub category: u8,
+  pub tags: u64,
+  #[max_len(MAX_METADATA_URI_LEN)]
+  pub image_uri: String,
+  #[max_len(MAX_METADATA_URI_LEN)]
+  pub rules_uri: String,
+  pub oracle: Pubkey,
+  pub duration: i64,
+  pub initial_liquidity: u64,
+  pub creator_fee_share_bps: u16,
+  pub next_index: u64,
+  pub next_start_time: i64,
+  pub last_market: Pubkey,
+  pub bump: u8,
+}
+
+impl MarketSeries {
+  /// Index and start time of the next instance, skipping periods that have
+  /// already ended.
+  pub fn next_period(&self, now: i64) -> Result<(u64, i64)> {
+    let mut index = self.next_index;
+    let mut start = self.next_start_time;
+    if now >= start {
+      let missed = (now - start) / self.duration;
+      index = index
+        .checked_add(missed as u64)
+        .ok_or(PredictionError::MathOverflow)?;
+      start = missed
+        .checked_mul(self.duration)
+        .and_then(|d| start.checked_add(d))
+        .ok_or(PredictionError::MathOverflow)?;
+    }
+    Ok((index, start))
+  }
+
+  /// Creation args for instance `index` starting at `start_time`.
+  pub fn instance_args(&self, market_id: u64, index: u64, start_time: i64) -> Result<CreateMarketCpmmArgs> {
+    Ok(CreateMarketCpmmArgs {
+      market_id,
+      question_hash: hashv(&[&self.question_hash, &index.to_le_bytes()]).to_bytes(),
+      metadata_uri: format!("{}/{}", self.metadata_uri, index),
+      start_time,
+      end_time: start_time
+        .checked_add(self.duration)
+        .ok_or(PredictionError::MathOverflow)?,
+      initial_yes_reserve: self.initial_liquidity,
+      initial_no_reserve: self.initial_liquidity,
+      metadata: MarketMetadataArgs {
+        category: self.category,
+        tags: self.tags,
+        image_uri: self.image_uri.clone(),
+        rules_uri: self.rules_uri.clone(),
+      },
+      opening_auction: None,
+    })
+  }
+}
+
+/// Parameterized market registered by the admin or curator, so
+/// permissionless creators produce consistently-resolvable markets.
+#[account]
+#[derive(InitSpace)]
+pub struct MarketTemplate {
+  pub template_id: u64,
+  pub registrar: Pubkey,
+  pub question_hash: [u8; 32],
+  #[max_len(MAX_METADATA_URI_LEN)]
+  pub rules_uri: String,
+  pub category: u8,
+  pub oracle: Pubkey,
+  pub duration: i64,
+  pub creator_fee_share_bps: u16,
+  pub min_liquidity: u64,
+  pub active: bool,
+  pub bump: u8,
+}
+
+/// Oracle config of a scalar market.
+#[account]
+#[derive(InitSpace)]
+pub struct ScalarMarket {
+  pub market: Pubkey,
+  pub feed_id: [u8; 32],
+  pub lower: i64,
+  pub upper: i64,
+  pub exponent: i32,
+  pub settlement_price: i64, // unclamped expiry price, once settled
+  pub bump: u8,
+}
+
+/// How a market resolves through `resolve_with_oracle`. New adapters only
+/// extend `OracleAdapter`; the market just records that it has a config.
+#[account]
+#[derive(InitSpace)]
+pub struct OracleConfig {
+  pub market: Pubkey,
+  pub adapter: u8, // OracleAdapter
+  pub feed: Pubkey, // Chainlink feed account or SignedReport reporter key
+  pub feed_id: [u8; 32], // Pyth price feed id
+  pub threshold: i64,
+  pub exponent: i32,
+  pub yes_if_above: bool,
+  pub settlement_price: i64, // expiry price, once resolved by a price adapter
+  pub bump: u8,
+}
+
+/// Shares held back from a position claimed during the correction window.
+#[account]
+#[derive(InitSpace)]
+pub struct ClaimEscrow {
+  pub market: Pubkey,
+  pub owner: Pubkey,
+  pub yes_shares: u64,
+  pub no_shares: u64,
+  pub bump: u8,
+}
+
+/// Mutually exclusive markets of which exactly one resolves YES.
+#[account]
+#[derive(InitSpace)]
+pub struct MarketGroup {
+  pub authority: Pubkey,
+  pub group_id: u64,
+  pub collateral_mint: Pubkey,
+  #[max_len(MAX_GROUP_MEMBERS)]
+  pub members: Vec<Pubkey>,
+  pub converted: u64, // total NO converted; membership is frozen once > 0
+  pub resolved: bool,
+  pub bump: u8,
+  // Display label of each member's outcome, parallel to `members`
+  #[max_len(MAX_GROUP_MEMBERS, MAX_OUTCOME_LABEL_LEN)]
+  pub labels: Vec<String>,
+  // Member index that wins when the result matches no listed outcome
+  pub catch_all: Option<u8>,
+  pub payout_mode: u8, // GROUP_PAYOUT_*
+  // Ranked groups: PRICE_SCALE paid per YES share of the member finishing
+  // 1st, 2nd, ... (summing to PRICE_SCALE)
+  #[max_len(MAX_GROUP_MEMBERS)]
+  pub rank_weights: Vec<u64>,
+}
+
+/// Marks an RFQ quote nonce as used.
+#[account]
+#[derive(InitSpace)]
+pub struct QuoteFill {
+  pub maker: Pubkey,
+  pub nonce: u64,
+  pub bump: u8,
+}
+
+/// OTC offer escrowing a maker's shares until filled or cancelled.
+#[account]
+#[derive(InitSpace)]
+pub struct Offer {
+  pub maker: Pubkey,
+  pub offer_id: u64,
+  pub market: Pubkey,
+  pub outcome_index: u8,
+  pub shares: u64,
+  pub ask_market: Pubkey, // default = collateral of `market`
+  pub ask_outcome: u8,
+  pub ask_amount: u64,
+  pub bump: u8,
+}
+
+/// Optional index of the markets a user holds positions in.
+#[account]
+#[derive(InitSpace)]
+pub struct Portfolio {
+  pub owner: Pubkey,
+  #[max_len(MAX_PORTFOLIO_MARKETS)]
+  pub markets: Vec<Pubkey>,
+  pub bump: u8,
+}
+
+/// A liquidity provider's share of a market's pool reserves.
+#[account]
+#[derive(InitSpace)]
+pub struct LpPosition {
+  pub market: Pubkey,
+  pub owner: Pubkey,
+  pub lp_shares: u64,
+  // Creator's seeded liquidity: can't be removed until the market resolves
+  pub locked: bool,
+  pub bump: u8,
+  // Liquidity mining: `lp_shares * acc_per_share` already accounted for, and
+  // rewards settled but not yet claimed
+  pub reward_debt: u128,
+  pub rewards_owed: u64,
+}
+
+/// Open rule-violation report against a market, one per (market, reporter).
+#[account]
+#[derive(InitSpace)]
+pub struct Report {
+  pub market: Pubkey,
+  pub reporter: Pubkey,
+  pub reason: u8, // ReportReason
+  pub bond: u64,
+  pub bump: u8,
+}
+
+/// Registry head; `market_count` is the next free registry slot.
+#[account]
+#[derive(InitSpace)]
+pub struct MarketRegistry {
+  pub market_count: u64,
+  pub bump: u8,
+}
+
+/// Fixed-size page of registry entries, so clients can enumerate markets
+/// by fetching pages instead of scanning all program accounts.
+#[account]
+#[derive(InitSpace)]
+pub struct RegistryPage {
+  pub page: u64,
+  #[max_len(REGISTRY_PAGE_SIZE)]
+  pub entries: Vec<RegistryEntry>,
+  pub bump: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, InitSpace)]
+pub struct RegistryEntry {
+  pub market: Pubkey,
+  pub status: u8, // MarketStatus
+  pub end_time: i64,
+}
+
+/// Legacy (pre-zero-copy) market layout. No longer written by the program;
+/// kept so existing accounts can be decoded by `migrate_market_v2`.
+#[account]
+#[derive(InitSpace)]
+pub struct MarketV2 {
+  pub market_id: u64,
+  pub authority: Pubkey,
+  #[max_len(256)]
+  pub question: String,
+  pub collateral_mint: Pubkey,
+  pub vault: Pubkey,
+  pub end_time: i64,
+  pub status: u8,
+  pub winning_outcome: i8,
+
+  // Virtual reserves (collateral units)
+  pub yes_pool: u64,
+  pub no_pool: u64,
+
+  // Total outstanding shares
+  pub total_yes_shares: u64,
+  pub total_no_shares: u64,
+
+  // CLASSIC PRO-RATA snapshots
+  pub resolved_vault_balance: u64,
+  pub resolved_total_winning_shares: u64,
+}
+
+/// Zero-copy market account.
+///
+/// Fixed-size fields only (the question is stored as a sha256 hash of the
+/// off-chain document at `metadata_uri`), ordered so the `repr(C)` layout has
+/// no implicit padding. New fields are carved out of `_reserved`; once it
+/// runs out the account grows again under a new `layout_version`.
+#[account(zero_copy)]
+pub struct MarketV3 {
+  pub market_id: u64,
+  pub authority: Pubkey,
+  pub question_hash: [u8; 32],
+  pub collateral_mint: Pubkey,
+  pub vault: Pubkey,
+  pub end_time: i64,
+
+  // Virtual reserves (collateral units)
+  pub yes_pool: u64,
+  pub no_pool: u64,
+
+  // Total outstanding shares
+  pub total_yes_shares: u64,
+  pub total_no_shares: u64,
+
+  // CLASSIC PRO-RATA snapshots
+  pub resolved_vault_balance: u64,
+  pub resolved_total_winning_shares: u64,
+
+  pub status: u8,
+  pub winning_outcome: i8,
+  pub collateral_decimals: u8,
+
+  // PDA bumps, stored at init so later instructions skip find_program_address
+  pub bump: u8,
+  pub vault_bump: u8,
+  pub vault_auth_bump: u8,
+  pub metadata_uri_len: u8,
+  pub _padding: [u8; 1],
+
+  // UTF-8, first `metadata_uri_len` bytes are meaningful
+  pub metadata_uri: [u8; MAX_METADATA_URI_LEN],
+
+  // Discovery metadata (editable until the first trade)
+  pub tags: u64, // bitflags, meanings defined off-chain
+  pub trade_count: u64,
+  pub image_uri: [u8; MAX_METADATA_URI_LEN],
+  pub rules_uri: [u8; MAX_METADATA_URI_LEN],
+  pub category: u8,
+  pub image_uri_len: u8,
+  pub rules_uri_len: u8,
+  pub _padding2: [u8; 5],
+
+  // Position in the market registry (page = index / REGISTRY_PAGE_SIZE)
+  pub registry_index: u64,
+  pub registered: u8,
+  pub verified: u8, // vetted by the config curator
+  pub paused: u8,   // set by the curator; blocks buy/sell
+  pub yield_enabled: u8, // opted in to idle-collateral yield
+  // Seconds `update_end_time` has pushed `end_time` back in total
+  pub end_time_extension: u32,
+
+  // Globally unique sequence number (1-based) from `ProtocolConfig::market_count`
+  pub market_seq: u64,
+
+  // Set at resolution; claims and bond release wait for `dispute_deadline`
+  pub resolved_at: i64,
+  pub dispute_deadline: i64,
+
+  // Buy/sell are rejected before this timestamp
+  pub start_time: i64,
+
+  // Outstanding LP shares over the pool reserves
+  pub total_lp_shares: u64,
+
+  // Optional opening auction (auction_duration = 0: none)
+  pub auction_start: i64,
+  pub auction_duration: i64,
+  pub auction_start_price: u64,
+  pub auction_end_price: u64,
+
+  // Resolution split of the vault snapshot: winners share `resolved_payable`
+  // (capped at 1 per winning share), LPs share `residual_liquidity`
+  pub resolved_payable: u64,
+  pub residual_liquidity: u64,
+
+  // Trading fees held in the vault apart from backing. Creator and protocol
+  // buckets are withdrawable any time; LP fees join the residual at resolution.
+  pub lp_fees: u64,
+  pub creator_fees: u64,
+  pub protocol_fees: u64,
+  pub creator_fee_share_bps: u16,
+  pub protocol_fee_share_bps: u16,
+  pub insurance_fee_share_bps: u16,
+  pub resolver_bounty_bps: u16, // see `resolver_bounty`
+
+  // Collateral the program itself moved into the vault, net of payouts.
+  // Direct transfers to the vault don't count and can be skimmed.
+  pub vault_balance: u64,
+
+  // Insurance slice of fees, swept to the per-mint insurance vault
+  pub insurance_fees: u64,
+
+  // Collateral paid per winning share at resolution (PRICE_SCALE); below
+  // PRICE_SCALE means winners take a socialized haircut.
+  pub payout_rate: u64,
+
+  // Principal currently deposited in the lending program (not in the vault)
+  pub yield_deployed: u64,
+
+  // Liquid-staking collateral: SPL stake pool quoting the token's SOL value.
+  // Shares stay denominated in token units; the rate is informational.
+  pub lst_stake_pool: Pubkey,
+  pub resolved_lst_rate: u64, // lamports per token (PRICE_SCALE) at resolution
+
+  // Negative-risk group membership. Converting n NO here into YES elsewhere
+  // leaves n collateral owed to the winner (`group_no_claim`); YES minted
+  // here by conversions is backed by the other members (`group_backed_yes`).
+  pub group: Pubkey,
+  pub group_no_claim: u64,
+  pub group_backed_yes: u64,
+
+  // Conditional market: collateral is `parent_outcome` tokens of the parent,
+  // which must resolve before this market can.
+  pub parent_market: Pubkey,
+  pub parent_outcome: u8,
+  pub _padding5: [u8; 7],
+
+  // Recurring series this market is an instance of (default = none)
+  pub series: Pubkey,
+  pub series_index: u64,
+
+  // Template this market was created from (default = none)
+  pub template: Pubkey,
+
+  // Seconds after the dispute deadline that claims stay open (0 = forever).
+  // Afterwards unclaimed funds go to the treasury, or with `rollover_unclaimed`
+  // seed a later market in the same series.
+  pub claim_window: i64,
+  pub rollover_unclaimed: u8,
+  pub unclaimed_swept: u8,
+  pub scalar: u8, // settles from its `ScalarMarket` oracle config
+  pub oracle_market: u8, // resolved through its `OracleConfig`
+  // 1 = winnings are paid from a committed `PayoutDistributor` root;
+  // position claims are closed
+  pub payout_distributor: u8,
+  pub layout_version: u8, // MARKET_LAYOUT_VERSION
+  pub _padding6: [u8; 2],
+
+  // Per-share payout of YES (PRICE_SCALE) when `winning_outcome` is
+  // OUTCOME_SPLIT or OUTCOME_INVALID; NO gets the complement
+  pub payout_fraction: u64,
+
+  // End of the correction window; payouts also wait for `dispute_deadline`,
+  // which is never earlier
+  pub correction_deadline: i64,
+
+  // Seconds after `resolved_at` before winnings can be claimed
+  pub claim_delay: i64,
+
+  // Paid from the vault to whoever executes a permissionless resolution
+  // (plus `resolver_bounty_bps` of the vault balance)
+  pub resolver_bounty: u64,
+
+  // Sum of every position's `cost_basis`; a market cancelled with
+  // OUTCOME_REFUND_AT_COST refunds positions against it
+  pub total_cost_basis: u64,
+
+  // Squads vault PDA that must sign resolutions instead of `authority`
+  // (default = none)
+  pub resolution_vault: Pubkey,
+
+  // Trading fee is waived in [fee_holiday_start, fee_holiday_end)
+  pub fee_holiday_start: i64,
+  pub fee_holiday_end: i64,
+
+  // Rebates accrued by designated market makers, not yet claimed
+  pub mm_rebates: u64,
+
+  // 1 = liquidity mining configured; LP share changes must pass `LpRewards`
+  pub lp_rewards: u8,
+  // 1 = `MarketStats` initialized; AMM trades must pass it
+  pub market_stats: u8,
+  // 1 = `PriceCandles` initialized; AMM trades must pass it
+  pub price_candles: u8,
+  // 1 = `TradeTape` initialized; AMM trades must pass it
+  pub trade_tape: u8,
+  // 1 = `PriceFeed` initialized; AMM trades must pass it
+  pub price_feed: u8,
+  // 1 = only wallets with an `AllowlistEntry` can buy or sell
+  pub allowlist: u8,
+  // 1 = buyers must hold the `TokenGate` mint
+  pub token_gate: u8,
+  // 1 = buys and sells need a `TradePermit` signed by the `PermitGate` operator
+  pub trade_permit: u8,
+
+  // Vault balance last counted in `ProtocolStats::tvl`
+  pub stats_tvl: u64,
+
+  // Cap on YES or NO shares one position can hold after a buy (0 = none)
+  pub max_position_shares: u64,
+
+  // Winning shares paid out of `resolved_payable` so far
+  pub claimed_winning_shares: u64,
+
+  pub _reserved: [u8; 256],
+}
+
+const _: () = assert!(std::mem::size_of::<MarketV3>() == 1280);
+
+impl MarketV3 {
+  pub fn metadata_uri(&self) -> &[u8] {
+    &self.metadata_uri[..self.metadata_uri_len as usize]
+  }
+
+  /// Open or still in the pre-market phase.
+  pub fn is_live(&self) -> bool {
+    self.status == MarketStatus::Open as u8 || self.status == MarketStatus::PreMarket as u8
+  }
+
+  /// Pre-market markets open lazily on the first instruction after `start_time`.
+  pub fn refresh_phase(&mut self, now: i64) {
+    if self.status == MarketStatus::PreMarket as u8 && now >= self.start_time {
+      self.status = MarketStatus::Open as u8;
+    }
+  }
+
+  /// Record the vault snapshot and split its backing capital (the vault minus
+  /// unwithdrawn fees) between winners and LPs; LP fees go to the residual.
+  pub fn snapshot_resolution(&mut self, vault_balance: u64, total_winning_shares: u64) -> Result<()> {
+    let backing = vault_balance
+      .checked_sub(self.lp_fees)
+      .and_then(|b| b.checked_sub(self.creator_fees))
+      .and_then(|b| b.checked_sub(self.protocol_fees))
+      .and_then(|b| b.checked_sub(self.insurance_fees))
+      .and_then(|b| b.checked_sub(self.mm_rebates))
+      .ok_or(PredictionError::MathOverflow)?;
+    let payable = backing.min(total_winning_shares);
+
+    self.resolved_vault_balance = vault_balance;
+    self.resolved_total_winning_shares = total_winning_shares;
+    self.resolved_payable = payable;
+    self.payout_rate = payout_rate(payable, total_winning_shares)?;
+    self.residual_liquidity = (backing - payable)
+      .checked_add(self.lp_fees)
+      .ok_or(PredictionError::MathOverflow)?;
+    Ok(())
+  }
+
+  /// Every fee ever charged and not yet withdrawn, across all buckets.
+  pub fn total_fees(&self) -> Result<u64> {
+    self
+      .lp_fees
+      .checked_add(self.creator_fees)
+      .and_then(|f| f.checked_add(self.protocol_fees))
+      .and_then(|f| f.checked_add(self.insurance_fees))
+      .and_then(|f| f.checked_add(self.mm_rebates))
+      .ok_or_else(|| PredictionError::MathOverflow.into())
+  }
+
+  /// Collateral owed if the side with more outstanding shares wins, plus
+  /// every fee bucket not yet withdrawn.
+  pub fn worst_case_liabilities(&self) -> Result<u64> {
+    // Group-minted YES is backed by the other members' vaults
+    self
+      .total_yes_shares
+      .saturating_sub(self.group_backed_yes)
+      .max(self.total_no_shares)
+      .checked_add(self.lp_fees)
+      .and_then(|l| l.checked_add(self.creator_fees))
+      .and_then(|l| l.checked_add(self.protocol_fees))
+      .and_then(|l| l.checked_add(self.insurance_fees))
+      .and_then(|l| l.checked_add(self.mm_rebates))
+      .ok_or_else(|| PredictionError::MathOverflow.into())
+  }
+
+  /// Collateral the market controls: the recorded vault balance plus
+  /// principal deployed to the lending program.
+  pub fn total_assets(&self) -> Result<u64> {
+    self
+      .vault_balance
+      .checked_add(self.yield_deployed)
+      .ok_or_else(|| PredictionError::MathOverflow.into())
+  }
+
+  pub fn credit_vault(&mut self, amount: u64) -> Result<()> {
+    self.vault_balance = self
+      .vault_balance
+      .checked_add(amount)
+      .ok_or(PredictionError::MathOverflow)?;
+    Ok(())
+  }
+
+  /// Pay `winning_shares` out of the winners' snapshot, see `pro_rata_step`.
+  pub fn pay_winnings(&mut self, winning_shares: u64) -> Result<u64> {
+    require!(self.payout_distributor == 0, PredictionError::PayoutsViaDistributor);
+    let payout = pro_rata_step(
+      self.resolved_payable,
+      self.claimed_winning_shares,
+      winning_shares,
+      self.resolved_total_winning_shares,
+    )?;
+    self.claimed_winning_shares += winning_shares;
+    self.debit_vault(payout)?;
+    Ok(payout)
+  }
+
+  pub fn debit_vault(&mut self, amount: u64) -> Result<()> {
+    self.vault_balance = self
+      .vault_balance
+      .checked_sub(amount)
+      .ok_or(PredictionError::VaultShortfall)?;
+    Ok(())
+  }
+
+  /// Split a trading fee into the LP / creator / protocol / insurance buckets.
+  pub fn accrue_fee(&mut self, fee: u64) -> Result<()> {
+    let creator = mul_div(fee, self.creator_fee_share_bps as u64, BPS_DENOM)?;
+    let protocol = mul_div(fee, self.protocol_fee_share_bps as u64, BPS_DENOM)?;
+    let insurance = mul_div(fee, self.insurance_fee_share_bps as u64, BPS_DENOM)?;
+    let lp = fee
+      .checked_sub(creator)
+      .and_then(|f| f.checked_sub(protocol))
+      .and_then(|f| f.checked_sub(insurance))
+      .ok_or(PredictionError::MathOverflow)?;
+
+    self.insurance_fees = self
+      .insurance_fees
+      .checked_add(insurance)
+      .ok_or(PredictionError::MathOverflow)?;
+
+    self.creator_fees = self
+      .creator_fees
+      .checked_add(creator)
+      .ok_or(PredictionError::MathOverflow)?;
+    self.protocol_fees = self
+      .protocol_fees
+      .checked_add(protocol)
+      .ok_or(PredictionError::MathOverflow)?;
+    self.lp_fees = self
+      .lp_fees
+      .checked_add(lp)
+      .ok_or(PredictionError::MathOverflow)?;
+    Ok(())
+  }
+
+  /// YES price of the opening auction at `now` (PRICE_SCALE).
+  pub fn auction_price(&self, now: i64) -> Result<u64> {
+    let elapsed = now
+      .saturating_sub(self.auction_start)
+      .clamp(0, self.auction_duration) as u128;
+    let duration = self.auction_duration as u128;
+    let start = self.auction_start_price as u128;
+    let end = self.auction_end_price as u128;
+
+    let price = if end >= start {
+      start + (end - start) * elapsed / duration
+    } else {
+      start - (start - end) * elapsed / duration
+    };
+    price.try_into().map_err(|_| PredictionError::MathOverflow.into())
+  }
+
+  /// On the first trade of a market with an opening auction, re-centre the
+  /// pool at the current auction price (keeping yes + no constant), then end
+  /// the auction. Returns the opening YES price if it was set.
+  pub fn settle_opening_auction(&mut self, now: i64) -> Result<Option<u64>> {
+    if self.auction_duration == 0 || self.trade_count > 0 {
+      return Ok(None);
+    }
+
+    let yes_price = self.auction_price(now)?;
+    let total = self
+      .yes_pool
+      .checked_add(self.no_pool)
+      .ok_or(PredictionError::MathOverflow)?;
+    // p_yes = no / (yes + no)
+    let no_pool = mul_div(total, yes_price, PRICE_SCALE)?;
+    let yes_pool = total
+      .checked_sub(no_pool)
+      .ok_or(PredictionError::MathOverflow)?;
+    require!(
+      yes_pool > 0 && no_pool > 0,
+      PredictionError::InvalidLiquidity
+    );
+
+    self.yes_pool = yes_pool;
+    self.no_pool = no_pool;
+    self.auction_duration = 0;
+    Ok(Some(yes_price))
+  }
+
+  pub fn image_uri(&self) -> &[u8] {
+    &self.image_uri[..self.image_uri_len as usize]
+  }
+
+  pub fn rules_uri(&self) -> &[u8] {
+    &self.rules_uri[..self.rules_uri_len as usize]
+  }
+
+  fn set_metadata(&mut self, metadata: &MarketMetadataArgs) -> Result<()> {
+    MarketCategory::try_from(metadata.category)?;
+    let (image_uri, image_uri_len) = encode_metadata_uri(&metadata.image_uri)?;
+    let (rules_uri, rules_uri_len) = encode_metadata_uri(&metadata.rules_uri)?;
+
+    self.category = metadata.category;
+    self.tags = metadata.tags;
+    self.image_uri = image_uri;
+    self.image_uri_len = image_uri_len;
+    self.rules_uri = rules_uri;
+    self.rules_uri_len = rules_uri_len;
+    Ok(())
+  }
+
+  /// Open the correction and dispute windows at resolution time `now`.
+  pub fn set_challenge_deadlines(&mut self, config: &ProtocolConfig, now: i64) -> Result<()> {
+    self.correction_deadline = now
+      .checked_add(config.correction_window)
+      .ok_or(PredictionError::MathOverflow)?;
+    self.dispute_deadline = now
+      .checked_add(config.dispute_window)
+      .ok_or(PredictionError::MathOverflow)?
+      .max(self.correction_deadline);
+    Ok(())
+  }
+
+  /// Debit the resolver bounty from the vault ahead of the resolution
+  /// snapshot, capped at the backing left over once every winning share is
+  /// covered. Returns the amount to transfer.
+  pub fn take_resolver_bounty(&mut self, total_winning_shares: u64) -> Result<u64> {
+    let spare = self
+      .vault_balance
+      .checked_sub(self.lp_fees)
+      .and_then(|b| b.checked_sub(self.creator_fees))
+      .and_then(|b| b.checked_sub(self.protocol_fees))
+      .and_then(|b| b.checked_sub(self.insurance_fees))
+      .and_then(|b| b.checked_sub(self.mm_rebates))
+      .ok_or(PredictionError::MathOverflow)?
+      .saturating_sub(total_winning_shares);
+    let bounty = mul_div(self.vault_balance, self.resolver_bounty_bps as u64, BPS_DENOM)?
+      .checked_add(self.resolver_bounty)
+      .ok_or(PredictionError::MathOverflow)?
+      .min(spare);
+    self.debit_vault(bounty)?;
+    Ok(bounty)
+  }
+
+  /// Resolved, or voided with refunds at cost (see `cancel_at_cost`) or, by
+  /// earlier builds of `void_unresolvable_market`, at a snapshotted split;
+  /// either way claims and LP withdrawals run off the resolution snapshot.
+  pub fn is_settled(&self) -> bool {
+    self.status == MarketStatus::Resolved as u8
+      || (self.status == MarketStatus::Cancelled as u8
+        && (self.winning_outcome == OUTCOME_SPLIT
+          || self.winning_outcome == OUTCOME_REFUND_AT_COST))
+  }
+
+  /// Cancel a live market and snapshot refunds at cost: positions share the
+  /// backing pro-rata by `cost_basis`, in full unless it falls short of
+  /// `total_cost_basis`, and LPs keep the rest as after a resolution.
+  pub fn cancel_at_cost(&mut self, now: i64) -> Result<()> {
+    require!(self.yield_deployed == 0, PredictionError::YieldDeployed);
+    self.winning_outcome = OUTCOME_REFUND_AT_COST;
+    self.snapshot_resolution(self.vault_balance, self.total_cost_basis)?;
+    self.status = MarketStatus::Cancelled as u8;
+    self.resolved_at = now;
+    self.dispute_deadline = now;
+    self.correction_deadline = now;
+    Ok(())
+  }
+
+  /// Key allowed to resolve manually: the Squads vault if one is set,
+  /// otherwise the authority.
+  pub fn resolver(&self) -> Pubkey {
+    if self.resolution_vault != Pubkey::default() {
+      self.resolution_vault
+    } else {
+      self.authority
+    }
+  }
+
+  /// Fee rate for a trade at `now`: zero during a fee holiday.
+  pub fn fee_bps(&self, now: i64) -> u64 {
+    if now >= self.fee_holiday_start && now < self.fee_holiday_end {
+      0
+    } else {
+      FEE_BPS
+    }
+  }
+
+  /// Earliest time claims may execute: after both the dispute deadline and
+  /// the market's claim delay.
+  pub fn claims_open_at(&self) -> Result<i64> {
+    let delayed = self
+      .resolved_at
+      .checked_add(self.claim_delay)
+      .ok_or(PredictionError::MathOverflow)?;
+    Ok(delayed.max(self.dispute_deadline))
+  }
+
+  /// Claim units `position` holds: its cost basis when refunding at cost,
+  /// otherwise its winning shares.
+  pub fn position_winnings(&self, position: &PositionV2) -> Result<u64> {
+    if self.winning_outcome == OUTCOME_REFUND_AT_COST {
+      Ok(position.cost_basis)
+    } else {
+      self.winning_shares(position.yes_shares, position.no_shares)
+    }
+  }
+
+  /// Winning-share units `yes` / `no` shares are worth under the resolved
+  /// outcome; claims are pro-rata over `resolved_total_winning_shares`.
+  pub fn winning_shares(&self, yes: u64, no: u64) -> Result<u64> {
+    match self.winning_outcome {
+      0 => Ok(yes),
+      1 => Ok(no),
+      OUTCOME_SPLIT | OUTCOME_INVALID => split_shares_value(yes, no, self.payout_fraction),
+      _ => err!(PredictionError::InvalidWinningOutcome),
+    }
+  }
+
+  /// Implied probability of `outcome_index` (0 = YES, 1 = NO) in PRICE_SCALE.
+  pub fn implied_probability(&self, outcome_index: u8) -> Result<u64> {
+    implied_probability(self.yes_pool, self.no_pool, outcome_index)
+  }
+
+  pub fn price(&self) -> Result<MarketPrice> {
+    let yes_price = self.implied_probability(0)?;
+    Ok(MarketPrice {
+      yes_price,
+      no_price: PRICE_SCALE
+        .checked_sub(yes_price)
+        .ok_or(PredictionError::MathOverflow)?,
+      collateral_decimals: self.collateral_decimals,
+    })
+  }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct PositionV2 {
+  pub market: Pubkey,
+  pub owner: Pubkey,
+  pub yes_shares: u64,
+  pub no_shares: u64,
+  pub claimed: bool,
+  pub bump: u8,
+  // Net collateral put in through the pool and complete sets: buys and splits
+  // minus sell and merge proceeds, floored at zero
+  pub cost_basis: u64,
+}
+
+/// A market's concurrent Merkle tree of compressed positions; each leaf is
+/// a `compressed_leaf` hash for one compressed buy.
+#[account]
+#[derive(InitSpace)]
+pub struct CompressedPositions {
+  pub market: Pubkey,
+  pub merkle_tree: Pubkey,
+  pub num_leaves: u64,
+  pub bump: u8,
+}
+
+/// Where a market's idle collateral is deployed: the lending program and
+/// reserve, and the `yield_receipt` token account holding the reserve's
+/// collateral tokens. Fixed by the first `deploy_idle_collateral`.
+#[account]
+#[derive(InitSpace)]
+pub struct YieldPosition {
+  pub market: Pubkey,
+  pub lending_program: Pubkey,
+  pub reserve: Pubkey,
+  pub receipt: Pubkey,
+  // Principal lost across withdrawals (returned below what was deployed)
+  pub total_shortfall: u64,
+  pub bump: u8,
+}
+
+/// Merkle root of a market's per-user payouts, committed after resolution.
+/// Leaves are `distributor_leaf(index, claimant, amount)`.
+#[account]
+#[derive(InitSpace)]
+pub struct PayoutDistributor {
+  pub market: Pubkey,
+  pub root: [u8; 32],
+  pub total_payout: u64,
+  pub claimed: u64,
+  pub num_claims: u64,
+  pub bump: u8,
+}
+
+/// Receipt for one claimed `PayoutDistributor` leaf, seeded by its index.
+#[account]
+#[derive(InitSpace)]
+pub struct DistributorClaim {
+  pub claimant: Pubkey,
+  pub amount: u64,
+  pub claimed_at: i64,
+}
+
+/// Position layout before `bump` (and later `cost_basis`) was stored; read by
+/// `migrate_position_v2`.
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct LegacyPositionV2 {
+  pub market: Pubkey,
+  pub owner: Pubkey,
+  pub yes_shares: u64,
+  pub no_shares: u64,
+  pub claimed: bool,
+}
+
+impl LegacyPositionV2 {
+  pub const SIZE: usize = 32 + 32 + 8 + 8 + 1;
+}
+
+// ----------------------------
+// Events
+// ----------------------------
+
+#[event]
+pub struct MarketCreated {
+  pub market: Pubkey,
+  pub authority: Pubkey,
+  pub market_id: u64,
+  pub market_seq: u64,
+  pub question_hash: [u8; 32],
+  pub metadata_uri: String,
+  pub collateral_mint: Pubkey,
+  pub start_time: i64,
+  pub end_time: i64,
+}
+
+#[event]
+pub struct ResolutionDisputed {
+  pub market: Pubkey,
+  pub disputer: Pubkey,
+  pub proposed_outcome: u8,
+  pub bond: u64,
+}
+
+#[event]
+pub struct MarketResolved {
+  pub market: Pubkey,
+  pub winning_outcome: i8,
+  pub total_winning_shares: u64,
+  pub payable: u64,
+  pub payout_rate: u64,
+  pub lst_rate: u64, // 0 unless the collateral is a liquid-staking token
+}
+
+#[event]
+pub struct DisputeSettled {
+  pub market: Pubkey,
+  pub disputer: Pubkey,
+  pub upheld: bool,
+  pub winning_outcome: i8,
+  pub payout_rate: u64,
+}
+
+#[event]
+pub struct CreatorBondSlashed {
+  pub market: Pubkey,
+  pub creator: Pubkey,
+  pub disputer: Pubkey,
+  pub disputer_amount: u64,
+  pub trader_amount: u64,
+}
+
+#[event]
+pub struct BondReleased {
+  pub market: Pubkey,
+  pub creator: Pubkey,
+  pub amount: u64,
+}
+
+/// Creator bond forfeited on a void: to the treasury when the admin voids,
+/// to the reporter as a reward when a report does.
+#[event]
+pub struct BondForfeited {
+  pub market: Pubkey,
+  pub creator: Pubkey,
+  pub recipient: Pubkey,
+  pub amount: u64,
+}
+
+#[event]
+pub struct SolvencyChecked {
+  pub market: Pubkey,
+  pub liabilities: u64,
+  pub vault_balance: u64,
+  pub vault_amount: u64,
+  pub solvent: bool,
+  pub paused: bool,
+}
+
+#[event]
+pub struct MarketVerificationChanged {
+  pub market: Pubkey,
+  pub curator: Pubkey,
+  pub verified: bool,
+}
+
+#[event]
+pub struct OpeningPriceSet {
+  pub market: Pubkey,
+  pub yes_price: u64,
+}
+
+#[event]
+pub struct LiquidityAdded {
+  pub market: Pubkey,
+  pub provider: Pubkey,
+  pub amount: u64,
+  pub lp_shares: u64,
+}
+
+#[event]
+pub struct LiquidityRemoved {
+  pub market: Pubkey,
+  pub provider: Pubkey,
+  pub lp_shares: u64,
+  pub yes_shares: u64,
+  pub no_shares: u64,
+}
+
+#[event]
+pub struct FeesWithdrawn {
+  pub market: Pubkey,
+  pub recipient: Pubkey,
+  pub amount: u64,
+  pub protocol: bool,
+}
+
+#[event]
+pub struct InsuranceFeesSwept {
+  pub market: Pubkey,
+  pub amount: u64,
+}
+
+#[event]
+pub struct InsuranceToppedUp {
+  pub market: Pubkey,
+  pub amount: u64,
+}
+
+#[event]
+pub struct IdleCollateralDeployed {
+  pub market: Pubkey,
+  pub reserve: Pubkey,
+  pub amount: u64,
+  pub receipt_minted: u64,
+}
+
+#[event]
+pub struct IdleCollateralWithdrawn {
+  pub market: Pubkey,
+  pub principal: u64,
+  pub returned: u64,
+  pub shortfall: u64, // principal the reserve didn't return
+}
+
+#[event]
+pub struct LstRateObserved {
+  pub market: Pubkey,
+  pub stake_pool: Pubkey,
+  pub rate: u64, // lamports per token, PRICE_SCALE
+}
+
+#[event]
+pub struct LimitOrderPlaced {
+  pub order: Pubkey,
+  pub owner: Pubkey,
+  pub market: Pubkey,
+  pub side: u8,
+  pub outcome_index: u8,
+  pub amount: u64,
+  pub limit_price: u64,
+  pub trigger_price: u64,
+}
+
+#[event]
+pub struct LimitOrderFilled {
+  pub order: Pubkey,
+  pub keeper: Pubkey,
+  pub tip: u64,
+}
+
+#[event]
+pub struct LimitOrderClosed {
+  pub order: Pubkey,
+  pub owner: Pubkey,
+  pub filled: bool,
+}
+
+#[event]
+pub struct TwapOrderPlaced {
+  pub order: Pubkey,
+  pub owner: Pubkey,
+  pub market: Pubkey,
+  pub side: u8,
+  pub outcome_index: u8,
+  pub amount: u64,
+  pub slices: u16,
+  pub interval: i64,
+  pub limit_price: u64,
+}
+
+#[event]
+pub struct TwapSliceExecuted {
+  pub order: Pubkey,
+  pub keeper: Pubkey,
+  pub slice_index: u16,
+  pub amount_in: u64,
+  pub amount_out: u64,
+  pub tip: u64,
+}
+
+#[event]
+pub struct TwapOrderClosed {
+  pub order: Pubkey,
+  pub owner: Pubkey,
+  pub slices_done: u16,
+}
+
+#[event]
+pub struct ParlayPlaced {
+  pub parlay: Pubkey,
+  pub owner: Pubkey,
+  pub legs: u8,
+  pub stake: u64,
+  pub payout: u64,
+}
+
+#[event]
+pub struct ParlaySettled {
+  pub parlay: Pubkey,
+  pub owner: Pubkey,
+  pub won: bool,
+  pub payout: u64,
+}
+
+#[event]
+pub struct SeriesMarketCreated {
+  pub series: Pubkey,
+  pub market: Pubkey,
+  pub index: u64,
+}
+
+#[event]
+pub struct UnclaimedSwept {
+  pub market: Pubkey,
+  pub destination: Pubkey, // next series market, or the treasury's token account
+  pub amount: u64,
+  pub rolled_over: bool,
+}
+
+#[event]
+pub struct ScalarSettled {
+  pub market: Pubkey,
+  pub price: i64,
+  pub exponent: i32,
+  pub publish_time: i64,
+  pub payout_fraction: u64,
+}
+
+#[event]
+pub struct MarketVoidedOnOracleFailure {
+  pub market: Pubkey,
+  pub refundable: u64, // cost basis refunded to positions
+}
+
+#[event]
+pub struct OracleResolved {
+  pub market: Pubkey,
+  pub adapter: u8,
+  pub price: i64, // 0 for report adapters
+  pub exponent: i32,
+  pub publish_time: i64, // report time for report adapters
+  pub winning_outcome: i8,
+}
+
+#[event]
+pub struct ResolutionMultisigSet {
+  pub market: Pubkey,
+  pub multisig: Pubkey,
+  pub vault: Pubkey,
+}
+
+#[event]
+pub struct AdminTransferred {
+  pub previous_admin: Pubkey,
+  pub new_admin: Pubkey,
+}
+
+#[event]
+pub struct ConfigParamsUpdated {
+  pub admin: Pubkey,
+}
+
+#[event]
+pub struct ParamsQueued {
+  pub params: ConfigParams,
+  pub eta: i64,
+}
+
+#[event]
+pub struct ParamsExecuted {
+  pub params: ConfigParams,
+}
+
+#[event]
+pub struct ParamsCancelled {
+  pub params: ConfigParams,
+}
+
+#[event]
+pub struct CompetitionCreated {
+  pub competition: Pubkey,
+  pub start: i64,
+  pub end: i64,
+  pub prize_total: u64,
+}
+
+#[event]
+pub struct CompetitionEntrySubmitted {
+  pub competition: Pubkey,
+  pub user: Pubkey,
+  pub realized_pnl: i64,
+}
+
+#[event]
+pub struct CompetitionFinalized {
+  pub competition: Pubkey,
+  pub winners: u8,
+}
+
+#[event]
+pub struct CompetitionPrizeClaimed {
+  pub competition: Pubkey,
+  pub user: Pubkey,
+  pub rank: u8,
+  pub amount: u64,
+}
+
+#[event]
+pub struct LpRewardsInitialized {
+  pub market: Pubkey,
+  pub reward_mint: Pubkey,
+  pub rate: u64,
+  pub start: i64,
+  pub end: i64,
+}
+
+#[event]
+pub struct LpRewardsClaimed {
+  pub market: Pubkey,
+  pub provider: Pubkey,
+  pub amount: u64,
+}
+
+#[event]
+pub struct MarketMakersSet {
+  pub market: Pubkey,
+  pub makers: Vec<Pubkey>,
+  pub rebate_bps: u16,
+}
+
+#[event]
+pub struct MmRebateClaimed {
+  pub market: Pubkey,
+  pub maker: Pubkey,
+  pub amount: u64,
+}
+
+#[event]
+pub struct FeeHolidaySet {
+  pub market: Pubkey,
+  pub start: i64,
+  pub end: i64,
+}
+
+#[event]
+pub struct DelegatedBuyExecuted {
+  pub market: Pubkey,
+  pub user: Pubkey,
+  pub delegate: Pubkey,
+  pub collateral_in: u64,
+  pub shares_out: u64,
+}
+
+#[event]
+pub struct SwapExecuted {
+  pub market: Pubkey,
+  pub source_mint: Pubkey,
+  pub destination_mint: Pubkey,
+  pub amount_in: u64,
+  pub amount_out: u64,
+}
+
+#[event]
+pub struct CompressedPositionsInitialized {
+  pub market: Pubkey,
+  pub merkle_tree: Pubkey,
+  pub max_depth: u32,
+  pub max_buffer_size: u32,
+}
+
+#[event]
+pub struct CompressedBuy {
+  pub market: Pubkey,
+  pub owner: Pubkey,
+  pub leaf_index: u64,
+  pub outcome: u8,
+  pub shares: u64,
+  pub leaf: [u8; 32],
+}
+
+#[event]
+pub struct CompressedClaimed {
+  pub market: Pubkey,
+  pub owner: Pubkey,
+  pub leaf_index: u64,
+  pub payout: u64,
+}
+
+#[event]
+pub struct PayoutRootCommitted {
+  pub market: Pubkey,
+  pub root: [u8; 32],
+  pub total_payout: u64,
+}
+
+#[event]
+pub struct DistributorClaimed {
+  pub market: Pubkey,
+  pub owner: Pubkey,
+  pub index: u64,
+  pub payout: u64,
+}
+
+#[event]
+pub struct PayoutsDistributed {
+  pub market: Pubkey,
+  pub positions: u32,
+  pub total_payout: u64,
+}
+
+#[event]
+pub struct GroupClaimed {
+  pub group: Pubkey,
+  pub owner: Pubkey,
+  pub markets: u32,
+  pub payout: u64,
+}
+
+#[event]
+pub struct SessionCreated {
+  pub owner: Pubkey,
+  pub session_key: Pubkey,
+  pub expiry: i64,
+  pub spend_cap: u64,
+}
+
+#[event]
+pub struct SessionRevoked {
+  pub owner: Pubkey,
+  pub session_key: Pubkey,
+}
+
+#[event]
+pub struct MaxPositionSet {
+  pub market: Pubkey,
+  pub max_shares: u64,
+}
+
+#[event]
+pub struct AllowlistGateSet {
+  pub market: Pubkey,
+  pub enabled: bool,
+}
+
+#[event]
+pub struct TokenGateSet {
+  pub market: Pubkey,
+  pub mint: Pubkey,
+  pub min_balance: u64,
+}
+
+#[event]
+pub struct PermitGateSet {
+  pub market: Pubkey,
+  pub operator: Pubkey,
+}
+
+#[event]
+pub struct AllowlistUpdated {
+  pub market: Pubkey,
+  pub wallet: Pubkey,
+  pub allowed: bool,
+}
+
+#[event]
+pub struct ResolverBountyPaid {
+  pub market: Pubkey,
+  pub resolver: Pubkey,
+  pub amount: u64,
+}
+
+#[event]
+pub struct ResolutionCorrected {
+  pub market: Pubkey,
+  pub corrected_by: Pubkey,
+  pub previous_outcome: i8,
+  pub winning_outcome: i8,
+}
+
+#[event]
+pub struct PositionConverted {
+  pub group: Pubkey,
+  pub owner: Pubkey,
+  pub market: Pubkey, // member whose NO was converted
+  pub amount: u64,
+}
+
+#[event]
+pub struct GroupResolved {
+  pub group: Pubkey,
+  pub winner: Pubkey,
+}
+
+#[event]
+pub struct GroupDistributionResolved {
+  pub group: Pubkey,
+  pub payouts: Vec<u64>,
+}
+
+#[event]
+pub struct GroupRankingResolved {
+  pub group: Pubkey,
+  pub ranking: Vec<u8>,
+}
+
+#[event]
+pub struct BookOrderPlaced {
+  pub market: Pubkey,
+  pub owner: Pubkey,
+  pub order_id: u64,
+  pub side: u8,
+  pub price: u64,
+  pub shares: u64,
+}
+
+#[event]
+pub struct BookOrderCancelled {
+  pub market: Pubkey,
+  pub owner: Pubkey,
+  pub order_id: u64,
+  pub side: u8,
+}
+
+#[event]
+pub struct OrderRouted {
+  pub market: Pubkey,
+  pub taker: Pubkey,
+  pub side: u8,
+  pub book_amount: u64, // shares bought / collateral received from the book
+  pub amm_amount: u64,  // shares bought / collateral received from the AMM
+}
+
+#[event]
+pub struct RfqSettled {
+  pub market: Pubkey,
+  pub maker: Pubkey,
+  pub taker: Pubkey,
+  pub maker_side: u8,
+  pub outcome_index: u8,
+  pub shares: u64,
+  pub price: u64,
+  pub nonce: u64,
+}
+
+#[event]
+pub struct OfferCreated {
+  pub offer: Pubkey,
+  pub maker: Pubkey,
+  pub market: Pubkey,
+  pub outcome_index: u8,
+  pub shares: u64,
+  pub ask_market: Pubkey,
+  pub ask_outcome: u8,
+  pub ask_amount: u64,
+}
+
+#[event]
+pub struct OfferFilled {
+  pub offer: Pubkey,
+  pub maker: Pubkey,
+  pub taker: Pubkey,
+}
+
+#[event]
+pub struct OfferCancelled {
+  pub offer: Pubkey,
+  pub maker: Pubkey,
+}
+
+#[event]
+pub struct WinningsRolled {
+  pub source_market: Pubkey,
+  pub target_market: Pubkey,
+  pub owner: Pubkey,
+  pub payout: u64,
+  pub outcome_index: u8,
+  pub shares_out: u64,
+}
+
+#[event]
+pub struct PortfolioClaimed {
+  pub owner: Pubkey,
+  pub markets: u32,
+}
+
+#[event]
+pub struct SurplusSkimmed {
+  pub market: Pubkey,
+  pub recipient: Pubkey,
+  pub amount: u64,
+}
+
+#[event]
+pub struct ResidualLiquidityWithdrawn {
+  pub market: Pubkey,
+  pub provider: Pubkey,
+  pub lp_shares: u64,
+  pub amount: u64,
+}
+
+#[event]
+pub struct MarketReported {
+  pub market: Pubkey,
+  pub reporter: Pubkey,
+  pub reason: u8,
+  pub bond: u64,
+}
+
+#[event]
+pub struct ReportResolved {
+  pub market: Pubkey,
+  pub reporter: Pubkey,
+  pub action: u8,
+}
+
+#[event]
+pub struct MetadataUpdated {
+  pub market: Pubkey,
+  pub metadata_uri: String,
+  pub category: u8,
+  pub tags: u64,
+  pub image_uri: String,
+  pub rules_uri: String,
+}
+
+#[event]
+pub struct EndTimeUpdated {
+  pub market: Pubkey,
+  pub old_end_time: i64,
+  pub new_end_time: i64,
+  pub total_extension: i64,
+}
+
+#[event]
+pub struct TradingClosedEarly {
+  pub market: Pubkey,
+  pub scheduled_end_time: i64,
+  pub closed_at: i64,
+}
+
+#[event]
+pub struct MarketMigrated {
+  pub market: Pubkey,
+  pub market_seq: u64,
+  pub question_hash: [u8; 32],
+  pub question: String,
+  pub metadata_uri: String,
+}
+
+// ----------------------------
+// Accounts
+// ----------------------------
+
+#[derive(Accounts)]
+pub struct InitializeConfig<'info> {
+  #[account(
+    init,
+    payer = admin,
+    space = 8 + ProtocolConfig::INIT_SPACE,
+    seeds = [b"config"],
+    bump
+  )]
+  pub config: Account<'info, ProtocolConfig>,
+
+  #[account(mut)]
+  pub admin: Signer<'info>,
+
+  #[account(constraint = program.programdata_address()? == Some(program_data.key()))]
+  pub program: Program<'info, crate::program::PredictionProgramV2>,
+
+  #[account(constraint = program_data.upgrade_authority_address == Some(admin.key()) @ PredictionError::Unauthorized)]
+  pub program_data: Account<'info, ProgramData>,
+
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct QueueParams<'info> {
+  #[account(
+    seeds = [b"config"],
+    bump = config.bump,
+    has_one = admin @ PredictionError::Unauthorized,
+  )]
+  pub config: Account<'info, ProtocolConfig>,
+
+  #[account(
+    init,
+    payer = admin,
+    space = 8 + PendingParams::INIT_SPACE,
+    seeds = [b"pending_params"],
+    bump
+  )]
+  pub pending_params: Account<'info, PendingParams>,
+
+  #[account(mut)]
+  pub admin: Signer<'info>,
+
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteParams<'info> {
+  #[account(mut, seeds = [b"config"], bump = config.bump)]
+  pub config: Account<'info, ProtocolConfig>,
+
+  #[account(
+    mut,
+    seeds = [b"pending_params"],
+    bump = pending_params.bump,
+    close = proposer
+  )]
+  pub pending_params: Account<'info, PendingParams>,
+
+  /// CHECK: receives the queue rent; must match `pending_params.proposer`
+  #[account(mut, address = pending_params.proposer)]
+  pub proposer: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CancelParams<'info> {
+  #[account(
+    seeds = [b"config"],
+    bump = config.bump,
+    has_one = admin @ PredictionError::Unauthorized,
+  )]
+  pub config: Account<'info, ProtocolConfig>,
+
+  #[account(
+    mut,
+    seeds = [b"pending_params"],
+    bump = pending_params.bump,
+    close = proposer
+  )]
+  pub pending_params: Account<'info, PendingParams>,
+
+  /// CHECK: receives the queue rent; must match `pending_params.proposer`
+  #[account(mut, address = pending_params.proposer)]
+  pub proposer: UncheckedAccount<'info>,
+
+  pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptAdmin<'info> {
+  #[account(
+    mut,
+    seeds = [b"config"],
+    bump = config.bump,
+    constraint = config.pending_admin == pending_admin.key() @ PredictionError::Unauthorized,
+  )]
+  pub config: Account<'info, ProtocolConfig>,
+
+  pub pending_admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+  #[account(
+    mut,
+    seeds = [b"config"],
+    bump = config.bump,
+    has_one = admin @ PredictionError::Unauthorized,
+  )]
+  pub config: Account<'info, ProtocolConfig>,
+
+  pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(args: CreateMarketCpmmArgs)]
+pub struct CreateMarketCpmm<'info> {
+  #[account(mut, seeds = [b"config"], bump = config.bump)]
+  pub config: Account<'info, ProtocolConfig>,
+
+  #[account(
+    init,
+    payer = authority,
+    space = 8 + std::mem::size_of::<MarketV3>(),
+    seeds = [b"market_v2", authority.key().as_ref(), &args.market_id.to_le_bytes()],
+    bump
+  )]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(
+    init,
+    payer = authority,
+    seeds = [b"vault_v2", market.key().as_ref()],
+    bump,
+    token::mint = collateral_mint,
+    token::authority = vault_authority,
+    token::token_program = token_program,
+  )]
+  pub vault: InterfaceAccount<'info, TokenAccount>,
+
+  /// CHECK: PDA that signs for vault transfers
+  #[account(
+    seeds = [b"vault_auth_v2", market.key().as_ref()],
+    bump
+  )]
+  pub vault_authority: UncheckedAccount<'info>,
+
+  pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+  #[account(mut)]
+  pub authority: Signer<'info>,
+
+  #[account(
+    mut,
+    constraint = authority_collateral_ata.mint == collateral_mint.key(),
+    constraint = authority_collateral_ata.owner == authority.key(),
+  )]
+  pub authority_collateral_ata: InterfaceAccount<'info, TokenAccount>,
+
+  #[account(
+    init_if_needed,
+    payer = authority,
+    space = 8 + MarketRegistry::INIT_SPACE,
+    seeds = [b"registry"],
+    bump
+  )]
+  pub registry: Account<'info, MarketRegistry>,
+
+  #[account(
+    init_if_needed,
+    payer = authority,
+    space = 8 + RegistryPage::INIT_SPACE,
+    seeds = [b"registry_page".as_ref(), &(registry.market_count / REGISTRY_PAGE_SIZE).to_le_bytes()],
+    bump
+  )]
+  pub registry_page: Account<'info, RegistryPage>,
+
+  #[account(mut, seeds = [b"protocol_stats"], bump = protocol_stats.bump)]
+  pub protocol_stats: Option<Account<'info, ProtocolStats>>,
+
+  #[account(
+    init,
+    payer = authority,
+    space = 8 + CreatorBond::INIT_SPACE,
+    seeds = [b"creator_bond", market.key().as_ref()],
+    bump
+  )]
+  pub creator_bond: Account<'info, CreatorBond>,
+
+  #[account(
+    init,
+    payer = authority,
+    space = 8 + LpPosition::INIT_SPACE,
+    seeds = [b"lp_position", market.key().as_ref(), authority.key().as_ref()],
+    bump
+  )]
+  pub creator_lp_position: Account<'info, LpPosition>,
+
+  pub token_program: Interface<'info, TokenInterface>,
+  pub system_program: Program<'info, System>,
+  pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct RegisterMarket<'info> {
+  #[account(mut)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(
+    init_if_needed,
+    payer = payer,
+    space = 8 + MarketRegistry::INIT_SPACE,
+    seeds = [b"registry"],
+    bump
+  )]
+  pub registry: Account<'info, MarketRegistry>,
+
+  #[account(
+    init_if_needed,
+    payer = payer,
+    space = 8 + RegistryPage::INIT_SPACE,
+    seeds = [b"registry_page".as_ref(), &(registry.market_count / REGISTRY_PAGE_SIZE).to_le_bytes()],
+    bump
+  )]
+  pub registry_page: Account<'info, RegistryPage>,
+
+  #[account(mut)]
+  pub payer: Signer<'info>,
+
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CuratorMarket<'info> {
+  #[account(
+    seeds = [b"config"],
+    bump = config.bump,
+    has_one = curator @ PredictionError::Unauthorized,
+  )]
+  pub config: Account<'info, ProtocolConfig>,
+
+  #[account(mut)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  pub curator: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ReportMarket<'info> {
+  #[account(seeds = [b"config"], bump = config.bump)]
+  pub config: Account<'info, ProtocolConfig>,
+
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(
+    init,
+    payer = reporter,
+    space = 8 + Report::INIT_SPACE,
+    seeds = [b"report", market.key().as_ref(), reporter.key().as_ref()],
+    bump
+  )]
+  pub report: Account<'info, Report>,
+
+  #[account(mut)]
+  pub reporter: Signer<'info>,
+
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ResolveReport<'info> {
+  #[account(
+    seeds = [b"config"],
+    bump = config.bump,
+    has_one = curator @ PredictionError::Unauthorized,
+    has_one = treasury,
+  )]
+  pub config: Account<'info, ProtocolConfig>,
+
+  #[account(mut)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(
+    mut,
+    seeds = [b"report", market.key().as_ref(), reporter.key().as_ref()],
+    bump = report.bump,
+    has_one = market,
+  )]
+  pub report: Account<'info, Report>,
+
+  /// Only used when voiding; absent for markets created before creation bonds
+  #[account(
+    mut,
+    seeds = [b"creator_bond", market.key().as_ref()],
+    bump = creator_bond.bump,
+  )]
+  pub creator_bond: Option<Account<'info, CreatorBond>>,
+
+  #[account(
+    mut,
+    seeds = [b"registry_page".as_ref(), &(market.load()?.registry_index / REGISTRY_PAGE_SIZE).to_le_bytes()],
+    bump = registry_page.bump
+  )]
+  pub registry_page: Option<Account<'info, RegistryPage>>,
+
+  /// CHECK: bound to the report via its PDA seeds; receives refunds/rewards
+  #[account(mut)]
+  pub reporter: UncheckedAccount<'info>,
+
+  /// CHECK: receives a rejected report's bond; must match `config.treasury`
+  #[account(mut)]
+  pub treasury: UncheckedAccount<'info>,
+
+  pub curator: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateMarketMetadata<'info> {
+  #[account(mut)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateEndTime<'info> {
+  #[account(mut)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  /// Required once the market is registered (its entry mirrors `end_time`)
+  #[account(
+    mut,
+    seeds = [b"registry_page".as_ref(), &(market.load()?.registry_index / REGISTRY_PAGE_SIZE).to_le_bytes()],
+    bump = registry_page.bump
+  )]
+  pub registry_page: Option<Account<'info, RegistryPage>>,
+
+  pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseTradingEarly<'info> {
+  #[account(mut)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  /// Required once the market is registered (its entry mirrors `end_time`)
+  #[account(
+    mut,
+    seeds = [b"registry_page".as_ref(), &(market.load()?.registry_index / REGISTRY_PAGE_SIZE).to_le_bytes()],
+    bump = registry_page.bump
+  )]
+  pub registry_page: Option<Account<'info, RegistryPage>>,
+
+  /// Market authority or resolver
+  pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateMarketV2<'info> {
+  #[account(mut, seeds = [b"config"], bump = config.bump)]
+  pub config: Account<'info, ProtocolConfig>,
+
+  /// CHECK: legacy MarketV2 account; discriminator, authority and PDA are verified in the handler
+  #[account(mut, owner = crate::ID)]
+  pub market: UncheckedAccount<'info>,
+
+  #[account(
+    seeds = [b"vault_v2", market.key().as_ref()],
+    bump
+  )]
+  pub vault: InterfaceAccount<'info, TokenAccount>,
+
+  /// CHECK: PDA that signs for vault transfers
+  #[account(
+    seeds = [b"vault_auth_v2", market.key().as_ref()],
+    bump
+  )]
+  pub vault_authority: UncheckedAccount<'info>,
+
+  #[account(address = vault.mint)]
+  pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+  #[account(mut)]
+  pub authority: Signer<'info>,
+
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigratePositionV2<'info> {
+  /// CHECK: legacy PositionV2 account; layout and PDA are verified in the handler
+  #[account(mut, owner = crate::ID)]
+  pub position: UncheckedAccount<'info>,
+
+  #[account(mut)]
+  pub payer: Signer<'info>,
+
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct BuyShares<'info> {
+  #[account(seeds = [b"config"], bump = config.bump)]
+  pub config: Account<'info, ProtocolConfig>,
+
+  #[account(mut)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(
+    mut,
+    seeds = [b"vault_v2", market.key().as_ref()],
+    bump = market.load()?.vault_bump
+  )]
+  pub vault: InterfaceAccount<'info, TokenAccount>,
+
+  /// CHECK: PDA that signs for vault transfers
+  #[account(
+    seeds = [b"vault_auth_v2", market.key().as_ref()],
+    bump = market.load()?.vault_auth_bump
+  )]
+  pub vault_authority: UncheckedAccount<'info>,
+
+  #[account(
+    init_if_needed,
+    payer = payer,
+    space = 8 + PositionV2::INIT_SPACE,
+    seeds = [b"position_v2", market.key().as_ref(), user.key().as_ref()],
+    bump
+  )]
+  pub position: Account<'info, PositionV2>,
+
+  #[account(mut)]
+  pub user: Signer<'info>,
+
+  /// Pays rent for accounts created here (the user or a relayer)
+  #[account(mut)]
+  pub payer: Signer<'info>,
+
+  #[account(address = market.load()?.collateral_mint)]
+  pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+  /// Not needed for native SOL markets (lamports are wrapped into the vault directly)
+  #[account(
+    mut,
+    constraint = user_collateral_ata.mint == market.load()?.collateral_mint,
+    constraint = user_collateral_ata.owner == user.key(),
+  )]
+  pub user_collateral_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+
+  /// Designated makers; pass to accrue the trader's maker rebate
+  #[account(mut, seeds = [b"market_makers", market.key().as_ref()], bump = market_makers.bump)]
+  pub market_makers: Option<Account<'info, MarketMakers>>,
+
+  /// The trader's stats; pass to earn points
+  #[account(mut, seeds = [b"user_stats", user.key().as_ref()], bump = user_stats.bump)]
+  pub user_stats: Option<Account<'info, UserStats>>,
+
+  /// The trader's competition entry; pass to count the trade
+  #[account(
+    mut,
+    seeds = [b"competition_entry", competition_entry.competition.as_ref(), user.key().as_ref()],
+    bump = competition_entry.bump
+  )]
+  pub competition_entry: Option<Account<'info, CompetitionEntry>>,
+
+  /// Required once the market's stats account is initialized
+  #[account(mut, seeds = [b"market_stats", market.key().as_ref()], bump = market_stats.bump)]
+  pub market_stats: Option<Account<'info, MarketStats>>,
+
+  #[account(mut, seeds = [b"protocol_stats"], bump = protocol_stats.bump)]
+  pub protocol_stats: Option<Account<'info, ProtocolStats>>,
+
+  /// Required once the market's candles are initialized
+  #[account(mut, seeds = [b"price_candles", market.key().as_ref()], bump = price_candles.load()?.bump)]
+  pub price_candles: Option<AccountLoader<'info, PriceCandles>>,
+
+  /// Required once the market's trade tape is initialized
+  #[account(mut, seeds = [b"trade_tape", market.key().as_ref()], bump = trade_tape.load()?.bump)]
+  pub trade_tape: Option<AccountLoader<'info, TradeTape>>,
+
+  /// Required once the market's price feed is initialized
+  #[account(mut, seeds = [b"price_feed", market.key().as_ref()], bump = price_feed.load()?.bump)]
+  pub price_feed: Option<AccountLoader<'info, PriceFeed>>,
+
+  /// Required while the market is allowlist-gated
+  #[account(
+    seeds = [b"allowlist", market.key().as_ref(), user.key().as_ref()],
+    bump = allowlist_entry.bump
+  )]
+  pub allowlist_entry: Option<Account<'info, AllowlistEntry>>,
+
+  /// Both required while the market is permit-gated; the permit's ed25519
+  /// verification must precede this instruction
+  #[account(seeds = [b"permit_gate", market.key().as_ref()], bump = permit_gate.bump)]
+  pub permit_gate: Option<Account<'info, PermitGate>>,
+  /// CHECK: instructions sysvar
+  #[account(address = ix_sysvar::ID)]
+  pub instructions: Option<UncheckedAccount<'info>>,
+
+  /// Both required while the market is token-gated
+  #[account(seeds = [b"token_gate", market.key().as_ref()], bump = token_gate.bump)]
+  pub token_gate: Option<Account<'info, TokenGate>>,
+  pub gate_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+  pub token_program: Interface<'info, TokenInterface>,
+  pub system_program: Program<'info, System>,
+  pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct DelegatedBuy<'info> {
+  #[account(seeds = [b"config"], bump = config.bump)]
+  pub config: Account<'info, ProtocolConfig>,
+
+  #[account(mut)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(
+    mut,
+    seeds = [b"vault_v2", market.key().as_ref()],
+    bump = market.load()?.vault_bump
+  )]
+  pub vault: InterfaceAccount<'info, TokenAccount>,
+
+  #[account(
+    init_if_needed,
+    payer = payer,
+    space = 8 + PositionV2::INIT_SPACE,
+    seeds = [b"position_v2", market.key().as_ref(), user.key().as_ref()],
+    bump
+  )]
+  pub position: Account<'info, PositionV2>,
+
+  /// CHECK: owner of `user_collateral_ata`; receives the shares
+  pub user: UncheckedAccount<'info>,
+
+  /// Approved delegate of `user_collateral_ata` (typically a router PDA)
+  pub delegate: Signer<'info>,
+
+  /// Pays rent for a new position (the delegate or a relayer)
+  #[account(mut)]
+  pub payer: Signer<'info>,
+
+  #[account(address = market.load()?.collateral_mint)]
+  pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+  #[account(
+    mut,
+    constraint = user_collateral_ata.mint == market.load()?.collateral_mint,
+    constraint = user_collateral_ata.owner == user.key(),
+    constraint = user_collateral_ata.delegate.contains(&delegate.key()) @ PredictionError::NotDelegate,
+  )]
+  pub user_collateral_ata: InterfaceAccount<'info, TokenAccount>,
+
+  /// Required once the market's stats account is initialized
+  #[account(mut, seeds = [b"market_stats", market.key().as_ref()], bump = market_stats.bump)]
+  pub market_stats: Option<Account<'info, MarketStats>>,
+
+  #[account(mut, seeds = [b"protocol_stats"], bump = protocol_stats.bump)]
+  pub protocol_stats: Option<Account<'info, ProtocolStats>>,
+
+  /// Required once the market's candles are initialized
+  #[account(mut, seeds = [b"price_candles", market.key().as_ref()], bump = price_candles.load()?.bump)]
+  pub price_candles: Option<AccountLoader<'info, PriceCandles>>,
+
+  /// Required once the market's trade tape is initialized
+  #[account(mut, seeds = [b"trade_tape", market.key().as_ref()], bump = trade_tape.load()?.bump)]
+  pub trade_tape: Option<AccountLoader<'info, TradeTape>>,
+
+  /// Required once the market's price feed is initialized
+  #[account(mut, seeds = [b"price_feed", market.key().as_ref()], bump = price_feed.load()?.bump)]
+  pub price_feed: Option<AccountLoader<'info, PriceFeed>>,
+
+  /// Required while the market is allowlist-gated
+  #[account(
+    seeds = [b"allowlist", market.key().as_ref(), user.key().as_ref()],
+    bump = allowlist_entry.bump
+  )]
+  pub allowlist_entry: Option<Account<'info, AllowlistEntry>>,
+
+  /// Both required while the market is permit-gated; the permit's ed25519
+  /// verification must precede this instruction
+  #[account(seeds = [b"permit_gate", market.key().as_ref()], bump = permit_gate.bump)]
+  pub permit_gate: Option<Account<'info, PermitGate>>,
+  /// CHECK: instructions sysvar
+  #[account(address = ix_sysvar::ID)]
+  pub instructions: Option<UncheckedAccount<'info>>,
+
+  /// Both required while the market is token-gated
+  #[account(seeds = [b"token_gate", market.key().as_ref()], bump = token_gate.bump)]
+  pub token_gate: Option<Account<'info, TokenGate>>,
+  pub gate_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+  pub token_program: Interface<'info, TokenInterface>,
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AddLiquidity<'info> {
+  #[account(mut)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(
+    mut,
+    seeds = [b"vault_v2", market.key().as_ref()],
+    bump = market.load()?.vault_bump
+  )]
+  pub vault: InterfaceAccount<'info, TokenAccount>,
+
+  #[account(
+    init_if_needed,
+    payer = payer,
+    space = 8 + LpPosition::INIT_SPACE,
+    seeds = [b"lp_position", market.key().as_ref(), user.key().as_ref()],
+    bump
+  )]
+  pub lp_position: Account<'info, LpPosition>,
+
+  // Receives outcome shares the pool doesn't absorb
+  #[account(
+    init_if_needed,
+    payer = payer,
+    space = 8 + PositionV2::INIT_SPACE,
+    seeds = [b"position_v2", market.key().as_ref(), user.key().as_ref()],
+    bump
+  )]
+  pub position: Account<'info, PositionV2>,
+
+  #[account(mut)]
+  pub user: Signer<'info>,
+
+  /// Pays rent for accounts created here (the user or a relayer)
+  #[account(mut)]
+  pub payer: Signer<'info>,
+
+  #[account(address = market.load()?.collateral_mint)]
+  pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+  /// Not needed for native SOL markets (lamports are wrapped into the vault directly)
+  #[account(
+    mut,
+    constraint = user_collateral_ata.mint == market.load()?.collateral_mint,
+    constraint = user_collateral_ata.owner == user.key(),
+  )]
+  pub user_collateral_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+
+  /// Required once the market has liquidity mining
+  #[account(mut, seeds = [b"lp_rewards", market.key().as_ref()], bump = lp_rewards.bump)]
+  pub lp_rewards: Option<Account<'info, LpRewards>>,
+
+  pub token_program: Interface<'info, TokenInterface>,
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RemoveLiquidity<'info> {
+  #[account(mut)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(
+    mut,
+    seeds = [b"lp_position", market.key().as_ref(), user.key().as_ref()],
+    bump = lp_position.bump
+  )]
+  pub lp_position: Account<'info, LpPosition>,
+
+  /// Required once the market has liquidity mining
+  #[account(mut, seeds = [b"lp_rewards", market.key().as_ref()], bump = lp_rewards.bump)]
+  pub lp_rewards: Option<Account<'info, LpRewards>>,
+
+  #[account(
+    init_if_needed,
+    payer = payer,
+    space = 8 + PositionV2::INIT_SPACE,
+    seeds = [b"position_v2", market.key().as_ref(), user.key().as_ref()],
+    bump
+  )]
+  pub position: Account<'info, PositionV2>,
+
+  #[account(mut)]
+  pub user: Signer<'info>,
+
+  /// Pays rent for accounts created here (the user or a relayer)
+  #[account(mut)]
+  pub payer: Signer<'info>,
+
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawFees<'info> {
+  #[account(seeds = [b"config"], bump = config.bump)]
+  pub config: Account<'info, ProtocolConfig>,
+
+  #[account(mut)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(
+    mut,
+    seeds = [b"vault_v2", market.key().as_ref()],
+    bump = market.load()?.vault_bump
+  )]
+  pub vault: InterfaceAccount<'info, TokenAccount>,
+
+  /// CHECK: PDA that signs for vault transfers
+  #[account(
+    seeds = [b"vault_auth_v2", market.key().as_ref()],
+    bump = market.load()?.vault_auth_bump
+  )]
+  pub vault_authority: UncheckedAccount<'info>,
+
+  /// CHECK: market authority (creator fees) or config treasury (protocol fees), checked in the handler
+  #[account(mut)]
+  pub recipient: UncheckedAccount<'info>,
+
+  /// Pays for the temporary wSOL account on native SOL markets
+  #[account(mut)]
+  pub payer: Signer<'info>,
+
+  #[account(address = market.load()?.collateral_mint)]
+  pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+  /// Not needed for native SOL markets (payout is unwrapped via `wsol_unwrap`)
+  #[account(
+    mut,
+    constraint = recipient_collateral_ata.mint == market.load()?.collateral_mint,
+    constraint = recipient_collateral_ata.owner == recipient.key(),
+  )]
+  pub recipient_collateral_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+
+  /// Temporary wSOL account, created and closed within the instruction (native SOL markets only)
+  #[account(
+    init,
+    payer = payer,
+    seeds = [b"wsol_unwrap", market.key().as_ref(), recipient.key().as_ref()],
+    bump,
+    token::mint = collateral_mint,
+    token::authority = vault_authority,
+    token::token_program = token_program,
+  )]
+  pub wsol_unwrap: Option<InterfaceAccount<'info, TokenAccount>>,
+
+  pub token_program: Interface<'info, TokenInterface>,
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(args: CreateCompetitionArgs)]
+pub struct CreateCompetition<'info> {
+  #[account(
+    seeds = [b"config"],
+    bump = config.bump,
+    has_one = admin @ PredictionError::Unauthorized,
+  )]
+  pub config: Account<'info, ProtocolConfig>,
+
+  #[account(
+    init,
+    payer = admin,
+    space = 8 + Competition::INIT_SPACE,
+    seeds = [b"competition", args.id.to_le_bytes().as_ref()],
+    bump
+  )]
+  pub competition: Account<'info, Competition>,
+
+  #[account(
+    init,
+    payer = admin,
+    seeds = [b"competition_vault", competition.key().as_ref()],
+    bump,
+    token::mint = prize_mint,
+    token::authority = competition,
+    token::token_program = token_program,
+  )]
+  pub prize_vault: InterfaceAccount<'info, TokenAccount>,
+
+  pub prize_mint: InterfaceAccount<'info, Mint>,
+
+  #[account(
+    mut,
+    constraint = admin_prize_ata.mint == prize_mint.key(),
+    constraint = admin_prize_ata.owner == admin.key(),
+  )]
+  pub admin_prize_ata: InterfaceAccount<'info, TokenAccount>,
+
+  #[account(mut)]
+  pub admin: Signer<'info>,
+
+  pub token_program: Interface<'info, TokenInterface>,
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct JoinCompetition<'info> {
+  pub competition: Account<'info, Competition>,
+
+  #[account(
+    init,
+    payer = payer,
+    space = 8 + CompetitionEntry::INIT_SPACE,
+    seeds = [b"competition_entry", competition.key().as_ref(), user.key().as_ref()],
+    bump
+  )]
+  pub competition_entry: Account<'info, CompetitionEntry>,
+
+  #[account(mut)]
+  pub user: Signer<'info>,
+
+  /// Pays rent for accounts created here (the user or a relayer)
+  #[account(mut)]
+  pub payer: Signer<'info>,
+
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SubmitCompetitionEntry<'info> {
+  #[account(mut)]
+  pub competition: Account<'info, Competition>,
+
+  #[account(
+    mut,
+    seeds = [b"competition_entry", competition.key().as_ref(), competition_entry.user.as_ref()],
+    bump = competition_entry.bump
+  )]
+  pub competition_entry: Account<'info, CompetitionEntry>,
+}
+
+#[derive(Accounts)]
+pub struct FinalizeCompetition<'info> {
+  #[account(seeds = [b"config"], bump = config.bump)]
+  pub config: Account<'info, ProtocolConfig>,
+
+  #[account(mut)]
+  pub competition: Account<'info, Competition>,
+
+  #[account(mut, seeds = [b"competition_vault", competition.key().as_ref()], bump)]
+  pub prize_vault: InterfaceAccount<'info, TokenAccount>,
+
+  #[account(address = competition.prize_mint)]
+  pub prize_mint: InterfaceAccount<'info, Mint>,
+
+  #[account(
+    mut,
+    constraint = treasury_prize_ata.mint == prize_mint.key(),
+    constraint = treasury_prize_ata.owner == config.treasury @ PredictionError::Unauthorized,
+  )]
+  pub treasury_prize_ata: InterfaceAccount<'info, TokenAccount>,
+
+  pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimCompetitionPrize<'info> {
+  #[account(mut)]
+  pub competition: Account<'info, Competition>,
+
+  #[account(mut, seeds = [b"competition_vault", competition.key().as_ref()], bump)]
+  pub prize_vault: InterfaceAccount<'info, TokenAccount>,
+
+  #[account(address = competition.prize_mint)]
+  pub prize_mint: InterfaceAccount<'info, Mint>,
+
+  #[account(
+    mut,
+    constraint = user_prize_ata.mint == prize_mint.key(),
+    constraint = user_prize_ata.owner == user.key(),
+  )]
+  pub user_prize_ata: InterfaceAccount<'info, TokenAccount>,
+
+  pub user: Signer<'info>,
+
+  pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct InitMarketStats<'info> {
+  #[account(mut)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(
+    init,
+    payer = payer,
+    space = 8 + MarketStats::INIT_SPACE,
+    seeds = [b"market_stats", market.key().as_ref()],
+    bump
+  )]
+  pub market_stats: Account<'info, MarketStats>,
+
+  #[account(mut)]
+  pub payer: Signer<'info>,
+
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitPriceCandles<'info> {
+  #[account(mut)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(
+    init,
+    payer = authority,
+    space = 8 + std::mem::size_of::<PriceCandles>(),
+    seeds = [b"price_candles", market.key().as_ref()],
+    bump
+  )]
+  pub price_candles: AccountLoader<'info, PriceCandles>,
+
+  #[account(mut)]
+  pub authority: Signer<'info>,
+
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SnapshotPrice<'info> {
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(mut, seeds = [b"price_candles", market.key().as_ref()], bump = price_candles.load()?.bump)]
+  pub price_candles: AccountLoader<'info, PriceCandles>,
+}
+
+#[derive(Accounts)]
+pub struct InitPriceFeed<'info> {
+  #[account(mut)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(
+    init,
+    payer = payer,
+    space = 8 + std::mem::size_of::<PriceFeed>(),
+    seeds = [b"price_feed", market.key().as_ref()],
+    bump
+  )]
+  pub price_feed: AccountLoader<'info, PriceFeed>,
+
+  #[account(mut)]
+  pub payer: Signer<'info>,
+
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdatePriceFeed<'info> {
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(mut, seeds = [b"price_feed", market.key().as_ref()], bump = price_feed.load()?.bump)]
+  pub price_feed: AccountLoader<'info, PriceFeed>,
+}
+
+#[derive(Accounts)]
+pub struct InitTradeTape<'info> {
+  #[account(mut)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(
+    init,
+    payer = payer,
+    space = 8 + std::mem::size_of::<TradeTape>(),
+    seeds = [b"trade_tape", market.key().as_ref()],
+    bump
+  )]
+  pub trade_tape: AccountLoader<'info, TradeTape>,
+
+  #[account(mut)]
+  pub payer: Signer<'info>,
+
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitProtocolStats<'info> {
+  #[account(
+    seeds = [b"config"],
+    bump = config.bump,
+    has_one = admin @ PredictionError::Unauthorized,
+  )]
+  pub config: Account<'info, ProtocolConfig>,
+
+  #[account(
+    init,
+    payer = admin,
+    space = 8 + ProtocolStats::INIT_SPACE,
+    seeds = [b"protocol_stats"],
+    bump
+  )]
+  pub protocol_stats: Account<'info, ProtocolStats>,
+
+  #[account(mut)]
+  pub admin: Signer<'info>,
+
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SyncProtocolStats<'info> {
+  #[account(mut)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(mut, seeds = [b"protocol_stats"], bump = protocol_stats.bump)]
+  pub protocol_stats: Account<'info, ProtocolStats>,
+}
+
+#[derive(Accounts)]
+pub struct InitUserStats<'info> {
+  #[account(
+    init,
+    payer = payer,
+    space = 8 + UserStats::INIT_SPACE,
+    seeds = [b"user_stats", user.key().as_ref()],
+    bump
+  )]
+  pub user_stats: Account<'info, UserStats>,
+
+  #[account(mut)]
+  pub user: Signer<'info>,
+
+  /// Pays rent for accounts created here (the user or a relayer)
+  #[account(mut)]
+  pub payer: Signer<'info>,
+
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CheckpointPoints<'info> {
+  #[account(mut, seeds = [b"user_stats", user_stats.owner.as_ref()], bump = user_stats.bump)]
+  pub user_stats: Account<'info, UserStats>,
+}
+
+#[derive(Accounts)]
+pub struct RealizeSettledPnl<'info> {
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(mut, seeds = [b"user_stats", user_stats.owner.as_ref()], bump = user_stats.bump)]
+  pub user_stats: Account<'info, UserStats>,
+}
+
+#[derive(Accounts)]
+pub struct InitLpRewards<'info> {
+  #[account(mut)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(
+    init,
+    payer = authority,
+    space = 8 + LpRewards::INIT_SPACE,
+    seeds = [b"lp_rewards", market.key().as_ref()],
+    bump
+  )]
+  pub lp_rewards: Account<'info, LpRewards>,
+
+  #[account(
+    init,
+    payer = authority,
+    seeds = [b"lp_reward_vault", market.key().as_ref()],
+    bump,
+    token::mint = reward_mint,
+    token::authority = vault_authority,
+    token::token_program = token_program,
+  )]
+  pub reward_vault: InterfaceAccount<'info, TokenAccount>,
+
+  /// CHECK: PDA that signs for vault transfers
+  #[account(
+    seeds = [b"vault_auth_v2", market.key().as_ref()],
+    bump = market.load()?.vault_auth_bump
+  )]
+  pub vault_authority: UncheckedAccount<'info>,
+
+  pub reward_mint: InterfaceAccount<'info, Mint>,
+
+  #[account(
+    mut,
+    constraint = funder_reward_ata.mint == reward_mint.key(),
+    constraint = funder_reward_ata.owner == authority.key(),
+  )]
+  pub funder_reward_ata: InterfaceAccount<'info, TokenAccount>,
+
+  #[account(mut, address = market.load()?.authority @ PredictionError::Unauthorized)]
+  pub authority: Signer<'info>,
+
+  pub token_program: Interface<'info, TokenInterface>,
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimLpRewards<'info> {
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(mut, seeds = [b"lp_rewards", market.key().as_ref()], bump = lp_rewards.bump)]
+  pub lp_rewards: Account<'info, LpRewards>,
+
+  #[account(
+    mut,
+    seeds = [b"lp_position", market.key().as_ref(), user.key().as_ref()],
+    bump = lp_position.bump
+  )]
+  pub lp_position: Account<'info, LpPosition>,
+
+  #[account(mut, seeds = [b"lp_reward_vault", market.key().as_ref()], bump)]
+  pub reward_vault: InterfaceAccount<'info, TokenAccount>,
+
+  /// CHECK: PDA that signs for vault transfers
+  #[account(
+    seeds = [b"vault_auth_v2", market.key().as_ref()],
+    bump = market.load()?.vault_auth_bump
+  )]
+  pub vault_authority: UncheckedAccount<'info>,
+
+  #[account(address = lp_rewards.reward_mint)]
+  pub reward_mint: InterfaceAccount<'info, Mint>,
+
+  #[account(
+    mut,
+    constraint = user_reward_ata.mint == reward_mint.key(),
+    constraint = user_reward_ata.owner == user.key(),
+  )]
+  pub user_reward_ata: InterfaceAccount<'info, TokenAccount>,
+
+  pub user: Signer<'info>,
+
+  pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct SetMarketMakers<'info> {
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(
+    init_if_needed,
+    payer = authority,
+    space = 8 + MarketMakers::INIT_SPACE,
+    seeds = [b"market_makers", market.key().as_ref()],
+    bump
+  )]
+  pub market_makers: Account<'info, MarketMakers>,
+
+  #[account(mut, address = market.load()?.authority @ PredictionError::Unauthorized)]
+  pub authority: Signer<'info>,
+
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimMmRebate<'info> {
+  #[account(mut)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(mut, seeds = [b"market_makers", market.key().as_ref()], bump = market_makers.bump)]
+  pub market_makers: Account<'info, MarketMakers>,
+
+  #[account(
+    mut,
+    seeds = [b"vault_v2", market.key().as_ref()],
+    bump = market.load()?.vault_bump
+  )]
+  pub vault: InterfaceAccount<'info, TokenAccount>,
+
+  /// CHECK: PDA that signs for vault transfers
+  #[account(
+    seeds = [b"vault_auth_v2", market.key().as_ref()],
+    bump = market.load()?.vault_auth_bump
+  )]
+  pub vault_authority: UncheckedAccount<'info>,
+
+  #[account(mut)]
+  pub maker: Signer<'info>,
+
+  #[account(address = market.load()?.collateral_mint)]
+  pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+  /// Not needed for native SOL markets (payout is unwrapped via `wsol_unwrap`)
+  #[account(
+    mut,
+    constraint = maker_collateral_ata.mint == market.load()?.collateral_mint,
+    constraint = maker_collateral_ata.owner == maker.key(),
+  )]
+  pub maker_collateral_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+
+  /// Temporary wSOL account, created and closed within the instruction (native SOL markets only)
+  #[account(
+    init,
+    payer = maker,
+    seeds = [b"wsol_unwrap", market.key().as_ref(), maker.key().as_ref()],
+    bump,
+    token::mint = collateral_mint,
+    token::authority = vault_authority,
+    token::token_program = token_program,
+  )]
+  pub wsol_unwrap: Option<InterfaceAccount<'info, TokenAccount>>,
+
+  pub token_program: Interface<'info, TokenInterface>,
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SweepInsuranceFees<'info> {
+  #[account(mut)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(
+    mut,
+    seeds = [b"vault_v2", market.key().as_ref()],
+    bump = market.load()?.vault_bump
+  )]
+  pub vault: InterfaceAccount<'info, TokenAccount>,
+
+  /// CHECK: PDA that signs for vault transfers
+  #[account(
+    seeds = [b"vault_auth_v2", market.key().as_ref()],
+    bump = market.load()?.vault_auth_bump
+  )]
+  pub vault_authority: UncheckedAccount<'info>,
+
+  #[account(address = market.load()?.collateral_mint)]
+  pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+  /// CHECK: PDA that owns every insurance vault
+  #[account(seeds = [b"insurance_auth"], bump)]
+  pub insurance_authority: UncheckedAccount<'info>,
+
+  #[account(
+    init_if_needed,
+    payer = payer,
+    seeds = [b"insurance_vault", collateral_mint.key().as_ref()],
+    bump,
+    token::mint = collateral_mint,
+    token::authority = insurance_authority,
+    token::token_program = token_program,
+  )]
+  pub insurance_vault: InterfaceAccount<'info, TokenAccount>,
+
+  #[account(mut)]
+  pub payer: Signer<'info>,
+
+  pub token_program: Interface<'info, TokenInterface>,
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InsuranceTopUp<'info> {
+  #[account(
+    seeds = [b"config"],
+    bump = config.bump,
+    has_one = admin @ PredictionError::Unauthorized,
+  )]
+  pub config: Account<'info, ProtocolConfig>,
+
+  pub admin: Signer<'info>,
+
+  #[account(mut)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(
+    mut,
+    seeds = [b"vault_v2", market.key().as_ref()],
+    bump = market.load()?.vault_bump
+  )]
+  pub vault: InterfaceAccount<'info, TokenAccount>,
+
+  #[account(address = market.load()?.collateral_mint)]
+  pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+  /// CHECK: PDA that owns every insurance vault
+  #[account(seeds = [b"insurance_auth"], bump)]
+  pub insurance_authority: UncheckedAccount<'info>,
+
+  #[account(
+    mut,
+    seeds = [b"insurance_vault", collateral_mint.key().as_ref()],
+    bump,
+  )]
+  pub insurance_vault: InterfaceAccount<'info, TokenAccount>,
+
+  pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct DeployIdleCollateral<'info> {
+  #[account(
+    seeds = [b"config"],
+    bump = config.bump,
+    has_one = admin @ PredictionError::Unauthorized,
+  )]
+  pub config: Account<'info, ProtocolConfig>,
+
+  #[account(mut)]
+  pub admin: Signer<'info>,
+
+  #[account(mut)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(
+    mut,
+    seeds = [b"vault_v2", market.key().as_ref()],
+    bump = market.load()?.vault_bump
+  )]
+  pub vault: InterfaceAccount<'info, TokenAccount>,
+
+  /// CHECK: PDA that signs for vault transfers and the lending CPI
+  #[account(
+    seeds = [b"vault_auth_v2", market.key().as_ref()],
+    bump = market.load()?.vault_auth_bump
+  )]
+  pub vault_authority: UncheckedAccount<'info>,
+
+  #[account(address = market.load()?.collateral_mint)]
+  pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+  #[account(
+    init_if_needed,
+    payer = admin,
+    space = 8 + YieldPosition::INIT_SPACE,
+    seeds = [b"yield_position", market.key().as_ref()],
+    bump,
+  )]
+  pub yield_position: Account<'info, YieldPosition>,
+
+  /// Holds the reserve's collateral tokens for the vault
+  #[account(
+    init_if_needed,
+    payer = admin,
+    seeds = [b"yield_receipt", market.key().as_ref()],
+    bump,
+    token::mint = reserve_collateral_mint,
+    token::authority = vault_authority,
+    token::token_program = receipt_token_program,
+  )]
+  pub yield_receipt: InterfaceAccount<'info, TokenAccount>,
+
+  /// CHECK: must be the configured lending program
+  #[account(
+    executable,
+    constraint = config.yield_program != Pubkey::default() @ PredictionError::YieldNotEnabled,
+    address = config.yield_program,
+  )]
+  pub lending_program: UncheckedAccount<'info>,
+
+  /// CHECK: reserve owned by the lending program, which validates the rest
+  /// of the reserve accounts against it
+  #[account(mut, owner = lending_program.key() @ PredictionError::YieldReserveMismatch)]
+  pub reserve: UncheckedAccount<'info>,
+
+  /// CHECK: validated by the lending program against `reserve`
+  pub lending_market: UncheckedAccount<'info>,
+
+  /// CHECK: validated by the lending program against `lending_market`
+  pub lending_market_authority: UncheckedAccount<'info>,
+
+  /// CHECK: validated by the lending program against `reserve`
+  #[account(mut)]
+  pub reserve_liquidity_supply: UncheckedAccount<'info>,
+
+  #[account(mut)]
+  pub reserve_collateral_mint: InterfaceAccount<'info, Mint>,
+
+  pub token_program: Interface<'info, TokenInterface>,
+  /// Token program of the reserve's collateral tokens
+  pub receipt_token_program: Interface<'info, TokenInterface>,
+
+  /// CHECK: instructions sysvar (Kamino Lend checks the refresh instructions)
+  #[account(address = ix_sysvar::ID)]
+  pub instructions: UncheckedAccount<'info>,
+
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawIdleCollateral<'info> {
+  #[account(
+    seeds = [b"config"],
+    bump = config.bump,
+    has_one = admin @ PredictionError::Unauthorized,
+  )]
+  pub config: Account<'info, ProtocolConfig>,
+
+  pub admin: Signer<'info>,
+
+  #[account(mut)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(
+    mut,
+    seeds = [b"vault_v2", market.key().as_ref()],
+    bump = market.load()?.vault_bump
+  )]
+  pub vault: InterfaceAccount<'info, TokenAccount>,
+
+  /// CHECK: PDA that signs for vault transfers and the lending CPI
+  #[account(
+    seeds = [b"vault_auth_v2", market.key().as_ref()],
+    bump = market.load()?.vault_auth_bump
+  )]
+  pub vault_authority: UncheckedAccount<'info>,
+
+  #[account(address = market.load()?.collateral_mint)]
+  pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+  #[account(
+    mut,
+    seeds = [b"yield_position", market.key().as_ref()],
+    bump = yield_position.bump,
+  )]
+  pub yield_position: Account<'info, YieldPosition>,
+
+  #[account(mut, address = yield_position.receipt)]
+  pub yield_receipt: InterfaceAccount<'info, TokenAccount>,
+
+  /// CHECK: the program the collateral was deployed to
+  #[account(executable, address = yield_position.lending_program)]
+  pub lending_program: UncheckedAccount<'info>,
+
+  /// CHECK: the reserve the collateral was deployed to
+  #[account(mut, address = yield_position.reserve)]
+  pub reserve: UncheckedAccount<'info>,
+
+  /// CHECK: validated by the lending program against `reserve`
+  pub lending_market: UncheckedAccount<'info>,
+
+  /// CHECK: validated by the lending program against `lending_market`
+  pub lending_market_authority: UncheckedAccount<'info>,
+
+  /// CHECK: validated by the lending program against `reserve`
+  #[account(mut)]
+  pub reserve_liquidity_supply: UncheckedAccount<'info>,
+
+  #[account(mut, address = yield_receipt.mint)]
+  pub reserve_collateral_mint: InterfaceAccount<'info, Mint>,
+
+  pub token_program: Interface<'info, TokenInterface>,
+  /// Token program of the reserve's collateral tokens
+  pub receipt_token_program: Interface<'info, TokenInterface>,
+
+  /// CHECK: instructions sysvar (Kamino Lend checks the refresh instructions)
+  #[account(address = ix_sysvar::ID)]
+  pub instructions: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetLstStakePool<'info> {
+  #[account(mut)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  /// CHECK: SPL stake pool; owner and pool mint checked in the handler
+  pub stake_pool: UncheckedAccount<'info>,
+
+  pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RecordLstRate<'info> {
+  pub market: AccountLoader<'info, MarketV3>,
+
+  /// CHECK: the market's configured stake pool
+  #[account(
+    constraint = market.load()?.lst_stake_pool != Pubkey::default() @ PredictionError::MissingStakePool,
+    address = market.load()?.lst_stake_pool,
+  )]
+  pub stake_pool: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct TradingAccountDeposit<'info> {
+  #[account(
+    init_if_needed,
+    payer = payer,
+    space = 8 + TradingAccount::INIT_SPACE,
+    seeds = [b"trading_account", user.key().as_ref(), collateral_mint.key().as_ref()],
+    bump
+  )]
+  pub trading_account: Account<'info, TradingAccount>,
+
+  /// CHECK: PDA that owns every trading custody account
+  #[account(seeds = [b"trading_custody_auth"], bump)]
+  pub custody_authority: UncheckedAccount<'info>,
+
+  #[account(
+    init_if_needed,
+    payer = payer,
+    seeds = [b"trading_custody", collateral_mint.key().as_ref()],
+    bump,
+    token::mint = collateral_mint,
+    token::authority = custody_authority,
+    token::token_program = token_program,
+  )]
+  pub custody: InterfaceAccount<'info, TokenAccount>,
+
+  #[account(mut)]
+  pub user: Signer<'info>,
+
+  /// Pays rent for accounts created here (the user or a relayer)
+  #[account(mut)]
+  pub payer: Signer<'info>,
+
+  pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+  /// Not needed for native SOL (lamports are wrapped into custody directly)
+  #[account(
+    mut,
+    constraint = user_collateral_ata.mint == collateral_mint.key(),
+    constraint = user_collateral_ata.owner == user.key(),
+  )]
+  pub user_collateral_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+
+  pub token_program: Interface<'info, TokenInterface>,
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct TradingAccountWithdraw<'info> {
+  #[account(
+    mut,
+    seeds = [b"trading_account", user.key().as_ref(), collateral_mint.key().as_ref()],
+    bump = trading_account.bump
+  )]
+  pub trading_account: Account<'info, TradingAccount>,
+
+  /// CHECK: PDA that owns every trading custody account
+  #[account(seeds = [b"trading_custody_auth"], bump)]
+  pub custody_authority: UncheckedAccount<'info>,
+
+  #[account(
+    mut,
+    seeds = [b"trading_custody", collateral_mint.key().as_ref()],
+    bump
+  )]
+  pub custody: InterfaceAccount<'info, TokenAccount>,
+
+  #[account(mut)]
+  pub user: Signer<'info>,
+
+  /// Pays rent for accounts created here (the user or a relayer)
+  #[account(mut)]
+  pub payer: Signer<'info>,
+
+  pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+  /// Not needed for native SOL (payout is unwrapped via `wsol_unwrap`)
+  #[account(
+    mut,
+    constraint = user_collateral_ata.mint == collateral_mint.key(),
+    constraint = user_collateral_ata.owner == user.key(),
+  )]
+  pub user_collateral_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+
+  /// Temporary wSOL account, created and closed within the instruction (native SOL only)
+  #[account(
+    init,
+    payer = payer,
+    seeds = [b"wsol_unwrap", custody.key().as_ref(), user.key().as_ref()],
+    bump,
+    token::mint = collateral_mint,
+    token::authority = custody_authority,
+    token::token_program = token_program,
+  )]
+  pub wsol_unwrap: Option<InterfaceAccount<'info, TokenAccount>>,
+
+  pub token_program: Interface<'info, TokenInterface>,
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct TradeFromAccount<'info> {
+  #[account(seeds = [b"config"], bump = config.bump)]
+  pub config: Account<'info, ProtocolConfig>,
+
+  #[account(mut)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(
+    mut,
+    seeds = [b"vault_v2", market.key().as_ref()],
+    bump = market.load()?.vault_bump
+  )]
+  pub vault: InterfaceAccount<'info, TokenAccount>,
+
+  /// CHECK: PDA that signs for vault transfers
+  #[account(
+    seeds = [b"vault_auth_v2", market.key().as_ref()],
+    bump = market.load()?.vault_auth_bump
+  )]
+  pub vault_authority: UncheckedAccount<'info>,
+
+  #[account(
+    init_if_needed,
+    payer = payer,
+    space = 8 + PositionV2::INIT_SPACE,
+    seeds = [b"position_v2", market.key().as_ref(), user.key().as_ref()],
+    bump
+  )]
+  pub position: Account<'info, PositionV2>,
+
+  #[account(
+    mut,
+    seeds = [b"trading_account", user.key().as_ref(), collateral_mint.key().as_ref()],
+    bump = trading_account.bump
+  )]
+  pub trading_account: Account<'info, TradingAccount>,
+
+  /// CHECK: PDA that owns every trading custody account
+  #[account(seeds = [b"trading_custody_auth"], bump)]
+  pub custody_authority: UncheckedAccount<'info>,
+
+  #[account(
+    mut,
+    seeds = [b"trading_custody", collateral_mint.key().as_ref()],
+    bump
+  )]
+  pub custody: InterfaceAccount<'info, TokenAccount>,
+
+  #[account(mut)]
+  pub user: Signer<'info>,
+
+  /// Pays rent for accounts created here (the user or a relayer)
+  #[account(mut)]
+  pub payer: Signer<'info>,
+
+  #[account(address = market.load()?.collateral_mint)]
+  pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+  /// Designated makers; pass to accrue the trader's maker rebate
+  #[account(mut, seeds = [b"market_makers", market.key().as_ref()], bump = market_makers.bump)]
+  pub market_makers: Option<Account<'info, MarketMakers>>,
+
+  /// The trader's stats; pass to earn points
+  #[account(mut, seeds = [b"user_stats", user.key().as_ref()], bump = user_stats.bump)]
+  pub user_stats: Option<Account<'info, UserStats>>,
+
+  /// The trader's competition entry; pass to count the trade
+  #[account(
+    mut,
+    seeds = [b"competition_entry", competition_entry.competition.as_ref(), user.key().as_ref()],
+    bump = competition_entry.bump
+  )]
+  pub competition_entry: Option<Account<'info, CompetitionEntry>>,
+
+  /// Required once the market's stats account is initialized
+  #[account(mut, seeds = [b"market_stats", market.key().as_ref()], bump = market_stats.bump)]
+  pub market_stats: Option<Account<'info, MarketStats>>,
+
+  #[account(mut, seeds = [b"protocol_stats"], bump = protocol_stats.bump)]
+  pub protocol_stats: Option<Account<'info, ProtocolStats>>,
+
+  /// Required once the market's candles are initialized
+  #[account(mut, seeds = [b"price_candles", market.key().as_ref()], bump = price_candles.load()?.bump)]
+  pub price_candles: Option<AccountLoader<'info, PriceCandles>>,
+
+  /// Required once the market's trade tape is initialized
+  #[account(mut, seeds = [b"trade_tape", market.key().as_ref()], bump = trade_tape.load()?.bump)]
+  pub trade_tape: Option<AccountLoader<'info, TradeTape>>,
+
+  /// Required once the market's price feed is initialized
+  #[account(mut, seeds = [b"price_feed", market.key().as_ref()], bump = price_feed.load()?.bump)]
+  pub price_feed: Option<AccountLoader<'info, PriceFeed>>,
+
+  /// Required while the market is allowlist-gated
+  #[account(
+    seeds = [b"allowlist", market.key().as_ref(), user.key().as_ref()],
+    bump = allowlist_entry.bump
+  )]
+  pub allowlist_entry: Option<Account<'info, AllowlistEntry>>,
+
+  /// Both required while the market is permit-gated; the permit's ed25519
+  /// verification must precede this instruction
+  #[account(seeds = [b"permit_gate", market.key().as_ref()], bump = permit_gate.bump)]
+  pub permit_gate: Option<Account<'info, PermitGate>>,
+  /// CHECK: instructions sysvar
+  #[account(address = ix_sysvar::ID)]
+  pub instructions: Option<UncheckedAccount<'info>>,
+
+  /// Both required while the market is token-gated
+  #[account(seeds = [b"token_gate", market.key().as_ref()], bump = token_gate.bump)]
+  pub token_gate: Option<Account<'info, TokenGate>>,
+  pub gate_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+  pub token_program: Interface<'info, TokenInterface>,
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(session_key: Pubkey)]
+pub struct CreateSession<'info> {
+  #[account(
+    init,
+    payer = payer,
+    space = 8 + SessionKey::INIT_SPACE,
+    seeds = [b"session", owner.key().as_ref(), session_key.as_ref()],
+    bump
+  )]
+  pub session: Account<'info, SessionKey>,
+
+  #[account(mut)]
+  pub owner: Signer<'info>,
+
+  /// Pays rent for accounts created here (the owner or a relayer)
+  #[account(mut)]
+  pub payer: Signer<'info>,
+
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RevokeSession<'info> {
+  #[account(
+    mut,
+    close = owner,
+    seeds = [b"session", owner.key().as_ref(), session.session_key.as_ref()],
+    bump = session.bump
+  )]
+  pub session: Account<'info, SessionKey>,
+
+  #[account(mut)]
+  pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SessionTrade<'info> {
+  #[account(seeds = [b"config"], bump = config.bump)]
+  pub config: Account<'info, ProtocolConfig>,
+
+  #[account(mut)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(
+    mut,
+    seeds = [b"vault_v2", market.key().as_ref()],
+    bump = market.load()?.vault_bump
+  )]
+  pub vault: InterfaceAccount<'info, TokenAccount>,
+
+  /// CHECK: PDA that signs for vault transfers
+  #[account(
+    seeds = [b"vault_auth_v2", market.key().as_ref()],
+    bump = market.load()?.vault_auth_bump
+  )]
+  pub vault_authority: UncheckedAccount<'info>,
+
+  #[account(
+    init_if_needed,
+    payer = payer,
+    space = 8 + PositionV2::INIT_SPACE,
+    seeds = [b"position_v2", market.key().as_ref(), owner.key().as_ref()],
+    bump
+  )]
+  pub position: Account<'info, PositionV2>,
+
+  #[account(
+    mut,
+    seeds = [b"trading_account", owner.key().as_ref(), collateral_mint.key().as_ref()],
+    bump = trading_account.bump
+  )]
+  pub trading_account: Account<'info, TradingAccount>,
+
+  #[account(
+    mut,
+    seeds = [b"session", owner.key().as_ref(), session_key.key().as_ref()],
+    bump = session.bump
+  )]
+  pub session: Account<'info, SessionKey>,
+
+  /// CHECK: owner of the session, position and trading account
+  pub owner: UncheckedAccount<'info>,
+
+  pub session_key: Signer<'info>,
+
+  /// Pays rent for accounts created here (the session key or a relayer)
+  #[account(mut)]
+  pub payer: Signer<'info>,
+
+  /// CHECK: PDA that owns every trading custody account
+  #[account(seeds = [b"trading_custody_auth"], bump)]
+  pub custody_authority: UncheckedAccount<'info>,
+
+  #[account(
+    mut,
+    seeds = [b"trading_custody", collateral_mint.key().as_ref()],
+    bump
+  )]
+  pub custody: InterfaceAccount<'info, TokenAccount>,
+
+  #[account(address = market.load()?.collateral_mint)]
+  pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+  /// Designated makers; pass to accrue the trader's maker rebate
+  #[account(mut, seeds = [b"market_makers", market.key().as_ref()], bump = market_makers.bump)]
+  pub market_makers: Option<Account<'info, MarketMakers>>,
+
+  /// The trader's stats; pass to earn points
+  #[account(mut, seeds = [b"user_stats", owner.key().as_ref()], bump = user_stats.bump)]
+  pub user_stats: Option<Account<'info, UserStats>>,
+
+  /// The trader's competition entry; pass to count the trade
+  #[account(
+    mut,
+    seeds = [b"competition_entry", competition_entry.competition.as_ref(), owner.key().as_ref()],
+    bump = competition_entry.bump
+  )]
+  pub competition_entry: Option<Account<'info, CompetitionEntry>>,
+
+  /// Required once the market's stats account is initialized
+  #[account(mut, seeds = [b"market_stats", market.key().as_ref()], bump = market_stats.bump)]
+  pub market_stats: Option<Account<'info, MarketStats>>,
+
+  #[account(mut, seeds = [b"protocol_stats"], bump = protocol_stats.bump)]
+  pub protocol_stats: Option<Account<'info, ProtocolStats>>,
+
+  /// Required once the market's candles are initialized
+  #[account(mut, seeds = [b"price_candles", market.key().as_ref()], bump = price_candles.load()?.bump)]
+  pub price_candles: Option<AccountLoader<'info, PriceCandles>>,
+
+  /// Required once the market's trade tape is initialized
+  #[account(mut, seeds = [b"trade_tape", market.key().as_ref()], bump = trade_tape.load()?.bump)]
+  pub trade_tape: Option<AccountLoader<'info, TradeTape>>,
+
+  /// Required once the market's price feed is initialized
+  #[account(mut, seeds = [b"price_feed", market.key().as_ref()], bump = price_feed.load()?.bump)]
+  pub price_feed: Option<AccountLoader<'info, PriceFeed>>,
+
+  /// Required while the market is allowlist-gated
+  #[account(
+    seeds = [b"allowlist", market.key().as_ref(), owner.key().as_ref()],
+    bump = allowlist_entry.bump
+  )]
+  pub allowlist_entry: Option<Account<'info, AllowlistEntry>>,
+
+  /// Both required while the market is permit-gated; the permit's ed25519
+  /// verification must precede this instruction
+  #[account(seeds = [b"permit_gate", market.key().as_ref()], bump = permit_gate.bump)]
+  pub permit_gate: Option<Account<'info, PermitGate>>,
+  /// CHECK: instructions sysvar
+  #[account(address = ix_sysvar::ID)]
+  pub instructions: Option<UncheckedAccount<'info>>,
+
+  /// Both required while the market is token-gated
+  #[account(seeds = [b"token_gate", market.key().as_ref()], bump = token_gate.bump)]
+  pub token_gate: Option<Account<'info, TokenGate>>,
+  pub gate_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+  pub token_program: Interface<'info, TokenInterface>,
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimAndBuy<'info> {
+  #[account(seeds = [b"config"], bump = config.bump)]
+  pub config: Account<'info, ProtocolConfig>,
+
+  #[account(mut)]
+  pub source_market: AccountLoader<'info, MarketV3>,
+
+  #[account(
+    mut,
+    seeds = [b"vault_v2", source_market.key().as_ref()],
+    bump = source_market.load()?.vault_bump
+  )]
+  pub source_vault: InterfaceAccount<'info, TokenAccount>,
+
+  /// CHECK: PDA that signs for source vault transfers
+  #[account(
+    seeds = [b"vault_auth_v2", source_market.key().as_ref()],
+    bump = source_market.load()?.vault_auth_bump
+  )]
+  pub source_vault_authority: UncheckedAccount<'info>,
+
+  #[account(
+    mut,
+    seeds = [b"position_v2", source_market.key().as_ref(), user.key().as_ref()],
+    bump = source_position.bump
+  )]
+  pub source_position: Account<'info, PositionV2>,
+
+  #[account(
+    mut,
+    constraint = target_market.load()?.collateral_mint == source_market.load()?.collateral_mint
+      @ PredictionError::InvalidTargetMarket
+  )]
+  pub target_market: AccountLoader<'info, MarketV3>,
+
+  #[account(
+    mut,
+    seeds = [b"vault_v2", target_market.key().as_ref()],
+    bump = target_market.load()?.vault_bump
+  )]
+  pub target_vault: InterfaceAccount<'info, TokenAccount>,
+
+  #[account(
+    init_if_needed,
+    payer = payer,
+    space = 8 + PositionV2::INIT_SPACE,
+    seeds = [b"position_v2", target_market.key().as_ref(), user.key().as_ref()],
+    bump
+  )]
+  pub target_position: Account<'info, PositionV2>,
+
+  #[account(mut)]
+  pub user: Signer<'info>,
+
+  /// Pays rent for accounts created here (the user or a relayer)
+  #[account(mut)]
+  pub payer: Signer<'info>,
+
+  #[account(address = source_market.load()?.collateral_mint)]
+  pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+  /// Designated makers; pass to accrue the trader's maker rebate
+  #[account(mut, seeds = [b"market_makers", target_market.key().as_ref()], bump = market_makers.bump)]
+  pub market_makers: Option<Account<'info, MarketMakers>>,
+
+  /// The trader's stats; pass to earn points
+  #[account(mut, seeds = [b"user_stats", user.key().as_ref()], bump = user_stats.bump)]
+  pub user_stats: Option<Account<'info, UserStats>>,
+
+  /// The trader's competition entry; pass to count the trade
+  #[account(
+    mut,
+    seeds = [b"competition_entry", competition_entry.competition.as_ref(), user.key().as_ref()],
+    bump = competition_entry.bump
+  )]
+  pub competition_entry: Option<Account<'info, CompetitionEntry>>,
+
+  /// Required once the market's stats account is initialized
+  #[account(mut, seeds = [b"market_stats", target_market.key().as_ref()], bump = market_stats.bump)]
+  pub market_stats: Option<Account<'info, MarketStats>>,
+
+  #[account(mut, seeds = [b"protocol_stats"], bump = protocol_stats.bump)]
+  pub protocol_stats: Option<Account<'info, ProtocolStats>>,
+
+  /// Required once the market's candles are initialized
+  #[account(mut, seeds = [b"price_candles", target_market.key().as_ref()], bump = price_candles.load()?.bump)]
+  pub price_candles: Option<AccountLoader<'info, PriceCandles>>,
+
+  /// Required once the market's trade tape is initialized
+  #[account(mut, seeds = [b"trade_tape", target_market.key().as_ref()], bump = trade_tape.load()?.bump)]
+  pub trade_tape: Option<AccountLoader<'info, TradeTape>>,
+
+  /// Required once the market's price feed is initialized
+  #[account(mut, seeds = [b"price_feed", target_market.key().as_ref()], bump = price_feed.load()?.bump)]
+  pub price_feed: Option<AccountLoader<'info, PriceFeed>>,
+
+  /// Required while the market is allowlist-gated
+  #[account(
+    seeds = [b"allowlist", target_market.key().as_ref(), user.key().as_ref()],
+    bump = allowlist_entry.bump
+  )]
+  pub allowlist_entry: Option<Account<'info, AllowlistEntry>>,
+
+  /// Both required while the market is permit-gated; the permit's ed25519
+  /// verification must precede this instruction
+  #[account(seeds = [b"permit_gate", target_market.key().as_ref()], bump = permit_gate.bump)]
+  pub permit_gate: Option<Account<'info, PermitGate>>,
+  /// CHECK: instructions sysvar
+  #[account(address = ix_sysvar::ID)]
+  pub instructions: Option<UncheckedAccount<'info>>,
+
+  /// Both required while the market is token-gated
+  #[account(seeds = [b"token_gate", target_market.key().as_ref()], bump = token_gate.bump)]
+  pub token_gate: Option<Account<'info, TokenGate>>,
+  pub gate_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+  pub token_program: Interface<'info, TokenInterface>,
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(args: PlaceLimitOrderArgs)]
+pub struct PlaceLimitOrder<'info> {
+  #[account(
+    init,
+    payer = payer,
+    space = 8 + LimitOrder::INIT_SPACE,
+    seeds = [b"limit_order", market.key().as_ref(), owner.key().as_ref(), &args.order_id.to_le_bytes()],
+    bump
+  )]
+  pub order: Account<'info, LimitOrder>,
+
+  /// Holds the order's collateral (buy) or proceeds (sell)
+  #[account(
+    init,
+    payer = payer,
+    seeds = [b"order_escrow", order.key().as_ref()],
+    bump,
+    token::mint = collateral_mint,
+    token::authority = vault_authority,
+    token::token_program = token_program,
+  )]
+  pub escrow: InterfaceAccount<'info, TokenAccount>,
+
+  #[account(mut)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  /// CHECK: PDA that signs for vault and escrow transfers
+  #[account(
+    seeds = [b"vault_auth_v2", market.key().as_ref()],
+    bump = market.load()?.vault_auth_bump
+  )]
+  pub vault_authority: UncheckedAccount<'info>,
+
+  #[account(
+    init_if_needed,
+    payer = payer,
+    space = 8 + PositionV2::INIT_SPACE,
+    seeds = [b"position_v2", market.key().as_ref(), owner.key().as_ref()],
+    bump
+  )]
+  pub position: Account<'info, PositionV2>,
+
+  #[account(mut)]
+  pub owner: Signer<'info>,
+
+  /// Pays rent for accounts created here (the owner or a relayer)
+  #[account(mut)]
+  pub payer: Signer<'info>,
+
+  #[account(address = market.load()?.collateral_mint)]
+  pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+  /// Not needed for native SOL markets or sell orders
+  #[account(
+    mut,
+    constraint = owner_collateral_ata.mint == collateral_mint.key(),
+    constraint = owner_collateral_ata.owner == owner.key(),
+  )]
+  pub owner_collateral_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+
+  pub token_program: Interface<'info, TokenInterface>,
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct FillLimitOrder<'info> {
+  #[account(seeds = [b"config"], bump = config.bump)]
+  pub config: Account<'info, ProtocolConfig>,
+
+  #[account(mut, has_one = market)]
+  pub order: Account<'info, LimitOrder>,
+
+  #[account(
+    mut,
+    seeds = [b"order_escrow", order.key().as_ref()],
+    bump
+  )]
+  pub escrow: InterfaceAccount<'info, TokenAccount>,
+
+  #[account(mut)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(
+    mut,
+    seeds = [b"vault_v2", market.key().as_ref()],
+    bump = market.load()?.vault_bump
+  )]
+  pub vault: InterfaceAccount<'info, TokenAccount>,
+
+  /// CHECK: PDA that signs for vault and escrow transfers
+  #[account(
+    seeds = [b"vault_auth_v2", market.key().as_ref()],
+    bump = market.load()?.vault_auth_bump
+  )]
+  pub vault_authority: UncheckedAccount<'info>,
+
+  #[account(
+    mut,
+    seeds = [b"position_v2", market.key().as_ref(), order.owner.as_ref()],
+    bump = position.bump
+  )]
+  pub position: Account<'info, PositionV2>,
+
+  /// CHECK: only for oracle-conditional orders; owner and layout checked when read
+  #[account(address = order.oracle_account)]
+  pub oracle: Option<UncheckedAccount<'info>>,
+
+  #[account(mut)]
+  pub keeper: Signer<'info>,
+
+  #[account(address = market.load()?.collateral_mint)]
+  pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+  /// Designated makers; pass to accrue the trader's maker rebate
+  #[account(mut, seeds = [b"market_makers", market.key().as_ref()], bump = market_makers.bump)]
+  pub market_makers: Option<Account<'info, MarketMakers>>,
+
+  /// The trader's stats; pass to earn points
+  #[account(mut, seeds = [b"user_stats", order.owner.as_ref()], bump = user_stats.bump)]
+  pub user_stats: Option<Account<'info, UserStats>>,
+
+  /// The trader's competition entry; pass to count the trade
+  #[account(
+    mut,
+    seeds = [b"competition_entry", competition_entry.competition.as_ref(), order.owner.as_ref()],
+    bump = competition_entry.bump
+  )]
+  pub competition_entry: Option<Account<'info, CompetitionEntry>>,
+
+  /// Required once the market's stats account is initialized
+  #[account(mut, seeds = [b"market_stats", market.key().as_ref()], bump = market_stats.bump)]
+  pub market_stats: Option<Account<'info, MarketStats>>,
+
+  #[account(mut, seeds = [b"protocol_stats"], bump = protocol_stats.bump)]
+  pub protocol_stats: Option<Account<'info, ProtocolStats>>,
+
+  /// Required once the market's candles are initialized
+  #[account(mut, seeds = [b"price_candles", market.key().as_ref()], bump = price_candles.load()?.bump)]
+  pub price_candles: Option<AccountLoader<'info, PriceCandles>>,
+
+  /// Required once the market's trade tape is initialized
+  #[account(mut, seeds = [b"trade_tape", market.key().as_ref()], bump = trade_tape.load()?.bump)]
+  pub trade_tape: Option<AccountLoader<'info, TradeTape>>,
+
+  /// Required once the market's price feed is initialized
+  #[account(mut, seeds = [b"price_feed", market.key().as_ref()], bump = price_feed.load()?.bump)]
+  pub price_feed: Option<AccountLoader<'info, PriceFeed>>,
+
+  /// Required while the market is allowlist-gated
+  #[account(
+    seeds = [b"allowlist", market.key().as_ref(), order.owner.as_ref()],
+    bump = allowlist_entry.bump
+  )]
+  pub allowlist_entry: Option<Account<'info, AllowlistEntry>>,
+
+  /// Both required while the market is permit-gated; the permit's ed25519
+  /// verification must precede this instruction
+  #[account(seeds = [b"permit_gate", market.key().as_ref()], bump = permit_gate.bump)]
+  pub permit_gate: Option<Account<'info, PermitGate>>,
+  /// CHECK: instructions sysvar
+  #[account(address = ix_sysvar::ID)]
+  pub instructions: Option<UncheckedAccount<'info>>,
+
+  /// Both required while the market is token-gated
+  #[account(seeds = [b"token_gate", market.key().as_ref()], bump = token_gate.bump)]
+  pub token_gate: Option<Account<'info, TokenGate>>,
+  pub gate_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+  pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct CloseLimitOrder<'info> {
+  #[account(mut, close = owner, has_one = owner, has_one = market)]
+  pub order: Account<'info, LimitOrder>,
+
+  #[account(
+    mut,
+    seeds = [b"order_escrow", order.key().as_ref()],
+    bump
+  )]
+  pub escrow: InterfaceAccount<'info, TokenAccount>,
+
+  pub market: AccountLoader<'info, MarketV3>,
+
+  /// CHECK: PDA that signs for escrow transfers
+  #[account(
+    seeds = [b"vault_auth_v2", market.key().as_ref()],
+    bump = market.load()?.vault_auth_bump
+  )]
+  pub vault_authority: UncheckedAccount<'info>,
+
+  #[account(
+    mut,
+    seeds = [b"position_v2", market.key().as_ref(), owner.key().as_ref()],
+    bump = position.bump
+  )]
+  pub position: Account<'info, PositionV2>,
+
+  #[account(mut)]
+  pub owner: Signer<'info>,
+
+  /// Pays rent for accounts created here (the owner or a relayer)
+  #[account(mut)]
+  pub payer: Signer<'info>,
+
+  #[account(address = market.load()?.collateral_mint)]
+  pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+  /// Not needed for native SOL markets (payout is unwrapped via `wsol_unwrap`)
+  #[account(
+    mut,
+    constraint = owner_collateral_ata.mint == collateral_mint.key(),
+    constraint = owner_collateral_ata.owner == owner.key(),
+  )]
+  pub owner_collateral_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+
+  /// Temporary wSOL account, created and closed within the instruction (native SOL markets only)
+  #[account(
+    init,
+    payer = payer,
+    seeds = [b"wsol_unwrap", escrow.key().as_ref(), owner.key().as_ref()],
+    bump,
+    token::mint = collateral_mint,
+    token::authority = vault_authority,
+    token::token_program = token_program,
+  )]
+  pub wsol_unwrap: Option<InterfaceAccount<'info, TokenAccount>>,
+
+  pub token_program: Interface<'info, TokenInterface>,
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(args: PlaceTwapOrderArgs)]
+pub struct PlaceTwapOrder<'info> {
+  #[account(
+    init,
+    payer = payer,
+    space = 8 + TwapOrder::INIT_SPACE,
+    seeds = [b"twap_order", market.key().as_ref(), owner.key().as_ref(), &args.order_id.to_le_bytes()],
+    bump
+  )]
+  pub order: Account<'info, TwapOrder>,
+
+  /// Holds the unexecuted collateral (buy) or accumulated proceeds (sell)
+  #[account(
+    init,
+    payer = payer,
+    seeds = [b"twap_escrow", order.key().as_ref()],
+    bump,
+    token::mint = collateral_mint,
+    token::authority = vault_authority,
+    token::token_program = token_program,
+  )]
+  pub escrow: InterfaceAccount<'info, TokenAccount>,
+
+  #[account(mut)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  /// CHECK: PDA that signs for vault and escrow transfers
+  #[account(
+    seeds = [b"vault_auth_v2", market.key().as_ref()],
+    bump = market.load()?.vault_auth_bump
+  )]
+  pub vault_authority: UncheckedAccount<'info>,
+
+  #[account(
+    init_if_needed,
+    payer = payer,
+    space = 8 + PositionV2::INIT_SPACE,
+    seeds = [b"position_v2", market.key().as_ref(), owner.key().as_ref()],
+    bump
+  )]
+  pub position: Account<'info, PositionV2>,
+
+  #[account(mut)]
+  pub owner: Signer<'info>,
+
+  /// Pays rent for accounts created here (the owner or a relayer)
+  #[account(mut)]
+  pub payer: Signer<'info>,
+
+  #[account(address = market.load()?.collateral_mint)]
+  pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+  /// Not needed for native SOL markets or sell orders
+  #[account(
+    mut,
+    constraint = owner_collateral_ata.mint == collateral_mint.key(),
+    constraint = owner_collateral_ata.owner == owner.key(),
+  )]
+  pub owner_collateral_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+
+  pub token_program: Interface<'info, TokenInterface>,
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteTwapSlice<'info> {
+  #[account(seeds = [b"config"], bump = config.bump)]
+  pub config: Account<'info, ProtocolConfig>,
+
+  #[account(mut, has_one = market)]
+  pub order: Account<'info, TwapOrder>,
+
+  #[account(
+    mut,
+    seeds = [b"twap_escrow", order.key().as_ref()],
+    bump
+  )]
+  pub escrow: InterfaceAccount<'info, TokenAccount>,
+
+  #[account(mut)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(
+    mut,
+    seeds = [b"vault_v2", market.key().as_ref()],
+    bump = market.load()?.vault_bump
+  )]
+  pub vault: InterfaceAccount<'info, TokenAccount>,
+
+  /// CHECK: PDA that signs for vault and escrow transfers
+  #[account(
+    seeds = [b"vault_auth_v2", market.key().as_ref()],
+    bump = market.load()?.vault_auth_bump
+  )]
+  pub vault_authority: UncheckedAccount<'info>,
+
+  #[account(
+    mut,
+    seeds = [b"position_v2", market.key().as_ref(), order.owner.as_ref()],
+    bump = position.bump
+  )]
+  pub position: Account<'info, PositionV2>,
+
+  #[account(mut)]
+  pub keeper: Signer<'info>,
+
+  #[account(address = market.load()?.collateral_mint)]
+  pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+  /// Designated makers; pass to accrue the trader's maker rebate
+  #[account(mut, seeds = [b"market_makers", market.key().as_ref()], bump = market_makers.bump)]
+  pub market_makers: Option<Account<'info, MarketMakers>>,
+
+  /// The trader's stats; pass to earn points
+  #[account(mut, seeds = [b"user_stats", order.owner.as_ref()], bump = user_stats.bump)]
+  pub user_stats: Option<Account<'info, UserStats>>,
+
+  /// The trader's competition entry; pass to count the trade
+  #[account(
+    mut,
+    seeds = [b"competition_entry", competition_entry.competition.as_ref(), order.owner.as_ref()],
+    bump = competition_entry.bump
+  )]
+  pub competition_entry: Option<Account<'info, CompetitionEntry>>,
+
+  /// Required once the market's stats account is initialized
+  #[account(mut, seeds = [b"market_stats", market.key().as_ref()], bump = market_stats.bump)]
+  pub market_stats: Option<Account<'info, MarketStats>>,
+
+  #[account(mut, seeds = [b"protocol_stats"], bump = protocol_stats.bump)]
+  pub protocol_stats: Option<Account<'info, ProtocolStats>>,
+
+  /// Required once the market's candles are initialized
+  #[account(mut, seeds = [b"price_candles", market.key().as_ref()], bump = price_candles.load()?.bump)]
+  pub price_candles: Option<AccountLoader<'info, PriceCandles>>,
+
+  /// Required once the market's trade tape is initialized
+  #[account(mut, seeds = [b"trade_tape", market.key().as_ref()], bump = trade_tape.load()?.bump)]
+  pub trade_tape: Option<AccountLoader<'info, TradeTape>>,
+
+  /// Required once the market's price feed is initialized
+  #[account(mut, seeds = [b"price_feed", market.key().as_ref()], bump = price_feed.load()?.bump)]
+  pub price_feed: Option<AccountLoader<'info, PriceFeed>>,
+
+  /// Required while the market is allowlist-gated
+  #[account(
+    seeds = [b"allowlist", market.key().as_ref(), order.owner.as_ref()],
+    bump = allowlist_entry.bump
+  )]
+  pub allowlist_entry: Option<Account<'info, AllowlistEntry>>,
+
+  /// Both required while the market is permit-gated; the permit's ed25519
+  /// verification must precede this instruction
+  #[account(seeds = [b"permit_gate", market.key().as_ref()], bump = permit_gate.bump)]
+  pub permit_gate: Option<Account<'info, PermitGate>>,
+  /// CHECK: instructions sysvar
+  #[account(address = ix_sysvar::ID)]
+  pub instructions: Option<UncheckedAccount<'info>>,
+
+  /// Both required while the market is token-gated
+  #[account(seeds = [b"token_gate", market.key().as_ref()], bump = token_gate.bump)]
+  pub token_gate: Option<Account<'info, TokenGate>>,
+  pub gate_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+  pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct CloseTwapOrder<'info> {
+  #[account(mut, close = owner, has_one = owner, has_one = market)]
+  pub order: Account<'info, TwapOrder>,
+
+  #[account(
+    mut,
+    seeds = [b"twap_escrow", order.key().as_ref()],
+    bump
+  )]
+  pub escrow: InterfaceAccount<'info, TokenAccount>,
+
+  pub market: AccountLoader<'info, MarketV3>,
+
+  /// CHECK: PDA that signs for escrow transfers
+  #[account(
+    seeds = [b"vault_auth_v2", market.key().as_ref()],
+    bump = market.load()?.vault_auth_bump
+  )]
+  pub vault_authority: UncheckedAccount<'info>,
+
+  #[account(
+    mut,
+    seeds = [b"position_v2", market.key().as_ref(), owner.key().as_ref()],
+    bump = position.bump
+  )]
+  pub position: Account<'info, PositionV2>,
+
+  #[account(mut)]
+  pub owner: Signer<'info>,
+
+  /// Pays rent for accounts created here (the owner or a relayer)
+  #[account(mut)]
+  pub payer: Signer<'info>,
+
+  #[account(address = market.load()?.collateral_mint)]
+  pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+  /// Not needed for native SOL markets (payout is unwrapped via `wsol_unwrap`)
+  #[account(
+    mut,
+    constraint = owner_collateral_ata.mint == collateral_mint.key(),
+    constraint = owner_collateral_ata.owner == owner.key(),
+  )]
+  pub owner_collateral_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+
+  /// Temporary wSOL account, created and closed within the instruction (native SOL markets only)
+  #[account(
+    init,
+    payer = payer,
+    seeds = [b"wsol_unwrap", escrow.key().as_ref(), owner.key().as_ref()],
+    bump,
+    token::mint = collateral_mint,
+    token::authority = vault_authority,
+    token::token_program = token_program,
+  )]
+  pub wsol_unwrap: Option<InterfaceAccount<'info, TokenAccount>>,
+
+  pub token_program: Interface<'info, TokenInterface>,
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(outcome: u8)]
+pub struct InitOutcomeMint<'info> {
+  pub market: AccountLoader<'info, MarketV3>,
+
+  /// CHECK: PDA that mints outcome tokens
+  #[account(
+    seeds = [b"vault_auth_v2", market.key().as_ref()],
+    bump = market.load()?.vault_auth_bump
+  )]
+  pub vault_authority: UncheckedAccount<'info>,
+
+  #[account(
+    init,
+    payer = payer,
+    seeds = [b"outcome_mint", market.key().as_ref(), &[outcome]],
+    bump,
+    mint::decimals = collateral_mint.decimals,
+    mint::authority = vault_authority,
+    mint::token_program = token_program,
+  )]
+  pub outcome_mint: InterfaceAccount<'info, Mint>,
+
+  // Outcome tokens live under the collateral's token program
+  #[account(
+    address = market.load()?.collateral_mint,
+    constraint = *collateral_mint.to_account_info().owner == token_program.key(),
+  )]
+  pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+  #[account(mut)]
+  pub payer: Signer<'info>,
+
+  pub token_program: Interface<'info, TokenInterface>,
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(outcome: u8)]
+pub struct TokenizeShares<'info> {
+  pub market: AccountLoader<'info, MarketV3>,
+
+  /// CHECK: PDA that mints outcome tokens
+  #[account(
+    seeds = [b"vault_auth_v2", market.key().as_ref()],
+    bump = market.load()?.vault_auth_bump
+  )]
+  pub vault_authority: UncheckedAccount<'info>,
+
+  #[account(
+    mut,
+    seeds = [b"outcome_mint", market.key().as_ref(), &[outcome]],
+    bump
+  )]
+  pub outcome_mint: InterfaceAccount<'info, Mint>,
+
+  #[account(
+    mut,
+    seeds = [b"position_v2", market.key().as_ref(), user.key().as_ref()],
+    bump = position.bump
+  )]
+  pub position: Account<'info, PositionV2>,
+
+  #[account(mut)]
+  pub user: Signer<'info>,
+
+  /// Pays rent for accounts created here (the user or a relayer)
+  #[account(mut)]
+  pub payer: Signer<'info>,
+
+  #[account(
+    init_if_needed,
+    payer = payer,
+    associated_token::mint = outcome_mint,
+    associated_token::authority = user,
+    associated_token::token_program = token_program,
+  )]
+  pub user_outcome_ata: InterfaceAccount<'info, TokenAccount>,
+
+  pub token_program: Interface<'info, TokenInterface>,
+  pub associated_token_program: Program<'info, AssociatedToken>,
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(outcome: u8)]
+pub struct RedeemOutcomeTokens<'info> {
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(
+    mut,
+    seeds = [b"outcome_mint", market.key().as_ref(), &[outcome]],
+    bump
+  )]
+  pub outcome_mint: InterfaceAccount<'info, Mint>,
+
+  #[account(
+    init_if_needed,
+    payer = payer,
+    space = 8 + PositionV2::INIT_SPACE,
+    seeds = [b"position_v2", market.key().as_ref(), user.key().as_ref()],
+    bump
+  )]
+  pub position: Account<'info, PositionV2>,
+
+  #[account(mut)]
+  pub user: Signer<'info>,
+
+  /// Pays rent for accounts created here (the user or a relayer)
+  #[account(mut)]
+  pub payer: Signer<'info>,
+
+  #[account(
+    mut,
+    constraint = user_outcome_ata.mint == outcome_mint.key(),
+    constraint = user_outcome_ata.owner == user.key(),
+  )]
+  pub user_outcome_ata: InterfaceAccount<'info, TokenAccount>,
+
+  pub token_program: Interface<'info, TokenInterface>,
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(outcome: u8)]
+pub struct ClaimOutcomeTokens<'info> {
+  #[account(mut)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(
+    mut,
+    seeds = [b"vault_v2", market.key().as_ref()],
+    bump = market.load()?.vault_bump
+  )]
+  pub vault: InterfaceAccount<'info, TokenAccount>,
+
+  /// CHECK: PDA that signs for vault transfers
+  #[account(
+    seeds = [b"vault_auth_v2", market.key().as_ref()],
+    bump = market.load()?.vault_auth_bump
+  )]
+  pub vault_authority: UncheckedAccount<'info>,
+
+  #[account(
+    mut,
+    seeds = [b"outcome_mint", market.key().as_ref(), &[outcome]],
+    bump
+  )]
+  pub outcome_mint: InterfaceAccount<'info, Mint>,
+
+  #[account(mut)]
+  pub user: Signer<'info>,
+
+  /// Pays rent for accounts created here (the user or a relayer)
+  #[account(mut)]
+  pub payer: Signer<'info>,
+
+  #[account(
+    mut,
+    constraint = user_outcome_ata.mint == outcome_mint.key(),
+    constraint = user_outcome_ata.owner == user.key(),
+  )]
+  pub user_outcome_ata: InterfaceAccount<'info, TokenAccount>,
+
+  #[account(address = market.load()?.collateral_mint)]
+  pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+  /// Not needed for native SOL markets (payout is unwrapped via `wsol_unwrap`)
+  #[account(
+    mut,
+    constraint = user_collateral_ata.mint == collateral_mint.key(),
+    constraint = user_collateral_ata.owner == user.key(),
+  )]
+  pub user_collateral_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+
+  /// Temporary wSOL account, created and closed within the instruction (native SOL markets only)
+  #[account(
+    init,
+    payer = payer,
+    seeds = [b"wsol_unwrap", outcome_mint.key().as_ref(), user.key().as_ref()],
+    bump,
+    token::mint = collateral_mint,
+    token::authority = vault_authority,
+    token::token_program = token_program,
+  )]
+  pub wsol_unwrap: Option<InterfaceAccount<'info, TokenAccount>>,
+
+  pub token_program: Interface<'info, TokenInterface>,
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Swap<'info> {
+  #[account(seeds = [b"config"], bump = config.bump)]
+  pub config: Account<'info, ProtocolConfig>,
+
+  #[account(mut)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(
+    mut,
+    seeds = [b"vault_v2", market.key().as_ref()],
+    bump = market.load()?.vault_bump
+  )]
+  pub vault: InterfaceAccount<'info, TokenAccount>,
+
+  /// CHECK: PDA that signs for vault transfers and mints outcome tokens
+  #[account(
+    seeds = [b"vault_auth_v2", market.key().as_ref()],
+    bump = market.load()?.vault_auth_bump
+  )]
+  pub vault_authority: UncheckedAccount<'info>,
+
+  pub user_transfer_authority: Signer<'info>,
+
+  /// Collateral or outcome mint; the other side is `destination_mint`
+  #[account(mut)]
+  pub source_mint: InterfaceAccount<'info, Mint>,
+
+  #[account(mut)]
+  pub destination_mint: InterfaceAccount<'info, Mint>,
+
+  #[account(mut, constraint = user_source_token_account.mint == source_mint.key())]
+  pub user_source_token_account: InterfaceAccount<'info, TokenAccount>,
+
+  #[account(mut, constraint = user_destination_token_account.mint == destination_mint.key())]
+  pub user_destination_token_account: InterfaceAccount<'info, TokenAccount>,
+
+  /// Required once the market's stats account is initialized
+  #[account(mut, seeds = [b"market_stats", market.key().as_ref()], bump = market_stats.bump)]
+  pub market_stats: Option<Account<'info, MarketStats>>,
+
+  #[account(mut, seeds = [b"protocol_stats"], bump = protocol_stats.bump)]
+  pub protocol_stats: Option<Account<'info, ProtocolStats>>,
+
+  /// Required once the market's candles are initialized
+  #[account(mut, seeds = [b"price_candles", market.key().as_ref()], bump = price_candles.load()?.bump)]
+  pub price_candles: Option<AccountLoader<'info, PriceCandles>>,
+
+  /// Required once the market's trade tape is initialized
+  #[account(mut, seeds = [b"trade_tape", market.key().as_ref()], bump = trade_tape.load()?.bump)]
+  pub trade_tape: Option<AccountLoader<'info, TradeTape>>,
+
+  /// Required once the market's price feed is initialized
+  #[account(mut, seeds = [b"price_feed", market.key().as_ref()], bump = price_feed.load()?.bump)]
+  pub price_feed: Option<AccountLoader<'info, PriceFeed>>,
+
+  // Outcome mints live under the collateral's token program
+  pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct InitCompressedPositions<'info> {
+  #[account(seeds = [b"config"], bump = config.bump)]
+  pub config: Account<'info, ProtocolConfig>,
+
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(
+    init,
+    payer = authority,
+    space = 8 + CompressedPositions::INIT_SPACE,
+    seeds = [b"compressed_positions", market.key().as_ref()],
+    bump
+  )]
+  pub compressed_positions: Account<'info, CompressedPositions>,
+
+  /// CHECK: pre-allocated tree, initialized by spl-account-compression
+  #[account(mut, owner = ACCOUNT_COMPRESSION_PROGRAM_ID)]
+  pub merkle_tree: UncheckedAccount<'info>,
+
+  /// Market authority or config admin
+  #[account(mut)]
+  pub authority: Signer<'info>,
+
+  /// CHECK: spl-account-compression
+  #[account(address = ACCOUNT_COMPRESSION_PROGRAM_ID)]
+  pub compression_program: UncheckedAccount<'info>,
+
+  /// CHECK: spl-noop, logs tree changes for indexers
+  #[account(address = NOOP_PROGRAM_ID)]
+  pub noop_program: UncheckedAccount<'info>,
+
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct BuyCompressed<'info> {
+  #[account(seeds = [b"config"], bump = config.bump)]
+  pub config: Account<'info, ProtocolConfig>,
+
+  #[account(mut)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(
+    mut,
+    seeds = [b"vault_v2", market.key().as_ref()],
+    bump = market.load()?.vault_bump
+  )]
+  pub vault: InterfaceAccount<'info, TokenAccount>,
+
+  #[account(
+    mut,
+    seeds = [b"compressed_positions", market.key().as_ref()],
+    bump = compressed_positions.bump,
+    has_one = merkle_tree,
+  )]
+  pub compressed_positions: Account<'info, CompressedPositions>,
+
+  /// CHECK: the market's position tree, checked against `compressed_positions`
+  #[account(mut)]
+  pub merkle_tree: UncheckedAccount<'info>,
+
+  #[account(mut)]
+  pub user: Signer<'info>,
+
+  #[account(address = market.load()?.collateral_mint)]
+  pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+  /// Not needed for native SOL markets (lamports are wrapped into the vault directly)
+  #[account(
+    mut,
+    constraint = user_collateral_ata.mint == market.load()?.collateral_mint,
+    constraint = user_collateral_ata.owner == user.key(),
+  )]
+  pub user_collateral_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+
+  /// Required once the market's stats account is initialized
+  #[account(mut, seeds = [b"market_stats", market.key().as_ref()], bump = market_stats.bump)]
+  pub market_stats: Option<Account<'info, MarketStats>>,
+
+  #[account(mut, seeds = [b"protocol_stats"], bump = protocol_stats.bump)]
+  pub protocol_stats: Option<Account<'info, ProtocolStats>>,
+
+  /// Required once the market's candles are initialized
+  #[account(mut, seeds = [b"price_candles", market.key().as_ref()], bump = price_candles.load()?.bump)]
+  pub price_candles: Option<AccountLoader<'info, PriceCandles>>,
+
+  /// Required once the market's trade tape is initialized
+  #[account(mut, seeds = [b"trade_tape", market.key().as_ref()], bump = trade_tape.load()?.bump)]
+  pub trade_tape: Option<AccountLoader<'info, TradeTape>>,
+
+  /// Required once the market's price feed is initialized
+  #[account(mut, seeds = [b"price_feed", market.key().as_ref()], bump = price_feed.load()?.bump)]
+  pub price_feed: Option<AccountLoader<'info, PriceFeed>>,
+
+  /// CHECK: spl-account-compression
+  #[account(address = ACCOUNT_COMPRESSION_PROGRAM_ID)]
+  pub compression_program: UncheckedAccount<'info>,
+
+  /// CHECK: spl-noop
+  #[account(address = NOOP_PROGRAM_ID)]
+  pub noop_program: UncheckedAccount<'info>,
+
+  pub token_program: Interface<'info, TokenInterface>,
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimCompressed<'info> {
+  #[account(mut)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(
+    mut,
+    seeds = [b"vault_v2", market.key().as_ref()],
+    bump = market.load()?.vault_bump
+  )]
+  pub vault: InterfaceAccount<'info, TokenAccount>,
+
+  /// CHECK: PDA that signs for vault transfers
+  #[account(
+    seeds = [b"vault_auth_v2", market.key().as_ref()],
+    bump = market.load()?.vault_auth_bump
+  )]
+  pub vault_authority: UncheckedAccount<'info>,
+
+  #[account(
+    seeds = [b"compressed_positions", market.key().as_ref()],
+    bump = compressed_positions.bump,
+    has_one = merkle_tree,
+  )]
+  pub compressed_positions: Account<'info, CompressedPositions>,
+
+  /// CHECK: the market's position tree, checked against `compressed_positions`
+  #[account(mut)]
+  pub merkle_tree: UncheckedAccount<'info>,
+
+  #[account(mut)]
+  pub user: Signer<'info>,
+
+  /// Pays rent for accounts created here (the user or a relayer)
+  #[account(mut)]
+  pub payer: Signer<'info>,
+
+  #[account(address = market.load()?.collateral_mint)]
+  pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+  /// Not needed for native SOL markets (payout is unwrapped via `wsol_unwrap`)
+  #[account(
+    mut,
+    constraint = user_collateral_ata.mint == collateral_mint.key(),
+    constraint = user_collateral_ata.owner == user.key(),
+  )]
+  pub user_collateral_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+
+  /// Temporary wSOL account, created and closed within the instruction (native SOL markets only)
+  #[account(
+    init,
+    payer = payer,
+    seeds = [b"wsol_unwrap", market.key().as_ref(), user.key().as_ref()],
+    bump,
+    token::mint = collateral_mint,
+    token::authority = vault_authority,
+    token::token_program = token_program,
+  )]
+  pub wsol_unwrap: Option<InterfaceAccount<'info, TokenAccount>>,
+
+  /// CHECK: spl-account-compression
+  #[account(address = ACCOUNT_COMPRESSION_PROGRAM_ID)]
+  pub compression_program: UncheckedAccount<'info>,
+
+  /// CHECK: spl-noop
+  #[account(address = NOOP_PROGRAM_ID)]
+  pub noop_program: UncheckedAccount<'info>,
+
+  pub token_program: Interface<'info, TokenInterface>,
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CommitPayoutRoot<'info> {
+  #[account(seeds = [b"config"], bump = config.bump)]
+  pub config: Account<'info, ProtocolConfig>,
+
+  #[account(mut)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(
+    init,
+    payer = authority,
+    space = 8 + PayoutDistributor::INIT_SPACE,
+    seeds = [b"payout_distributor", market.key().as_ref()],
+    bump
+  )]
+  pub payout_distributor: Account<'info, PayoutDistributor>,
+
+  /// Market resolver or config admin
+  #[account(mut)]
+  pub authority: Signer<'info>,
+
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(index: u64)]
+pub struct ClaimFromDistributor<'info> {
+  #[account(mut)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(
+    mut,
+    seeds = [b"vault_v2", market.key().as_ref()],
+    bump = market.load()?.vault_bump
+  )]
+  pub vault: InterfaceAccount<'info, TokenAccount>,
+
+  /// CHECK: PDA that signs for vault transfers
+  #[account(
+    seeds = [b"vault_auth_v2", market.key().as_ref()],
+    bump = market.load()?.vault_auth_bump
+  )]
+  pub vault_authority: UncheckedAccount<'info>,
+
+  #[account(
+    mut,
+    seeds = [b"payout_distributor", market.key().as_ref()],
+    bump = payout_distributor.bump,
+  )]
+  pub payout_distributor: Account<'info, PayoutDistributor>,
+
+  #[account(
+    init,
+    payer = payer,
+    space = 8 + DistributorClaim::INIT_SPACE,
+    seeds = [b"distributor_claim", payout_distributor.key().as_ref(), &index.to_le_bytes()],
+    bump
+  )]
+  pub claim_receipt: Account<'info, DistributorClaim>,
+
+  #[account(mut)]
+  pub user: Signer<'info>,
+
+  /// Pays rent for accounts created here (the user or a relayer)
+  #[account(mut)]
+  pub payer: Signer<'info>,
+
+  #[account(address = market.load()?.collateral_mint)]
+  pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+  /// Not needed for native SOL markets (payout is unwrapped via `wsol_unwrap`)
+  #[account(
+    mut,
+    constraint = user_collateral_ata.mint == collateral_mint.key(),
+    constraint = user_collateral_ata.owner == user.key(),
+  )]
+  pub user_collateral_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+
+  /// Temporary wSOL account, created and closed within the instruction (native SOL markets only)
+  #[account(
+    init,
+    payer = payer,
+    seeds = [b"wsol_unwrap", market.key().as_ref(), user.key().as_ref()],
+    bump,
+    token::mint = collateral_mint,
+    token::authority = vault_authority,
+    token::token_program = token_program,
+  )]
+  pub wsol_unwrap: Option<InterfaceAccount<'info, TokenAccount>>,
+
+  pub token_program: Interface<'info, TokenInterface>,
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetResolutionMultisig<'info> {
+  #[account(mut)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  /// CHECK: Squads `Multisig` account; only its owner is checked here
+  #[account(owner = SQUADS_PROGRAM_ID @ PredictionError::InvalidMultisig)]
+  pub multisig: UncheckedAccount<'info>,
+
+  #[account(address = market.load()?.authority @ PredictionError::Unauthorized)]
+  pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetParentMarket<'info> {
+  #[account(mut, constraint = market.key() != parent_market.key() @ PredictionError::InvalidTargetMarket)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  pub parent_market: AccountLoader<'info, MarketV3>,
+
+  #[account(address = market.load()?.authority @ PredictionError::Unauthorized)]
+  pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(args: CreateSeriesArgs)]
+pub struct CreateMarketSeries<'info> {
+  #[account(seeds = [b"config"], bump = config.bump)]
+  pub config: Account<'info, ProtocolConfig>,
+
+  #[account(
+    init,
+    payer = authority,
+    space = 8 + MarketSeries::INIT_SPACE,
+    seeds = [b"market_series", authority.key().as_ref(), &args.series_id.to_le_bytes()],
+    bump
+  )]
+  pub series: Account<'info, MarketSeries>,
+
+  pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+  #[account(mut)]
+  pub authority: Signer<'info>,
+
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct CreateNextInSeries<'info> {
+  #[account(mut, seeds = [b"config"], bump = config.bump)]
+  pub config: Account<'info, ProtocolConfig>,
+
+  #[account(mut, has_one = authority, has_one = collateral_mint)]
+  pub series: Account<'info, MarketSeries>,
+
+  #[account(
+    init,
+    payer = authority,
+    space = 8 + std::mem::size_of::<MarketV3>(),
+    seeds = [b"market_v2", authority.key().as_ref(), &market_id.to_le_bytes()],
+    bump
+  )]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(
+    init,
+    payer = authority,
+    seeds = [b"vault_v2", market.key().as_ref()],
+    bump,
+    token::mint = collateral_mint,
+    token::authority = vault_authority,
+    token::token_program = token_program,
+  )]
+  pub vault: InterfaceAccount<'info, TokenAccount>,
+
+  /// CHECK: PDA that signs for vault transfers
+  #[account(
+    seeds = [b"vault_auth_v2", market.key().as_ref()],
+    bump
+  )]
+  pub vault_authority: UncheckedAccount<'info>,
+
+  pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+  #[account(mut)]
+  pub authority: Signer<'info>,
+
+  #[account(
+    mut,
+    constraint = authority_collateral_ata.mint == collateral_mint.key(),
+    constraint = authority_collateral_ata.owner == authority.key(),
+  )]
+  pub authority_collateral_ata: InterfaceAccount<'info, TokenAccount>,
+
+  #[account(
+    init_if_needed,
+    payer = authority,
+    space = 8 + MarketRegistry::INIT_SPACE,
+    seeds = [b"registry"],
+    bump
+  )]
+  pub registry: Account<'info, MarketRegistry>,
+
+  #[account(
+    init_if_needed,
+    payer = authority,
+    space = 8 + RegistryPage::INIT_SPACE,
+    seeds = [b"registry_page".as_ref(), &(registry.market_count / REGISTRY_PAGE_SIZE).to_le_bytes()],
+    bump
+  )]
+  pub registry_page: Account<'info, RegistryPage>,
+
+  #[account(mut, seeds = [b"protocol_stats"], bump = protocol_stats.bump)]
+  pub protocol_stats: Option<Account<'info, ProtocolStats>>,
+
+  #[account(
+    init,
+    payer = authority,
+    space = 8 + CreatorBond::INIT_SPACE,
+    seeds = [b"creator_bond", market.key().as_ref()],
+    bump
+  )]
+  pub creator_bond: Account<'info, CreatorBond>,
+
+  #[account(
+    init,
+    payer = authority,
+    space = 8 + LpPosition::INIT_SPACE,
+    seeds = [b"lp_position", market.key().as_ref(), authority.key().as_ref()],
+    bump
+  )]
+  pub creator_lp_position: Account<'info, LpPosition>,
+
+  pub token_program: Interface<'info, TokenInterface>,
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(args: RegisterTemplateArgs)]
+pub struct RegisterMarketTemplate<'info> {
+  #[account(
+    seeds = [b"config"],
+    bump = config.bump,
+    constraint = registrar.key() == config.admin
+      || registrar.key() == config.curator @ PredictionError::Unauthorized,
+  )]
+  pub config: Account<'info, ProtocolConfig>,
+
+  #[account(
+    init,
+    payer = registrar,
+    space = 8 + MarketTemplate::INIT_SPACE,
+    seeds = [b"market_template".as_ref(), &args.template_id.to_le_bytes()],
+    bump
+  )]
+  pub template: Account<'info, MarketTemplate>,
+
+  #[account(mut)]
+  pub registrar: Signer<'info>,
+
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetTemplateActive<'info> {
+  #[account(
+    seeds = [b"config"],
+    bump = config.bump,
+    constraint = registrar.key() == config.admin
+      || registrar.key() == config.curator @ PredictionError::Unauthorized,
+  )]
+  pub config: Account<'info, ProtocolConfig>,
+
+  #[account(mut)]
+  pub template: Account<'info, MarketTemplate>,
+
+  pub registrar: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(template_id: u64, params: TemplateMarketParams)]
+pub struct CreateFromTemplate<'info> {
+  #[account(mut, seeds = [b"config"], bump = config.bump)]
+  pub config: Account<'info, ProtocolConfig>,
+
+  #[account(seeds = [b"market_template".as_ref(), &template_id.to_le_bytes()], bump = template.bump)]
+  pub template: Account<'info, MarketTemplate>,
+
+  #[account(
+    init,
+    payer = authority,
+    space = 8 + std::mem::size_of::<MarketV3>(),
+    seeds = [b"market_v2", authority.key().as_ref(), &params.market_id.to_le_bytes()],
+    bump
+  )]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(
+    init,
+    payer = authority,
+    seeds = [b"vault_v2", market.key().as_ref()],
+    bump,
+    token::mint = collateral_mint,
+    token::authority = vault_authority,
+    token::token_program = token_program,
+  )]
+  pub vault: InterfaceAccount<'info, TokenAccount>,
+
+  /// CHECK: PDA that signs for vault transfers
+  #[account(
+    seeds = [b"vault_auth_v2", market.key().as_ref()],
+    bump
+  )]
+  pub vault_authority: UncheckedAccount<'info>,
+
+  pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+  #[account(mut)]
+  pub authority: Signer<'info>,
+
+  #[account(
+    mut,
+    constraint = authority_collateral_ata.mint == collateral_mint.key(),
+    constraint = authority_collateral_ata.owner == authority.key(),
+  )]
+  pub authority_collateral_ata: InterfaceAccount<'info, TokenAccount>,
+
+  #[account(
+    init_if_needed,
+    payer = authority,
+    space = 8 + MarketRegistry::INIT_SPACE,
+    seeds = [b"registry"],
+    bump
+  )]
+  pub registry: Account<'info, MarketRegistry>,
+
+  #[account(
+    init_if_needed,
+    payer = authority,
+    space = 8 + RegistryPage::INIT_SPACE,
+    seeds = [b"registry_page".as_ref(), &(registry.market_count / REGISTRY_PAGE_SIZE).to_le_bytes()],
+    bump
+  )]
+  pub registry_page: Account<'info, RegistryPage>,
+
+  #[account(mut, seeds = [b"protocol_stats"], bump = protocol_stats.bump)]
+  pub protocol_stats: Option<Account<'info, ProtocolStats>>,
+
+  #[account(
+    init,
+    payer = authority,
+    space = 8 + CreatorBond::INIT_SPACE,
+    seeds = [b"creator_bond", market.key().as_ref()],
+    bump
+  )]
+  pub creator_bond: Account<'info, CreatorBond>,
+
+  #[account(
+    init,
+    payer = authority,
+    space = 8 + LpPosition::INIT_SPACE,
+    seeds = [b"lp_position", market.key().as_ref(), authority.key().as_ref()],
+    bump
+  )]
+  pub creator_lp_position: Account<'info, LpPosition>,
+
+  pub token_program: Interface<'info, TokenInterface>,
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetFeeHoliday<'info> {
+  #[account(seeds = [b"config"], bump = config.bump)]
+  pub config: Account<'info, ProtocolConfig>,
+
+  #[account(mut)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  /// Market authority or config admin
+  pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetMaxPosition<'info> {
+  #[account(seeds = [b"config"], bump = config.bump)]
+  pub config: Account<'info, ProtocolConfig>,
+
+  #[account(mut)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  /// Market authority or config admin
+  pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetAllowlistGate<'info> {
+  #[account(mut)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(address = market.load()?.authority @ PredictionError::Unauthorized)]
+  pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetTokenGate<'info> {
+  #[account(mut)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(
+    init_if_needed,
+    payer = authority,
+    space = 8 + TokenGate::INIT_SPACE,
+    seeds = [b"token_gate", market.key().as_ref()],
+    bump
+  )]
+  pub token_gate: Account<'info, TokenGate>,
+
+  pub gate_mint: InterfaceAccount<'info, Mint>,
+
+  #[account(mut, address = market.load()?.authority @ PredictionError::Unauthorized)]
+  pub authority: Signer<'info>,
+
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetPermitGate<'info> {
+  #[account(mut)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(
+    init_if_needed,
+    payer = authority,
+    space = 8 + PermitGate::INIT_SPACE,
+    seeds = [b"permit_gate", market.key().as_ref()],
+    bump
+  )]
+  pub permit_gate: Account<'info, PermitGate>,
+
+  #[account(mut, address = market.load()?.authority @ PredictionError::Unauthorized)]
+  pub authority: Signer<'info>,
+
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(wallet: Pubkey)]
+pub struct AllowWallet<'info> {
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(
+    init,
+    payer = authority,
+    space = 8 + AllowlistEntry::INIT_SPACE,
+    seeds = [b"allowlist", market.key().as_ref(), wallet.as_ref()],
+    bump
+  )]
+  pub allowlist_entry: Account<'info, AllowlistEntry>,
+
+  #[account(mut, address = market.load()?.authority @ PredictionError::Unauthorized)]
+  pub authority: Signer<'info>,
+
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RevokeWallet<'info> {
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(
+    mut,
+    close = authority,
+    seeds = [b"allowlist", market.key().as_ref(), allowlist_entry.wallet.as_ref()],
+    bump = allowlist_entry.bump
+  )]
+  pub allowlist_entry: Account<'info, AllowlistEntry>,
+
+  #[account(mut, address = market.load()?.authority @ PredictionError::Unauthorized)]
+  pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetClaimWindow<'info> {
+  #[account(mut)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(address = market.load()?.authority @ PredictionError::Unauthorized)]
+  pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SweepUnclaimed<'info> {
+  #[account(seeds = [b"config"], bump = config.bump)]
+  pub config: Account<'info, ProtocolConfig>,
+
+  #[account(mut)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(
+    mut,
+    seeds = [b"vault_v2", market.key().as_ref()],
+    bump = market.load()?.vault_bump
+  )]
+  pub vault: InterfaceAccount<'info, TokenAccount>,
+
+  /// CHECK: PDA that signs for vault transfers
+  #[account(
+    seeds = [b"vault_auth_v2", market.key().as_ref()],
+    bump = market.load()?.vault_auth_bump
+  )]
+  pub vault_authority: UncheckedAccount<'info>,
+
+  #[account(address = market.load()?.collateral_mint)]
+  pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+  /// Rollover markets: the later series market and its vault / creator LP position
+  #[account(mut, constraint = next_market.key() != market.key() @ PredictionError::NotInSeries)]
+  pub next_market: Option<AccountLoader<'info, MarketV3>>,
+
+  #[account(mut)]
+  pub next_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+
+  #[account(mut)]
+  pub next_lp_position: Option<Account<'info, LpPosition>>,
+
+  /// The later market's emissions, if it has liquidity mining
+  #[account(mut)]
+  pub next_lp_rewards: Option<Account<'info, LpRewards>>,
+
+  /// Other markets: the treasury's collateral account
+  #[account(
+    mut,
+    constraint = treasury_collateral_ata.mint == collateral_mint.key(),
+    constraint = treasury_collateral_ata.owner == config.treasury @ PredictionError::Unauthorized,
+  )]
+  pub treasury_collateral_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+
+  pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct InitScalarMarket<'info> {
+  #[account(mut)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(
+    init,
+    payer = authority,
+    space = 8 + ScalarMarket::INIT_SPACE,
+    seeds = [b"scalar_market", market.key().as_ref()],
+    bump
+  )]
+  pub scalar: Account<'info, ScalarMarket>,
+
+  #[account(mut, address = market.load()?.authority @ PredictionError::Unauthorized)]
+  pub authority: Signer<'info>,
+
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitOracleConfig<'info> {
+  #[account(mut)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(
+    init,
+    payer = authority,
+    space = 8 + OracleConfig::INIT_SPACE,
+    seeds = [b"oracle_config", market.key().as_ref()],
+    bump
+  )]
+  pub oracle_config: Account<'info, OracleConfig>,
+
+  #[account(mut, address = market.load()?.authority @ PredictionError::Unauthorized)]
+  pub authority: Signer<'info>,
+
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ResolveWithOracle<'info> {
+  #[account(seeds = [b"config"], bump = config.bump)]
+  pub config: Account<'info, ProtocolConfig>,
+
+  #[account(mut)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(
+    mut,
+    seeds = [b"oracle_config", market.key().as_ref()],
+    bump = oracle_config.bump
+  )]
+  pub oracle_config: Account<'info, OracleConfig>,
+
+  #[account(
+    mut,
+    seeds = [b"vault_v2", market.key().as_ref()],
+    bump = market.load()?.vault_bump
+  )]
+  pub vault: InterfaceAccount<'info, TokenAccount>,
+
+  /// CHECK: PDA that signs for vault transfers
+  #[account(
+    seeds = [b"vault_auth_v2", market.key().as_ref()],
+    bump = market.load()?.vault_auth_bump
+  )]
+  pub vault_authority: UncheckedAccount<'info>,
+
+  #[account(address = market.load()?.collateral_mint)]
+  pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+  /// CHECK: price adapters only: Pyth `PriceUpdateV2` or the configured
+  /// Chainlink feed; owner, feed and timestamps checked in the handler
+  pub oracle_account: Option<UncheckedAccount<'info>>,
+
+  /// CHECK: SignedReport only: instructions sysvar, used to find the ed25519 verification
+  #[account(address = ix_sysvar::ID)]
+  pub instructions: Option<UncheckedAccount<'info>>,
+
+  /// Anyone for price and signed-report adapters; the market's resolver for Manual
+  pub resolver: Signer<'info>,
+
+  /// Receives the market's resolver bounty; omit to forgo it
+  #[account(
+    mut,
+    constraint = resolver_collateral_ata.mint == collateral_mint.key(),
+    constraint = resolver_collateral_ata.owner == resolver.key() @ PredictionError::Unauthorized,
+  )]
+  pub resolver_collateral_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+
+  pub token_program: Interface<'info, TokenInterface>,
+
+  #[account(
+    mut,
+    seeds = [b"registry_page".as_ref(), &(market.load()?.registry_index / REGISTRY_PAGE_SIZE).to_le_bytes()],
+    bump = registry_page.bump
+  )]
+  pub registry_page: Option<Account<'info, RegistryPage>>,
+}
+
+#[derive(Accounts)]
+pub struct VoidUnresolvableMarket<'info> {
+  #[account(mut)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  // Checked against the internal balance before snapshotting
+  #[account(
+    seeds = [b"vault_v2", market.key().as_ref()],
+    bump = market.load()?.vault_bump
+  )]
+  pub vault: InterfaceAccount<'info, TokenAccount>,
+
+  #[account(
+    mut,
+    seeds = [b"registry_page".as_ref(), &(market.load()?.registry_index / REGISTRY_PAGE_SIZE).to_le_bytes()],
+    bump = registry_page.bump
+  )]
+  pub registry_page: Option<Account<'info, RegistryPage>>,
+}
+
+#[derive(Accounts)]
+pub struct SettleScalarWithPyth<'info> {
+  #[account(seeds = [b"config"], bump = config.bump)]
+  pub config: Account<'info, ProtocolConfig>,
+
+  #[account(mut)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(
+    mut,
+    seeds = [b"scalar_market", market.key().as_ref()],
+    bump = scalar.bump
+  )]
+  pub scalar: Account<'info, ScalarMarket>,
+
+  // Checked against the internal balance before snapshotting
+  #[account(
+    mut,
+    seeds = [b"vault_v2", market.key().as_ref()],
+    bump = market.load()?.vault_bump
+  )]
+  pub vault: InterfaceAccount<'info, TokenAccount>,
+
+  /// CHECK: PDA that signs for vault transfers
+  #[account(
+    seeds = [b"vault_auth_v2", market.key().as_ref()],
+    bump = market.load()?.vault_auth_bump
+  )]
+  pub vault_authority: UncheckedAccount<'info>,
+
+  #[account(address = market.load()?.collateral_mint)]
+  pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+  /// CHECK: Pyth `PriceUpdateV2`; owner, feed id and publish time checked in the handler
+  pub price_update: UncheckedAccount<'info>,
+
+  pub resolver: Signer<'info>,
+
+  /// Receives the market's resolver bounty; omit to forgo it
+  #[account(
+    mut,
+    constraint = resolver_collateral_ata.mint == collateral_mint.key(),
+    constraint = resolver_collateral_ata.owner == resolver.key() @ PredictionError::Unauthorized,
+  )]
+  pub resolver_collateral_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+
+  pub token_program: Interface<'info, TokenInterface>,
+
+  // Required for registered markets so the registry status stays current
+  #[account(
+    mut,
+    seeds = [b"registry_page".as_ref(), &(market.load()?.registry_index / REGISTRY_PAGE_SIZE).to_le_bytes()],
+    bump = registry_page.bump
+  )]
+  pub registry_page: Option<Account<'info, RegistryPage>>,
+}
+
+#[derive(Accounts)]
+pub struct CorrectResolution<'info> {
+  #[account(seeds = [b"config"], bump = config.bump)]
+  pub config: Account<'info, ProtocolConfig>,
+
+  #[account(mut)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  /// Market authority or config admin
+  pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct EscrowClaim<'info> {
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(
+    mut,
+    seeds = [b"position_v2", market.key().as_ref(), user.key().as_ref()],
+    bump = position.bump
+  )]
+  pub position: Account<'info, PositionV2>,
+
+  #[account(
+    init,
+    payer = payer,
+    space = 8 + ClaimEscrow::INIT_SPACE,
+    seeds = [b"claim_escrow", market.key().as_ref(), user.key().as_ref()],
+    bump
+  )]
+  pub claim_escrow: Account<'info, ClaimEscrow>,
+
+  #[account(mut)]
+  pub user: Signer<'info>,
+
+  /// Pays rent for accounts created here (the user or a relayer)
+  #[account(mut)]
+  pub payer: Signer<'info>,
+
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct FundParlayPool<'info> {
+  #[account(
+    seeds = [b"config"],
+    bump = config.bump,
+    has_one = admin @ PredictionError::Unauthorized,
+  )]
+  pub config: Account<'info, ProtocolConfig>,
+
+  #[account(
+    init_if_needed,
+    payer = admin,
+    space = 8 + ParlayPool::INIT_SPACE,
+    seeds = [b"parlay_pool", collateral_mint.key().as_ref()],
+    bump
+  )]
+  pub pool: Account<'info, ParlayPool>,
+
+  /// CHECK: PDA that owns every parlay pool vault
+  #[account(seeds = [b"parlay_auth"], bump)]
+  pub pool_authority: UncheckedAccount<'info>,
+
+  #[account(
+    init_if_needed,
+    payer = admin,
+    seeds = [b"parlay_vault", collateral_mint.key().as_ref()],
+    bump,
+    token::mint = collateral_mint,
+    token::authority = pool_authority,
+    token::token_program = token_program,
+  )]
+  pub pool_vault: InterfaceAccount<'info, TokenAccount>,
+
+  #[account(mut)]
+  pub admin: Signer<'info>,
+
+  #[account(
+    mut,
+    constraint = admin_collateral_ata.mint == collateral_mint.key(),
+    constraint = admin_collateral_ata.owner == admin.key(),
+  )]
+  pub admin_collateral_ata: InterfaceAccount<'info, TokenAccount>,
+
+  pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+  pub token_program: Interface<'info, TokenInterface>,
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawParlayPool<'info> {
+  #[account(
+    seeds = [b"config"],
+    bump = config.bump,
+    has_one = admin @ PredictionError::Unauthorized,
+  )]
+  pub config: Account<'info, ProtocolConfig>,
+
+  #[account(
+    seeds = [b"parlay_pool", collateral_mint.key().as_ref()],
+    bump = pool.bump
+  )]
+  pub pool: Account<'info, ParlayPool>,
+
+  /// CHECK: PDA that owns every parlay pool vault
+  #[account(seeds = [b"parlay_auth"], bump)]
+  pub pool_authority: UncheckedAccount<'info>,
+
+  #[account(
+    mut,
+    seeds = [b"parlay_vault", collateral_mint.key().as_ref()],
+    bump
+  )]
+  pub pool_vault: InterfaceAccount<'info, TokenAccount>,
+
+  pub admin: Signer<'info>,
+
+  #[account(
+    mut,
+    constraint = admin_collateral_ata.mint == collateral_mint.key(),
+  )]
+  pub admin_collateral_ata: InterfaceAccount<'info, TokenAccount>,
+
+  pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+  pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(args: PlaceParlayArgs)]
+pub struct PlaceParlay<'info> {
+  #[account(
+    init,
+    payer = payer,
+    space = 8 + Parlay::INIT_SPACE,
+    seeds = [b"parlay", owner.key().as_ref(), &args.parlay_id.to_le_bytes()],
+    bump
+  )]
+  pub parlay: Account<'info, Parlay>,
+
+  #[account(
+    mut,
+    seeds = [b"parlay_pool", collateral_mint.key().as_ref()],
+    bump = pool.bump
+  )]
+  pub pool: Account<'info, ParlayPool>,
+
+  #[account(
+    mut,
+    seeds = [b"parlay_vault", collateral_mint.key().as_ref()],
+    bump
+  )]
+  pub pool_vault: InterfaceAccount<'info, TokenAccount>,
+
+  #[account(mut)]
+  pub owner: Signer<'info>,
+
+  /// Pays rent for accounts created here (the owner or a relayer)
+  #[account(mut)]
+  pub payer: Signer<'info>,
+
+  /// Not needed for native SOL
+  #[account(
+    mut,
+    constraint = owner_collateral_ata.mint == collateral_mint.key(),
+    constraint = owner_collateral_ata.owner == owner.key(),
+  )]
+  pub owner_collateral_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+
+  pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+  pub token_program: Interface<'info, TokenInterface>,
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SettleParlay<'info> {
+  #[account(mut, close = owner, has_one = owner, has_one = collateral_mint)]
+  pub parlay: Account<'info, Parlay>,
+
+  #[account(
+    mut,
+    seeds = [b"parlay_pool", collateral_mint.key().as_ref()],
+    bump = pool.bump
+  )]
+  pub pool: Account<'info, ParlayPool>,
+
+  /// CHECK: PDA that owns every parlay pool vault
+  #[account(seeds = [b"parlay_auth"], bump)]
+  pub pool_authority: UncheckedAccount<'info>,
+
+  #[account(
+    mut,
+    seeds = [b"parlay_vault", collateral_mint.key().as_ref()],
+    bump
+  )]
+  pub pool_vault: InterfaceAccount<'info, TokenAccount>,
+
+  /// CHECK: parlay owner; receives the account rent
+  #[account(mut)]
+  pub owner: UncheckedAccount<'info>,
+
+  /// Owner's token account (a wSOL account for native SOL)
+  #[account(
+    mut,
+    constraint = owner_collateral_ata.mint == collateral_mint.key(),
+    constraint = owner_collateral_ata.owner == owner.key(),
+  )]
+  pub owner_collateral_ata: InterfaceAccount<'info, TokenAccount>,
+
+  pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+  pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct OpenPosition<'info> {
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(
+    init_if_needed,
+    payer = payer,
+    space = 8 + PositionV2::INIT_SPACE,
+    seeds = [b"position_v2", market.key().as_ref(), owner.key().as_ref()],
+    bump
+  )]
+  pub position: Account<'info, PositionV2>,
+
+  #[account(mut)]
+  pub owner: Signer<'info>,
+
+  /// Pays rent for accounts created here (the owner or a relayer)
+  #[account(mut)]
+  pub payer: Signer<'info>,
+
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(group_id: u64)]
+pub struct CreateMarketGroup<'info> {
+  #[account(
+    init,
+    payer = authority,
+    space = 8 + MarketGroup::INIT_SPACE,
+    seeds = [b"market_group", authority.key().as_ref(), &group_id.to_le_bytes()],
+    bump
+  )]
+  pub group: Account<'info, MarketGroup>,
+
+  pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+  #[account(mut)]
+  pub authority: Signer<'info>,
+
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AddGroupMember<'info> {
+  #[account(mut, has_one = authority)]
+  pub group: Account<'info, MarketGroup>,
+
+  #[account(mut)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetGroupCatchAll<'info> {
+  #[account(mut, has_one = authority)]
+  pub group: Account<'info, MarketGroup>,
+
+  pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetGroupRankWeights<'info> {
+  #[account(mut, has_one = authority)]
+  pub group: Account<'info, MarketGroup>,
+
+  pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ConvertPosition<'info> {
+  #[account(mut, constraint = market.load()?.group == group.key() @ PredictionError::GroupedMarket)]
+  pub group: Account<'info, MarketGroup>,
+
+  #[account(mut)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(
+    mut,
+    seeds = [b"position_v2", market.key().as_ref(), user.key().as_ref()],
+    bump = position.bump
+  )]
+  pub position: Account<'info, PositionV2>,
+
+  pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ResolveGroup<'info> {
+  #[account(mut, has_one = authority, has_one = collateral_mint)]
+  pub group: Account<'info, MarketGroup>,
+
+  pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+  pub authority: Signer<'info>,
+
+  pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimGroup<'info> {
+  #[account(has_one = collateral_mint)]
+  pub group: Account<'info, MarketGroup>,
+
+  pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+  #[account(mut)]
+  pub owner: Signer<'info>,
+
+  #[account(
+    mut,
+    constraint = owner_collateral_ata.mint == collateral_mint.key(),
+    constraint = owner_collateral_ata.owner == owner.key(),
+  )]
+  pub owner_collateral_ata: InterfaceAccount<'info, TokenAccount>,
+
+  pub token_program: Interface<'info, TokenInterface>,
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitOrderBook<'info> {
+  #[account(
+    init,
+    payer = payer,
+    space = 8 + OrderBook::INIT_SPACE,
+    seeds = [b"order_book", market.key().as_ref()],
+    bump
+  )]
+  pub order_book: Account<'info, OrderBook>,
+
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(mut)]
+  pub payer: Signer<'info>,
+
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct PlaceBookOrder<'info> {
+  #[account(
+    mut,
+    seeds = [b"order_book", market.key().as_ref()],
+    bump = order_book.bump
+  )]
+  pub order_book: Account<'info, OrderBook>,
+
+  #[account(mut)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(
+    init_if_needed,
+    payer = payer,
+    space = 8 + PositionV2::INIT_SPACE,
+    seeds = [b"position_v2", market.key().as_ref(), owner.key().as_ref()],
+    bump
+  )]
+  pub position: Account<'info, PositionV2>,
+
+  /// Funds bids and receives ask proceeds
+  #[account(
+    mut,
+    seeds = [b"trading_account", owner.key().as_ref(), order_book.collateral_mint.as_ref()],
+    bump = trading_account.bump
+  )]
+  pub trading_account: Account<'info, TradingAccount>,
+
+  #[account(mut)]
+  pub owner: Signer<'info>,
+
+  /// Pays rent for accounts created here (the owner or a relayer)
+  #[account(mut)]
+  pub payer: Signer<'info>,
+
+  /// Required while the market is allowlist-gated
+  #[account(
+    seeds = [b"allowlist", market.key().as_ref(), owner.key().as_ref()],
+    bump = allowlist_entry.bump
+  )]
+  pub allowlist_entry: Option<Account<'info, AllowlistEntry>>,
+
+  /// Both required while the market is permit-gated; the permit's ed25519
+  /// verification must precede this instruction
+  #[account(seeds = [b"permit_gate", market.key().as_ref()], bump = permit_gate.bump)]
+  pub permit_gate: Option<Account<'info, PermitGate>>,
+  /// CHECK: instructions sysvar
+  #[account(address = ix_sysvar::ID)]
+  pub instructions: Option<UncheckedAccount<'info>>,
+
+  /// Both required for bids while the market is token-gated
+  #[account(seeds = [b"token_gate", market.key().as_ref()], bump = token_gate.bump)]
+  pub token_gate: Option<Account<'info, TokenGate>>,
+  pub gate_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CancelBookOrder<'info> {
+  #[account(
+    mut,
+    seeds = [b"order_book", order_book.market.as_ref()],
+    bump = order_book.bump
+  )]
+  pub order_book: Account<'info, OrderBook>,
+
+  #[account(
+    mut,
+    seeds = [b"position_v2", order_book.market.as_ref(), owner.key().as_ref()],
+    bump = position.bump
+  )]
+  pub position: Account<'info, PositionV2>,
+
+  #[account(
+    mut,
+    seeds = [b"trading_account", owner.key().as_ref(), order_book.collateral_mint.as_ref()],
+    bump = trading_account.bump
+  )]
+  pub trading_account: Account<'info, TradingAccount>,
+
+  pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RouteOrder<'info> {
+  #[account(seeds = [b"config"], bump = config.bump)]
+  pub config: Account<'info, ProtocolConfig>,
+
+  #[account(
+    mut,
+    seeds = [b"order_book", market.key().as_ref()],
+    bump = order_book.bump
+  )]
+  pub order_book: Account<'info, OrderBook>,
+
+  #[account(mut)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(
+    mut,
+    seeds = [b"vault_v2", market.key().as_ref()],
+    bump = market.load()?.vault_bump
+  )]
+  pub vault: InterfaceAccount<'info, TokenAccount>,
+
+  /// CHECK: PDA that signs for vault transfers
+  #[account(
+    seeds = [b"vault_auth_v2", market.key().as_ref()],
+    bump = market.load()?.vault_auth_bump
+  )]
+  pub vault_authority: UncheckedAccount<'info>,
+
+  #[account(
+    init_if_needed,
+    payer = payer,
+    space = 8 + PositionV2::INIT_SPACE,
+    seeds = [b"position_v2", market.key().as_ref(), user.key().as_ref()],
+    bump
+  )]
+  pub position: Account<'info, PositionV2>,
+
+  #[account(
+    mut,
+    seeds = [b"trading_account", user.key().as_ref(), collateral_mint.key().as_ref()],
+    bump = trading_account.bump
+  )]
+  pub trading_account: Account<'info, TradingAccount>,
+
+  /// CHECK: PDA that owns every trading custody account
+  #[account(seeds = [b"trading_custody_auth"], bump)]
+  pub custody_authority: UncheckedAccount<'info>,
+
+  #[account(
+    mut,
+    seeds = [b"trading_custody", collateral_mint.key().as_ref()],
+    bump
+  )]
+  pub custody: InterfaceAccount<'info, TokenAccount>,
+
+  #[account(mut)]
+  pub user: Signer<'info>,
+
+  /// Pays rent for accounts created here (the user or a relayer)
+  #[account(mut)]
+  pub payer: Signer<'info>,
+
+  #[account(address = market.load()?.collateral_mint)]
+  pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+  /// Designated makers; pass to accrue the trader's maker rebate
+  #[account(mut, seeds = [b"market_makers", market.key().as_ref()], bump = market_makers.bump)]
+  pub market_makers: Option<Account<'info, MarketMakers>>,
+
+  /// The trader's stats; pass to earn points
+  #[account(mut, seeds = [b"user_stats", user.key().as_ref()], bump = user_stats.bump)]
+  pub user_stats: Option<Account<'info, UserStats>>,
+
+  /// The trader's competition entry; pass to count the trade
+  #[account(
+    mut,
+    seeds = [b"competition_entry", competition_entry.competition.as_ref(), user.key().as_ref()],
+    bump = competition_entry.bump
+  )]
+  pub competition_entry: Option<Account<'info, CompetitionEntry>>,
+
+  /// Required once the market's stats account is initialized
+  #[account(mut, seeds = [b"market_stats", market.key().as_ref()], bump = market_stats.bump)]
+  pub market_stats: Option<Account<'info, MarketStats>>,
+
+  #[account(mut, seeds = [b"protocol_stats"], bump = protocol_stats.bump)]
+  pub protocol_stats: Option<Account<'info, ProtocolStats>>,
+
+  /// Required once the market's candles are initialized
+  #[account(mut, seeds = [b"price_candles", market.key().as_ref()], bump = price_candles.load()?.bump)]
+  pub price_candles: Option<AccountLoader<'info, PriceCandles>>,
+
+  /// Required once the market's trade tape is initialized
+  #[account(mut, seeds = [b"trade_tape", market.key().as_ref()], bump = trade_tape.load()?.bump)]
+  pub trade_tape: Option<AccountLoader<'info, TradeTape>>,
+
+  /// Required once the market's price feed is initialized
+  #[account(mut, seeds = [b"price_feed", market.key().as_ref()], bump = price_feed.load()?.bump)]
+  pub price_feed: Option<AccountLoader<'info, PriceFeed>>,
+
+  /// Required while the market is allowlist-gated
+  #[account(
+    seeds = [b"allowlist", market.key().as_ref(), user.key().as_ref()],
+    bump = allowlist_entry.bump
+  )]
+  pub allowlist_entry: Option<Account<'info, AllowlistEntry>>,
+
+  /// Both required while the market is permit-gated; the permit's ed25519
+  /// verification must precede this instruction
+  #[account(seeds = [b"permit_gate", market.key().as_ref()], bump = permit_gate.bump)]
+  pub permit_gate: Option<Account<'info, PermitGate>>,
+  /// CHECK: instructions sysvar
+  #[account(address = ix_sysvar::ID)]
+  pub instructions: Option<UncheckedAccount<'info>>,
+
+  /// Both required while the market is token-gated
+  #[account(seeds = [b"token_gate", market.key().as_ref()], bump = token_gate.bump)]
+  pub token_gate: Option<Account<'info, TokenGate>>,
+  pub gate_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+  pub token_program: Interface<'info, TokenInterface>,
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(quote: RfqQuote)]
+pub struct SettleRfq<'info> {
+  #[account(mut, address = quote.market)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(
+    init,
+    payer = payer,
+    space = 8 + QuoteFill::INIT_SPACE,
+    seeds = [b"rfq_fill", maker.key().as_ref(), &quote.nonce.to_le_bytes()],
+    bump
+  )]
+  pub quote_fill: Account<'info, QuoteFill>,
+
+  #[account(
+    init_if_needed,
+    payer = payer,
+    space = 8 + PositionV2::INIT_SPACE,
+    seeds = [b"position_v2", market.key().as_ref(), maker.key().as_ref()],
+    bump
+  )]
+  pub maker_position: Account<'info, PositionV2>,
+
+  #[account(
+    init_if_needed,
+    payer = payer,
+    space = 8 + PositionV2::INIT_SPACE,
+    seeds = [b"position_v2", market.key().as_ref(), taker.key().as_ref()],
+    bump
+  )]
+  pub taker_position: Account<'info, PositionV2>,
+
+  #[account(
+    mut,
+    seeds = [b"trading_account", maker.key().as_ref(), collateral_mint.key().as_ref()],
+    bump = maker_trading_account.bump
+  )]
+  pub maker_trading_account: Account<'info, TradingAccount>,
+
+  #[account(
+    mut,
+    seeds = [b"trading_account", taker.key().as_ref(), collateral_mint.key().as_ref()],
+    bump = taker_trading_account.bump
+  )]
+  pub taker_trading_account: Account<'info, TradingAccount>,
+
+  /// CHECK: quote signer; authorizes via the ed25519 instruction
+  #[account(
+    address = quote.maker,
+    constraint = maker.key() != taker.key() @ PredictionError::SelfTrade
+  )]
+  pub maker: UncheckedAccount<'info>,
+
+  #[account(mut)]
+  pub taker: Signer<'info>,
+
+  /// Pays rent for accounts created here (the taker or a relayer)
+  #[account(mut)]
+  pub payer: Signer<'info>,
+
+  #[account(address = market.load()?.collateral_mint)]
+  pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+  /// CHECK: instructions sysvar, used to find the ed25519 verification
+  #[account(address = ix_sysvar::ID)]
+  pub instructions: UncheckedAccount<'info>,
+
+  /// Both required while the market is allowlist-gated
+  #[account(
+    seeds = [b"allowlist", market.key().as_ref(), maker.key().as_ref()],
+    bump = maker_allowlist_entry.bump
+  )]
+  pub maker_allowlist_entry: Option<Account<'info, AllowlistEntry>>,
+  #[account(
+    seeds = [b"allowlist", market.key().as_ref(), taker.key().as_ref()],
+    bump = taker_allowlist_entry.bump
+  )]
+  pub taker_allowlist_entry: Option<Account<'info, AllowlistEntry>>,
+
+  /// Required while the market is permit-gated, with a permit for each
+  /// party verified earlier in the transaction
+  #[account(seeds = [b"permit_gate", market.key().as_ref()], bump = permit_gate.bump)]
+  pub permit_gate: Option<Account<'info, PermitGate>>,
+
+  /// Required while the market is token-gated, with the buyer's gate token
+  /// account
+  #[account(seeds = [b"token_gate", market.key().as_ref()], bump = token_gate.bump)]
+  pub token_gate: Option<Account<'info, TokenGate>>,
+  pub maker_gate_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+  pub taker_gate_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(args: CreateOfferArgs)]
+pub struct CreateOffer<'info> {
+  #[account(
+    init,
+    payer = payer,
+    space = 8 + Offer::INIT_SPACE,
+    seeds = [b"otc_offer", maker.key().as_ref(), &args.offer_id.to_le_bytes()],
+    bump
+  )]
+  pub offer: Account<'info, Offer>,
+
+  #[account(mut)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(
+    mut,
+    seeds = [b"position_v2", market.key().as_ref(), maker.key().as_ref()],
+    bump = maker_position.bump
+  )]
+  pub maker_position: Account<'info, PositionV2>,
+
+  #[account(mut)]
+  pub maker: Signer<'info>,
+
+  /// Pays rent for accounts created here (the maker or a relayer)
+  #[account(mut)]
+  pub payer: Signer<'info>,
+
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptOffer<'info> {
+  #[account(mut, close = maker, has_one = maker, has_one = market)]
+  pub offer: Account<'info, Offer>,
+
+  #[account(mut)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(
+    init_if_needed,
+    payer = payer,
+    space = 8 + PositionV2::INIT_SPACE,
+    seeds = [b"position_v2", market.key().as_ref(), taker.key().as_ref()],
+    bump
+  )]
+  pub taker_position: Account<'info, PositionV2>,
+
+  /// CHECK: offer maker; receives the payment and the offer rent
+  #[account(mut)]
+  pub maker: UncheckedAccount<'info>,
+
+  #[account(mut)]
+  pub taker: Signer<'info>,
+
+  /// Pays rent for accounts created here (the taker or a relayer)
+  #[account(mut)]
+  pub payer: Signer<'info>,
+
+  #[account(address = market.load()?.collateral_mint)]
+  pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+  /// Not needed for native SOL markets (paid in lamports)
+  #[account(
+    mut,
+    constraint = taker_collateral_ata.mint == collateral_mint.key(),
+    constraint = taker_collateral_ata.owner == taker.key(),
+  )]
+  pub taker_collateral_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+
+  #[account(
+    mut,
+    constraint = maker_collateral_ata.mint == collateral_mint.key(),
+    constraint = maker_collateral_ata.owner == maker.key(),
+  )]
+  pub maker_collateral_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+
+  /// Required while the market is allowlist-gated
+  #[account(
+    seeds = [b"allowlist", market.key().as_ref(), taker.key().as_ref()],
+    bump = allowlist_entry.bump
+  )]
+  pub allowlist_entry: Option<Account<'info, AllowlistEntry>>,
+
+  /// Both required while the market is permit-gated; the permit's ed25519
+  /// verification must precede this instruction
+  #[account(seeds = [b"permit_gate", market.key().as_ref()], bump = permit_gate.bump)]
+  pub permit_gate: Option<Account<'info, PermitGate>>,
+  /// CHECK: instructions sysvar
+  #[account(address = ix_sysvar::ID)]
+  pub instructions: Option<UncheckedAccount<'info>>,
+
+  /// Both required while the market is token-gated
+  #[account(seeds = [b"token_gate", market.key().as_ref()], bump = token_gate.bump)]
+  pub token_gate: Option<Account<'info, TokenGate>>,
+  pub gate_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+  pub token_program: Interface<'info, TokenInterface>,
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptShareOffer<'info> {
+  #[account(mut, close = maker, has_one = maker, has_one = market, has_one = ask_market)]
+  pub offer: Account<'info, Offer>,
+
+  #[account(mut)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(mut)]
+  pub ask_market: AccountLoader<'info, MarketV3>,
+
+  #[account(
+    init_if_needed,
+    payer = payer,
+    space = 8 + PositionV2::INIT_SPACE,
+    seeds = [b"position_v2", market.key().as_ref(), taker.key().as_ref()],
+    bump
+  )]
+  pub taker_position: Account<'info, PositionV2>,
+
+  #[account(
+    mut,
+    seeds = [b"position_v2", ask_market.key().as_ref(), taker.key().as_ref()],
+    bump = taker_ask_position.bump
+  )]
+  pub taker_ask_position: Account<'info, PositionV2>,
+
+  #[account(
+    init_if_needed,
+    payer = payer,
+    space = 8 + PositionV2::INIT_SPACE,
+    seeds = [b"position_v2", ask_market.key().as_ref(), maker.key().as_ref()],
+    bump
+  )]
+  pub maker_ask_position: Account<'info, PositionV2>,
+
+  /// CHECK: offer maker; receives the offer rent
+  #[account(mut, constraint = maker.key() != taker.key() @ PredictionError::SelfTrade)]
+  pub maker: UncheckedAccount<'info>,
+
+  #[account(mut)]
+  pub taker: Signer<'info>,
+
+  /// Pays rent for accounts created here (the taker or a relayer)
+  #[account(mut)]
+  pub payer: Signer<'info>,
+
+  /// Required while `market` is allowlist-gated
+  #[account(
+    seeds = [b"allowlist", market.key().as_ref(), taker.key().as_ref()],
+    bump = allowlist_entry.bump
+  )]
+  pub allowlist_entry: Option<Account<'info, AllowlistEntry>>,
+  /// Required while `ask_market` is allowlist-gated
+  #[account(
+    seeds = [b"allowlist", ask_market.key().as_ref(), maker.key().as_ref()],
+    bump = maker_allowlist_entry.bump
+  )]
+  pub maker_allowlist_entry: Option<Account<'info, AllowlistEntry>>,
+
+  /// Required while `market` / `ask_market` is permit-gated, with the
+  /// instructions sysvar and a permit for the taker / maker verified
+  /// earlier in the transaction
+  #[account(seeds = [b"permit_gate", market.key().as_ref()], bump = permit_gate.bump)]
+  pub permit_gate: Option<Account<'info, PermitGate>>,
+  #[account(seeds = [b"permit_gate", ask_market.key().as_ref()], bump = ask_permit_gate.bump)]
+  pub ask_permit_gate: Option<Account<'info, PermitGate>>,
+  /// CHECK: instructions sysvar
+  #[account(address = ix_sysvar::ID)]
+  pub instructions: Option<UncheckedAccount<'info>>,
+
+  /// Required while `market` is token-gated, with the taker's gate token
+  /// account
+  #[account(seeds = [b"token_gate", market.key().as_ref()], bump = token_gate.bump)]
+  pub token_gate: Option<Account<'info, TokenGate>>,
+  pub gate_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+  /// Required while `ask_market` is token-gated, with the maker's gate
+  /// token account
+  #[account(seeds = [b"token_gate", ask_market.key().as_ref()], bump = ask_token_gate.bump)]
+  pub ask_token_gate: Option<Account<'info, TokenGate>>,
+  pub maker_gate_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CancelOffer<'info> {
+  #[account(mut, close = maker, has_one = maker)]
+  pub offer: Account<'info, Offer>,
+
+  #[account(
+    mut,
+    seeds = [b"position_v2", offer.market.as_ref(), maker.key().as_ref()],
+    bump = maker_position.bump
+  )]
+  pub maker_position: Account<'info, PositionV2>,
+
+  #[account(mut)]
+  pub maker: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitPortfolio<'info> {
+  #[account(
+    init,
+    payer = payer,
+    space = 8 + Portfolio::INIT_SPACE,
+    seeds = [b"portfolio", owner.key().as_ref()],
+    bump
+  )]
+  pub portfolio: Account<'info, Portfolio>,
+
+  #[account(mut)]
+  pub owner: Signer<'info>,
+
+  /// Pays rent for accounts created here (the owner or a relayer)
+  #[account(mut)]
+  pub payer: Signer<'info>,
+
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct TrackMarket<'info> {
+  #[account(
+    mut,
+    seeds = [b"portfolio", owner.key().as_ref()],
+    bump = portfolio.bump
+  )]
+  pub portfolio: Account<'info, Portfolio>,
+
+  #[account(
+    constraint = position.owner == owner.key() @ PredictionError::PositionOwnerMismatch
+  )]
+  pub position: Account<'info, PositionV2>,
+
+  pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdatePortfolio<'info> {
+  #[account(
+    mut,
+    seeds = [b"portfolio", owner.key().as_ref()],
+    bump = portfolio.bump
+  )]
+  pub portfolio: Account<'info, Portfolio>,
+
+  pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimAll<'info> {
+  #[account(
+    mut,
+    seeds = [b"portfolio", owner.key().as_ref()],
+    bump = portfolio.bump
+  )]
+  pub portfolio: Account<'info, Portfolio>,
+
+  pub owner: Signer<'info>,
+
+  pub token_program: Interface<'info, TokenInterface>,
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DistributePayoutsBatch<'info> {
+  #[account(mut)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(
+    mut,
+    seeds = [b"vault_v2", market.key().as_ref()],
+    bump = market.load()?.vault_bump
+  )]
+  pub vault: InterfaceAccount<'info, TokenAccount>,
+
+  /// CHECK: PDA that signs for vault transfers
+  #[account(
+    seeds = [b"vault_auth_v2", market.key().as_ref()],
+    bump = market.load()?.vault_auth_bump
+  )]
+  pub vault_authority: UncheckedAccount<'info>,
+
+  #[account(address = market.load()?.collateral_mint)]
+  pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+  /// Only needed when the creator bond was slashed (pays the winners' share)
+  #[account(
+    mut,
+    seeds = [b"creator_bond", market.key().as_ref()],
+    bump = creator_bond.bump,
+  )]
+  pub creator_bond: Option<Account<'info, CreatorBond>>,
+
+  #[account(mut, seeds = [b"protocol_stats"], bump = protocol_stats.bump)]
+  pub protocol_stats: Option<Account<'info, ProtocolStats>>,
+
+  pub keeper: Signer<'info>,
+
+  pub token_program: Interface<'info, TokenInterface>,
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawResidualLiquidity<'info> {
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(
+    mut,
+    seeds = [b"vault_v2", market.key().as_ref()],
+    bump = market.load()?.vault_bump
+  )]
+  pub vault: InterfaceAccount<'info, TokenAccount>,
+
+  /// CHECK: PDA that signs for vault transfers
+  #[account(
+    seeds = [b"vault_auth_v2", market.key().as_ref()],
+    bump = market.load()?.vault_auth_bump
+  )]
+  pub vault_authority: UncheckedAccount<'info>,
+
+  #[account(
+    mut,
+    seeds = [b"lp_position", market.key().as_ref(), user.key().as_ref()],
+    bump = lp_position.bump
+  )]
+  pub lp_position: Account<'info, LpPosition>,
+
+  /// Required once the market has liquidity mining
+  #[account(mut, seeds = [b"lp_rewards", market.key().as_ref()], bump = lp_rewards.bump)]
+  pub lp_rewards: Option<Account<'info, LpRewards>>,
+
+  #[account(mut)]
+  pub user: Signer<'info>,
+
+  /// Pays rent for accounts created here (the user or a relayer)
+  #[account(mut)]
+  pub payer: Signer<'info>,
+
+  #[account(address = market.load()?.collateral_mint)]
+  pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+  /// Not needed for native SOL markets (payout is unwrapped via `wsol_unwrap`)
+  #[account(
+    mut,
+    constraint = user_collateral_ata.mint == market.load()?.collateral_mint,
+    constraint = user_collateral_ata.owner == user.key(),
+  )]
+  pub user_collateral_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+
+  /// Temporary wSOL account, created and closed within the instruction (native SOL markets only)
+  #[account(
+    init,
+    payer = payer,
+    seeds = [b"wsol_unwrap", market.key().as_ref(), user.key().as_ref()],
+    bump,
+    token::mint = collateral_mint,
+    token::authority = vault_authority,
+    token::token_program = token_program,
+  )]
+  pub wsol_unwrap: Option<InterfaceAccount<'info, TokenAccount>>,
+
+  pub token_program: Interface<'info, TokenInterface>,
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SplitShares<'info> {
+  #[account(mut)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(
+    mut,
+    seeds = [b"vault_v2", market.key().as_ref()],
+    bump = market.load()?.vault_bump
+  )]
+  pub vault: InterfaceAccount<'info, TokenAccount>,
+
+  #[account(
+    init_if_needed,
+    payer = payer,
+    space = 8 + PositionV2::INIT_SPACE,
+    seeds = [b"position_v2", market.key().as_ref(), user.key().as_ref()],
+    bump
+  )]
+  pub position: Account<'info, PositionV2>,
+
+  #[account(mut)]
+  pub user: Signer<'info>,
+
+  /// Pays rent for accounts created here (the user or a relayer)
+  #[account(mut)]
+  pub payer: Signer<'info>,
+
+  #[account(address = market.load()?.collateral_mint)]
+  pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+  /// Not needed for native SOL markets (lamports are wrapped into the vault directly)
+  #[account(
+    mut,
+    constraint = user_collateral_ata.mint == market.load()?.collateral_mint,
+    constraint = user_collateral_ata.owner == user.key(),
+  )]
+  pub user_collateral_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+
+  pub token_program: Interface<'info, TokenInterface>,
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MergeShares<'info> {
+  #[account(mut)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(
+    mut,
+    seeds = [b"vault_v2", market.key().as_ref()],
+    bump = market.load()?.vault_bump
+  )]
+  pub vault: InterfaceAccount<'info, TokenAccount>,
+
+  /// CHECK: PDA that signs for vault transfers
+  #[account(
+    seeds = [b"vault_auth_v2", market.key().as_ref()],
+    bump = market.load()?.vault_auth_bump
+  )]
+  pub vault_authority: UncheckedAccount<'info>,
+
+  #[account(
+    mut,
+    seeds = [b"position_v2", market.key().as_ref(), user.key().as_ref()],
+    bump = position.bump
+  )]
+  pub position: Account<'info, PositionV2>,
+
+  #[account(mut)]
+  pub user: Signer<'info>,
+
+  /// Pays rent for accounts created here (the user or a relayer)
+  #[account(mut)]
+  pub payer: Signer<'info>,
+
+  #[account(address = market.load()?.collateral_mint)]
+  pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+  /// Not needed for native SOL markets (payout is unwrapped via `wsol_unwrap`)
+  #[account(
+    mut,
+    constraint = user_collateral_ata.mint == market.load()?.collateral_mint,
+    constraint = user_collateral_ata.owner == user.key(),
+  )]
+  pub user_collateral_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+
+  /// Temporary wSOL account, created and closed within the instruction (native SOL markets only)
+  #[account(
+    init,
+    payer = payer,
+    seeds = [b"wsol_unwrap", market.key().as_ref(), user.key().as_ref()],
+    bump,
+    token::mint = collateral_mint,
+    token::authority = vault_authority,
+    token::token_program = token_program,
+  )]
+  pub wsol_unwrap: Option<InterfaceAccount<'info, TokenAccount>>,
+
+  pub token_program: Interface<'info, TokenInterface>,
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SellShares<'info> {
+  #[account(seeds = [b"config"], bump = config.bump)]
+  pub config: Account<'info, ProtocolConfig>,
+
+  #[account(mut)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(
+    mut,
+    seeds = [b"vault_v2", market.key().as_ref()],
+    bump = market.load()?.vault_bump
+  )]
+  pub vault: InterfaceAccount<'info, TokenAccount>,
+
+  /// CHECK: PDA that signs for vault transfers
+  #[account(
+    seeds = [b"vault_auth_v2", market.key().as_ref()],
+    bump = market.load()?.vault_auth_bump
+  )]
+  pub vault_authority: UncheckedAccount<'info>,
+
+  #[account(
+    mut,
+    seeds = [b"position_v2", market.key().as_ref(), user.key().as_ref()],
+    bump = position.bump
+  )]
+  pub position: Account<'info, PositionV2>,
+
+  #[account(mut)]
+  pub user: Signer<'info>,
+
+  /// Pays rent for accounts created here (the user or a relayer)
+  #[account(mut)]
+  pub payer: Signer<'info>,
+
+  #[account(address = market.load()?.collateral_mint)]
+  pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+  /// Not needed for native SOL markets (payout is unwrapped via `wsol_unwrap`)
+  #[account(
+    mut,
+    constraint = user_collateral_ata.mint == market.load()?.collateral_mint,
+    constraint = user_collateral_ata.owner == user.key(),
+  )]
+  pub user_collateral_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+
+  /// Temporary wSOL account, created and closed within the instruction (native SOL markets only)
+  #[account(
+    init,
+    payer = payer,
+    seeds = [b"wsol_unwrap", market.key().as_ref(), user.key().as_ref()],
+    bump,
+    token::mint = collateral_mint,
+    token::authority = vault_authority,
+    token::token_program = token_program,
+  )]
+  pub wsol_unwrap: Option<InterfaceAccount<'info, TokenAccount>>,
+
+  /// Designated makers; pass to accrue the trader's maker rebate
+  #[account(mut, seeds = [b"market_makers", market.key().as_ref()], bump = market_makers.bump)]
+  pub market_makers: Option<Account<'info, MarketMakers>>,
+
+  /// The trader's stats; pass to earn points
+  #[account(mut, seeds = [b"user_stats", user.key().as_ref()], bump = user_stats.bump)]
+  pub user_stats: Option<Account<'info, UserStats>>,
+
+  /// The trader's competition entry; pass to count the trade
+  #[account(
+    mut,
+    seeds = [b"competition_entry", competition_entry.competition.as_ref(), user.key().as_ref()],
+    bump = competition_entry.bump
+  )]
+  pub competition_entry: Option<Account<'info, CompetitionEntry>>,
+
+  /// Required once the market's stats account is initialized
+  #[account(mut, seeds = [b"market_stats", market.key().as_ref()], bump = market_stats.bump)]
+  pub market_stats: Option<Account<'info, MarketStats>>,
+
+  #[account(mut, seeds = [b"protocol_stats"], bump = protocol_stats.bump)]
+  pub protocol_stats: Option<Account<'info, ProtocolStats>>,
+
+  /// Required once the market's candles are initialized
+  #[account(mut, seeds = [b"price_candles", market.key().as_ref()], bump = price_candles.load()?.bump)]
+  pub price_candles: Option<AccountLoader<'info, PriceCandles>>,
+
+  /// Required once the market's trade tape is initialized
+  #[account(mut, seeds = [b"trade_tape", market.key().as_ref()], bump = trade_tape.load()?.bump)]
+  pub trade_tape: Option<AccountLoader<'info, TradeTape>>,
+
+  /// Required once the market's price feed is initialized
+  #[account(mut, seeds = [b"price_feed", market.key().as_ref()], bump = price_feed.load()?.bump)]
+  pub price_feed: Option<AccountLoader<'info, PriceFeed>>,
+
+  /// Required while the market is allowlist-gated
+  #[account(
+    seeds = [b"allowlist", market.key().as_ref(), user.key().as_ref()],
+    bump = allowlist_entry.bump
+  )]
+  pub allowlist_entry: Option<Account<'info, AllowlistEntry>>,
+
+  /// Both required while the market is permit-gated; the permit's ed25519
+  /// verification must precede this instruction
+  #[account(seeds = [b"permit_gate", market.key().as_ref()], bump = permit_gate.bump)]
+  pub permit_gate: Option<Account<'info, PermitGate>>,
+  /// CHECK: instructions sysvar
+  #[account(address = ix_sysvar::ID)]
+  pub instructions: Option<UncheckedAccount<'info>>,
+
+  pub token_program: Interface<'info, TokenInterface>,
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ResolveMarketV2<'info> {
+  #[account(seeds = [b"config"], bump = config.bump)]
+  pub config: Account<'info, ProtocolConfig>,
+
+  #[account(mut)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  // Checked against the internal balance before snapshotting
+  #[account(
+    seeds = [b"vault_v2", market.key().as_ref()],
+    bump = market.load()?.vault_bump
+  )]
+  pub vault: InterfaceAccount<'info, TokenAccount>,
+
+  // Required for registered markets so the registry status stays current
+  #[account(
+    mut,
+    seeds = [b"registry_page".as_ref(), &(market.load()?.registry_index / REGISTRY_PAGE_SIZE).to_le_bytes()],
+    bump = registry_page.bump
+  )]
+  pub registry_page: Option<Account<'info, RegistryPage>>,
+
+  /// CHECK: required for LST markets; address and layout checked against the market
+  #[account(address = market.load()?.lst_stake_pool)]
+  pub lst_stake_pool: Option<UncheckedAccount<'info>>,
+
+  /// Required for conditional markets; must be resolved and past its dispute window
+  #[account(address = market.load()?.parent_market)]
+  pub parent_market: Option<AccountLoader<'info, MarketV3>>,
+
+  pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ReleaseBond<'info> {
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(
+    mut,
+    seeds = [b"creator_bond", market.key().as_ref()],
+    bump = creator_bond.bump,
+    has_one = market,
+    has_one = creator @ PredictionError::Unauthorized,
+    close = creator
+  )]
+  pub creator_bond: Account<'info, CreatorBond>,
+
+  /// CHECK: bond refund destination; must match `creator_bond.creator`
+  #[account(mut)]
+  pub creator: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct DisputeResolution<'info> {
+  #[account(seeds = [b"config"], bump = config.bump)]
+  pub config: Account<'info, ProtocolConfig>,
+
+  #[account(mut)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(
+    init,
+    payer = disputer,
+    space = 8 + Dispute::INIT_SPACE,
+    seeds = [b"dispute", market.key().as_ref()],
+    bump
+  )]
+  pub dispute: Account<'info, Dispute>,
+
+  #[account(mut)]
+  pub disputer: Signer<'info>,
+
+  pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SettleDispute<'info> {
+  #[account(
+    seeds = [b"config"],
+    bump = config.bump,
+    has_one = admin @ PredictionError::Unauthorized,
+    has_one = treasury,
+  )]
+  pub config: Account<'info, ProtocolConfig>,
+
+  #[account(mut)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(
+    mut,
+    seeds = [b"dispute", market.key().as_ref()],
+    bump = dispute.bump,
+    has_one = market,
+    has_one = disputer,
+  )]
+  pub dispute: Account<'info, Dispute>,
+
+  #[account(
+    mut,
+    seeds = [b"creator_bond", market.key().as_ref()],
+    bump = creator_bond.bump,
+    has_one = market,
+  )]
+  pub creator_bond: Account<'info, CreatorBond>,
+
+  /// CHECK: must match `dispute.disputer`; receives bond refund and slash share
+  #[account(mut)]
+  pub disputer: UncheckedAccount<'info>,
+
+  /// CHECK: receives a rejected dispute's bond; must match `config.treasury`
+  #[account(mut)]
+  pub treasury: UncheckedAccount<'info>,
+
+  pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct VoidMarket<'info> {
+  #[account(
+    seeds = [b"config"],
+    bump = config.bump,
+    has_one = admin @ PredictionError::Unauthorized,
+    has_one = treasury,
+  )]
+  pub config: Account<'info, ProtocolConfig>,
+
+  #[account(mut)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(
+    mut,
+    seeds = [b"creator_bond", market.key().as_ref()],
+    bump = creator_bond.bump,
+    has_one = market,
+    close = treasury
+  )]
+  pub creator_bond: Account<'info, CreatorBond>,
+
+  #[account(
+    mut,
+    seeds = [b"registry_page".as_ref(), &(market.load()?.registry_index / REGISTRY_PAGE_SIZE).to_le_bytes()],
+    bump = registry_page.bump
+  )]
+  pub registry_page: Option<Account<'info, RegistryPage>>,
+
+  /// CHECK: receives the forfeited bond; must match `config.treasury`
+  #[account(mut)]
+  pub treasury: UncheckedAccount<'info>,
+
+  pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ViewMarket<'info> {
+  pub market: AccountLoader<'info, MarketV3>,
+}
+
+#[derive(Accounts)]
+pub struct AssertSolvency<'info> {
+  #[account(mut)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(
+    seeds = [b"vault_v2", market.key().as_ref()],
+    bump = market.load()?.vault_bump
+  )]
+  pub vault: InterfaceAccount<'info, TokenAccount>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimWinningsV2<'info> {
+  #[account(mut)]
+  pub market: AccountLoader<'info, MarketV3>,
+
+  #[account(
+    mut,
+    seeds = [b"vault_v2", market.key().as_ref()],
+    bump = market.load()?.vault_bump
+  )]
+  pub vault: InterfaceAccount<'info, TokenAccount>,
+
+  /// CHECK: PDA that signs for vault transfers
+  #[account(
+    seeds = [b"vault_auth_v2", market.key().as_ref()],
+    bump = market.load()?.vault_auth_bump
+  )]
+  pub vault_authority: UncheckedAccount<'info>,
+
+  #[account(
+    mut,
+    seeds = [b"position_v2", market.key().as_ref(), user.key().as_ref()],
+    bump = position.bump
+  )]
+  pub position: Account<'info, PositionV2>,
+
+  /// CHECK: position owner; bound to the position via its PDA seeds and receives the payout
+  #[account(mut)]
+  pub user: UncheckedAccount<'info>,
+
+  /// Pays rent for the owner's ATA if it has to be created (owner or cranker)
+  #[account(mut)]
+  pub payer: Signer<'info>,
+
+  #[account(address = market.load()?.collateral_mint)]
+  pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+  /// Not needed for native SOL markets (payout is unwrapped via `wsol_unwrap`)
+  #[account(
+    init_if_needed,
+    payer = payer,
+    associated_token::mint = collateral_mint,
+    associated_token::authority = user,
+    associated_token::token_program = token_program,
+  )]
+  pub user_collateral_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+
+  /// Temporary wSOL account, created and closed within the instruction (native SOL markets only)
+  #[account(
+    init,
+    payer = payer,
+    seeds = [b"wsol_unwrap", market.key().as_ref(), user.key().as_ref()],
+    bump,
+    token::mint = collateral_mint,
+    token::authority = vault_authority,
+    token::token_program = token_program,
+  )]
+  pub wsol_unwrap: Option<InterfaceAccount<'info, TokenAccount>>,
+
+  /// Only needed when the creator bond was slashed (pays the winners' share)
+  #[account(
+    mut,
+    seeds = [b"creator_bond", market.key().as_ref()],
+    bump = creator_bond.bump,
+  )]
+  pub creator_bond: Option<Account<'info, CreatorBond>>,
+
+  /// Required if the claim was escrowed during the correction window
+  #[account(
+    mut,
+    close = user,
+    seeds = [b"claim_escrow", market.key().as_ref(), user.key().as_ref()],
+    bump = claim_escrow.bump,
+  )]
+  pub claim_escrow: Option<Account<'info, ClaimEscrow>>,
+
+  #[account(mut, seeds = [b"user_stats", user.key().as_ref()], bump = user_stats.bump)]
+  pub user_stats: Option<Account<'info, UserStats>>,
+
+  #[account(mut, seeds = [b"protocol_stats"], bump = protocol_stats.bump)]
+  pub protocol_stats: Option<Account<'info, ProtocolStats>>,
+
+  pub token_program: Interface<'info, TokenInterface>,
+  pub associated_token_program: Program<'info, AssociatedToken>,
+  pub system_program: Program<'info, System>,
+}
+
+// ----------------------------
+// CPMM Math (swap-style)
+// ----------------------------
+
+// Swap math lives in `prediction_quote` so off-chain quotes round exactly
+// like the program; each returns (new yes_pool, new no_pool, amount out).
+
+fn cpmm_buy_yes(yes_pool: u64, no_pool: u64, net_in: u64) -> Result<(u64, u64, u64)> {
+  quote::cpmm_buy_yes(yes_pool, no_pool, net_in).map_err(quote_error)
+}
+
+fn cpmm_buy_no(yes_pool: u64, no_pool: u64, net_in: u64) -> Result<(u64, u64, u64)> {
+  quote::cpmm_buy_no(yes_pool, no_pool, net_in).map_err(quote_error)
+}
+
+fn cpmm_sell_yes(yes_pool: u64, no_pool: u64, shares_in: u64) -> Result<(u64, u64, u64)> {
+  quote::cpmm_sell_yes(yes_pool, no_pool, shares_in).map_err(quote_error)
+}
+
+fn cpmm_sell_no(yes_pool: u64, no_pool: u64, shares_in: u64) -> Result<(u64, u64, u64)> {
+  quote::cpmm_sell_no(yes_pool, no_pool, shares_in).map_err(quote_error)
+}
 
-    Ok(())
+fn quote_error(e: QuoteError) -> Error {
+  match e {
+    QuoteError::InvalidLiquidity => PredictionError::InvalidLiquidity,
+    QuoteError::InvalidOutcome => PredictionError::InvalidOutcome,
+    QuoteError::FeeCapExceeded => PredictionError::FeeCapExceeded,
+    QuoteError::MathOverflow => PredictionError::MathOverflow,
   }
+  .into()
 }
 
 // ----------------------------
-// Args / Enums
+// Trade execution
 // ----------------------------
-#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
-pub struct CreateMarketCpmmArgs {
-  pub market_id: u64,
-  pub question: String,
-  pub end_time: i64,
-  pub initial_liquidity: u64,
+
+/// Bring the phase up to date and check the market accepts AMM trades.
+fn check_tradable(market: &mut MarketV3, now: i64) -> Result<()> {
+  market.refresh_phase(now);
+  require!(
+    market.status != MarketStatus::PreMarket as u8,
+    PredictionError::MarketNotStarted
+  );
+  require!(
+    market.status == MarketStatus::Open as u8,
+    PredictionError::InvalidMarketStatus
+  );
+  require!(market.paused == 0, PredictionError::MarketPaused);
+  require!(now < market.end_time, PredictionError::MarketExpired);
+  Ok(())
 }
 
-#[repr(u8)]
-pub enum MarketStatus {
-  Open = 0,
-  Resolved = 1,
-  Cancelled = 2,
+/// Accounts besides the market and position that an AMM trade by `trader`
+/// is checked against and recorded into. Paths that can't carry a gate or
+/// market recorder pass `None`, which only trades while the market has it
+/// off; the trader's own recorders are opt-in.
+struct TradeAccounts<'a, 'info> {
+  config: &'a ProtocolConfig,
+  trader: Pubkey,
+  new_trader: bool, // first trade of this position, for the market's trader count
+  // Shares only pass through a stand-in position (swaps, compressed buys):
+  // it keeps no cost basis and a sell never counts as closing it out
+  transient: bool,
+  allowlist_entry: Option<&'a AllowlistEntry>,
+  permit_gate: Option<&'a PermitGate>,
+  instructions: Option<&'a UncheckedAccount<'info>>,
+  token_gate: Option<&'a TokenGate>,
+  gate_token_account: Option<&'a TokenAccount>,
+  market_makers: Option<&'a mut MarketMakers>,
+  user_stats: Option<&'a mut UserStats>,
+  competition_entry: Option<&'a mut CompetitionEntry>,
+  market_stats: Option<&'a mut MarketStats>,
+  protocol_stats: Option<&'a mut ProtocolStats>,
+  price_candles: Option<&'a AccountLoader<'info, PriceCandles>>,
+  trade_tape: Option<&'a AccountLoader<'info, TradeTape>>,
+  price_feed: Option<&'a AccountLoader<'info, PriceFeed>>,
 }
 
-#[account]
-#[derive(InitSpace)]
-pub struct MarketV2 {
-  pub market_id: u64,
-  pub authority: Pubkey,
-  #[max_len(256)]
-  pub question: String,
-  pub collateral_mint: Pubkey,
-  pub vault: Pubkey,
-  pub end_time: i64,
-  pub status: u8,
-  pub winning_outcome: i8,
+/// Allowlist and trade-permit checks for the trader, plus the token gate on
+/// buys. Every AMM fill runs them in `execute_buy`/`execute_sell`.
+fn check_trade_gates(
+  market: &MarketV3,
+  market_key: Pubkey,
+  trade: &TradeAccounts,
+  buying: bool,
+  now: i64,
+) -> Result<()> {
+  let gates = PartyGates {
+    allowlist_entry: trade.allowlist_entry,
+    permit_gate: trade.permit_gate,
+    instructions: trade.instructions,
+    token_gate: trade.token_gate,
+    gate_token_account: trade.gate_token_account,
+  };
+  check_party_gates(market, market_key, trade.trader, &gates, buying, now)
+}
 
-  // Virtual reserves (collateral units)
-  pub yes_pool: u64,
-  pub no_pool: u64,
+/// One wallet's gate accounts in a market, for the paths that move shares
+/// between users (OTC offers, RFQ, the order book).
+struct PartyGates<'a, 'info> {
+  allowlist_entry: Option<&'a AllowlistEntry>,
+  permit_gate: Option<&'a PermitGate>,
+  instructions: Option<&'a UncheckedAccount<'info>>,
+  token_gate: Option<&'a TokenGate>,
+  gate_token_account: Option<&'a TokenAccount>,
+}
 
-  // Total outstanding shares
-  pub total_yes_shares: u64,
-  pub total_no_shares: u64,
+/// Allowlist and trade-permit checks for `wallet`, plus the token gate when
+/// it ends up holding shares.
+fn check_party_gates(
+  market: &MarketV3,
+  market_key: Pubkey,
+  wallet: Pubkey,
+  gates: &PartyGates,
+  buying: bool,
+  now: i64,
+) -> Result<()> {
+  check_allowlist(market, gates.allowlist_entry)?;
+  check_trade_permit(
+    market,
+    market_key,
+    gates.permit_gate,
+    gates.instructions,
+    wallet,
+    now,
+  )?;
+  if buying {
+    check_token_gate(market, gates.token_gate, gates.gate_token_account, wallet)?;
+  }
+  Ok(())
+}
 
-  // CLASSIC PRO-RATA snapshots
-  pub resolved_vault_balance: u64,
-  pub resolved_total_winning_shares: u64,
+/// Bookkeeping after every AMM fill: the trade-size minimum, the maker
+/// rebate, the trader's points, PnL and competition entry, then the market
+/// and protocol stats, candles, price feed and tape.
+#[allow(clippy::too_many_arguments)]
+fn record_fill(
+  market: &mut MarketV3,
+  market_key: Pubkey,
+  trade: &mut TradeAccounts,
+  side: u8,
+  outcome_index: u8,
+  collateral: u64,
+  shares: u64,
+  fee: u64,
+  closing: bool,
+  now: i64,
+) -> Result<()> {
+  trade.config.check_trade_size(collateral, shares, closing)?;
+
+  if let Some(makers) = trade.market_makers.as_deref_mut() {
+    accrue_mm_rebate(market, makers, trade.trader, collateral)?;
+  }
+  if let Some(stats) = trade.user_stats.as_deref_mut() {
+    stats.accrue_points(fee, now)?;
+    match side {
+      TAPE_BUY => stats.record_buy(market_key, outcome_index, shares, collateral)?,
+      _ => stats.record_sell(market_key, outcome_index, shares, collateral)?,
+    }
+  }
+  if let Some(entry) = trade.competition_entry.as_deref_mut() {
+    match side {
+      TAPE_BUY => entry.record_buy(market_key, outcome_index, shares, collateral, now)?,
+      _ => entry.record_sell(market_key, outcome_index, shares, collateral, now)?,
+    }
+  }
+
+  record_market_trade(
+    market,
+    trade.market_stats.as_deref_mut(),
+    collateral,
+    fee,
+    trade.new_trader,
+    now,
+  )?;
+  if let Some(stats) = trade.protocol_stats.as_deref_mut() {
+    stats.record_trade(collateral, fee)?;
+    stats.sync_tvl(market)?;
+  }
+  record_price_candle(market, trade.price_candles, now)?;
+  refresh_price_feed(market, trade.price_feed, now)?;
+  record_tape_trade(
+    market,
+    trade.trade_tape,
+    side,
+    outcome_index,
+    collateral,
+    shares,
+    now,
+  )
 }
 
-#[account]
-#[derive(InitSpace)]
-pub struct PositionV2 {
-  pub market: Pubkey,
-  pub owner: Pubkey,
-  pub yes_shares: u64,
-  pub no_shares: u64,
-  pub claimed: bool,
+/// Swap `received` collateral (already in the vault) for outcome shares and
+/// credit them to `position`. The fee is taken from the input.
+#[allow(clippy::too_many_arguments)]
+fn execute_buy(
+  market: &mut MarketV3,
+  market_key: Pubkey,
+  position: &mut PositionV2,
+  outcome_index: u8,
+  received: u64,
+  min_shares_out: u64,
+  now: i64,
+  trade: &mut TradeAccounts,
+) -> Result<u64> {
+  require!(outcome_index <= 1, PredictionError::InvalidOutcome);
+  check_trade_gates(market, market_key, trade, true, now)?;
+
+  // First taker sets the opening level of an auctioned market
+  if let Some(yes_price) = market.settle_opening_auction(now)? {
+    emit!(OpeningPriceSet {
+      market: market_key,
+      yes_price,
+    });
+  }
+
+  // Fee on input, charged on what the vault actually received
+  let (net_in, fee) = apply_fee_in(received, market.fee_bps(now))?;
+  market.accrue_fee(fee)?;
+
+  // CPMM buy using net_in
+  let (new_yes, new_no, shares_out) = match outcome_index {
+    0 => cpmm_buy_yes(market.yes_pool, market.no_pool, net_in)?,
+    1 => cpmm_buy_no(market.yes_pool, market.no_pool, net_in)?,
+    _ => return err!(PredictionError::InvalidOutcome),
+  };
+
+  require!(shares_out >= min_shares_out, PredictionError::SlippageExceeded);
+  require!(shares_out > 0, PredictionError::ZeroSharesOut);
+  let held = match outcome_index {
+    0 => position.yes_shares,
+    _ => position.no_shares,
+  };
+  require!(
+    market.max_position_shares == 0
+      || held.saturating_add(shares_out) <= market.max_position_shares,
+    PredictionError::PositionLimitExceeded
+  );
+
+  // Update reserves
+  market.yes_pool = new_yes;
+  market.no_pool = new_no;
+  market.trade_count = market
+    .trade_count
+    .checked_add(1)
+    .ok_or(PredictionError::MathOverflow)?;
+
+  match outcome_index {
+    0 => credit_shares(market, position, shares_out, 0)?,
+    _ => credit_shares(market, position, 0, shares_out)?,
+  }
+  if !trade.transient {
+    add_cost_basis(market, position, received)?;
+  }
+
+  record_fill(
+    market,
+    market_key,
+    trade,
+    TAPE_BUY,
+    outcome_index,
+    received,
+    shares_out,
+    fee,
+    false,
+    now,
+  )?;
+  Ok(shares_out)
+}
+
+/// Sell `shares_in` outcome shares from `position` back to the pool and
+/// return the collateral owed (after fee). The caller pays it out of the
+/// vault and debits `vault_balance`.
+#[allow(clippy::too_many_arguments)]
+fn execute_sell(
+  market: &mut MarketV3,
+  market_key: Pubkey,
+  position: &mut PositionV2,
+  outcome_index: u8,
+  shares_in: u64,
+  min_collateral_out: u64,
+  now: i64,
+  trade: &mut TradeAccounts,
+) -> Result<u64> {
+  require!(outcome_index <= 1, PredictionError::InvalidOutcome);
+  check_trade_gates(market, market_key, trade, false, now)?;
+  require!(shares_in > 0, PredictionError::ZeroAmount);
+  require!(
+    position.market == market_key,
+    PredictionError::PositionMarketMismatch
+  );
+  require!(!position.claimed, PredictionError::AlreadyClaimed);
+
+  // Ensure user has shares
+  match outcome_index {
+    0 => require!(
+      position.yes_shares >= shares_in,
+      PredictionError::InsufficientShares
+    ),
+    _ => require!(
+      position.no_shares >= shares_in,
+      PredictionError::InsufficientShares
+    ),
+  }
+
+  // First taker sets the opening level of an auctioned market
+  if let Some(yes_price) = market.settle_opening_auction(now)? {
+    emit!(OpeningPriceSet {
+      market: market_key,
+      yes_price,
+    });
+  }
+
+  // Compute gross collateral out by CPMM
+  let (new_yes, new_no, gross_out) = match outcome_index {
+    0 => cpmm_sell_yes(market.yes_pool, market.no_pool, shares_in)?,
+    1 => cpmm_sell_no(market.yes_pool, market.no_pool, shares_in)?,
+    _ => return err!(PredictionError::InvalidOutcome),
+  };
+
+  require!(gross_out > 0, PredictionError::ZeroAmount);
+
+  // Fee on output; user receives net_out
+  let (net_out, fee) = apply_fee_out(gross_out, market.fee_bps(now))?;
+  require!(net_out >= min_collateral_out, PredictionError::SlippageExceeded);
+
+  market.yes_pool = new_yes;
+  market.no_pool = new_no;
+  market.trade_count = market
+    .trade_count
+    .checked_add(1)
+    .ok_or(PredictionError::MathOverflow)?;
+
+  // Fee stays in the vault, tracked apart from pool backing
+  market.accrue_fee(fee)?;
+
+  // Burn shares from position and totals
+  match outcome_index {
+    0 => {
+      position.yes_shares -= shares_in;
+      market.total_yes_shares = market
+        .total_yes_shares
+        .checked_sub(shares_in)
+        .ok_or(PredictionError::MathOverflow)?;
+    }
+    _ => {
+      position.no_shares -= shares_in;
+      market.total_no_shares = market
+        .total_no_shares
+        .checked_sub(shares_in)
+        .ok_or(PredictionError::MathOverflow)?;
+    }
+  }
+  if !trade.transient {
+    reduce_cost_basis(market, position, net_out)?;
+  }
+
+  let closing = !trade.transient
+    && match outcome_index {
+      0 => position.yes_shares == 0,
+      _ => position.no_shares == 0,
+    };
+  record_fill(
+    market,
+    market_key,
+    trade,
+    TAPE_SELL,
+    outcome_index,
+    net_out,
+    shares_in,
+    fee,
+    closing,
+    now,
+  )?;
+  Ok(net_out)
+}
+
+/// Validate a winning claim, mark the position claimed and debit the payout
+/// from the market's vault balance. Returns (payout, winning shares); the
+/// caller transfers the collateral.
+fn settle_claim(
+  market: &mut MarketV3,
+  market_key: Pubkey,
+  position: &mut PositionV2,
+  owner: Pubkey,
+  now: i64,
+) -> Result<(u64, u64)> {
+  require!(
+    market.is_settled(),
+    PredictionError::MarketNotResolved
+  );
+  require!(now >= market.dispute_deadline, PredictionError::DisputeWindowOpen);
+  require!(now >= market.claims_open_at()?, PredictionError::ClaimDelayActive);
+
+  let winning = market.winning_outcome;
+  require!(winning >= 0, PredictionError::InvalidWinningOutcome);
+
+  require!(!position.claimed, PredictionError::AlreadyClaimed);
+  require!(market.unclaimed_swept == 0, PredictionError::ClaimWindowClosed);
+  require!(
+    position.market == market_key,
+    PredictionError::PositionMarketMismatch
+  );
+  require!(position.owner == owner, PredictionError::PositionOwnerMismatch);
+
+  // CLASSIC PRO-RATA: step through the snapshot (see `pay_winnings`)
+  require!(
+    market.resolved_total_winning_shares > 0 && market.resolved_payable > 0,
+    PredictionError::NoWinnings
+  );
+  let user_winning_shares = market.position_winnings(position)?;
+  require!(user_winning_shares > 0, PredictionError::NoWinnings);
+
+  let payout = market.pay_winnings(user_winning_shares)?;
+  require!(payout > 0, PredictionError::NoWinnings);
+
+  position.claimed = true;
+
+  Ok((payout, user_winning_shares))
+}
+
+/// Pay the winners' share of a slashed creator bond, in lamports, for
+/// `winning_shares` that `settle_claim` just paid: the same pro-rata step
+/// over the bond's trader pool as the collateral payout.
+fn pay_slashed_bond_share<'info>(
+  bond: Option<&Account<'info, CreatorBond>>,
+  market: &MarketV3,
+  winning_shares: u64,
+  recipient: &AccountInfo<'info>,
+) -> Result<()> {
+  let Some(bond) = bond.filter(|b| b.slashed && b.trader_pool > 0) else {
+    return Ok(());
+  };
+  let bond_payout = pro_rata_step(
+    bond.trader_pool,
+    market.claimed_winning_shares - winning_shares,
+    winning_shares,
+    market.resolved_total_winning_shares,
+  )?;
+  if bond_payout > 0 {
+    let bond_info = bond.to_account_info();
+    **bond_info.try_borrow_mut_lamports()? -= bond_payout;
+    **recipient.try_borrow_mut_lamports()? += bond_payout;
+  }
+  Ok(())
+}
+
+/// Shared body of `resolve_market` / `resolve_market_payouts`.
+fn resolve_market_as(ctx: Context<ResolveMarketV2>, winning_outcome: i8, payout_fraction: u64) -> Result<()> {
+  let mut market = ctx.accounts.market.load_mut()?;
+  require!(market.group == Pubkey::default(), PredictionError::GroupedMarket);
+  require!(market.scalar == 0, PredictionError::ScalarMarket);
+  require!(market.oracle_market == 0, PredictionError::OracleMarket);
+
+  require!(
+    ctx.accounts.authority.key() == market.resolver(),
+    PredictionError::Unauthorized
+  );
+  require!(
+    market.is_live(),
+    PredictionError::InvalidMarketStatus
+  );
+
+  if market.parent_market != Pubkey::default() {
+    let parent = ctx
+      .accounts
+      .parent_market
+      .as_ref()
+      .ok_or(PredictionError::ParentNotResolved)?
+      .load()?;
+    require!(
+      parent.status == MarketStatus::Resolved as u8
+        && Clock::get()?.unix_timestamp >= parent.dispute_deadline,
+      PredictionError::ParentNotResolved
+    );
+  }
+
+  market.winning_outcome = winning_outcome;
+  market.payout_fraction = payout_fraction;
+  let total_winning_shares = if winning_outcome == OUTCOME_REFUND_AT_COST {
+    market.total_cost_basis
+  } else {
+    market.winning_shares(market.total_yes_shares, market.total_no_shares)?
+  };
+  require!(total_winning_shares > 0, PredictionError::NoWinnings);
+
+  require!(market.yield_deployed == 0, PredictionError::YieldDeployed);
+  require!(
+    ctx.accounts.vault.amount >= market.vault_balance,
+    PredictionError::VaultShortfall
+  );
+
+  // Snapshot at resolution time
+  let vault_balance = market.vault_balance;
+  market.snapshot_resolution(vault_balance, total_winning_shares)?;
+
+  if market.lst_stake_pool != Pubkey::default() {
+    let pool = ctx
+      .accounts
+      .lst_stake_pool
+      .as_ref()
+      .ok_or(PredictionError::MissingStakePool)?;
+    market.resolved_lst_rate = read_stake_pool_rate(pool, &market.collateral_mint)?;
+  }
+
+  market.status = MarketStatus::Resolved as u8;
+
+  let now = Clock::get()?.unix_timestamp;
+  market.resolved_at = now;
+  market.set_challenge_deadlines(&ctx.accounts.config, now)?;
+
+  sync_registry_entry(
+    ctx.accounts.registry_page.as_deref_mut(),
+    ctx.accounts.market.key(),
+    &market,
+  )?;
+
+  emit!(MarketResolved {
+    market: ctx.accounts.market.key(),
+    winning_outcome: market.winning_outcome,
+    total_winning_shares,
+    payable: market.resolved_payable,
+    payout_rate: market.payout_rate,
+    lst_rate: market.resolved_lst_rate,
+  });
+
+  Ok(())
+}
+
+/// Checks on creation args that don't depend on accounts.
+fn validate_market_args(args: &CreateMarketCpmmArgs) -> Result<()> {
+  require!(
+    args.initial_yes_reserve > 0 && args.initial_no_reserve > 0,
+    PredictionError::InvalidLiquidity
+  );
+  require!(args.start_time < args.end_time, PredictionError::InvalidStartTime);
+  require!(
+    args.question_hash != [0u8; 32],
+    PredictionError::InvalidQuestionHash
+  );
+  Ok(())
+}
+
+/// Fill in a new market from `args` once `received` collateral has reached
+/// its vault. Identity fields (bumps, authority, mint, vault) are set by the
+/// caller. Returns the creator's LP shares.
+fn init_market_state(
+  market: &mut MarketV3,
+  config: &mut ProtocolConfig,
+  args: &CreateMarketCpmmArgs,
+  received: u64,
+  now: i64,
+) -> Result<u64> {
+  let (metadata_uri, metadata_uri_len) = encode_metadata_uri(&args.metadata_uri)?;
+  market.market_id = args.market_id;
+  market.question_hash = args.question_hash;
+  market.metadata_uri = metadata_uri;
+  market.metadata_uri_len = metadata_uri_len;
+  market.set_metadata(&args.metadata)?;
+  market.market_seq = config.next_market_seq()?;
+  market.creator_fee_share_bps = config.creator_fee_share_bps;
+  market.protocol_fee_share_bps = config.protocol_fee_share_bps;
+  market.insurance_fee_share_bps = config.insurance_fee_share_bps;
+  market.vault_balance = received;
+  market.start_time = args.start_time;
+  market.end_time = args.end_time;
+  if let Some(auction) = &args.opening_auction {
+    require!(
+      auction.duration > 0
+        && auction.start_yes_price > 0
+        && auction.start_yes_price < PRICE_SCALE
+        && auction.end_yes_price > 0
+        && auction.end_yes_price < PRICE_SCALE,
+      PredictionError::InvalidAuctionParams
+    );
+    market.auction_start = args.start_time.max(now);
+    market.auction_duration = auction.duration;
+    market.auction_start_price = auction.start_yes_price;
+    market.auction_end_price = auction.end_yes_price;
+  }
+  market.status = if args.start_time > now {
+    MarketStatus::PreMarket as u8
+  } else {
+    MarketStatus::Open as u8
+  };
+  market.winning_outcome = -1;
+
+  // Initial reserves set the opening odds (p_yes = no / (yes + no)).
+  // These reserves are in "collateral units" and are backed 1:1 by the
+  // collateral that actually reached the vault (a transfer-fee mint delivers
+  // less than yes + no), split in the requested ratio.
+  let backing = args
+    .initial_yes_reserve
+    .checked_add(args.initial_no_reserve)
+    .ok_or(PredictionError::MathOverflow)?;
+  let yes_reserve = mul_div(received, args.initial_yes_reserve, backing)?;
+  let no_reserve = received
+    .checked_sub(yes_reserve)
+    .ok_or(PredictionError::MathOverflow)?;
+  require!(
+    yes_reserve > 0 && no_reserve > 0,
+    PredictionError::InvalidLiquidity
+  );
+  market.yes_pool = yes_reserve;
+  market.no_pool = no_reserve;
+
+  // The creator owns the initial pool: one LP share per unit of the larger reserve
+  let lp_shares = yes_reserve.max(no_reserve);
+  market.total_lp_shares = lp_shares;
+  Ok(lp_shares)
+}
+
+/// Resolve each group member to its `payouts` entry (PRICE_SCALE per YES
+/// share; NO gets the complement). `remaining_accounts` holds (market,
+/// vault) per member, in group order.
+fn settle_group_payouts<'info>(
+  members: &[Pubkey],
+  remaining_accounts: &'info [AccountInfo<'info>],
+  payouts: &[u64],
+  now: i64,
+) -> Result<()> {
+  for ((member, pair), &payout) in members
+    .iter()
+    .zip(remaining_accounts.chunks_exact(2))
+    .zip(payouts)
+  {
+    let [market_info, vault_info] = pair else {
+      unreachable!()
+    };
+    require_keys_eq!(market_info.key(), *member, PredictionError::InvalidRemainingAccounts);
+    let loader = AccountLoader::<MarketV3>::try_from(market_info)?;
+    let mut market = loader.load_mut()?;
+    require!(market.is_live(), PredictionError::InvalidMarketStatus);
+    require!(market.yield_deployed == 0, PredictionError::YieldDeployed);
+    require_keys_eq!(market.vault, vault_info.key(), PredictionError::InvalidRemainingAccounts);
+    let vault = InterfaceAccount::<TokenAccount>::try_from(vault_info)?;
+
+    let (winning_outcome, payout_fraction) = match payout {
+      PRICE_SCALE => (0, 0),
+      0 => (1, 0),
+      yes => (OUTCOME_SPLIT, yes),
+    };
+    finalize_group_member(
+      &mut market,
+      *member,
+      winning_outcome,
+      payout_fraction,
+      vault.amount,
+      now,
+    )?;
+  }
+  Ok(())
+}
+
+/// Snapshot and mark a negative-risk group member resolved. Claims open
+/// immediately: group resolutions are not disputable.
+fn finalize_group_member(
+  market: &mut MarketV3,
+  market_key: Pubkey,
+  winning_outcome: i8,
+  payout_fraction: u64,
+  vault_amount: u64,
+  now: i64,
+) -> Result<()> {
+  require!(vault_amount >= market.vault_balance, PredictionError::VaultShortfall);
+
+  market.winning_outcome = winning_outcome;
+  market.payout_fraction = payout_fraction;
+  let total_winning_shares =
+    market.winning_shares(market.total_yes_shares, market.total_no_shares)?;
+  let vault_balance = market.vault_balance;
+  market.snapshot_resolution(vault_balance, total_winning_shares)?;
+  market.status = MarketStatus::Resolved as u8;
+  market.resolved_at = now;
+  market.dispute_deadline = now;
+
+  emit!(MarketResolved {
+    market: market_key,
+    winning_outcome: market.winning_outcome,
+    total_winning_shares,
+    payable: market.resolved_payable,
+    payout_rate: market.payout_rate,
+    lst_rate: 0,
+  });
+  Ok(())
 }
 
 // ----------------------------
-// Accounts
+// Metadata
 // ----------------------------
 
-#[derive(Accounts)]
-#[instruction(args: CreateMarketCpmmArgs)]
-pub struct CreateMarketCpmm<'info> {
-  #[account(
-    init,
-    payer = authority,
-    space = 8 + MarketV2::INIT_SPACE,
-    seeds = [b"market_v2", authority.key().as_ref(), &args.market_id.to_le_bytes()],
-    bump
-  )]
-  pub market: Account<'info, MarketV2>,
+fn encode_metadata_uri(uri: &str) -> Result<([u8; MAX_METADATA_URI_LEN], u8)> {
+  require!(
+    uri.len() <= MAX_METADATA_URI_LEN,
+    PredictionError::MetadataUriTooLong
+  );
 
-  #[account(
-    init,
-    payer = authority,
-    seeds = [b"vault_v2", market.key().as_ref()],
-    bump,
-    token::mint = collateral_mint,
-    token::authority = vault_authority
-  )]
-  pub vault: Account<'info, TokenAccount>,
+  let mut bytes = [0u8; MAX_METADATA_URI_LEN];
+  bytes[..uri.len()].copy_from_slice(uri.as_bytes());
+  Ok((bytes, uri.len() as u8))
+}
 
-  /// CHECK: PDA that signs for vault transfers
-  #[account(
-    seeds = [b"vault_auth_v2", market.key().as_ref()],
-    bump
-  )]
-  pub vault_authority: UncheckedAccount<'info>,
+// ----------------------------
+// Vault transfers
+// ----------------------------
 
-  pub collateral_mint: Account<'info, Mint>,
+/// Move `amount` of collateral from `owner` into the vault and return what the
+/// vault actually received (less than `amount` for transfer-fee mints).
+/// Native SOL markets take lamports directly and wrap them with `sync_native`,
+/// so `owner_ata` may be omitted.
+fn deposit_collateral<'info>(
+  owner: AccountInfo<'info>,
+  owner_ata: Option<AccountInfo<'info>>,
+  vault: &mut InterfaceAccount<'info, TokenAccount>,
+  collateral_mint: &InterfaceAccount<'info, Mint>,
+  token_program: AccountInfo<'info>,
+  system_program: AccountInfo<'info>,
+  amount: u64,
+) -> Result<u64> {
+  let vault_before = vault.amount;
 
-  #[account(mut)]
-  pub authority: Signer<'info>,
+  if collateral_mint.key() == native_mint::ID {
+    system_program::transfer(
+      CpiContext::new(
+        system_program,
+        system_program::Transfer {
+          from: owner,
+          to: vault.to_account_info(),
+        },
+      ),
+      amount,
+    )?;
 
-  #[account(
-    mut,
-    constraint = authority_collateral_ata.mint == collateral_mint.key(),
-    constraint = authority_collateral_ata.owner == authority.key(),
-  )]
-  pub authority_collateral_ata: Account<'info, TokenAccount>,
+    token_interface::sync_native(CpiContext::new(
+      token_program,
+      SyncNative {
+        account: vault.to_account_info(),
+      },
+    ))?;
+  } else {
+    let owner_ata = owner_ata.ok_or(PredictionError::MissingCollateralAccount)?;
+
+    token_interface::transfer_checked(
+      CpiContext::new(
+        token_program,
+        TransferChecked {
+          from: owner_ata,
+          mint: collateral_mint.to_account_info(),
+          to: vault.to_account_info(),
+          authority: owner,
+        },
+      ),
+      amount,
+      collateral_mint.decimals,
+    )?;
+  }
+
+  vault.reload()?;
+  let received = vault
+    .amount
+    .checked_sub(vault_before)
+    .ok_or(PredictionError::MathOverflow)?;
+  require!(received > 0, PredictionError::ZeroAmount);
+  Ok(received)
+}
+
+/// Pay `amount` of collateral out of the vault to `recipient`.
+/// SPL markets pay into `recipient_ata`; native SOL markets pay into the
+/// temporary `wsol_unwrap` account and close it to `rent_payer`, which
+/// gets its rent back and forwards the payout lamports to `recipient`.
+#[allow(clippy::too_many_arguments)]
+fn pay_out_collateral<'info>(
+  vault: &InterfaceAccount<'info, TokenAccount>,
+  vault_authority: AccountInfo<'info>,
+  vault_authority_seeds: &[&[u8]],
+  collateral_mint: &InterfaceAccount<'info, Mint>,
+  recipient_ata: Option<AccountInfo<'info>>,
+  wsol_unwrap: Option<AccountInfo<'info>>,
+  recipient: AccountInfo<'info>,
+  rent_payer: AccountInfo<'info>,
+  token_program: AccountInfo<'info>,
+  system_program: AccountInfo<'info>,
+  amount: u64,
+) -> Result<()> {
+  let native = collateral_mint.key() == native_mint::ID;
+  let destination = if native {
+    wsol_unwrap.ok_or(PredictionError::MissingCollateralAccount)?
+  } else {
+    require!(wsol_unwrap.is_none(), PredictionError::UnexpectedWsolAccount);
+    recipient_ata.ok_or(PredictionError::MissingCollateralAccount)?
+  };
+
+  token_interface::transfer_checked(
+    CpiContext::new_with_signer(
+      token_program.clone(),
+      TransferChecked {
+        from: vault.to_account_info(),
+        mint: collateral_mint.to_account_info(),
+        to: destination.clone(),
+        authority: vault_authority.clone(),
+      },
+      &[vault_authority_seeds],
+    ),
+    amount,
+    collateral_mint.decimals,
+  )?;
+
+  if native {
+    token_interface::close_account(CpiContext::new_with_signer(
+      token_program,
+      CloseAccount {
+        account: destination,
+        destination: rent_payer.clone(),
+        authority: vault_authority,
+      },
+      &[vault_authority_seeds],
+    ))?;
+
+    if rent_payer.key() != recipient.key() {
+      system_program::transfer(
+        CpiContext::new(
+          system_program,
+          system_program::Transfer {
+            from: rent_payer,
+            to: recipient,
+          },
+        ),
+        amount,
+      )?;
+    }
+  }
+
+  Ok(())
+}
+
+/// Move shares out of a position without touching market totals (OTC escrow).
+fn take_position_shares(position: &mut PositionV2, outcome_index: u8, amount: u64) -> Result<()> {
+  require!(!position.claimed, PredictionError::AlreadyClaimed);
+  let held = match outcome_index {
+    0 => &mut position.yes_shares,
+    1 => &mut position.no_shares,
+    _ => return err!(PredictionError::InvalidOutcome),
+  };
+  *held = held
+    .checked_sub(amount)
+    .ok_or(PredictionError::InsufficientShares)?;
+  Ok(())
+}
+
+/// Move shares into a position without touching market totals (OTC escrow).
+fn give_position_shares(position: &mut PositionV2, outcome_index: u8, amount: u64) -> Result<()> {
+  require!(!position.claimed, PredictionError::AlreadyClaimed);
+  let held = match outcome_index {
+    0 => &mut position.yes_shares,
+    1 => &mut position.no_shares,
+    _ => return err!(PredictionError::InvalidOutcome),
+  };
+  *held = held
+    .checked_add(amount)
+    .ok_or(PredictionError::MathOverflow)?;
+  Ok(())
+}
+
+/// Burn outcome tokens held by `owner`.
+fn burn_outcome_tokens<'info>(
+  outcome_mint: &InterfaceAccount<'info, Mint>,
+  owner_ata: &InterfaceAccount<'info, TokenAccount>,
+  owner: AccountInfo<'info>,
+  token_program: AccountInfo<'info>,
+  amount: u64,
+) -> Result<()> {
+  token_interface::burn(
+    CpiContext::new(
+      token_program,
+      Burn {
+        mint: outcome_mint.to_account_info(),
+        from: owner_ata.to_account_info(),
+        authority: owner,
+      },
+    ),
+    amount,
+  )
+}
+
+/// Snapshot an oracle-driven resolution once `winning_outcome` and
+/// `payout_fraction` are set, debiting the resolver bounty first if it will
+/// be paid. Returns the total winning shares and the bounty.
+fn finish_oracle_resolution(
+  market: &mut MarketV3,
+  config: &ProtocolConfig,
+  vault_amount: u64,
+  pay_bounty: bool,
+  now: i64,
+) -> Result<(u64, u64)> {
+  let total_winning_shares =
+    market.winning_shares(market.total_yes_shares, market.total_no_shares)?;
+  require!(total_winning_shares > 0, PredictionError::NoWinnings);
+
+  require!(market.yield_deployed == 0, PredictionError::YieldDeployed);
+  require!(
+    vault_amount >= market.vault_balance,
+    PredictionError::VaultShortfall
+  );
+  let bounty = if pay_bounty {
+    market.take_resolver_bounty(total_winning_shares)?
+  } else {
+    0
+  };
+  let vault_balance = market.vault_balance;
+  market.snapshot_resolution(vault_balance, total_winning_shares)?;
+
+  market.status = MarketStatus::Resolved as u8;
+  market.resolved_at = now;
+  market.set_challenge_deadlines(config, now)?;
+  Ok((total_winning_shares, bounty))
+}
+
+/// Transfer an already-debited resolver bounty out of the vault.
+#[allow(clippy::too_many_arguments)]
+fn pay_resolver_bounty<'info>(
+  vault: &InterfaceAccount<'info, TokenAccount>,
+  resolver_ata: &mut InterfaceAccount<'info, TokenAccount>,
+  collateral_mint: &InterfaceAccount<'info, Mint>,
+  vault_authority: AccountInfo<'info>,
+  seeds: &[&[u8]],
+  token_program: AccountInfo<'info>,
+  market: Pubkey,
+  resolver: Pubkey,
+  amount: u64,
+) -> Result<()> {
+  if amount == 0 {
+    return Ok(());
+  }
+  transfer_signed(
+    vault,
+    resolver_ata,
+    collateral_mint,
+    vault_authority,
+    seeds,
+    token_program,
+    amount,
+  )?;
+  emit!(ResolverBountyPaid { market, resolver, amount });
+  Ok(())
+}
+
+/// Move `amount` of a trading account's balance from custody into the
+/// market vault; returns what the vault received.
+#[allow(clippy::too_many_arguments)]
+fn fund_from_trading_account<'info>(
+  market: &mut MarketV3,
+  account: &mut TradingAccount,
+  custody: &InterfaceAccount<'info, TokenAccount>,
+  custody_authority: AccountInfo<'info>,
+  custody_authority_bump: u8,
+  vault: &mut InterfaceAccount<'info, TokenAccount>,
+  collateral_mint: &InterfaceAccount<'info, Mint>,
+  token_program: AccountInfo<'info>,
+  amount: u64,
+) -> Result<u64> {
+  account.balance = account
+    .balance
+    .checked_sub(amount)
+    .ok_or(PredictionError::InsufficientBalance)?;
+
+  let seeds: &[&[u8]] = &[b"trading_custody_auth", &[custody_authority_bump]];
+  let received = transfer_signed(
+    custody,
+    vault,
+    collateral_mint,
+    custody_authority,
+    seeds,
+    token_program,
+    amount,
+  )?;
+  market.credit_vault(received)?;
+  Ok(received)
+}
+
+/// Move `amount` out of the market vault into custody, credited to the
+/// trading account.
+#[allow(clippy::too_many_arguments)]
+fn credit_trading_account<'info>(
+  market: &mut MarketV3,
+  market_key: Pubkey,
+  account: &mut TradingAccount,
+  vault: &InterfaceAccount<'info, TokenAccount>,
+  vault_authority: AccountInfo<'info>,
+  custody: &mut InterfaceAccount<'info, TokenAccount>,
+  collateral_mint: &InterfaceAccount<'info, Mint>,
+  token_program: AccountInfo<'info>,
+  amount: u64,
+) -> Result<()> {
+  market.debit_vault(amount)?;
+
+  let seeds: &[&[u8]] = &[
+    b"vault_auth_v2",
+    market_key.as_ref(),
+    &[market.vault_auth_bump],
+  ];
+  let received = transfer_signed(
+    vault,
+    custody,
+    collateral_mint,
+    vault_authority,
+    seeds,
+    token_program,
+    amount,
+  )?;
+
+  account.balance = account
+    .balance
+    .checked_add(received)
+    .ok_or(PredictionError::MathOverflow)?;
+  Ok(())
+}
+
+/// Token transfer signed by a program PDA; returns what `to` actually
+/// received (less than `amount` for Token-2022 transfer-fee mints).
+fn transfer_signed<'info>(
+  from: &InterfaceAccount<'info, TokenAccount>,
+  to: &mut InterfaceAccount<'info, TokenAccount>,
+  collateral_mint: &InterfaceAccount<'info, Mint>,
+  authority: AccountInfo<'info>,
+  authority_seeds: &[&[u8]],
+  token_program: AccountInfo<'info>,
+  amount: u64,
+) -> Result<u64> {
+  let to_before = to.amount;
+
+  token_interface::transfer_checked(
+    CpiContext::new_with_signer(
+      token_program,
+      TransferChecked {
+        from: from.to_account_info(),
+        mint: collateral_mint.to_account_info(),
+        to: to.to_account_info(),
+        authority,
+      },
+      &[authority_seeds],
+    ),
+    amount,
+    collateral_mint.decimals,
+  )?;
+
+  to.reload()?;
+  to.amount
+    .checked_sub(to_before)
+    .ok_or_else(|| PredictionError::MathOverflow.into())
+}
+
+/// Initialize a fresh (`init_if_needed`) position or check an existing one
+/// belongs to this market and owner.
+fn init_or_check_position(
+  position: &mut PositionV2,
+  market_key: Pubkey,
+  owner: Pubkey,
+  bump: u8,
+) -> Result<()> {
+  if position.owner == Pubkey::default() {
+    position.market = market_key;
+    position.owner = owner;
+    position.bump = bump;
+    position.yes_shares = 0;
+    position.no_shares = 0;
+    position.claimed = false;
+  } else {
+    require!(
+      position.market == market_key,
+      PredictionError::PositionMarketMismatch
+    );
+    require!(
+      position.owner == owner,
+      PredictionError::PositionOwnerMismatch
+    );
+  }
+  Ok(())
+}
+
+/// Add YES/NO shares to a position and the market totals.
+fn credit_shares(
+  market: &mut MarketV3,
+  position: &mut PositionV2,
+  yes_shares: u64,
+  no_shares: u64,
+) -> Result<()> {
+  position.yes_shares = position
+    .yes_shares
+    .checked_add(yes_shares)
+    .ok_or(PredictionError::MathOverflow)?;
+  position.no_shares = position
+    .no_shares
+    .checked_add(no_shares)
+    .ok_or(PredictionError::MathOverflow)?;
+  market.total_yes_shares = market
+    .total_yes_shares
+    .checked_add(yes_shares)
+    .ok_or(PredictionError::MathOverflow)?;
+  market.total_no_shares = market
+    .total_no_shares
+    .checked_add(no_shares)
+    .ok_or(PredictionError::MathOverflow)?;
+  Ok(())
+}
+
+/// Add collateral put into the market to a position's cost basis.
+fn add_cost_basis(market: &mut MarketV3, position: &mut PositionV2, amount: u64) -> Result<()> {
+  position.cost_basis = position
+    .cost_basis
+    .checked_add(amount)
+    .ok_or(PredictionError::MathOverflow)?;
+  market.total_cost_basis = market
+    .total_cost_basis
+    .checked_add(amount)
+    .ok_or(PredictionError::MathOverflow)?;
+  Ok(())
+}
+
+/// Take collateral paid back out of a position's cost basis. Proceeds beyond
+/// it are profit, so the basis stops at zero.
+fn reduce_cost_basis(market: &mut MarketV3, position: &mut PositionV2, amount: u64) -> Result<()> {
+  let reduced = amount.min(position.cost_basis);
+  position.cost_basis -= reduced;
+  market.total_cost_basis = market
+    .total_cost_basis
+    .checked_sub(reduced)
+    .ok_or(PredictionError::MathOverflow)?;
+  Ok(())
+}
+
+/// Leaf of one compressed position: binds the shares to the market, owner
+/// and leaf index so no two leaves hash alike.
+fn compressed_leaf(market: &Pubkey, owner: &Pubkey, outcome: u8, shares: u64, index: u64) -> [u8; 32] {
+  hashv(&[
+    market.as_ref(),
+    owner.as_ref(),
+    &[outcome],
+    &shares.to_le_bytes(),
+    &index.to_le_bytes(),
+  ])
+  .to_bytes()
+}
+
+/// Leaf of a `PayoutDistributor` tree: `amount` of collateral owed to
+/// `claimant` at position `index` of the payout list.
+fn distributor_leaf(index: u64, claimant: &Pubkey, amount: u64) -> [u8; 32] {
+  hashv(&[&index.to_le_bytes(), claimant.as_ref(), &amount.to_le_bytes()]).to_bytes()
+}
+
+/// Verify a Merkle proof where each parent is the hash of its two children
+/// in sorted order, so the proof needs no left/right flags.
+fn verify_merkle_proof(proof: &[[u8; 32]], root: [u8; 32], leaf: [u8; 32]) -> bool {
+  let computed = proof.iter().fold(leaf, |node, sibling| {
+    if node <= *sibling {
+      hashv(&[&node, sibling]).to_bytes()
+    } else {
+      hashv(&[sibling, &node]).to_bytes()
+    }
+  });
+  computed == root
+}
+
+/// Call the spl-account-compression instruction `name` on a market's tree,
+/// signing as its `compressed_positions` PDA. `proof` is appended to the
+/// accounts read-only.
+fn invoke_compression<'info>(
+  name: &str,
+  args: &[u8],
+  tree: &Account<'info, CompressedPositions>,
+  merkle_tree: AccountInfo<'info>,
+  noop_program: AccountInfo<'info>,
+  compression_program: AccountInfo<'info>,
+  proof: &[AccountInfo<'info>],
+) -> Result<()> {
+  let mut data = hash(format!("global:{name}").as_bytes()).to_bytes()[..8].to_vec();
+  data.extend_from_slice(args);
+
+  let mut accounts = vec![
+    AccountMeta::new(merkle_tree.key(), false),
+    AccountMeta::new_readonly(tree.key(), true),
+    AccountMeta::new_readonly(noop_program.key(), false),
+  ];
+  accounts.extend(proof.iter().map(|node| AccountMeta::new_readonly(node.key(), false)));
+  let ix = Instruction {
+    program_id: compression_program.key(),
+    accounts,
+    data,
+  };
+
+  let mut infos = vec![merkle_tree, tree.to_account_info(), noop_program];
+  infos.extend_from_slice(proof);
+  infos.push(compression_program);
+
+  let seeds: &[&[u8]] = &[b"compressed_positions", tree.market.as_ref(), &[tree.bump]];
+  invoke_signed(&ix, &infos, &[seeds])?;
+  Ok(())
+}
+
+/// Only lending programs with an adapter in `invoke_lending_program` (or
+/// none) can be configured.
+fn check_yield_program(program: Pubkey) -> Result<()> {
+  require!(
+    program == Pubkey::default() || program == KAMINO_LEND_PROGRAM_ID || program == SOLEND_PROGRAM_ID,
+    PredictionError::UnsupportedYieldProgram
+  );
+  Ok(())
+}
+
+enum LendingOp {
+  Deposit, // liquidity from the vault -> collateral tokens to the receipt
+  Redeem,  // collateral tokens from the receipt -> liquidity to the vault
+}
+
+/// Accounts of a reserve deposit or redemption, for `invoke_lending_program`.
+struct LendingAccounts<'info> {
+  program: AccountInfo<'info>,
+  reserve: AccountInfo<'info>,
+  lending_market: AccountInfo<'info>,
+  lending_market_authority: AccountInfo<'info>,
+  liquidity_mint: AccountInfo<'info>,
+  liquidity_supply: AccountInfo<'info>,
+  collateral_mint: AccountInfo<'info>,
+  vault: AccountInfo<'info>,
+  receipt: AccountInfo<'info>,
+  vault_authority: AccountInfo<'info>,
+  token_program: AccountInfo<'info>,
+  receipt_token_program: AccountInfo<'info>,
+  instructions: AccountInfo<'info>,
+}
+
+/// Deposit `amount` of vault liquidity into the reserve, or redeem `amount`
+/// receipt tokens from it, signing as the market's vault authority. The
+/// instruction is built here for each supported program, so only the vault
+/// and the receipt can be the token accounts moved. Both programs need the
+/// reserve refreshed earlier in the same transaction.
+fn invoke_lending_program<'info>(
+  op: LendingOp,
+  amount: u64,
+  a: LendingAccounts<'info>,
+  vault_authority_seeds: &[&[u8]],
+) -> Result<()> {
+  let (accounts, mut data) = match (a.program.key(), op) {
+    (KAMINO_LEND_PROGRAM_ID, LendingOp::Deposit) => (
+      vec![
+        AccountMeta::new_readonly(a.vault_authority.key(), true),
+        AccountMeta::new(a.reserve.key(), false),
+        AccountMeta::new_readonly(a.lending_market.key(), false),
+        AccountMeta::new_readonly(a.lending_market_authority.key(), false),
+        AccountMeta::new_readonly(a.liquidity_mint.key(), false),
+        AccountMeta::new(a.liquidity_supply.key(), false),
+        AccountMeta::new(a.collateral_mint.key(), false),
+        AccountMeta::new(a.vault.key(), false),
+        AccountMeta::new(a.receipt.key(), false),
+        AccountMeta::new_readonly(a.receipt_token_program.key(), false),
+        AccountMeta::new_readonly(a.token_program.key(), false),
+        AccountMeta::new_readonly(a.instructions.key(), false),
+      ],
+      hash(b"global:deposit_reserve_liquidity").to_bytes()[..8].to_vec(),
+    ),
+    (KAMINO_LEND_PROGRAM_ID, LendingOp::Redeem) => (
+      vec![
+        AccountMeta::new_readonly(a.vault_authority.key(), true),
+        AccountMeta::new_readonly(a.lending_market.key(), false),
+        AccountMeta::new(a.reserve.key(), false),
+        AccountMeta::new_readonly(a.lending_market_authority.key(), false),
+        AccountMeta::new_readonly(a.liquidity_mint.key(), false),
+        AccountMeta::new(a.collateral_mint.key(), false),
+        AccountMeta::new(a.liquidity_supply.key(), false),
+        AccountMeta::new(a.receipt.key(), false),
+        AccountMeta::new(a.vault.key(), false),
+        AccountMeta::new_readonly(a.receipt_token_program.key(), false),
+        AccountMeta::new_readonly(a.token_program.key(), false),
+        AccountMeta::new_readonly(a.instructions.key(), false),
+      ],
+      hash(b"global:redeem_reserve_collateral").to_bytes()[..8].to_vec(),
+    ),
+    // SPL token-lending layout: DepositReserveLiquidity = 4
+    (SOLEND_PROGRAM_ID, LendingOp::Deposit) => (
+      vec![
+        AccountMeta::new(a.vault.key(), false),
+        AccountMeta::new(a.receipt.key(), false),
+        AccountMeta::new(a.reserve.key(), false),
+        AccountMeta::new(a.liquidity_supply.key(), false),
+        AccountMeta::new(a.collateral_mint.key(), false),
+        AccountMeta::new_readonly(a.lending_market.key(), false),
+        AccountMeta::new_readonly(a.lending_market_authority.key(), false),
+        AccountMeta::new_readonly(a.vault_authority.key(), true),
+        AccountMeta::new_readonly(a.token_program.key(), false),
+      ],
+      vec![4],
+    ),
+    // RedeemReserveCollateral = 5
+    (SOLEND_PROGRAM_ID, LendingOp::Redeem) => (
+      vec![
+        AccountMeta::new(a.receipt.key(), false),
+        AccountMeta::new(a.vault.key(), false),
+        AccountMeta::new(a.reserve.key(), false),
+        AccountMeta::new(a.collateral_mint.key(), false),
+        AccountMeta::new(a.liquidity_supply.key(), false),
+        AccountMeta::new_readonly(a.lending_market.key(), false),
+        AccountMeta::new_readonly(a.lending_market_authority.key(), false),
+        AccountMeta::new_readonly(a.vault_authority.key(), true),
+        AccountMeta::new_readonly(a.token_program.key(), false),
+      ],
+      vec![5],
+    ),
+    _ => return err!(PredictionError::UnsupportedYieldProgram),
+  };
+  data.extend_from_slice(&amount.to_le_bytes());
+
+  let ix = Instruction {
+    program_id: a.program.key(),
+    accounts,
+    data,
+  };
+  let infos = [
+    a.reserve,
+    a.lending_market,
+    a.lending_market_authority,
+    a.liquidity_mint,
+    a.liquidity_supply,
+    a.collateral_mint,
+    a.vault,
+    a.receipt,
+    a.vault_authority,
+    a.token_program,
+    a.receipt_token_program,
+    a.instructions,
+    a.program,
+  ];
+  invoke_signed(&ix, &infos, &[vault_authority_seeds])?;
+  Ok(())
+}
 
-  pub token_program: Program<'info, Token>,
-  pub system_program: Program<'info, System>,
-  pub rent: Sysvar<'info, Rent>,
+fn pay_fees(ctx: &Context<WithdrawFees>, market_key: Pubkey, vault_auth_bump: u8, amount: u64) -> Result<()> {
+  let seeds: &[&[u8]] = &[b"vault_auth_v2", market_key.as_ref(), &[vault_auth_bump]];
+
+  pay_out_collateral(
+    &ctx.accounts.vault,
+    ctx.accounts.vault_authority.to_account_info(),
+    seeds,
+    &ctx.accounts.collateral_mint,
+    ctx.accounts.recipient_collateral_ata.as_ref().map(|a| a.to_account_info()),
+    ctx.accounts.wsol_unwrap.as_ref().map(|a| a.to_account_info()),
+    ctx.accounts.recipient.to_account_info(),
+    ctx.accounts.payer.to_account_info(),
+    ctx.accounts.token_program.to_account_info(),
+    ctx.accounts.system_program.to_account_info(),
+    amount,
+  )
 }
 
-#[derive(Accounts)]
-pub struct BuyShares<'info> {
-  #[account(mut)]
-  pub market: Account<'info, MarketV2>,
+// ----------------------------
+// Registry
+// ----------------------------
 
-  #[account(
-    mut,
-    seeds = [b"vault_v2", market.key().as_ref()],
-    bump
-  )]
-  pub vault: Account<'info, TokenAccount>,
+fn register_market_entry(
+  registry: &mut MarketRegistry,
+  page: &mut RegistryPage,
+  page_bump: u8,
+  market_key: Pubkey,
+  market: &mut MarketV3,
+) -> Result<()> {
+  let index = registry.market_count;
+  page.page = index / REGISTRY_PAGE_SIZE;
+  page.bump = page_bump;
+  page.entries.push(RegistryEntry {
+    market: market_key,
+    status: market.status,
+    end_time: market.end_time,
+  });
 
-  /// CHECK: PDA that signs for vault transfers
-  #[account(
-    seeds = [b"vault_auth_v2", market.key().as_ref()],
-    bump
-  )]
-  pub vault_authority: UncheckedAccount<'info>,
+  market.registry_index = index;
+  market.registered = 1;
+  registry.market_count = index.checked_add(1).ok_or(PredictionError::MathOverflow)?;
+  Ok(())
+}
 
-  #[account(
-    init_if_needed,
-    payer = user,
-    space = 8 + PositionV2::INIT_SPACE,
-    seeds = [b"position_v2", market.key().as_ref(), user.key().as_ref()],
-    bump
-  )]
-  pub position: Account<'info, PositionV2>,
+/// Copy the market's current status / end_time into its registry entry.
+/// No-op for unregistered markets; registered ones must pass their page.
+fn sync_registry_entry(
+  page: Option<&mut RegistryPage>,
+  market_key: Pubkey,
+  market: &MarketV3,
+) -> Result<()> {
+  if market.registered == 0 {
+    return Ok(());
+  }
+  let page = page.ok_or(PredictionError::MissingRegistryPage)?;
+  let slot = (market.registry_index % REGISTRY_PAGE_SIZE) as usize;
+  let entry = page
+    .entries
+    .get_mut(slot)
+    .ok_or(PredictionError::MissingRegistryPage)?;
+  require_keys_eq!(entry.market, market_key, PredictionError::MissingRegistryPage);
 
-  #[account(mut)]
-  pub user: Signer<'info>,
+  entry.status = market.status;
+  entry.end_time = market.end_time;
+  Ok(())
+}
 
-  #[account(
-    mut,
-    constraint = user_collateral_ata.mint == market.collateral_mint,
-    constraint = user_collateral_ata.owner == user.key(),
-  )]
-  pub user_collateral_ata: Account<'info, TokenAccount>,
+// ----------------------------
+// Oracles
+// ----------------------------
 
-  pub token_program: Program<'info, Token>,
-  pub system_program: Program<'info, System>,
-  pub rent: Sysvar<'info, Rent>,
+pub struct OraclePrice {
+  pub feed_id: [u8; 32],
+  pub verified: bool, // full Wormhole verification
+  pub price: i64,
+  pub conf: u64,
+  pub exponent: i32,
+  pub publish_time: i64,
 }
 
-#[derive(Accounts)]
-pub struct SellShares<'info> {
-  #[account(mut)]
-  pub market: Account<'info, MarketV2>,
+/// Read the price an oracle-settled market expires at: a fully verified
+/// update for `feed_id` / `exponent` published within
+/// `ORACLE_MAX_STALENESS_SECS` of `end_time` with a tight enough confidence.
+fn read_settlement_price(
+  info: &AccountInfo,
+  feed_id: &[u8; 32],
+  exponent: i32,
+  end_time: i64,
+) -> Result<OraclePrice> {
+  let price = read_pyth_price(info, end_time)?;
+  require!(
+    price.verified && price.feed_id == *feed_id && price.exponent == exponent,
+    PredictionError::InvalidOracle
+  );
+  require!(
+    price.publish_time.saturating_sub(end_time) <= ORACLE_MAX_STALENESS_SECS,
+    PredictionError::StaleOracle
+  );
+  require!(
+    (price.conf as u128) * (BPS_DENOM as u128)
+      <= (price.price.unsigned_abs() as u128) * (ORACLE_MAX_CONF_BPS as u128),
+    PredictionError::OracleConfidenceTooWide
+  );
+  Ok(price)
+}
 
-  #[account(
-    mut,
-    seeds = [b"vault_v2", market.key().as_ref()],
-    bump
-  )]
-  pub vault: Account<'info, TokenAccount>,
+/// Read a Pyth pull-oracle `PriceUpdateV2` account, rejecting stale prices.
+fn read_pyth_price(info: &AccountInfo, now: i64) -> Result<OraclePrice> {
+  require_keys_eq!(*info.owner, PYTH_RECEIVER_PROGRAM_ID, PredictionError::InvalidOracle);
+  let data = info.try_borrow_data()?;
 
-  /// CHECK: PDA that signs for vault transfers
-  #[account(
-    seeds = [b"vault_auth_v2", market.key().as_ref()],
-    bump
-  )]
-  pub vault_authority: UncheckedAccount<'info>,
+  // discriminator (8) + write_authority (32) + verification_level (enum:
+  // Partial { num_signatures: u8 } = 2 bytes, Full = 1 byte)
+  let level_at = 8 + 32;
+  require!(data.len() > level_at, PredictionError::InvalidOracle);
+  let message_at = match data[level_at] {
+    0 => level_at + 2,
+    1 => level_at + 1,
+    _ => return err!(PredictionError::InvalidOracle),
+  };
+  // feed_id (32), price i64, conf u64, exponent i32, publish_time i64
+  let price_at = message_at + 32;
+  require!(data.len() >= price_at + 28, PredictionError::InvalidOracle);
 
-  #[account(
-    mut,
-    seeds = [b"position_v2", market.key().as_ref(), user.key().as_ref()],
-    bump
-  )]
-  pub position: Account<'info, PositionV2>,
+  let read_i64 = |at: usize| i64::from_le_bytes(data[at..at + 8].try_into().unwrap());
+  let price = OraclePrice {
+    feed_id: data[message_at..price_at].try_into().unwrap(),
+    verified: data[level_at] == 1,
+    price: read_i64(price_at),
+    conf: u64::from_le_bytes(data[price_at + 8..price_at + 16].try_into().unwrap()),
+    exponent: i32::from_le_bytes(data[price_at + 16..price_at + 20].try_into().unwrap()),
+    publish_time: read_i64(price_at + 20),
+  };
+  require!(
+    now.saturating_sub(price.publish_time) <= ORACLE_MAX_STALENESS_SECS,
+    PredictionError::StaleOracle
+  );
+  Ok(price)
+}
 
-  #[account(mut)]
-  pub user: Signer<'info>,
+// Chainlink store `Transmissions` feed: 8-byte discriminator, then a
+// 192-byte header and a ring buffer of 48-byte transmissions
+const CHAINLINK_DECIMALS_OFFSET: usize = 8 + 138;
+const CHAINLINK_LIVE_LENGTH_OFFSET: usize = 8 + 152;
+const CHAINLINK_LIVE_CURSOR_OFFSET: usize = 8 + 156;
+const CHAINLINK_ROUNDS_OFFSET: usize = 8 + 192;
+const CHAINLINK_ROUND_LEN: usize = 48; // slot u64, timestamp u32, pad u32, answer i128, pad
 
-  #[account(
-    mut,
-    constraint = user_collateral_ata.mint == market.collateral_mint,
-    constraint = user_collateral_ata.owner == user.key(),
-  )]
-  pub user_collateral_ata: Account<'info, TokenAccount>,
+/// Read the Chainlink round whose timestamp is closest to `target`, within
+/// `ORACLE_MAX_STALENESS_SECS` either side. Decimals map to a negative
+/// exponent so thresholds compare as they do for Pyth.
+fn read_chainlink_price(info: &AccountInfo, target: i64) -> Result<OraclePrice> {
+  require_keys_eq!(*info.owner, CHAINLINK_STORE_PROGRAM_ID, PredictionError::InvalidOracle);
+  let data = info.try_borrow_data()?;
+  require!(data.len() >= CHAINLINK_ROUNDS_OFFSET, PredictionError::InvalidOracle);
+
+  let read_u32 = |at: usize| u32::from_le_bytes(data[at..at + 4].try_into().unwrap()) as usize;
+  let decimals = data[CHAINLINK_DECIMALS_OFFSET];
+  let live_length = read_u32(CHAINLINK_LIVE_LENGTH_OFFSET);
+  let live_cursor = read_u32(CHAINLINK_LIVE_CURSOR_OFFSET);
+  require!(
+    live_length > 0
+      && data.len() >= CHAINLINK_ROUNDS_OFFSET + live_length * CHAINLINK_ROUND_LEN,
+    PredictionError::InvalidOracle
+  );
+
+  let mut best: Option<(i64, i128)> = None;
+  for i in 0..live_length {
+    let at = CHAINLINK_ROUNDS_OFFSET + ((live_cursor + i) % live_length) * CHAINLINK_ROUND_LEN;
+    let timestamp = read_u32(at + 8) as i64;
+    if timestamp == 0 || (timestamp - target).abs() > ORACLE_MAX_STALENESS_SECS {
+      continue;
+    }
+    let answer = i128::from_le_bytes(data[at + 16..at + 32].try_into().unwrap());
+    if best.is_none_or(|(t, _)| (timestamp - target).abs() < (t - target).abs()) {
+      best = Some((timestamp, answer));
+    }
+  }
+  let (publish_time, answer) = best.ok_or(PredictionError::StaleOracle)?;
 
-  pub token_program: Program<'info, Token>,
+  Ok(OraclePrice {
+    feed_id: info.key().to_bytes(),
+    verified: true,
+    price: i64::try_from(answer).map_err(|_| PredictionError::MathOverflow)?,
+    conf: 0,
+    exponent: -(decimals as i32),
+    publish_time,
+  })
 }
 
-#[derive(Accounts)]
-pub struct ResolveMarketV2<'info> {
-  #[account(mut)]
-  pub market: Account<'info, MarketV2>,
+// ----------------------------
+// Signature verification
+// ----------------------------
 
-  // CLASSIC PRO-RATA: include vault so we can snapshot vault.amount
-  #[account(
-    mut,
-    seeds = [b"vault_v2", market.key().as_ref()],
-    bump
-  )]
-  pub vault: Account<'info, TokenAccount>,
+// Ed25519SignatureOffsets: 7 little-endian u16s after the 2-byte header
+const ED25519_HEADER_LEN: usize = 2;
+const ED25519_OFFSETS_LEN: usize = 14;
 
-  pub authority: Signer<'info>,
+/// Check that the instruction before the current one is an ed25519 program
+/// verification of exactly one `signer` signature over `message`, with all
+/// data inline in that instruction.
+fn verify_ed25519_signature(instructions: &AccountInfo, signer: &Pubkey, message: &[u8]) -> Result<()> {
+  require!(
+    ed25519_signed_message(instructions, signer)? == message,
+    PredictionError::InvalidSignature
+  );
+  Ok(())
 }
 
-#[derive(Accounts)]
-pub struct ClaimWinningsV2<'info> {
-  #[account(mut)]
-  pub market: Account<'info, MarketV2>,
+/// Message of a `signer` signature verified, with all data inline, by the
+/// ed25519 program instruction right before the current one.
+fn ed25519_signed_message(instructions: &AccountInfo, signer: &Pubkey) -> Result<Vec<u8>> {
+  let current = ix_sysvar::load_current_index_checked(instructions)?;
+  require!(current > 0, PredictionError::InvalidSignature);
+  ed25519_signed_message_at(instructions, current as usize - 1, signer)
+}
 
-  #[account(
-    mut,
-    seeds = [b"vault_v2", market.key().as_ref()],
-    bump
-  )]
-  pub vault: Account<'info, TokenAccount>,
+/// Message of a `signer` signature verified, with all data inline, by the
+/// ed25519 program instruction at `index` in this transaction.
+fn ed25519_signed_message_at(
+  instructions: &AccountInfo,
+  index: usize,
+  signer: &Pubkey,
+) -> Result<Vec<u8>> {
+  let ix = ix_sysvar::load_instruction_at_checked(index, instructions)?;
+  require_keys_eq!(ix.program_id, ED25519_PROGRAM_ID, PredictionError::InvalidSignature);
 
-  /// CHECK: PDA that signs for vault transfers
-  #[account(
-    seeds = [b"vault_auth_v2", market.key().as_ref()],
-    bump
-  )]
-  pub vault_authority: UncheckedAccount<'info>,
+  let data = &ix.data;
+  require!(
+    data.len() >= ED25519_HEADER_LEN + ED25519_OFFSETS_LEN && data[0] == 1,
+    PredictionError::InvalidSignature
+  );
+  let read_u16 = |i: usize| {
+    let at = ED25519_HEADER_LEN + i * 2;
+    u16::from_le_bytes([data[at], data[at + 1]]) as usize
+  };
+  // signature_offset, signature_ix, pubkey_offset, pubkey_ix, msg_offset, msg_size, msg_ix
+  let (pubkey_offset, message_offset, message_size) = (read_u16(2), read_u16(4), read_u16(5));
+  for ix_index in [read_u16(1), read_u16(3), read_u16(6)] {
+    require!(ix_index == u16::MAX as usize, PredictionError::InvalidSignature);
+  }
 
-  #[account(
-    mut,
-    seeds = [b"position_v2", market.key().as_ref(), user.key().as_ref()],
-    bump
-  )]
-  pub position: Account<'info, PositionV2>,
+  let pubkey = data
+    .get(pubkey_offset..pubkey_offset + 32)
+    .ok_or(PredictionError::InvalidSignature)?;
+  let signed = data
+    .get(message_offset..message_offset + message_size)
+    .ok_or(PredictionError::InvalidSignature)?;
+  require!(pubkey == signer.as_ref(), PredictionError::InvalidSignature);
+  Ok(signed.to_vec())
+}
 
-  #[account(mut)]
-  pub user: Signer<'info>,
+// ----------------------------
+// LST collateral
+// ----------------------------
 
-  #[account(
-    mut,
-    constraint = user_collateral_ata.mint == market.collateral_mint,
-    constraint = user_collateral_ata.owner == user.key(),
-  )]
-  pub user_collateral_ata: Account<'info, TokenAccount>,
+// SPL stake pool `StakePool` layout offsets (after the 1-byte account type)
+const STAKE_POOL_MINT_OFFSET: usize = 162;
+const STAKE_POOL_TOTAL_LAMPORTS_OFFSET: usize = 258;
+const STAKE_POOL_SUPPLY_OFFSET: usize = 266;
+
+/// Lamports per pool token in PRICE_SCALE, read from an SPL stake pool whose
+/// pool mint must be `collateral_mint`.
+fn read_stake_pool_rate(pool: &AccountInfo, collateral_mint: &Pubkey) -> Result<u64> {
+  require_keys_eq!(
+    *pool.owner,
+    SPL_STAKE_POOL_PROGRAM_ID,
+    PredictionError::InvalidStakePool
+  );
+  let data = pool.try_borrow_data()?;
+  require!(
+    data.len() >= STAKE_POOL_SUPPLY_OFFSET + 8 && data[0] == 1,
+    PredictionError::InvalidStakePool
+  );
+
+  let pool_mint = Pubkey::try_from(&data[STAKE_POOL_MINT_OFFSET..STAKE_POOL_MINT_OFFSET + 32])
+    .map_err(|_| PredictionError::InvalidStakePool)?;
+  require_keys_eq!(pool_mint, *collateral_mint, PredictionError::InvalidStakePool);
 
-  pub token_program: Program<'info, Token>,
+  let read_u64 = |at: usize| u64::from_le_bytes(data[at..at + 8].try_into().unwrap());
+  let total_lamports = read_u64(STAKE_POOL_TOTAL_LAMPORTS_OFFSET);
+  let supply = read_u64(STAKE_POOL_SUPPLY_OFFSET);
+  require!(supply > 0, PredictionError::InvalidStakePool);
+
+  mul_div(total_lamports, PRICE_SCALE, supply)
 }
 
 // ----------------------------
-// CPMM Math (swap-style)
+// Pricing (1e6 fixed-point)
 // ----------------------------
 
-// Buying YES: add net_in to NO reserve, take YES out.
-fn cpmm_buy_yes(yes_pool: u64, no_pool: u64, net_in: u64) -> Result<(u64, u64, u64)> {
-  require!(yes_pool > 0 && no_pool > 0, PredictionError::InvalidLiquidity);
-  let x = yes_pool as u128;
-  let y = no_pool as u128;
-  let dy = net_in as u128;
-
-  let k = x.checked_mul(y).ok_or(PredictionError::MathOverflow)?;
-  let y_new = y.checked_add(dy).ok_or(PredictionError::MathOverflow)?;
-  let x_new = k.checked_div(y_new).ok_or(PredictionError::MathOverflow)?;
+/// Value of `yes` / `no` shares when YES pays `fraction` (PRICE_SCALE) per
+/// share and NO the complement.
+fn split_shares_value(yes: u64, no: u64, fraction: u64) -> Result<u64> {
+  let short = PRICE_SCALE
+    .checked_sub(fraction)
+    .ok_or(PredictionError::MathOverflow)?;
+  mul_div(yes, fraction, PRICE_SCALE)?
+    .checked_add(mul_div(no, short, PRICE_SCALE)?)
+    .ok_or_else(|| PredictionError::MathOverflow.into())
+}
 
-  let out = x.checked_sub(x_new).ok_or(PredictionError::MathOverflow)?;
-  let out_u64: u64 = out.try_into().map_err(|_| PredictionError::MathOverflow)?;
+/// `a * b / c` in u128, floored.
+fn mul_div(a: u64, b: u64, c: u64) -> Result<u64> {
+  quote::mul_div(a, b, c).map_err(quote_error)
+}
 
-  Ok((
-    x_new.try_into().map_err(|_| PredictionError::MathOverflow)?,
-    y_new.try_into().map_err(|_| PredictionError::MathOverflow)?,
-    out_u64,
-  ))
+// YES is priced by the opposite reserve: p_yes = no_pool / (yes_pool + no_pool).
+pub fn implied_probability(yes_pool: u64, no_pool: u64, outcome_index: u8) -> Result<u64> {
+  quote::implied_probability(yes_pool, no_pool, outcome_index).map_err(quote_error)
 }
 
-// Buying NO: add net_in to YES reserve, take NO out.
-fn cpmm_buy_no(yes_pool: u64, no_pool: u64, net_in: u64) -> Result<(u64, u64, u64)> {
-  require!(yes_pool > 0 && no_pool > 0, PredictionError::InvalidLiquidity);
-  let x = no_pool as u128;
-  let y = yes_pool as u128;
-  let dy = net_in as u128;
+/// Share of `pool` owed for `shares` claimed after `claimed` of `total`:
+/// the step in the running total `pool * claimed / total`. Flooring the
+/// running total instead of each claim keeps every payout within one unit
+/// of its exact share while the payouts sum to exactly `pool`, whatever
+/// the claim order.
+pub fn pro_rata_step(pool: u64, claimed: u64, shares: u64, total: u64) -> Result<u64> {
+  let claimed_after = claimed
+    .checked_add(shares)
+    .filter(|c| *c <= total)
+    .ok_or(PredictionError::MathOverflow)?;
+  let paid_before = mul_div(pool, claimed, total)?;
+  let paid_after = mul_div(pool, claimed_after, total)?;
+  paid_after
+    .checked_sub(paid_before)
+    .ok_or_else(|| PredictionError::MathOverflow.into())
+}
 
-  let k = x.checked_mul(y).ok_or(PredictionError::MathOverflow)?;
-  let y_new = y.checked_add(dy).ok_or(PredictionError::MathOverflow)?;
-  let x_new = k.checked_div(y_new).ok_or(PredictionError::MathOverflow)?;
+/// Collateral per winning share in PRICE_SCALE; 0 when nothing is payable.
+pub fn payout_rate(payable: u64, total_winning_shares: u64) -> Result<u64> {
+  if total_winning_shares == 0 {
+    return Ok(0);
+  }
+  mul_div(payable, PRICE_SCALE, total_winning_shares)
+}
 
-  let out = x.checked_sub(x_new).ok_or(PredictionError::MathOverflow)?;
-  let out_u64: u64 = out.try_into().map_err(|_| PredictionError::MathOverflow)?;
+// Average execution price (collateral per share) in PRICE_SCALE. Shares carry
+// the collateral's decimals, so the ratio is decimal-independent.
+pub fn price_per_share(collateral: u64, shares: u64) -> Result<u64> {
+  require!(shares > 0, PredictionError::ZeroSharesOut);
+  let p = (collateral as u128)
+    .checked_mul(PRICE_SCALE as u128)
+    .ok_or(PredictionError::MathOverflow)?
+    .checked_div(shares as u128)
+    .ok_or(PredictionError::MathOverflow)?;
 
-  Ok((
-    y_new.try_into().map_err(|_| PredictionError::MathOverflow)?,
-    x_new.try_into().map_err(|_| PredictionError::MathOverflow)?,
-    out_u64,
-  ))
+  p.try_into().map_err(|_| PredictionError::MathOverflow.into())
 }
 
-// Selling YES: add shares_in to YES reserve, take NO out.
-fn cpmm_sell_yes(yes_pool: u64, no_pool: u64, shares_in: u64) -> Result<(u64, u64, u64)> {
-  require!(yes_pool > 0 && no_pool > 0, PredictionError::InvalidLiquidity);
-  let x = yes_pool as u128;
-  let y = no_pool as u128;
-  let dx = shares_in as u128;
+// Normalize a raw collateral amount to PRICE_SCALE units (6 decimals), so
+// 1 USDC (1e6 @ 6dp) and 1 SOL (1e9 @ 9dp) both map to 1_000_000.
+pub fn to_price_scale(amount: u64, decimals: u8) -> Result<u64> {
+  let scaled = match decimals.cmp(&6) {
+    std::cmp::Ordering::Equal => amount as u128,
+    std::cmp::Ordering::Greater => {
+      (amount as u128) / 10u128.pow((decimals - 6) as u32)
+    }
+    std::cmp::Ordering::Less => (amount as u128)
+      .checked_mul(10u128.pow((6 - decimals) as u32))
+      .ok_or(PredictionError::MathOverflow)?,
+  };
 
-  let k = x.checked_mul(y).ok_or(PredictionError::MathOverflow)?;
-  let x_new = x.checked_add(dx).ok_or(PredictionError::MathOverflow)?;
-  let y_new = k.checked_div(x_new).ok_or(PredictionError::MathOverflow)?;
-  let out = y.checked_sub(y_new).ok_or(PredictionError::MathOverflow)?;
+  scaled.try_into().map_err(|_| PredictionError::MathOverflow.into())
+}
+
+// ----------------------------
+// Collateral policy
+// ----------------------------
 
-  Ok((
-    x_new.try_into().map_err(|_| PredictionError::MathOverflow)?,
-    y_new.try_into().map_err(|_| PredictionError::MathOverflow)?,
-    out.try_into().map_err(|_| PredictionError::MathOverflow)?,
-  ))
+/// The mint must be whitelisted (or the config permissionless) and pass the
+/// config's extension policy.
+fn check_collateral_allowed(config: &ProtocolConfig, mint: &InterfaceAccount<Mint>) -> Result<()> {
+  require!(
+    config.permissionless_collateral || config.approved_mints.contains(&mint.key()),
+    PredictionError::MintNotApproved
+  );
+  validate_collateral_mint(&mint.to_account_info(), &config.collateral_policy)
 }
 
-// Selling NO: add shares_in to NO reserve, take YES out.
-fn cpmm_sell_no(yes_pool: u64, no_pool: u64, shares_in: u64) -> Result<(u64, u64, u64)> {
-  require!(yes_pool > 0 && no_pool > 0, PredictionError::InvalidLiquidity);
-  let x = no_pool as u128;
-  let y = yes_pool as u128;
-  let dx = shares_in as u128;
+// Reject collateral mints whose authorities or Token-2022 extensions could
+// freeze, claw back, or silently tax the vault, unless the policy allows it.
+fn validate_collateral_mint(mint: &AccountInfo, policy: &CollateralPolicy) -> Result<()> {
+  let data = mint.try_borrow_data()?;
+  let state = StateWithExtensions::<MintState>::unpack(&data)?;
+
+  if state.base.freeze_authority.is_some() {
+    require!(
+      policy.allow_freeze_authority,
+      PredictionError::CollateralMintNotAllowed
+    );
+  }
+
+  for extension in state.get_extension_types()? {
+    let allowed = match extension {
+      ExtensionType::TransferFeeConfig => policy.allow_transfer_fee,
+      ExtensionType::TransferHook => policy.allow_transfer_hook,
+      ExtensionType::PermanentDelegate => policy.allow_permanent_delegate,
+      ExtensionType::DefaultAccountState => policy.allow_default_account_state,
+      ExtensionType::Pausable => policy.allow_pausable,
+      ExtensionType::MintCloseAuthority
+      | ExtensionType::InterestBearingConfig
+      | ExtensionType::ScaledUiAmount
+      | ExtensionType::MetadataPointer
+      | ExtensionType::TokenMetadata
+      | ExtensionType::GroupPointer
+      | ExtensionType::TokenGroup
+      | ExtensionType::GroupMemberPointer
+      | ExtensionType::TokenGroupMember => true,
+      _ => false,
+    };
 
-  let k = x.checked_mul(y).ok_or(PredictionError::MathOverflow)?;
-  let x_new = x.checked_add(dx).ok_or(PredictionError::MathOverflow)?;
-  let y_new = k.checked_div(x_new).ok_or(PredictionError::MathOverflow)?;
-  let out = y.checked_sub(y_new).ok_or(PredictionError::MathOverflow)?;
+    if !allowed {
+      msg!("Collateral mint extension not allowed: {:?}", extension);
+      return err!(PredictionError::CollateralMintNotAllowed);
+    }
+  }
 
-  Ok((
-    y_new.try_into().map_err(|_| PredictionError::MathOverflow)?,
-    x_new.try_into().map_err(|_| PredictionError::MathOverflow)?,
-    out.try_into().map_err(|_| PredictionError::MathOverflow)?,
-  ))
+  Ok(())
 }
 
 // ----------------------------
 // Fees
 // ----------------------------
-fn apply_fee_in(gross_in: u64) -> Result<(u64, u64)> {
-  let fee = gross_in
-    .checked_mul(FEE_BPS)
-    .ok_or(PredictionError::MathOverflow)?
-    .checked_div(BPS_DENOM)
-    .ok_or(PredictionError::MathOverflow)?;
+// Fees are capped at `MAX_FEE_BPS` (see `quote::fee_for`).
+fn apply_fee_in(gross_in: u64, fee_bps: u64) -> Result<(u64, u64)> {
+  quote::apply_fee_in(gross_in, fee_bps).map_err(quote_error)
+}
 
-  let net = gross_in.checked_sub(fee).ok_or(PredictionError::MathOverflow)?;
-  Ok((net, fee))
+fn apply_fee_out(gross_out: u64, fee_bps: u64) -> Result<(u64, u64)> {
+  quote::apply_fee_out(gross_out, fee_bps).map_err(quote_error)
 }
 
-fn apply_fee_out(gross_out: u64) -> Result<(u64, u64)> {
-  let fee = gross_out
-    .checked_mul(FEE_BPS)
-    .ok_or(PredictionError::MathOverflow)?
-    .checked_div(BPS_DENOM)
+/// The market's emissions, which must be passed once liquidity mining is
+/// configured so no LP share change skips the reward checkpoint. Settles
+/// `lp_position` up to `now`; call `LpRewards::reset_debt` after the change.
+fn checkpoint_lp_rewards<'a>(
+  market: &MarketV3,
+  rewards: Option<&'a mut LpRewards>,
+  lp_position: &mut LpPosition,
+  now: i64,
+) -> Result<Option<&'a mut LpRewards>> {
+  require!(
+    market.lp_rewards == 0 || rewards.is_some(),
+    PredictionError::LpRewardsRequired
+  );
+  if let Some(rewards) = rewards {
+    rewards.update(market.total_lp_shares, now)?;
+    rewards.settle(lp_position)?;
+    return Ok(Some(rewards));
+  }
+  Ok(None)
+}
+
+/// Record the market's post-trade YES probability in its candles, which
+/// must be passed once they're initialized.
+fn record_price_candle(
+  market: &MarketV3,
+  candles: Option<&AccountLoader<PriceCandles>>,
+  now: i64,
+) -> Result<()> {
+  require!(
+    market.price_candles == 0 || candles.is_some(),
+    PredictionError::PriceCandlesRequired
+  );
+  if let Some(candles) = candles {
+    candles.load_mut()?.record(market.implied_probability(0)?, now);
+  }
+  Ok(())
+}
+
+/// Append an AMM trade to the market's tape, which must be passed once it's
+/// initialized.
+fn record_tape_trade(
+  market: &MarketV3,
+  tape: Option<&AccountLoader<TradeTape>>,
+  side: u8,
+  outcome: u8,
+  collateral: u64,
+  shares: u64,
+  now: i64,
+) -> Result<()> {
+  require!(
+    market.trade_tape == 0 || tape.is_some(),
+    PredictionError::TradeTapeRequired
+  );
+  let Some(tape) = tape else {
+    return Ok(());
+  };
+  let price = if shares > 0 {
+    mul_div(collateral, PRICE_SCALE, shares)?
+  } else {
+    0
+  };
+  tape.load_mut()?.push(TapeTrade {
+    timestamp: now,
+    collateral,
+    shares,
+    price,
+    side,
+    outcome,
+    _padding: [0; 6],
+  });
+  Ok(())
+}
+
+/// Refresh the market's price feed, which must be passed once it's
+/// initialized.
+fn refresh_price_feed(market: &MarketV3, feed: Option<&AccountLoader<PriceFeed>>, now: i64) -> Result<()> {
+  require!(
+    market.price_feed == 0 || feed.is_some(),
+    PredictionError::PriceFeedRequired
+  );
+  match feed {
+    Some(feed) => feed.load_mut()?.refresh(market, now),
+    None => Ok(()),
+  }
+}
+
+/// Count a newly created market (and its seed liquidity) in the protocol
+/// stats, if passed.
+fn count_created_market(stats: Option<&mut ProtocolStats>, market: &mut MarketV3) -> Result<()> {
+  let Some(stats) = stats else {
+    return Ok(());
+  };
+  stats.markets_created = stats
+    .markets_created
+    .checked_add(1)
     .ok_or(PredictionError::MathOverflow)?;
+  stats.sync_tvl(market)
+}
+
+/// Gated markets only trade with the user's `AllowlistEntry`; its seeds
+/// already tie it to the market and user.
+fn check_allowlist(market: &MarketV3, entry: Option<&AllowlistEntry>) -> Result<()> {
+  require!(
+    market.allowlist == 0 || entry.is_some(),
+    PredictionError::NotAllowlisted
+  );
+  Ok(())
+}
+
+/// Permit-gated markets only trade behind a fresh `TradePermit` for this
+/// market and user, signed by the gate's operator. The permit's ed25519
+/// verification may sit anywhere before this instruction, so one
+/// transaction can carry a permit per party next to other signed messages.
+fn check_trade_permit(
+  market: &MarketV3,
+  market_key: Pubkey,
+  gate: Option<&PermitGate>,
+  instructions: Option<&UncheckedAccount>,
+  user: Pubkey,
+  now: i64,
+) -> Result<()> {
+  if market.trade_permit == 0 {
+    return Ok(());
+  }
+  let (Some(gate), Some(instructions)) = (gate, instructions) else {
+    return err!(PredictionError::TradePermitRequired);
+  };
+  let current = ix_sysvar::load_current_index_checked(instructions)? as usize;
+  let permit = (0..current)
+    .rev()
+    .filter_map(|index| ed25519_signed_message_at(instructions, index, &gate.operator).ok())
+    .filter_map(|message| TradePermit::try_from_slice(&message).ok())
+    .find(|permit| permit.market == market_key && permit.wallet == user)
+    .ok_or(PredictionError::TradePermitRequired)?;
+  require!(
+    now <= permit.expiry && permit.expiry - now <= MAX_PERMIT_SECS,
+    PredictionError::TradePermitExpired
+  );
+  Ok(())
+}
+
+/// Buyers of a token-gated market must pass a token account of theirs
+/// holding at least the gate's minimum of its mint.
+fn check_token_gate(
+  market: &MarketV3,
+  gate: Option<&TokenGate>,
+  holding: Option<&TokenAccount>,
+  user: Pubkey,
+) -> Result<()> {
+  if market.token_gate == 0 {
+    return Ok(());
+  }
+  let (Some(gate), Some(holding)) = (gate, holding) else {
+    return err!(PredictionError::TokenGateNotMet);
+  };
+  require!(
+    holding.owner == user && holding.mint == gate.mint && holding.amount >= gate.min_balance,
+    PredictionError::TokenGateNotMet
+  );
+  Ok(())
+}
+
+/// Count an AMM trade in the market's stats, which must be passed once
+/// they're initialized.
+fn record_market_trade(
+  market: &MarketV3,
+  stats: Option<&mut MarketStats>,
+  notional: u64,
+  fee: u64,
+  new_trader: bool,
+  now: i64,
+) -> Result<()> {
+  require!(
+    market.market_stats == 0 || stats.is_some(),
+    PredictionError::MarketStatsRequired
+  );
+  match stats {
+    Some(stats) => stats.record_trade(notional, fee, new_trader, now),
+    None => Ok(()),
+  }
+}
 
-  let net = gross_out.checked_sub(fee).ok_or(PredictionError::MathOverflow)?;
-  Ok((net, fee))
+/// Credit `trader`'s maker rebate on `notional` traded, if they're a
+/// designated maker. Moved out of the LP fees, capped at what's accrued.
+fn accrue_mm_rebate(
+  market: &mut MarketV3,
+  makers: &mut MarketMakers,
+  trader: Pubkey,
+  notional: u64,
+) -> Result<()> {
+  let rebate_bps = makers.rebate_bps as u64;
+  let Some(entry) = makers.makers.iter_mut().find(|e| e.maker == trader) else {
+    return Ok(());
+  };
+  let rebate = mul_div(notional, rebate_bps, BPS_DENOM)?.min(market.lp_fees);
+
+  market.lp_fees -= rebate;
+  market.mm_rebates = market
+    .mm_rebates
+    .checked_add(rebate)
+    .ok_or(PredictionError::MathOverflow)?;
+  entry.accrued = entry
+    .accrued
+    .checked_add(rebate)
+    .ok_or(PredictionError::MathOverflow)?;
+  Ok(())
 }
 
 // ----------------------------
@@ -812,6 +17142,616 @@ pub enum PredictionError {
   ZeroSharesOut,
   #[msg("Insufficient shares to sell")]
   InsufficientShares,
+  #[msg("Collateral token account required for this market")]
+  MissingCollateralAccount,
+  #[msg("wSOL unwrap account is only used by native SOL markets")]
+  UnexpectedWsolAccount,
+  #[msg("Collateral mint is not allowed by the protocol policy")]
+  CollateralMintNotAllowed,
+  #[msg("Collateral mint is not on the approved list")]
+  MintNotApproved,
+  #[msg("Collateral mint is already approved")]
+  MintAlreadyApproved,
+  #[msg("Approved collateral mint list is full")]
+  ApprovedMintsFull,
+  #[msg("Metadata URI is too long")]
+  MetadataUriTooLong,
+  #[msg("Question hash must be set")]
+  InvalidQuestionHash,
+  #[msg("Account does not match the legacy layout being migrated")]
+  InvalidMigrationAccount,
+  #[msg("Invalid market category")]
+  InvalidCategory,
+  #[msg("Market has already traded")]
+  MarketAlreadyTraded,
+  #[msg("Market is already registered")]
+  MarketAlreadyRegistered,
+  #[msg("Registry page missing or does not match the market")]
+  MissingRegistryPage,
+  #[msg("Invalid dispute parameters")]
+  InvalidDisputeParams,
+  #[msg("Dispute window is still open")]
+  DisputeWindowOpen,
+  #[msg("Dispute window has closed")]
+  DisputeWindowClosed,
+  #[msg("Creator bond was slashed")]
+  BondSlashed,
+  #[msg("Market is paused")]
+  MarketPaused,
+  #[msg("Invalid report reason")]
+  InvalidReportReason,
+  #[msg("Invalid report action")]
+  InvalidReportAction,
+  #[msg("start_time must be before end_time")]
+  InvalidStartTime,
+  #[msg("Market has not started trading yet")]
+  MarketNotStarted,
+  #[msg("Liquidity provision is not enabled for this market")]
+  LiquidityNotEnabled,
+  #[msg("Invalid opening auction parameters")]
+  InvalidAuctionParams,
+  #[msg("Liquidity is locked until the market resolves")]
+  LiquidityLocked,
+  #[msg("Fee shares exceed 100%")]
+  InvalidFeeShares,
+  #[msg("Vault holds less than the recorded balance")]
+  VaultShortfall,
+  #[msg("Market is solvent; no top-up needed")]
+  MarketSolvent,
+  #[msg("Vault yield is not enabled")]
+  YieldNotEnabled,
+  #[msg("Collateral is still deployed to the lending program")]
+  YieldDeployed,
+  #[msg("Lending program moved an unexpected amount")]
+  YieldTransferMismatch,
+  #[msg("Not an SPL stake pool for the collateral mint")]
+  InvalidStakePool,
+  #[msg("LST market requires its stake pool account")]
+  MissingStakePool,
+  #[msg("Insufficient trading account balance")]
+  InsufficientBalance,
+  #[msg("Position holds no offsetting YES/NO shares")]
+  NothingToNet,
+  #[msg("Market is already in the portfolio")]
+  MarketAlreadyTracked,
+  #[msg("Portfolio is full")]
+  PortfolioFull,
+  #[msg("Invalid remaining accounts")]
+  InvalidRemainingAccounts,
+  #[msg("Target market must differ and share the collateral mint")]
+  InvalidTargetMarket,
+  #[msg("Offer is priced differently")]
+  OfferAskMismatch,
+  #[msg("Maker and taker must differ")]
+  SelfTrade,
+  #[msg("Invalid order side")]
+  InvalidOrderSide,
+  #[msg("Limit price must be between 0 and 1")]
+  InvalidLimitPrice,
+  #[msg("Order already filled")]
+  OrderFilled,
+  #[msg("Stop trigger must be below 1 and only on sell orders")]
+  InvalidTriggerPrice,
+  #[msg("Stop-loss trigger not reached")]
+  StopNotTriggered,
+  #[msg("Invalid oracle account")]
+  InvalidOracle,
+  #[msg("Oracle price is stale")]
+  StaleOracle,
+  #[msg("Oracle trigger condition not met")]
+  OracleTriggerNotMet,
+  #[msg("Invalid TWAP slice count or interval")]
+  InvalidSliceCount,
+  #[msg("Next TWAP slice is not due yet")]
+  SliceNotDue,
+  #[msg("Missing or invalid ed25519 signature")]
+  InvalidSignature,
+  #[msg("Quote has expired")]
+  QuoteExpired,
+  #[msg("Quote is for a different taker")]
+  QuoteTakerMismatch,
+  #[msg("Post-only order would cross the book")]
+  OrderWouldCross,
+  #[msg("Order book side is full")]
+  OrderBookFull,
+  #[msg("Order not found")]
+  OrderNotFound,
+  #[msg("Market belongs to a different (or no) negative-risk group")]
+  GroupedMarket,
+  #[msg("Group membership is frozen after the first conversion")]
+  GroupFrozen,
+  #[msg("Group is full")]
+  GroupFull,
+  #[msg("Group needs at least two members")]
+  GroupTooSmall,
+  #[msg("Parlay legs must be 2-8 distinct open markets in the same collateral")]
+  InvalidParlayLegs,
+  #[msg("Parlay pool cannot cover the payout")]
+  InsufficientPoolLiquidity,
+  #[msg("Parlay legs are not final yet")]
+  ParlayNotSettled,
+  #[msg("Parent market must be resolved first")]
+  ParentNotResolved,
+  #[msg("Next series market cannot be created yet")]
+  SeriesNotDue,
+  #[msg("Template is not active")]
+  TemplateInactive,
+  #[msg("Template params must be 1-128 bytes")]
+  InvalidTemplateParams,
+  #[msg("Invalid claim window")]
+  InvalidClaimWindow,
+  #[msg("Market is not part of the same series")]
+  NotInSeries,
+  #[msg("Claim window has closed")]
+  ClaimWindowClosed,
+  #[msg("Claim window is still open")]
+  ClaimWindowOpen,
+  #[msg("Scalar range must have lower < upper")]
+  InvalidScalarRange,
+  #[msg("Scalar markets settle from their oracle")]
+  ScalarMarket,
+  #[msg("Market has not reached its end time")]
+  MarketNotEnded,
+  #[msg("Oracle confidence interval too wide")]
+  OracleConfidenceTooWide,
+  #[msg("Payout vector must be [yes, no] summing to PRICE_SCALE")]
+  InvalidPayoutVector,
+  #[msg("Correction window has closed")]
+  CorrectionWindowClosed,
+  #[msg("Claims are still in the post-resolution delay")]
+  ClaimDelayActive,
+  #[msg("Resolver bounty bps exceeds 100%")]
+  InvalidResolverBounty,
+  #[msg("Oracle-configured markets resolve via resolve_with_oracle")]
+  OracleMarket,
+  #[msg("Market is not oracle-settled")]
+  NotOracleMarket,
+  #[msg("Oracle resolution timeout has not passed")]
+  OracleTimeoutPending,
+  #[msg("Oracle adapter is not supported yet")]
+  UnsupportedOracleAdapter,
+  #[msg("Account is not a Squads multisig")]
+  InvalidMultisig,
+  #[msg("Resolution multisig is already set")]
+  ResolutionMultisigSet,
+  #[msg("Change must be queued while a parameter timelock is set")]
+  TimelockRequired,
+  #[msg("Queued change is still timelocked")]
+  TimelockPending,
+  #[msg("Fee exceeds the hard cap")]
+  FeeCapExceeded,
+  #[msg("Fee holiday must end after it starts")]
+  InvalidFeeHoliday,
+  #[msg("Too many or duplicate market makers")]
+  TooManyMarketMakers,
+  #[msg("Signer is not a designated market maker")]
+  NotMarketMaker,
+  #[msg("Removed market maker has an unclaimed rebate")]
+  MakerRebateOutstanding,
+  #[msg("Market has liquidity mining: pass its LpRewards account")]
+  LpRewardsRequired,
+  #[msg("Invalid emissions schedule")]
+  InvalidEmissions,
+  #[msg("Invalid competition")]
+  InvalidCompetition,
+  #[msg("Competition hasn't ended")]
+  CompetitionNotEnded,
+  #[msg("Competition submissions are closed")]
+  CompetitionSubmissionsClosed,
+  #[msg("Competition entry already submitted")]
+  EntryAlreadySubmitted,
+  #[msg("Market stats account required")]
+  MarketStatsRequired,
+  #[msg("Invalid candle interval")]
+  InvalidCandleInterval,
+  #[msg("Price candles account required")]
+  PriceCandlesRequired,
+  #[msg("Trade tape account required")]
+  TradeTapeRequired,
+  #[msg("Price feed account required")]
+  PriceFeedRequired,
+  #[msg("Position size limit exceeded")]
+  PositionLimitExceeded,
+  #[msg("Trade is below the minimum size")]
+  TradeTooSmall,
+  #[msg("Wallet is not allowlisted for this market")]
+  NotAllowlisted,
+  #[msg("Buyer doesn't hold the market's gate token")]
+  TokenGateNotMet,
+  #[msg("Missing or invalid trade permit")]
+  TradePermitRequired,
+  #[msg("Trade permit expired or valid for too long")]
+  TradePermitExpired,
+  #[msg("Session expiry must be in the future and within the maximum length")]
+  InvalidSessionExpiry,
+  #[msg("Session expired")]
+  SessionExpired,
+  #[msg("Session spend cap exceeded")]
+  SessionSpendCapExceeded,
+  #[msg("Signer is not the collateral account's delegate")]
+  NotDelegate,
+  #[msg("Swap must be between the collateral mint and one of the market's outcome mints")]
+  InvalidSwapMints,
+  #[msg("Market pays out through its payout distributor")]
+  PayoutsViaDistributor,
+  #[msg("Payouts exceed what the market owes winners")]
+  PayoutExceedsPayable,
+  #[msg("Invalid Merkle proof")]
+  InvalidMerkleProof,
+  #[msg("Outcome label too long")]
+  OutcomeLabelTooLong,
+  #[msg("Group has no catch-all outcome")]
+  NoCatchAllOutcome,
+  #[msg("Invalid payout mode")]
+  InvalidPayoutMode,
+  #[msg("Only winner-take-all groups support this")]
+  WinnerTakeAllOnly,
+  #[msg("Rank weights already set")]
+  RankWeightsAlreadySet,
+  #[msg("Ranking must place each member at most once, one per weighted place")]
+  InvalidRanking,
+  #[msg("New end time must be later than the current one")]
+  InvalidEndTime,
+  #[msg("End time extended past the maximum")]
+  EndTimeExtensionCapExceeded,
+  #[msg("Yield program has no lending adapter")]
+  UnsupportedYieldProgram,
+  #[msg("Lending program or reserve differs from the market's yield position")]
+  YieldReserveMismatch,
+  #[msg("Parlay leg's spot price is too far from its candle reference")]
+  ParlayLegPriceDeviation,
 //   #[msg("Invalid liquidity")]
 //   InvalidLiquidity, // keep if you used earlier; otherwise remove duplicates
 }
+
+// ----------------------------
+// Off-chain client helpers
+// ----------------------------
+
+/// PDA derivation and typed instruction builders for off-chain Rust clients
+/// (`client` feature). Builders go through the generated `accounts` /
+/// `instruction` structs, so a changed account list fails to compile instead
+/// of producing a transaction that silently breaks.
+#[cfg(feature = "client")]
+pub mod client {
+  use super::*;
+  use anchor_lang::{InstructionData, ToAccountMetas};
+  use anchor_spl::associated_token::get_associated_token_address_with_program_id;
+
+  pub fn config_pda() -> Pubkey {
+    Pubkey::find_program_address(&[b"config"], &ID).0
+  }
+
+  pub fn market_pda(authority: &Pubkey, market_id: u64) -> Pubkey {
+    Pubkey::find_program_address(&[b"market_v2", authority.as_ref(), &market_id.to_le_bytes()], &ID).0
+  }
+
+  pub fn vault_pda(market: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"vault_v2", market.as_ref()], &ID).0
+  }
+
+  pub fn vault_authority_pda(market: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"vault_auth_v2", market.as_ref()], &ID).0
+  }
+
+  pub fn position_pda(market: &Pubkey, owner: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"position_v2", market.as_ref(), owner.as_ref()], &ID).0
+  }
+
+  pub fn lp_position_pda(market: &Pubkey, owner: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"lp_position", market.as_ref(), owner.as_ref()], &ID).0
+  }
+
+  pub fn wsol_unwrap_pda(market: &Pubkey, owner: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"wsol_unwrap", market.as_ref(), owner.as_ref()], &ID).0
+  }
+
+  pub fn user_stats_pda(owner: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"user_stats", owner.as_ref()], &ID).0
+  }
+
+  pub fn protocol_stats_pda() -> Pubkey {
+    Pubkey::find_program_address(&[b"protocol_stats"], &ID).0
+  }
+
+  pub fn allowlist_entry_pda(market: &Pubkey, wallet: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"allowlist", market.as_ref(), wallet.as_ref()], &ID).0
+  }
+
+  pub fn outcome_mint_pda(market: &Pubkey, outcome: u8) -> Pubkey {
+    Pubkey::find_program_address(&[b"outcome_mint", market.as_ref(), &[outcome]], &ID).0
+  }
+
+  fn market_pda_of(seed: &[u8], market: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[seed, market.as_ref()], &ID).0
+  }
+
+  /// Accounts of a `buy_shares` / `sell_shares` call. `new` fills in every
+  /// account the market currently requires; the rest are opt-in. `payer`
+  /// defaults to the user; point it at a relayer to sponsor rent.
+  #[derive(Clone, Debug)]
+  pub struct TradeAccounts {
+    pub market: Pubkey,
+    pub user: Pubkey,
+    pub payer: Pubkey,
+    pub collateral_mint: Pubkey,
+    pub token_program: Pubkey,
+    pub market_makers: Option<Pubkey>,
+    pub user_stats: Option<Pubkey>,
+    pub competition_entry: Option<Pubkey>,
+    pub market_stats: Option<Pubkey>,
+    pub protocol_stats: Option<Pubkey>,
+    pub price_candles: Option<Pubkey>,
+    pub trade_tape: Option<Pubkey>,
+    pub price_feed: Option<Pubkey>,
+    pub allowlist_entry: Option<Pubkey>,
+    // Permit-gated markets: the ed25519 verification of the permit must be
+    // placed right before the trade instruction
+    pub permit_gate: Option<Pubkey>,
+    // Buys on token-gated markets also need the user's `gate_token_account`
+    // of the gate mint, which `new` can't know
+    pub token_gate: Option<Pubkey>,
+    pub gate_token_account: Option<Pubkey>,
+  }
+
+  impl TradeAccounts {
+    pub fn new(market_key: Pubkey, market: &MarketV3, user: Pubkey, token_program: Pubkey) -> Self {
+      let required = |flag: u8, seed: &[u8]| (flag == 1).then(|| market_pda_of(seed, &market_key));
+      Self {
+        market: market_key,
+        user,
+        payer: user,
+        collateral_mint: market.collateral_mint,
+        token_program,
+        market_makers: None,
+        user_stats: None,
+        competition_entry: None,
+        market_stats: required(market.market_stats, b"market_stats"),
+        protocol_stats: None,
+        price_candles: required(market.price_candles, b"price_candles"),
+        trade_tape: required(market.trade_tape, b"trade_tape"),
+        price_feed: required(market.price_feed, b"price_feed"),
+        allowlist_entry: (market.allowlist == 1).then(|| allowlist_entry_pda(&market_key, &user)),
+        permit_gate: required(market.trade_permit, b"permit_gate"),
+        token_gate: required(market.token_gate, b"token_gate"),
+        gate_token_account: None,
+      }
+    }
+
+    fn is_native(&self) -> bool {
+      self.collateral_mint == native_mint::ID
+    }
+
+    /// The user's collateral ATA; native SOL markets move lamports instead.
+    fn user_collateral_ata(&self) -> Option<Pubkey> {
+      (!self.is_native()).then(|| {
+        get_associated_token_address_with_program_id(&self.user, &self.collateral_mint, &self.token_program)
+      })
+    }
+  }
+
+  pub fn buy_shares(
+    t: &TradeAccounts,
+    outcome_index: u8,
+    max_collateral_in: u64,
+    min_shares_out: u64,
+  ) -> Instruction {
+    let accounts = crate::accounts::BuyShares {
+      config: config_pda(),
+      market: t.market,
+      vault: vault_pda(&t.market),
+      vault_authority: vault_authority_pda(&t.market),
+      position: position_pda(&t.market, &t.user),
+      user: t.user,
+      payer: t.payer,
+      collateral_mint: t.collateral_mint,
+      user_collateral_ata: t.user_collateral_ata(),
+      market_makers: t.market_makers,
+      user_stats: t.user_stats,
+      competition_entry: t.competition_entry,
+      market_stats: t.market_stats,
+      protocol_stats: t.protocol_stats,
+      price_candles: t.price_candles,
+      trade_tape: t.trade_tape,
+      price_feed: t.price_feed,
+      allowlist_entry: t.allowlist_entry,
+      permit_gate: t.permit_gate,
+      instructions: t.permit_gate.map(|_| ix_sysvar::ID),
+      token_gate: t.token_gate,
+      gate_token_account: t.gate_token_account,
+      token_program: t.token_program,
+      system_program: system_program::ID,
+      rent: anchor_lang::solana_program::sysvar::rent::ID,
+    };
+    Instruction {
+      program_id: ID,
+      accounts: accounts.to_account_metas(None),
+      data: crate::instruction::BuyShares {
+        outcome_index,
+        max_collateral_in,
+        min_shares_out,
+      }
+      .data(),
+    }
+  }
+
+  pub fn sell_shares(
+    t: &TradeAccounts,
+    outcome_index: u8,
+    shares_in: u64,
+    min_collateral_out: u64,
+  ) -> Instruction {
+    let accounts = crate::accounts::SellShares {
+      config: config_pda(),
+      market: t.market,
+      vault: vault_pda(&t.market),
+      vault_authority: vault_authority_pda(&t.market),
+      position: position_pda(&t.market, &t.user),
+      user: t.user,
+      payer: t.payer,
+      collateral_mint: t.collateral_mint,
+      user_collateral_ata: t.user_collateral_ata(),
+      wsol_unwrap: t.is_native().then(|| wsol_unwrap_pda(&t.market, &t.user)),
+      market_makers: t.market_makers,
+      user_stats: t.user_stats,
+      competition_entry: t.competition_entry,
+      market_stats: t.market_stats,
+      protocol_stats: t.protocol_stats,
+      price_candles: t.price_candles,
+      trade_tape: t.trade_tape,
+      price_feed: t.price_feed,
+      allowlist_entry: t.allowlist_entry,
+      permit_gate: t.permit_gate,
+      instructions: t.permit_gate.map(|_| ix_sysvar::ID),
+      token_program: t.token_program,
+      system_program: system_program::ID,
+    };
+    Instruction {
+      program_id: ID,
+      accounts: accounts.to_account_metas(None),
+      data: crate::instruction::SellShares {
+        outcome_index,
+        shares_in,
+        min_collateral_out,
+      }
+      .data(),
+    }
+  }
+
+  /// `swap` between the collateral and `outcome`'s token through `user`'s
+  /// ATAs: collateral in when `buy`, outcome tokens in otherwise.
+  #[allow(clippy::too_many_arguments)]
+  pub fn swap(
+    market_key: Pubkey,
+    market: &MarketV3,
+    user: Pubkey,
+    token_program: Pubkey,
+    outcome: u8,
+    buy: bool,
+    amount_in: u64,
+    minimum_amount_out: u64,
+  ) -> Instruction {
+    let required = |flag: u8, seed: &[u8]| (flag == 1).then(|| market_pda_of(seed, &market_key));
+    let outcome_mint = outcome_mint_pda(&market_key, outcome);
+    let (source_mint, destination_mint) = if buy {
+      (market.collateral_mint, outcome_mint)
+    } else {
+      (outcome_mint, market.collateral_mint)
+    };
+    let ata = |mint: &Pubkey| get_associated_token_address_with_program_id(&user, mint, &token_program);
+    let accounts = crate::accounts::Swap {
+      config: config_pda(),
+      market: market_key,
+      vault: vault_pda(&market_key),
+      vault_authority: vault_authority_pda(&market_key),
+      user_transfer_authority: user,
+      source_mint,
+      destination_mint,
+      user_source_token_account: ata(&source_mint),
+      user_destination_token_account: ata(&destination_mint),
+      market_stats: required(market.market_stats, b"market_stats"),
+      protocol_stats: None,
+      price_candles: required(market.price_candles, b"price_candles"),
+      trade_tape: required(market.trade_tape, b"trade_tape"),
+      price_feed: required(market.price_feed, b"price_feed"),
+      token_program,
+    };
+    Instruction {
+      program_id: ID,
+      accounts: accounts.to_account_metas(None),
+      data: crate::instruction::Swap {
+        amount_in,
+        minimum_amount_out,
+      }
+      .data(),
+    }
+  }
+
+  /// Accounts of a `claim_winnings_v2` call for `user`, paid by `payer`.
+  /// `creator_bond` must be set when the bond was slashed and
+  /// `claim_escrow` when the claim was escrowed.
+  #[derive(Clone, Debug)]
+  pub struct ClaimAccounts {
+    pub market: Pubkey,
+    pub user: Pubkey,
+    pub payer: Pubkey,
+    pub collateral_mint: Pubkey,
+    pub token_program: Pubkey,
+    pub creator_bond: Option<Pubkey>,
+    pub claim_escrow: Option<Pubkey>,
+    pub user_stats: Option<Pubkey>,
+    pub protocol_stats: Option<Pubkey>,
+  }
+
+  pub fn claim_winnings_v2(c: &ClaimAccounts) -> Instruction {
+    let native = c.collateral_mint == native_mint::ID;
+    let accounts = crate::accounts::ClaimWinningsV2 {
+      market: c.market,
+      vault: vault_pda(&c.market),
+      vault_authority: vault_authority_pda(&c.market),
+      position: position_pda(&c.market, &c.user),
+      user: c.user,
+      payer: c.payer,
+      collateral_mint: c.collateral_mint,
+      user_collateral_ata: (!native).then(|| {
+        get_associated_token_address_with_program_id(&c.user, &c.collateral_mint, &c.token_program)
+      }),
+      wsol_unwrap: native.then(|| wsol_unwrap_pda(&c.market, &c.user)),
+      creator_bond: c.creator_bond,
+      claim_escrow: c.claim_escrow,
+      user_stats: c.user_stats,
+      protocol_stats: c.protocol_stats,
+      token_program: c.token_program,
+      associated_token_program: anchor_spl::associated_token::ID,
+      system_program: system_program::ID,
+    };
+    Instruction {
+      program_id: ID,
+      accounts: accounts.to_account_metas(None),
+      data: crate::instruction::ClaimWinningsV2 {}.data(),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn position(cost_basis: u64, yes_shares: u64, no_shares: u64) -> PositionV2 {
+    PositionV2 {
+      market: Pubkey::default(),
+      owner: Pubkey::new_unique(),
+      yes_shares,
+      no_shares,
+      claimed: false,
+      bump: 0,
+      cost_basis,
+    }
+  }
+
+  // `void_unresolvable_market` past the oracle timeout: holders get back
+  // what they paid whatever the pool price, and LPs keep the rest
+  #[test]
+  fn voided_oracle_market_refunds_holders_at_cost() {
+    let holders = [position(100_000_000, 180_000_000, 0), position(300_000_000, 0, 450_000_000)];
+    let mut market = MarketV3::zeroed();
+    market.status = MarketStatus::Open as u8;
+    market.oracle_market = 1;
+    market.vault_balance = 1_400_000_000;
+    market.total_cost_basis = 400_000_000;
+    market.total_yes_shares = 180_000_000;
+    market.total_no_shares = 450_000_000;
+
+    market.cancel_at_cost(1_000).unwrap();
+    assert!(market.is_settled());
+    assert_eq!(market.winning_outcome, OUTCOME_REFUND_AT_COST);
+    assert_eq!(market.dispute_deadline, 1_000);
+    assert_eq!(market.residual_liquidity, 1_000_000_000);
+
+    for holder in &holders {
+      let units = market.position_winnings(holder).unwrap();
+      assert_eq!(market.pay_winnings(units).unwrap(), holder.cost_basis);
+    }
+    assert_eq!(market.vault_balance, 1_000_000_000);
+  }
+}
@@ -0,0 +1,282 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { PublicKey, SystemProgram } from "@solana/web3.js";
+import {
+  TOKEN_PROGRAM_ID,
+  createMint,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+} from "@solana/spl-token";
+import { expect } from "chai";
+
+import { PredictionProgramV2 } from "../target/types/prediction_program_v2";
+import {
+  airdrop,
+  buyAccounts,
+  createTestMarket,
+  ensureConfig,
+  pda,
+  sellAccounts,
+  uniqueId,
+} from "./utils";
+
+describe("prediction_program_v2 (allowlist-gated markets)", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace
+    .PredictionProgramV2 as Program<PredictionProgramV2>;
+
+  const payer = (provider.wallet as any).payer as anchor.web3.Keypair;
+  const wallet = provider.wallet as anchor.Wallet;
+
+  const user = anchor.web3.Keypair.generate();
+  const marketId = uniqueId();
+
+  let collateralMint: PublicKey;
+  let userAta: PublicKey;
+  let config: PublicKey;
+  let marketPda: PublicKey;
+  let vaultPda: PublicKey;
+  let vaultAuthPda: PublicKey;
+  let positionPda: PublicKey;
+  let entryPda: PublicKey;
+
+  const tradeAccounts = (allowlistEntry: PublicKey | null) => ({
+    config,
+    market: marketPda,
+    vault: vaultPda,
+    vaultAuthority: vaultAuthPda,
+    position: positionPda,
+    user: user.publicKey,
+    payer: user.publicKey,
+    collateralMint,
+    userCollateralAta: userAta,
+    allowlistEntry,
+  });
+
+  async function buy(amount: number, entry: PublicKey | null) {
+    await program.methods
+      .buyShares(0, new anchor.BN(amount), new anchor.BN(1))
+      .accounts(buyAccounts(tradeAccounts(entry)))
+      .signers([user])
+      .rpc({ commitment: "confirmed" });
+  }
+
+  async function sell(shares: anchor.BN, entry: PublicKey | null) {
+    await program.methods
+      .sellShares(0, shares, new anchor.BN(1))
+      .accounts(sellAccounts(tradeAccounts(entry)))
+      .signers([user])
+      .rpc({ commitment: "confirmed" });
+  }
+
+  it("setup: market + funded trader", async () => {
+    config = await ensureConfig(program, provider);
+
+    await airdrop(provider, user.publicKey);
+
+    collateralMint = await createMint(provider.connection, payer, wallet.publicKey, null, 6);
+    const authorityAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, wallet.publicKey)
+    ).address;
+    userAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, user.publicKey)
+    ).address;
+    await mintTo(provider.connection, payer, collateralMint, authorityAta, payer, 10_000_000_000);
+    await mintTo(provider.connection, payer, collateralMint, userAta, payer, 1_000_000_000);
+
+    marketPda = pda(program, [
+      Buffer.from("market_v2"),
+      wallet.publicKey.toBuffer(),
+      marketId.toArrayLike(Buffer, "le", 8),
+    ]);
+    vaultPda = pda(program, [Buffer.from("vault_v2"), marketPda.toBuffer()]);
+    vaultAuthPda = pda(program, [Buffer.from("vault_auth_v2"), marketPda.toBuffer()]);
+    positionPda = pda(program, [Buffer.from("position_v2"), marketPda.toBuffer(), user.publicKey.toBuffer()]);
+    entryPda = pda(program, [Buffer.from("allowlist"), marketPda.toBuffer(), user.publicKey.toBuffer()]);
+
+    await createTestMarket(program, provider, {
+      marketId,
+      question: "Will the regulated market resolve YES?",
+      metadataUri: "ar://kyc-market",
+      collateralMint,
+      authorityCollateralAta: authorityAta,
+    });
+  });
+
+  it("allow_wallet is restricted to the market authority", async () => {
+    let failed = false;
+    try {
+      await program.methods
+        .allowWallet(user.publicKey)
+        .accounts({
+          market: marketPda,
+          allowlistEntry: entryPda,
+          authority: user.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([user])
+        .rpc({ commitment: "confirmed" });
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("Unauthorized");
+    }
+    expect(failed).to.eq(true);
+  });
+
+  it("gated markets reject wallets without an entry", async () => {
+    await program.methods
+      .setAllowlistGate(true)
+      .accounts({ market: marketPda, authority: wallet.publicKey })
+      .rpc({ commitment: "confirmed" });
+
+    let failed = false;
+    try {
+      await buy(10_000_000, null);
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("NotAllowlisted");
+    }
+    expect(failed).to.eq(true);
+  });
+
+  it("allowlisted wallets trade; revoked ones can't", async () => {
+    await program.methods
+      .allowWallet(user.publicKey)
+      .accounts({
+        market: marketPda,
+        allowlistEntry: entryPda,
+        authority: wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc({ commitment: "confirmed" });
+
+    await buy(10_000_000, entryPda);
+    const position = await program.account.positionV2.fetch(positionPda);
+    expect(position.yesShares.toNumber()).to.be.greaterThan(0);
+
+    await program.methods
+      .revokeWallet()
+      .accounts({ market: marketPda, allowlistEntry: entryPda, authority: wallet.publicKey })
+      .rpc({ commitment: "confirmed" });
+    expect(await provider.connection.getAccountInfo(entryPda)).to.eq(null);
+
+    let failed = false;
+    try {
+      await sell(position.yesShares, null);
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("NotAllowlisted");
+    }
+    expect(failed).to.eq(true);
+  });
+
+  it("trading-account buys are gated too", async () => {
+    const tradingAccount = pda(program, [
+      Buffer.from("trading_account"),
+      user.publicKey.toBuffer(),
+      collateralMint.toBuffer(),
+    ]);
+    const custodyAuthority = pda(program, [Buffer.from("trading_custody_auth")]);
+    const custody = pda(program, [Buffer.from("trading_custody"), collateralMint.toBuffer()]);
+
+    await program.methods
+      .depositToTradingAccount(new anchor.BN(50_000_000))
+      .accounts({
+        tradingAccount,
+        custodyAuthority,
+        custody,
+        user: user.publicKey,
+        payer: user.publicKey,
+        collateralMint,
+        userCollateralAta: userAta,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([user])
+      .rpc({ commitment: "confirmed" });
+
+    let failed = false;
+    try {
+      await program.methods
+        .buyFromTradingAccount(0, new anchor.BN(10_000_000), new anchor.BN(1))
+        .accounts({
+          market: marketPda,
+          vault: vaultPda,
+          vaultAuthority: vaultAuthPda,
+          position: positionPda,
+          tradingAccount,
+          custodyAuthority,
+          custody,
+          user: user.publicKey,
+          payer: user.publicKey,
+          collateralMint,
+          allowlistEntry: null,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([user])
+        .rpc({ commitment: "confirmed" });
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("NotAllowlisted");
+    }
+    expect(failed).to.eq(true);
+  });
+
+  it("book bids are gated when placed, since their owner isn't present at fill", async () => {
+    const orderBook = pda(program, [Buffer.from("order_book"), marketPda.toBuffer()]);
+    await program.methods
+      .initOrderBook()
+      .accounts({
+        orderBook,
+        market: marketPda,
+        payer: wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc({ commitment: "confirmed" });
+
+    let failed = false;
+    try {
+      await program.methods
+        .placeBookOrder(0, new anchor.BN(100_000), new anchor.BN(10_000_000))
+        .accounts({
+          orderBook,
+          market: marketPda,
+          position: positionPda,
+          tradingAccount: pda(program, [
+            Buffer.from("trading_account"),
+            user.publicKey.toBuffer(),
+            collateralMint.toBuffer(),
+          ]),
+          owner: user.publicKey,
+          payer: user.publicKey,
+          allowlistEntry: null,
+          permitGate: null,
+          instructions: null,
+          tokenGate: null,
+          gateTokenAccount: null,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([user])
+        .rpc({ commitment: "confirmed" });
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("NotAllowlisted");
+    }
+    expect(failed).to.eq(true);
+  });
+
+  it("lifting the gate reopens trading", async () => {
+    await program.methods
+      .setAllowlistGate(false)
+      .accounts({ market: marketPda, authority: wallet.publicKey })
+      .rpc({ commitment: "confirmed" });
+
+    const position = await program.account.positionV2.fetch(positionPda);
+    await sell(position.yesShares, null);
+    const after = await program.account.positionV2.fetch(positionPda);
+    expect(after.yesShares.toNumber()).to.eq(0);
+  });
+});
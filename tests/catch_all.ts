@@ -0,0 +1,129 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { PublicKey, SystemProgram } from "@solana/web3.js";
+import {
+  TOKEN_PROGRAM_ID,
+  createMint,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+} from "@solana/spl-token";
+import { expect } from "chai";
+
+import { PredictionProgramV2 } from "../target/types/prediction_program_v2";
+import {
+  createTestMarket,
+  ensureConfig,
+  pda,
+  uniqueId,
+} from "./utils";
+
+const UNLISTED_OUTCOME = 255;
+
+describe("prediction_program_v2 (catch-all outcome)", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace
+    .PredictionProgramV2 as Program<PredictionProgramV2>;
+
+  const payer = (provider.wallet as any).payer as anchor.web3.Keypair;
+  const wallet = provider.wallet as anchor.Wallet;
+
+  const groupId = uniqueId();
+  const labels = ["Alice", "Bob", "Other"];
+
+  let collateralMint: PublicKey;
+  let group: PublicKey;
+  const markets: PublicKey[] = [];
+
+  const vaultPda = (market: PublicKey) => pda(program, [Buffer.from("vault_v2"), market.toBuffer()]);
+  const vaultAuthPda = (market: PublicKey) => pda(program, [Buffer.from("vault_auth_v2"), market.toBuffer()]);
+
+  async function resolveGroup(winnerIndex: number) {
+    await program.methods
+      .resolveGroup(winnerIndex)
+      .accounts({
+        group,
+        collateralMint,
+        authority: wallet.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .remainingAccounts(
+        markets.flatMap((m) => [
+          { pubkey: m, isWritable: true, isSigner: false },
+          { pubkey: vaultPda(m), isWritable: true, isSigner: false },
+          { pubkey: vaultAuthPda(m), isWritable: false, isSigner: false },
+        ])
+      )
+      .rpc({ commitment: "confirmed" });
+  }
+
+  it("setup: group with an Other member", async () => {
+    await ensureConfig(program, provider);
+
+    collateralMint = await createMint(provider.connection, payer, wallet.publicKey, null, 6);
+    const authorityAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, wallet.publicKey)
+    ).address;
+    await mintTo(provider.connection, payer, collateralMint, authorityAta, payer, 10_000_000_000);
+
+    group = pda(program, [Buffer.from("market_group"), wallet.publicKey.toBuffer(), groupId.toArrayLike(Buffer, "le", 8)]);
+    await program.methods
+      .createMarketGroup(groupId, 0)
+      .accounts({
+        group,
+        collateralMint,
+        authority: wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc({ commitment: "confirmed" });
+
+    for (const label of labels) {
+      const marketId = uniqueId();
+      const market = pda(program, [
+        Buffer.from("market_v2"),
+        wallet.publicKey.toBuffer(),
+        marketId.toArrayLike(Buffer, "le", 8),
+      ]);
+      await createTestMarket(program, provider, {
+        marketId,
+        question: `Will ${label} win the race?`,
+        metadataUri: `ar://race-${label.toLowerCase()}`,
+        collateralMint,
+        authorityCollateralAta: authorityAta,
+      });
+
+      await program.methods
+        .addGroupMember(label)
+        .accounts({ group, market, authority: wallet.publicKey })
+        .rpc({ commitment: "confirmed" });
+      markets.push(market);
+    }
+  });
+
+  it("an unlisted result can't resolve a group without a catch-all", async () => {
+    let failed = false;
+    try {
+      await resolveGroup(UNLISTED_OUTCOME);
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("NoCatchAllOutcome");
+    }
+    expect(failed).to.eq(true);
+  });
+
+  it("an unlisted result resolves the catch-all member YES", async () => {
+    await program.methods
+      .setGroupCatchAll(2)
+      .accounts({ group, authority: wallet.publicKey })
+      .rpc({ commitment: "confirmed" });
+    expect((await program.account.marketGroup.fetch(group)).catchAll).to.eq(2);
+
+    await resolveGroup(UNLISTED_OUTCOME);
+
+    const outcomes = await Promise.all(
+      markets.map(async (m) => (await program.account.marketV3.fetch(m)).winningOutcome)
+    );
+    expect(outcomes).to.deep.eq([1, 1, 0]);
+  });
+});
@@ -0,0 +1,167 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { PublicKey, SystemProgram } from "@solana/web3.js";
+import {
+  ASSOCIATED_TOKEN_PROGRAM_ID,
+  TOKEN_PROGRAM_ID,
+  createMint,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+} from "@solana/spl-token";
+import { expect } from "chai";
+
+import { PredictionProgramV2 } from "../target/types/prediction_program_v2";
+import {
+  REGISTRY_PAGE_SIZE,
+  airdrop,
+  buyAccounts,
+  createTestMarket,
+  ensureConfig,
+  pda,
+  registryPagePda,
+  uniqueId,
+} from "./utils";
+
+describe("prediction_program_v2 (claim delay after resolution)", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace
+    .PredictionProgramV2 as Program<PredictionProgramV2>;
+
+  const payer = (provider.wallet as any).payer as anchor.web3.Keypair;
+  const wallet = provider.wallet as anchor.Wallet;
+
+  const user = anchor.web3.Keypair.generate();
+  const marketId = uniqueId();
+
+  let collateralMint: PublicKey;
+  let authorityAta: PublicKey;
+  let userAta: PublicKey;
+  let config: PublicKey;
+  let marketPda: PublicKey;
+  let vaultPda: PublicKey;
+  let vaultAuthPda: PublicKey;
+  let positionPda: PublicKey;
+
+  it("setup: market with a 1h claim delay + a YES position", async () => {
+    config = await ensureConfig(program, provider);
+
+    await airdrop(provider, user.publicKey);
+
+    collateralMint = await createMint(provider.connection, payer, wallet.publicKey, null, 6);
+    authorityAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, wallet.publicKey)
+    ).address;
+    userAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, user.publicKey)
+    ).address;
+    await mintTo(provider.connection, payer, collateralMint, authorityAta, payer, 10_000_000_000);
+    await mintTo(provider.connection, payer, collateralMint, userAta, payer, 1_000_000_000);
+
+    marketPda = pda(program, [
+      Buffer.from("market_v2"),
+      wallet.publicKey.toBuffer(),
+      marketId.toArrayLike(Buffer, "le", 8),
+    ]);
+    vaultPda = pda(program, [Buffer.from("vault_v2"), marketPda.toBuffer()]);
+    vaultAuthPda = pda(program, [Buffer.from("vault_auth_v2"), marketPda.toBuffer()]);
+    positionPda = pda(program, [Buffer.from("position_v2"), marketPda.toBuffer(), user.publicKey.toBuffer()]);
+
+    await createTestMarket(program, provider, {
+      marketId,
+      question: "Will the merger close this quarter?",
+      metadataUri: "ar://merger-close-quarter",
+      collateralMint,
+      authorityCollateralAta: authorityAta,
+    });
+
+    await program.methods
+      .setClaimDelay(new anchor.BN(3600))
+      .accounts({ market: marketPda, authority: wallet.publicKey })
+      .rpc({ commitment: "confirmed" });
+
+    await program.methods
+      .buyShares(0, new anchor.BN(100_000_000), new anchor.BN(1))
+      .accounts(buyAccounts({
+        config,
+        market: marketPda,
+        vault: vaultPda,
+        vaultAuthority: vaultAuthPda,
+        position: positionPda,
+        user: user.publicKey,
+        payer: user.publicKey,
+        collateralMint,
+        userCollateralAta: userAta,
+      }))
+      .signers([user])
+      .rpc({ commitment: "confirmed" });
+  });
+
+  it("claim_winnings_v2 waits out the delay after resolution", async () => {
+    const market = await program.account.marketV3.fetch(marketPda);
+    await program.methods
+      .resolveMarket(0)
+      .accounts({
+        config,
+        market: marketPda,
+        vault: vaultPda,
+        registryPage: registryPagePda(
+          program,
+          Math.floor(market.registryIndex.toNumber() / REGISTRY_PAGE_SIZE)
+        ),
+        lstStakePool: null,
+        parentMarket: null,
+        authority: wallet.publicKey,
+      })
+      .rpc({ commitment: "confirmed" });
+
+    const resolved = await program.account.marketV3.fetch(marketPda);
+    expect(resolved.claimDelay.toNumber()).to.eq(3600);
+
+    let failed = false;
+    try {
+      await program.methods
+        .claimWinningsV2()
+        .accounts({
+          market: marketPda,
+          vault: vaultPda,
+          vaultAuthority: vaultAuthPda,
+          position: positionPda,
+          user: user.publicKey,
+          payer: user.publicKey,
+          collateralMint,
+          userCollateralAta: userAta,
+          wsolUnwrap: null,
+          creatorBond: null,
+          claimEscrow: null,
+          userStats: null,
+          protocolStats: null,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([user])
+        .rpc({ commitment: "confirmed" });
+    } catch (e: any) {
+      failed = true;
+      // The default dispute window may still be open as well
+      expect(String(e)).to.match(/ClaimDelayActive|DisputeWindowOpen/);
+    }
+    expect(failed).to.eq(true);
+  });
+
+  it("set_claim_delay is rejected once the market is resolved", async () => {
+    let failed = false;
+    try {
+      await program.methods
+        .setClaimDelay(new anchor.BN(0))
+        .accounts({ market: marketPda, authority: wallet.publicKey })
+        .rpc({ commitment: "confirmed" });
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("InvalidMarketStatus");
+    }
+    expect(failed).to.eq(true);
+  });
+});
@@ -0,0 +1,161 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { PublicKey } from "@solana/web3.js";
+import {
+  createMint,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+} from "@solana/spl-token";
+import { expect } from "chai";
+
+import { PredictionProgramV2 } from "../target/types/prediction_program_v2";
+import {
+  REGISTRY_PAGE_SIZE,
+  buyAccounts,
+  createTestMarket,
+  ensureConfig,
+  pda,
+  registryPagePda,
+  uniqueId,
+} from "./utils";
+
+describe("prediction_program_v2 (early trading close)", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace
+    .PredictionProgramV2 as Program<PredictionProgramV2>;
+
+  const payer = (provider.wallet as any).payer as anchor.web3.Keypair;
+  const wallet = provider.wallet as anchor.Wallet;
+
+  const marketId = uniqueId();
+  const endTime = Math.floor(Date.now() / 1000) + 3600;
+
+  let collateralMint: PublicKey;
+  let authorityAta: PublicKey;
+  let config: PublicKey;
+  let marketPda: PublicKey;
+  let vaultPda: PublicKey;
+  let vaultAuthPda: PublicKey;
+  let registryPage: PublicKey;
+
+  async function closeEarly(authority: anchor.web3.Keypair | null = null) {
+    const builder = program.methods.closeTradingEarly().accounts({
+      market: marketPda,
+      registryPage,
+      authority: authority ? authority.publicKey : wallet.publicKey,
+    });
+    await (authority ? builder.signers([authority]) : builder).rpc({ commitment: "confirmed" });
+  }
+
+  async function buy() {
+    await program.methods
+      .buyShares(0, new anchor.BN(10_000_000), new anchor.BN(1))
+      .accounts(buyAccounts({
+        config,
+        market: marketPda,
+        vault: vaultPda,
+        vaultAuthority: vaultAuthPda,
+        position: pda(program, [Buffer.from("position_v2"), marketPda.toBuffer(), wallet.publicKey.toBuffer()]),
+        user: wallet.publicKey,
+        payer: wallet.publicKey,
+        collateralMint,
+        userCollateralAta: authorityAta,
+      }))
+      .rpc({ commitment: "confirmed" });
+  }
+
+  it("setup: open market", async () => {
+    config = await ensureConfig(program, provider);
+
+    collateralMint = await createMint(provider.connection, payer, wallet.publicKey, null, 6);
+    authorityAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, wallet.publicKey)
+    ).address;
+    await mintTo(provider.connection, payer, collateralMint, authorityAta, payer, 10_000_000_000);
+
+    marketPda = pda(program, [
+      Buffer.from("market_v2"),
+      wallet.publicKey.toBuffer(),
+      marketId.toArrayLike(Buffer, "le", 8),
+    ]);
+    vaultPda = pda(program, [Buffer.from("vault_v2"), marketPda.toBuffer()]);
+    vaultAuthPda = pda(program, [Buffer.from("vault_auth_v2"), marketPda.toBuffer()]);
+
+    await createTestMarket(program, provider, {
+      marketId,
+      question: "Will the match go the distance?",
+      metadataUri: "ar://close-early",
+      collateralMint,
+      authorityCollateralAta: authorityAta,
+      endTime: new anchor.BN(endTime),
+    });
+
+    const market = await program.account.marketV3.fetch(marketPda);
+    registryPage = registryPagePda(program, Math.floor(market.registryIndex.toNumber() / REGISTRY_PAGE_SIZE));
+  });
+
+  it("only the authority or resolver can close trading", async () => {
+    await buy();
+
+    let failed = false;
+    try {
+      await closeEarly(anchor.web3.Keypair.generate());
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("Unauthorized");
+    }
+    expect(failed).to.eq(true);
+  });
+
+  it("a pre-market market can't be closed before it opens", async () => {
+    const now = Math.floor(Date.now() / 1000);
+    const { market } = await createTestMarket(program, provider, {
+      marketId: uniqueId(),
+      question: "Will the replay go to penalties?",
+      metadataUri: "ar://close-early-pre-market",
+      collateralMint,
+      authorityCollateralAta: authorityAta,
+      startTime: new anchor.BN(now + 3600),
+      endTime: new anchor.BN(now + 7200),
+    });
+    const registryIndex = (await program.account.marketV3.fetch(market)).registryIndex.toNumber();
+
+    let failed = false;
+    try {
+      await program.methods
+        .closeTradingEarly()
+        .accounts({
+          market,
+          registryPage: registryPagePda(program, Math.floor(registryIndex / REGISTRY_PAGE_SIZE)),
+          authority: wallet.publicKey,
+        })
+        .rpc({ commitment: "confirmed" });
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("MarketNotStarted");
+    }
+    expect(failed).to.eq(true);
+  });
+
+  it("closing early halts trading and moves end_time to now", async () => {
+    await closeEarly();
+
+    const market = await program.account.marketV3.fetch(marketPda);
+    expect(market.endTime.toNumber()).to.be.lessThan(endTime);
+
+    const page = await program.account.registryPage.fetch(registryPage);
+    const entry = page.entries.find((e: any) => e.market.equals(marketPda))!;
+    expect(entry.endTime.toNumber()).to.eq(market.endTime.toNumber());
+
+    let failed = false;
+    try {
+      await buy();
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("MarketExpired");
+    }
+    expect(failed).to.eq(true);
+  });
+});
@@ -0,0 +1,190 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { PublicKey, SystemProgram } from "@solana/web3.js";
+import {
+  TOKEN_PROGRAM_ID,
+  createMint,
+  getAccount,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+} from "@solana/spl-token";
+import { expect } from "chai";
+
+import { PredictionProgramV2 } from "../target/types/prediction_program_v2";
+import {
+  airdrop,
+  buyAccounts,
+  createTestMarket,
+  ensureConfig,
+  pda,
+  sellAccounts,
+  uniqueId,
+} from "./utils";
+
+describe("prediction_program_v2 (trading competitions)", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace
+    .PredictionProgramV2 as Program<PredictionProgramV2>;
+
+  const payer = (provider.wallet as any).payer as anchor.web3.Keypair;
+  const wallet = provider.wallet as anchor.Wallet;
+
+  const user = anchor.web3.Keypair.generate();
+  const marketId = uniqueId();
+  const competitionId = uniqueId();
+
+  let collateralMint: PublicKey;
+  let prizeMint: PublicKey;
+  let userAta: PublicKey;
+  let config: PublicKey;
+  let marketPda: PublicKey;
+  let vaultPda: PublicKey;
+  let vaultAuthPda: PublicKey;
+  let positionPda: PublicKey;
+  let competition: PublicKey;
+  let prizeVault: PublicKey;
+  let entry: PublicKey;
+
+  const tradeAccounts = () => ({
+    config,
+    market: marketPda,
+    vault: vaultPda,
+    vaultAuthority: vaultAuthPda,
+    position: positionPda,
+    user: user.publicKey,
+    payer: user.publicKey,
+    collateralMint,
+    userCollateralAta: userAta,
+    competitionEntry: entry,
+  });
+
+  it("setup: market + funded trader", async () => {
+    config = await ensureConfig(program, provider);
+
+    await airdrop(provider, user.publicKey);
+
+    collateralMint = await createMint(provider.connection, payer, wallet.publicKey, null, 6);
+    const authorityAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, wallet.publicKey)
+    ).address;
+    userAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, user.publicKey)
+    ).address;
+    await mintTo(provider.connection, payer, collateralMint, authorityAta, payer, 10_000_000_000);
+    await mintTo(provider.connection, payer, collateralMint, userAta, payer, 1_000_000_000);
+
+    marketPda = pda(program, [
+      Buffer.from("market_v2"),
+      wallet.publicKey.toBuffer(),
+      marketId.toArrayLike(Buffer, "le", 8),
+    ]);
+    vaultPda = pda(program, [Buffer.from("vault_v2"), marketPda.toBuffer()]);
+    vaultAuthPda = pda(program, [Buffer.from("vault_auth_v2"), marketPda.toBuffer()]);
+    positionPda = pda(program, [Buffer.from("position_v2"), marketPda.toBuffer(), user.publicKey.toBuffer()]);
+
+    await createTestMarket(program, provider, {
+      marketId,
+      question: "Will the playoffs go to game seven?",
+      metadataUri: "ar://playoffs-game-seven",
+      collateralMint,
+      authorityCollateralAta: authorityAta,
+    });
+  });
+
+  it("create_competition escrows the prizes", async () => {
+    prizeMint = await createMint(provider.connection, payer, wallet.publicKey, null, 6);
+    const adminPrizeAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, prizeMint, wallet.publicKey)
+    ).address;
+    await mintTo(provider.connection, payer, prizeMint, adminPrizeAta, payer, 1_000_000);
+
+    competition = pda(program, [Buffer.from("competition"), competitionId.toArrayLike(Buffer, "le", 8)]);
+    prizeVault = pda(program, [Buffer.from("competition_vault"), competition.toBuffer()]);
+    entry = pda(program, [Buffer.from("competition_entry"), competition.toBuffer(), user.publicKey.toBuffer()]);
+
+    const now = Math.floor(Date.now() / 1000);
+    await program.methods
+      .createCompetition({
+        id: competitionId,
+        start: new anchor.BN(now - 60),
+        end: new anchor.BN(now + 8),
+        markets: [marketPda],
+        prizes: [new anchor.BN(600_000), new anchor.BN(400_000)],
+      })
+      .accounts({
+        config,
+        competition,
+        prizeVault,
+        prizeMint,
+        adminPrizeAta,
+        admin: wallet.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc({ commitment: "confirmed" });
+
+    expect(Number((await getAccount(provider.connection, prizeVault)).amount)).to.eq(1_000_000);
+  });
+
+  it("sells realize PnL against the entry's tracked cost", async () => {
+    await program.methods
+      .joinCompetition()
+      .accounts({
+        competition,
+        competitionEntry: entry,
+        user: user.publicKey,
+        payer: user.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([user])
+      .rpc({ commitment: "confirmed" });
+
+    await program.methods
+      .buyShares(0, new anchor.BN(100_000_000), new anchor.BN(1))
+      .accounts(buyAccounts(tradeAccounts()))
+      .signers([user])
+      .rpc({ commitment: "confirmed" });
+
+    let record = await program.account.competitionEntry.fetch(entry);
+    expect(record.lots[0].yesCost.toNumber()).to.eq(100_000_000);
+    const bought = record.lots[0].yesShares;
+
+    await program.methods
+      .sellShares(0, bought, new anchor.BN(1))
+      .accounts(sellAccounts(tradeAccounts()))
+      .signers([user])
+      .rpc({ commitment: "confirmed" });
+
+    // A round trip against the pool loses the fees both ways
+    record = await program.account.competitionEntry.fetch(entry);
+    expect(record.lots[0].yesShares.toNumber()).to.eq(0);
+    expect(record.realizedPnl.toNumber()).to.be.lessThan(0);
+  });
+
+  it("submit_competition_entry ranks the entry once the competition ends", async () => {
+    await new Promise((r) => setTimeout(r, 9_000));
+
+    await program.methods
+      .submitCompetitionEntry()
+      .accounts({ competition, competitionEntry: entry })
+      .rpc({ commitment: "confirmed" });
+
+    const state = await program.account.competition.fetch(competition);
+    expect(state.leaders.length).to.eq(1);
+    expect(state.leaders[0].user.equals(user.publicKey)).to.eq(true);
+
+    let failed = false;
+    try {
+      await program.methods
+        .submitCompetitionEntry()
+        .accounts({ competition, competitionEntry: entry })
+        .rpc({ commitment: "confirmed" });
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("EntryAlreadySubmitted");
+    }
+    expect(failed).to.eq(true);
+  });
+});
@@ -0,0 +1,233 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { PublicKey, SystemProgram, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import {
+  TOKEN_PROGRAM_ID,
+  createMint,
+  getAccount,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+} from "@solana/spl-token";
+import { expect } from "chai";
+
+import { PredictionProgramV2 } from "../target/types/prediction_program_v2";
+import {
+  REGISTRY_PAGE_SIZE,
+  airdrop,
+  createTestMarket,
+  ensureConfig,
+  pda,
+  registryPagePda,
+  uniqueId,
+} from "./utils";
+
+const COMPRESSION_PROGRAM_ID = new PublicKey("cmtDvXumGCrqC1Age74AVPhSRVXJMd8PJS91L8KbNCK");
+const NOOP_PROGRAM_ID = new PublicKey("noopb9bkMVfRPU8AsbpTUg8AQkHtKwMYZiFUjNRtMmV");
+
+// ConcurrentMerkleTree<3, 8> with no canopy
+const MAX_DEPTH = 3;
+const MAX_BUFFER_SIZE = 8;
+const HEADER_SIZE = 56;
+const CHANGELOG_SIZE = 32 + 32 * MAX_DEPTH + 8;
+const TREE_SIZE = HEADER_SIZE + 24 + MAX_BUFFER_SIZE * CHANGELOG_SIZE + (32 * MAX_DEPTH + 32 + 8);
+
+describe("prediction_program_v2 (compressed positions)", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace
+    .PredictionProgramV2 as Program<PredictionProgramV2>;
+
+  const payer = (provider.wallet as any).payer as anchor.web3.Keypair;
+  const wallet = provider.wallet as anchor.Wallet;
+
+  const user = anchor.web3.Keypair.generate();
+  const merkleTree = anchor.web3.Keypair.generate();
+  const marketId = uniqueId();
+
+  let collateralMint: PublicKey;
+  let userAta: PublicKey;
+  let config: PublicKey;
+  let marketPda: PublicKey;
+  let vaultPda: PublicKey;
+  let vaultAuthPda: PublicKey;
+  let compressedPositions: PublicKey;
+  let boughtShares: anchor.BN;
+
+  // Root of the latest changelog entry and the rightmost leaf's proof,
+  // i.e. the proof of the last appended leaf
+  async function latestRootAndProof(): Promise<[number[], PublicKey[]]> {
+    const data = (await provider.connection.getAccountInfo(merkleTree.publicKey))!.data;
+    const tree = data.subarray(HEADER_SIZE);
+    const activeIndex = Number(tree.readBigUInt64LE(8));
+    const changelog = 24 + activeIndex * CHANGELOG_SIZE;
+    const root = Array.from(tree.subarray(changelog, changelog + 32));
+    const rightmost = 24 + MAX_BUFFER_SIZE * CHANGELOG_SIZE;
+    const proof = [...Array(MAX_DEPTH).keys()].map(
+      (i) => new PublicKey(tree.subarray(rightmost + 32 * i, rightmost + 32 * (i + 1)))
+    );
+    return [root, proof];
+  }
+
+  it("setup: market + position tree", async () => {
+    config = await ensureConfig(program, provider);
+
+    await airdrop(provider, user.publicKey, LAMPORTS_PER_SOL);
+
+    collateralMint = await createMint(provider.connection, payer, wallet.publicKey, null, 6);
+    const authorityAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, wallet.publicKey)
+    ).address;
+    userAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, user.publicKey)
+    ).address;
+    await mintTo(provider.connection, payer, collateralMint, authorityAta, payer, 10_000_000_000);
+    await mintTo(provider.connection, payer, collateralMint, userAta, payer, 1_000_000_000);
+
+    marketPda = pda(program, [
+      Buffer.from("market_v2"),
+      wallet.publicKey.toBuffer(),
+      marketId.toArrayLike(Buffer, "le", 8),
+    ]);
+    vaultPda = pda(program, [Buffer.from("vault_v2"), marketPda.toBuffer()]);
+    vaultAuthPda = pda(program, [Buffer.from("vault_auth_v2"), marketPda.toBuffer()]);
+    compressedPositions = pda(program, [Buffer.from("compressed_positions"), marketPda.toBuffer()]);
+
+    await createTestMarket(program, provider, {
+      marketId,
+      question: "Will micro-bets add up?",
+      metadataUri: "ar://compressed-positions",
+      collateralMint,
+      authorityCollateralAta: authorityAta,
+    });
+
+    const allocTree = SystemProgram.createAccount({
+      fromPubkey: wallet.publicKey,
+      newAccountPubkey: merkleTree.publicKey,
+      lamports: await provider.connection.getMinimumBalanceForRentExemption(TREE_SIZE),
+      space: TREE_SIZE,
+      programId: COMPRESSION_PROGRAM_ID,
+    });
+    await program.methods
+      .initCompressedPositions(MAX_DEPTH, MAX_BUFFER_SIZE)
+      .accounts({
+        config,
+        market: marketPda,
+        compressedPositions,
+        merkleTree: merkleTree.publicKey,
+        authority: wallet.publicKey,
+        compressionProgram: COMPRESSION_PROGRAM_ID,
+        noopProgram: NOOP_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .preInstructions([allocTree])
+      .signers([merkleTree])
+      .rpc({ commitment: "confirmed" });
+  });
+
+  it("buy_compressed appends a leaf instead of creating a position", async () => {
+    const before = await program.account.marketV3.fetch(marketPda);
+
+    await program.methods
+      .buyCompressed(0, new anchor.BN(1_000_000), new anchor.BN(1))
+      .accounts({
+        config,
+        market: marketPda,
+        vault: vaultPda,
+        compressedPositions,
+        merkleTree: merkleTree.publicKey,
+        user: user.publicKey,
+        collateralMint,
+        userCollateralAta: userAta,
+        marketStats: null,
+        protocolStats: null,
+        priceCandles: null,
+        tradeTape: null,
+        priceFeed: null,
+        compressionProgram: COMPRESSION_PROGRAM_ID,
+        noopProgram: NOOP_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([user])
+      .rpc({ commitment: "confirmed" });
+
+    const tree = await program.account.compressedPositions.fetch(compressedPositions);
+    expect(tree.numLeaves.toNumber()).to.eq(1);
+
+    const after = await program.account.marketV3.fetch(marketPda);
+    boughtShares = after.totalYesShares.sub(before.totalYesShares);
+    expect(boughtShares.toNumber()).to.be.greaterThan(0);
+
+    const positionPda = pda(program, [Buffer.from("position_v2"), marketPda.toBuffer(), user.publicKey.toBuffer()]);
+    expect(await provider.connection.getAccountInfo(positionPda)).to.eq(null);
+  });
+
+  async function claim(shares: anchor.BN) {
+    const [root, proof] = await latestRootAndProof();
+    await program.methods
+      .claimCompressed(root, new anchor.BN(0), 0, shares)
+      .accounts({
+        market: marketPda,
+        vault: vaultPda,
+        vaultAuthority: vaultAuthPda,
+        compressedPositions,
+        merkleTree: merkleTree.publicKey,
+        user: user.publicKey,
+        payer: user.publicKey,
+        collateralMint,
+        userCollateralAta: userAta,
+        wsolUnwrap: null,
+        compressionProgram: COMPRESSION_PROGRAM_ID,
+        noopProgram: NOOP_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .remainingAccounts(proof.map((pubkey) => ({ pubkey, isSigner: false, isWritable: false })))
+      .signers([user])
+      .rpc({ commitment: "confirmed" });
+  }
+
+  it("claim_compressed pays the leaf once it is proven", async () => {
+    const market = await program.account.marketV3.fetch(marketPda);
+    await program.methods
+      .resolveMarketPayouts([new anchor.BN(1_000_000), new anchor.BN(0)])
+      .accounts({
+        config,
+        market: marketPda,
+        vault: vaultPda,
+        registryPage: registryPagePda(
+          program,
+          Math.floor(market.registryIndex.toNumber() / REGISTRY_PAGE_SIZE)
+        ),
+        lstStakePool: null,
+        parentMarket: null,
+        authority: wallet.publicKey,
+      })
+      .rpc({ commitment: "confirmed" });
+
+    // A leaf with inflated shares doesn't match the tree
+    let failed = false;
+    try {
+      await claim(boughtShares.muln(2));
+    } catch (e: any) {
+      failed = true;
+    }
+    expect(failed).to.eq(true);
+
+    const before = Number((await getAccount(provider.connection, userAta)).amount);
+    await claim(boughtShares);
+    const after = Number((await getAccount(provider.connection, userAta)).amount);
+    expect(after).to.be.greaterThan(before);
+  });
+
+  it("a claimed leaf can't be claimed again", async () => {
+    let failed = false;
+    try {
+      await claim(boughtShares);
+    } catch (e: any) {
+      failed = true;
+    }
+    expect(failed).to.eq(true);
+  });
+});
@@ -0,0 +1,178 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { PublicKey, SystemProgram } from "@solana/web3.js";
+import {
+  ASSOCIATED_TOKEN_PROGRAM_ID,
+  TOKEN_PROGRAM_ID,
+  createMint,
+  getAccount,
+  getAssociatedTokenAddressSync,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+} from "@solana/spl-token";
+import { expect } from "chai";
+
+import { PredictionProgramV2 } from "../target/types/prediction_program_v2";
+import {
+  REGISTRY_PAGE_SIZE,
+  createTestMarket,
+  ensureConfig,
+  pda,
+  registryPagePda,
+  uniqueId,
+} from "./utils";
+
+describe("prediction_program_v2 (outcome tokens + conditional markets)", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace
+    .PredictionProgramV2 as Program<PredictionProgramV2>;
+
+  const payer = (provider.wallet as any).payer as anchor.web3.Keypair;
+  const wallet = provider.wallet as anchor.Wallet;
+
+
+  let collateralMint: PublicKey;
+  let authorityAta: PublicKey;
+  let config: PublicKey;
+  let parent: PublicKey;
+  let child: PublicKey;
+  let yesMint: PublicKey;
+  let yesAta: PublicKey;
+
+  const vaultPda = (market: PublicKey) => pda(program, [Buffer.from("vault_v2"), market.toBuffer()]);
+  const vaultAuthPda = (market: PublicKey) => pda(program, [Buffer.from("vault_auth_v2"), market.toBuffer()]);
+  const positionPda = (market: PublicKey) =>
+    pda(program, [Buffer.from("position_v2"), market.toBuffer(), wallet.publicKey.toBuffer()]);
+  const outcomeMintPda = (market: PublicKey, outcome: number) =>
+    pda(program, [Buffer.from("outcome_mint"), market.toBuffer(), Buffer.from([outcome])]);
+
+  const createMarket = async (question: string, mint: PublicKey, ata: PublicKey) => {
+    const marketId = uniqueId();
+    const { market } = await createTestMarket(program, provider, {
+      marketId,
+      question,
+      metadataUri: `ar://conditional-${marketId}`,
+      collateralMint: mint,
+      authorityCollateralAta: ata,
+      initialYesReserve: new anchor.BN(100_000_000),
+      initialNoReserve: new anchor.BN(100_000_000),
+    });
+    return market;
+  };
+
+  it("setup: parent market + YES outcome mint", async () => {
+    config = await ensureConfig(program, provider);
+
+    collateralMint = await createMint(provider.connection, payer, wallet.publicKey, null, 6);
+    authorityAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, wallet.publicKey)
+    ).address;
+    await mintTo(provider.connection, payer, collateralMint, authorityAta, payer, 10_000_000_000);
+
+    parent = await createMarket("Will the incumbent be re-elected?", collateralMint, authorityAta);
+
+    yesMint = outcomeMintPda(parent, 0);
+    await program.methods
+      .initOutcomeMint(0)
+      .accounts({
+        market: parent,
+        vaultAuthority: vaultAuthPda(parent),
+        outcomeMint: yesMint,
+        collateralMint,
+        payer: wallet.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc({ commitment: "confirmed" });
+  });
+
+  it("tokenize_shares + redeem_outcome_tokens round-trip YES shares", async () => {
+    await program.methods
+      .splitShares(new anchor.BN(500_000_000))
+      .accounts({
+        market: parent,
+        vault: vaultPda(parent),
+        position: positionPda(parent),
+        user: wallet.publicKey,
+        payer: wallet.publicKey,
+        collateralMint,
+        userCollateralAta: authorityAta,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc({ commitment: "confirmed" });
+    const before = await program.account.positionV2.fetch(positionPda(parent));
+
+    yesAta = getAssociatedTokenAddressSync(yesMint, wallet.publicKey);
+    await program.methods
+      .tokenizeShares(0, new anchor.BN(300_000_000))
+      .accounts({
+        market: parent,
+        vaultAuthority: vaultAuthPda(parent),
+        outcomeMint: yesMint,
+        position: positionPda(parent),
+        user: wallet.publicKey,
+        payer: wallet.publicKey,
+        userOutcomeAta: yesAta,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc({ commitment: "confirmed" });
+    expect(Number((await getAccount(provider.connection, yesAta)).amount)).to.eq(300_000_000);
+
+    await program.methods
+      .redeemOutcomeTokens(0, new anchor.BN(100_000_000))
+      .accounts({
+        market: parent,
+        outcomeMint: yesMint,
+        position: positionPda(parent),
+        user: wallet.publicKey,
+        payer: wallet.publicKey,
+        userOutcomeAta: yesAta,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc({ commitment: "confirmed" });
+
+    const after = await program.account.positionV2.fetch(positionPda(parent));
+    expect(before.yesShares.toNumber() - after.yesShares.toNumber()).to.eq(200_000_000);
+    expect(Number((await getAccount(provider.connection, yesAta)).amount)).to.eq(200_000_000);
+  });
+
+  it("a child market collateralized by parent YES tokens waits for the parent", async () => {
+    child = await createMarket("If re-elected, will the tax bill pass?", yesMint, yesAta);
+
+    await program.methods
+      .setParentMarket(0)
+      .accounts({ market: child, parentMarket: parent, authority: wallet.publicKey })
+      .rpc({ commitment: "confirmed" });
+    const market = await program.account.marketV3.fetch(child);
+    expect(market.parentMarket.toBase58()).to.eq(parent.toBase58());
+
+    let failed = false;
+    try {
+      await program.methods
+        .resolveMarket(0)
+        .accounts({
+          config,
+          market: child,
+          vault: vaultPda(child),
+          registryPage: registryPagePda(
+            program,
+            Math.floor(market.registryIndex.toNumber() / REGISTRY_PAGE_SIZE)
+          ),
+          lstStakePool: null,
+          parentMarket: parent,
+          authority: wallet.publicKey,
+        })
+        .rpc({ commitment: "confirmed" });
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("ParentNotResolved");
+    }
+    expect(failed).to.eq(true);
+  });
+});
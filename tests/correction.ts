@@ -0,0 +1,181 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { PublicKey, SystemProgram } from "@solana/web3.js";
+import {
+  createMint,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+} from "@solana/spl-token";
+import { expect } from "chai";
+
+import { PredictionProgramV2 } from "../target/types/prediction_program_v2";
+import {
+  REGISTRY_PAGE_SIZE,
+  airdrop,
+  buyAccounts,
+  createTestMarket,
+  ensureConfig,
+  pda,
+  registryPagePda,
+  uniqueId,
+} from "./utils";
+
+describe("prediction_program_v2 (resolution correction window)", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace
+    .PredictionProgramV2 as Program<PredictionProgramV2>;
+
+  const payer = (provider.wallet as any).payer as anchor.web3.Keypair;
+  const wallet = provider.wallet as anchor.Wallet;
+
+  const user = anchor.web3.Keypair.generate();
+  const marketId = uniqueId();
+
+  let collateralMint: PublicKey;
+  let authorityAta: PublicKey;
+  let userAta: PublicKey;
+  let config: PublicKey;
+  let marketPda: PublicKey;
+  let vaultPda: PublicKey;
+  let vaultAuthPda: PublicKey;
+  let positionPda: PublicKey;
+  let escrowPda: PublicKey;
+
+  it("setup: market + a YES/NO position", async () => {
+    config = await ensureConfig(program, provider);
+
+    await airdrop(provider, user.publicKey);
+
+    collateralMint = await createMint(provider.connection, payer, wallet.publicKey, null, 6);
+    authorityAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, wallet.publicKey)
+    ).address;
+    userAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, user.publicKey)
+    ).address;
+    await mintTo(provider.connection, payer, collateralMint, authorityAta, payer, 10_000_000_000);
+    await mintTo(provider.connection, payer, collateralMint, userAta, payer, 1_000_000_000);
+
+    marketPda = pda(program, [
+      Buffer.from("market_v2"),
+      wallet.publicKey.toBuffer(),
+      marketId.toArrayLike(Buffer, "le", 8),
+    ]);
+    vaultPda = pda(program, [Buffer.from("vault_v2"), marketPda.toBuffer()]);
+    vaultAuthPda = pda(program, [Buffer.from("vault_auth_v2"), marketPda.toBuffer()]);
+    positionPda = pda(program, [Buffer.from("position_v2"), marketPda.toBuffer(), user.publicKey.toBuffer()]);
+    escrowPda = pda(program, [Buffer.from("claim_escrow"), marketPda.toBuffer(), user.publicKey.toBuffer()]);
+
+    await createTestMarket(program, provider, {
+      marketId,
+      question: "Did the home side win on penalties?",
+      metadataUri: "ar://home-side-penalties",
+      collateralMint,
+      authorityCollateralAta: authorityAta,
+    });
+
+    await program.methods
+      .buyShares(0, new anchor.BN(100_000_000), new anchor.BN(1))
+      .accounts(buyAccounts({
+        config,
+        market: marketPda,
+        vault: vaultPda,
+        vaultAuthority: vaultAuthPda,
+        position: positionPda,
+        user: user.publicKey,
+        payer: user.publicKey,
+        collateralMint,
+        userCollateralAta: userAta,
+      }))
+      .signers([user])
+      .rpc({ commitment: "confirmed" });
+  });
+
+  it("resolution opens a correction window that holds back payouts", async () => {
+    await program.methods
+      .setCorrectionWindow(new anchor.BN(3600))
+      .accounts({ config, admin: wallet.publicKey })
+      .rpc({ commitment: "confirmed" });
+
+    const market = await program.account.marketV3.fetch(marketPda);
+    try {
+      await program.methods
+        .resolveMarket(1) // wrong outcome, corrected below
+        .accounts({
+          config,
+          market: marketPda,
+          vault: vaultPda,
+          registryPage: registryPagePda(
+            program,
+            Math.floor(market.registryIndex.toNumber() / REGISTRY_PAGE_SIZE)
+          ),
+          lstStakePool: null,
+          parentMarket: null,
+          authority: wallet.publicKey,
+        })
+        .rpc({ commitment: "confirmed" });
+    } finally {
+      // Other suites share the config
+      await program.methods
+        .setCorrectionWindow(new anchor.BN(0))
+        .accounts({ config, admin: wallet.publicKey })
+        .rpc({ commitment: "confirmed" });
+    }
+
+    const resolved = await program.account.marketV3.fetch(marketPda);
+    expect(resolved.correctionDeadline.toNumber()).to.eq(resolved.resolvedAt.toNumber() + 3600);
+    expect(resolved.disputeDeadline.toNumber()).to.be.at.least(resolved.correctionDeadline.toNumber());
+  });
+
+  it("escrow_claim moves the position's shares into escrow", async () => {
+    const before = await program.account.positionV2.fetch(positionPda);
+
+    await program.methods
+      .escrowClaim()
+      .accounts({
+        market: marketPda,
+        position: positionPda,
+        claimEscrow: escrowPda,
+        user: user.publicKey,
+        payer: user.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([user])
+      .rpc({ commitment: "confirmed" });
+
+    const escrow = await program.account.claimEscrow.fetch(escrowPda);
+    expect(escrow.yesShares.toNumber()).to.eq(before.yesShares.toNumber());
+    expect(escrow.noShares.toNumber()).to.eq(before.noShares.toNumber());
+    const after = await program.account.positionV2.fetch(positionPda);
+    expect(after.yesShares.toNumber()).to.eq(0);
+    expect(after.noShares.toNumber()).to.eq(0);
+  });
+
+  it("correct_resolution: only the resolver or admin can flip the outcome", async () => {
+    let failed = false;
+    try {
+      await program.methods
+        .correctResolution(0)
+        .accounts({ config, market: marketPda, authority: user.publicKey })
+        .signers([user])
+        .rpc({ commitment: "confirmed" });
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("Unauthorized");
+    }
+    expect(failed).to.eq(true);
+
+    const before = await program.account.marketV3.fetch(marketPda);
+    await program.methods
+      .correctResolution(0)
+      .accounts({ config, market: marketPda, authority: wallet.publicKey })
+      .rpc({ commitment: "confirmed" });
+
+    const after = await program.account.marketV3.fetch(marketPda);
+    expect(after.winningOutcome).to.eq(0);
+    expect(after.resolvedVaultBalance.toNumber()).to.eq(before.resolvedVaultBalance.toNumber());
+    expect(after.resolvedTotalWinningShares.toNumber()).to.eq(after.totalYesShares.toNumber());
+  });
+});
@@ -0,0 +1,139 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { PublicKey, SystemProgram } from "@solana/web3.js";
+import {
+  TOKEN_PROGRAM_ID,
+  approve,
+  createMint,
+  getAccount,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+} from "@solana/spl-token";
+import { expect } from "chai";
+
+import { PredictionProgramV2 } from "../target/types/prediction_program_v2";
+import {
+  createTestMarket,
+  ensureConfig,
+  pda,
+  uniqueId,
+} from "./utils";
+
+describe("prediction_program_v2 (delegated buys)", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace
+    .PredictionProgramV2 as Program<PredictionProgramV2>;
+
+  const payer = (provider.wallet as any).payer as anchor.web3.Keypair;
+  const wallet = provider.wallet as anchor.Wallet;
+
+  const user = anchor.web3.Keypair.generate();
+  // Stands in for a router program's PDA
+  const router = anchor.web3.Keypair.generate();
+  const marketId = uniqueId();
+  const allowance = 60_000_000;
+
+  let collateralMint: PublicKey;
+  let userAta: PublicKey;
+  let config: PublicKey;
+  let marketPda: PublicKey;
+  let vaultPda: PublicKey;
+  let positionPda: PublicKey;
+
+  async function delegatedBuy(delegate: anchor.web3.Keypair, amount: number) {
+    await program.methods
+      .buySharesDelegated(0, new anchor.BN(amount), new anchor.BN(1))
+      .accounts({
+        config,
+        market: marketPda,
+        vault: vaultPda,
+        position: positionPda,
+        user: user.publicKey,
+        delegate: delegate.publicKey,
+        payer: wallet.publicKey,
+        collateralMint,
+        userCollateralAta: userAta,
+        marketStats: null,
+        protocolStats: null,
+        priceCandles: null,
+        tradeTape: null,
+        priceFeed: null,
+        allowlistEntry: null,
+        permitGate: null,
+        instructions: null,
+        tokenGate: null,
+        gateTokenAccount: null,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([delegate])
+      .rpc({ commitment: "confirmed" });
+  }
+
+  it("setup: market + user approving the router", async () => {
+    config = await ensureConfig(program, provider);
+
+    collateralMint = await createMint(provider.connection, payer, wallet.publicKey, null, 6);
+    const authorityAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, wallet.publicKey)
+    ).address;
+    userAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, user.publicKey)
+    ).address;
+    await mintTo(provider.connection, payer, collateralMint, authorityAta, payer, 10_000_000_000);
+    await mintTo(provider.connection, payer, collateralMint, userAta, payer, 1_000_000_000);
+    await approve(provider.connection, payer, userAta, router.publicKey, user, allowance);
+
+    marketPda = pda(program, [
+      Buffer.from("market_v2"),
+      wallet.publicKey.toBuffer(),
+      marketId.toArrayLike(Buffer, "le", 8),
+    ]);
+    vaultPda = pda(program, [Buffer.from("vault_v2"), marketPda.toBuffer()]);
+    positionPda = pda(program, [Buffer.from("position_v2"), marketPda.toBuffer(), user.publicKey.toBuffer()]);
+
+    await createTestMarket(program, provider, {
+      marketId,
+      question: "Will the router find liquidity?",
+      metadataUri: "ar://delegated-buys",
+      collateralMint,
+      authorityCollateralAta: authorityAta,
+    });
+  });
+
+  it("delegate buys into the user's position without the user signing", async () => {
+    await delegatedBuy(router, 40_000_000);
+
+    const position = await program.account.positionV2.fetch(positionPda);
+    expect(position.owner.toBase58()).to.eq(user.publicKey.toBase58());
+    expect(position.yesShares.toNumber()).to.be.greaterThan(0);
+
+    const ata = await getAccount(provider.connection, userAta);
+    expect(Number(ata.amount)).to.eq(960_000_000);
+    expect(Number(ata.delegatedAmount)).to.eq(allowance - 40_000_000);
+  });
+
+  it("buys beyond the remaining allowance fail", async () => {
+    let failed = false;
+    try {
+      await delegatedBuy(router, 40_000_000);
+    } catch (e: any) {
+      failed = true;
+    }
+    expect(failed).to.eq(true);
+  });
+
+  it("non-delegates are rejected", async () => {
+    const stranger = anchor.web3.Keypair.generate();
+    let failed = false;
+    try {
+      await delegatedBuy(stranger, 1_000_000);
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("NotDelegate");
+    }
+    expect(failed).to.eq(true);
+  });
+});
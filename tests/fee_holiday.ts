@@ -0,0 +1,148 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { PublicKey } from "@solana/web3.js";
+import {
+  createMint,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+} from "@solana/spl-token";
+import { expect } from "chai";
+
+import { PredictionProgramV2 } from "../target/types/prediction_program_v2";
+import {
+  airdrop,
+  buyAccounts,
+  createTestMarket,
+  ensureConfig,
+  pda,
+  uniqueId,
+} from "./utils";
+
+describe("prediction_program_v2 (promotional fee holidays)", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace
+    .PredictionProgramV2 as Program<PredictionProgramV2>;
+
+  const payer = (provider.wallet as any).payer as anchor.web3.Keypair;
+  const wallet = provider.wallet as anchor.Wallet;
+
+  const user = anchor.web3.Keypair.generate();
+  const marketId = uniqueId();
+
+  let collateralMint: PublicKey;
+  let userAta: PublicKey;
+  let config: PublicKey;
+  let marketPda: PublicKey;
+  let vaultPda: PublicKey;
+  let vaultAuthPda: PublicKey;
+  let positionPda: PublicKey;
+
+  const totalFees = (m: any) =>
+    m.lpFees.toNumber() +
+    m.creatorFees.toNumber() +
+    m.protocolFees.toNumber() +
+    m.insuranceFees.toNumber();
+
+  async function buy(amount: number) {
+    await program.methods
+      .buyShares(0, new anchor.BN(amount), new anchor.BN(1))
+      .accounts(buyAccounts({
+        config,
+        market: marketPda,
+        vault: vaultPda,
+        vaultAuthority: vaultAuthPda,
+        position: positionPda,
+        user: user.publicKey,
+        payer: user.publicKey,
+        collateralMint,
+        userCollateralAta: userAta,
+      }))
+      .signers([user])
+      .rpc({ commitment: "confirmed" });
+  }
+
+  it("setup: market + funded trader", async () => {
+    config = await ensureConfig(program, provider);
+
+    await airdrop(provider, user.publicKey);
+
+    collateralMint = await createMint(provider.connection, payer, wallet.publicKey, null, 6);
+    const authorityAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, wallet.publicKey)
+    ).address;
+    userAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, user.publicKey)
+    ).address;
+    await mintTo(provider.connection, payer, collateralMint, authorityAta, payer, 10_000_000_000);
+    await mintTo(provider.connection, payer, collateralMint, userAta, payer, 1_000_000_000);
+
+    marketPda = pda(program, [
+      Buffer.from("market_v2"),
+      wallet.publicKey.toBuffer(),
+      marketId.toArrayLike(Buffer, "le", 8),
+    ]);
+    vaultPda = pda(program, [Buffer.from("vault_v2"), marketPda.toBuffer()]);
+    vaultAuthPda = pda(program, [Buffer.from("vault_auth_v2"), marketPda.toBuffer()]);
+    positionPda = pda(program, [Buffer.from("position_v2"), marketPda.toBuffer(), user.publicKey.toBuffer()]);
+
+    await createTestMarket(program, provider, {
+      marketId,
+      question: "Will the launch token list on day one?",
+      metadataUri: "ar://launch-listing",
+      collateralMint,
+      authorityCollateralAta: authorityAta,
+    });
+  });
+
+  it("set_fee_holiday rejects strangers and inverted windows", async () => {
+    const now = Math.floor(Date.now() / 1000);
+    for (const [signer, start, end, error] of [
+      [user, now, now + 60, "Unauthorized"],
+      [null, now + 60, now, "InvalidFeeHoliday"],
+    ] as const) {
+      let failed = false;
+      try {
+        await program.methods
+          .setFeeHoliday(new anchor.BN(start), new anchor.BN(end))
+          .accounts({
+            config,
+            market: marketPda,
+            authority: signer ? signer.publicKey : wallet.publicKey,
+          })
+          .signers(signer ? [signer] : [])
+          .rpc({ commitment: "confirmed" });
+      } catch (e: any) {
+        failed = true;
+        expect(String(e)).to.contain(error);
+      }
+      expect(failed).to.eq(true);
+    }
+  });
+
+  it("trades during the holiday pay no fee", async () => {
+    const now = Math.floor(Date.now() / 1000);
+    await program.methods
+      .setFeeHoliday(new anchor.BN(now - 60), new anchor.BN(now + 3600))
+      .accounts({ config, market: marketPda, authority: wallet.publicKey })
+      .rpc({ commitment: "confirmed" });
+
+    await buy(100_000_000);
+    const market = await program.account.marketV3.fetch(marketPda);
+    expect(market.feeHolidayEnd.toNumber()).to.eq(now + 3600);
+    expect(totalFees(market)).to.eq(0);
+  });
+
+  it("clearing the holiday restores the fee", async () => {
+    await program.methods
+      .setFeeHoliday(new anchor.BN(0), new anchor.BN(0))
+      .accounts({ config, market: marketPda, authority: wallet.publicKey })
+      .rpc({ commitment: "confirmed" });
+
+    await buy(100_000_000);
+    const market = await program.account.marketV3.fetch(marketPda);
+    // 0.50% of the input
+    expect(totalFees(market)).to.eq(500_000);
+  });
+});
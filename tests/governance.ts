@@ -0,0 +1,105 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { LAMPORTS_PER_SOL } from "@solana/web3.js";
+import { expect } from "chai";
+
+import { PredictionProgramV2 } from "../target/types/prediction_program_v2";
+import { NO_CONFIG_CHANGES, airdrop, ensureConfig } from "./utils";
+
+describe("prediction_program_v2 (governance-controlled parameters)", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace
+    .PredictionProgramV2 as Program<PredictionProgramV2>;
+
+  const wallet = provider.wallet as anchor.Wallet;
+  // Stands in for a Realms governance PDA, which signs via the proposal
+  const governance = anchor.web3.Keypair.generate();
+
+  const noChanges = NO_CONFIG_CHANGES;
+
+  let config: anchor.web3.PublicKey;
+
+  it("transfer_admin + accept_admin hand the config to governance", async () => {
+    config = await ensureConfig(program, provider);
+    await airdrop(provider, governance.publicKey, LAMPORTS_PER_SOL);
+
+    await program.methods
+      .transferAdmin(governance.publicKey)
+      .accounts({ config, admin: wallet.publicKey })
+      .rpc({ commitment: "confirmed" });
+    await program.methods
+      .acceptAdmin()
+      .accounts({ config, pendingAdmin: governance.publicKey })
+      .signers([governance])
+      .rpc({ commitment: "confirmed" });
+
+    const cfg = await program.account.protocolConfig.fetch(config);
+    expect(cfg.admin.equals(governance.publicKey)).to.eq(true);
+  });
+
+  it("set_params applies several changes at once and rejects the old admin", async () => {
+    const before = await program.account.protocolConfig.fetch(config);
+
+    let failed = false;
+    try {
+      await program.methods
+        .setParams({ ...noChanges, reportBond: new anchor.BN(1) })
+        .accounts({ config, admin: wallet.publicKey })
+        .rpc({ commitment: "confirmed" });
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("Unauthorized");
+    }
+    expect(failed).to.eq(true);
+
+    await program.methods
+      .setParams({
+        ...noChanges,
+        reportBond: before.reportBond.addn(1),
+        disputeBond: before.disputeBond.addn(1),
+      })
+      .accounts({ config, admin: governance.publicKey })
+      .signers([governance])
+      .rpc({ commitment: "confirmed" });
+
+    const after = await program.account.protocolConfig.fetch(config);
+    expect(after.reportBond.toString()).to.eq(before.reportBond.addn(1).toString());
+    expect(after.disputeBond.toString()).to.eq(before.disputeBond.addn(1).toString());
+
+    await program.methods
+      .setParams({ ...noChanges, reportBond: before.reportBond, disputeBond: before.disputeBond })
+      .accounts({ config, admin: governance.publicKey })
+      .signers([governance])
+      .rpc({ commitment: "confirmed" });
+  });
+
+  it("set_params rejects fee shares above 100%", async () => {
+    let failed = false;
+    try {
+      await program.methods
+        .setParams({ ...noChanges, creatorFeeShareBps: 6_000, protocolFeeShareBps: 5_000 })
+        .accounts({ config, admin: governance.publicKey })
+        .signers([governance])
+        .rpc({ commitment: "confirmed" });
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("InvalidFeeShares");
+    }
+    expect(failed).to.eq(true);
+  });
+
+  after(async () => {
+    // Other suites expect the provider wallet to administer the config
+    await program.methods
+      .transferAdmin(wallet.publicKey)
+      .accounts({ config, admin: governance.publicKey })
+      .signers([governance])
+      .rpc({ commitment: "confirmed" });
+    await program.methods
+      .acceptAdmin()
+      .accounts({ config, pendingAdmin: wallet.publicKey })
+      .rpc({ commitment: "confirmed" });
+  });
+});
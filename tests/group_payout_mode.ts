@@ -0,0 +1,148 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { PublicKey, SystemProgram } from "@solana/web3.js";
+import {
+  TOKEN_PROGRAM_ID,
+  createMint,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+} from "@solana/spl-token";
+import { expect } from "chai";
+
+import { PredictionProgramV2 } from "../target/types/prediction_program_v2";
+import {
+  createTestMarket,
+  ensureConfig,
+  pda,
+  uniqueId,
+} from "./utils";
+
+const GROUP_PAYOUT_PROPORTIONAL = 1;
+
+describe("prediction_program_v2 (proportional groups)", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace
+    .PredictionProgramV2 as Program<PredictionProgramV2>;
+
+  const payer = (provider.wallet as any).payer as anchor.web3.Keypair;
+  const wallet = provider.wallet as anchor.Wallet;
+
+  const groupId = uniqueId();
+  const labels = ["Alice", "Bob", "Carol"];
+
+  let collateralMint: PublicKey;
+  let group: PublicKey;
+  const markets: PublicKey[] = [];
+
+  const vaultPda = (market: PublicKey) => pda(program, [Buffer.from("vault_v2"), market.toBuffer()]);
+  const vaultAuthPda = (market: PublicKey) => pda(program, [Buffer.from("vault_auth_v2"), market.toBuffer()]);
+
+  async function resolveDistribution(payouts: number[]) {
+    await program.methods
+      .resolveGroupDistribution(payouts.map((p) => new anchor.BN(p)))
+      .accounts({
+        group,
+        collateralMint,
+        authority: wallet.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .remainingAccounts(
+        markets.flatMap((m) => [
+          { pubkey: m, isWritable: true, isSigner: false },
+          { pubkey: vaultPda(m), isWritable: false, isSigner: false },
+        ])
+      )
+      .rpc({ commitment: "confirmed" });
+  }
+
+  it("setup: proportional group", async () => {
+    await ensureConfig(program, provider);
+
+    collateralMint = await createMint(provider.connection, payer, wallet.publicKey, null, 6);
+    const authorityAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, wallet.publicKey)
+    ).address;
+    await mintTo(provider.connection, payer, collateralMint, authorityAta, payer, 10_000_000_000);
+
+    group = pda(program, [Buffer.from("market_group"), wallet.publicKey.toBuffer(), groupId.toArrayLike(Buffer, "le", 8)]);
+    await program.methods
+      .createMarketGroup(groupId, GROUP_PAYOUT_PROPORTIONAL)
+      .accounts({
+        group,
+        collateralMint,
+        authority: wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc({ commitment: "confirmed" });
+
+    for (const label of labels) {
+      const marketId = uniqueId();
+      const market = pda(program, [
+        Buffer.from("market_v2"),
+        wallet.publicKey.toBuffer(),
+        marketId.toArrayLike(Buffer, "le", 8),
+      ]);
+      await createTestMarket(program, provider, {
+        marketId,
+        question: `Will ${label} top the podium?`,
+        metadataUri: `ar://podium-${label.toLowerCase()}`,
+        collateralMint,
+        authorityCollateralAta: authorityAta,
+      });
+
+      await program.methods
+        .addGroupMember(label)
+        .accounts({ group, market, authority: wallet.publicKey })
+        .rpc({ commitment: "confirmed" });
+      markets.push(market);
+    }
+  });
+
+  it("winner-take-all resolution is rejected", async () => {
+    let failed = false;
+    try {
+      await program.methods
+        .resolveGroup(0)
+        .accounts({
+          group,
+          collateralMint,
+          authority: wallet.publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .remainingAccounts(
+          markets.flatMap((m) => [
+            { pubkey: m, isWritable: true, isSigner: false },
+            { pubkey: vaultPda(m), isWritable: true, isSigner: false },
+            { pubkey: vaultAuthPda(m), isWritable: false, isSigner: false },
+          ])
+        )
+        .rpc({ commitment: "confirmed" });
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("WinnerTakeAllOnly");
+    }
+    expect(failed).to.eq(true);
+  });
+
+  it("a distribution that doesn't sum to 100% is rejected", async () => {
+    let failed = false;
+    try {
+      await resolveDistribution([600_000, 300_000, 50_000]);
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("InvalidPayoutVector");
+    }
+    expect(failed).to.eq(true);
+  });
+
+  it("members pay out their share of the distribution", async () => {
+    await resolveDistribution([600_000, 300_000, 100_000]);
+
+    const resolved = await Promise.all(markets.map((m) => program.account.marketV3.fetch(m)));
+    expect(resolved.map((m) => m.winningOutcome)).to.deep.eq([2, 2, 2]);
+    expect(resolved.map((m) => m.payoutFraction.toNumber())).to.deep.eq([600_000, 300_000, 100_000]);
+    expect((await program.account.marketGroup.fetch(group)).resolved).to.eq(true);
+  });
+});
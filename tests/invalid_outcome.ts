@@ -0,0 +1,164 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { PublicKey, SystemProgram } from "@solana/web3.js";
+import {
+  ASSOCIATED_TOKEN_PROGRAM_ID,
+  TOKEN_PROGRAM_ID,
+  createMint,
+  getAccount,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+} from "@solana/spl-token";
+import { expect } from "chai";
+
+import { PredictionProgramV2 } from "../target/types/prediction_program_v2";
+import {
+  REGISTRY_PAGE_SIZE,
+  airdrop,
+  buyAccounts,
+  createTestMarket,
+  ensureConfig,
+  pda,
+  registryPagePda,
+  uniqueId,
+} from "./utils";
+
+describe("prediction_program_v2 (invalid resolution)", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace
+    .PredictionProgramV2 as Program<PredictionProgramV2>;
+
+  const payer = (provider.wallet as any).payer as anchor.web3.Keypair;
+  const wallet = provider.wallet as anchor.Wallet;
+
+  const user = anchor.web3.Keypair.generate();
+  const marketId = uniqueId();
+
+  let collateralMint: PublicKey;
+  let authorityAta: PublicKey;
+  let userAta: PublicKey;
+  let config: PublicKey;
+  let marketPda: PublicKey;
+  let vaultPda: PublicKey;
+  let vaultAuthPda: PublicKey;
+  let positionPda: PublicKey;
+
+  const resolveInvalid = async () => {
+    const market = await program.account.marketV3.fetch(marketPda);
+    await program.methods
+      .resolveInvalid()
+      .accounts({
+        config,
+        market: marketPda,
+        vault: vaultPda,
+        registryPage: registryPagePda(
+          program,
+          Math.floor(market.registryIndex.toNumber() / REGISTRY_PAGE_SIZE)
+        ),
+        lstStakePool: null,
+        parentMarket: null,
+        authority: wallet.publicKey,
+      })
+      .rpc({ commitment: "confirmed" });
+  };
+
+  it("setup: market + a YES position", async () => {
+    config = await ensureConfig(program, provider);
+
+    await airdrop(provider, user.publicKey);
+
+    collateralMint = await createMint(provider.connection, payer, wallet.publicKey, null, 6);
+    authorityAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, wallet.publicKey)
+    ).address;
+    userAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, user.publicKey)
+    ).address;
+    await mintTo(provider.connection, payer, collateralMint, authorityAta, payer, 10_000_000_000);
+    await mintTo(provider.connection, payer, collateralMint, userAta, payer, 1_000_000_000);
+
+    marketPda = pda(program, [
+      Buffer.from("market_v2"),
+      wallet.publicKey.toBuffer(),
+      marketId.toArrayLike(Buffer, "le", 8),
+    ]);
+    vaultPda = pda(program, [Buffer.from("vault_v2"), marketPda.toBuffer()]);
+    vaultAuthPda = pda(program, [Buffer.from("vault_auth_v2"), marketPda.toBuffer()]);
+    positionPda = pda(program, [Buffer.from("position_v2"), marketPda.toBuffer(), user.publicKey.toBuffer()]);
+
+    await createTestMarket(program, provider, {
+      marketId,
+      question: "Will the referee's call stand?",
+      metadataUri: "ar://invalid-outcome",
+      collateralMint,
+      authorityCollateralAta: authorityAta,
+      endTime: new anchor.BN(Math.floor(Date.now() / 1000) + 5),
+    });
+
+    await program.methods
+      .buyShares(0, new anchor.BN(100_000_000), new anchor.BN(1))
+      .accounts(buyAccounts({
+        config,
+        market: marketPda,
+        vault: vaultPda,
+        vaultAuthority: vaultAuthPda,
+        position: positionPda,
+        user: user.publicKey,
+        payer: user.publicKey,
+        collateralMint,
+        userCollateralAta: userAta,
+      }))
+      .signers([user])
+      .rpc({ commitment: "confirmed" });
+  });
+
+  it("resolve_invalid waits for trading to end", async () => {
+    let failed = false;
+    try {
+      await resolveInvalid();
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("MarketNotEnded");
+    }
+    expect(failed).to.eq(true);
+  });
+
+  it("resolve_invalid refunds positions at cost", async () => {
+    await new Promise((r) => setTimeout(r, 6_000));
+    await resolveInvalid();
+
+    const resolved = await program.account.marketV3.fetch(marketPda);
+    expect(resolved.winningOutcome).to.eq(4); // OUTCOME_REFUND_AT_COST
+
+    const position = await program.account.positionV2.fetch(positionPda);
+    const before = Number((await getAccount(provider.connection, userAta)).amount);
+    await program.methods
+      .claimWinningsV2()
+      .accounts({
+        market: marketPda,
+        vault: vaultPda,
+        vaultAuthority: vaultAuthPda,
+        position: positionPda,
+        user: user.publicKey,
+        payer: user.publicKey,
+        collateralMint,
+        userCollateralAta: userAta,
+        wsolUnwrap: null,
+        creatorBond: null,
+        claimEscrow: null,
+        userStats: null,
+        protocolStats: null,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([user])
+      .rpc({ commitment: "confirmed" });
+    const after = Number((await getAccount(provider.connection, userAta)).amount);
+
+    // Refunded what the position paid, not a pool-price valuation
+    expect(after - before).to.eq(position.costBasis.toNumber());
+  });
+});
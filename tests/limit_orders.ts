@@ -0,0 +1,274 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { PublicKey, SystemProgram, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import {
+  TOKEN_PROGRAM_ID,
+  createMint,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+  getAccount,
+} from "@solana/spl-token";
+import { expect } from "chai";
+
+import { PredictionProgramV2 } from "../target/types/prediction_program_v2";
+import {
+  airdrop,
+  createTestMarket,
+  ensureConfig,
+  pda,
+  uniqueId,
+} from "./utils";
+
+describe("prediction_program_v2 (limit orders)", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace
+    .PredictionProgramV2 as Program<PredictionProgramV2>;
+
+  const payer = (provider.wallet as any).payer as anchor.web3.Keypair;
+  const wallet = provider.wallet as anchor.Wallet;
+
+  const trader = anchor.web3.Keypair.generate();
+  const marketId = uniqueId();
+  const initialLiquidity = new anchor.BN(1_000_000_000);
+
+  let collateralMint: PublicKey;
+  let authorityAta: PublicKey;
+  let traderAta: PublicKey;
+  let marketPda: PublicKey;
+  let vaultPda: PublicKey;
+  let vaultAuthPda: PublicKey;
+  let positionPda: PublicKey;
+
+  const keeper = anchor.web3.Keypair.generate();
+
+  const orderPda = (id: number) =>
+    pda(program, [
+      Buffer.from("limit_order"),
+      marketPda.toBuffer(),
+      trader.publicKey.toBuffer(),
+      new anchor.BN(id).toArrayLike(Buffer, "le", 8),
+    ]);
+  const escrowPda = (order: PublicKey) =>
+    pda(program, [Buffer.from("order_escrow"), order.toBuffer()]);
+
+  it("setup: mint + market + PDAs", async () => {
+    await ensureConfig(program, provider);
+
+    await airdrop(provider, trader.publicKey);
+
+    collateralMint = await createMint(
+      provider.connection,
+      payer,
+      wallet.publicKey,
+      null,
+      6
+    );
+    authorityAta = (
+      await getOrCreateAssociatedTokenAccount(
+        provider.connection,
+        payer,
+        collateralMint,
+        wallet.publicKey
+      )
+    ).address;
+    traderAta = (
+      await getOrCreateAssociatedTokenAccount(
+        provider.connection,
+        payer,
+        collateralMint,
+        trader.publicKey
+      )
+    ).address;
+    await mintTo(provider.connection, payer, collateralMint, authorityAta, payer, 10_000_000_000);
+    await mintTo(provider.connection, payer, collateralMint, traderAta, payer, 1_000_000_000);
+
+    marketPda = pda(program, [
+      Buffer.from("market_v2"),
+      wallet.publicKey.toBuffer(),
+      marketId.toArrayLike(Buffer, "le", 8),
+    ]);
+    vaultPda = pda(program, [Buffer.from("vault_v2"), marketPda.toBuffer()]);
+    vaultAuthPda = pda(program, [Buffer.from("vault_auth_v2"), marketPda.toBuffer()]);
+    positionPda = pda(program, [Buffer.from("position_v2"), marketPda.toBuffer(), trader.publicKey.toBuffer()]);
+
+    await createTestMarket(program, provider, {
+      marketId,
+      question: "Will the limit order fill?",
+      metadataUri: "ar://limit-order",
+      collateralMint,
+      authorityCollateralAta: authorityAta,
+      initialYesReserve: initialLiquidity,
+      initialNoReserve: initialLiquidity,
+    });
+  });
+
+  it("place_limit_order escrows collateral and the tip", async () => {
+    const order = orderPda(1);
+    await program.methods
+      .placeLimitOrder({
+        orderId: new anchor.BN(1),
+        side: 0,
+        outcomeIndex: 0,
+        amount: new anchor.BN(50_000_000),
+        limitPrice: new anchor.BN(900_000),
+        keeperTip: new anchor.BN(10_000),
+        triggerPrice: new anchor.BN(0),
+        oracleTrigger: null,
+      })
+      .accounts({
+        order,
+        escrow: escrowPda(order),
+        market: marketPda,
+        vaultAuthority: vaultAuthPda,
+        position: positionPda,
+        owner: trader.publicKey,
+        payer: trader.publicKey,
+        collateralMint,
+        ownerCollateralAta: traderAta,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([trader])
+      .rpc({ commitment: "confirmed" });
+
+    const escrow = await getAccount(provider.connection, escrowPda(order));
+    expect(Number(escrow.amount)).to.eq(50_000_000);
+  });
+
+  it("fill_limit_order executes against the AMM and tips the keeper", async () => {
+    const order = orderPda(1);
+    await airdrop(provider, keeper.publicKey, LAMPORTS_PER_SOL);
+    const keeperBefore = await provider.connection.getBalance(keeper.publicKey);
+
+    await program.methods
+      .fillLimitOrder()
+      .accounts({
+        order,
+        escrow: escrowPda(order),
+        market: marketPda,
+        vault: vaultPda,
+        vaultAuthority: vaultAuthPda,
+        position: positionPda,
+        oracle: null,
+        keeper: keeper.publicKey,
+        collateralMint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([keeper])
+      .rpc({ commitment: "confirmed" });
+
+    const position = await program.account.positionV2.fetch(positionPda);
+    expect(position.yesShares.toNumber()).to.be.greaterThan(50_000_000);
+    expect((await program.account.limitOrder.fetch(order)).filled).to.eq(true);
+
+    const keeperAfter = await provider.connection.getBalance(keeper.publicKey);
+    expect(keeperAfter).to.be.greaterThan(keeperBefore); // tip exceeds the tx fee
+  });
+
+  it("close_limit_order closes the order and escrow", async () => {
+    const order = orderPda(1);
+    await program.methods
+      .closeLimitOrder()
+      .accounts({
+        order,
+        escrow: escrowPda(order),
+        market: marketPda,
+        vaultAuthority: vaultAuthPda,
+        position: positionPda,
+        owner: trader.publicKey,
+        payer: trader.publicKey,
+        collateralMint,
+        ownerCollateralAta: traderAta,
+        wsolUnwrap: null,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([trader])
+      .rpc({ commitment: "confirmed" });
+
+    expect(await provider.connection.getAccountInfo(order)).to.eq(null);
+    expect(await provider.connection.getAccountInfo(escrowPda(order))).to.eq(null);
+  });
+
+  it("stop-loss: sell order only fills once the probability drops to the trigger", async () => {
+    const order = orderPda(2);
+    const before = await program.account.positionV2.fetch(positionPda);
+
+    await program.methods
+      .placeLimitOrder({
+        orderId: new anchor.BN(2),
+        side: 1,
+        outcomeIndex: 0,
+        amount: before.yesShares,
+        limitPrice: new anchor.BN(1),
+        keeperTip: new anchor.BN(0),
+        triggerPrice: new anchor.BN(100_000), // 10%
+        oracleTrigger: null,
+      })
+      .accounts({
+        order,
+        escrow: escrowPda(order),
+        market: marketPda,
+        vaultAuthority: vaultAuthPda,
+        position: positionPda,
+        owner: trader.publicKey,
+        payer: trader.publicKey,
+        collateralMint,
+        ownerCollateralAta: null,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([trader])
+      .rpc({ commitment: "confirmed" });
+    expect((await program.account.positionV2.fetch(positionPda)).yesShares.toNumber()).to.eq(0);
+
+    let failed = false;
+    try {
+      await program.methods
+        .fillLimitOrder()
+        .accounts({
+          order,
+          escrow: escrowPda(order),
+          market: marketPda,
+          vault: vaultPda,
+          vaultAuthority: vaultAuthPda,
+          position: positionPda,
+          oracle: null,
+          keeper: keeper.publicKey,
+          collateralMint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([keeper])
+        .rpc({ commitment: "confirmed" });
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("StopNotTriggered");
+    }
+    expect(failed).to.eq(true);
+
+    // Cancelling hands the escrowed shares back
+    await program.methods
+      .closeLimitOrder()
+      .accounts({
+        order,
+        escrow: escrowPda(order),
+        market: marketPda,
+        vaultAuthority: vaultAuthPda,
+        position: positionPda,
+        owner: trader.publicKey,
+        payer: trader.publicKey,
+        collateralMint,
+        ownerCollateralAta: traderAta,
+        wsolUnwrap: null,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([trader])
+      .rpc({ commitment: "confirmed" });
+    expect((await program.account.positionV2.fetch(positionPda)).yesShares.toNumber()).to.eq(
+      before.yesShares.toNumber()
+    );
+  });
+});
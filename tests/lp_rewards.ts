@@ -0,0 +1,176 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { PublicKey, SystemProgram } from "@solana/web3.js";
+import {
+  TOKEN_PROGRAM_ID,
+  createMint,
+  getAccount,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+} from "@solana/spl-token";
+import { expect } from "chai";
+
+import { PredictionProgramV2 } from "../target/types/prediction_program_v2";
+import {
+  airdrop,
+  createTestMarket,
+  ensureConfig,
+  lpPositionPda,
+  pda,
+  uniqueId,
+} from "./utils";
+
+describe("prediction_program_v2 (liquidity mining emissions)", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace
+    .PredictionProgramV2 as Program<PredictionProgramV2>;
+
+  const payer = (provider.wallet as any).payer as anchor.web3.Keypair;
+  const wallet = provider.wallet as anchor.Wallet;
+
+  const lp = anchor.web3.Keypair.generate();
+  const marketId = uniqueId();
+  const rate = 1_000;
+
+  let collateralMint: PublicKey;
+  let rewardMint: PublicKey;
+  let lpAta: PublicKey;
+  let lpRewardAta: PublicKey;
+  let marketPda: PublicKey;
+  let vaultPda: PublicKey;
+  let vaultAuthPda: PublicKey;
+  let lpRewards: PublicKey;
+  let rewardVault: PublicKey;
+
+  async function addLiquidity(withRewards: boolean) {
+    await program.methods
+      .addLiquidity(new anchor.BN(500_000_000), new anchor.BN(1))
+      .accounts({
+        market: marketPda,
+        vault: vaultPda,
+        lpPosition: lpPositionPda(program, marketPda, lp.publicKey),
+        position: pda(program, [Buffer.from("position_v2"), marketPda.toBuffer(), lp.publicKey.toBuffer()]),
+        user: lp.publicKey,
+        payer: lp.publicKey,
+        collateralMint,
+        userCollateralAta: lpAta,
+        lpRewards: withRewards ? lpRewards : null,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([lp])
+      .rpc({ commitment: "confirmed" });
+  }
+
+  it("setup: market + reward token", async () => {
+    await ensureConfig(program, provider);
+
+    await airdrop(provider, lp.publicKey);
+
+    collateralMint = await createMint(provider.connection, payer, wallet.publicKey, null, 6);
+    rewardMint = await createMint(provider.connection, payer, wallet.publicKey, null, 6);
+    const authorityAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, wallet.publicKey)
+    ).address;
+    lpAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, lp.publicKey)
+    ).address;
+    lpRewardAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, rewardMint, lp.publicKey)
+    ).address;
+    await mintTo(provider.connection, payer, collateralMint, authorityAta, payer, 10_000_000_000);
+    await mintTo(provider.connection, payer, collateralMint, lpAta, payer, 1_000_000_000);
+
+    marketPda = pda(program, [
+      Buffer.from("market_v2"),
+      wallet.publicKey.toBuffer(),
+      marketId.toArrayLike(Buffer, "le", 8),
+    ]);
+    vaultPda = pda(program, [Buffer.from("vault_v2"), marketPda.toBuffer()]);
+    vaultAuthPda = pda(program, [Buffer.from("vault_auth_v2"), marketPda.toBuffer()]);
+    lpRewards = pda(program, [Buffer.from("lp_rewards"), marketPda.toBuffer()]);
+    rewardVault = pda(program, [Buffer.from("lp_reward_vault"), marketPda.toBuffer()]);
+
+    await createTestMarket(program, provider, {
+      marketId,
+      question: "Will the new L2 reach 1M daily txs?",
+      metadataUri: "ar://l2-daily-txs",
+      collateralMint,
+      authorityCollateralAta: authorityAta,
+    });
+  });
+
+  it("init_lp_rewards escrows the full emissions budget", async () => {
+    const funderAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, rewardMint, wallet.publicKey)
+    ).address;
+    await mintTo(provider.connection, payer, rewardMint, funderAta, payer, 1_000_000_000);
+
+    const now = Math.floor(Date.now() / 1000);
+    await program.methods
+      .initLpRewards(new anchor.BN(rate), new anchor.BN(now + 2), new anchor.BN(now + 600))
+      .accounts({
+        market: marketPda,
+        lpRewards,
+        rewardVault,
+        vaultAuthority: vaultAuthPda,
+        rewardMint,
+        funderRewardAta: funderAta,
+        authority: wallet.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc({ commitment: "confirmed" });
+
+    const vault = await getAccount(provider.connection, rewardVault);
+    expect(Number(vault.amount)).to.eq(rate * 598);
+    expect((await program.account.marketV3.fetch(marketPda)).lpRewards).to.eq(1);
+  });
+
+  it("LP share changes must checkpoint the emissions", async () => {
+    let failed = false;
+    try {
+      await addLiquidity(false);
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("LpRewardsRequired");
+    }
+    expect(failed).to.eq(true);
+
+    await addLiquidity(true);
+  });
+
+  it("claim_lp_rewards pays the LP's time-weighted share", async () => {
+    await new Promise((r) => setTimeout(r, 5_000));
+
+    await program.methods
+      .claimLpRewards()
+      .accounts({
+        market: marketPda,
+        lpRewards,
+        lpPosition: lpPositionPda(program, marketPda, lp.publicKey),
+        rewardVault,
+        vaultAuthority: vaultAuthPda,
+        rewardMint,
+        userRewardAta: lpRewardAta,
+        user: lp.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([lp])
+      .rpc({ commitment: "confirmed" });
+
+    // The LP holds a third of the shares, so earns at most a third of the stream
+    const claimed = Number((await getAccount(provider.connection, lpRewardAta)).amount);
+    expect(claimed).to.be.greaterThan(0);
+    const rewards = await program.account.lpRewards.fetch(lpRewards);
+    const elapsed = rewards.lastUpdate.toNumber() - rewards.start.toNumber();
+    expect(claimed).to.be.at.most(Math.ceil((elapsed * rate) / 3));
+
+    const position = await program.account.lpPosition.fetch(
+      lpPositionPda(program, marketPda, lp.publicKey)
+    );
+    expect(position.rewardsOwed.toNumber()).to.eq(0);
+  });
+});
@@ -0,0 +1,171 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { PublicKey, SystemProgram } from "@solana/web3.js";
+import {
+  TOKEN_PROGRAM_ID,
+  createMint,
+  getAccount,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+} from "@solana/spl-token";
+import { expect } from "chai";
+
+import { PredictionProgramV2 } from "../target/types/prediction_program_v2";
+import {
+  airdrop,
+  buyAccounts,
+  createTestMarket,
+  ensureConfig,
+  pda,
+  uniqueId,
+} from "./utils";
+
+describe("prediction_program_v2 (designated market maker rebates)", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace
+    .PredictionProgramV2 as Program<PredictionProgramV2>;
+
+  const payer = (provider.wallet as any).payer as anchor.web3.Keypair;
+  const wallet = provider.wallet as anchor.Wallet;
+
+  const maker = anchor.web3.Keypair.generate();
+  const trader = anchor.web3.Keypair.generate();
+  const marketId = uniqueId();
+
+  let collateralMint: PublicKey;
+  let config: PublicKey;
+  let marketPda: PublicKey;
+  let vaultPda: PublicKey;
+  let vaultAuthPda: PublicKey;
+  let marketMakers: PublicKey;
+  const atas = new Map<string, PublicKey>();
+
+  async function buy(user: anchor.web3.Keypair, amount: number) {
+    await program.methods
+      .buyShares(0, new anchor.BN(amount), new anchor.BN(1))
+      .accounts(buyAccounts({
+        config,
+        market: marketPda,
+        vault: vaultPda,
+        vaultAuthority: vaultAuthPda,
+        position: pda(program, [Buffer.from("position_v2"), marketPda.toBuffer(), user.publicKey.toBuffer()]),
+        user: user.publicKey,
+        payer: user.publicKey,
+        collateralMint,
+        userCollateralAta: atas.get(user.publicKey.toBase58())!,
+        marketMakers,
+      }))
+      .signers([user])
+      .rpc({ commitment: "confirmed" });
+  }
+
+  it("setup: market + funded maker and trader", async () => {
+    config = await ensureConfig(program, provider);
+
+    collateralMint = await createMint(provider.connection, payer, wallet.publicKey, null, 6);
+    const authorityAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, wallet.publicKey)
+    ).address;
+    await mintTo(provider.connection, payer, collateralMint, authorityAta, payer, 10_000_000_000);
+    for (const kp of [maker, trader]) {
+      await airdrop(provider, kp.publicKey);
+      const ata = (
+        await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, kp.publicKey)
+      ).address;
+      await mintTo(provider.connection, payer, collateralMint, ata, payer, 1_000_000_000);
+      atas.set(kp.publicKey.toBase58(), ata);
+    }
+
+    marketPda = pda(program, [
+      Buffer.from("market_v2"),
+      wallet.publicKey.toBuffer(),
+      marketId.toArrayLike(Buffer, "le", 8),
+    ]);
+    vaultPda = pda(program, [Buffer.from("vault_v2"), marketPda.toBuffer()]);
+    vaultAuthPda = pda(program, [Buffer.from("vault_auth_v2"), marketPda.toBuffer()]);
+    marketMakers = pda(program, [Buffer.from("market_makers"), marketPda.toBuffer()]);
+
+    await createTestMarket(program, provider, {
+      marketId,
+      question: "Will the new perp venue pass $1B volume?",
+      metadataUri: "ar://perp-venue-volume",
+      collateralMint,
+      authorityCollateralAta: authorityAta,
+    });
+
+    await program.methods
+      .setMarketMakers([maker.publicKey], 10)
+      .accounts({
+        market: marketPda,
+        marketMakers,
+        authority: wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc({ commitment: "confirmed" });
+  });
+
+  it("only the designated maker's trades accrue a rebate", async () => {
+    await buy(trader, 200_000_000);
+    let list = await program.account.marketMakers.fetch(marketMakers);
+    expect(list.makers[0].accrued.toNumber()).to.eq(0);
+
+    await buy(maker, 100_000_000);
+    list = await program.account.marketMakers.fetch(marketMakers);
+    // 0.10% of the collateral traded
+    expect(list.makers[0].accrued.toNumber()).to.eq(100_000);
+
+    const market = await program.account.marketV3.fetch(marketPda);
+    expect(market.mmRebates.toNumber()).to.eq(100_000);
+  });
+
+  it("set_market_makers won't drop a maker with an unclaimed rebate", async () => {
+    let failed = false;
+    try {
+      await program.methods
+        .setMarketMakers([], 10)
+        .accounts({
+          market: marketPda,
+          marketMakers,
+          authority: wallet.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc({ commitment: "confirmed" });
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("MakerRebateOutstanding");
+    }
+    expect(failed).to.eq(true);
+  });
+
+  it("claim_mm_rebate pays the maker and clears the accrual", async () => {
+    const makerAta = atas.get(maker.publicKey.toBase58())!;
+    const before = await getAccount(provider.connection, makerAta);
+
+    await program.methods
+      .claimMmRebate()
+      .accounts({
+        market: marketPda,
+        marketMakers,
+        vault: vaultPda,
+        vaultAuthority: vaultAuthPda,
+        maker: maker.publicKey,
+        collateralMint,
+        makerCollateralAta: makerAta,
+        wsolUnwrap: null,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([maker])
+      .rpc({ commitment: "confirmed" });
+
+    const after = await getAccount(provider.connection, makerAta);
+    expect(Number(after.amount) - Number(before.amount)).to.eq(100_000);
+
+    const list = await program.account.marketMakers.fetch(marketMakers);
+    expect(list.makers[0].accrued.toNumber()).to.eq(0);
+    const market = await program.account.marketV3.fetch(marketPda);
+    expect(market.mmRebates.toNumber()).to.eq(0);
+  });
+});
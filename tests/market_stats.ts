@@ -0,0 +1,135 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { PublicKey, SystemProgram } from "@solana/web3.js";
+import {
+  createMint,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+} from "@solana/spl-token";
+import { expect } from "chai";
+
+import { PredictionProgramV2 } from "../target/types/prediction_program_v2";
+import {
+  airdrop,
+  buyAccounts,
+  createTestMarket,
+  ensureConfig,
+  pda,
+  sellAccounts,
+  uniqueId,
+} from "./utils";
+
+describe("prediction_program_v2 (market statistics)", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace
+    .PredictionProgramV2 as Program<PredictionProgramV2>;
+
+  const payer = (provider.wallet as any).payer as anchor.web3.Keypair;
+  const wallet = provider.wallet as anchor.Wallet;
+
+  const user = anchor.web3.Keypair.generate();
+  const marketId = uniqueId();
+
+  let collateralMint: PublicKey;
+  let userAta: PublicKey;
+  let config: PublicKey;
+  let marketPda: PublicKey;
+  let vaultPda: PublicKey;
+  let vaultAuthPda: PublicKey;
+  let positionPda: PublicKey;
+  let marketStats: PublicKey;
+
+  it("setup: market + funded trader + market stats", async () => {
+    config = await ensureConfig(program, provider);
+
+    await airdrop(provider, user.publicKey);
+
+    collateralMint = await createMint(provider.connection, payer, wallet.publicKey, null, 6);
+    const authorityAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, wallet.publicKey)
+    ).address;
+    userAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, user.publicKey)
+    ).address;
+    await mintTo(provider.connection, payer, collateralMint, authorityAta, payer, 10_000_000_000);
+    await mintTo(provider.connection, payer, collateralMint, userAta, payer, 1_000_000_000);
+
+    marketPda = pda(program, [
+      Buffer.from("market_v2"),
+      wallet.publicKey.toBuffer(),
+      marketId.toArrayLike(Buffer, "le", 8),
+    ]);
+    vaultPda = pda(program, [Buffer.from("vault_v2"), marketPda.toBuffer()]);
+    vaultAuthPda = pda(program, [Buffer.from("vault_auth_v2"), marketPda.toBuffer()]);
+    positionPda = pda(program, [Buffer.from("position_v2"), marketPda.toBuffer(), user.publicKey.toBuffer()]);
+    marketStats = pda(program, [Buffer.from("market_stats"), marketPda.toBuffer()]);
+
+    await createTestMarket(program, provider, {
+      marketId,
+      question: "Will a stablecoin top 300B in supply?",
+      metadataUri: "ar://stablecoin-300b",
+      collateralMint,
+      authorityCollateralAta: authorityAta,
+    });
+
+    await program.methods
+      .initMarketStats()
+      .accounts({ market: marketPda, marketStats, payer: wallet.publicKey, systemProgram: SystemProgram.programId })
+      .rpc({ commitment: "confirmed" });
+  });
+
+  const tradeAccounts = (stats: PublicKey | null) => ({
+    config,
+    market: marketPda,
+    vault: vaultPda,
+    vaultAuthority: vaultAuthPda,
+    position: positionPda,
+    user: user.publicKey,
+    payer: user.publicKey,
+    collateralMint,
+    userCollateralAta: userAta,
+    marketStats: stats,
+  });
+
+  it("buy_shares without the stats account is rejected", async () => {
+    let failed = false;
+    try {
+      await program.methods
+        .buyShares(0, new anchor.BN(10_000_000), new anchor.BN(1))
+        .accounts(buyAccounts(tradeAccounts(null)))
+        .signers([user])
+        .rpc({ commitment: "confirmed" });
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("MarketStatsRequired");
+    }
+    expect(failed).to.eq(true);
+  });
+
+  it("trades accumulate volume, fees, trade and trader counts", async () => {
+    await program.methods
+      .buyShares(0, new anchor.BN(100_000_000), new anchor.BN(1))
+      .accounts(buyAccounts(tradeAccounts(marketStats)))
+      .signers([user])
+      .rpc({ commitment: "confirmed" });
+
+    const position = await program.account.positionV2.fetch(positionPda);
+    await program.methods
+      .sellShares(0, position.yesShares.divn(2), new anchor.BN(1))
+      .accounts(sellAccounts(tradeAccounts(marketStats)))
+      .signers([user])
+      .rpc({ commitment: "confirmed" });
+
+    const stats = await program.account.marketStats.fetch(marketStats);
+    expect(stats.trades.toNumber()).to.eq(2);
+    expect(stats.traders.toNumber()).to.eq(1);
+    expect(stats.volume.toNumber()).to.be.greaterThan(100_000_000);
+    // 0.50% of the buy at least
+    expect(stats.fees.toNumber()).to.be.at.least(500_000);
+
+    const bucket = stats.hour.toNumber() % 24;
+    expect(stats.hourlyVolume[bucket].toString()).to.eq(stats.volume.toString());
+  });
+});
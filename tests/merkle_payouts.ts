@@ -0,0 +1,230 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { PublicKey, SystemProgram } from "@solana/web3.js";
+import {
+  ASSOCIATED_TOKEN_PROGRAM_ID,
+  TOKEN_PROGRAM_ID,
+  createMint,
+  getAccount,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+} from "@solana/spl-token";
+import { expect } from "chai";
+import { createHash } from "crypto";
+
+import { PredictionProgramV2 } from "../target/types/prediction_program_v2";
+import {
+  REGISTRY_PAGE_SIZE,
+  buyAccounts,
+  createTestMarket,
+  ensureConfig,
+  pda,
+  registryPagePda,
+  uniqueId,
+} from "./utils";
+
+const sha256 = (...parts: Buffer[]) =>
+  createHash("sha256").update(Buffer.concat(parts)).digest();
+
+// Parents hash their children in sorted order, matching `verify_merkle_proof`
+const hashPair = (a: Buffer, b: Buffer) =>
+  Buffer.compare(a, b) <= 0 ? sha256(a, b) : sha256(b, a);
+
+const payoutLeaf = (index: number, claimant: PublicKey, amount: anchor.BN) =>
+  sha256(
+    new anchor.BN(index).toArrayLike(Buffer, "le", 8),
+    claimant.toBuffer(),
+    amount.toArrayLike(Buffer, "le", 8)
+  );
+
+describe("prediction_program_v2 (merkle payouts)", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace
+    .PredictionProgramV2 as Program<PredictionProgramV2>;
+
+  const payer = (provider.wallet as any).payer as anchor.web3.Keypair;
+  const wallet = provider.wallet as anchor.Wallet;
+
+  const marketId = uniqueId();
+  const other = anchor.web3.Keypair.generate().publicKey;
+
+  let collateralMint: PublicKey;
+  let walletAta: PublicKey;
+  let config: PublicKey;
+  let marketPda: PublicKey;
+  let vaultPda: PublicKey;
+  let vaultAuthPda: PublicKey;
+  let positionPda: PublicKey;
+  let distributorPda: PublicKey;
+  let amount: anchor.BN;
+  let leaves: Buffer[];
+  let root: Buffer;
+
+  async function claim(index: number, claimAmount: anchor.BN, proof: Buffer[]) {
+    await program.methods
+      .claimFromDistributor(new anchor.BN(index), claimAmount, proof.map((p) => Array.from(p)))
+      .accounts({
+        market: marketPda,
+        vault: vaultPda,
+        vaultAuthority: vaultAuthPda,
+        payoutDistributor: distributorPda,
+        claimReceipt: pda(program, [
+          Buffer.from("distributor_claim"),
+          distributorPda.toBuffer(),
+          new anchor.BN(index).toArrayLike(Buffer, "le", 8),
+        ]),
+        user: wallet.publicKey,
+        payer: wallet.publicKey,
+        collateralMint,
+        userCollateralAta: walletAta,
+        wsolUnwrap: null,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc({ commitment: "confirmed" });
+  }
+
+  it("setup: resolved market with a winning position", async () => {
+    config = await ensureConfig(program, provider);
+
+    collateralMint = await createMint(provider.connection, payer, wallet.publicKey, null, 6);
+    walletAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, wallet.publicKey)
+    ).address;
+    await mintTo(provider.connection, payer, collateralMint, walletAta, payer, 10_000_000_000);
+
+    marketPda = pda(program, [
+      Buffer.from("market_v2"),
+      wallet.publicKey.toBuffer(),
+      marketId.toArrayLike(Buffer, "le", 8),
+    ]);
+    vaultPda = pda(program, [Buffer.from("vault_v2"), marketPda.toBuffer()]);
+    vaultAuthPda = pda(program, [Buffer.from("vault_auth_v2"), marketPda.toBuffer()]);
+    positionPda = pda(program, [Buffer.from("position_v2"), marketPda.toBuffer(), wallet.publicKey.toBuffer()]);
+    distributorPda = pda(program, [Buffer.from("payout_distributor"), marketPda.toBuffer()]);
+
+    await createTestMarket(program, provider, {
+      marketId,
+      question: "Will the root hold?",
+      metadataUri: "ar://merkle-payouts",
+      collateralMint,
+      authorityCollateralAta: walletAta,
+    });
+
+    await program.methods
+      .buyShares(0, new anchor.BN(50_000_000), new anchor.BN(1))
+      .accounts(buyAccounts({
+        config,
+        market: marketPda,
+        vault: vaultPda,
+        vaultAuthority: vaultAuthPda,
+        position: positionPda,
+        user: wallet.publicKey,
+        payer: wallet.publicKey,
+        collateralMint,
+        userCollateralAta: walletAta,
+      }))
+      .rpc({ commitment: "confirmed" });
+
+    const market = await program.account.marketV3.fetch(marketPda);
+    await program.methods
+      .resolveMarketPayouts([new anchor.BN(1_000_000), new anchor.BN(0)])
+      .accounts({
+        config,
+        market: marketPda,
+        vault: vaultPda,
+        registryPage: registryPagePda(
+          program,
+          Math.floor(market.registryIndex.toNumber() / REGISTRY_PAGE_SIZE)
+        ),
+        lstStakePool: null,
+        parentMarket: null,
+        authority: wallet.publicKey,
+      })
+      .rpc({ commitment: "confirmed" });
+  });
+
+  it("resolver commits the payout root", async () => {
+    const market = await program.account.marketV3.fetch(marketPda);
+    amount = market.resolvedPayable.divn(2);
+    leaves = [payoutLeaf(0, wallet.publicKey, amount), payoutLeaf(1, other, amount)];
+    root = hashPair(leaves[0], leaves[1]);
+
+    await program.methods
+      .commitPayoutRoot(Array.from(root), amount.muln(2))
+      .accounts({
+        config,
+        market: marketPda,
+        payoutDistributor: distributorPda,
+        authority: wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc({ commitment: "confirmed" });
+
+    const distributor = await program.account.payoutDistributor.fetch(distributorPda);
+    expect(Buffer.from(distributor.root).equals(root)).to.eq(true);
+    expect((await program.account.marketV3.fetch(marketPda)).payoutDistributor).to.eq(1);
+  });
+
+  it("a leaf with the wrong amount doesn't verify", async () => {
+    let failed = false;
+    try {
+      await claim(0, amount.muln(2), [leaves[1]]);
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("InvalidMerkleProof");
+    }
+    expect(failed).to.eq(true);
+  });
+
+  it("claimant is paid with a valid proof, once", async () => {
+    const before = await getAccount(provider.connection, walletAta);
+    await claim(0, amount, [leaves[1]]);
+    const after = await getAccount(provider.connection, walletAta);
+    expect((after.amount - before.amount).toString()).to.eq(amount.toString());
+
+    const distributor = await program.account.payoutDistributor.fetch(distributorPda);
+    expect(distributor.claimed.toString()).to.eq(amount.toString());
+
+    let failed = false;
+    try {
+      await claim(0, amount, [leaves[1]]);
+    } catch (e: any) {
+      failed = true;
+    }
+    expect(failed).to.eq(true);
+  });
+
+  it("position claims are closed once a root is committed", async () => {
+    let failed = false;
+    try {
+      await program.methods
+        .claimWinningsV2()
+        .accounts({
+          market: marketPda,
+          vault: vaultPda,
+          vaultAuthority: vaultAuthPda,
+          position: positionPda,
+          user: wallet.publicKey,
+          payer: wallet.publicKey,
+          collateralMint,
+          userCollateralAta: walletAta,
+          wsolUnwrap: null,
+          creatorBond: null,
+          claimEscrow: null,
+          userStats: null,
+          protocolStats: null,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc({ commitment: "confirmed" });
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("PayoutsViaDistributor");
+    }
+    expect(failed).to.eq(true);
+  });
+});
@@ -0,0 +1,157 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { PublicKey } from "@solana/web3.js";
+import {
+  createMint,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+} from "@solana/spl-token";
+import { expect } from "chai";
+
+import { PredictionProgramV2 } from "../target/types/prediction_program_v2";
+import {
+  NO_CONFIG_CHANGES,
+  airdrop,
+  buyAccounts,
+  createTestMarket,
+  ensureConfig,
+  pda,
+  sellAccounts,
+  uniqueId,
+} from "./utils";
+
+describe("prediction_program_v2 (minimum trade size)", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace
+    .PredictionProgramV2 as Program<PredictionProgramV2>;
+
+  const payer = (provider.wallet as any).payer as anchor.web3.Keypair;
+  const wallet = provider.wallet as anchor.Wallet;
+
+  const user = anchor.web3.Keypair.generate();
+  const marketId = uniqueId();
+
+  let collateralMint: PublicKey;
+  let userAta: PublicKey;
+  let config: PublicKey;
+  let marketPda: PublicKey;
+  let vaultPda: PublicKey;
+  let vaultAuthPda: PublicKey;
+  let positionPda: PublicKey;
+
+  const noChanges = NO_CONFIG_CHANGES;
+
+  async function setMinimums(collateral: number, shares: number) {
+    await program.methods
+      .setParams({
+        ...noChanges,
+        minTradeCollateral: new anchor.BN(collateral),
+        minTradeShares: new anchor.BN(shares),
+      })
+      .accounts({ config, admin: wallet.publicKey })
+      .rpc({ commitment: "confirmed" });
+  }
+
+  const tradeAccounts = () => ({
+    config,
+    market: marketPda,
+    vault: vaultPda,
+    vaultAuthority: vaultAuthPda,
+    position: positionPda,
+    user: user.publicKey,
+    payer: user.publicKey,
+    collateralMint,
+    userCollateralAta: userAta,
+  });
+
+  async function buy(amount: number) {
+    await program.methods
+      .buyShares(0, new anchor.BN(amount), new anchor.BN(1))
+      .accounts(buyAccounts(tradeAccounts()))
+      .signers([user])
+      .rpc({ commitment: "confirmed" });
+  }
+
+  async function sell(shares: anchor.BN) {
+    await program.methods
+      .sellShares(0, shares, new anchor.BN(1))
+      .accounts(sellAccounts(tradeAccounts()))
+      .signers([user])
+      .rpc({ commitment: "confirmed" });
+  }
+
+  it("setup: market + funded trader", async () => {
+    config = await ensureConfig(program, provider);
+
+    await airdrop(provider, user.publicKey);
+
+    collateralMint = await createMint(provider.connection, payer, wallet.publicKey, null, 6);
+    const authorityAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, wallet.publicKey)
+    ).address;
+    userAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, user.publicKey)
+    ).address;
+    await mintTo(provider.connection, payer, collateralMint, authorityAta, payer, 10_000_000_000);
+    await mintTo(provider.connection, payer, collateralMint, userAta, payer, 1_000_000_000);
+
+    marketPda = pda(program, [
+      Buffer.from("market_v2"),
+      wallet.publicKey.toBuffer(),
+      marketId.toArrayLike(Buffer, "le", 8),
+    ]);
+    vaultPda = pda(program, [Buffer.from("vault_v2"), marketPda.toBuffer()]);
+    vaultAuthPda = pda(program, [Buffer.from("vault_auth_v2"), marketPda.toBuffer()]);
+    positionPda = pda(program, [Buffer.from("position_v2"), marketPda.toBuffer(), user.publicKey.toBuffer()]);
+
+    await createTestMarket(program, provider, {
+      marketId,
+      question: "Will the dust market resolve YES?",
+      metadataUri: "ar://dust-market",
+      collateralMint,
+      authorityCollateralAta: authorityAta,
+    });
+  });
+
+  it("buys below the minimum are rejected", async () => {
+    await setMinimums(10_000_000, 0);
+
+    let failed = false;
+    try {
+      await buy(1_000_000);
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("TradeTooSmall");
+    }
+    expect(failed).to.eq(true);
+
+    await buy(50_000_000);
+  });
+
+  it("partial sells below the minimum are rejected, closing sells are not", async () => {
+    const position = await program.account.positionV2.fetch(positionPda);
+
+    let failed = false;
+    try {
+      await sell(new anchor.BN(1_000));
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("TradeTooSmall");
+    }
+    expect(failed).to.eq(true);
+
+    // Leave a dust remainder, then close it out below the minimum
+    const dust = new anchor.BN(1_000);
+    await sell(position.yesShares.sub(dust));
+    await sell(dust);
+    const after = await program.account.positionV2.fetch(positionPda);
+    expect(after.yesShares.toNumber()).to.eq(0);
+  });
+
+  after(async () => {
+    // The config is shared with the other suites
+    await setMinimums(0, 0);
+  });
+});
@@ -0,0 +1,141 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { PublicKey } from "@solana/web3.js";
+import {
+  createMint,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+} from "@solana/spl-token";
+import { expect } from "chai";
+
+import { PredictionProgramV2 } from "../target/types/prediction_program_v2";
+import {
+  REGISTRY_PAGE_SIZE,
+  airdrop,
+  buyAccounts,
+  createTestMarket,
+  ensureConfig,
+  pda,
+  registryPagePda,
+  uniqueId,
+} from "./utils";
+
+describe("prediction_program_v2 (Squads multisig-gated resolution)", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace
+    .PredictionProgramV2 as Program<PredictionProgramV2>;
+
+  const payer = (provider.wallet as any).payer as anchor.web3.Keypair;
+  const wallet = provider.wallet as anchor.Wallet;
+
+  const user = anchor.web3.Keypair.generate();
+  const marketId = uniqueId();
+
+  let collateralMint: PublicKey;
+  let authorityAta: PublicKey;
+  let userAta: PublicKey;
+  let config: PublicKey;
+  let marketPda: PublicKey;
+  let vaultPda: PublicKey;
+  let vaultAuthPda: PublicKey;
+  let positionPda: PublicKey;
+
+  it("setup: flagship market + a YES position", async () => {
+    config = await ensureConfig(program, provider);
+
+    await airdrop(provider, user.publicKey);
+
+    collateralMint = await createMint(provider.connection, payer, wallet.publicKey, null, 6);
+    authorityAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, wallet.publicKey)
+    ).address;
+    userAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, user.publicKey)
+    ).address;
+    await mintTo(provider.connection, payer, collateralMint, authorityAta, payer, 10_000_000_000);
+    await mintTo(provider.connection, payer, collateralMint, userAta, payer, 1_000_000_000);
+
+    marketPda = pda(program, [
+      Buffer.from("market_v2"),
+      wallet.publicKey.toBuffer(),
+      marketId.toArrayLike(Buffer, "le", 8),
+    ]);
+    vaultPda = pda(program, [Buffer.from("vault_v2"), marketPda.toBuffer()]);
+    vaultAuthPda = pda(program, [Buffer.from("vault_auth_v2"), marketPda.toBuffer()]);
+    positionPda = pda(program, [Buffer.from("position_v2"), marketPda.toBuffer(), user.publicKey.toBuffer()]);
+
+    await createTestMarket(program, provider, {
+      marketId,
+      question: "Will the flagship index close above 6000?",
+      metadataUri: "ar://flagship-index-6000",
+      collateralMint,
+      authorityCollateralAta: authorityAta,
+    });
+
+    await program.methods
+      .buyShares(0, new anchor.BN(100_000_000), new anchor.BN(1))
+      .accounts(buyAccounts({
+        config,
+        market: marketPda,
+        vault: vaultPda,
+        vaultAuthority: vaultAuthPda,
+        position: positionPda,
+        user: user.publicKey,
+        payer: user.publicKey,
+        collateralMint,
+        userCollateralAta: userAta,
+      }))
+      .signers([user])
+      .rpc({ commitment: "confirmed" });
+  });
+
+  it("set_resolution_multisig only accepts Squads-owned multisig accounts", async () => {
+    let failed = false;
+    try {
+      await program.methods
+        .setResolutionMultisig(0)
+        .accounts({
+          market: marketPda,
+          multisig: user.publicKey, // system-owned
+          authority: wallet.publicKey,
+        })
+        .rpc({ commitment: "confirmed" });
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("InvalidMultisig");
+    }
+    expect(failed).to.eq(true);
+
+    const market = await program.account.marketV3.fetch(marketPda);
+    expect(market.resolutionVault.equals(PublicKey.default)).to.eq(true);
+  });
+
+  it("without a multisig only the authority can resolve", async () => {
+    const market = await program.account.marketV3.fetch(marketPda);
+    let failed = false;
+    try {
+      await program.methods
+        .resolveMarket(0)
+        .accounts({
+          config,
+          market: marketPda,
+          vault: vaultPda,
+          registryPage: registryPagePda(
+            program,
+            Math.floor(market.registryIndex.toNumber() / REGISTRY_PAGE_SIZE)
+          ),
+          lstStakePool: null,
+          parentMarket: null,
+          authority: user.publicKey,
+        })
+        .signers([user])
+        .rpc({ commitment: "confirmed" });
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("Unauthorized");
+    }
+    expect(failed).to.eq(true);
+  });
+});
@@ -0,0 +1,133 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { PublicKey, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import {
+  NATIVE_MINT,
+  createWrappedNativeAccount,
+  getAccount,
+} from "@solana/spl-token";
+import { expect } from "chai";
+
+import { PredictionProgramV2 } from "../target/types/prediction_program_v2";
+import {
+  airdrop,
+  buyAccounts,
+  createTestMarket,
+  ensureConfig,
+  pda,
+  sellAccounts,
+  uniqueId,
+} from "./utils";
+
+describe("prediction_program_v2 (native SOL collateral via wSOL wrapping)", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace
+    .PredictionProgramV2 as Program<PredictionProgramV2>;
+
+  const payer = (provider.wallet as any).payer as anchor.web3.Keypair;
+  const wallet = provider.wallet as anchor.Wallet;
+
+  const user = anchor.web3.Keypair.generate();
+  const marketId = uniqueId();
+  const initialLiquidity = new anchor.BN(LAMPORTS_PER_SOL / 2);
+
+  let authorityWsol: PublicKey;
+  let config: PublicKey;
+  let marketPda: PublicKey;
+  let vaultPda: PublicKey;
+  let vaultAuthPda: PublicKey;
+  let positionPda: PublicKey;
+  let unwrapPda: PublicKey;
+
+  it("setup: fund user + wrap authority SOL + derive PDAs", async () => {
+    config = await ensureConfig(program, provider);
+
+    await airdrop(provider, user.publicKey, 3 * LAMPORTS_PER_SOL);
+
+    authorityWsol = await createWrappedNativeAccount(
+      provider.connection,
+      payer,
+      wallet.publicKey,
+      2 * LAMPORTS_PER_SOL
+    );
+
+    marketPda = pda(program, [
+      Buffer.from("market_v2"),
+      wallet.publicKey.toBuffer(),
+      marketId.toArrayLike(Buffer, "le", 8),
+    ]);
+    vaultPda = pda(program, [Buffer.from("vault_v2"), marketPda.toBuffer()]);
+    vaultAuthPda = pda(program, [Buffer.from("vault_auth_v2"), marketPda.toBuffer()]);
+    positionPda = pda(program, [Buffer.from("position_v2"), marketPda.toBuffer(), user.publicKey.toBuffer()]);
+    unwrapPda = pda(program, [Buffer.from("wsol_unwrap"), marketPda.toBuffer(), user.publicKey.toBuffer()]);
+  });
+
+  it("create_market_cpmm: wSOL-collateralized market", async () => {
+    await createTestMarket(program, provider, {
+      marketId,
+      question: "Will SOL flip ETH by 2028?",
+      metadataUri: "ar://sol-flip-eth-2028",
+      collateralMint: NATIVE_MINT,
+      authorityCollateralAta: authorityWsol,
+      initialYesReserve: initialLiquidity,
+      initialNoReserve: initialLiquidity,
+    });
+
+    const vault = await getAccount(provider.connection, vaultPda);
+    expect(Number(vault.amount)).to.eq(initialLiquidity.toNumber() * 2);
+  });
+
+  it("buy_shares: pays lamports without a wSOL account", async () => {
+    const amountIn = new anchor.BN(LAMPORTS_PER_SOL / 10);
+    const vaultBefore = await getAccount(provider.connection, vaultPda);
+
+    await program.methods
+      .buyShares(0, amountIn, new anchor.BN(1))
+      .accounts(buyAccounts({
+        config,
+        market: marketPda,
+        vault: vaultPda,
+        vaultAuthority: vaultAuthPda,
+        position: positionPda,
+        user: user.publicKey,
+        payer: user.publicKey,
+        collateralMint: NATIVE_MINT,
+        userCollateralAta: null,
+      }))
+      .signers([user])
+      .rpc({ commitment: "confirmed" });
+
+    const vaultAfter = await getAccount(provider.connection, vaultPda);
+    expect(Number(vaultAfter.amount) - Number(vaultBefore.amount)).to.eq(
+      amountIn.toNumber()
+    );
+  });
+
+  it("sell_shares: payout is unwrapped to lamports and the temp account closed", async () => {
+    const position = await program.account.positionV2.fetch(positionPda);
+    const lamportsBefore = await provider.connection.getBalance(user.publicKey);
+
+    await program.methods
+      .sellShares(0, position.yesShares, new anchor.BN(1))
+      .accounts(sellAccounts({
+        config,
+        market: marketPda,
+        vault: vaultPda,
+        vaultAuthority: vaultAuthPda,
+        position: positionPda,
+        user: user.publicKey,
+        payer: user.publicKey,
+        collateralMint: NATIVE_MINT,
+        userCollateralAta: null,
+        wsolUnwrap: unwrapPda,
+      }))
+      .signers([user])
+      .rpc({ commitment: "confirmed" });
+
+    const lamportsAfter = await provider.connection.getBalance(user.publicKey);
+    expect(lamportsAfter).to.be.greaterThan(lamportsBefore);
+    expect(await provider.connection.getAccountInfo(unwrapPda)).to.eq(null);
+  });
+});
@@ -0,0 +1,240 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { PublicKey, SystemProgram } from "@solana/web3.js";
+import {
+  TOKEN_PROGRAM_ID,
+  createMint,
+  getAccount,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+} from "@solana/spl-token";
+import { expect } from "chai";
+
+import { PredictionProgramV2 } from "../target/types/prediction_program_v2";
+import {
+  airdrop,
+  createTestMarket,
+  ensureConfig,
+  pda,
+  uniqueId,
+} from "./utils";
+
+describe("prediction_program_v2 (negative-risk groups)", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace
+    .PredictionProgramV2 as Program<PredictionProgramV2>;
+
+  const payer = (provider.wallet as any).payer as anchor.web3.Keypair;
+  const wallet = provider.wallet as anchor.Wallet;
+
+  const user = anchor.web3.Keypair.generate();
+  const groupId = uniqueId();
+  const candidates = ["Alice", "Bob", "Carol"];
+
+  let collateralMint: PublicKey;
+  let authorityAta: PublicKey;
+  let userAta: PublicKey;
+  let group: PublicKey;
+  const markets: PublicKey[] = [];
+
+  const vaultPda = (market: PublicKey) => pda(program, [Buffer.from("vault_v2"), market.toBuffer()]);
+  const vaultAuthPda = (market: PublicKey) => pda(program, [Buffer.from("vault_auth_v2"), market.toBuffer()]);
+  const positionPda = (market: PublicKey) =>
+    pda(program, [Buffer.from("position_v2"), market.toBuffer(), user.publicKey.toBuffer()]);
+
+  it("setup: one market per candidate, grouped", async () => {
+    await ensureConfig(program, provider);
+
+    await airdrop(provider, user.publicKey);
+
+    collateralMint = await createMint(provider.connection, payer, wallet.publicKey, null, 6);
+    authorityAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, wallet.publicKey)
+    ).address;
+    userAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, user.publicKey)
+    ).address;
+    await mintTo(provider.connection, payer, collateralMint, authorityAta, payer, 10_000_000_000);
+    await mintTo(provider.connection, payer, collateralMint, userAta, payer, 1_000_000_000);
+
+    group = pda(program, [Buffer.from("market_group"), wallet.publicKey.toBuffer(), groupId.toArrayLike(Buffer, "le", 8)]);
+    await program.methods
+      .createMarketGroup(groupId, 0)
+      .accounts({
+        group,
+        collateralMint,
+        authority: wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc({ commitment: "confirmed" });
+
+    for (const name of candidates) {
+      const marketId = uniqueId();
+      const market = pda(program, [
+        Buffer.from("market_v2"),
+        wallet.publicKey.toBuffer(),
+        marketId.toArrayLike(Buffer, "le", 8),
+      ]);
+      await createTestMarket(program, provider, {
+        marketId,
+        question: `Will ${name} win?`,
+        metadataUri: `ar://election-${name.toLowerCase()}`,
+        collateralMint,
+        authorityCollateralAta: authorityAta,
+      });
+
+      await program.methods
+        .addGroupMember(name)
+        .accounts({ group, market, authority: wallet.publicKey })
+        .rpc({ commitment: "confirmed" });
+      markets.push(market);
+    }
+
+    const fetched = await program.account.marketGroup.fetch(group);
+    expect(fetched.members.length).to.eq(3);
+    expect(fetched.labels).to.deep.eq(candidates);
+  });
+
+  it("convert turns NO in one member into YES in the others", async () => {
+    const [alice, bob, carol] = markets;
+
+    await program.methods
+      .splitShares(new anchor.BN(100_000_000))
+      .accounts({
+        market: alice,
+        vault: vaultPda(alice),
+        position: positionPda(alice),
+        user: user.publicKey,
+        payer: user.publicKey,
+        collateralMint,
+        userCollateralAta: userAta,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([user])
+      .rpc({ commitment: "confirmed" });
+
+    for (const market of [bob, carol]) {
+      await program.methods
+        .openPosition()
+        .accounts({
+          market,
+          position: positionPda(market),
+          owner: user.publicKey,
+          payer: user.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([user])
+        .rpc({ commitment: "confirmed" });
+    }
+
+    await program.methods
+      .convert(new anchor.BN(100_000_000))
+      .accounts({
+        group,
+        market: alice,
+        position: positionPda(alice),
+        user: user.publicKey,
+      })
+      .remainingAccounts(
+        [bob, carol].flatMap((m) => [
+          { pubkey: m, isWritable: true, isSigner: false },
+          { pubkey: positionPda(m), isWritable: true, isSigner: false },
+        ])
+      )
+      .signers([user])
+      .rpc({ commitment: "confirmed" });
+
+    expect((await program.account.positionV2.fetch(positionPda(alice))).noShares.toNumber()).to.eq(0);
+    for (const market of [bob, carol]) {
+      const pos = await program.account.positionV2.fetch(positionPda(market));
+      expect(pos.yesShares.toNumber()).to.eq(100_000_000);
+      expect((await program.account.marketV3.fetch(market)).groupBackedYes.toNumber()).to.eq(100_000_000);
+    }
+    expect((await program.account.marketV3.fetch(alice)).groupNoClaim.toNumber()).to.eq(100_000_000);
+  });
+
+  it("a split in Carol leaves NO there too", async () => {
+    const carol = markets[2];
+    await program.methods
+      .splitShares(new anchor.BN(50_000_000))
+      .accounts({
+        market: carol,
+        vault: vaultPda(carol),
+        position: positionPda(carol),
+        user: user.publicKey,
+        payer: user.publicKey,
+        collateralMint,
+        userCollateralAta: userAta,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([user])
+      .rpc({ commitment: "confirmed" });
+
+    expect((await program.account.positionV2.fetch(positionPda(carol))).noShares.toNumber()).to.eq(50_000_000);
+  });
+
+  it("resolve_group moves the losers' claims to the winner's vault", async () => {
+    const [alice, bob] = markets;
+    const bobBefore = await program.account.marketV3.fetch(bob);
+
+    await program.methods
+      .resolveGroup(1)
+      .accounts({
+        group,
+        collateralMint,
+        authority: wallet.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .remainingAccounts(
+        markets.flatMap((m) => [
+          { pubkey: m, isWritable: true, isSigner: false },
+          { pubkey: vaultPda(m), isWritable: true, isSigner: false },
+          { pubkey: vaultAuthPda(m), isWritable: false, isSigner: false },
+        ])
+      )
+      .rpc({ commitment: "confirmed" });
+
+    const bobAfter = await program.account.marketV3.fetch(bob);
+    expect(bobAfter.winningOutcome).to.eq(0);
+    expect(bobAfter.vaultBalance.toNumber() - bobBefore.vaultBalance.toNumber()).to.eq(100_000_000);
+    expect((await program.account.marketV3.fetch(alice)).winningOutcome).to.eq(1);
+  });
+
+  it("claim_group pays every winning side the user holds in one instruction", async () => {
+    const [alice, bob, carol] = markets;
+    const before = await getAccount(provider.connection, userAta);
+
+    await program.methods
+      .claimGroup()
+      .accounts({
+        group,
+        collateralMint,
+        owner: user.publicKey,
+        ownerCollateralAta: userAta,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .remainingAccounts(
+        markets.flatMap((m) => [
+          { pubkey: m, isWritable: true, isSigner: false },
+          { pubkey: vaultPda(m), isWritable: true, isSigner: false },
+          { pubkey: vaultAuthPda(m), isWritable: false, isSigner: false },
+          { pubkey: positionPda(m), isWritable: true, isSigner: false },
+        ])
+      )
+      .signers([user])
+      .rpc({ commitment: "confirmed" });
+
+    // YES in Bob and NO in Carol win; YES in Alice loses
+    expect((await program.account.positionV2.fetch(positionPda(bob))).claimed).to.eq(true);
+    expect((await program.account.positionV2.fetch(positionPda(carol))).claimed).to.eq(true);
+    expect((await program.account.positionV2.fetch(positionPda(alice))).claimed).to.eq(false);
+
+    const after = await getAccount(provider.connection, userAta);
+    expect(Number(after.amount - before.amount)).to.be.greaterThan(100_000_000);
+  });
+});
@@ -0,0 +1,201 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { PublicKey, SystemProgram } from "@solana/web3.js";
+import {
+  TOKEN_PROGRAM_ID,
+  createMint,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+} from "@solana/spl-token";
+import { expect } from "chai";
+
+import { PredictionProgramV2 } from "../target/types/prediction_program_v2";
+import {
+  REGISTRY_PAGE_SIZE,
+  createTestMarket,
+  ensureConfig,
+  pda,
+  registryPagePda,
+  uniqueId,
+} from "./utils";
+
+describe("prediction_program_v2 (oracle configs + resolve_with_oracle)", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace
+    .PredictionProgramV2 as Program<PredictionProgramV2>;
+
+  const payer = (provider.wallet as any).payer as anchor.web3.Keypair;
+  const wallet = provider.wallet as anchor.Wallet;
+
+  const marketId = uniqueId();
+  // Pyth SOL/USD feed id
+  const feedId = Buffer.from(
+    "ef0d8b6fda2ceba41da15d4095d1da392a0d2f8ed0c6c7bc0f4cfac8c280b56d",
+    "hex"
+  );
+
+  let collateralMint: PublicKey;
+  let authorityAta: PublicKey;
+  let config: PublicKey;
+  let marketPda: PublicKey;
+  let oracleConfigPda: PublicKey;
+
+  it("setup: market", async () => {
+    config = await ensureConfig(program, provider);
+
+    collateralMint = await createMint(provider.connection, payer, wallet.publicKey, null, 6);
+    authorityAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, wallet.publicKey)
+    ).address;
+    await mintTo(provider.connection, payer, collateralMint, authorityAta, payer, 10_000_000_000);
+
+    marketPda = pda(program, [
+      Buffer.from("market_v2"),
+      wallet.publicKey.toBuffer(),
+      marketId.toArrayLike(Buffer, "le", 8),
+    ]);
+    await createTestMarket(program, provider, {
+      marketId,
+      question: "Will SOL/USD close at or above $200?",
+      metadataUri: "ar://sol-usd-200",
+      collateralMint,
+      authorityCollateralAta: authorityAta,
+    });
+  });
+
+  it("init_oracle_config requires a feed account for Chainlink", async () => {
+    oracleConfigPda = pda(program, [Buffer.from("oracle_config"), marketPda.toBuffer()]);
+    let failed = false;
+    try {
+      await program.methods
+        .initOracleConfig({
+          adapter: 2, // Chainlink
+          feed: PublicKey.default,
+          feedId: new Array(32).fill(0),
+          threshold: new anchor.BN(200_00000000),
+          exponent: -8,
+          yesIfAbove: true,
+        })
+        .accounts({
+          market: marketPda,
+          oracleConfig: oracleConfigPda,
+          authority: wallet.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc({ commitment: "confirmed" });
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("InvalidOracle");
+    }
+    expect(failed).to.eq(true);
+  });
+
+  it("init_oracle_config stores the feed and threshold", async () => {
+    await program.methods
+      .initOracleConfig({
+        adapter: 0, // Pyth
+        feed: PublicKey.default,
+        feedId: Array.from(feedId),
+        threshold: new anchor.BN(200_00000000),
+        exponent: -8,
+        yesIfAbove: true,
+      })
+      .accounts({
+        market: marketPda,
+        oracleConfig: oracleConfigPda,
+        authority: wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc({ commitment: "confirmed" });
+
+    const oracle = await program.account.oracleConfig.fetch(oracleConfigPda);
+    expect(Buffer.from(oracle.feedId).equals(feedId)).to.eq(true);
+    expect(oracle.threshold.toString()).to.eq("20000000000");
+    expect(oracle.yesIfAbove).to.eq(true);
+    expect(oracle.adapter).to.eq(0);
+    expect((await program.account.marketV3.fetch(marketPda)).oracleMarket).to.eq(1);
+  });
+
+  it("oracle markets cannot be resolved manually", async () => {
+    const market = await program.account.marketV3.fetch(marketPda);
+    let failed = false;
+    try {
+      await program.methods
+        .resolveMarket(0)
+        .accounts({
+          config,
+          market: marketPda,
+          vault: pda(program, [Buffer.from("vault_v2"), marketPda.toBuffer()]),
+          registryPage: registryPagePda(
+            program,
+            Math.floor(market.registryIndex.toNumber() / REGISTRY_PAGE_SIZE)
+          ),
+          lstStakePool: null,
+          parentMarket: null,
+          authority: wallet.publicKey,
+        })
+        .rpc({ commitment: "confirmed" });
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("OracleMarket");
+    }
+    expect(failed).to.eq(true);
+  });
+
+  it("resolve_with_oracle waits for end_time", async () => {
+    const market = await program.account.marketV3.fetch(marketPda);
+    const cranker = anchor.web3.Keypair.generate();
+    let failed = false;
+    try {
+      await program.methods
+        .resolveWithOracle(null)
+        .accounts({
+          config,
+          market: marketPda,
+          oracleConfig: oracleConfigPda,
+          vault: pda(program, [Buffer.from("vault_v2"), marketPda.toBuffer()]),
+          vaultAuthority: pda(program, [Buffer.from("vault_auth_v2"), marketPda.toBuffer()]),
+          collateralMint,
+          oracleAccount: SystemProgram.programId,
+          instructions: null,
+          resolver: cranker.publicKey,
+          resolverCollateralAta: null,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          registryPage: registryPagePda(
+            program,
+            Math.floor(market.registryIndex.toNumber() / REGISTRY_PAGE_SIZE)
+          ),
+        })
+        .signers([cranker])
+        .rpc({ commitment: "confirmed" });
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("MarketNotEnded");
+    }
+    expect(failed).to.eq(true);
+  });
+
+  it("void_unresolvable_market waits out the oracle timeout", async () => {
+    const market = await program.account.marketV3.fetch(marketPda);
+    let failed = false;
+    try {
+      await program.methods
+        .voidUnresolvableMarket()
+        .accounts({
+          market: marketPda,
+          vault: pda(program, [Buffer.from("vault_v2"), marketPda.toBuffer()]),
+          registryPage: registryPagePda(
+            program,
+            Math.floor(market.registryIndex.toNumber() / REGISTRY_PAGE_SIZE)
+          ),
+        })
+        .rpc({ commitment: "confirmed" });
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("OracleTimeoutPending");
+    }
+    expect(failed).to.eq(true);
+  });
+});
@@ -0,0 +1,229 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { PublicKey, SystemProgram } from "@solana/web3.js";
+import {
+  TOKEN_PROGRAM_ID,
+  createMint,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+} from "@solana/spl-token";
+import { expect } from "chai";
+
+import { PredictionProgramV2 } from "../target/types/prediction_program_v2";
+import {
+  airdrop,
+  createTestMarket,
+  ensureConfig,
+  pda,
+  uniqueId,
+} from "./utils";
+
+describe("prediction_program_v2 (order book + router)", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace
+    .PredictionProgramV2 as Program<PredictionProgramV2>;
+
+  const payer = (provider.wallet as any).payer as anchor.web3.Keypair;
+  const wallet = provider.wallet as anchor.Wallet;
+
+  const maker = anchor.web3.Keypair.generate();
+  const taker = anchor.web3.Keypair.generate();
+  const marketId = uniqueId();
+  const initialLiquidity = new anchor.BN(1_000_000_000);
+
+  let collateralMint: PublicKey;
+  let authorityAta: PublicKey;
+  let marketPda: PublicKey;
+  let vaultPda: PublicKey;
+  let vaultAuthPda: PublicKey;
+  let custodyAuthority: PublicKey;
+  let custody: PublicKey;
+  const atas: Record<string, PublicKey> = {};
+
+  const positionPda = (owner: PublicKey) =>
+    pda(program, [Buffer.from("position_v2"), marketPda.toBuffer(), owner.toBuffer()]);
+  const tradingAccountPda = (owner: PublicKey) =>
+    pda(program, [Buffer.from("trading_account"), owner.toBuffer(), collateralMint.toBuffer()]);
+  let orderBook: PublicKey;
+
+  it("setup: market, order book, funded trading accounts, maker inventory", async () => {
+    await ensureConfig(program, provider);
+
+    for (const kp of [maker, taker]) {
+      await airdrop(provider, kp.publicKey);
+    }
+
+    collateralMint = await createMint(provider.connection, payer, wallet.publicKey, null, 6);
+    authorityAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, wallet.publicKey)
+    ).address;
+    await mintTo(provider.connection, payer, collateralMint, authorityAta, payer, 10_000_000_000);
+    for (const kp of [maker, taker]) {
+      atas[kp.publicKey.toBase58()] = (
+        await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, kp.publicKey)
+      ).address;
+      await mintTo(provider.connection, payer, collateralMint, atas[kp.publicKey.toBase58()], payer, 1_000_000_000);
+    }
+
+    marketPda = pda(program, [Buffer.from("market_v2"), wallet.publicKey.toBuffer(), marketId.toArrayLike(Buffer, "le", 8)]);
+    vaultPda = pda(program, [Buffer.from("vault_v2"), marketPda.toBuffer()]);
+    vaultAuthPda = pda(program, [Buffer.from("vault_auth_v2"), marketPda.toBuffer()]);
+    orderBook = pda(program, [Buffer.from("order_book"), marketPda.toBuffer()]);
+    custodyAuthority = pda(program, [Buffer.from("trading_custody_auth")]);
+    custody = pda(program, [Buffer.from("trading_custody"), collateralMint.toBuffer()]);
+
+    await createTestMarket(program, provider, {
+      marketId,
+      question: "Will the book beat the AMM?",
+      metadataUri: "ar://order-book",
+      collateralMint,
+      authorityCollateralAta: authorityAta,
+      initialYesReserve: initialLiquidity,
+      initialNoReserve: initialLiquidity,
+    });
+
+    for (const kp of [maker, taker]) {
+      await program.methods
+        .depositToTradingAccount(new anchor.BN(200_000_000))
+        .accounts({
+          tradingAccount: tradingAccountPda(kp.publicKey),
+          custodyAuthority,
+          custody,
+          user: kp.publicKey,
+          payer: kp.publicKey,
+          collateralMint,
+          userCollateralAta: atas[kp.publicKey.toBase58()],
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([kp])
+        .rpc({ commitment: "confirmed" });
+    }
+
+    await program.methods
+      .splitShares(new anchor.BN(100_000_000))
+      .accounts({
+        market: marketPda,
+        vault: vaultPda,
+        position: positionPda(maker.publicKey),
+        user: maker.publicKey,
+        payer: maker.publicKey,
+        collateralMint,
+        userCollateralAta: atas[maker.publicKey.toBase58()],
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([maker])
+      .rpc({ commitment: "confirmed" });
+
+    await program.methods
+      .initOrderBook()
+      .accounts({
+        orderBook,
+        market: marketPda,
+        payer: wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc({ commitment: "confirmed" });
+  });
+
+  const placeOrder = (side: number, price: number, shares: number) =>
+    program.methods
+      .placeBookOrder(side, new anchor.BN(price), new anchor.BN(shares))
+      .accounts({
+        orderBook,
+        market: marketPda,
+        position: positionPda(maker.publicKey),
+        tradingAccount: tradingAccountPda(maker.publicKey),
+        owner: maker.publicKey,
+        payer: maker.publicKey,
+        allowlistEntry: null,
+        permitGate: null,
+        instructions: null,
+        tokenGate: null,
+        gateTokenAccount: null,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([maker])
+      .rpc({ commitment: "confirmed" });
+
+  it("place_book_order rests a post-only ask below the AMM price", async () => {
+    await placeOrder(1, 400_000, 50_000_000);
+
+    const book = await program.account.orderBook.fetch(orderBook);
+    expect(book.asks.length).to.eq(1);
+    expect(book.asks[0].price.toNumber()).to.eq(400_000);
+    const makerPos = await program.account.positionV2.fetch(positionPda(maker.publicKey));
+    expect(makerPos.yesShares.toNumber()).to.eq(50_000_000);
+
+    let failed = false;
+    try {
+      await placeOrder(0, 450_000, 10_000_000);
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("OrderWouldCross");
+    }
+    expect(failed).to.eq(true);
+  });
+
+  it("route_buy_yes fills the cheaper ask first, then the AMM", async () => {
+    const marketBefore = await program.account.marketV3.fetch(marketPda);
+
+    await program.methods
+      .routeBuyYes(new anchor.BN(100_000_000), new anchor.BN(1))
+      .accounts({
+        orderBook,
+        market: marketPda,
+        vault: vaultPda,
+        vaultAuthority: vaultAuthPda,
+        position: positionPda(taker.publicKey),
+        tradingAccount: tradingAccountPda(taker.publicKey),
+        custodyAuthority,
+        custody,
+        user: taker.publicKey,
+        payer: taker.publicKey,
+        collateralMint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .remainingAccounts([
+        { pubkey: tradingAccountPda(maker.publicKey), isWritable: true, isSigner: false },
+      ])
+      .signers([taker])
+      .rpc({ commitment: "confirmed" });
+
+    const book = await program.account.orderBook.fetch(orderBook);
+    expect(book.asks.length).to.eq(0);
+    const makerAcc = await program.account.tradingAccount.fetch(tradingAccountPda(maker.publicKey));
+    expect(makerAcc.balance.toNumber()).to.eq(220_000_000);
+
+    // 20M bought 50M shares off the book, the other 80M went to the AMM
+    const takerPos = await program.account.positionV2.fetch(positionPda(taker.publicKey));
+    expect(takerPos.yesShares.toNumber()).to.be.greaterThan(50_000_000);
+    const marketAfter = await program.account.marketV3.fetch(marketPda);
+    expect(marketAfter.vaultBalance.toNumber() - marketBefore.vaultBalance.toNumber()).to.eq(80_000_000);
+  });
+
+  it("cancel_book_order refunds a resting bid", async () => {
+    await placeOrder(0, 100_000, 10_000_000);
+    const book = await program.account.orderBook.fetch(orderBook);
+    const bid = book.bids[0];
+
+    await program.methods
+      .cancelBookOrder(0, bid.orderId)
+      .accounts({
+        orderBook,
+        position: positionPda(maker.publicKey),
+        tradingAccount: tradingAccountPda(maker.publicKey),
+        owner: maker.publicKey,
+      })
+      .signers([maker])
+      .rpc({ commitment: "confirmed" });
+
+    expect((await program.account.orderBook.fetch(orderBook)).bids.length).to.eq(0);
+    const makerAcc = await program.account.tradingAccount.fetch(tradingAccountPda(maker.publicKey));
+    expect(makerAcc.balance.toNumber()).to.eq(220_000_000);
+  });
+});
@@ -0,0 +1,237 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { PublicKey, SystemProgram } from "@solana/web3.js";
+import {
+  TOKEN_PROGRAM_ID,
+  createMint,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+  getAccount,
+} from "@solana/spl-token";
+import { expect } from "chai";
+
+import { PredictionProgramV2 } from "../target/types/prediction_program_v2";
+import {
+  airdrop,
+  buyAccounts,
+  createTestMarket,
+  ensureConfig,
+  pda,
+  uniqueId,
+} from "./utils";
+
+describe("prediction_program_v2 (OTC offers)", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace
+    .PredictionProgramV2 as Program<PredictionProgramV2>;
+
+  const payer = (provider.wallet as any).payer as anchor.web3.Keypair;
+  const wallet = provider.wallet as anchor.Wallet;
+
+  const trader = anchor.web3.Keypair.generate();
+  const marketId = uniqueId();
+  const initialLiquidity = new anchor.BN(1_000_000_000);
+
+  let collateralMint: PublicKey;
+  let authorityAta: PublicKey;
+  let traderAta: PublicKey;
+  let config: PublicKey;
+  let marketPda: PublicKey;
+  let vaultPda: PublicKey;
+  let vaultAuthPda: PublicKey;
+  let positionPda: PublicKey;
+  let makerPosition: PublicKey;
+
+  const offerPda = (id: number) =>
+    pda(program, [
+      Buffer.from("otc_offer"),
+      wallet.publicKey.toBuffer(),
+      new anchor.BN(id).toArrayLike(Buffer, "le", 8),
+    ]);
+
+  it("setup: mint + market + PDAs", async () => {
+    config = await ensureConfig(program, provider);
+
+    await airdrop(provider, trader.publicKey);
+
+    collateralMint = await createMint(
+      provider.connection,
+      payer,
+      wallet.publicKey,
+      null,
+      6
+    );
+    authorityAta = (
+      await getOrCreateAssociatedTokenAccount(
+        provider.connection,
+        payer,
+        collateralMint,
+        wallet.publicKey
+      )
+    ).address;
+    traderAta = (
+      await getOrCreateAssociatedTokenAccount(
+        provider.connection,
+        payer,
+        collateralMint,
+        trader.publicKey
+      )
+    ).address;
+    await mintTo(provider.connection, payer, collateralMint, authorityAta, payer, 10_000_000_000);
+    await mintTo(provider.connection, payer, collateralMint, traderAta, payer, 1_000_000_000);
+
+    marketPda = pda(program, [
+      Buffer.from("market_v2"),
+      wallet.publicKey.toBuffer(),
+      marketId.toArrayLike(Buffer, "le", 8),
+    ]);
+    vaultPda = pda(program, [Buffer.from("vault_v2"), marketPda.toBuffer()]);
+    vaultAuthPda = pda(program, [Buffer.from("vault_auth_v2"), marketPda.toBuffer()]);
+    positionPda = pda(program, [Buffer.from("position_v2"), marketPda.toBuffer(), trader.publicKey.toBuffer()]);
+
+    makerPosition = pda(program, [Buffer.from("position_v2"), marketPda.toBuffer(), wallet.publicKey.toBuffer()]);
+
+    await createTestMarket(program, provider, {
+      marketId,
+      question: "Will the block trade clear?",
+      metadataUri: "ar://block-trade",
+      collateralMint,
+      authorityCollateralAta: authorityAta,
+      initialYesReserve: initialLiquidity,
+      initialNoReserve: initialLiquidity,
+    });
+  });
+
+  it("maker buys YES and escrows it in an offer", async () => {
+    await program.methods
+      .buyShares(0, new anchor.BN(200_000_000), new anchor.BN(1))
+      .accounts(buyAccounts({
+        config,
+        market: marketPda,
+        vault: vaultPda,
+        vaultAuthority: vaultAuthPda,
+        position: makerPosition,
+        user: wallet.publicKey,
+        payer: wallet.publicKey,
+        collateralMint,
+        userCollateralAta: authorityAta,
+      }))
+      .rpc({ commitment: "confirmed" });
+
+    const before = await program.account.positionV2.fetch(makerPosition);
+    const shares = before.yesShares;
+
+    await program.methods
+      .createOffer({
+        offerId: new anchor.BN(1),
+        outcomeIndex: 0,
+        shares,
+        askMarket: PublicKey.default,
+        askOutcome: 0,
+        askAmount: new anchor.BN(150_000_000),
+      })
+      .accounts({
+        offer: offerPda(1),
+        market: marketPda,
+        makerPosition,
+        maker: wallet.publicKey,
+        payer: wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc({ commitment: "confirmed" });
+
+    const after = await program.account.positionV2.fetch(makerPosition);
+    expect(after.yesShares.toNumber()).to.eq(0);
+    const offer = await program.account.offer.fetch(offerPda(1));
+    expect(offer.shares.toNumber()).to.eq(shares.toNumber());
+  });
+
+  it("accept_offer swaps collateral for the escrowed shares without moving the pool", async () => {
+    const marketBefore = await program.account.marketV3.fetch(marketPda);
+    const offer = await program.account.offer.fetch(offerPda(1));
+    const makerBefore = Number((await getAccount(provider.connection, authorityAta)).amount);
+
+    await program.methods
+      .acceptOffer()
+      .accounts({
+        offer: offerPda(1),
+        market: marketPda,
+        takerPosition: positionPda,
+        maker: wallet.publicKey,
+        taker: trader.publicKey,
+        payer: trader.publicKey,
+        collateralMint,
+        takerCollateralAta: traderAta,
+        makerCollateralAta: authorityAta,
+        allowlistEntry: null,
+        permitGate: null,
+        instructions: null,
+        tokenGate: null,
+        gateTokenAccount: null,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([trader])
+      .rpc({ commitment: "confirmed" });
+
+    const makerAfter = Number((await getAccount(provider.connection, authorityAta)).amount);
+    expect(makerAfter - makerBefore).to.eq(offer.askAmount.toNumber());
+
+    const position = await program.account.positionV2.fetch(positionPda);
+    expect(position.yesShares.toNumber()).to.eq(offer.shares.toNumber());
+
+    const marketAfter = await program.account.marketV3.fetch(marketPda);
+    expect(marketAfter.yesPool.toNumber()).to.eq(marketBefore.yesPool.toNumber());
+    expect(await provider.connection.getAccountInfo(offerPda(1))).to.eq(null);
+  });
+
+  it("cancel_offer returns the escrowed shares", async () => {
+    await program.methods
+      .buyShares(1, new anchor.BN(50_000_000), new anchor.BN(1))
+      .accounts(buyAccounts({
+        config,
+        market: marketPda,
+        vault: vaultPda,
+        vaultAuthority: vaultAuthPda,
+        position: makerPosition,
+        user: wallet.publicKey,
+        payer: wallet.publicKey,
+        collateralMint,
+        userCollateralAta: authorityAta,
+      }))
+      .rpc({ commitment: "confirmed" });
+
+    const before = await program.account.positionV2.fetch(makerPosition);
+    await program.methods
+      .createOffer({
+        offerId: new anchor.BN(2),
+        outcomeIndex: 1,
+        shares: before.noShares,
+        askMarket: PublicKey.default,
+        askOutcome: 0,
+        askAmount: new anchor.BN(1),
+      })
+      .accounts({
+        offer: offerPda(2),
+        market: marketPda,
+        makerPosition,
+        maker: wallet.publicKey,
+        payer: wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc({ commitment: "confirmed" });
+    expect(
+      (await program.account.positionV2.fetch(makerPosition)).noShares.toNumber()
+    ).to.eq(0);
+
+    await program.methods
+      .cancelOffer()
+      .accounts({ offer: offerPda(2), makerPosition, maker: wallet.publicKey })
+      .rpc({ commitment: "confirmed" });
+
+    const after = await program.account.positionV2.fetch(makerPosition);
+    expect(after.noShares.toNumber()).to.eq(before.noShares.toNumber());
+  });
+});
@@ -0,0 +1,196 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { PublicKey, SystemProgram } from "@solana/web3.js";
+import {
+  TOKEN_PROGRAM_ID,
+  createMint,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+} from "@solana/spl-token";
+import { expect } from "chai";
+
+import { PredictionProgramV2 } from "../target/types/prediction_program_v2";
+import {
+  airdrop,
+  createTestMarket,
+  ensureConfig,
+  pda,
+  uniqueId,
+} from "./utils";
+
+describe("prediction_program_v2 (parlays)", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace
+    .PredictionProgramV2 as Program<PredictionProgramV2>;
+
+  const payer = (provider.wallet as any).payer as anchor.web3.Keypair;
+  const wallet = provider.wallet as anchor.Wallet;
+
+  const user = anchor.web3.Keypair.generate();
+  const questions = ["Will the home side win?", "Will the away striker score?"];
+
+  let collateralMint: PublicKey;
+  let authorityAta: PublicKey;
+  let userAta: PublicKey;
+  let config: PublicKey;
+  let pool: PublicKey;
+  let poolVault: PublicKey;
+  let poolAuthority: PublicKey;
+  const markets: PublicKey[] = [];
+  const candles: PublicKey[] = [];
+
+  const parlayPda = (id: anchor.BN) =>
+    pda(program, [Buffer.from("parlay"), user.publicKey.toBuffer(), id.toArrayLike(Buffer, "le", 8)]);
+
+  // place_parlay takes [market, price_candles] per leg
+  const legAccounts = () =>
+    markets.flatMap((m, i) => [
+      { pubkey: m, isWritable: true, isSigner: false },
+      { pubkey: candles[i], isWritable: false, isSigner: false },
+    ]);
+
+  it("setup: two leg markets with candles and a funded parlay pool", async () => {
+    config = await ensureConfig(program, provider);
+
+    await airdrop(provider, user.publicKey);
+
+    collateralMint = await createMint(provider.connection, payer, wallet.publicKey, null, 6);
+    authorityAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, wallet.publicKey)
+    ).address;
+    userAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, user.publicKey)
+    ).address;
+    await mintTo(provider.connection, payer, collateralMint, authorityAta, payer, 10_000_000_000);
+    await mintTo(provider.connection, payer, collateralMint, userAta, payer, 1_000_000_000);
+
+    for (const [i, question] of questions.entries()) {
+      const { market } = await createTestMarket(program, provider, {
+        marketId: uniqueId(),
+        question,
+        metadataUri: `ar://parlay-leg-${i}`,
+        collateralMint,
+        authorityCollateralAta: authorityAta,
+      });
+      markets.push(market);
+
+      const priceCandles = pda(program, [Buffer.from("price_candles"), market.toBuffer()]);
+      await program.methods
+        .initPriceCandles(new anchor.BN(3600))
+        .accounts({ market, priceCandles, authority: wallet.publicKey, systemProgram: SystemProgram.programId })
+        .rpc({ commitment: "confirmed" });
+      await program.methods
+        .snapshotPrice()
+        .accounts({ market, priceCandles })
+        .rpc({ commitment: "confirmed" });
+      candles.push(priceCandles);
+    }
+
+    pool = pda(program, [Buffer.from("parlay_pool"), collateralMint.toBuffer()]);
+    poolVault = pda(program, [Buffer.from("parlay_vault"), collateralMint.toBuffer()]);
+    poolAuthority = pda(program, [Buffer.from("parlay_auth")]);
+    await program.methods
+      .fundParlayPool(new anchor.BN(1_000_000_000))
+      .accounts({
+        config,
+        pool,
+        poolAuthority,
+        poolVault,
+        admin: wallet.publicKey,
+        adminCollateralAta: authorityAta,
+        collateralMint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc({ commitment: "confirmed" });
+  });
+
+  it("place_parlay multiplies the legs' odds less the house edge and reserves the payout", async () => {
+    const parlayId = new anchor.BN(1);
+    await program.methods
+      .placeParlay({
+        parlayId,
+        outcomes: Buffer.from([0, 1]),
+        stake: new anchor.BN(10_000_000),
+        minPayout: new anchor.BN(1),
+      })
+      .accounts({
+        parlay: parlayPda(parlayId),
+        pool,
+        poolVault,
+        owner: user.publicKey,
+        payer: user.publicKey,
+        ownerCollateralAta: userAta,
+        collateralMint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .remainingAccounts(legAccounts())
+      .signers([user])
+      .rpc({ commitment: "confirmed" });
+
+    // 50% x 50% => 4x the stake, less the 5% house edge
+    const parlay = await program.account.parlay.fetch(parlayPda(parlayId));
+    expect(parlay.payout.toNumber()).to.eq(38_000_000);
+    expect((await program.account.parlayPool.fetch(pool)).reserved.toNumber()).to.eq(38_000_000);
+  });
+
+  it("place_parlay rejects legs without a candle reference", async () => {
+    const parlayId = new anchor.BN(2);
+    let failed = false;
+    try {
+      await program.methods
+        .placeParlay({
+          parlayId,
+          outcomes: Buffer.from([0, 1]),
+          stake: new anchor.BN(10_000_000),
+          minPayout: new anchor.BN(1),
+        })
+        .accounts({
+          parlay: parlayPda(parlayId),
+          pool,
+          poolVault,
+          owner: user.publicKey,
+          payer: user.publicKey,
+          ownerCollateralAta: userAta,
+          collateralMint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .remainingAccounts(markets.map((m) => ({ pubkey: m, isWritable: true, isSigner: false })))
+        .signers([user])
+        .rpc({ commitment: "confirmed" });
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("InvalidRemainingAccounts");
+    }
+    expect(failed).to.eq(true);
+  });
+
+  it("settle_parlay waits for the legs to be final", async () => {
+    const parlayId = new anchor.BN(1);
+    let failed = false;
+    try {
+      await program.methods
+        .settleParlay()
+        .accounts({
+          parlay: parlayPda(parlayId),
+          pool,
+          poolAuthority,
+          poolVault,
+          owner: user.publicKey,
+          ownerCollateralAta: userAta,
+          collateralMint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .remainingAccounts(markets.map((m) => ({ pubkey: m, isWritable: false, isSigner: false })))
+        .rpc({ commitment: "confirmed" });
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("ParlayNotSettled");
+    }
+    expect(failed).to.eq(true);
+  });
+});
@@ -0,0 +1,172 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { PublicKey, SystemProgram } from "@solana/web3.js";
+import {
+  TOKEN_PROGRAM_ID,
+  createMint,
+  getAccount,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+} from "@solana/spl-token";
+import { expect } from "chai";
+
+import { PredictionProgramV2 } from "../target/types/prediction_program_v2";
+import {
+  REGISTRY_PAGE_SIZE,
+  buyAccounts,
+  createTestMarket,
+  ensureConfig,
+  pda,
+  registryPagePda,
+  uniqueId,
+} from "./utils";
+
+describe("prediction_program_v2 (batched payouts)", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace
+    .PredictionProgramV2 as Program<PredictionProgramV2>;
+
+  const payer = (provider.wallet as any).payer as anchor.web3.Keypair;
+  const wallet = provider.wallet as anchor.Wallet;
+
+  const marketId = uniqueId();
+  // Two YES winners and one NO loser
+  const traders = [0, 0, 1].map((outcome) => ({
+    keypair: anchor.web3.Keypair.generate(),
+    outcome,
+    ata: PublicKey.default,
+    position: PublicKey.default,
+  }));
+
+  let collateralMint: PublicKey;
+  let config: PublicKey;
+  let marketPda: PublicKey;
+  let vaultPda: PublicKey;
+  let vaultAuthPda: PublicKey;
+
+  async function distribute(maxPositions: number, batch: typeof traders) {
+    await program.methods
+      .distributePayoutsBatch(maxPositions)
+      .accounts({
+        market: marketPda,
+        vault: vaultPda,
+        vaultAuthority: vaultAuthPda,
+        collateralMint,
+        creatorBond: null,
+        protocolStats: null,
+        keeper: wallet.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .remainingAccounts(
+        batch.flatMap((t) => [
+          { pubkey: t.position, isSigner: false, isWritable: true },
+          { pubkey: t.ata, isSigner: false, isWritable: true },
+          { pubkey: t.keypair.publicKey, isSigner: false, isWritable: true },
+        ])
+      )
+      .rpc({ commitment: "confirmed" });
+  }
+
+  it("setup: market resolved with winners and a loser", async () => {
+    config = await ensureConfig(program, provider);
+
+    collateralMint = await createMint(provider.connection, payer, wallet.publicKey, null, 6);
+    const authorityAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, wallet.publicKey)
+    ).address;
+    await mintTo(provider.connection, payer, collateralMint, authorityAta, payer, 10_000_000_000);
+
+    marketPda = pda(program, [
+      Buffer.from("market_v2"),
+      wallet.publicKey.toBuffer(),
+      marketId.toArrayLike(Buffer, "le", 8),
+    ]);
+    vaultPda = pda(program, [Buffer.from("vault_v2"), marketPda.toBuffer()]);
+    vaultAuthPda = pda(program, [Buffer.from("vault_auth_v2"), marketPda.toBuffer()]);
+
+    await createTestMarket(program, provider, {
+      marketId,
+      question: "Will the crank settle everyone?",
+      metadataUri: "ar://payout-batch",
+      collateralMint,
+      authorityCollateralAta: authorityAta,
+    });
+
+    for (const t of traders) {
+      t.ata = (
+        await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, t.keypair.publicKey)
+      ).address;
+      await mintTo(provider.connection, payer, collateralMint, t.ata, payer, 100_000_000);
+      t.position = pda(program, [Buffer.from("position_v2"), marketPda.toBuffer(), t.keypair.publicKey.toBuffer()]);
+
+      await program.methods
+        .buyShares(t.outcome, new anchor.BN(20_000_000), new anchor.BN(1))
+        .accounts(buyAccounts({
+          config,
+          market: marketPda,
+          vault: vaultPda,
+          vaultAuthority: vaultAuthPda,
+          position: t.position,
+          user: t.keypair.publicKey,
+          payer: wallet.publicKey,
+          collateralMint,
+          userCollateralAta: t.ata,
+        }))
+        .signers([t.keypair])
+        .rpc({ commitment: "confirmed" });
+    }
+
+    const market = await program.account.marketV3.fetch(marketPda);
+    await program.methods
+      .resolveMarketPayouts([new anchor.BN(1_000_000), new anchor.BN(0)])
+      .accounts({
+        config,
+        market: marketPda,
+        vault: vaultPda,
+        registryPage: registryPagePda(
+          program,
+          Math.floor(market.registryIndex.toNumber() / REGISTRY_PAGE_SIZE)
+        ),
+        lstStakePool: null,
+        parentMarket: null,
+        authority: wallet.publicKey,
+      })
+      .rpc({ commitment: "confirmed" });
+  });
+
+  it("pays at most max_positions per call", async () => {
+    await distribute(1, traders);
+
+    expect((await program.account.positionV2.fetch(traders[0].position)).claimed).to.eq(true);
+    expect((await program.account.positionV2.fetch(traders[1].position)).claimed).to.eq(false);
+    expect(Number((await getAccount(provider.connection, traders[0].ata)).amount)).to.be.greaterThan(80_000_000);
+  });
+
+  it("rejects a token account that isn't the owner's", async () => {
+    let failed = false;
+    try {
+      await distribute(1, [{ ...traders[1], ata: traders[0].ata }]);
+    } catch (e: any) {
+      failed = true;
+    }
+    expect(failed).to.eq(true);
+  });
+
+  it("skips claimed and losing positions", async () => {
+    const loserBefore = await getAccount(provider.connection, traders[2].ata);
+
+    await distribute(3, traders);
+
+    expect((await program.account.positionV2.fetch(traders[1].position)).claimed).to.eq(true);
+    expect(Number((await getAccount(provider.connection, traders[1].ata)).amount)).to.be.greaterThan(80_000_000);
+    expect((await program.account.positionV2.fetch(traders[2].position)).claimed).to.eq(false);
+    const loserAfter = await getAccount(provider.connection, traders[2].ata);
+    expect(loserAfter.amount.toString()).to.eq(loserBefore.amount.toString());
+
+    const market = await program.account.marketV3.fetch(marketPda);
+    expect(market.claimedWinningShares.toString()).to.eq(market.resolvedTotalWinningShares.toString());
+  });
+});
@@ -0,0 +1,177 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { PublicKey, SystemProgram } from "@solana/web3.js";
+import {
+  ASSOCIATED_TOKEN_PROGRAM_ID,
+  TOKEN_PROGRAM_ID,
+  createMint,
+  getAccount,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+} from "@solana/spl-token";
+import { expect } from "chai";
+
+import { PredictionProgramV2 } from "../target/types/prediction_program_v2";
+import {
+  REGISTRY_PAGE_SIZE,
+  airdrop,
+  buyAccounts,
+  createTestMarket,
+  ensureConfig,
+  pda,
+  registryPagePda,
+  uniqueId,
+} from "./utils";
+
+describe("prediction_program_v2 (payout-vector resolution)", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace
+    .PredictionProgramV2 as Program<PredictionProgramV2>;
+
+  const payer = (provider.wallet as any).payer as anchor.web3.Keypair;
+  const wallet = provider.wallet as anchor.Wallet;
+
+  const user = anchor.web3.Keypair.generate();
+  const marketId = uniqueId();
+
+  let collateralMint: PublicKey;
+  let authorityAta: PublicKey;
+  let userAta: PublicKey;
+  let config: PublicKey;
+  let marketPda: PublicKey;
+  let vaultPda: PublicKey;
+  let vaultAuthPda: PublicKey;
+  let positionPda: PublicKey;
+
+  it("setup: market + a YES/NO position", async () => {
+    config = await ensureConfig(program, provider);
+
+    await airdrop(provider, user.publicKey);
+
+    collateralMint = await createMint(provider.connection, payer, wallet.publicKey, null, 6);
+    authorityAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, wallet.publicKey)
+    ).address;
+    userAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, user.publicKey)
+    ).address;
+    await mintTo(provider.connection, payer, collateralMint, authorityAta, payer, 10_000_000_000);
+    await mintTo(provider.connection, payer, collateralMint, userAta, payer, 1_000_000_000);
+
+    marketPda = pda(program, [
+      Buffer.from("market_v2"),
+      wallet.publicKey.toBuffer(),
+      marketId.toArrayLike(Buffer, "le", 8),
+    ]);
+    vaultPda = pda(program, [Buffer.from("vault_v2"), marketPda.toBuffer()]);
+    vaultAuthPda = pda(program, [Buffer.from("vault_auth_v2"), marketPda.toBuffer()]);
+    positionPda = pda(program, [Buffer.from("position_v2"), marketPda.toBuffer(), user.publicKey.toBuffer()]);
+
+    await createTestMarket(program, provider, {
+      marketId,
+      question: "Will the bill pass both chambers?",
+      metadataUri: "ar://bill-both-chambers",
+      collateralMint,
+      authorityCollateralAta: authorityAta,
+    });
+
+    await program.methods
+      .buyShares(0, new anchor.BN(100_000_000), new anchor.BN(1))
+      .accounts(buyAccounts({
+        config,
+        market: marketPda,
+        vault: vaultPda,
+        vaultAuthority: vaultAuthPda,
+        position: positionPda,
+        user: user.publicKey,
+        payer: user.publicKey,
+        collateralMint,
+        userCollateralAta: userAta,
+      }))
+      .signers([user])
+      .rpc({ commitment: "confirmed" });
+  });
+
+  it("resolve_market_payouts rejects vectors that don't sum to 100%", async () => {
+    const market = await program.account.marketV3.fetch(marketPda);
+    let failed = false;
+    try {
+      await program.methods
+        .resolveMarketPayouts([new anchor.BN(700_000), new anchor.BN(200_000)])
+        .accounts({
+          config,
+          market: marketPda,
+          vault: vaultPda,
+          registryPage: registryPagePda(
+            program,
+            Math.floor(market.registryIndex.toNumber() / REGISTRY_PAGE_SIZE)
+          ),
+          lstStakePool: null,
+          parentMarket: null,
+          authority: wallet.publicKey,
+        })
+        .rpc({ commitment: "confirmed" });
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("InvalidPayoutVector");
+    }
+    expect(failed).to.eq(true);
+  });
+
+  it("a 70/30 resolution pays YES holders 70% of a full win", async () => {
+    const market = await program.account.marketV3.fetch(marketPda);
+    await program.methods
+      .resolveMarketPayouts([new anchor.BN(700_000), new anchor.BN(300_000)])
+      .accounts({
+        config,
+        market: marketPda,
+        vault: vaultPda,
+        registryPage: registryPagePda(
+          program,
+          Math.floor(market.registryIndex.toNumber() / REGISTRY_PAGE_SIZE)
+        ),
+        lstStakePool: null,
+        parentMarket: null,
+        authority: wallet.publicKey,
+      })
+      .rpc({ commitment: "confirmed" });
+
+    const resolved = await program.account.marketV3.fetch(marketPda);
+    expect(resolved.winningOutcome).to.eq(2); // OUTCOME_SPLIT
+    expect(resolved.payoutFraction.toNumber()).to.eq(700_000);
+
+    const position = await program.account.positionV2.fetch(positionPda);
+    const before = Number((await getAccount(provider.connection, userAta)).amount);
+    await program.methods
+      .claimWinningsV2()
+      .accounts({
+        market: marketPda,
+        vault: vaultPda,
+        vaultAuthority: vaultAuthPda,
+        position: positionPda,
+        user: user.publicKey,
+        payer: user.publicKey,
+        collateralMint,
+        userCollateralAta: userAta,
+        wsolUnwrap: null,
+        creatorBond: null,
+        claimEscrow: null,
+        userStats: null,
+        protocolStats: null,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([user])
+      .rpc({ commitment: "confirmed" });
+    const after = Number((await getAccount(provider.connection, userAta)).amount);
+
+    const weighted = position.yesShares.muln(700_000).divn(1_000_000);
+    const expected = resolved.resolvedPayable
+      .mul(weighted)
+      .div(resolved.resolvedTotalWinningShares);
+    expect(after - before).to.eq(expected.toNumber());
+  });
+});
@@ -0,0 +1,131 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { PublicKey, SystemProgram } from "@solana/web3.js";
+import {
+  createMint,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+} from "@solana/spl-token";
+import { expect } from "chai";
+
+import { PredictionProgramV2 } from "../target/types/prediction_program_v2";
+import {
+  airdrop,
+  buyAccounts,
+  createTestMarket,
+  ensureConfig,
+  pda,
+  sellAccounts,
+  uniqueId,
+} from "./utils";
+
+describe("prediction_program_v2 (realized PnL accounting)", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace
+    .PredictionProgramV2 as Program<PredictionProgramV2>;
+
+  const payer = (provider.wallet as any).payer as anchor.web3.Keypair;
+  const wallet = provider.wallet as anchor.Wallet;
+
+  const user = anchor.web3.Keypair.generate();
+  const marketId = uniqueId();
+
+  let collateralMint: PublicKey;
+  let userAta: PublicKey;
+  let config: PublicKey;
+  let marketPda: PublicKey;
+  let vaultPda: PublicKey;
+  let vaultAuthPda: PublicKey;
+  let positionPda: PublicKey;
+  let userStats: PublicKey;
+
+  it("setup: market + funded trader + stats account", async () => {
+    config = await ensureConfig(program, provider);
+
+    await airdrop(provider, user.publicKey);
+
+    collateralMint = await createMint(provider.connection, payer, wallet.publicKey, null, 6);
+    const authorityAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, wallet.publicKey)
+    ).address;
+    userAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, user.publicKey)
+    ).address;
+    await mintTo(provider.connection, payer, collateralMint, authorityAta, payer, 10_000_000_000);
+    await mintTo(provider.connection, payer, collateralMint, userAta, payer, 1_000_000_000);
+
+    marketPda = pda(program, [
+      Buffer.from("market_v2"),
+      wallet.publicKey.toBuffer(),
+      marketId.toArrayLike(Buffer, "le", 8),
+    ]);
+    vaultPda = pda(program, [Buffer.from("vault_v2"), marketPda.toBuffer()]);
+    vaultAuthPda = pda(program, [Buffer.from("vault_auth_v2"), marketPda.toBuffer()]);
+    positionPda = pda(program, [Buffer.from("position_v2"), marketPda.toBuffer(), user.publicKey.toBuffer()]);
+    userStats = pda(program, [Buffer.from("user_stats"), user.publicKey.toBuffer()]);
+
+    await createTestMarket(program, provider, {
+      marketId,
+      question: "Will a rollup settle to two L1s by 2027?",
+      metadataUri: "ar://dual-settlement-rollup",
+      collateralMint,
+      authorityCollateralAta: authorityAta,
+    });
+
+    await program.methods
+      .initUserStats()
+      .accounts({ userStats, user: user.publicKey, payer: user.publicKey, systemProgram: SystemProgram.programId })
+      .signers([user])
+      .rpc({ commitment: "confirmed" });
+  });
+
+  const tradeAccounts = () => ({
+    config,
+    market: marketPda,
+    vault: vaultPda,
+    vaultAuthority: vaultAuthPda,
+    position: positionPda,
+    user: user.publicKey,
+    payer: user.publicKey,
+    collateralMint,
+    userCollateralAta: userAta,
+    userStats,
+  });
+
+  it("buy_shares opens a cost-basis lot for the market", async () => {
+    await program.methods
+      .buyShares(0, new anchor.BN(100_000_000), new anchor.BN(1))
+      .accounts(buyAccounts(tradeAccounts()))
+      .signers([user])
+      .rpc({ commitment: "confirmed" });
+
+    const stats = await program.account.userStats.fetch(userStats);
+    const position = await program.account.positionV2.fetch(positionPda);
+    expect(stats.volume.toNumber()).to.eq(100_000_000);
+    expect(stats.lots.length).to.eq(1);
+    expect(stats.lots[0].market.toBase58()).to.eq(marketPda.toBase58());
+    expect(stats.lots[0].yesShares.toString()).to.eq(position.yesShares.toString());
+    expect(stats.lots[0].yesCost.toNumber()).to.eq(100_000_000);
+    expect(stats.realizedPnl.toNumber()).to.eq(0);
+  });
+
+  it("sell_shares realizes PnL against the lot and closes it", async () => {
+    const position = await program.account.positionV2.fetch(positionPda);
+    const before = await program.account.userStats.fetch(userStats);
+
+    await program.methods
+      .sellShares(0, position.yesShares, new anchor.BN(1))
+      .accounts(sellAccounts(tradeAccounts()))
+      .signers([user])
+      .rpc({ commitment: "confirmed" });
+
+    const stats = await program.account.userStats.fetch(userStats);
+    const proceeds = stats.volume.toNumber() - before.volume.toNumber();
+    expect(stats.lots.length).to.eq(0);
+    // A round trip pays the fee twice: proceeds fall short of the cost
+    expect(stats.realizedPnl.toNumber()).to.eq(proceeds - 100_000_000);
+    expect(stats.realizedPnl.toNumber()).to.be.lessThan(0);
+  });
+});
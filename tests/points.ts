@@ -0,0 +1,118 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { PublicKey, SystemProgram } from "@solana/web3.js";
+import {
+  createMint,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+} from "@solana/spl-token";
+import { expect } from "chai";
+
+import { PredictionProgramV2 } from "../target/types/prediction_program_v2";
+import {
+  airdrop,
+  buyAccounts,
+  createTestMarket,
+  ensureConfig,
+  pda,
+  uniqueId,
+} from "./utils";
+
+describe("prediction_program_v2 (trading points)", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace
+    .PredictionProgramV2 as Program<PredictionProgramV2>;
+
+  const payer = (provider.wallet as any).payer as anchor.web3.Keypair;
+  const wallet = provider.wallet as anchor.Wallet;
+
+  const user = anchor.web3.Keypair.generate();
+  const marketId = uniqueId();
+
+  let collateralMint: PublicKey;
+  let userAta: PublicKey;
+  let config: PublicKey;
+  let marketPda: PublicKey;
+  let vaultPda: PublicKey;
+  let vaultAuthPda: PublicKey;
+  let positionPda: PublicKey;
+  let userStats: PublicKey;
+
+  it("setup: market + funded trader + stats account", async () => {
+    config = await ensureConfig(program, provider);
+
+    await airdrop(provider, user.publicKey);
+
+    collateralMint = await createMint(provider.connection, payer, wallet.publicKey, null, 6);
+    const authorityAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, wallet.publicKey)
+    ).address;
+    userAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, user.publicKey)
+    ).address;
+    await mintTo(provider.connection, payer, collateralMint, authorityAta, payer, 10_000_000_000);
+    await mintTo(provider.connection, payer, collateralMint, userAta, payer, 1_000_000_000);
+
+    marketPda = pda(program, [
+      Buffer.from("market_v2"),
+      wallet.publicKey.toBuffer(),
+      marketId.toArrayLike(Buffer, "le", 8),
+    ]);
+    vaultPda = pda(program, [Buffer.from("vault_v2"), marketPda.toBuffer()]);
+    vaultAuthPda = pda(program, [Buffer.from("vault_auth_v2"), marketPda.toBuffer()]);
+    positionPda = pda(program, [Buffer.from("position_v2"), marketPda.toBuffer(), user.publicKey.toBuffer()]);
+    userStats = pda(program, [Buffer.from("user_stats"), user.publicKey.toBuffer()]);
+
+    await createTestMarket(program, provider, {
+      marketId,
+      question: "Will the DEX flip the CEX in spot volume?",
+      metadataUri: "ar://dex-cex-flip",
+      collateralMint,
+      authorityCollateralAta: authorityAta,
+    });
+
+    await program.methods
+      .initUserStats()
+      .accounts({ userStats, user: user.publicKey, payer: user.publicKey, systemProgram: SystemProgram.programId })
+      .signers([user])
+      .rpc({ commitment: "confirmed" });
+  });
+
+  it("buy_shares with the stats account earns fee-weighted points", async () => {
+    await program.methods
+      .buyShares(0, new anchor.BN(100_000_000), new anchor.BN(1))
+      .accounts(buyAccounts({
+        config,
+        market: marketPda,
+        vault: vaultPda,
+        vaultAuthority: vaultAuthPda,
+        position: positionPda,
+        user: user.publicKey,
+        payer: user.publicKey,
+        collateralMint,
+        userCollateralAta: userAta,
+        userStats,
+      }))
+      .signers([user])
+      .rpc({ commitment: "confirmed" });
+
+    const stats = await program.account.userStats.fetch(userStats);
+    // One point for the trade + the 0.50% fee paid
+    expect(stats.points.toNumber()).to.eq(500_001);
+    expect(stats.epochPoints.toNumber()).to.eq(500_001);
+    expect(stats.epoch.toNumber()).to.eq(Math.floor(Date.now() / 1000 / (7 * 24 * 60 * 60)));
+  });
+
+  it("checkpoint_points leaves an open epoch alone", async () => {
+    await program.methods
+      .checkpointPoints()
+      .accounts({ userStats })
+      .rpc({ commitment: "confirmed" });
+
+    const stats = await program.account.userStats.fetch(userStats);
+    expect(stats.checkpoints.length).to.eq(0);
+    expect(stats.epochPoints.toNumber()).to.eq(500_001);
+  });
+});
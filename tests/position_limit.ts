@@ -0,0 +1,138 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { PublicKey } from "@solana/web3.js";
+import {
+  createMint,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+} from "@solana/spl-token";
+import { expect } from "chai";
+
+import { PredictionProgramV2 } from "../target/types/prediction_program_v2";
+import {
+  airdrop,
+  buyAccounts,
+  createTestMarket,
+  ensureConfig,
+  pda,
+  uniqueId,
+} from "./utils";
+
+describe("prediction_program_v2 (per-position size cap)", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace
+    .PredictionProgramV2 as Program<PredictionProgramV2>;
+
+  const payer = (provider.wallet as any).payer as anchor.web3.Keypair;
+  const wallet = provider.wallet as anchor.Wallet;
+
+  const user = anchor.web3.Keypair.generate();
+  const marketId = uniqueId();
+
+  let collateralMint: PublicKey;
+  let userAta: PublicKey;
+  let config: PublicKey;
+  let marketPda: PublicKey;
+  let vaultPda: PublicKey;
+  let vaultAuthPda: PublicKey;
+  let positionPda: PublicKey;
+
+  async function buy(amount: number) {
+    await program.methods
+      .buyShares(0, new anchor.BN(amount), new anchor.BN(1))
+      .accounts(buyAccounts({
+        config,
+        market: marketPda,
+        vault: vaultPda,
+        vaultAuthority: vaultAuthPda,
+        position: positionPda,
+        user: user.publicKey,
+        payer: user.publicKey,
+        collateralMint,
+        userCollateralAta: userAta,
+      }))
+      .signers([user])
+      .rpc({ commitment: "confirmed" });
+  }
+
+  it("setup: market + funded trader", async () => {
+    config = await ensureConfig(program, provider);
+
+    await airdrop(provider, user.publicKey);
+
+    collateralMint = await createMint(provider.connection, payer, wallet.publicKey, null, 6);
+    const authorityAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, wallet.publicKey)
+    ).address;
+    userAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, user.publicKey)
+    ).address;
+    await mintTo(provider.connection, payer, collateralMint, authorityAta, payer, 10_000_000_000);
+    await mintTo(provider.connection, payer, collateralMint, userAta, payer, 1_000_000_000);
+
+    marketPda = pda(program, [
+      Buffer.from("market_v2"),
+      wallet.publicKey.toBuffer(),
+      marketId.toArrayLike(Buffer, "le", 8),
+    ]);
+    vaultPda = pda(program, [Buffer.from("vault_v2"), marketPda.toBuffer()]);
+    vaultAuthPda = pda(program, [Buffer.from("vault_auth_v2"), marketPda.toBuffer()]);
+    positionPda = pda(program, [Buffer.from("position_v2"), marketPda.toBuffer(), user.publicKey.toBuffer()]);
+
+    await createTestMarket(program, provider, {
+      marketId,
+      question: "Will the promo market resolve YES?",
+      metadataUri: "ar://capped-promo",
+      collateralMint,
+      authorityCollateralAta: authorityAta,
+    });
+  });
+
+  it("set_max_position is restricted to the market authority or admin", async () => {
+    let failed = false;
+    try {
+      await program.methods
+        .setMaxPosition(new anchor.BN(1))
+        .accounts({ config, market: marketPda, authority: user.publicKey })
+        .signers([user])
+        .rpc({ commitment: "confirmed" });
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("Unauthorized");
+    }
+    expect(failed).to.eq(true);
+  });
+
+  it("buys past the cap are rejected", async () => {
+    await program.methods
+      .setMaxPosition(new anchor.BN(100_000_000))
+      .accounts({ config, market: marketPda, authority: wallet.publicKey })
+      .rpc({ commitment: "confirmed" });
+
+    await buy(50_000_000);
+    const position = await program.account.positionV2.fetch(positionPda);
+    expect(position.yesShares.toNumber()).to.be.lessThan(100_000_000);
+
+    let failed = false;
+    try {
+      await buy(100_000_000);
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("PositionLimitExceeded");
+    }
+    expect(failed).to.eq(true);
+  });
+
+  it("removing the cap allows the buy", async () => {
+    await program.methods
+      .setMaxPosition(new anchor.BN(0))
+      .accounts({ config, market: marketPda, authority: wallet.publicKey })
+      .rpc({ commitment: "confirmed" });
+
+    await buy(100_000_000);
+    const position = await program.account.positionV2.fetch(positionPda);
+    expect(position.yesShares.toNumber()).to.be.greaterThan(100_000_000);
+  });
+});
@@ -0,0 +1,283 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { PublicKey, SystemProgram } from "@solana/web3.js";
+import {
+  TOKEN_PROGRAM_ID,
+  createMint,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+} from "@solana/spl-token";
+import { expect } from "chai";
+
+import { PredictionProgramV2 } from "../target/types/prediction_program_v2";
+import {
+  airdrop,
+  buyAccounts,
+  createTestMarket,
+  ensureConfig,
+  lpPositionPda,
+  pda,
+  uniqueId,
+} from "./utils";
+
+describe("prediction_program_v2 (pre-market liquidity phase)", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace
+    .PredictionProgramV2 as Program<PredictionProgramV2>;
+
+  const payer = (provider.wallet as any).payer as anchor.web3.Keypair;
+  const wallet = provider.wallet as anchor.Wallet;
+
+  const lp = anchor.web3.Keypair.generate();
+  const marketId = uniqueId();
+  const initialLiquidity = new anchor.BN(1_000_000_000);
+
+  let collateralMint: PublicKey;
+  let authorityAta: PublicKey;
+  let lpAta: PublicKey;
+  let config: PublicKey;
+  let marketPda: PublicKey;
+  let vaultPda: PublicKey;
+  let vaultAuthPda: PublicKey;
+  let positionPda: PublicKey;
+
+  it("setup: mint + ATAs + PDAs", async () => {
+    config = await ensureConfig(program, provider);
+
+    await airdrop(provider, lp.publicKey);
+
+    collateralMint = await createMint(
+      provider.connection,
+      payer,
+      wallet.publicKey,
+      null,
+      6
+    );
+    authorityAta = (
+      await getOrCreateAssociatedTokenAccount(
+        provider.connection,
+        payer,
+        collateralMint,
+        wallet.publicKey
+      )
+    ).address;
+    lpAta = (
+      await getOrCreateAssociatedTokenAccount(
+        provider.connection,
+        payer,
+        collateralMint,
+        lp.publicKey
+      )
+    ).address;
+    await mintTo(provider.connection, payer, collateralMint, authorityAta, payer, 10_000_000_000);
+    await mintTo(provider.connection, payer, collateralMint, lpAta, payer, 1_000_000_000);
+
+    marketPda = pda(program, [
+      Buffer.from("market_v2"),
+      wallet.publicKey.toBuffer(),
+      marketId.toArrayLike(Buffer, "le", 8),
+    ]);
+    vaultPda = pda(program, [Buffer.from("vault_v2"), marketPda.toBuffer()]);
+    vaultAuthPda = pda(program, [Buffer.from("vault_auth_v2"), marketPda.toBuffer()]);
+    positionPda = pda(program, [Buffer.from("position_v2"), marketPda.toBuffer(), lp.publicKey.toBuffer()]);
+  });
+
+  it("create_market_cpmm with a future start_time starts in PreMarket", async () => {
+    const now = Math.floor(Date.now() / 1000);
+    await createTestMarket(program, provider, {
+      marketId,
+      question: "Will the final go to extra time?",
+      metadataUri: "ar://final-extra-time",
+      collateralMint,
+      authorityCollateralAta: authorityAta,
+      startTime: new anchor.BN(now + 3600),
+      endTime: new anchor.BN(now + 7200),
+      initialYesReserve: initialLiquidity,
+      initialNoReserve: initialLiquidity,
+    });
+
+    const market = await program.account.marketV3.fetch(marketPda);
+    expect(market.status).to.eq(4); // PreMarket
+    expect(market.totalLpShares.toNumber()).to.eq(initialLiquidity.toNumber());
+  });
+
+  it("add_liquidity + split_shares are allowed before start_time", async () => {
+    const lpPosition = lpPositionPda(program, marketPda, lp.publicKey);
+
+    await program.methods
+      .addLiquidity(new anchor.BN(500_000_000), new anchor.BN(1))
+      .accounts({
+        market: marketPda,
+        vault: vaultPda,
+        lpPosition,
+        lpRewards: null,
+        position: positionPda,
+        user: lp.publicKey,
+        payer: lp.publicKey,
+        collateralMint,
+        userCollateralAta: lpAta,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([lp])
+      .rpc({ commitment: "confirmed" });
+
+    await program.methods
+      .splitShares(new anchor.BN(100_000_000))
+      .accounts({
+        market: marketPda,
+        vault: vaultPda,
+        position: positionPda,
+        user: lp.publicKey,
+        payer: lp.publicKey,
+        collateralMint,
+        userCollateralAta: lpAta,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([lp])
+      .rpc({ commitment: "confirmed" });
+
+    const market = await program.account.marketV3.fetch(marketPda);
+    // 50/50 pool takes the whole deposit
+    expect(market.yesPool.toNumber()).to.eq(1_500_000_000);
+    expect(market.noPool.toNumber()).to.eq(1_500_000_000);
+
+    const lpPos = await program.account.lpPosition.fetch(lpPosition);
+    expect(lpPos.lpShares.toNumber()).to.eq(500_000_000);
+
+    const position = await program.account.positionV2.fetch(positionPda);
+    // Complete sets are minted 1:1, without the trading fee
+    expect(position.yesShares.toNumber()).to.eq(100_000_000);
+    expect(position.noShares.toNumber()).to.eq(100_000_000);
+    expect(market.lpFees.toNumber()).to.eq(0);
+  });
+
+  it("remove_liquidity: LPs can withdraw, the creator's seed is locked", async () => {
+    const lpPosition = lpPositionPda(program, marketPda, lp.publicKey);
+
+    await program.methods
+      .removeLiquidity(new anchor.BN(250_000_000), new anchor.BN(0), new anchor.BN(0))
+      .accounts({
+        market: marketPda,
+        lpPosition,
+        lpRewards: null,
+        position: positionPda,
+        user: lp.publicKey,
+        payer: lp.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([lp])
+      .rpc({ commitment: "confirmed" });
+
+    const lpPos = await program.account.lpPosition.fetch(lpPosition);
+    expect(lpPos.lpShares.toNumber()).to.eq(250_000_000);
+
+    const creatorLp = lpPositionPda(program, marketPda, wallet.publicKey);
+    expect((await program.account.lpPosition.fetch(creatorLp)).locked).to.eq(true);
+
+    const creatorPosition = pda(program, [Buffer.from("position_v2"), marketPda.toBuffer(), wallet.publicKey.toBuffer()]);
+    let failed = false;
+    try {
+      await program.methods
+        .removeLiquidity(new anchor.BN(1), new anchor.BN(0), new anchor.BN(0))
+        .accounts({
+          market: marketPda,
+          lpPosition: creatorLp,
+          lpRewards: null,
+          position: creatorPosition,
+          user: wallet.publicKey,
+          payer: wallet.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc({ commitment: "confirmed" });
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("LiquidityLocked");
+    }
+    expect(failed).to.eq(true);
+  });
+
+  it("net_position merges the hedged YES/NO back into collateral", async () => {
+    const before = await program.account.positionV2.fetch(positionPda);
+    const hedged = Math.min(before.yesShares.toNumber(), before.noShares.toNumber());
+    expect(hedged).to.be.greaterThan(0);
+
+    await program.methods
+      .netPosition()
+      .accounts({
+        market: marketPda,
+        vault: vaultPda,
+        vaultAuthority: vaultAuthPda,
+        position: positionPda,
+        user: lp.publicKey,
+        collateralMint,
+        userCollateralAta: lpAta,
+        wsolUnwrap: null,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([lp])
+      .rpc({ commitment: "confirmed" });
+
+    const after = await program.account.positionV2.fetch(positionPda);
+    expect(after.yesShares.toNumber()).to.eq(before.yesShares.toNumber() - hedged);
+    expect(after.noShares.toNumber()).to.eq(before.noShares.toNumber() - hedged);
+    expect(Math.min(after.yesShares.toNumber(), after.noShares.toNumber())).to.eq(0);
+  });
+
+  it("buy_shares is rejected before start_time", async () => {
+    let failed = false;
+    try {
+      await program.methods
+        .buyShares(0, new anchor.BN(10_000_000), new anchor.BN(1))
+        .accounts(buyAccounts({
+          config,
+          market: marketPda,
+          vault: vaultPda,
+          vaultAuthority: vaultAuthPda,
+          position: positionPda,
+          user: lp.publicKey,
+          payer: lp.publicKey,
+          collateralMint,
+          userCollateralAta: lpAta,
+        }))
+        .signers([lp])
+        .rpc({ commitment: "confirmed" });
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("MarketNotStarted");
+    }
+    expect(failed).to.eq(true);
+  });
+
+  it("create_market_cpmm: asymmetric reserves open YES at 80%", async () => {
+    const oddsMarketId = uniqueId();
+    const oddsMarket = pda(program, [
+      Buffer.from("market_v2"),
+      wallet.publicKey.toBuffer(),
+      oddsMarketId.toArrayLike(Buffer, "le", 8),
+    ]);
+    const oddsVault = pda(program, [Buffer.from("vault_v2"), oddsMarket.toBuffer()]);
+    const oddsVaultAuth = pda(program, [Buffer.from("vault_auth_v2"), oddsMarket.toBuffer()]);
+
+    await createTestMarket(program, provider, {
+      marketId: oddsMarketId,
+      question: "Will the favourite win?",
+      metadataUri: "ar://favourite-wins",
+      collateralMint,
+      authorityCollateralAta: authorityAta,
+      initialYesReserve: new anchor.BN(200_000_000),
+      initialNoReserve: new anchor.BN(800_000_000),
+    });
+
+    const price = await program.methods
+      .getMarketPrice()
+      .accounts({ market: oddsMarket })
+      .view();
+    expect(Number(price.yesPrice)).to.eq(800_000);
+    expect(Number(price.noPrice)).to.eq(200_000);
+  });
+});
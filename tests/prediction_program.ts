@@ -3,14 +3,26 @@ import { Program } from "@coral-xyz/anchor";
 import { PublicKey, SystemProgram, LAMPORTS_PER_SOL } from "@solana/web3.js";
 import {
   TOKEN_PROGRAM_ID,
+  ASSOCIATED_TOKEN_PROGRAM_ID,
   createMint,
   getOrCreateAssociatedTokenAccount,
   mintTo,
   getAccount,
+  transfer,
 } from "@solana/spl-token";
 import { expect } from "chai";
+import { createHash } from "crypto";
 
 import { PredictionProgramV2 } from "../target/types/prediction_program_v2";
+import {
+  DEFAULT_MARKET_METADATA,
+  creatorBondPda,
+  ensureConfig,
+  lpPositionPda,
+  nextRegistryAccounts,
+  registryPagePda,
+  REGISTRY_PAGE_SIZE,
+} from "./utils";
 
 describe("prediction_program_v2 (CPMM + fees + pro-rata) e2e", () => {
   const provider = anchor.AnchorProvider.env();
@@ -41,14 +53,17 @@ describe("prediction_program_v2 (CPMM + fees + pro-rata) e2e", () => {
     return n;
   }
 
-  function proRataPayoutFloor(
-  vaultBalance: number,
+  // Mirrors `pro_rata_step`: payouts are steps in the floored running total
+  function proRataPayoutStep(
+  payable: number,
+  claimedBefore: number,
   userWinningShares: number,
   totalWinningShares: number
 ): number {
   if (totalWinningShares <= 0) throw new Error("totalWinningShares must be > 0");
   if (userWinningShares <= 0) return 0;
-  return Math.floor((vaultBalance * userWinningShares) / totalWinningShares);
+  const paid = (claimed: number) => Math.floor((payable * claimed) / totalWinningShares);
+  return paid(claimedBefore + userWinningShares) - paid(claimedBefore);
 }
 
   async function airdrop(pubkey: PublicKey, sol: number, retries = 3) {
@@ -128,6 +143,8 @@ describe("prediction_program_v2 (CPMM + fees + pro-rata) e2e", () => {
   let userBAta: PublicKey;
 
   // PDAs
+  let config: PublicKey;
+  let marketRegistryPage: PublicKey;
   let marketPda: PublicKey;
   let vaultPda: PublicKey;
   let vaultAuthPda: PublicKey;
@@ -135,6 +152,8 @@ describe("prediction_program_v2 (CPMM + fees + pro-rata) e2e", () => {
   let posBPda: PublicKey;
 
   it("setup: airdrops + mint + ATAs + mint balances", async () => {
+    config = await ensureConfig(program, provider);
+
     const walletBal = await provider.connection.getBalance(
       wallet.publicKey,
       "confirmed"
@@ -254,6 +273,7 @@ describe("prediction_program_v2 (CPMM + fees + pro-rata) e2e", () => {
 
   it("create_market_cpmm: creates market + PDA vault token account + deposits backing", async () => {
     const endTime = new anchor.BN(Math.floor(Date.now() / 1000) + 24 * 3600); // 24h
+    const question = "Will BTC be above 100k on Jan 1 2027?";
     const backing = initialLiquidity.mul(new anchor.BN(2));
 
     const authAcc = await getAccount(provider.connection, authorityAta);
@@ -266,32 +286,155 @@ describe("prediction_program_v2 (CPMM + fees + pro-rata) e2e", () => {
     await program.methods
       .createMarketCpmm({
         marketId,
-        question: "Will BTC be above 100k on Jan 1 2027?",
+        questionHash: Array.from(createHash("sha256").update(question).digest()),
+        metadataUri: "ipfs://bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi",
+        startTime: new anchor.BN(0),
         endTime,
-        initialLiquidity,
+        initialYesReserve: initialLiquidity,
+        initialNoReserve: initialLiquidity,
+        metadata: DEFAULT_MARKET_METADATA,
+        openingAuction: null,
       })
       .accounts({
+        config,
         market: marketPda,
         vault: vaultPda,
         vaultAuthority: vaultAuthPda,
         collateralMint,
         authority: wallet.publicKey,
         authorityCollateralAta: authorityAta,
+        ...(await nextRegistryAccounts(program)),
+        creatorBond: creatorBondPda(program, marketPda),
+        creatorLpPosition: lpPositionPda(program, marketPda, wallet.publicKey),
         tokenProgram: TOKEN_PROGRAM_ID,
         systemProgram: SystemProgram.programId,
         rent: anchor.web3.SYSVAR_RENT_PUBKEY,
       })
       .rpc({ commitment: "confirmed" });
 
-    const market = await program.account.marketV2.fetch(marketPda);
+    const market = await program.account.marketV3.fetch(marketPda);
     expect(market.authority.toBase58()).to.eq(wallet.publicKey.toBase58());
     expect(market.vault.toBase58()).to.eq(vaultPda.toBase58());
     expect(market.status).to.eq(0); // Open
+    expect(Buffer.from(market.questionHash)).to.deep.eq(
+      createHash("sha256").update(question).digest()
+    );
+
+    const cfg = await program.account.protocolConfig.fetch(config);
+    expect(market.marketSeq.toNumber()).to.eq(cfg.marketCount.toNumber());
+
+    expect(market.registered).to.eq(1);
+    marketRegistryPage = registryPagePda(
+      program,
+      Math.floor(market.registryIndex.toNumber() / REGISTRY_PAGE_SIZE)
+    );
+    const page = await program.account.registryPage.fetch(marketRegistryPage);
+    expect(page.entries.some((e) => e.market.equals(marketPda))).to.eq(true);
 
     const vaultAcc = await waitForTokenAccount(vaultPda);
     expect(safeNumber(vaultAcc.amount, "vault amount after create")).to.eq(
       backingAmt
     );
+    expect(safeNumber(market.vaultBalance, "vaultBalance")).to.eq(backingAmt);
+  });
+
+  it("get_market_price: fresh market quotes 50/50 in 1e6 fixed-point", async () => {
+    const price = await program.methods
+      .getMarketPrice()
+      .accounts({ market: marketPda })
+      .view();
+
+    expect(Number(price.yesPrice)).to.eq(500_000);
+    expect(Number(price.noPrice)).to.eq(500_000);
+    expect(price.collateralDecimals).to.eq(6);
+  });
+
+  it("quote_buy / quote_sell: simulated trades return fee, pools and price impact", async () => {
+    const buy = await program.methods
+      .quoteBuy(0, new anchor.BN(10_000_000))
+      .accounts({ market: marketPda })
+      .view();
+
+    expect(Number(buy.fee)).to.eq(50_000);
+    expect(Number(buy.priceBefore)).to.eq(500_000);
+    expect(Number(buy.priceAfter)).to.be.greaterThan(500_000);
+    expect(Number(buy.amountOut)).to.be.greaterThan(0);
+    expect(Number(buy.priceImpactBps)).to.be.greaterThan(0);
+
+    const sell = await program.methods
+      .quoteSell(0, buy.amountOut)
+      .accounts({ market: marketPda })
+      .view();
+    // Selling into the untouched pool: below the 50% marginal price
+    expect(Number(sell.priceAfter)).to.be.lessThan(500_000);
+    expect(Number(sell.amountOut)).to.be.lessThan(10_000_000);
+  });
+
+  it("update_metadata: authority can edit metadata before the first trade", async () => {
+    await program.methods
+      .updateMetadata("ipfs://bafy-updated", {
+        category: 3, // Crypto
+        tags: new anchor.BN(0b101),
+        imageUri: "https://example.com/market.png",
+        rulesUri: "ipfs://bafy-rules",
+      })
+      .accounts({ market: marketPda, authority: wallet.publicKey })
+      .rpc({ commitment: "confirmed" });
+
+    const market = await program.account.marketV3.fetch(marketPda);
+    expect(market.category).to.eq(3);
+    expect(Number(market.tags)).to.eq(5);
+    expect(
+      Buffer.from(market.rulesUri.slice(0, market.rulesUriLen)).toString()
+    ).to.eq("ipfs://bafy-rules");
+  });
+
+  it("set_market_verified: curator flags the market as verified", async () => {
+    await program.methods
+      .setMarketVerified(true)
+      .accounts({ config, market: marketPda, curator: wallet.publicKey })
+      .rpc({ commitment: "confirmed" });
+
+    const market = await program.account.marketV3.fetch(marketPda);
+    expect(market.verified).to.eq(1);
+  });
+
+  it("report_market + resolve_report: curator rejects a report, market keeps trading", async () => {
+    const [report] = PublicKey.findProgramAddressSync(
+      [Buffer.from("report"), marketPda.toBuffer(), userA.publicKey.toBuffer()],
+      program.programId
+    );
+
+    await program.methods
+      .reportMarket(1) // Ambiguous
+      .accounts({
+        config,
+        market: marketPda,
+        report,
+        reporter: userA.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([userA])
+      .rpc({ commitment: "confirmed" });
+
+    await program.methods
+      .resolveReport(0) // Reject
+      .accounts({
+        config,
+        market: marketPda,
+        report,
+        creatorBond: null,
+        registryPage: null,
+        reporter: userA.publicKey,
+        treasury: wallet.publicKey,
+        curator: wallet.publicKey,
+      })
+      .rpc({ commitment: "confirmed" });
+
+    expect(await provider.connection.getAccountInfo(report)).to.eq(null);
+    const market = await program.account.marketV3.fetch(marketPda);
+    expect(market.status).to.eq(0);
+    expect(market.paused).to.eq(0);
   });
 
   it("buy_shares: userA buys YES, userB buys YES (fee-aware vault deltas + sanity)", async () => {
@@ -309,12 +452,28 @@ describe("prediction_program_v2 (CPMM + fees + pro-rata) e2e", () => {
     await program.methods
       .buyShares(0, aIn, minSharesOut) // 0=YES
       .accounts({
+        config,
         market: marketPda,
         vault: vaultPda,
         vaultAuthority: vaultAuthPda,
         position: posAPda,
         user: userA.publicKey,
+        payer: userA.publicKey,
+        collateralMint,
         userCollateralAta: userAAta,
+        marketMakers: null,
+        userStats: null,
+        competitionEntry: null,
+        marketStats: null,
+        protocolStats: null,
+        priceCandles: null,
+        tradeTape: null,
+        priceFeed: null,
+        allowlistEntry: null,
+        permitGate: null,
+        instructions: null,
+        tokenGate: null,
+        gateTokenAccount: null,
         tokenProgram: TOKEN_PROGRAM_ID,
         systemProgram: SystemProgram.programId,
         rent: anchor.web3.SYSVAR_RENT_PUBKEY,
@@ -325,12 +484,28 @@ describe("prediction_program_v2 (CPMM + fees + pro-rata) e2e", () => {
     await program.methods
       .buyShares(0, bIn, minSharesOut) // 0=YES
       .accounts({
+        config,
         market: marketPda,
         vault: vaultPda,
         vaultAuthority: vaultAuthPda,
         position: posBPda,
         user: userB.publicKey,
+        payer: userB.publicKey,
+        collateralMint,
         userCollateralAta: userBAta,
+        marketMakers: null,
+        userStats: null,
+        competitionEntry: null,
+        marketStats: null,
+        protocolStats: null,
+        priceCandles: null,
+        tradeTape: null,
+        priceFeed: null,
+        allowlistEntry: null,
+        permitGate: null,
+        instructions: null,
+        tokenGate: null,
+        gateTokenAccount: null,
         tokenProgram: TOKEN_PROGRAM_ID,
         systemProgram: SystemProgram.programId,
         rent: anchor.web3.SYSVAR_RENT_PUBKEY,
@@ -378,13 +553,29 @@ describe("prediction_program_v2 (CPMM + fees + pro-rata) e2e", () => {
     await program.methods
       .sellShares(0, sellSharesIn, minOut)
       .accounts({
+        config,
         market: marketPda,
         vault: vaultPda,
         vaultAuthority: vaultAuthPda,
         position: posAPda,
         user: userA.publicKey,
+        payer: userA.publicKey,
+        collateralMint,
         userCollateralAta: userAAta,
+        marketMakers: null,
+        userStats: null,
+        competitionEntry: null,
+        marketStats: null,
+        protocolStats: null,
+        priceCandles: null,
+        tradeTape: null,
+        priceFeed: null,
+        allowlistEntry: null,
+        permitGate: null,
+        instructions: null,
+        wsolUnwrap: null,
         tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
       })
       .signers([userA])
       .rpc({ commitment: "confirmed" });
@@ -422,35 +613,121 @@ describe("prediction_program_v2 (CPMM + fees + pro-rata) e2e", () => {
     expect(userDelta).to.be.lessThanOrEqual(vaultDelta); // fee kept => vault pays >= user receives
   });
 
+  it("assert_solvency: a healthy market stays unpaused", async () => {
+    await program.methods
+      .assertSolvency(true)
+      .accounts({ market: marketPda, vault: vaultPda })
+      .rpc({ commitment: "confirmed" });
+
+    const market = await program.account.marketV3.fetch(marketPda);
+    expect(market.paused).to.eq(0);
+  });
+
+  it("skim_surplus: direct vault donations go to the treasury, not the snapshot", async () => {
+    const donation = 5_000_000;
+    await transfer(provider.connection, payer, authorityAta, vaultPda, wallet.publicKey, donation);
+
+    const cfg = await program.account.protocolConfig.fetch(config);
+    const treasuryAta = (
+      await getOrCreateAssociatedTokenAccount(
+        provider.connection,
+        payer,
+        collateralMint,
+        cfg.treasury,
+        true
+      )
+    ).address;
+    const before = safeNumber((await getAccount(provider.connection, treasuryAta)).amount, "treasury before");
+
+    await program.methods
+      .skimSurplus()
+      .accounts({
+        config,
+        market: marketPda,
+        vault: vaultPda,
+        vaultAuthority: vaultAuthPda,
+        recipient: cfg.treasury,
+        payer: wallet.publicKey,
+        collateralMint,
+        recipientCollateralAta: treasuryAta,
+        wsolUnwrap: null,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc({ commitment: "confirmed" });
+
+    const after = safeNumber((await getAccount(provider.connection, treasuryAta)).amount, "treasury after");
+    expect(after - before).to.eq(donation);
+
+    const market = await program.account.marketV3.fetch(marketPda);
+    const vaultAcc = await getAccount(provider.connection, vaultPda);
+    expect(safeNumber(vaultAcc.amount, "vault")).to.eq(
+      safeNumber(market.vaultBalance, "vaultBalance")
+    );
+  });
+
   it("resolve_market: authority resolves YES", async () => {
     await program.methods
       .resolveMarket(0)
       .accounts({
+        config,
         market: marketPda,
         vault: vaultPda, // NEW: required for snapshot
+        registryPage: marketRegistryPage,
+        lstStakePool: null,
+        parentMarket: null,
         authority: wallet.publicKey,
       })
       .rpc({ commitment: "confirmed" });
 
-    const market = await program.account.marketV2.fetch(marketPda);
+    const market = await program.account.marketV3.fetch(marketPda);
     expect(market.status).to.eq(1); // Resolved
     expect(market.winningOutcome).to.eq(0);
 
     // NEW: ensure snapshots got populated
     expect(Number(market.resolvedVaultBalance)).to.be.greaterThan(0);
     expect(Number(market.resolvedTotalWinningShares)).to.be.greaterThan(0);
+
+    // Fully backed: winners are paid 1:1
+    const rate = await program.methods
+      .getPayoutRate()
+      .accounts({ market: marketPda })
+      .view();
+    expect(Number(rate)).to.eq(1_000_000);
+    expect(Number(market.payoutRate)).to.eq(1_000_000);
+
+    // Default config has no dispute window: claims open immediately
+    expect(market.disputeDeadline.toNumber()).to.eq(market.resolvedAt.toNumber());
+
+    const page = await program.account.registryPage.fetch(marketRegistryPage);
+    const entry = page.entries.find((e) => e.market.equals(marketPda));
+    expect(entry!.status).to.eq(1);
   });
 
 
-  it("claim_winnings_v2: classic pro-rata payout (order-independent) + invariants", async () => {
-    const market = await program.account.marketV2.fetch(marketPda);
+  it("claim_winnings_v2: pro-rata payout off the running total + invariants", async () => {
+    const market = await program.account.marketV3.fetch(marketPda);
     expect(market.status).to.eq(1);
     expect(market.winningOutcome).to.eq(0);
 
     const snapshotVault = safeNumber(market.resolvedVaultBalance, "resolvedVaultBalance");
     const snapshotTotal = safeNumber(market.resolvedTotalWinningShares, "resolvedTotalWinningShares");
 
+    const snapshotPayable = safeNumber(market.resolvedPayable, "resolvedPayable");
+
     expect(snapshotVault).to.be.greaterThan(0);
+    // Fees are not backing; default config routes all of them to LPs
+    const lpFees = safeNumber(market.lpFees, "lpFees");
+    expect(lpFees).to.be.greaterThan(0);
+    expect(Number(market.creatorFees)).to.eq(0);
+    expect(Number(market.protocolFees)).to.eq(0);
+    expect(Number(market.insuranceFees)).to.eq(0);
+    const backing = snapshotVault - lpFees;
+    // Winners redeem at most 1:1; the remainder plus LP fees is LP residual
+    expect(snapshotPayable).to.eq(Math.min(backing, snapshotTotal));
+    expect(safeNumber(market.residualLiquidity, "residualLiquidity")).to.eq(
+      backing - snapshotPayable + lpFees
+    );
     expect(snapshotTotal).to.be.greaterThan(0);
 
     const userABefore = safeNumber((await getAccount(provider.connection, userAAta)).amount, "userABefore");
@@ -462,8 +739,9 @@ describe("prediction_program_v2 (CPMM + fees + pro-rata) e2e", () => {
     const aShares = safeNumber(posA.yesShares, "posA.yesShares");
     const bShares = safeNumber(posB.yesShares, "posB.yesShares");
 
-    const expectedA = proRataPayoutFloor(snapshotVault, aShares, snapshotTotal);
-    const expectedB = proRataPayoutFloor(snapshotVault, bShares, snapshotTotal);
+    const claimedBefore = safeNumber(market.claimedWinningShares, "claimedWinningShares");
+    const expectedA = proRataPayoutStep(snapshotPayable, claimedBefore, aShares, snapshotTotal);
+    const expectedB = proRataPayoutStep(snapshotPayable, claimedBefore + aShares, bShares, snapshotTotal);
 
     await program.methods
       .claimWinningsV2()
@@ -473,8 +751,17 @@ describe("prediction_program_v2 (CPMM + fees + pro-rata) e2e", () => {
         vaultAuthority: vaultAuthPda,
         position: posAPda,
         user: userA.publicKey,
+        payer: userA.publicKey,
+        collateralMint,
         userCollateralAta: userAAta,
+        wsolUnwrap: null,
+        creatorBond: null,
+        claimEscrow: null,
+        userStats: null,
+        protocolStats: null,
         tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
       })
       .signers([userA])
       .rpc({ commitment: "confirmed" });
@@ -487,8 +774,17 @@ describe("prediction_program_v2 (CPMM + fees + pro-rata) e2e", () => {
         vaultAuthority: vaultAuthPda,
         position: posBPda,
         user: userB.publicKey,
+        payer: userB.publicKey,
+        collateralMint,
         userCollateralAta: userBAta,
+        wsolUnwrap: null,
+        creatorBond: null,
+        claimEscrow: null,
+        userStats: null,
+        protocolStats: null,
         tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
       })
       .signers([userB])
       .rpc({ commitment: "confirmed" });
@@ -516,6 +812,56 @@ describe("prediction_program_v2 (CPMM + fees + pro-rata) e2e", () => {
     const vaultEnd = safeNumber((await getAccount(provider.connection, vaultPda)).amount, "vaultEnd");
     const vaultNowPaid = snapshotVault - vaultEnd;
     expect(vaultNowPaid).to.eq(actualA + actualB);
+
+    // Claimed shares are tracked against the snapshot total
+    const marketAfter = await program.account.marketV3.fetch(marketPda);
+    expect(
+      safeNumber(marketAfter.claimedWinningShares, "claimedWinningShares") - claimedBefore
+    ).to.eq(aShares + bShares);
+  });
+
+  it("withdraw_residual_liquidity: creator LP takes the vault remainder", async () => {
+    const market = await program.account.marketV3.fetch(marketPda);
+    const residual = safeNumber(market.residualLiquidity, "residualLiquidity");
+    const before = safeNumber((await getAccount(provider.connection, authorityAta)).amount, "authorityBefore");
+
+    await program.methods
+      .withdrawResidualLiquidity()
+      .accounts({
+        market: marketPda,
+        vault: vaultPda,
+        vaultAuthority: vaultAuthPda,
+        lpPosition: lpPositionPda(program, marketPda, wallet.publicKey),
+        lpRewards: null,
+        user: wallet.publicKey,
+        payer: wallet.publicKey,
+        collateralMint,
+        userCollateralAta: authorityAta,
+        wsolUnwrap: null,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc({ commitment: "confirmed" });
+
+    const after = safeNumber((await getAccount(provider.connection, authorityAta)).amount, "authorityAfter");
+    // Creator is the only LP
+    expect(after - before).to.eq(residual);
+  });
+
+  it("release_bond: creator gets the creation bond back after resolution", async () => {
+    const creatorBond = creatorBondPda(program, marketPda);
+    const bondLamports = await provider.connection.getBalance(creatorBond);
+    const before = await provider.connection.getBalance(wallet.publicKey);
+
+    await program.methods
+      .releaseBond()
+      .accounts({ market: marketPda, creatorBond, creator: wallet.publicKey })
+      .rpc({ commitment: "confirmed" });
+
+    expect(await provider.connection.getAccountInfo(creatorBond)).to.eq(null);
+    const after = await provider.connection.getBalance(wallet.publicKey);
+    // Provider wallet also pays the tx fee
+    expect(after).to.be.greaterThan(before + bondLamports - 10_000);
   });
 
 });
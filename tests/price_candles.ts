@@ -0,0 +1,143 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { PublicKey, SystemProgram } from "@solana/web3.js";
+import {
+  createMint,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+} from "@solana/spl-token";
+import { expect } from "chai";
+
+import { PredictionProgramV2 } from "../target/types/prediction_program_v2";
+import {
+  airdrop,
+  buyAccounts,
+  createTestMarket,
+  ensureConfig,
+  pda,
+  uniqueId,
+} from "./utils";
+
+describe("prediction_program_v2 (price candles)", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace
+    .PredictionProgramV2 as Program<PredictionProgramV2>;
+
+  const payer = (provider.wallet as any).payer as anchor.web3.Keypair;
+  const wallet = provider.wallet as anchor.Wallet;
+
+  const user = anchor.web3.Keypair.generate();
+  const marketId = uniqueId();
+
+  let collateralMint: PublicKey;
+  let userAta: PublicKey;
+  let config: PublicKey;
+  let marketPda: PublicKey;
+  let vaultPda: PublicKey;
+  let vaultAuthPda: PublicKey;
+  let positionPda: PublicKey;
+  let priceCandles: PublicKey;
+
+  it("setup: market + funded trader + hourly candles", async () => {
+    config = await ensureConfig(program, provider);
+
+    await airdrop(provider, user.publicKey);
+
+    collateralMint = await createMint(provider.connection, payer, wallet.publicKey, null, 6);
+    const authorityAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, wallet.publicKey)
+    ).address;
+    userAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, user.publicKey)
+    ).address;
+    await mintTo(provider.connection, payer, collateralMint, authorityAta, payer, 10_000_000_000);
+    await mintTo(provider.connection, payer, collateralMint, userAta, payer, 1_000_000_000);
+
+    marketPda = pda(program, [
+      Buffer.from("market_v2"),
+      wallet.publicKey.toBuffer(),
+      marketId.toArrayLike(Buffer, "le", 8),
+    ]);
+    vaultPda = pda(program, [Buffer.from("vault_v2"), marketPda.toBuffer()]);
+    vaultAuthPda = pda(program, [Buffer.from("vault_auth_v2"), marketPda.toBuffer()]);
+    positionPda = pda(program, [Buffer.from("position_v2"), marketPda.toBuffer(), user.publicKey.toBuffer()]);
+    priceCandles = pda(program, [Buffer.from("price_candles"), marketPda.toBuffer()]);
+
+    await createTestMarket(program, provider, {
+      marketId,
+      question: "Will a prediction market list on a major exchange?",
+      metadataUri: "ar://pm-exchange-listing",
+      collateralMint,
+      authorityCollateralAta: authorityAta,
+    });
+
+    let failed = false;
+    try {
+      await program.methods
+        .initPriceCandles(new anchor.BN(1))
+        .accounts({ market: marketPda, priceCandles, authority: wallet.publicKey, systemProgram: SystemProgram.programId })
+        .rpc({ commitment: "confirmed" });
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("InvalidCandleInterval");
+    }
+    expect(failed).to.eq(true);
+
+    await program.methods
+      .initPriceCandles(new anchor.BN(3600))
+      .accounts({ market: marketPda, priceCandles, authority: wallet.publicKey, systemProgram: SystemProgram.programId })
+      .rpc({ commitment: "confirmed" });
+  });
+
+  const tradeAccounts = () => ({
+    config,
+    market: marketPda,
+    vault: vaultPda,
+    vaultAuthority: vaultAuthPda,
+    position: positionPda,
+    user: user.publicKey,
+    payer: user.publicKey,
+    collateralMint,
+    userCollateralAta: userAta,
+    priceCandles,
+  });
+
+  it("trades fold the YES probability into the current candle", async () => {
+    await program.methods
+      .buyShares(0, new anchor.BN(100_000_000), new anchor.BN(1))
+      .accounts(buyAccounts(tradeAccounts()))
+      .signers([user])
+      .rpc({ commitment: "confirmed" });
+
+    await program.methods
+      .buyShares(1, new anchor.BN(300_000_000), new anchor.BN(1))
+      .accounts(buyAccounts(tradeAccounts()))
+      .signers([user])
+      .rpc({ commitment: "confirmed" });
+
+    const candles = await program.account.priceCandles.fetch(priceCandles);
+    expect(candles.count).to.eq(1);
+    const candle = candles.candles[candles.head];
+    expect(candle.start.toNumber() % 3600).to.eq(0);
+    // YES rallied on the first buy, then sold off below 50%
+    expect(candle.open.toNumber()).to.be.greaterThan(500_000);
+    expect(candle.high.toNumber()).to.eq(candle.open.toNumber());
+    expect(candle.low.toNumber()).to.eq(candle.close.toNumber());
+    expect(candle.close.toNumber()).to.be.lessThan(500_000);
+  });
+
+  it("snapshot_price records the current probability without a trade", async () => {
+    await program.methods
+      .snapshotPrice()
+      .accounts({ market: marketPda, priceCandles })
+      .rpc({ commitment: "confirmed" });
+
+    const market = await program.account.marketV3.fetch(marketPda);
+    const probability = market.noPool.muln(1_000_000).div(market.yesPool.add(market.noPool));
+
+    const candles = await program.account.priceCandles.fetch(priceCandles);
+    expect(candles.candles[candles.head].close.toString()).to.eq(probability.toString());
+  });
+});
@@ -0,0 +1,115 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { PublicKey, SystemProgram } from "@solana/web3.js";
+import {
+  createMint,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+} from "@solana/spl-token";
+import { expect } from "chai";
+
+import { PredictionProgramV2 } from "../target/types/prediction_program_v2";
+import {
+  airdrop,
+  buyAccounts,
+  createTestMarket,
+  ensureConfig,
+  pda,
+  protocolStatsPda,
+  uniqueId,
+} from "./utils";
+
+describe("prediction_program_v2 (protocol statistics)", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace
+    .PredictionProgramV2 as Program<PredictionProgramV2>;
+
+  const payer = (provider.wallet as any).payer as anchor.web3.Keypair;
+  const wallet = provider.wallet as anchor.Wallet;
+
+  const user = anchor.web3.Keypair.generate();
+  const marketId = uniqueId();
+
+  let collateralMint: PublicKey;
+  let userAta: PublicKey;
+  let config: PublicKey;
+  let marketPda: PublicKey;
+  let vaultPda: PublicKey;
+  let vaultAuthPda: PublicKey;
+  let positionPda: PublicKey;
+  let protocolStats: PublicKey;
+
+  it("setup: protocol stats + market + funded trader", async () => {
+    config = await ensureConfig(program, provider);
+
+    await airdrop(provider, user.publicKey);
+
+    collateralMint = await createMint(provider.connection, payer, wallet.publicKey, null, 6);
+    const authorityAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, wallet.publicKey)
+    ).address;
+    userAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, user.publicKey)
+    ).address;
+    await mintTo(provider.connection, payer, collateralMint, authorityAta, payer, 10_000_000_000);
+    await mintTo(provider.connection, payer, collateralMint, userAta, payer, 1_000_000_000);
+
+    marketPda = pda(program, [
+      Buffer.from("market_v2"),
+      wallet.publicKey.toBuffer(),
+      marketId.toArrayLike(Buffer, "le", 8),
+    ]);
+    vaultPda = pda(program, [Buffer.from("vault_v2"), marketPda.toBuffer()]);
+    vaultAuthPda = pda(program, [Buffer.from("vault_auth_v2"), marketPda.toBuffer()]);
+    positionPda = pda(program, [Buffer.from("position_v2"), marketPda.toBuffer(), user.publicKey.toBuffer()]);
+
+    protocolStats = protocolStatsPda(program);
+    if (!(await program.account.protocolStats.fetchNullable(protocolStats))) {
+      await program.methods
+        .initProtocolStats()
+        .accounts({ config, protocolStats, admin: wallet.publicKey, systemProgram: SystemProgram.programId })
+        .rpc({ commitment: "confirmed" });
+    }
+    const before = await program.account.protocolStats.fetch(protocolStats);
+
+    await createTestMarket(program, provider, {
+      marketId,
+      question: "Will an L2 process 10k TPS sustained?",
+      metadataUri: "ar://l2-10k-tps",
+      collateralMint,
+      authorityCollateralAta: authorityAta,
+    });
+
+    const after = await program.account.protocolStats.fetch(protocolStats);
+    expect(after.marketsCreated.toNumber()).to.eq(before.marketsCreated.toNumber() + 1);
+    expect(after.tvl.toNumber()).to.eq(before.tvl.toNumber() + 1_000_000_000);
+  });
+
+  it("buy_shares with the stats account adds volume, fees and TVL", async () => {
+    const before = await program.account.protocolStats.fetch(protocolStats);
+
+    await program.methods
+      .buyShares(1, new anchor.BN(100_000_000), new anchor.BN(1))
+      .accounts(buyAccounts({
+        config,
+        market: marketPda,
+        vault: vaultPda,
+        vaultAuthority: vaultAuthPda,
+        position: positionPda,
+        user: user.publicKey,
+        payer: user.publicKey,
+        collateralMint,
+        userCollateralAta: userAta,
+        protocolStats,
+      }))
+      .signers([user])
+      .rpc({ commitment: "confirmed" });
+
+    const after = await program.account.protocolStats.fetch(protocolStats);
+    expect(after.volume.toNumber() - before.volume.toNumber()).to.eq(100_000_000);
+    expect(after.fees.toNumber() - before.fees.toNumber()).to.eq(500_000);
+    expect(after.tvl.toNumber() - before.tvl.toNumber()).to.eq(100_000_000);
+  });
+});
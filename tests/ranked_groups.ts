@@ -0,0 +1,143 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { PublicKey, SystemProgram } from "@solana/web3.js";
+import {
+  TOKEN_PROGRAM_ID,
+  createMint,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+} from "@solana/spl-token";
+import { expect } from "chai";
+
+import { PredictionProgramV2 } from "../target/types/prediction_program_v2";
+import {
+  createTestMarket,
+  ensureConfig,
+  pda,
+  uniqueId,
+} from "./utils";
+
+const GROUP_PAYOUT_RANKED = 2;
+const PODIUM = [600_000, 300_000, 100_000];
+
+describe("prediction_program_v2 (ranked groups)", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace
+    .PredictionProgramV2 as Program<PredictionProgramV2>;
+
+  const payer = (provider.wallet as any).payer as anchor.web3.Keypair;
+  const wallet = provider.wallet as anchor.Wallet;
+
+  const groupId = uniqueId();
+  const labels = ["Alice", "Bob", "Carol", "Dave"];
+
+  let collateralMint: PublicKey;
+  let group: PublicKey;
+  const markets: PublicKey[] = [];
+
+  const vaultPda = (market: PublicKey) => pda(program, [Buffer.from("vault_v2"), market.toBuffer()]);
+
+  async function resolveRanking(ranking: number[]) {
+    await program.methods
+      .resolveGroupRanking(Buffer.from(ranking))
+      .accounts({
+        group,
+        collateralMint,
+        authority: wallet.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .remainingAccounts(
+        markets.flatMap((m) => [
+          { pubkey: m, isWritable: true, isSigner: false },
+          { pubkey: vaultPda(m), isWritable: false, isSigner: false },
+        ])
+      )
+      .rpc({ commitment: "confirmed" });
+  }
+
+  it("setup: ranked group", async () => {
+    await ensureConfig(program, provider);
+
+    collateralMint = await createMint(provider.connection, payer, wallet.publicKey, null, 6);
+    const authorityAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, wallet.publicKey)
+    ).address;
+    await mintTo(provider.connection, payer, collateralMint, authorityAta, payer, 10_000_000_000);
+
+    group = pda(program, [Buffer.from("market_group"), wallet.publicKey.toBuffer(), groupId.toArrayLike(Buffer, "le", 8)]);
+    await program.methods
+      .createMarketGroup(groupId, GROUP_PAYOUT_RANKED)
+      .accounts({
+        group,
+        collateralMint,
+        authority: wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc({ commitment: "confirmed" });
+
+    for (const label of labels) {
+      const marketId = uniqueId();
+      const market = pda(program, [
+        Buffer.from("market_v2"),
+        wallet.publicKey.toBuffer(),
+        marketId.toArrayLike(Buffer, "le", 8),
+      ]);
+      await createTestMarket(program, provider, {
+        marketId,
+        question: `Will ${label} make the podium?`,
+        metadataUri: `ar://ranked-${label.toLowerCase()}`,
+        collateralMint,
+        authorityCollateralAta: authorityAta,
+      });
+
+      await program.methods
+        .addGroupMember(label)
+        .accounts({ group, market, authority: wallet.publicKey })
+        .rpc({ commitment: "confirmed" });
+      markets.push(market);
+    }
+  });
+
+  it("podium weights are fixed once set", async () => {
+    await program.methods
+      .setRankWeights(PODIUM.map((w) => new anchor.BN(w)))
+      .accounts({ group, authority: wallet.publicKey })
+      .rpc({ commitment: "confirmed" });
+    const fetched = await program.account.marketGroup.fetch(group);
+    expect(fetched.rankWeights.map((w) => w.toNumber())).to.deep.eq(PODIUM);
+
+    let failed = false;
+    try {
+      await program.methods
+        .setRankWeights([new anchor.BN(1_000_000)])
+        .accounts({ group, authority: wallet.publicKey })
+        .rpc({ commitment: "confirmed" });
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("RankWeightsAlreadySet");
+    }
+    expect(failed).to.eq(true);
+  });
+
+  it("a ranking placing a member twice is rejected", async () => {
+    let failed = false;
+    try {
+      await resolveRanking([2, 2, 3]);
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("InvalidRanking");
+    }
+    expect(failed).to.eq(true);
+  });
+
+  it("placed members pay their place's weight, the rest resolve NO", async () => {
+    // Carol 1st, Alice 2nd, Dave 3rd; Bob unplaced
+    await resolveRanking([2, 0, 3]);
+
+    const resolved = await Promise.all(markets.map((m) => program.account.marketV3.fetch(m)));
+    expect(resolved.map((m) => m.winningOutcome)).to.deep.eq([2, 1, 2, 2]);
+    expect(resolved.map((m) => m.payoutFraction.toNumber())).to.deep.eq([300_000, 0, 600_000, 100_000]);
+  });
+});
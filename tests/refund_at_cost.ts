@@ -0,0 +1,200 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { PublicKey, SystemProgram } from "@solana/web3.js";
+import {
+  ASSOCIATED_TOKEN_PROGRAM_ID,
+  TOKEN_PROGRAM_ID,
+  createMint,
+  getAccount,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+} from "@solana/spl-token";
+import { expect } from "chai";
+
+import { PredictionProgramV2 } from "../target/types/prediction_program_v2";
+import {
+  REGISTRY_PAGE_SIZE,
+  airdrop,
+  buyAccounts,
+  createTestMarket,
+  creatorBondPda,
+  ensureConfig,
+  pda,
+  registryPagePda,
+  sellAccounts,
+  uniqueId,
+} from "./utils";
+
+describe("prediction_program_v2 (refunds at cost)", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace
+    .PredictionProgramV2 as Program<PredictionProgramV2>;
+
+  const payer = (provider.wallet as any).payer as anchor.web3.Keypair;
+  const wallet = provider.wallet as anchor.Wallet;
+
+  // Buys YES cheap, then sells part of it
+  const early = anchor.web3.Keypair.generate();
+  // Buys YES after the price has moved
+  const late = anchor.web3.Keypair.generate();
+  const marketId = uniqueId();
+
+  let collateralMint: PublicKey;
+  let config: PublicKey;
+  let marketPda: PublicKey;
+  let vaultPda: PublicKey;
+  let vaultAuthPda: PublicKey;
+  const atas = new Map<string, PublicKey>();
+
+  const positionPda = (user: anchor.web3.Keypair) =>
+    pda(program, [Buffer.from("position_v2"), marketPda.toBuffer(), user.publicKey.toBuffer()]);
+  const balance = async (user: anchor.web3.Keypair) =>
+    Number((await getAccount(provider.connection, atas.get(user.publicKey.toBase58())!)).amount);
+
+  const tradeAccounts = (user: anchor.web3.Keypair) => ({
+    config,
+    market: marketPda,
+    vault: vaultPda,
+    vaultAuthority: vaultAuthPda,
+    position: positionPda(user),
+    user: user.publicKey,
+    payer: user.publicKey,
+    collateralMint,
+    userCollateralAta: atas.get(user.publicKey.toBase58())!,
+  });
+
+  async function buy(user: anchor.web3.Keypair, amount: number) {
+    await program.methods
+      .buyShares(0, new anchor.BN(amount), new anchor.BN(1))
+      .accounts(buyAccounts(tradeAccounts(user)))
+      .signers([user])
+      .rpc({ commitment: "confirmed" });
+  }
+
+  async function claim(user: anchor.web3.Keypair) {
+    await program.methods
+      .claimWinningsV2()
+      .accounts({
+        market: marketPda,
+        vault: vaultPda,
+        vaultAuthority: vaultAuthPda,
+        position: positionPda(user),
+        user: user.publicKey,
+        payer: user.publicKey,
+        collateralMint,
+        userCollateralAta: atas.get(user.publicKey.toBase58())!,
+        wsolUnwrap: null,
+        creatorBond: null,
+        claimEscrow: null,
+        userStats: null,
+        protocolStats: null,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([user])
+      .rpc({ commitment: "confirmed" });
+  }
+
+  it("setup: market", async () => {
+    config = await ensureConfig(program, provider);
+
+    collateralMint = await createMint(provider.connection, payer, wallet.publicKey, null, 6);
+    const authorityAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, wallet.publicKey)
+    ).address;
+    await mintTo(provider.connection, payer, collateralMint, authorityAta, payer, 10_000_000_000);
+
+    for (const user of [early, late]) {
+      await airdrop(provider, user.publicKey);
+      const ata = (
+        await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, user.publicKey)
+      ).address;
+      await mintTo(provider.connection, payer, collateralMint, ata, payer, 1_000_000_000);
+      atas.set(user.publicKey.toBase58(), ata);
+    }
+
+    marketPda = pda(program, [
+      Buffer.from("market_v2"),
+      wallet.publicKey.toBuffer(),
+      marketId.toArrayLike(Buffer, "le", 8),
+    ]);
+    vaultPda = pda(program, [Buffer.from("vault_v2"), marketPda.toBuffer()]);
+    vaultAuthPda = pda(program, [Buffer.from("vault_auth_v2"), marketPda.toBuffer()]);
+
+    await createTestMarket(program, provider, {
+      marketId,
+      question: "Will the question survive review?",
+      metadataUri: "ar://refund-at-cost",
+      collateralMint,
+      authorityCollateralAta: authorityAta,
+    });
+  });
+
+  it("buys add to the cost basis and sell proceeds come off it", async () => {
+    await buy(early, 100_000_000);
+    await buy(late, 200_000_000);
+
+    const position = await program.account.positionV2.fetch(positionPda(early));
+    expect(position.costBasis.toNumber()).to.eq(100_000_000);
+
+    const before = await balance(early);
+    await program.methods
+      .sellShares(0, position.yesShares.divn(4), new anchor.BN(1))
+      .accounts(sellAccounts(tradeAccounts(early)))
+      .signers([early])
+      .rpc({ commitment: "confirmed" });
+    const proceeds = (await balance(early)) - before;
+
+    const earlyAfter = await program.account.positionV2.fetch(positionPda(early));
+    expect(earlyAfter.costBasis.toNumber()).to.eq(100_000_000 - proceeds);
+    const lateAfter = await program.account.positionV2.fetch(positionPda(late));
+    expect(lateAfter.costBasis.toNumber()).to.eq(200_000_000);
+
+    const market = await program.account.marketV3.fetch(marketPda);
+    expect(market.totalCostBasis.toNumber()).to.eq(300_000_000 - proceeds);
+  });
+
+  it("void_market refunds every position exactly what it put in", async () => {
+    const market = await program.account.marketV3.fetch(marketPda);
+    const { treasury } = await program.account.protocolConfig.fetch(config);
+    await program.methods
+      .voidMarket()
+      .accounts({
+        config,
+        market: marketPda,
+        creatorBond: creatorBondPda(program, marketPda),
+        registryPage: registryPagePda(
+          program,
+          Math.floor(market.registryIndex.toNumber() / REGISTRY_PAGE_SIZE)
+        ),
+        treasury,
+        admin: wallet.publicKey,
+      })
+      .rpc({ commitment: "confirmed" });
+
+    const voided = await program.account.marketV3.fetch(marketPda);
+    expect(voided.winningOutcome).to.eq(4); // OUTCOME_REFUND_AT_COST
+    expect(voided.resolvedPayable.toString()).to.eq(voided.totalCostBasis.toString());
+
+    for (const user of [early, late]) {
+      const position = await program.account.positionV2.fetch(positionPda(user));
+      const before = await balance(user);
+      await claim(user);
+      expect((await balance(user)) - before).to.eq(position.costBasis.toNumber());
+    }
+  });
+
+  it("a refunded position can't claim again", async () => {
+    let failed = false;
+    try {
+      await claim(late);
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("AlreadyClaimed");
+    }
+    expect(failed).to.eq(true);
+  });
+});
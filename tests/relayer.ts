@@ -0,0 +1,133 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { PublicKey } from "@solana/web3.js";
+import {
+  createMint,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+} from "@solana/spl-token";
+import { expect } from "chai";
+
+import { PredictionProgramV2 } from "../target/types/prediction_program_v2";
+import {
+  buyAccounts,
+  createTestMarket,
+  ensureConfig,
+  pda,
+  sellAccounts,
+  uniqueId,
+} from "./utils";
+
+describe("prediction_program_v2 (relayer-paid transactions)", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace
+    .PredictionProgramV2 as Program<PredictionProgramV2>;
+
+  const payer = (provider.wallet as any).payer as anchor.web3.Keypair;
+  const wallet = provider.wallet as anchor.Wallet;
+
+  // Never funded with SOL: the provider wallet pays fees and rent
+  const user = anchor.web3.Keypair.generate();
+  const marketId = uniqueId();
+
+  let collateralMint: PublicKey;
+  let userAta: PublicKey;
+  let config: PublicKey;
+  let marketPda: PublicKey;
+  let vaultPda: PublicKey;
+  let vaultAuthPda: PublicKey;
+  let positionPda: PublicKey;
+
+  const tradeAccounts = () => ({
+    config,
+    market: marketPda,
+    vault: vaultPda,
+    vaultAuthority: vaultAuthPda,
+    position: positionPda,
+    user: user.publicKey,
+    payer: wallet.publicKey,
+    collateralMint,
+    userCollateralAta: userAta,
+  });
+
+  it("setup: market + trader without SOL", async () => {
+    config = await ensureConfig(program, provider);
+
+    collateralMint = await createMint(provider.connection, payer, wallet.publicKey, null, 6);
+    const authorityAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, wallet.publicKey)
+    ).address;
+    userAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, user.publicKey)
+    ).address;
+    await mintTo(provider.connection, payer, collateralMint, authorityAta, payer, 10_000_000_000);
+    await mintTo(provider.connection, payer, collateralMint, userAta, payer, 1_000_000_000);
+
+    marketPda = pda(program, [
+      Buffer.from("market_v2"),
+      wallet.publicKey.toBuffer(),
+      marketId.toArrayLike(Buffer, "le", 8),
+    ]);
+    vaultPda = pda(program, [Buffer.from("vault_v2"), marketPda.toBuffer()]);
+    vaultAuthPda = pda(program, [Buffer.from("vault_auth_v2"), marketPda.toBuffer()]);
+    positionPda = pda(program, [Buffer.from("position_v2"), marketPda.toBuffer(), user.publicKey.toBuffer()]);
+
+    await createTestMarket(program, provider, {
+      marketId,
+      question: "Will the relayer keep paying?",
+      metadataUri: "ar://relayer",
+      collateralMint,
+      authorityCollateralAta: authorityAta,
+    });
+
+    expect(await provider.connection.getBalance(user.publicKey)).to.eq(0);
+  });
+
+  it("relayer pays the fee and the new position's rent on a buy", async () => {
+    const relayerBefore = await provider.connection.getBalance(wallet.publicKey);
+
+    await program.methods
+      .buyShares(0, new anchor.BN(50_000_000), new anchor.BN(1))
+      .accounts(buyAccounts(tradeAccounts()))
+      .signers([user])
+      .rpc({ commitment: "confirmed" });
+
+    const position = await program.account.positionV2.fetch(positionPda);
+    expect(position.owner.toBase58()).to.eq(user.publicKey.toBase58());
+    expect(position.yesShares.toNumber()).to.be.greaterThan(0);
+
+    const positionRent = await provider.connection.getBalance(positionPda);
+    const relayerAfter = await provider.connection.getBalance(wallet.publicKey);
+    expect(relayerBefore - relayerAfter).to.be.greaterThanOrEqual(positionRent);
+    expect(await provider.connection.getBalance(user.publicKey)).to.eq(0);
+  });
+
+  it("user still has to sign: the relayer can't trade on their behalf", async () => {
+    let failed = false;
+    try {
+      await program.methods
+        .sellShares(0, new anchor.BN(1_000_000), new anchor.BN(1))
+        .accounts(sellAccounts(tradeAccounts()))
+        .rpc({ commitment: "confirmed" });
+    } catch (e: any) {
+      failed = true;
+    }
+    expect(failed).to.eq(true);
+  });
+
+  it("sell goes through with the user signing and no SOL", async () => {
+    const position = await program.account.positionV2.fetch(positionPda);
+
+    await program.methods
+      .sellShares(0, position.yesShares, new anchor.BN(1))
+      .accounts(sellAccounts(tradeAccounts()))
+      .signers([user])
+      .rpc({ commitment: "confirmed" });
+
+    const after = await program.account.positionV2.fetch(positionPda);
+    expect(after.yesShares.toNumber()).to.eq(0);
+    expect(await provider.connection.getBalance(user.publicKey)).to.eq(0);
+  });
+});
@@ -0,0 +1,205 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import {
+  PublicKey,
+  SystemProgram,
+  Ed25519Program,
+  SYSVAR_INSTRUCTIONS_PUBKEY,
+} from "@solana/web3.js";
+import {
+  TOKEN_PROGRAM_ID,
+  createMint,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+} from "@solana/spl-token";
+import { expect } from "chai";
+
+import { PredictionProgramV2 } from "../target/types/prediction_program_v2";
+import {
+  airdrop,
+  createTestMarket,
+  ensureConfig,
+  pda,
+  uniqueId,
+} from "./utils";
+
+describe("prediction_program_v2 (RFQ signed quotes)", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace
+    .PredictionProgramV2 as Program<PredictionProgramV2>;
+
+  const payer = (provider.wallet as any).payer as anchor.web3.Keypair;
+  const wallet = provider.wallet as anchor.Wallet;
+
+  const maker = anchor.web3.Keypair.generate();
+  const taker = anchor.web3.Keypair.generate();
+  const marketId = uniqueId();
+  const initialLiquidity = new anchor.BN(1_000_000_000);
+
+  let collateralMint: PublicKey;
+  let authorityAta: PublicKey;
+  let marketPda: PublicKey;
+  let vaultPda: PublicKey;
+  let vaultAuthPda: PublicKey;
+  let custodyAuthority: PublicKey;
+  let custody: PublicKey;
+  const atas: Record<string, PublicKey> = {};
+
+  const positionPda = (owner: PublicKey) =>
+    pda(program, [Buffer.from("position_v2"), marketPda.toBuffer(), owner.toBuffer()]);
+  const tradingAccountPda = (owner: PublicKey) =>
+    pda(program, [Buffer.from("trading_account"), owner.toBuffer(), collateralMint.toBuffer()]);
+  const quoteFillPda = (nonce: anchor.BN) =>
+    pda(program, [Buffer.from("rfq_fill"), maker.publicKey.toBuffer(), nonce.toArrayLike(Buffer, "le", 8)]);
+
+  it("setup: market, funded trading accounts, maker inventory", async () => {
+    await ensureConfig(program, provider);
+
+    for (const kp of [maker, taker]) {
+      await airdrop(provider, kp.publicKey);
+    }
+
+    collateralMint = await createMint(provider.connection, payer, wallet.publicKey, null, 6);
+    authorityAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, wallet.publicKey)
+    ).address;
+    await mintTo(provider.connection, payer, collateralMint, authorityAta, payer, 10_000_000_000);
+    for (const kp of [maker, taker]) {
+      atas[kp.publicKey.toBase58()] = (
+        await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, kp.publicKey)
+      ).address;
+      await mintTo(provider.connection, payer, collateralMint, atas[kp.publicKey.toBase58()], payer, 1_000_000_000);
+    }
+
+    marketPda = pda(program, [Buffer.from("market_v2"), wallet.publicKey.toBuffer(), marketId.toArrayLike(Buffer, "le", 8)]);
+    vaultPda = pda(program, [Buffer.from("vault_v2"), marketPda.toBuffer()]);
+    vaultAuthPda = pda(program, [Buffer.from("vault_auth_v2"), marketPda.toBuffer()]);
+    custodyAuthority = pda(program, [Buffer.from("trading_custody_auth")]);
+    custody = pda(program, [Buffer.from("trading_custody"), collateralMint.toBuffer()]);
+
+    await createTestMarket(program, provider, {
+      marketId,
+      question: "Will the quote settle?",
+      metadataUri: "ar://rfq-quote",
+      collateralMint,
+      authorityCollateralAta: authorityAta,
+      initialYesReserve: initialLiquidity,
+      initialNoReserve: initialLiquidity,
+    });
+
+    for (const kp of [maker, taker]) {
+      await program.methods
+        .depositToTradingAccount(new anchor.BN(200_000_000))
+        .accounts({
+          tradingAccount: tradingAccountPda(kp.publicKey),
+          custodyAuthority,
+          custody,
+          user: kp.publicKey,
+          payer: kp.publicKey,
+          collateralMint,
+          userCollateralAta: atas[kp.publicKey.toBase58()],
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([kp])
+        .rpc({ commitment: "confirmed" });
+    }
+
+    await program.methods
+      .splitShares(new anchor.BN(100_000_000))
+      .accounts({
+        market: marketPda,
+        vault: vaultPda,
+        position: positionPda(maker.publicKey),
+        user: maker.publicKey,
+        payer: maker.publicKey,
+        collateralMint,
+        userCollateralAta: atas[maker.publicKey.toBase58()],
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([maker])
+      .rpc({ commitment: "confirmed" });
+  });
+
+  const settle = async (quote: any, signer: anchor.web3.Keypair) => {
+    const message = program.coder.types.encode("rfqQuote", quote);
+    const verifyIx = Ed25519Program.createInstructionWithPrivateKey({
+      privateKey: signer.secretKey,
+      message,
+    });
+    return program.methods
+      .settleRfq(quote)
+      .accounts({
+        market: marketPda,
+        quoteFill: quoteFillPda(quote.nonce),
+        makerPosition: positionPda(maker.publicKey),
+        takerPosition: positionPda(taker.publicKey),
+        makerTradingAccount: tradingAccountPda(maker.publicKey),
+        takerTradingAccount: tradingAccountPda(taker.publicKey),
+        maker: maker.publicKey,
+        taker: taker.publicKey,
+        payer: taker.publicKey,
+        collateralMint,
+        instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+        makerAllowlistEntry: null,
+        takerAllowlistEntry: null,
+        permitGate: null,
+        tokenGate: null,
+        makerGateTokenAccount: null,
+        takerGateTokenAccount: null,
+        systemProgram: SystemProgram.programId,
+      })
+      .preInstructions([verifyIx])
+      .signers([taker])
+      .rpc({ commitment: "confirmed" });
+  };
+
+  const quote = (nonce: number) => ({
+    market: marketPda,
+    maker: maker.publicKey,
+    taker: PublicKey.default,
+    makerSide: 1, // maker sells YES
+    outcomeIndex: 0,
+    shares: new anchor.BN(50_000_000),
+    price: new anchor.BN(600_000),
+    expiry: new anchor.BN(Math.floor(Date.now() / 1000) + 600),
+    nonce: new anchor.BN(nonce),
+  });
+
+  it("settle_rfq fills at the quoted price outside the AMM", async () => {
+    const marketBefore = await program.account.marketV3.fetch(marketPda);
+    await settle(quote(1), maker);
+
+    const takerPos = await program.account.positionV2.fetch(positionPda(taker.publicKey));
+    expect(takerPos.yesShares.toNumber()).to.eq(50_000_000);
+    const makerAcc = await program.account.tradingAccount.fetch(tradingAccountPda(maker.publicKey));
+    const takerAcc = await program.account.tradingAccount.fetch(tradingAccountPda(taker.publicKey));
+    expect(makerAcc.balance.toNumber()).to.eq(230_000_000);
+    expect(takerAcc.balance.toNumber()).to.eq(170_000_000);
+
+    const marketAfter = await program.account.marketV3.fetch(marketPda);
+    expect(marketAfter.yesPool.toNumber()).to.eq(marketBefore.yesPool.toNumber());
+  });
+
+  it("settle_rfq rejects a replayed nonce and a forged signature", async () => {
+    let failed = false;
+    try {
+      await settle(quote(1), maker);
+    } catch (e: any) {
+      failed = true;
+    }
+    expect(failed).to.eq(true);
+
+    failed = false;
+    try {
+      await settle(quote(2), taker);
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("InvalidSignature");
+    }
+    expect(failed).to.eq(true);
+  });
+});
@@ -0,0 +1,169 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { PublicKey, SystemProgram } from "@solana/web3.js";
+import {
+  TOKEN_PROGRAM_ID,
+  createMint,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+} from "@solana/spl-token";
+import { expect } from "chai";
+
+import { PredictionProgramV2 } from "../target/types/prediction_program_v2";
+import {
+  REGISTRY_PAGE_SIZE,
+  createTestMarket,
+  ensureConfig,
+  pda,
+  registryPagePda,
+  uniqueId,
+} from "./utils";
+
+describe("prediction_program_v2 (scalar markets)", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace
+    .PredictionProgramV2 as Program<PredictionProgramV2>;
+
+  const payer = (provider.wallet as any).payer as anchor.web3.Keypair;
+  const wallet = provider.wallet as anchor.Wallet;
+
+  const marketId = uniqueId();
+  // Pyth SOL/USD feed id
+  const feedId = Buffer.from(
+    "ef0d8b6fda2ceba41da15d4095d1da392a0d2f8ed0c6c7bc0f4cfac8c280b56d",
+    "hex"
+  );
+
+  let collateralMint: PublicKey;
+  let authorityAta: PublicKey;
+  let config: PublicKey;
+  let marketPda: PublicKey;
+  let scalarPda: PublicKey;
+
+  it("setup: market", async () => {
+    config = await ensureConfig(program, provider);
+
+    collateralMint = await createMint(provider.connection, payer, wallet.publicKey, null, 6);
+    authorityAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, wallet.publicKey)
+    ).address;
+    await mintTo(provider.connection, payer, collateralMint, authorityAta, payer, 10_000_000_000);
+
+    marketPda = pda(program, [
+      Buffer.from("market_v2"),
+      wallet.publicKey.toBuffer(),
+      marketId.toArrayLike(Buffer, "le", 8),
+    ]);
+    await createTestMarket(program, provider, {
+      marketId,
+      question: "SOL/USD at expiry, $100-$300",
+      metadataUri: "ar://sol-usd-scalar",
+      collateralMint,
+      authorityCollateralAta: authorityAta,
+    });
+  });
+
+  it("init_scalar_market stores the feed and range", async () => {
+    scalarPda = pda(program, [Buffer.from("scalar_market"), marketPda.toBuffer()]);
+    await program.methods
+      .initScalarMarket({
+        feedId: Array.from(feedId),
+        lower: new anchor.BN(100_00000000),
+        upper: new anchor.BN(300_00000000),
+        exponent: -8,
+      })
+      .accounts({
+        market: marketPda,
+        scalar: scalarPda,
+        authority: wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc({ commitment: "confirmed" });
+
+    const scalar = await program.account.scalarMarket.fetch(scalarPda);
+    expect(Buffer.from(scalar.feedId).equals(feedId)).to.eq(true);
+    expect(scalar.exponent).to.eq(-8);
+    expect((await program.account.marketV3.fetch(marketPda)).scalar).to.eq(1);
+  });
+
+  it("scalar markets cannot be resolved manually", async () => {
+    const market = await program.account.marketV3.fetch(marketPda);
+    let failed = false;
+    try {
+      await program.methods
+        .resolveMarket(0)
+        .accounts({
+          config,
+          market: marketPda,
+          vault: pda(program, [Buffer.from("vault_v2"), marketPda.toBuffer()]),
+          registryPage: registryPagePda(
+            program,
+            Math.floor(market.registryIndex.toNumber() / REGISTRY_PAGE_SIZE)
+          ),
+          lstStakePool: null,
+          parentMarket: null,
+          authority: wallet.publicKey,
+        })
+        .rpc({ commitment: "confirmed" });
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("ScalarMarket");
+    }
+    expect(failed).to.eq(true);
+  });
+
+  it("set_resolver_bounty stores a fixed + bps bounty", async () => {
+    let failed = false;
+    try {
+      await program.methods
+        .setResolverBounty(new anchor.BN(0), 10_001)
+        .accounts({ market: marketPda, authority: wallet.publicKey })
+        .rpc({ commitment: "confirmed" });
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("InvalidResolverBounty");
+    }
+    expect(failed).to.eq(true);
+
+    await program.methods
+      .setResolverBounty(new anchor.BN(1_000_000), 10)
+      .accounts({ market: marketPda, authority: wallet.publicKey })
+      .rpc({ commitment: "confirmed" });
+
+    const market = await program.account.marketV3.fetch(marketPda);
+    expect(market.resolverBounty.toNumber()).to.eq(1_000_000);
+    expect(market.resolverBountyBps).to.eq(10);
+  });
+
+  it("settle_scalar_with_pyth waits for end_time", async () => {
+    const market = await program.account.marketV3.fetch(marketPda);
+    let failed = false;
+    try {
+      await program.methods
+        .settleScalarWithPyth()
+        .accounts({
+          config,
+          market: marketPda,
+          scalar: scalarPda,
+          vault: pda(program, [Buffer.from("vault_v2"), marketPda.toBuffer()]),
+          vaultAuthority: pda(program, [Buffer.from("vault_auth_v2"), marketPda.toBuffer()]),
+          collateralMint,
+          priceUpdate: SystemProgram.programId,
+          resolver: wallet.publicKey,
+          resolverCollateralAta: authorityAta,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          registryPage: registryPagePda(
+            program,
+            Math.floor(market.registryIndex.toNumber() / REGISTRY_PAGE_SIZE)
+          ),
+        })
+        .rpc({ commitment: "confirmed" });
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("MarketNotEnded");
+    }
+    expect(failed).to.eq(true);
+  });
+});
@@ -0,0 +1,190 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { PublicKey, SystemProgram } from "@solana/web3.js";
+import {
+  TOKEN_PROGRAM_ID,
+  createMint,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+} from "@solana/spl-token";
+import { expect } from "chai";
+import { createHash } from "crypto";
+
+import { PredictionProgramV2 } from "../target/types/prediction_program_v2";
+import {
+  DEFAULT_MARKET_METADATA,
+  creatorBondPda,
+  ensureConfig,
+  lpPositionPda,
+  nextRegistryAccounts,
+  pda,
+  uniqueId,
+} from "./utils";
+
+describe("prediction_program_v2 (recurring market series)", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace
+    .PredictionProgramV2 as Program<PredictionProgramV2>;
+
+  const payer = (provider.wallet as any).payer as anchor.web3.Keypair;
+  const wallet = provider.wallet as anchor.Wallet;
+
+  const seriesId = uniqueId();
+  const week = 7 * 24 * 3600;
+  const patternHash = createHash("sha256").update("Will BTC close week {n} higher?").digest();
+
+  let collateralMint: PublicKey;
+  let authorityAta: PublicKey;
+  let config: PublicKey;
+  let series: PublicKey;
+  let firstMarket: PublicKey;
+
+  const createNext = async (marketId: anchor.BN) => {
+    const market = pda(program, [
+      Buffer.from("market_v2"),
+      wallet.publicKey.toBuffer(),
+      marketId.toArrayLike(Buffer, "le", 8),
+    ]);
+    await program.methods
+      .createNextInSeries(marketId)
+      .accounts({
+        config,
+        series,
+        market,
+        vault: pda(program, [Buffer.from("vault_v2"), market.toBuffer()]),
+        vaultAuthority: pda(program, [Buffer.from("vault_auth_v2"), market.toBuffer()]),
+        collateralMint,
+        authority: wallet.publicKey,
+        authorityCollateralAta: authorityAta,
+        ...(await nextRegistryAccounts(program)),
+        creatorBond: creatorBondPda(program, market),
+        creatorLpPosition: lpPositionPda(program, market, wallet.publicKey),
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc({ commitment: "confirmed" });
+    return market;
+  };
+
+  it("setup: weekly series", async () => {
+    config = await ensureConfig(program, provider);
+
+    collateralMint = await createMint(provider.connection, payer, wallet.publicKey, null, 6);
+    authorityAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, wallet.publicKey)
+    ).address;
+    await mintTo(provider.connection, payer, collateralMint, authorityAta, payer, 10_000_000_000);
+
+    series = pda(program, [
+      Buffer.from("market_series"),
+      wallet.publicKey.toBuffer(),
+      seriesId.toArrayLike(Buffer, "le", 8),
+    ]);
+    await program.methods
+      .createMarketSeries({
+        seriesId,
+        questionHash: Array.from(patternHash),
+        metadataUri: "ar://btc-weekly",
+        metadata: DEFAULT_MARKET_METADATA,
+        oracle: PublicKey.default,
+        firstStartTime: new anchor.BN(Math.floor(Date.now() / 1000)),
+        duration: new anchor.BN(week),
+        initialLiquidity: new anchor.BN(100_000_000),
+        creatorFeeShareBps: 0,
+      })
+      .accounts({
+        config,
+        series,
+        collateralMint,
+        authority: wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc({ commitment: "confirmed" });
+  });
+
+  it("create_next_in_series clones the template for consecutive weeks", async () => {
+    const first = await createNext(uniqueId());
+    firstMarket = first;
+    const second = await createNext(uniqueId());
+
+    const a = await program.account.marketV3.fetch(first);
+    const b = await program.account.marketV3.fetch(second);
+    expect(a.series.toBase58()).to.eq(series.toBase58());
+    expect(a.seriesIndex.toNumber()).to.eq(0);
+    expect(b.seriesIndex.toNumber()).to.eq(1);
+    expect(b.startTime.toNumber()).to.eq(a.endTime.toNumber());
+    expect(b.endTime.toNumber() - b.startTime.toNumber()).to.eq(week);
+    expect(b.status).to.eq(4); // PreMarket
+
+    const expected = createHash("sha256")
+      .update(Buffer.concat([patternHash, new anchor.BN(1).toArrayLike(Buffer, "le", 8)]))
+      .digest();
+    expect(Buffer.from(b.questionHash).equals(expected)).to.eq(true);
+
+    const s = await program.account.marketSeries.fetch(series);
+    expect(s.nextIndex.toNumber()).to.eq(2);
+    expect(s.lastMarket.toBase58()).to.eq(second.toBase58());
+  });
+
+  it("create_next_in_series refuses to run more than one period ahead", async () => {
+    let failed = false;
+    try {
+      await createNext(uniqueId());
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("SeriesNotDue");
+    }
+    expect(failed).to.eq(true);
+  });
+
+  it("set_claim_window rejects windows below the protocol minimum", async () => {
+    let failed = false;
+    try {
+      await program.methods
+        .setClaimWindow(new anchor.BN(60), false)
+        .accounts({ market: firstMarket, authority: wallet.publicKey })
+        .rpc({ commitment: "confirmed" });
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("InvalidClaimWindow");
+    }
+    expect(failed).to.eq(true);
+  });
+
+  it("set_claim_window: series markets can roll unclaimed funds forward", async () => {
+    await program.methods
+      .setClaimWindow(new anchor.BN(30 * 24 * 3600), true)
+      .accounts({ market: firstMarket, authority: wallet.publicKey })
+      .rpc({ commitment: "confirmed" });
+
+    const m = await program.account.marketV3.fetch(firstMarket);
+    expect(m.claimWindow.toNumber()).to.eq(30 * 24 * 3600);
+    expect(m.rolloverUnclaimed).to.eq(1);
+
+    let failed = false;
+    try {
+      await program.methods
+        .sweepUnclaimed()
+        .accounts({
+          config,
+          market: firstMarket,
+          vault: pda(program, [Buffer.from("vault_v2"), firstMarket.toBuffer()]),
+          vaultAuthority: pda(program, [Buffer.from("vault_auth_v2"), firstMarket.toBuffer()]),
+          collateralMint,
+          nextMarket: null,
+          nextVault: null,
+          nextLpPosition: null,
+          nextLpRewards: null,
+          treasuryCollateralAta: null,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .rpc({ commitment: "confirmed" });
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("MarketNotResolved");
+    }
+    expect(failed).to.eq(true);
+  });
+});
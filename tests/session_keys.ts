@@ -0,0 +1,244 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { PublicKey, SystemProgram, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import {
+  TOKEN_PROGRAM_ID,
+  createMint,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+  getAccount,
+} from "@solana/spl-token";
+import { expect } from "chai";
+
+import { PredictionProgramV2 } from "../target/types/prediction_program_v2";
+import {
+  airdrop,
+  createTestMarket,
+  ensureConfig,
+  pda,
+  uniqueId,
+} from "./utils";
+
+describe("prediction_program_v2 (session keys)", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace
+    .PredictionProgramV2 as Program<PredictionProgramV2>;
+
+  const payer = (provider.wallet as any).payer as anchor.web3.Keypair;
+  const wallet = provider.wallet as anchor.Wallet;
+
+  const trader = anchor.web3.Keypair.generate();
+  const marketId = uniqueId();
+  const initialLiquidity = new anchor.BN(1_000_000_000);
+
+  let collateralMint: PublicKey;
+  let authorityAta: PublicKey;
+  let traderAta: PublicKey;
+  let marketPda: PublicKey;
+  let vaultPda: PublicKey;
+  let vaultAuthPda: PublicKey;
+  let positionPda: PublicKey;
+  let tradingAccount: PublicKey;
+  let custodyAuthority: PublicKey;
+  let custody: PublicKey;
+  let sessionPda: PublicKey;
+  const sessionKey = anchor.web3.Keypair.generate();
+
+  it("setup: mint + market + PDAs", async () => {
+    await ensureConfig(program, provider);
+
+    await airdrop(provider, trader.publicKey);
+
+    collateralMint = await createMint(
+      provider.connection,
+      payer,
+      wallet.publicKey,
+      null,
+      6
+    );
+    authorityAta = (
+      await getOrCreateAssociatedTokenAccount(
+        provider.connection,
+        payer,
+        collateralMint,
+        wallet.publicKey
+      )
+    ).address;
+    traderAta = (
+      await getOrCreateAssociatedTokenAccount(
+        provider.connection,
+        payer,
+        collateralMint,
+        trader.publicKey
+      )
+    ).address;
+    await mintTo(provider.connection, payer, collateralMint, authorityAta, payer, 10_000_000_000);
+    await mintTo(provider.connection, payer, collateralMint, traderAta, payer, 1_000_000_000);
+
+    marketPda = pda(program, [
+      Buffer.from("market_v2"),
+      wallet.publicKey.toBuffer(),
+      marketId.toArrayLike(Buffer, "le", 8),
+    ]);
+    vaultPda = pda(program, [Buffer.from("vault_v2"), marketPda.toBuffer()]);
+    vaultAuthPda = pda(program, [Buffer.from("vault_auth_v2"), marketPda.toBuffer()]);
+    positionPda = pda(program, [Buffer.from("position_v2"), marketPda.toBuffer(), trader.publicKey.toBuffer()]);
+    tradingAccount = pda(program, [Buffer.from("trading_account"), trader.publicKey.toBuffer(), collateralMint.toBuffer()]);
+    custodyAuthority = pda(program, [Buffer.from("trading_custody_auth")]);
+    custody = pda(program, [Buffer.from("trading_custody"), collateralMint.toBuffer()]);
+
+    await createTestMarket(program, provider, {
+      marketId,
+      question: "Will the session outlive the trader?",
+      metadataUri: "ar://session-keys",
+      collateralMint,
+      authorityCollateralAta: authorityAta,
+      initialYesReserve: initialLiquidity,
+      initialNoReserve: initialLiquidity,
+    });
+
+    sessionPda = pda(program, [Buffer.from("session"), trader.publicKey.toBuffer(), sessionKey.publicKey.toBuffer()]);
+    await airdrop(provider, sessionKey.publicKey, LAMPORTS_PER_SOL);
+
+    await program.methods
+      .depositToTradingAccount(new anchor.BN(500_000_000))
+      .accounts({
+        tradingAccount,
+        custodyAuthority,
+        custody,
+        user: trader.publicKey,
+        payer: trader.publicKey,
+        collateralMint,
+        userCollateralAta: traderAta,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([trader])
+      .rpc({ commitment: "confirmed" });
+  });
+
+  const sessionAccounts = () => ({
+    market: marketPda,
+    vault: vaultPda,
+    vaultAuthority: vaultAuthPda,
+    position: positionPda,
+    tradingAccount,
+    session: sessionPda,
+    owner: trader.publicKey,
+    sessionKey: sessionKey.publicKey,
+    payer: sessionKey.publicKey,
+    custodyAuthority,
+    custody,
+    collateralMint,
+    tokenProgram: TOKEN_PROGRAM_ID,
+    systemProgram: SystemProgram.programId,
+  });
+
+  it("create_session rejects an expiry past the maximum length", async () => {
+    let failed = false;
+    try {
+      await program.methods
+        .createSession(
+          sessionKey.publicKey,
+          new anchor.BN(Math.floor(Date.now() / 1000) + 8 * 24 * 3600),
+          new anchor.BN(150_000_000)
+        )
+        .accounts({
+          session: sessionPda,
+          owner: trader.publicKey,
+          payer: trader.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([trader])
+        .rpc({ commitment: "confirmed" });
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("InvalidSessionExpiry");
+    }
+    expect(failed).to.eq(true);
+  });
+
+  it("session key buys and sells for the owner within its spend cap", async () => {
+    await program.methods
+      .createSession(
+        sessionKey.publicKey,
+        new anchor.BN(Math.floor(Date.now() / 1000) + 3600),
+        new anchor.BN(150_000_000)
+      )
+      .accounts({
+        session: sessionPda,
+        owner: trader.publicKey,
+        payer: trader.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([trader])
+      .rpc({ commitment: "confirmed" });
+
+    const walletBefore = Number((await getAccount(provider.connection, traderAta)).amount);
+
+    await program.methods
+      .sessionBuy(0, new anchor.BN(100_000_000), new anchor.BN(1))
+      .accounts(sessionAccounts())
+      .signers([sessionKey])
+      .rpc({ commitment: "confirmed" });
+
+    const session = await program.account.sessionKey.fetch(sessionPda);
+    expect(session.spent.toNumber()).to.eq(100_000_000);
+    const acc = await program.account.tradingAccount.fetch(tradingAccount);
+    expect(acc.balance.toNumber()).to.eq(400_000_000);
+
+    const position = await program.account.positionV2.fetch(positionPda);
+    expect(position.owner.toBase58()).to.eq(trader.publicKey.toBase58());
+    expect(position.yesShares.toNumber()).to.be.greaterThan(0);
+
+    await program.methods
+      .sessionSell(0, position.yesShares, new anchor.BN(1))
+      .accounts(sessionAccounts())
+      .signers([sessionKey])
+      .rpc({ commitment: "confirmed" });
+    expect(
+      (await program.account.tradingAccount.fetch(tradingAccount)).balance.toNumber()
+    ).to.be.greaterThan(400_000_000);
+
+    const walletAfter = Number((await getAccount(provider.connection, traderAta)).amount);
+    expect(walletAfter).to.eq(walletBefore);
+  });
+
+  it("session_buy past the spend cap fails", async () => {
+    let failed = false;
+    try {
+      await program.methods
+        .sessionBuy(0, new anchor.BN(60_000_000), new anchor.BN(1))
+        .accounts(sessionAccounts())
+        .signers([sessionKey])
+        .rpc({ commitment: "confirmed" });
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("SessionSpendCapExceeded");
+    }
+    expect(failed).to.eq(true);
+  });
+
+  it("revoke_session closes the session and stops the key", async () => {
+    await program.methods
+      .revokeSession()
+      .accounts({ session: sessionPda, owner: trader.publicKey })
+      .signers([trader])
+      .rpc({ commitment: "confirmed" });
+    expect(await provider.connection.getAccountInfo(sessionPda)).to.eq(null);
+
+    let failed = false;
+    try {
+      await program.methods
+        .sessionBuy(0, new anchor.BN(1_000_000), new anchor.BN(1))
+        .accounts(sessionAccounts())
+        .signers([sessionKey])
+        .rpc({ commitment: "confirmed" });
+    } catch (e: any) {
+      failed = true;
+    }
+    expect(failed).to.eq(true);
+  });
+});
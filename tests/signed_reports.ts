@@ -0,0 +1,182 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import {
+  Ed25519Program,
+  PublicKey,
+  SystemProgram,
+  SYSVAR_INSTRUCTIONS_PUBKEY,
+} from "@solana/web3.js";
+import {
+  TOKEN_PROGRAM_ID,
+  createMint,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+} from "@solana/spl-token";
+import { expect } from "chai";
+
+import { PredictionProgramV2 } from "../target/types/prediction_program_v2";
+import {
+  REGISTRY_PAGE_SIZE,
+  airdrop,
+  buyAccounts,
+  createTestMarket,
+  ensureConfig,
+  pda,
+  registryPagePda,
+  uniqueId,
+} from "./utils";
+
+describe("prediction_program_v2 (ed25519-attested resolution reports)", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace
+    .PredictionProgramV2 as Program<PredictionProgramV2>;
+
+  const payer = (provider.wallet as any).payer as anchor.web3.Keypair;
+  const wallet = provider.wallet as anchor.Wallet;
+
+  const user = anchor.web3.Keypair.generate();
+  const reporter = anchor.web3.Keypair.generate();
+  const marketId = uniqueId();
+
+  let collateralMint: PublicKey;
+  let authorityAta: PublicKey;
+  let userAta: PublicKey;
+  let config: PublicKey;
+  let marketPda: PublicKey;
+  let vaultPda: PublicKey;
+  let vaultAuthPda: PublicKey;
+  let positionPda: PublicKey;
+  let oracleConfigPda: PublicKey;
+
+  it("setup: reporter-resolved market + a YES position", async () => {
+    config = await ensureConfig(program, provider);
+
+    await airdrop(provider, user.publicKey);
+
+    collateralMint = await createMint(provider.connection, payer, wallet.publicKey, null, 6);
+    authorityAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, wallet.publicKey)
+    ).address;
+    userAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, user.publicKey)
+    ).address;
+    await mintTo(provider.connection, payer, collateralMint, authorityAta, payer, 10_000_000_000);
+    await mintTo(provider.connection, payer, collateralMint, userAta, payer, 1_000_000_000);
+
+    marketPda = pda(program, [
+      Buffer.from("market_v2"),
+      wallet.publicKey.toBuffer(),
+      marketId.toArrayLike(Buffer, "le", 8),
+    ]);
+    vaultPda = pda(program, [Buffer.from("vault_v2"), marketPda.toBuffer()]);
+    vaultAuthPda = pda(program, [Buffer.from("vault_auth_v2"), marketPda.toBuffer()]);
+    positionPda = pda(program, [Buffer.from("position_v2"), marketPda.toBuffer(), user.publicKey.toBuffer()]);
+
+    await createTestMarket(program, provider, {
+      marketId,
+      question: "Will the home side win the derby?",
+      metadataUri: "ar://derby-home-win",
+      collateralMint,
+      authorityCollateralAta: authorityAta,
+      endTime: new anchor.BN(Math.floor(Date.now() / 1000) + 5),
+    });
+
+    oracleConfigPda = pda(program, [Buffer.from("oracle_config"), marketPda.toBuffer()]);
+    await program.methods
+      .initOracleConfig({
+        adapter: 3, // SignedReport
+        feed: reporter.publicKey,
+        feedId: new Array(32).fill(0),
+        threshold: new anchor.BN(0),
+        exponent: 0,
+        yesIfAbove: false,
+      })
+      .accounts({
+        market: marketPda,
+        oracleConfig: oracleConfigPda,
+        authority: wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc({ commitment: "confirmed" });
+
+    await program.methods
+      .buyShares(0, new anchor.BN(100_000_000), new anchor.BN(1))
+      .accounts(buyAccounts({
+        config,
+        market: marketPda,
+        vault: vaultPda,
+        vaultAuthority: vaultAuthPda,
+        position: positionPda,
+        user: user.publicKey,
+        payer: user.publicKey,
+        collateralMint,
+        userCollateralAta: userAta,
+      }))
+      .signers([user])
+      .rpc({ commitment: "confirmed" });
+  });
+
+  const submit = async (report: any, signer: anchor.web3.Keypair) => {
+    const market = await program.account.marketV3.fetch(marketPda);
+    const verifyIx = Ed25519Program.createInstructionWithPrivateKey({
+      privateKey: signer.secretKey,
+      message: program.coder.types.encode("resolutionReport", report),
+    });
+    return program.methods
+      .resolveWithOracle(report)
+      .accounts({
+        config,
+        market: marketPda,
+        oracleConfig: oracleConfigPda,
+        vault: vaultPda,
+        vaultAuthority: vaultAuthPda,
+        collateralMint,
+        oracleAccount: null,
+        resolver: wallet.publicKey,
+        resolverCollateralAta: null,
+        instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        registryPage: registryPagePda(
+          program,
+          Math.floor(market.registryIndex.toNumber() / REGISTRY_PAGE_SIZE)
+        ),
+      })
+      .preInstructions([verifyIx])
+      .rpc({ commitment: "confirmed" });
+  };
+
+  it("resolve_with_oracle rejects a report not signed by the reporter", async () => {
+    // Let end_time pass
+    await new Promise((r) => setTimeout(r, 6000));
+    const report = {
+      market: marketPda,
+      winningOutcome: 0,
+      reportedAt: new anchor.BN(Math.floor(Date.now() / 1000)),
+    };
+    let failed = false;
+    try {
+      await submit(report, user);
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("InvalidSignature");
+    }
+    expect(failed).to.eq(true);
+  });
+
+  it("resolve_with_oracle resolves from the reporter's attestation", async () => {
+    await submit(
+      {
+        market: marketPda,
+        winningOutcome: 0,
+        reportedAt: new anchor.BN(Math.floor(Date.now() / 1000)),
+      },
+      reporter
+    );
+
+    const market = await program.account.marketV3.fetch(marketPda);
+    expect(market.status).to.eq(1); // Resolved
+    expect(market.winningOutcome).to.eq(0);
+  });
+});
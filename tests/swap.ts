@@ -0,0 +1,167 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { PublicKey, SystemProgram } from "@solana/web3.js";
+import {
+  TOKEN_PROGRAM_ID,
+  createMint,
+  getAccount,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+} from "@solana/spl-token";
+import { expect } from "chai";
+
+import { PredictionProgramV2 } from "../target/types/prediction_program_v2";
+import {
+  createTestMarket,
+  ensureConfig,
+  pda,
+  uniqueId,
+} from "./utils";
+
+describe("prediction_program_v2 (aggregator swaps)", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace
+    .PredictionProgramV2 as Program<PredictionProgramV2>;
+
+  const payer = (provider.wallet as any).payer as anchor.web3.Keypair;
+  const wallet = provider.wallet as anchor.Wallet;
+
+  const marketId = uniqueId();
+
+  let collateralMint: PublicKey;
+  let collateralAta: PublicKey;
+  let yesMint: PublicKey;
+  let yesAta: PublicKey;
+  let config: PublicKey;
+  let marketPda: PublicKey;
+  let vaultPda: PublicKey;
+  let vaultAuthPda: PublicKey;
+
+  async function swap(
+    source: [PublicKey, PublicKey],
+    destination: [PublicKey, PublicKey],
+    amountIn: anchor.BN,
+    minOut = new anchor.BN(1)
+  ) {
+    await program.methods
+      .swap(amountIn, minOut)
+      .accounts({
+        config,
+        market: marketPda,
+        vault: vaultPda,
+        vaultAuthority: vaultAuthPda,
+        userTransferAuthority: wallet.publicKey,
+        sourceMint: source[0],
+        destinationMint: destination[0],
+        userSourceTokenAccount: source[1],
+        userDestinationTokenAccount: destination[1],
+        marketStats: null,
+        protocolStats: null,
+        priceCandles: null,
+        tradeTape: null,
+        priceFeed: null,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .rpc({ commitment: "confirmed" });
+  }
+
+  it("setup: market + YES outcome mint", async () => {
+    config = await ensureConfig(program, provider);
+
+    collateralMint = await createMint(provider.connection, payer, wallet.publicKey, null, 6);
+    collateralAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, wallet.publicKey)
+    ).address;
+    await mintTo(provider.connection, payer, collateralMint, collateralAta, payer, 10_000_000_000);
+
+    marketPda = pda(program, [
+      Buffer.from("market_v2"),
+      wallet.publicKey.toBuffer(),
+      marketId.toArrayLike(Buffer, "le", 8),
+    ]);
+    vaultPda = pda(program, [Buffer.from("vault_v2"), marketPda.toBuffer()]);
+    vaultAuthPda = pda(program, [Buffer.from("vault_auth_v2"), marketPda.toBuffer()]);
+    yesMint = pda(program, [Buffer.from("outcome_mint"), marketPda.toBuffer(), Buffer.from([0])]);
+
+    await createTestMarket(program, provider, {
+      marketId,
+      question: "Will the route go through?",
+      metadataUri: "ar://swap",
+      collateralMint,
+      authorityCollateralAta: collateralAta,
+    });
+
+    await program.methods
+      .initOutcomeMint(0)
+      .accounts({
+        market: marketPda,
+        vaultAuthority: vaultAuthPda,
+        outcomeMint: yesMint,
+        collateralMint,
+        payer: wallet.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc({ commitment: "confirmed" });
+
+    yesAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, yesMint, wallet.publicKey)
+    ).address;
+  });
+
+  it("collateral -> YES mints outcome tokens and moves the curve", async () => {
+    const before = await program.account.marketV3.fetch(marketPda);
+
+    await swap([collateralMint, collateralAta], [yesMint, yesAta], new anchor.BN(50_000_000));
+
+    const yes = await getAccount(provider.connection, yesAta);
+    expect(Number(yes.amount)).to.be.greaterThan(50_000_000);
+
+    const after = await program.account.marketV3.fetch(marketPda);
+    expect(after.yesPool.lt(before.yesPool)).to.eq(true);
+    expect(after.totalYesShares.toString()).to.eq(yes.amount.toString());
+  });
+
+  it("YES -> collateral burns the tokens and pays out", async () => {
+    const yes = await getAccount(provider.connection, yesAta);
+    const collateralBefore = await getAccount(provider.connection, collateralAta);
+
+    await swap([yesMint, yesAta], [collateralMint, collateralAta], new anchor.BN(yes.amount.toString()));
+
+    expect(Number((await getAccount(provider.connection, yesAta)).amount)).to.eq(0);
+    const collateralAfter = await getAccount(provider.connection, collateralAta);
+    expect(collateralAfter.amount > collateralBefore.amount).to.eq(true);
+
+    const market = await program.account.marketV3.fetch(marketPda);
+    expect(market.totalYesShares.toNumber()).to.eq(0);
+  });
+
+  it("minimum_amount_out is enforced", async () => {
+    let failed = false;
+    try {
+      await swap(
+        [collateralMint, collateralAta],
+        [yesMint, yesAta],
+        new anchor.BN(1_000_000),
+        new anchor.BN(1_000_000_000)
+      );
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("SlippageExceeded");
+    }
+    expect(failed).to.eq(true);
+  });
+
+  it("mints outside the market are rejected", async () => {
+    let failed = false;
+    try {
+      await swap([collateralMint, collateralAta], [collateralMint, collateralAta], new anchor.BN(1_000_000));
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("InvalidSwapMints");
+    }
+    expect(failed).to.eq(true);
+  });
+});
@@ -0,0 +1,151 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { PublicKey, SystemProgram } from "@solana/web3.js";
+import {
+  TOKEN_PROGRAM_ID,
+  createMint,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+} from "@solana/spl-token";
+import { expect } from "chai";
+import { createHash } from "crypto";
+
+import { PredictionProgramV2 } from "../target/types/prediction_program_v2";
+import {
+  airdrop,
+  creatorBondPda,
+  ensureConfig,
+  lpPositionPda,
+  nextRegistryAccounts,
+  pda,
+  uniqueId,
+} from "./utils";
+
+describe("prediction_program_v2 (market templates)", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace
+    .PredictionProgramV2 as Program<PredictionProgramV2>;
+
+  const payer = (provider.wallet as any).payer as anchor.web3.Keypair;
+  const wallet = provider.wallet as anchor.Wallet;
+
+  const creator = anchor.web3.Keypair.generate();
+  const templateId = uniqueId();
+  const formatHash = createHash("sha256").update("Will {asset} close above {price} on {date}?").digest();
+  const duration = 24 * 3600;
+
+  let collateralMint: PublicKey;
+  let creatorAta: PublicKey;
+  let config: PublicKey;
+  let template: PublicKey;
+
+  const createFromTemplate = async (marketId: anchor.BN, params: Buffer, reserve: number) => {
+    const market = pda(program, [
+      Buffer.from("market_v2"),
+      creator.publicKey.toBuffer(),
+      marketId.toArrayLike(Buffer, "le", 8),
+    ]);
+    await program.methods
+      .createFromTemplate(templateId, {
+        marketId,
+        params,
+        metadataUri: `ar://template-${marketId.toString()}`,
+        imageUri: "",
+        tags: new anchor.BN(0),
+        startTime: new anchor.BN(0),
+        initialYesReserve: new anchor.BN(reserve),
+        initialNoReserve: new anchor.BN(reserve),
+      })
+      .accounts({
+        config,
+        template,
+        market,
+        vault: pda(program, [Buffer.from("vault_v2"), market.toBuffer()]),
+        vaultAuthority: pda(program, [Buffer.from("vault_auth_v2"), market.toBuffer()]),
+        collateralMint,
+        authority: creator.publicKey,
+        authorityCollateralAta: creatorAta,
+        ...(await nextRegistryAccounts(program)),
+        creatorBond: creatorBondPda(program, market),
+        creatorLpPosition: lpPositionPda(program, market, creator.publicKey),
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([creator])
+      .rpc({ commitment: "confirmed" });
+    return market;
+  };
+
+  it("setup: admin registers a price-threshold template", async () => {
+    config = await ensureConfig(program, provider);
+
+    await airdrop(provider, creator.publicKey);
+
+    collateralMint = await createMint(provider.connection, payer, wallet.publicKey, null, 6);
+    creatorAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, creator.publicKey)
+    ).address;
+    await mintTo(provider.connection, payer, collateralMint, creatorAta, payer, 1_000_000_000);
+
+    template = pda(program, [Buffer.from("market_template"), templateId.toArrayLike(Buffer, "le", 8)]);
+    await program.methods
+      .registerMarketTemplate({
+        templateId,
+        questionHash: Array.from(formatHash),
+        rulesUri: "ar://price-threshold-rules",
+        category: 3, // Crypto
+        oracle: PublicKey.default,
+        duration: new anchor.BN(duration),
+        creatorFeeShareBps: 0,
+        minLiquidity: new anchor.BN(10_000_000),
+      })
+      .accounts({
+        config,
+        template,
+        registrar: wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc({ commitment: "confirmed" });
+  });
+
+  it("create_from_template: anyone creates a market with the templated question", async () => {
+    const params = Buffer.from("SOL|300|2026-12-31");
+    const market = await createFromTemplate(uniqueId(), params, 50_000_000);
+
+    const m = await program.account.marketV3.fetch(market);
+    const expected = createHash("sha256").update(Buffer.concat([formatHash, params])).digest();
+    expect(Buffer.from(m.questionHash).equals(expected)).to.eq(true);
+    expect(m.template.toBase58()).to.eq(template.toBase58());
+    expect(m.category).to.eq(3);
+    expect(m.endTime.toNumber() - m.startTime.toNumber()).to.be.at.least(duration);
+  });
+
+  it("create_from_template enforces the template's minimum liquidity", async () => {
+    let failed = false;
+    try {
+      await createFromTemplate(uniqueId(), Buffer.from("BTC|1|2026-12-31"), 1_000);
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("InvalidLiquidity");
+    }
+    expect(failed).to.eq(true);
+  });
+
+  it("retired templates cannot be used", async () => {
+    await program.methods
+      .setTemplateActive(false)
+      .accounts({ config, template, registrar: wallet.publicKey })
+      .rpc({ commitment: "confirmed" });
+
+    let failed = false;
+    try {
+      await createFromTemplate(uniqueId(), Buffer.from("ETH|5000|2026-12-31"), 50_000_000);
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("TemplateInactive");
+    }
+    expect(failed).to.eq(true);
+  });
+});
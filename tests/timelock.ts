@@ -0,0 +1,144 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { PublicKey, SystemProgram } from "@solana/web3.js";
+import { expect } from "chai";
+
+import { PredictionProgramV2 } from "../target/types/prediction_program_v2";
+import {
+  NO_CONFIG_CHANGES,
+  ensureConfig,
+  pda,
+} from "./utils";
+
+describe("prediction_program_v2 (timelocked parameter changes)", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace
+    .PredictionProgramV2 as Program<PredictionProgramV2>;
+
+  const wallet = provider.wallet as anchor.Wallet;
+
+  const noChanges = NO_CONFIG_CHANGES;
+
+  let config: PublicKey;
+  let pendingParams: PublicKey;
+
+  async function queueAndExecute(params: any) {
+    await program.methods
+      .queueParams(params)
+      .accounts({ config, pendingParams, admin: wallet.publicKey, systemProgram: SystemProgram.programId })
+      .rpc({ commitment: "confirmed" });
+    await new Promise((r) => setTimeout(r, 3_000));
+    await program.methods
+      .executeParams()
+      .accounts({ config, pendingParams, proposer: wallet.publicKey })
+      .rpc({ commitment: "confirmed" });
+  }
+
+  it("setup: enable a 2s timelock", async () => {
+    config = await ensureConfig(program, provider);
+    pendingParams = pda(program, [Buffer.from("pending_params")]);
+
+    await program.methods
+      .setParams({ ...noChanges, paramTimelock: new anchor.BN(2) })
+      .accounts({ config, admin: wallet.publicKey })
+      .rpc({ commitment: "confirmed" });
+
+    const cfg = await program.account.protocolConfig.fetch(config);
+    expect(cfg.paramTimelock.toNumber()).to.eq(2);
+  });
+
+  it("direct fee changes are rejected while the timelock is set", async () => {
+    const cfg = await program.account.protocolConfig.fetch(config);
+    let failed = false;
+    try {
+      await program.methods
+        .setFeeShares(cfg.creatorFeeShareBps, cfg.protocolFeeShareBps)
+        .accounts({ config, admin: wallet.publicKey })
+        .rpc({ commitment: "confirmed" });
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("TimelockRequired");
+    }
+    expect(failed).to.eq(true);
+
+    // Untimelocked fields still go through directly
+    await program.methods
+      .setParams({ ...noChanges, reportBond: cfg.reportBond })
+      .accounts({ config, admin: wallet.publicKey })
+      .rpc({ commitment: "confirmed" });
+  });
+
+  it("curator, dispute and yield-program changes need the timelock too", async () => {
+    const cfg = await program.account.protocolConfig.fetch(config);
+    const direct = [
+      program.methods.setCurator(cfg.curator),
+      program.methods.setYieldProgram(cfg.yieldProgram),
+      program.methods.setCorrectionWindow(cfg.correctionWindow),
+      program.methods.setParams({ ...noChanges, disputeWindow: cfg.disputeWindow }),
+    ];
+    for (const builder of direct) {
+      let failed = false;
+      try {
+        await builder.accounts({ config, admin: wallet.publicKey }).rpc({ commitment: "confirmed" });
+      } catch (e: any) {
+        failed = true;
+        expect(String(e)).to.contain("TimelockRequired");
+      }
+      expect(failed).to.eq(true);
+    }
+  });
+
+  it("queue_params + execute_params apply the change after the delay", async () => {
+    const before = await program.account.protocolConfig.fetch(config);
+    const bumped = before.insuranceFeeShareBps + 1;
+
+    await program.methods
+      .queueParams({ ...noChanges, insuranceFeeShareBps: bumped })
+      .accounts({ config, pendingParams, admin: wallet.publicKey, systemProgram: SystemProgram.programId })
+      .rpc({ commitment: "confirmed" });
+
+    let failed = false;
+    try {
+      await program.methods
+        .executeParams()
+        .accounts({ config, pendingParams, proposer: wallet.publicKey })
+        .rpc({ commitment: "confirmed" });
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("TimelockPending");
+    }
+    expect(failed).to.eq(true);
+
+    await new Promise((r) => setTimeout(r, 3_000));
+    await program.methods
+      .executeParams()
+      .accounts({ config, pendingParams, proposer: wallet.publicKey })
+      .rpc({ commitment: "confirmed" });
+
+    const after = await program.account.protocolConfig.fetch(config);
+    expect(after.insuranceFeeShareBps).to.eq(bumped);
+    expect(await provider.connection.getAccountInfo(pendingParams)).to.eq(null);
+
+    await queueAndExecute({ ...noChanges, insuranceFeeShareBps: before.insuranceFeeShareBps });
+  });
+
+  it("cancel_params drops a queued change", async () => {
+    await program.methods
+      .queueParams({ ...noChanges, treasury: anchor.web3.Keypair.generate().publicKey })
+      .accounts({ config, pendingParams, admin: wallet.publicKey, systemProgram: SystemProgram.programId })
+      .rpc({ commitment: "confirmed" });
+    await program.methods
+      .cancelParams()
+      .accounts({ config, pendingParams, proposer: wallet.publicKey, admin: wallet.publicKey })
+      .rpc({ commitment: "confirmed" });
+
+    expect(await provider.connection.getAccountInfo(pendingParams)).to.eq(null);
+  });
+
+  after(async () => {
+    // Other suites change fees directly
+    await queueAndExecute({ ...noChanges, paramTimelock: new anchor.BN(0) });
+  });
+});
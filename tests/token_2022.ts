@@ -0,0 +1,257 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import {
+  PublicKey,
+  SystemProgram,
+  Keypair,
+  Transaction,
+  sendAndConfirmTransaction,
+} from "@solana/web3.js";
+import {
+  TOKEN_2022_PROGRAM_ID,
+  ExtensionType,
+  getMintLen,
+  createInitializePermanentDelegateInstruction,
+  createInitializeMintInstruction,
+  createMint,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+  getAccount,
+} from "@solana/spl-token";
+import { expect } from "chai";
+
+import { PredictionProgramV2 } from "../target/types/prediction_program_v2";
+import {
+  airdrop,
+  buyAccounts,
+  createTestMarket,
+  ensureConfig,
+  pda,
+  uniqueId,
+} from "./utils";
+
+describe("prediction_program_v2 (Token-2022 collateral)", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace
+    .PredictionProgramV2 as Program<PredictionProgramV2>;
+
+  const payer = (provider.wallet as any).payer as anchor.web3.Keypair;
+  const wallet = provider.wallet as anchor.Wallet;
+
+  const user = anchor.web3.Keypair.generate();
+  const marketId = uniqueId();
+  const initialLiquidity = new anchor.BN(1_000_000_000);
+
+  let collateralMint: PublicKey;
+  let authorityAta: PublicKey;
+  let userAta: PublicKey;
+  let config: PublicKey;
+  let marketPda: PublicKey;
+  let vaultPda: PublicKey;
+  let vaultAuthPda: PublicKey;
+  let positionPda: PublicKey;
+
+  it("setup: Token-2022 mint + ATAs + PDAs", async () => {
+    config = await ensureConfig(program, provider);
+
+    await airdrop(provider, user.publicKey);
+
+    collateralMint = await createMint(
+      provider.connection,
+      payer,
+      wallet.publicKey,
+      null,
+      6,
+      undefined,
+      undefined,
+      TOKEN_2022_PROGRAM_ID
+    );
+
+    authorityAta = (
+      await getOrCreateAssociatedTokenAccount(
+        provider.connection,
+        payer,
+        collateralMint,
+        wallet.publicKey,
+        false,
+        undefined,
+        undefined,
+        TOKEN_2022_PROGRAM_ID
+      )
+    ).address;
+    userAta = (
+      await getOrCreateAssociatedTokenAccount(
+        provider.connection,
+        payer,
+        collateralMint,
+        user.publicKey,
+        false,
+        undefined,
+        undefined,
+        TOKEN_2022_PROGRAM_ID
+      )
+    ).address;
+
+    await mintTo(
+      provider.connection,
+      payer,
+      collateralMint,
+      authorityAta,
+      payer,
+      10_000_000_000,
+      [],
+      undefined,
+      TOKEN_2022_PROGRAM_ID
+    );
+    await mintTo(
+      provider.connection,
+      payer,
+      collateralMint,
+      userAta,
+      payer,
+      1_000_000_000,
+      [],
+      undefined,
+      TOKEN_2022_PROGRAM_ID
+    );
+
+    marketPda = pda(program, [
+      Buffer.from("market_v2"),
+      wallet.publicKey.toBuffer(),
+      marketId.toArrayLike(Buffer, "le", 8),
+    ]);
+    vaultPda = pda(program, [Buffer.from("vault_v2"), marketPda.toBuffer()]);
+    vaultAuthPda = pda(program, [Buffer.from("vault_auth_v2"), marketPda.toBuffer()]);
+    positionPda = pda(program, [Buffer.from("position_v2"), marketPda.toBuffer(), user.publicKey.toBuffer()]);
+  });
+
+  it("create_market_cpmm + buy_shares with a Token-2022 mint", async () => {
+    await createTestMarket(program, provider, {
+      marketId,
+      question: "Will PYUSD supply exceed 5B by 2027?",
+      metadataUri: "ar://pyusd-supply-2027",
+      collateralMint,
+      authorityCollateralAta: authorityAta,
+      initialYesReserve: initialLiquidity,
+      initialNoReserve: initialLiquidity,
+      tokenProgram: TOKEN_2022_PROGRAM_ID,
+    });
+
+    const amountIn = new anchor.BN(100_000_000);
+    await program.methods
+      .buyShares(1, amountIn, new anchor.BN(1))
+      .accounts(buyAccounts({
+        config,
+        market: marketPda,
+        vault: vaultPda,
+        vaultAuthority: vaultAuthPda,
+        position: positionPda,
+        user: user.publicKey,
+        payer: user.publicKey,
+        collateralMint,
+        userCollateralAta: userAta,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+      }))
+      .signers([user])
+      .rpc({ commitment: "confirmed" });
+
+    const vault = await getAccount(
+      provider.connection,
+      vaultPda,
+      "confirmed",
+      TOKEN_2022_PROGRAM_ID
+    );
+    expect(Number(vault.amount)).to.eq(
+      initialLiquidity.toNumber() * 2 + amountIn.toNumber()
+    );
+
+    const position = await program.account.positionV2.fetch(positionPda);
+    expect(Number(position.noShares)).to.be.greaterThan(0);
+  });
+
+  it("create_market_cpmm rejects a permanent-delegate mint under the default policy", async () => {
+    const mintKp = Keypair.generate();
+    const mintLen = getMintLen([ExtensionType.PermanentDelegate]);
+    const lamports =
+      await provider.connection.getMinimumBalanceForRentExemption(mintLen);
+
+    await sendAndConfirmTransaction(
+      provider.connection,
+      new Transaction().add(
+        SystemProgram.createAccount({
+          fromPubkey: payer.publicKey,
+          newAccountPubkey: mintKp.publicKey,
+          space: mintLen,
+          lamports,
+          programId: TOKEN_2022_PROGRAM_ID,
+        }),
+        createInitializePermanentDelegateInstruction(
+          mintKp.publicKey,
+          payer.publicKey,
+          TOKEN_2022_PROGRAM_ID
+        ),
+        createInitializeMintInstruction(
+          mintKp.publicKey,
+          6,
+          payer.publicKey,
+          null,
+          TOKEN_2022_PROGRAM_ID
+        )
+      ),
+      [payer, mintKp]
+    );
+
+    const badAta = (
+      await getOrCreateAssociatedTokenAccount(
+        provider.connection,
+        payer,
+        mintKp.publicKey,
+        wallet.publicKey,
+        false,
+        undefined,
+        undefined,
+        TOKEN_2022_PROGRAM_ID
+      )
+    ).address;
+    await mintTo(
+      provider.connection,
+      payer,
+      mintKp.publicKey,
+      badAta,
+      payer,
+      1_000_000,
+      [],
+      undefined,
+      TOKEN_2022_PROGRAM_ID
+    );
+
+    const badMarketId = uniqueId();
+    const badMarket = pda(program, [
+      Buffer.from("market_v2"),
+      wallet.publicKey.toBuffer(),
+      badMarketId.toArrayLike(Buffer, "le", 8),
+    ]);
+    const badVault = pda(program, [Buffer.from("vault_v2"), badMarket.toBuffer()]);
+    const badVaultAuth = pda(program, [Buffer.from("vault_auth_v2"), badMarket.toBuffer()]);
+
+    let failed = false;
+    try {
+      await createTestMarket(program, provider, {
+        marketId: badMarketId,
+        question: "Clawback-able collateral?",
+        metadataUri: "ar://clawback-collateral",
+        collateralMint: mintKp.publicKey,
+        authorityCollateralAta: badAta,
+        initialYesReserve: new anchor.BN(100_000),
+        initialNoReserve: new anchor.BN(100_000),
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+      });
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("CollateralMintNotAllowed");
+    }
+    expect(failed).to.eq(true);
+  });
+});
@@ -0,0 +1,172 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { PublicKey, SystemProgram } from "@solana/web3.js";
+import {
+  createMint,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+} from "@solana/spl-token";
+import { expect } from "chai";
+
+import { PredictionProgramV2 } from "../target/types/prediction_program_v2";
+import {
+  airdrop,
+  buyAccounts,
+  createTestMarket,
+  ensureConfig,
+  pda,
+  sellAccounts,
+  uniqueId,
+} from "./utils";
+
+describe("prediction_program_v2 (token-gated markets)", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace
+    .PredictionProgramV2 as Program<PredictionProgramV2>;
+
+  const payer = (provider.wallet as any).payer as anchor.web3.Keypair;
+  const wallet = provider.wallet as anchor.Wallet;
+
+  const user = anchor.web3.Keypair.generate();
+  const marketId = uniqueId();
+
+  let collateralMint: PublicKey;
+  let userAta: PublicKey;
+  let config: PublicKey;
+  let marketPda: PublicKey;
+  let vaultPda: PublicKey;
+  let vaultAuthPda: PublicKey;
+  let positionPda: PublicKey;
+  let gateMint: PublicKey;
+  let gatePda: PublicKey;
+
+  const tradeAccounts = () => ({
+    config,
+    market: marketPda,
+    vault: vaultPda,
+    vaultAuthority: vaultAuthPda,
+    position: positionPda,
+    user: user.publicKey,
+    payer: user.publicKey,
+    collateralMint,
+    userCollateralAta: userAta,
+  });
+
+  async function buy(amount: number, gateTokenAccount: PublicKey | null) {
+    await program.methods
+      .buyShares(0, new anchor.BN(amount), new anchor.BN(1))
+      .accounts(buyAccounts({ ...tradeAccounts(), tokenGate: gatePda, gateTokenAccount }))
+      .signers([user])
+      .rpc({ commitment: "confirmed" });
+  }
+
+  async function sell(shares: anchor.BN) {
+    await program.methods
+      .sellShares(0, shares, new anchor.BN(1))
+      .accounts(sellAccounts(tradeAccounts()))
+      .signers([user])
+      .rpc({ commitment: "confirmed" });
+  }
+
+  it("setup: market + funded trader", async () => {
+    config = await ensureConfig(program, provider);
+
+    await airdrop(provider, user.publicKey);
+
+    collateralMint = await createMint(provider.connection, payer, wallet.publicKey, null, 6);
+    const authorityAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, wallet.publicKey)
+    ).address;
+    userAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, user.publicKey)
+    ).address;
+    await mintTo(provider.connection, payer, collateralMint, authorityAta, payer, 10_000_000_000);
+    await mintTo(provider.connection, payer, collateralMint, userAta, payer, 1_000_000_000);
+
+    marketPda = pda(program, [
+      Buffer.from("market_v2"),
+      wallet.publicKey.toBuffer(),
+      marketId.toArrayLike(Buffer, "le", 8),
+    ]);
+    vaultPda = pda(program, [Buffer.from("vault_v2"), marketPda.toBuffer()]);
+    vaultAuthPda = pda(program, [Buffer.from("vault_auth_v2"), marketPda.toBuffer()]);
+    positionPda = pda(program, [Buffer.from("position_v2"), marketPda.toBuffer(), user.publicKey.toBuffer()]);
+    gatePda = pda(program, [Buffer.from("token_gate"), marketPda.toBuffer()]);
+    // NFT-style gate: supply of whole tokens, hold at least one
+    gateMint = await createMint(provider.connection, payer, wallet.publicKey, null, 0);
+
+    await createTestMarket(program, provider, {
+      marketId,
+      question: "Will the holders-only market resolve YES?",
+      metadataUri: "ar://holders-only",
+      collateralMint,
+      authorityCollateralAta: authorityAta,
+    });
+  });
+
+  it("set_token_gate is restricted to the market authority", async () => {
+    let failed = false;
+    try {
+      await program.methods
+        .setTokenGate(new anchor.BN(1))
+        .accounts({
+          market: marketPda,
+          tokenGate: gatePda,
+          gateMint,
+          authority: user.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([user])
+        .rpc({ commitment: "confirmed" });
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("Unauthorized");
+    }
+    expect(failed).to.eq(true);
+  });
+
+  it("buyers without the gate token are rejected", async () => {
+    await program.methods
+      .setTokenGate(new anchor.BN(1))
+      .accounts({
+        market: marketPda,
+        tokenGate: gatePda,
+        gateMint,
+        authority: wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc({ commitment: "confirmed" });
+
+    const emptyAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, gateMint, user.publicKey)
+    ).address;
+
+    for (const holding of [null, emptyAta]) {
+      let failed = false;
+      try {
+        await buy(10_000_000, holding);
+      } catch (e: any) {
+        failed = true;
+        expect(String(e)).to.contain("TokenGateNotMet");
+      }
+      expect(failed).to.eq(true);
+    }
+  });
+
+  it("holders buy; anyone can sell", async () => {
+    const holding = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, gateMint, user.publicKey)
+    ).address;
+    await mintTo(provider.connection, payer, gateMint, holding, payer, 1);
+
+    await buy(10_000_000, holding);
+    const position = await program.account.positionV2.fetch(positionPda);
+    expect(position.yesShares.toNumber()).to.be.greaterThan(0);
+
+    await sell(position.yesShares);
+    const after = await program.account.positionV2.fetch(positionPda);
+    expect(after.yesShares.toNumber()).to.eq(0);
+  });
+});
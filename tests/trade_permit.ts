@@ -0,0 +1,182 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import {
+  Ed25519Program,
+  PublicKey,
+  SystemProgram,
+  SYSVAR_INSTRUCTIONS_PUBKEY,
+} from "@solana/web3.js";
+import {
+  createMint,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+} from "@solana/spl-token";
+import { expect } from "chai";
+
+import { PredictionProgramV2 } from "../target/types/prediction_program_v2";
+import {
+  airdrop,
+  buyAccounts,
+  createTestMarket,
+  ensureConfig,
+  pda,
+  sellAccounts,
+  uniqueId,
+} from "./utils";
+
+describe("prediction_program_v2 (signed trade permits)", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace
+    .PredictionProgramV2 as Program<PredictionProgramV2>;
+
+  const payer = (provider.wallet as any).payer as anchor.web3.Keypair;
+  const wallet = provider.wallet as anchor.Wallet;
+
+  const user = anchor.web3.Keypair.generate();
+  const operator = anchor.web3.Keypair.generate();
+  const marketId = uniqueId();
+
+  let collateralMint: PublicKey;
+  let userAta: PublicKey;
+  let config: PublicKey;
+  let marketPda: PublicKey;
+  let vaultPda: PublicKey;
+  let vaultAuthPda: PublicKey;
+  let positionPda: PublicKey;
+  let gatePda: PublicKey;
+
+  const tradeAccounts = (gated: boolean) => ({
+    config,
+    market: marketPda,
+    vault: vaultPda,
+    vaultAuthority: vaultAuthPda,
+    position: positionPda,
+    user: user.publicKey,
+    payer: user.publicKey,
+    collateralMint,
+    userCollateralAta: userAta,
+    permitGate: gated ? gatePda : null,
+    instructions: gated ? SYSVAR_INSTRUCTIONS_PUBKEY : null,
+  });
+
+  // borsh `TradePermit { market, wallet, expiry }`
+  const permitIx = (signer: anchor.web3.Keypair, expiry: number) =>
+    Ed25519Program.createInstructionWithPrivateKey({
+      privateKey: signer.secretKey,
+      message: Buffer.concat([
+        marketPda.toBuffer(),
+        user.publicKey.toBuffer(),
+        new anchor.BN(expiry).toArrayLike(Buffer, "le", 8),
+      ]),
+    });
+
+  async function buy(amount: number, permit: anchor.web3.TransactionInstruction | null) {
+    await program.methods
+      .buyShares(0, new anchor.BN(amount), new anchor.BN(1))
+      .accounts(buyAccounts(tradeAccounts(permit !== null)))
+      .preInstructions(permit ? [permit] : [])
+      .signers([user])
+      .rpc({ commitment: "confirmed" });
+  }
+
+  async function sell(shares: anchor.BN, permit: anchor.web3.TransactionInstruction) {
+    await program.methods
+      .sellShares(0, shares, new anchor.BN(1))
+      .accounts(sellAccounts(tradeAccounts(true)))
+      .preInstructions([permit])
+      .signers([user])
+      .rpc({ commitment: "confirmed" });
+  }
+
+  it("setup: market + funded trader", async () => {
+    config = await ensureConfig(program, provider);
+
+    await airdrop(provider, user.publicKey);
+
+    collateralMint = await createMint(provider.connection, payer, wallet.publicKey, null, 6);
+    const authorityAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, wallet.publicKey)
+    ).address;
+    userAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, user.publicKey)
+    ).address;
+    await mintTo(provider.connection, payer, collateralMint, authorityAta, payer, 10_000_000_000);
+    await mintTo(provider.connection, payer, collateralMint, userAta, payer, 1_000_000_000);
+
+    marketPda = pda(program, [
+      Buffer.from("market_v2"),
+      wallet.publicKey.toBuffer(),
+      marketId.toArrayLike(Buffer, "le", 8),
+    ]);
+    vaultPda = pda(program, [Buffer.from("vault_v2"), marketPda.toBuffer()]);
+    vaultAuthPda = pda(program, [Buffer.from("vault_auth_v2"), marketPda.toBuffer()]);
+    positionPda = pda(program, [Buffer.from("position_v2"), marketPda.toBuffer(), user.publicKey.toBuffer()]);
+    gatePda = pda(program, [Buffer.from("permit_gate"), marketPda.toBuffer()]);
+
+    await createTestMarket(program, provider, {
+      marketId,
+      question: "Will the geo-fenced market resolve YES?",
+      metadataUri: "ar://geo-fenced",
+      collateralMint,
+      authorityCollateralAta: authorityAta,
+    });
+  });
+
+  const expectFailure = async (p: Promise<unknown>, error: string) => {
+    let failed = false;
+    try {
+      await p;
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain(error);
+    }
+    expect(failed).to.eq(true);
+  };
+
+  it("gated markets reject trades without a valid permit", async () => {
+    await program.methods
+      .setPermitGate(operator.publicKey)
+      .accounts({
+        market: marketPda,
+        permitGate: gatePda,
+        authority: wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc({ commitment: "confirmed" });
+
+    const now = Math.floor(Date.now() / 1000);
+    await expectFailure(buy(10_000_000, null), "TradePermitRequired");
+    // Signed by someone other than the operator
+    await expectFailure(buy(10_000_000, permitIx(user, now + 60)), "InvalidSignature");
+    await expectFailure(buy(10_000_000, permitIx(operator, now - 60)), "TradePermitExpired");
+    // Valid for longer than MAX_PERMIT_SECS
+    await expectFailure(buy(10_000_000, permitIx(operator, now + 3600)), "TradePermitExpired");
+  });
+
+  it("a fresh operator permit lets the wallet buy and sell", async () => {
+    const now = Math.floor(Date.now() / 1000);
+    await buy(10_000_000, permitIx(operator, now + 60));
+    const position = await program.account.positionV2.fetch(positionPda);
+    expect(position.yesShares.toNumber()).to.be.greaterThan(0);
+
+    await sell(position.yesShares, permitIx(operator, now + 60));
+    const after = await program.account.positionV2.fetch(positionPda);
+    expect(after.yesShares.toNumber()).to.eq(0);
+  });
+
+  it("the default operator lifts the gate", async () => {
+    await program.methods
+      .setPermitGate(PublicKey.default)
+      .accounts({
+        market: marketPda,
+        permitGate: gatePda,
+        authority: wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc({ commitment: "confirmed" });
+
+    await buy(10_000_000, null);
+  });
+});
@@ -0,0 +1,188 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { PublicKey, SystemProgram } from "@solana/web3.js";
+import {
+  TOKEN_PROGRAM_ID,
+  createMint,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+} from "@solana/spl-token";
+import { expect } from "chai";
+
+import { PredictionProgramV2 } from "../target/types/prediction_program_v2";
+import {
+  airdrop,
+  buyAccounts,
+  createTestMarket,
+  ensureConfig,
+  pda,
+  sellAccounts,
+  uniqueId,
+} from "./utils";
+
+describe("prediction_program_v2 (trade tape)", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace
+    .PredictionProgramV2 as Program<PredictionProgramV2>;
+
+  const payer = (provider.wallet as any).payer as anchor.web3.Keypair;
+  const wallet = provider.wallet as anchor.Wallet;
+
+  const user = anchor.web3.Keypair.generate();
+  const marketId = uniqueId();
+
+  let collateralMint: PublicKey;
+  let userAta: PublicKey;
+  let config: PublicKey;
+  let marketPda: PublicKey;
+  let vaultPda: PublicKey;
+  let vaultAuthPda: PublicKey;
+  let positionPda: PublicKey;
+  let tradeTape: PublicKey;
+
+  it("setup: market + funded trader + trade tape", async () => {
+    config = await ensureConfig(program, provider);
+
+    await airdrop(provider, user.publicKey);
+
+    collateralMint = await createMint(provider.connection, payer, wallet.publicKey, null, 6);
+    const authorityAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, wallet.publicKey)
+    ).address;
+    userAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, user.publicKey)
+    ).address;
+    await mintTo(provider.connection, payer, collateralMint, authorityAta, payer, 10_000_000_000);
+    await mintTo(provider.connection, payer, collateralMint, userAta, payer, 1_000_000_000);
+
+    marketPda = pda(program, [
+      Buffer.from("market_v2"),
+      wallet.publicKey.toBuffer(),
+      marketId.toArrayLike(Buffer, "le", 8),
+    ]);
+    vaultPda = pda(program, [Buffer.from("vault_v2"), marketPda.toBuffer()]);
+    vaultAuthPda = pda(program, [Buffer.from("vault_auth_v2"), marketPda.toBuffer()]);
+    positionPda = pda(program, [Buffer.from("position_v2"), marketPda.toBuffer(), user.publicKey.toBuffer()]);
+    tradeTape = pda(program, [Buffer.from("trade_tape"), marketPda.toBuffer()]);
+
+    await createTestMarket(program, provider, {
+      marketId,
+      question: "Will a validator client reach 50% stake share?",
+      metadataUri: "ar://client-50-stake",
+      collateralMint,
+      authorityCollateralAta: authorityAta,
+    });
+
+    await program.methods
+      .initTradeTape()
+      .accounts({ market: marketPda, tradeTape, payer: wallet.publicKey, systemProgram: SystemProgram.programId })
+      .rpc({ commitment: "confirmed" });
+  });
+
+  const tradeAccounts = () => ({
+    config,
+    market: marketPda,
+    vault: vaultPda,
+    vaultAuthority: vaultAuthPda,
+    position: positionPda,
+    user: user.publicKey,
+    payer: user.publicKey,
+    collateralMint,
+    userCollateralAta: userAta,
+    tradeTape,
+  });
+
+  it("buys and sells are appended to the tape", async () => {
+    await program.methods
+      .buyShares(1, new anchor.BN(50_000_000), new anchor.BN(1))
+      .accounts(buyAccounts(tradeAccounts()))
+      .signers([user])
+      .rpc({ commitment: "confirmed" });
+
+    const position = await program.account.positionV2.fetch(positionPda);
+    await program.methods
+      .sellShares(1, position.noShares, new anchor.BN(1))
+      .accounts(sellAccounts(tradeAccounts()))
+      .signers([user])
+      .rpc({ commitment: "confirmed" });
+
+    const tape = await program.account.tradeTape.fetch(tradeTape);
+    expect(tape.count).to.eq(2);
+    const sell = tape.trades[tape.head];
+    const buy = tape.trades[tape.head - 1];
+
+    expect(buy.side).to.eq(0);
+    expect(buy.outcome).to.eq(1);
+    expect(buy.collateral.toNumber()).to.eq(50_000_000);
+    expect(buy.shares.toString()).to.eq(position.noShares.toString());
+    expect(sell.side).to.eq(1);
+    expect(sell.shares.toString()).to.eq(position.noShares.toString());
+    // Round trip pays the spread: sold for less than bought
+    expect(sell.price.toNumber()).to.be.lessThan(buy.price.toNumber());
+    expect(sell.timestamp.toNumber()).to.be.at.least(buy.timestamp.toNumber());
+  });
+
+  it("trading-account buys need the tape and land on it", async () => {
+    const tradingAccount = pda(program, [
+      Buffer.from("trading_account"),
+      user.publicKey.toBuffer(),
+      collateralMint.toBuffer(),
+    ]);
+    const custodyAuthority = pda(program, [Buffer.from("trading_custody_auth")]);
+    const custody = pda(program, [Buffer.from("trading_custody"), collateralMint.toBuffer()]);
+
+    await program.methods
+      .depositToTradingAccount(new anchor.BN(50_000_000))
+      .accounts({
+        tradingAccount,
+        custodyAuthority,
+        custody,
+        user: user.publicKey,
+        payer: user.publicKey,
+        collateralMint,
+        userCollateralAta: userAta,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([user])
+      .rpc({ commitment: "confirmed" });
+
+    const buy = (tape: PublicKey | null) =>
+      program.methods
+        .buyFromTradingAccount(0, new anchor.BN(20_000_000), new anchor.BN(1))
+        .accounts({
+          config,
+          market: marketPda,
+          vault: vaultPda,
+          vaultAuthority: vaultAuthPda,
+          position: positionPda,
+          tradingAccount,
+          custodyAuthority,
+          custody,
+          user: user.publicKey,
+          payer: user.publicKey,
+          collateralMint,
+          tradeTape: tape,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([user])
+        .rpc({ commitment: "confirmed" });
+
+    let failed = false;
+    try {
+      await buy(null);
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("TradeTapeRequired");
+    }
+    expect(failed).to.eq(true);
+
+    await buy(tradeTape);
+    const tape = await program.account.tradeTape.fetch(tradeTape);
+    expect(tape.count).to.eq(3);
+    expect(tape.trades[tape.head].collateral.toNumber()).to.eq(20_000_000);
+  });
+});
@@ -0,0 +1,218 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { PublicKey, SystemProgram } from "@solana/web3.js";
+import {
+  TOKEN_PROGRAM_ID,
+  createMint,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+  getAccount,
+} from "@solana/spl-token";
+import { expect } from "chai";
+
+import { PredictionProgramV2 } from "../target/types/prediction_program_v2";
+import {
+  airdrop,
+  createTestMarket,
+  ensureConfig,
+  pda,
+  uniqueId,
+} from "./utils";
+
+describe("prediction_program_v2 (trading accounts)", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace
+    .PredictionProgramV2 as Program<PredictionProgramV2>;
+
+  const payer = (provider.wallet as any).payer as anchor.web3.Keypair;
+  const wallet = provider.wallet as anchor.Wallet;
+
+  const trader = anchor.web3.Keypair.generate();
+  const marketId = uniqueId();
+  const initialLiquidity = new anchor.BN(1_000_000_000);
+
+  let collateralMint: PublicKey;
+  let authorityAta: PublicKey;
+  let traderAta: PublicKey;
+  let marketPda: PublicKey;
+  let vaultPda: PublicKey;
+  let vaultAuthPda: PublicKey;
+  let positionPda: PublicKey;
+  let tradingAccount: PublicKey;
+  let custodyAuthority: PublicKey;
+  let custody: PublicKey;
+
+  it("setup: mint + market + PDAs", async () => {
+    await ensureConfig(program, provider);
+
+    await airdrop(provider, trader.publicKey);
+
+    collateralMint = await createMint(
+      provider.connection,
+      payer,
+      wallet.publicKey,
+      null,
+      6
+    );
+    authorityAta = (
+      await getOrCreateAssociatedTokenAccount(
+        provider.connection,
+        payer,
+        collateralMint,
+        wallet.publicKey
+      )
+    ).address;
+    traderAta = (
+      await getOrCreateAssociatedTokenAccount(
+        provider.connection,
+        payer,
+        collateralMint,
+        trader.publicKey
+      )
+    ).address;
+    await mintTo(provider.connection, payer, collateralMint, authorityAta, payer, 10_000_000_000);
+    await mintTo(provider.connection, payer, collateralMint, traderAta, payer, 1_000_000_000);
+
+    marketPda = pda(program, [
+      Buffer.from("market_v2"),
+      wallet.publicKey.toBuffer(),
+      marketId.toArrayLike(Buffer, "le", 8),
+    ]);
+    vaultPda = pda(program, [Buffer.from("vault_v2"), marketPda.toBuffer()]);
+    vaultAuthPda = pda(program, [Buffer.from("vault_auth_v2"), marketPda.toBuffer()]);
+    positionPda = pda(program, [Buffer.from("position_v2"), marketPda.toBuffer(), trader.publicKey.toBuffer()]);
+    tradingAccount = pda(program, [Buffer.from("trading_account"), trader.publicKey.toBuffer(), collateralMint.toBuffer()]);
+    custodyAuthority = pda(program, [Buffer.from("trading_custody_auth")]);
+    custody = pda(program, [Buffer.from("trading_custody"), collateralMint.toBuffer()]);
+
+    await createTestMarket(program, provider, {
+      marketId,
+      question: "Will the bot make money?",
+      metadataUri: "ar://bot-pnl",
+      collateralMint,
+      authorityCollateralAta: authorityAta,
+      initialYesReserve: initialLiquidity,
+      initialNoReserve: initialLiquidity,
+    });
+  });
+
+  it("deposit_to_trading_account credits the internal balance", async () => {
+    await program.methods
+      .depositToTradingAccount(new anchor.BN(500_000_000))
+      .accounts({
+        tradingAccount,
+        custodyAuthority,
+        custody,
+        user: trader.publicKey,
+        payer: trader.publicKey,
+        collateralMint,
+        userCollateralAta: traderAta,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([trader])
+      .rpc({ commitment: "confirmed" });
+
+    const acc = await program.account.tradingAccount.fetch(tradingAccount);
+    expect(acc.balance.toNumber()).to.eq(500_000_000);
+  });
+
+  it("buy/sell against the trading account never touch the wallet", async () => {
+    const walletBefore = Number((await getAccount(provider.connection, traderAta)).amount);
+    const tradeAccounts = {
+      market: marketPda,
+      vault: vaultPda,
+      vaultAuthority: vaultAuthPda,
+      position: positionPda,
+      tradingAccount,
+      custodyAuthority,
+      custody,
+      user: trader.publicKey,
+      payer: trader.publicKey,
+      collateralMint,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      systemProgram: SystemProgram.programId,
+    };
+
+    await program.methods
+      .buyFromTradingAccount(0, new anchor.BN(100_000_000), new anchor.BN(1))
+      .accounts(tradeAccounts)
+      .signers([trader])
+      .rpc({ commitment: "confirmed" });
+
+    let acc = await program.account.tradingAccount.fetch(tradingAccount);
+    expect(acc.balance.toNumber()).to.eq(400_000_000);
+
+    const position = await program.account.positionV2.fetch(positionPda);
+    expect(position.yesShares.toNumber()).to.be.greaterThan(0);
+
+    await program.methods
+      .sellToTradingAccount(0, position.yesShares, new anchor.BN(1))
+      .accounts(tradeAccounts)
+      .signers([trader])
+      .rpc({ commitment: "confirmed" });
+
+    acc = await program.account.tradingAccount.fetch(tradingAccount);
+    expect(acc.balance.toNumber()).to.be.greaterThan(400_000_000);
+    expect(acc.balance.toNumber()).to.be.lessThan(500_000_000); // fees both ways
+
+    const walletAfter = Number((await getAccount(provider.connection, traderAta)).amount);
+    expect(walletAfter).to.eq(walletBefore);
+  });
+
+  it("portfolio: track and untrack a market", async () => {
+    const portfolio = pda(program, [Buffer.from("portfolio"), trader.publicKey.toBuffer()]);
+
+    await program.methods
+      .initPortfolio()
+      .accounts({ portfolio, owner: trader.publicKey, payer: trader.publicKey, systemProgram: SystemProgram.programId })
+      .signers([trader])
+      .rpc({ commitment: "confirmed" });
+
+    await program.methods
+      .trackMarket()
+      .accounts({ portfolio, position: positionPda, owner: trader.publicKey })
+      .signers([trader])
+      .rpc({ commitment: "confirmed" });
+    expect(
+      (await program.account.portfolio.fetch(portfolio)).markets.map((m) => m.toBase58())
+    ).to.deep.eq([marketPda.toBase58()]);
+
+    await program.methods
+      .untrackMarket(marketPda)
+      .accounts({ portfolio, owner: trader.publicKey })
+      .signers([trader])
+      .rpc({ commitment: "confirmed" });
+    expect((await program.account.portfolio.fetch(portfolio)).markets).to.have.length(0);
+  });
+
+  it("withdraw_from_trading_account returns collateral to the wallet", async () => {
+    const acc = await program.account.tradingAccount.fetch(tradingAccount);
+    const walletBefore = Number((await getAccount(provider.connection, traderAta)).amount);
+
+    await program.methods
+      .withdrawFromTradingAccount(acc.balance)
+      .accounts({
+        tradingAccount,
+        custodyAuthority,
+        custody,
+        user: trader.publicKey,
+        payer: trader.publicKey,
+        collateralMint,
+        userCollateralAta: traderAta,
+        wsolUnwrap: null,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([trader])
+      .rpc({ commitment: "confirmed" });
+
+    const walletAfter = Number((await getAccount(provider.connection, traderAta)).amount);
+    expect(walletAfter - walletBefore).to.eq(acc.balance.toNumber());
+    expect(
+      (await program.account.tradingAccount.fetch(tradingAccount)).balance.toNumber()
+    ).to.eq(0);
+  });
+});
@@ -0,0 +1,204 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { PublicKey, SystemProgram, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import {
+  TOKEN_PROGRAM_ID,
+  createMint,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+  getAccount,
+} from "@solana/spl-token";
+import { expect } from "chai";
+
+import { PredictionProgramV2 } from "../target/types/prediction_program_v2";
+import {
+  airdrop,
+  createTestMarket,
+  ensureConfig,
+  pda,
+  uniqueId,
+} from "./utils";
+
+describe("prediction_program_v2 (TWAP orders)", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace
+    .PredictionProgramV2 as Program<PredictionProgramV2>;
+
+  const payer = (provider.wallet as any).payer as anchor.web3.Keypair;
+  const wallet = provider.wallet as anchor.Wallet;
+
+  const trader = anchor.web3.Keypair.generate();
+  const marketId = uniqueId();
+  const initialLiquidity = new anchor.BN(1_000_000_000);
+
+  let collateralMint: PublicKey;
+  let authorityAta: PublicKey;
+  let traderAta: PublicKey;
+  let marketPda: PublicKey;
+  let vaultPda: PublicKey;
+  let vaultAuthPda: PublicKey;
+  let positionPda: PublicKey;
+
+  const keeper = anchor.web3.Keypair.generate();
+
+  const orderPda = (id: number) =>
+    pda(program, [
+      Buffer.from("twap_order"),
+      marketPda.toBuffer(),
+      trader.publicKey.toBuffer(),
+      new anchor.BN(id).toArrayLike(Buffer, "le", 8),
+    ]);
+  const escrowPda = (order: PublicKey) =>
+    pda(program, [Buffer.from("twap_escrow"), order.toBuffer()]);
+
+  it("setup: mint + market + PDAs", async () => {
+    await ensureConfig(program, provider);
+
+    await airdrop(provider, trader.publicKey);
+
+    collateralMint = await createMint(
+      provider.connection,
+      payer,
+      wallet.publicKey,
+      null,
+      6
+    );
+    authorityAta = (
+      await getOrCreateAssociatedTokenAccount(
+        provider.connection,
+        payer,
+        collateralMint,
+        wallet.publicKey
+      )
+    ).address;
+    traderAta = (
+      await getOrCreateAssociatedTokenAccount(
+        provider.connection,
+        payer,
+        collateralMint,
+        trader.publicKey
+      )
+    ).address;
+    await mintTo(provider.connection, payer, collateralMint, authorityAta, payer, 10_000_000_000);
+    await mintTo(provider.connection, payer, collateralMint, traderAta, payer, 1_000_000_000);
+
+    marketPda = pda(program, [
+      Buffer.from("market_v2"),
+      wallet.publicKey.toBuffer(),
+      marketId.toArrayLike(Buffer, "le", 8),
+    ]);
+    vaultPda = pda(program, [Buffer.from("vault_v2"), marketPda.toBuffer()]);
+    vaultAuthPda = pda(program, [Buffer.from("vault_auth_v2"), marketPda.toBuffer()]);
+    positionPda = pda(program, [Buffer.from("position_v2"), marketPda.toBuffer(), trader.publicKey.toBuffer()]);
+
+    await createTestMarket(program, provider, {
+      marketId,
+      question: "Will the TWAP order fill?",
+      metadataUri: "ar://twap-order",
+      collateralMint,
+      authorityCollateralAta: authorityAta,
+      initialYesReserve: initialLiquidity,
+      initialNoReserve: initialLiquidity,
+    });
+  });
+
+  it("place_twap_order escrows the full notional", async () => {
+    const order = orderPda(1);
+    await program.methods
+      .placeTwapOrder({
+        orderId: new anchor.BN(1),
+        side: 0,
+        outcomeIndex: 0,
+        amount: new anchor.BN(90_000_000),
+        slices: 3,
+        interval: new anchor.BN(3600),
+        limitPrice: new anchor.BN(900_000),
+        keeperTip: new anchor.BN(10_000),
+      })
+      .accounts({
+        order,
+        escrow: escrowPda(order),
+        market: marketPda,
+        vaultAuthority: vaultAuthPda,
+        position: positionPda,
+        owner: trader.publicKey,
+        payer: trader.publicKey,
+        collateralMint,
+        ownerCollateralAta: traderAta,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([trader])
+      .rpc({ commitment: "confirmed" });
+
+    const escrow = await getAccount(provider.connection, escrowPda(order));
+    expect(Number(escrow.amount)).to.eq(90_000_000);
+  });
+
+  it("execute_twap_slice fills one slice, then waits for the interval", async () => {
+    const order = orderPda(1);
+    await airdrop(provider, keeper.publicKey, LAMPORTS_PER_SOL);
+
+    const executeSlice = () =>
+      program.methods
+        .executeTwapSlice()
+        .accounts({
+          order,
+          escrow: escrowPda(order),
+          market: marketPda,
+          vault: vaultPda,
+          vaultAuthority: vaultAuthPda,
+          position: positionPda,
+          keeper: keeper.publicKey,
+          collateralMint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([keeper])
+        .rpc({ commitment: "confirmed" });
+
+    await executeSlice();
+    const state = await program.account.twapOrder.fetch(order);
+    expect(state.slicesDone).to.eq(1);
+    expect(state.remaining.toNumber()).to.eq(60_000_000);
+    expect((await program.account.positionV2.fetch(positionPda)).yesShares.toNumber()).to.be.greaterThan(30_000_000);
+
+    let failed = false;
+    try {
+      await executeSlice();
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("SliceNotDue");
+    }
+    expect(failed).to.eq(true);
+  });
+
+  it("close_twap_order refunds the unexecuted collateral", async () => {
+    const order = orderPda(1);
+    const before = await getAccount(provider.connection, traderAta);
+
+    await program.methods
+      .closeTwapOrder()
+      .accounts({
+        order,
+        escrow: escrowPda(order),
+        market: marketPda,
+        vaultAuthority: vaultAuthPda,
+        position: positionPda,
+        owner: trader.publicKey,
+        payer: trader.publicKey,
+        collateralMint,
+        ownerCollateralAta: traderAta,
+        wsolUnwrap: null,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([trader])
+      .rpc({ commitment: "confirmed" });
+
+    const after = await getAccount(provider.connection, traderAta);
+    expect(Number(after.amount) - Number(before.amount)).to.eq(60_000_000);
+    expect(await provider.connection.getAccountInfo(order)).to.eq(null);
+  });
+});
@@ -0,0 +1,134 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { PublicKey } from "@solana/web3.js";
+import {
+  createMint,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+} from "@solana/spl-token";
+import { expect } from "chai";
+
+import { PredictionProgramV2 } from "../target/types/prediction_program_v2";
+import {
+  REGISTRY_PAGE_SIZE,
+  buyAccounts,
+  createTestMarket,
+  ensureConfig,
+  pda,
+  registryPagePda,
+  uniqueId,
+} from "./utils";
+
+const DAY = 24 * 60 * 60;
+
+describe("prediction_program_v2 (update end time)", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace
+    .PredictionProgramV2 as Program<PredictionProgramV2>;
+
+  const payer = (provider.wallet as any).payer as anchor.web3.Keypair;
+  const wallet = provider.wallet as anchor.Wallet;
+
+  const marketId = uniqueId();
+  const endTime = Math.floor(Date.now() / 1000) + 3600;
+
+  let collateralMint: PublicKey;
+  let authorityAta: PublicKey;
+  let config: PublicKey;
+  let marketPda: PublicKey;
+  let vaultPda: PublicKey;
+  let vaultAuthPda: PublicKey;
+  let registryPage: PublicKey;
+
+  async function updateEndTime(newEndTime: number) {
+    await program.methods
+      .updateEndTime(new anchor.BN(newEndTime))
+      .accounts({
+        market: marketPda,
+        registryPage,
+        authority: wallet.publicKey,
+      })
+      .rpc({ commitment: "confirmed" });
+  }
+
+  it("setup: untraded market", async () => {
+    config = await ensureConfig(program, provider);
+
+    collateralMint = await createMint(provider.connection, payer, wallet.publicKey, null, 6);
+    authorityAta = (
+      await getOrCreateAssociatedTokenAccount(provider.connection, payer, collateralMint, wallet.publicKey)
+    ).address;
+    await mintTo(provider.connection, payer, collateralMint, authorityAta, payer, 10_000_000_000);
+
+    marketPda = pda(program, [
+      Buffer.from("market_v2"),
+      wallet.publicKey.toBuffer(),
+      marketId.toArrayLike(Buffer, "le", 8),
+    ]);
+    vaultPda = pda(program, [Buffer.from("vault_v2"), marketPda.toBuffer()]);
+    vaultAuthPda = pda(program, [Buffer.from("vault_auth_v2"), marketPda.toBuffer()]);
+
+    await createTestMarket(program, provider, {
+      marketId,
+      question: "Will the match be rescheduled?",
+      metadataUri: "ar://update-end-time",
+      collateralMint,
+      authorityCollateralAta: authorityAta,
+      endTime: new anchor.BN(endTime),
+    });
+
+    const market = await program.account.marketV3.fetch(marketPda);
+    registryPage = registryPagePda(program, Math.floor(market.registryIndex.toNumber() / REGISTRY_PAGE_SIZE));
+  });
+
+  it("creator extends end_time before the first trade", async () => {
+    await updateEndTime(endTime + DAY);
+
+    const market = await program.account.marketV3.fetch(marketPda);
+    expect(market.endTime.toNumber()).to.eq(endTime + DAY);
+    expect(market.endTimeExtension).to.eq(DAY);
+
+    const page = await program.account.registryPage.fetch(registryPage);
+    const entry = page.entries.find((e: any) => e.market.equals(marketPda))!;
+    expect(entry.endTime.toNumber()).to.eq(endTime + DAY);
+  });
+
+  it("total extension is capped", async () => {
+    let failed = false;
+    try {
+      await updateEndTime(endTime + 91 * DAY);
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("EndTimeExtensionCapExceeded");
+    }
+    expect(failed).to.eq(true);
+  });
+
+  it("end_time is fixed after the first trade", async () => {
+    await program.methods
+      .buyShares(0, new anchor.BN(10_000_000), new anchor.BN(1))
+      .accounts(buyAccounts({
+        config,
+        market: marketPda,
+        vault: vaultPda,
+        vaultAuthority: vaultAuthPda,
+        position: pda(program, [Buffer.from("position_v2"), marketPda.toBuffer(), wallet.publicKey.toBuffer()]),
+        user: wallet.publicKey,
+        payer: wallet.publicKey,
+        collateralMint,
+        userCollateralAta: authorityAta,
+      }))
+      .rpc({ commitment: "confirmed" });
+
+    let failed = false;
+    try {
+      await updateEndTime(endTime + 2 * DAY);
+    } catch (e: any) {
+      failed = true;
+      expect(String(e)).to.contain("MarketAlreadyTraded");
+    }
+    expect(failed).to.eq(true);
+  });
+});
@@ -0,0 +1,321 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import {
+  LAMPORTS_PER_SOL,
+  PublicKey,
+  SystemProgram,
+  SYSVAR_RENT_PUBKEY,
+} from "@solana/web3.js";
+import { TOKEN_PROGRAM_ID } from "@solana/spl-token";
+import { createHash } from "crypto";
+
+import { PredictionProgramV2 } from "../target/types/prediction_program_v2";
+
+const BPF_LOADER_UPGRADEABLE_PROGRAM_ID = new PublicKey(
+  "BPFLoaderUpgradeab1e11111111111111111111111"
+);
+
+export const DEFAULT_COLLATERAL_POLICY = {
+  allowFreezeAuthority: true,
+  allowTransferFee: true,
+  allowTransferHook: false,
+  allowPermanentDelegate: false,
+  allowDefaultAccountState: false,
+  allowPausable: false,
+};
+
+export const DEFAULT_MARKET_METADATA = {
+  category: 0, // Other
+  tags: new anchor.BN(0),
+  imageUri: "",
+  rulesUri: "",
+};
+
+// `ConfigParams` with every field left unchanged
+export const NO_CONFIG_CHANGES = {
+  creatorFeeShareBps: null,
+  protocolFeeShareBps: null,
+  insuranceFeeShareBps: null,
+  disputeWindow: null,
+  correctionWindow: null,
+  disputeBond: null,
+  slashDisputerBps: null,
+  creationBond: null,
+  reportBond: null,
+  treasury: null,
+  permissionlessCollateral: null,
+  approvedMints: null,
+  paramTimelock: null,
+  minTradeCollateral: null,
+  minTradeShares: null,
+  curator: null,
+  yieldProgram: null,
+};
+
+export function pda(program: Program<PredictionProgramV2>, seeds: Buffer[]): PublicKey {
+  return PublicKey.findProgramAddressSync(seeds, program.programId)[0];
+}
+
+/** Fund `to` with SOL and wait for the airdrop to confirm. */
+export async function airdrop(
+  provider: anchor.AnchorProvider,
+  to: PublicKey,
+  lamports: number = 2 * LAMPORTS_PER_SOL
+): Promise<void> {
+  const sig = await provider.connection.requestAirdrop(to, lamports);
+  await provider.connection.confirmTransaction(sig, "confirmed");
+}
+
+// Market, group, series and template ids handed out to the suites. The base
+// moves with the clock so reruns against a long-lived validator don't collide.
+const ID_BASE = Date.now() * 1000;
+let idsIssued = 0;
+
+/** An id no other suite in this run is given. */
+export function uniqueId(): anchor.BN {
+  return new anchor.BN(ID_BASE + idsIssued++);
+}
+
+export function configPda(program: Program<PredictionProgramV2>): PublicKey {
+  return PublicKey.findProgramAddressSync(
+    [Buffer.from("config")],
+    program.programId
+  )[0];
+}
+
+export function creatorBondPda(
+  program: Program<PredictionProgramV2>,
+  market: PublicKey
+): PublicKey {
+  return PublicKey.findProgramAddressSync(
+    [Buffer.from("creator_bond"), market.toBuffer()],
+    program.programId
+  )[0];
+}
+
+export function lpPositionPda(
+  program: Program<PredictionProgramV2>,
+  market: PublicKey,
+  owner: PublicKey
+): PublicKey {
+  return PublicKey.findProgramAddressSync(
+    [Buffer.from("lp_position"), market.toBuffer(), owner.toBuffer()],
+    program.programId
+  )[0];
+}
+
+export const REGISTRY_PAGE_SIZE = 32;
+
+export function registryPagePda(
+  program: Program<PredictionProgramV2>,
+  page: number
+): PublicKey {
+  return PublicKey.findProgramAddressSync(
+    [Buffer.from("registry_page"), new anchor.BN(page).toArrayLike(Buffer, "le", 8)],
+    program.programId
+  )[0];
+}
+
+export function protocolStatsPda(program: Program<PredictionProgramV2>): PublicKey {
+  return PublicKey.findProgramAddressSync(
+    [Buffer.from("protocol_stats")],
+    program.programId
+  )[0];
+}
+
+/**
+ * Registry accounts the next created market will be written into, plus the
+ * protocol stats account once it exists.
+ */
+export async function nextRegistryAccounts(
+  program: Program<PredictionProgramV2>
+): Promise<{ registry: PublicKey; registryPage: PublicKey; protocolStats: PublicKey | null }> {
+  const [registry] = PublicKey.findProgramAddressSync(
+    [Buffer.from("registry")],
+    program.programId
+  );
+  const head = await program.account.marketRegistry.fetchNullable(registry);
+  const count = head ? head.marketCount.toNumber() : 0;
+  const protocolStats = protocolStatsPda(program);
+  const stats = await program.account.protocolStats.fetchNullable(protocolStats);
+  return {
+    registry,
+    registryPage: registryPagePda(program, Math.floor(count / REGISTRY_PAGE_SIZE)),
+    protocolStats: stats ? protocolStats : null,
+  };
+}
+
+/**
+ * Initialize the protocol config once per validator; every test file calls this
+ * so they can run in any order. The provider wallet is the upgrade authority
+ * under `anchor test`, so it becomes the config admin. Tests mint fresh
+ * collateral per file, so the config starts in permissionless-collateral mode.
+ */
+export async function ensureConfig(
+  program: Program<PredictionProgramV2>,
+  provider: anchor.AnchorProvider
+): Promise<PublicKey> {
+  const config = configPda(program);
+  const existing = await provider.connection.getAccountInfo(config);
+  if (existing) return config;
+
+  const [programData] = PublicKey.findProgramAddressSync(
+    [program.programId.toBuffer()],
+    BPF_LOADER_UPGRADEABLE_PROGRAM_ID
+  );
+
+  await program.methods
+    .initializeConfig(DEFAULT_COLLATERAL_POLICY, true)
+    .accounts({
+      config,
+      admin: provider.wallet.publicKey,
+      program: program.programId,
+      programData,
+      systemProgram: SystemProgram.programId,
+    })
+    .rpc({ commitment: "confirmed" });
+
+  return config;
+}
+
+export function marketPda(
+  program: Program<PredictionProgramV2>,
+  authority: PublicKey,
+  marketId: anchor.BN
+): PublicKey {
+  return pda(program, [
+    Buffer.from("market_v2"),
+    authority.toBuffer(),
+    marketId.toArrayLike(Buffer, "le", 8),
+  ]);
+}
+
+export interface TestMarketParams {
+  marketId: anchor.BN;
+  question: string;
+  metadataUri: string;
+  collateralMint: PublicKey;
+  authorityCollateralAta: PublicKey;
+  startTime?: anchor.BN;
+  endTime?: anchor.BN;
+  initialYesReserve?: anchor.BN;
+  initialNoReserve?: anchor.BN;
+  tokenProgram?: PublicKey;
+}
+
+/**
+ * Create a CPMM market owned by the provider wallet. Unless overridden it
+ * trades from now for an hour with 500/500 reserves, default metadata and no
+ * opening auction.
+ */
+export async function createTestMarket(
+  program: Program<PredictionProgramV2>,
+  provider: anchor.AnchorProvider,
+  params: TestMarketParams
+): Promise<{ market: PublicKey; vault: PublicKey; vaultAuthority: PublicKey }> {
+  const authority = provider.wallet.publicKey;
+  const market = marketPda(program, authority, params.marketId);
+  const vault = pda(program, [Buffer.from("vault_v2"), market.toBuffer()]);
+  const vaultAuthority = pda(program, [Buffer.from("vault_auth_v2"), market.toBuffer()]);
+
+  await program.methods
+    .createMarketCpmm({
+      marketId: params.marketId,
+      questionHash: Array.from(createHash("sha256").update(params.question).digest()),
+      metadataUri: params.metadataUri,
+      startTime: params.startTime ?? new anchor.BN(0),
+      endTime: params.endTime ?? new anchor.BN(Math.floor(Date.now() / 1000) + 3600),
+      initialYesReserve: params.initialYesReserve ?? new anchor.BN(500_000_000),
+      initialNoReserve: params.initialNoReserve ?? new anchor.BN(500_000_000),
+      metadata: DEFAULT_MARKET_METADATA,
+      openingAuction: null,
+    })
+    .accounts({
+      config: configPda(program),
+      market,
+      vault,
+      vaultAuthority,
+      collateralMint: params.collateralMint,
+      authority,
+      authorityCollateralAta: params.authorityCollateralAta,
+      ...(await nextRegistryAccounts(program)),
+      creatorBond: creatorBondPda(program, market),
+      creatorLpPosition: lpPositionPda(program, market, authority),
+      tokenProgram: params.tokenProgram ?? TOKEN_PROGRAM_ID,
+      systemProgram: SystemProgram.programId,
+      rent: SYSVAR_RENT_PUBKEY,
+    })
+    .rpc({ commitment: "confirmed" });
+
+  return { market, vault, vaultAuthority };
+}
+
+export interface TradeAccountsInput {
+  config: PublicKey;
+  market: PublicKey;
+  vault: PublicKey;
+  vaultAuthority: PublicKey;
+  position: PublicKey;
+  user: PublicKey;
+  payer: PublicKey;
+  collateralMint: PublicKey;
+  userCollateralAta: PublicKey | null;
+  marketMakers?: PublicKey | null;
+  userStats?: PublicKey | null;
+  competitionEntry?: PublicKey | null;
+  marketStats?: PublicKey | null;
+  protocolStats?: PublicKey | null;
+  priceCandles?: PublicKey | null;
+  tradeTape?: PublicKey | null;
+  priceFeed?: PublicKey | null;
+  allowlistEntry?: PublicKey | null;
+  permitGate?: PublicKey | null;
+  instructions?: PublicKey | null;
+  tokenProgram?: PublicKey;
+}
+
+// Optional recorder and gate accounts shared by every AMM trade, all unset
+const NO_TRADE_EXTRAS = {
+  marketMakers: null,
+  userStats: null,
+  competitionEntry: null,
+  marketStats: null,
+  protocolStats: null,
+  priceCandles: null,
+  tradeTape: null,
+  priceFeed: null,
+  allowlistEntry: null,
+  permitGate: null,
+  instructions: null,
+};
+
+/** `buy_shares` accounts with every optional account unset unless given. */
+export function buyAccounts(
+  accounts: TradeAccountsInput & {
+    tokenGate?: PublicKey | null;
+    gateTokenAccount?: PublicKey | null;
+  }
+) {
+  return {
+    ...NO_TRADE_EXTRAS,
+    tokenGate: null,
+    gateTokenAccount: null,
+    tokenProgram: TOKEN_PROGRAM_ID,
+    systemProgram: SystemProgram.programId,
+    rent: SYSVAR_RENT_PUBKEY,
+    ...accounts,
+  };
+}
+
+/** `sell_shares` accounts with every optional account unset unless given. */
+export function sellAccounts(
+  accounts: TradeAccountsInput & { wsolUnwrap?: PublicKey | null }
+) {
+  return {
+    ...NO_TRADE_EXTRAS,
+    wsolUnwrap: null,
+    tokenProgram: TOKEN_PROGRAM_ID,
+    systemProgram: SystemProgram.programId,
+    ...accounts,
+  };
+}